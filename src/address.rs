@@ -65,6 +65,18 @@ pub enum Address {
 }
 
 impl Address {
+    /// Get address of the HTTP server which
+    /// can be requested without resolving.
+    /// 
+    /// Return `None` for hyperborea clients
+    /// and HTTPS servers.
+    pub fn server_address(&self) -> Option<&str> {
+        match self {
+            Self::Http { address } | Self::Raw(address) => Some(address),
+            _ => None
+        }
+    }
+
     pub async fn resolve<T: HttpClient>(self, client: &ConnectedClientMiddleware<T>) -> Result<String, MiddlewareError> {
         let address = match self {
             Self::Hyperborea { public_key, client_type } => {
@@ -73,6 +85,7 @@ impl Address {
 
                     None => return Err(MiddlewareError::RequestFailed {
                         status: ResponseStatus::ClientNotFound,
+                        code: ErrorCode::NotFound,
                         reason: String::from("Failed to lookup hyperborea client")
                     })
                 }
@@ -87,6 +100,16 @@ impl Address {
     }
 }
 
+impl From<PublicKey> for Address {
+    #[inline]
+    fn from(public_key: PublicKey) -> Self {
+        Self::Hyperborea {
+            public_key,
+            client_type: None
+        }
+    }
+}
+
 impl FromStr for Address {
     type Err = CryptographyError;
 
@@ -1,5 +1,4 @@
-use rand_chacha::ChaCha20Rng;
-use rand_chacha::rand_core::{CryptoRngCore, SeedableRng};
+use rand_chacha::rand_core::CryptoRngCore;
 
 use k256::ecdsa::signature::Signer;
 
@@ -11,7 +10,7 @@ pub struct SecretKey(pub(crate) k256::SecretKey);
 impl SecretKey {
     /// Generate new random secret key.
     pub fn random() -> Self {
-        Self(k256::SecretKey::random(&mut ChaCha20Rng::from_entropy()))
+        Self(k256::SecretKey::random(&mut random_generator()))
     }
 
     /// Generate new random secret key from given
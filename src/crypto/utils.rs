@@ -1,6 +1,58 @@
+use std::cell::RefCell;
+
 use rand_chacha::ChaCha20Rng;
 use rand_chacha::rand_core::{SeedableRng, RngCore};
 
+thread_local! {
+    static SEEDED_RNG: RefCell<Option<ChaCha20Rng>> = const { RefCell::new(None) };
+}
+
+/// Make random numbers generated on the current
+/// thread deterministic.
+/// 
+/// All the library components take their random numbers
+/// from the `random_generator` function, so after seeding
+/// repeated runs on the same thread produce the same keys,
+/// proof seeds and fan-out selections. Pass `None` to
+/// return back to the system entropy.
+/// 
+/// This is intended for tests and simulations only.
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use hyperborealib::crypto::utils::{seed_thread_rng, safe_random_u64};
+/// 
+/// seed_thread_rng(Some(42));
+/// 
+/// let a = safe_random_u64();
+/// 
+/// seed_thread_rng(Some(42));
+/// 
+/// assert_eq!(safe_random_u64(), a);
+/// 
+/// seed_thread_rng(None);
+/// ```
+pub fn seed_thread_rng(seed: Option<u64>) {
+    SEEDED_RNG.with(|rng| {
+        *rng.borrow_mut() = seed.map(ChaCha20Rng::seed_from_u64);
+    });
+}
+
+/// Get new random numbers generator.
+/// 
+/// Generator is seeded from the system entropy, or from
+/// the current thread's deterministic generator if it
+/// was set by the `seed_thread_rng` function.
+pub fn random_generator() -> ChaCha20Rng {
+    SEEDED_RNG.with(|rng| {
+        match rng.borrow_mut().as_mut() {
+            Some(rng) => ChaCha20Rng::seed_from_u64(rng.next_u64()),
+            None => ChaCha20Rng::from_entropy()
+        }
+    })
+}
+
 #[inline]
 /// Generate random u64 number.
 /// 
@@ -13,7 +65,7 @@ use rand_chacha::rand_core::{SeedableRng, RngCore};
 /// assert!(safe_random_u64() <= u64::MAX);
 /// ```
 pub fn safe_random_u64() -> u64 {
-    ChaCha20Rng::from_entropy().next_u64()
+    random_generator().next_u64()
 }
 
 #[inline]
@@ -34,3 +86,44 @@ pub fn safe_random_u64() -> u64 {
 pub fn safe_random_u64_long() -> u64 {
     (1 << 63) | (safe_random_u64() >> 1)
 }
+
+/// Calculate HMAC-SHA256 of the data.
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use hyperborealib::crypto::utils::hmac_sha256;
+/// 
+/// let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+/// 
+/// assert_eq!(&mac[..4], &[0x5b, 0xdc, 0xc1, 0x46]);
+/// ```
+pub fn hmac_sha256(key: impl AsRef<[u8]>, data: impl AsRef<[u8]>) -> [u8; 32] {
+    use k256::sha2::{Sha256, Digest};
+
+    const BLOCK_SIZE: usize = 64;
+
+    let key = key.as_ref();
+
+    let mut block = [0; BLOCK_SIZE];
+
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let inner_key = block.map(|byte| byte ^ 0x36);
+    let outer_key = block.map(|byte| byte ^ 0x5c);
+
+    let inner = Sha256::new()
+        .chain_update(inner_key)
+        .chain_update(data)
+        .finalize();
+
+    Sha256::new()
+        .chain_update(outer_key)
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
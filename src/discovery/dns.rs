@@ -0,0 +1,361 @@
+//! Minimal DNS packets codec used by the mDNS discovery.
+
+use super::DiscoveryError;
+
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_TXT: u16 = 16;
+pub const TYPE_SRV: u16 = 33;
+pub const TYPE_ANY: u16 = 255;
+
+const CLASS_IN: u16 = 1;
+
+/// Flags of the authoritative response.
+const FLAGS_RESPONSE: u16 = 0x8400;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Question {
+    pub name: String,
+    pub kind: u16
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RecordData {
+    Ptr(String),
+    Txt(Vec<String>),
+
+    Srv {
+        port: u16,
+        target: String
+    },
+
+    Other(u16)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Record {
+    pub name: String,
+    pub ttl: u32,
+    pub data: RecordData
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Packet {
+    pub id: u16,
+    pub response: bool,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>
+}
+
+fn write_name(buf: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label);
+    }
+
+    buf.push(0);
+}
+
+struct Reader<'a> {
+    packet: &'a [u8],
+    offset: usize
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> Result<&[u8], DiscoveryError> {
+        let bytes = self.packet.get(self.offset..self.offset + len)
+            .ok_or(DiscoveryError::InvalidPacket)?;
+
+        self.offset += len;
+
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, DiscoveryError> {
+        let bytes = self.bytes(2)?;
+
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, DiscoveryError> {
+        let bytes = self.bytes(4)?;
+
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read possibly compressed name.
+    fn name(&mut self) -> Result<String, DiscoveryError> {
+        let mut labels = Vec::new();
+        let mut offset = self.offset;
+        let mut jumped = false;
+
+        // Limit amount of pointers to prevent loops
+        for _ in 0..128 {
+            let len = *self.packet.get(offset).ok_or(DiscoveryError::InvalidPacket)? as usize;
+
+            if len == 0 {
+                if !jumped {
+                    self.offset = offset + 1;
+                }
+
+                return Ok(labels.join("."));
+            }
+
+            // Compression pointer
+            if len & 0xC0 == 0xC0 {
+                let next = *self.packet.get(offset + 1).ok_or(DiscoveryError::InvalidPacket)? as usize;
+
+                if !jumped {
+                    self.offset = offset + 2;
+                }
+
+                offset = ((len & 0x3F) << 8) | next;
+                jumped = true;
+
+                continue;
+            }
+
+            let label = self.packet.get(offset + 1..offset + 1 + len)
+                .ok_or(DiscoveryError::InvalidPacket)?;
+
+            labels.push(String::from_utf8_lossy(label).to_string());
+
+            offset += len + 1;
+        }
+
+        Err(DiscoveryError::InvalidPacket)
+    }
+}
+
+impl Packet {
+    /// Create query of the given name's PTR records.
+    pub fn query(name: impl ToString) -> Self {
+        Self {
+            questions: vec![Question {
+                name: name.to_string(),
+                kind: TYPE_PTR
+            }],
+            ..Self::default()
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(512);
+
+        let flags = if self.response { FLAGS_RESPONSE } else { 0 };
+
+        buf.extend(self.id.to_be_bytes());
+        buf.extend(flags.to_be_bytes());
+        buf.extend((self.questions.len() as u16).to_be_bytes());
+        buf.extend((self.answers.len() as u16).to_be_bytes());
+        buf.extend([0; 4]);
+
+        for question in &self.questions {
+            write_name(&mut buf, &question.name);
+
+            buf.extend(question.kind.to_be_bytes());
+            buf.extend(CLASS_IN.to_be_bytes());
+        }
+
+        for answer in &self.answers {
+            write_name(&mut buf, &answer.name);
+
+            let mut data = Vec::new();
+
+            let kind = match &answer.data {
+                RecordData::Ptr(name) => {
+                    write_name(&mut data, name);
+
+                    TYPE_PTR
+                }
+
+                RecordData::Txt(entries) => {
+                    for entry in entries {
+                        let entry = &entry.as_bytes()[..entry.len().min(255)];
+
+                        data.push(entry.len() as u8);
+                        data.extend_from_slice(entry);
+                    }
+
+                    TYPE_TXT
+                }
+
+                RecordData::Srv { port, target } => {
+                    // Priority and weight
+                    data.extend([0; 4]);
+                    data.extend(port.to_be_bytes());
+
+                    write_name(&mut data, target);
+
+                    TYPE_SRV
+                }
+
+                RecordData::Other(kind) => *kind
+            };
+
+            buf.extend(kind.to_be_bytes());
+            buf.extend(CLASS_IN.to_be_bytes());
+            buf.extend(answer.ttl.to_be_bytes());
+            buf.extend((data.len() as u16).to_be_bytes());
+            buf.extend(data);
+        }
+
+        buf
+    }
+
+    pub fn from_bytes(packet: &[u8]) -> Result<Self, DiscoveryError> {
+        let mut reader = Reader {
+            packet,
+            offset: 0
+        };
+
+        let id = reader.u16()?;
+        let flags = reader.u16()?;
+        let questions_count = reader.u16()?;
+        let answers_count = reader.u16()?;
+
+        // Authority and additional records are ignored
+        reader.bytes(4)?;
+
+        let mut questions = Vec::with_capacity(questions_count as usize);
+        let mut answers = Vec::with_capacity(answers_count as usize);
+
+        for _ in 0..questions_count {
+            let name = reader.name()?;
+            let kind = reader.u16()?;
+
+            // Class
+            reader.u16()?;
+
+            questions.push(Question {
+                name,
+                kind
+            });
+        }
+
+        for _ in 0..answers_count {
+            let name = reader.name()?;
+            let kind = reader.u16()?;
+
+            // Class
+            reader.u16()?;
+
+            let ttl = reader.u32()?;
+            let len = reader.u16()? as usize;
+
+            let end = reader.offset + len;
+
+            if end > packet.len() {
+                return Err(DiscoveryError::InvalidPacket);
+            }
+
+            let data = match kind {
+                TYPE_PTR => RecordData::Ptr(reader.name()?),
+
+                TYPE_TXT => {
+                    let mut entries = Vec::new();
+
+                    while reader.offset < end {
+                        let len = reader.bytes(1)?[0] as usize;
+
+                        entries.push(String::from_utf8_lossy(reader.bytes(len)?).to_string());
+                    }
+
+                    RecordData::Txt(entries)
+                }
+
+                TYPE_SRV => {
+                    reader.bytes(4)?;
+
+                    RecordData::Srv {
+                        port: reader.u16()?,
+                        target: reader.name()?
+                    }
+                }
+
+                kind => RecordData::Other(kind)
+            };
+
+            reader.offset = end;
+
+            answers.push(Record {
+                name,
+                ttl,
+                data
+            });
+        }
+
+        Ok(Self {
+            id,
+            response: flags & 0x8000 != 0,
+            questions,
+            answers
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packet() -> Result<(), DiscoveryError> {
+        let query = Packet::query("_hyperborea._tcp.local");
+
+        assert_eq!(Packet::from_bytes(&query.to_bytes())?, query);
+
+        let response = Packet {
+            id: 0,
+            response: true,
+            questions: vec![],
+            answers: vec![
+                Record {
+                    name: String::from("_hyperborea._tcp.local"),
+                    ttl: 120,
+                    data: RecordData::Ptr(String::from("0011223344556677._hyperborea._tcp.local"))
+                },
+                Record {
+                    name: String::from("0011223344556677._hyperborea._tcp.local"),
+                    ttl: 120,
+                    data: RecordData::Srv { port: 8001, target: String::from("0011223344556677.local") }
+                },
+                Record {
+                    name: String::from("0011223344556677._hyperborea._tcp.local"),
+                    ttl: 120,
+                    data: RecordData::Txt(vec![String::from("port=8001"), String::from("key=abc")])
+                }
+            ]
+        };
+
+        assert_eq!(Packet::from_bytes(&response.to_bytes())?, response);
+
+        // Truncated packet
+        let bytes = response.to_bytes();
+
+        assert!(Packet::from_bytes(&bytes[..bytes.len() - 4]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_names() -> Result<(), DiscoveryError> {
+        let mut bytes = Packet::query("_hyperborea._tcp.local").to_bytes();
+
+        // Question name starting at offset 12, answer name is a pointer to it
+        bytes[7] = 1;
+        bytes.extend([0xC0, 12]);
+        bytes.extend(TYPE_PTR.to_be_bytes());
+        bytes.extend(CLASS_IN.to_be_bytes());
+        bytes.extend(120u32.to_be_bytes());
+        bytes.extend(7u16.to_be_bytes());
+        bytes.extend([4, b't', b'e', b's', b't', 0xC0, 12]);
+
+        let packet = Packet::from_bytes(&bytes)?;
+
+        assert_eq!(packet.answers[0].name, "_hyperborea._tcp.local");
+        assert_eq!(packet.answers[0].data, RecordData::Ptr(String::from("test._hyperborea._tcp.local")));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+use socket2::{Socket, Domain, Type, Protocol};
+
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+use super::dns::*;
+use super::*;
+
+/// Create UDP socket bound to the given address
+/// with multicast packets sent to the params' interface.
+fn bind_socket(params: &DiscoveryParams, address: SocketAddrV4, join: bool) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+
+    socket.set_reuse_address(true)?;
+
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+
+    socket.set_nonblocking(true)?;
+    socket.bind(&address.into())?;
+
+    if join {
+        socket.join_multicast_v4(params.group.ip(), &params.interface)?;
+    }
+
+    socket.set_multicast_if_v4(&params.interface)?;
+    socket.set_multicast_loop_v4(true)?;
+
+    UdpSocket::from_std(socket.into())
+}
+
+/// Check if the packet is a query of the hyperborea service.
+fn is_service_query(packet: &Packet) -> bool {
+    !packet.response && packet.questions.iter().any(|question| {
+        matches!(question.kind, TYPE_PTR | TYPE_ANY) &&
+            question.name.eq_ignore_ascii_case(SERVICE_NAME)
+    })
+}
+
+fn service_response(id: u16, ttl: u32, info: &ServiceInfo) -> Packet {
+    let instance = info.instance_name();
+
+    let target = format!("{}.local", instance.trim_end_matches(SERVICE_NAME).trim_end_matches('.'));
+
+    Packet {
+        id,
+        response: true,
+        questions: vec![],
+        answers: vec![
+            Record {
+                name: SERVICE_NAME.to_string(),
+                ttl,
+                data: RecordData::Ptr(instance.clone())
+            },
+            Record {
+                name: instance.clone(),
+                ttl,
+                data: RecordData::Srv {
+                    port: info.port,
+                    target
+                }
+            },
+            Record {
+                name: instance,
+                ttl,
+                data: RecordData::Txt(info.to_txt())
+            }
+        ]
+    }
+}
+
+/// Task answering local network queries
+/// of the hyperborea service.
+/// 
+/// Advertisement is stopped when the value is dropped.
+pub struct Advertiser {
+    task: JoinHandle<()>
+}
+
+impl Advertiser {
+    /// Start advertising the service in background.
+    /// 
+    /// Must be called within the tokio runtime.
+    pub fn start(params: DiscoveryParams, info: ServiceInfo) -> Result<Self, DiscoveryError> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?params, port = info.port, "Starting local discovery advertiser");
+
+        let address = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, params.group.port());
+
+        let socket = bind_socket(&params, address, true)?;
+
+        let task = tokio::spawn(async move {
+            let mut buf = [0; 9000];
+
+            loop {
+                let Ok((len, querier)) = socket.recv_from(&mut buf).await else {
+                    continue;
+                };
+
+                let Ok(packet) = Packet::from_bytes(&buf[..len]) else {
+                    continue;
+                };
+
+                if !is_service_query(&packet) {
+                    continue;
+                }
+
+                #[cfg(feature = "tracing")]
+                tracing::trace!(?querier, "Answering local discovery query");
+
+                // Responses are always sent directly to the querier
+                let response = service_response(packet.id, params.ttl, &info);
+
+                if let Err(_err) = socket.send_to(&response.to_bytes(), querier).await {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(?querier, ?_err, "Failed to answer local discovery query");
+                }
+            }
+        });
+
+        Ok(Self {
+            task
+        })
+    }
+
+    #[inline]
+    /// Stop advertising the service.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for Advertiser {
+    #[inline]
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[inline]
+/// Find hyperborea servers in the local network
+/// using default discovery params.
+/// 
+/// Found servers' public keys are not verified.
+pub async fn discover_local(timeout: Duration) -> Result<Vec<DiscoveredServer>, DiscoveryError> {
+    discover_local_with(&DiscoveryParams::default(), timeout).await
+}
+
+/// Find hyperborea servers in the local network.
+/// 
+/// Query is sent to the multicast group and responses
+/// are collected until the timeout is reached.
+/// 
+/// Found servers' public keys are not verified.
+pub async fn discover_local_with(params: &DiscoveryParams, timeout: Duration) -> Result<Vec<DiscoveredServer>, DiscoveryError> {
+    let socket = bind_socket(params, SocketAddrV4::new(params.interface, 0), false)?;
+
+    let query = Packet::query(SERVICE_NAME);
+
+    socket.send_to(&query.to_bytes(), SocketAddr::V4(params.group)).await?;
+
+    let deadline = tokio::time::Instant::now() + timeout;
+
+    let mut servers = Vec::new();
+    let mut found = HashSet::new();
+
+    let mut buf = [0; 9000];
+
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, responder) = received?;
+
+        let Ok(packet) = Packet::from_bytes(&buf[..len]) else {
+            continue;
+        };
+
+        if !packet.response {
+            continue;
+        }
+
+        for answer in packet.answers {
+            let RecordData::Txt(entries) = answer.data else {
+                continue;
+            };
+
+            if !answer.name.to_ascii_lowercase().ends_with(SERVICE_NAME) {
+                continue;
+            }
+
+            let Ok(info) = ServiceInfo::from_txt(&entries) else {
+                continue;
+            };
+
+            let server = DiscoveredServer {
+                address: responder.ip(),
+                port: info.port,
+                public_key: info.public_key
+            };
+
+            if found.insert(server.clone()) {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(?server, "Found local server");
+
+                servers.push(server);
+            }
+        }
+    }
+
+    Ok(servers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Multicast tests can be skipped in environments
+    /// without multicast support on the loopback interface.
+    fn multicast_disabled() -> bool {
+        std::env::var_os("HYPERBOREA_SKIP_MULTICAST_TESTS").is_some()
+    }
+
+    fn loopback_params(port: u16) -> DiscoveryParams {
+        DiscoveryParams {
+            group: SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), port),
+            interface: Ipv4Addr::LOCALHOST,
+            ttl: 120
+        }
+    }
+
+    #[test]
+    fn response() -> Result<(), DiscoveryError> {
+        let info = ServiceInfo::new(8001, SecretKey::random().public_key());
+
+        assert!(is_service_query(&Packet::query(SERVICE_NAME)));
+        assert!(!is_service_query(&Packet::query("_http._tcp.local")));
+
+        let response = Packet::from_bytes(&service_response(7, 120, &info).to_bytes())?;
+
+        assert!(response.response);
+        assert!(!is_service_query(&response));
+        assert_eq!(response.id, 7);
+
+        let txt = response.answers.into_iter()
+            .find_map(|answer| match answer.data {
+                RecordData::Txt(entries) => Some(entries),
+                _ => None
+            });
+
+        assert_eq!(ServiceInfo::from_txt(&txt.unwrap())?, info);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn discover() -> Result<(), DiscoveryError> {
+        if multicast_disabled() {
+            return Ok(());
+        }
+
+        let params = loopback_params(48482);
+
+        let info_a = ServiceInfo::new(8001, SecretKey::random().public_key());
+        let info_b = ServiceInfo::new(8002, SecretKey::random().public_key());
+
+        let (advertiser_a, advertiser_b) = match (Advertiser::start(params, info_a.clone()), Advertiser::start(params, info_b.clone())) {
+            (Ok(a), Ok(b)) => (a, b),
+
+            // Multicast is not supported by the environment
+            (Err(DiscoveryError::Io(_)), _) | (_, Err(DiscoveryError::Io(_))) => return Ok(()),
+
+            (Err(err), _) | (_, Err(err)) => return Err(err)
+        };
+
+        let servers = discover_local_with(&params, Duration::from_millis(500)).await?;
+
+        assert!(servers.iter().any(|server| server.public_key == info_a.public_key && server.port == 8001));
+        assert!(servers.iter().any(|server| server.public_key == info_b.public_key && server.port == 8002));
+
+        // Stopped advertiser doesn't answer queries anymore
+        advertiser_b.stop();
+
+        let servers = discover_local_with(&params, Duration::from_millis(500)).await?;
+
+        assert!(servers.iter().any(|server| server.public_key == info_a.public_key));
+        assert!(!servers.iter().any(|server| server.public_key == info_b.public_key));
+
+        drop(advertiser_a);
+
+        Ok(())
+    }
+}
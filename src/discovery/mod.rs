@@ -0,0 +1,192 @@
+//! Local peer discovery.
+//! 
+//! Servers can advertise themselves in the local network as
+//! a `_hyperborea._tcp.local` mDNS service, so clients without
+//! any bootstrap addresses can find nearby servers. Advertised
+//! public keys are only claims and must be verified with the
+//! `GET /api/v1/info` request before trusting the server.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use crate::crypto::prelude::*;
+
+#[cfg(feature = "mdns")]
+mod dns;
+
+#[cfg(feature = "mdns")]
+mod mdns;
+
+#[cfg(feature = "mdns")]
+pub use mdns::{
+    Advertiser,
+    discover_local,
+    discover_local_with
+};
+
+/// Name of the advertised mDNS service.
+pub const SERVICE_NAME: &str = "_hyperborea._tcp.local";
+
+#[derive(Debug, thiserror::Error)]
+pub enum DiscoveryError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid DNS packet")]
+    InvalidPacket,
+
+    #[error("TXT record entry `{0}` is not specified")]
+    MissingTxtEntry(&'static str),
+
+    #[error("TXT record entry `{0}` has invalid value")]
+    InvalidTxtEntry(&'static str)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DiscoveryParams {
+    /// Multicast group to which queries are sent.
+    pub group: SocketAddrV4,
+
+    /// Local interface used to send and receive
+    /// multicast packets.
+    pub interface: Ipv4Addr,
+
+    /// Time to live of the advertised records in seconds.
+    pub ttl: u32
+}
+
+impl Default for DiscoveryParams {
+    fn default() -> Self {
+        Self {
+            group: SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353),
+            interface: Ipv4Addr::UNSPECIFIED,
+            ttl: 120
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Information about the server stored
+/// in the service's TXT record.
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use hyperborealib::crypto::prelude::*;
+/// use hyperborealib::discovery::ServiceInfo;
+/// 
+/// let info = ServiceInfo::new(8001, SecretKey::random().public_key());
+/// 
+/// assert_eq!(ServiceInfo::from_txt(&info.to_txt()).unwrap(), info);
+/// ```
+pub struct ServiceInfo {
+    pub port: u16,
+    pub public_key: PublicKey
+}
+
+impl ServiceInfo {
+    #[inline]
+    pub fn new(port: u16, public_key: PublicKey) -> Self {
+        Self {
+            port,
+            public_key
+        }
+    }
+
+    /// Get name of the advertised service instance.
+    /// 
+    /// Name contains hex encoded first 8 bytes
+    /// of the public key.
+    pub fn instance_name(&self) -> String {
+        let fingerprint = self.public_key.to_bytes()[1..9]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+
+        format!("{fingerprint}.{SERVICE_NAME}")
+    }
+
+    /// Encode info as TXT record entries.
+    pub fn to_txt(&self) -> Vec<String> {
+        vec![
+            format!("standard={}", crate::STANDARD_VERSION),
+            format!("port={}", self.port),
+            format!("key={}", self.public_key.to_base64())
+        ]
+    }
+
+    /// Decode info from TXT record entries.
+    /// 
+    /// Unknown entries are ignored.
+    pub fn from_txt(entries: &[String]) -> Result<Self, DiscoveryError> {
+        let get = |name: &'static str| entries.iter()
+            .find_map(|entry| entry.strip_prefix(name).and_then(|entry| entry.strip_prefix('=')))
+            .ok_or(DiscoveryError::MissingTxtEntry(name));
+
+        let port = get("port")?.parse::<u16>()
+            .map_err(|_| DiscoveryError::InvalidTxtEntry("port"))?;
+
+        let public_key = PublicKey::from_base64(get("key")?)
+            .map_err(|_| DiscoveryError::InvalidTxtEntry("key"))?;
+
+        Ok(Self {
+            port,
+            public_key
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Server found in the local network.
+pub struct DiscoveredServer {
+    /// Address from which the server has responded.
+    pub address: IpAddr,
+
+    pub port: u16,
+
+    /// Public key claimed by the server.
+    /// 
+    /// It's not verified and must be checked
+    /// before trusting the server.
+    pub public_key: PublicKey
+}
+
+impl DiscoveredServer {
+    #[inline]
+    pub fn socket_address(&self) -> SocketAddr {
+        SocketAddr::new(self.address, self.port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn txt() -> Result<(), DiscoveryError> {
+        let info = ServiceInfo::new(8001, SecretKey::random().public_key());
+
+        let txt = info.to_txt();
+
+        assert!(txt.contains(&String::from("port=8001")));
+        assert_eq!(ServiceInfo::from_txt(&txt)?, info);
+
+        // Unknown entries are ignored
+        let mut extended = vec![String::from("unknown=entry"), String::from("portal=1")];
+
+        extended.extend(txt);
+
+        assert_eq!(ServiceInfo::from_txt(&extended)?, info);
+
+        assert!(matches!(
+            ServiceInfo::from_txt(&[String::from("port=8001")]),
+            Err(DiscoveryError::MissingTxtEntry("key"))
+        ));
+
+        assert!(matches!(
+            ServiceInfo::from_txt(&[String::from("port=http"), format!("key={}", info.public_key.to_base64())]),
+            Err(DiscoveryError::InvalidTxtEntry("port"))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use serde_json::{json, Value as Json};
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlacklistError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] AsJsonError),
+
+    #[error(transparent)]
+    Serialize(#[from] serde_json::Error)
+}
+
+#[derive(Default, Debug, Clone)]
+/// Set of banned public keys.
+/// 
+/// Requests of the blacklisted keys are rejected by the
+/// server middleware, and announced records of these keys
+/// are not indexed. Clones of the blacklist share the same
+/// keys, so bans can be changed while the server is running.
+pub struct Blacklist(Arc<RwLock<HashSet<PublicKey>>>);
+
+impl Blacklist {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load blacklist from the JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BlacklistError> {
+        let json = serde_json::from_slice::<Json>(&std::fs::read(path)?)?;
+
+        Ok(Self::from_json(&json)?)
+    }
+
+    /// Save blacklist to the JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BlacklistError> {
+        std::fs::write(path, serde_json::to_vec_pretty(&self.to_json()?)?)?;
+
+        Ok(())
+    }
+
+    /// Ban the key.
+    /// 
+    /// Return `false` if the key was already banned.
+    pub fn add(&self, key: PublicKey) -> bool {
+        self.0.write()
+            .expect("Failed to lock blacklist")
+            .insert(key)
+    }
+
+    /// Unban the key.
+    /// 
+    /// Return `false` if the key wasn't banned.
+    pub fn remove(&self, key: &PublicKey) -> bool {
+        self.0.write()
+            .expect("Failed to lock blacklist")
+            .remove(key)
+    }
+
+    #[inline]
+    pub fn contains(&self, key: &PublicKey) -> bool {
+        self.0.read()
+            .expect("Failed to lock blacklist")
+            .contains(key)
+    }
+
+    /// Get all the banned keys.
+    pub fn keys(&self) -> Vec<PublicKey> {
+        self.0.read()
+            .expect("Failed to lock blacklist")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.read()
+            .expect("Failed to lock blacklist")
+            .len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl FromIterator<PublicKey> for Blacklist {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = PublicKey>>(iter: T) -> Self {
+        Self(Arc::new(RwLock::new(iter.into_iter().collect())))
+    }
+}
+
+impl PartialEq for Blacklist {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Blacklist {}
+
+impl std::hash::Hash for Blacklist {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
+
+impl AsJson for Blacklist {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let mut keys = self.keys()
+            .iter()
+            .map(PublicKey::to_base64)
+            .collect::<Vec<_>>();
+
+        // Keep the file stable between saves
+        keys.sort();
+
+        Ok(json!({
+            "keys": keys
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(keys) = json.get("keys").and_then(Json::as_array) else {
+            return Err(AsJsonError::FieldNotFound("keys"));
+        };
+
+        keys.iter()
+            .map(|key| {
+                key.as_str()
+                    .and_then(|key| PublicKey::from_base64(key).ok())
+                    .ok_or(AsJsonError::FieldValueInvalid("keys"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn add_remove() {
+        let blacklist = Blacklist::new();
+        let shared = blacklist.clone();
+
+        let key = SecretKey::random().public_key();
+
+        assert!(blacklist.add(key.clone()));
+        assert!(!blacklist.add(key.clone()));
+
+        // Clones share banned keys
+        assert!(shared.contains(&key));
+        assert_eq!(shared.len(), 1);
+
+        assert!(shared.remove(&key));
+        assert!(!shared.remove(&key));
+
+        assert!(blacklist.is_empty());
+    }
+
+    #[test]
+    fn serialize() -> Result<(), BlacklistError> {
+        let keys = [
+            SecretKey::random().public_key(),
+            SecretKey::random().public_key()
+        ];
+
+        let blacklist = Blacklist::from_iter(keys.clone());
+
+        let path = std::env::temp_dir().join("blacklist-serialize-test.json");
+
+        blacklist.save(&path)?;
+
+        let loaded = Blacklist::load(&path)?;
+
+        assert_eq!(loaded.len(), 2);
+        assert!(keys.iter().all(|key| loaded.contains(key)));
+
+        assert!(matches!(Blacklist::from_json(&json!({ "keys": ["invalid"] })), Err(AsJsonError::FieldValueInvalid("keys"))));
+
+        Ok(())
+    }
+}
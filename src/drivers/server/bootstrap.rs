@@ -0,0 +1,193 @@
+use crate::address::Address;
+use crate::http::client::HttpClient;
+use crate::rest_api::middleware::{Client as ClientMiddleware, Error as MiddlewareError};
+use crate::rest_api::types::Server as ServerApiRecord;
+
+use super::prelude::*;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BootstrapError {
+    #[error("Bootstrap address is not supported: {0:?}")]
+    UnsupportedAddress(Address),
+
+    #[error("Bootstrap address belongs to the current server")]
+    LocalServer,
+
+    #[error(transparent)]
+    Request(#[from] MiddlewareError),
+
+    #[error("Failed to index bootstrap server: {0}")]
+    Router(String)
+}
+
+impl BootstrapError {
+    #[inline]
+    /// Check if the bootstrap attempt can succeed later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Request(_) | Self::Router(_))
+    }
+}
+
+#[derive(Debug, Default)]
+/// Results of the bootstrap.
+pub struct BootstrapSummary {
+    /// Bootstrap servers which responded.
+    pub servers: Vec<ServerApiRecord>,
+
+    /// Amount of indexed servers, including the
+    /// ones known by the bootstrap servers.
+    pub indexed: usize,
+
+    /// Bootstrap addresses which failed.
+    pub failed: Vec<(Address, BootstrapError)>
+}
+
+impl BootstrapSummary {
+    #[inline]
+    /// Get failed addresses which can be retried later.
+    pub fn retryable(&self) -> Vec<Address> {
+        self.failed.iter()
+            .filter(|(_, err)| err.is_retryable())
+            .map(|(address, _)| address.clone())
+            .collect()
+    }
+}
+
+/// Request info and known servers of the bootstrap
+/// server and index them by the router.
+/// 
+/// Return the bootstrap server's record and
+/// amount of the indexed servers.
+pub(crate) async fn bootstrap_server<R, T, I>(
+    http_client: impl HttpClient,
+    driver: &ServerDriver<R, T, I>,
+    address: &Address
+) -> Result<(ServerApiRecord, usize), BootstrapError>
+where
+    R: Router + Sync,
+    T: Traversal + Sync,
+    I: MessagesInbox + Sync
+{
+    let Some(server_address) = address.server_address() else {
+        return Err(BootstrapError::UnsupportedAddress(address.clone()));
+    };
+
+    let client = ClientMiddleware::new(http_client, driver.as_client());
+    let local_public = driver.params().secret_key.public_key();
+
+    // Info response is validated by the middleware
+    let info = client.get_info(server_address).await?;
+
+    if info.public_key == local_public {
+        return Err(BootstrapError::LocalServer);
+    }
+
+    let servers = client.get_servers(server_address).await?;
+
+    let bootstrap_server = ServerApiRecord::new(info.public_key, server_address);
+
+    let mut indexed = driver.router().index_server(bootstrap_server.clone()).await
+        .map_err(|err| BootstrapError::Router(err.to_string()))? as usize;
+
+    let servers = servers.into_iter()
+        .filter(|server| server.public_key != local_public && server.public_key != bootstrap_server.public_key)
+        .filter(|server| !driver.is_blacklisted(&server.public_key))
+        .collect::<Vec<_>>();
+
+    for result in driver.router().index_servers(servers).await {
+        match result {
+            Ok(true) => indexed += 1,
+            Ok(false) => (),
+
+            Err(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(bootstrap = server_address, "Failed to index server known by the bootstrap server: {_err}");
+            }
+        }
+    }
+
+    Ok((bootstrap_server, indexed))
+}
+
+#[cfg(all(test, feature = "router-ram", feature = "traversal-bfs-recursion", feature = "inbox-ram"))]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::crypto::prelude::*;
+    use crate::http::client::tests::MockHttpClient;
+    use crate::rest_api::prelude::*;
+    use crate::drivers::server::router::ram::RamRouter;
+    use crate::drivers::server::traversal::bfs_recursion::BfsRecursionTraversal;
+    use crate::drivers::server::messages_inbox::ram::RamMessagesInbox;
+
+    use super::*;
+
+    fn get_driver(servers: Vec<Address>) -> ServerDriver<RamRouter, BfsRecursionTraversal, RamMessagesInbox> {
+        let mut params = ServerParams::default();
+
+        params.bootstrap.servers = servers;
+
+        ServerDriver::new(RamRouter::default(), BfsRecursionTraversal, RamMessagesInbox::default(), params)
+    }
+
+    #[tokio::test]
+    async fn bootstrap() -> Result<(), Box<dyn std::error::Error>> {
+        let bootstrap_secret = SecretKey::random();
+        let forged_secret = SecretKey::random();
+
+        let known_server = ServerApiRecord::new(SecretKey::random().public_key(), "known.example.org");
+
+        let mut forged_info = InfoResponse::new(&forged_secret);
+
+        forged_info.public_key = SecretKey::random().public_key();
+
+        let http = MockHttpClient::new([
+            (String::from("http://bootstrap.example.org/api/v1/info"), InfoResponse::new(&bootstrap_secret).to_json()?),
+            (String::from("http://bootstrap.example.org/api/v1/servers"), ServersResponse::new(vec![known_server.clone()]).to_json()?),
+            (String::from("http://forged.example.org/api/v1/info"), forged_info.to_json()?),
+            (String::from("http://forged.example.org/api/v1/servers"), ServersResponse::new(vec![]).to_json()?)
+        ]);
+
+        let driver = get_driver(vec![
+            Address::from_str("http://bootstrap.example.org")?,
+            Address::from_str("forged.example.org")?,
+            Address::from_str("offline.example.org")?,
+            Address::from_str("https://secure.example.org")?
+        ]);
+
+        let summary = driver.bootstrap(http).await;
+
+        assert_eq!(summary.servers, [ServerApiRecord::new(bootstrap_secret.public_key(), "bootstrap.example.org")]);
+        assert_eq!(summary.indexed, 2);
+        assert_eq!(summary.failed.len(), 3);
+
+        assert!(driver.router().lookup_server(&bootstrap_secret.public_key()).await?.is_some());
+        assert!(driver.router().lookup_server(&known_server.public_key).await?.is_some());
+        assert_eq!(driver.router().servers().await?.len(), 2);
+
+        // Forged and unreachable servers are retried, unsupported addresses are not
+        assert_eq!(summary.retryable(), [
+            Address::from_str("forged.example.org")?,
+            Address::from_str("offline.example.org")?
+        ]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bootstrap_local_server() -> Result<(), Box<dyn std::error::Error>> {
+        let driver = get_driver(vec![Address::from_str("127.0.0.1:8001")?]);
+
+        let http = MockHttpClient::new([
+            (String::from("http://127.0.0.1:8001/api/v1/info"), InfoResponse::new(&driver.params().secret_key).to_json()?)
+        ]);
+
+        let summary = driver.bootstrap(http).await;
+
+        assert!(summary.servers.is_empty());
+        assert!(matches!(summary.failed[0].1, BootstrapError::LocalServer));
+        assert!(summary.retryable().is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use crate::http::client::HttpClient;
+use crate::rest_api::prelude::*;
+
+use super::prelude::*;
+
+/// Request info of all the known servers
+/// and mark their health in the router.
+pub(crate) async fn check_servers_health<HttpClientExt, RouterExt, TraversalExt, MessagesInboxExt>(
+    http_client: &HttpClientExt,
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    timeout: Duration
+) -> (u64, u64)
+where
+    HttpClientExt: HttpClient + 'static,
+    RouterExt: Router + Send + Sync,
+    TraversalExt: Traversal + Send + Sync,
+    MessagesInboxExt: MessagesInbox + Send + Sync
+{
+    let servers = match driver.router().servers().await {
+        Ok(servers) => servers,
+
+        Err(_err) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Failed to list servers for health checks: {_err}");
+
+            return (0, 0);
+        }
+    };
+
+    let mut checks = tokio::task::JoinSet::new();
+
+    for server in servers {
+        let client = ClientMiddleware::new(http_client.clone(), driver.as_client());
+
+        checks.spawn(async move {
+            let health = match tokio::time::timeout(timeout, client.get_info(&server.address)).await {
+                Ok(Ok(info)) if info.public_key == server.public_key => ServerHealth::Healthy,
+                _ => ServerHealth::Unhealthy
+            };
+
+            (server, health)
+        });
+    }
+
+    let mut healthy = 0;
+    let mut unhealthy = 0;
+
+    while let Some(check) = checks.join_next().await {
+        let Ok((server, health)) = check else {
+            continue;
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(server = server.address, ?health, "Checked server health");
+
+        if let Err(_err) = driver.router().mark_server_health(&server.public_key, health).await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(server = server.address, "Failed to store server health: {_err}");
+        }
+
+        if health.is_unhealthy() {
+            unhealthy += 1;
+        } else {
+            healthy += 1;
+        }
+    }
+
+    (healthy, unhealthy)
+}
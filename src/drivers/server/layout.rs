@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+
+use k256::sha2::{Sha256, Digest};
+
+use crate::crypto::prelude::*;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Files layout used by the filesystem based backends.
+/// 
+/// Large relays can store hundreds of thousands of
+/// records which are poorly handled by some filesystems
+/// when placed in a single folder. `Sharded` layout
+/// groups records in sub-folders named by the first
+/// two bytes of the SHA256 hash of the record's public key.
+/// 
+/// ```text
+/// Flat:    storage/<public key>
+/// Sharded: storage/<shard>/<public key>
+/// ```
+pub enum StorageLayout {
+    #[default]
+    Flat,
+    Sharded
+}
+
+impl StorageLayout {
+    /// Get shard name of the given public key.
+    /// 
+    /// Shard name is a hex encoded first two bytes
+    /// of the SHA256 hash of the public key.
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::drivers::server::layout::StorageLayout;
+    /// 
+    /// let public_key = SecretKey::random().public_key();
+    /// 
+    /// assert_eq!(StorageLayout::shard(&public_key).len(), 4);
+    /// ```
+    pub fn shard(public_key: &PublicKey) -> String {
+        let hash = Sha256::digest(public_key.to_bytes());
+
+        format!("{:02x}{:02x}", hash[0], hash[1])
+    }
+
+    /// Check if given file name is a shard name.
+    pub fn is_shard(name: &str) -> bool {
+        name.len() == 4 && name.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+    }
+
+    /// Get path to the record of the given public key
+    /// within the given folder using the current layout.
+    pub fn path(&self, folder: impl AsRef<Path>, public_key: &PublicKey) -> PathBuf {
+        match self {
+            Self::Flat => folder.as_ref()
+                .join(public_key.to_base64()),
+
+            Self::Sharded => folder.as_ref()
+                .join(Self::shard(public_key))
+                .join(public_key.to_base64())
+        }
+    }
+
+    /// Get paths to the record of the given public key
+    /// within the given folder in the current layout and,
+    /// for the sharded one, in the legacy flat layout.
+    /// 
+    /// Records of the half-migrated storage can
+    /// be stored in both layouts at once.
+    pub fn paths(&self, folder: impl AsRef<Path>, public_key: &PublicKey) -> Vec<PathBuf> {
+        let mut paths = vec![self.path(folder.as_ref(), public_key)];
+
+        if *self == Self::Sharded {
+            paths.push(Self::Flat.path(folder, public_key));
+        }
+
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shard() {
+        let public_key = SecretKey::random().public_key();
+        let shard = StorageLayout::shard(&public_key);
+
+        assert!(StorageLayout::is_shard(&shard));
+        assert!(!StorageLayout::is_shard(&public_key.to_base64()));
+
+        assert_eq!(StorageLayout::shard(&public_key), shard);
+
+        assert_eq!(StorageLayout::Flat.path("storage", &public_key), PathBuf::from("storage").join(public_key.to_base64()));
+        assert_eq!(StorageLayout::Sharded.path("storage", &public_key), PathBuf::from("storage").join(&shard).join(public_key.to_base64()));
+
+        assert_eq!(StorageLayout::Flat.paths("storage", &public_key), [
+            PathBuf::from("storage").join(public_key.to_base64())
+        ]);
+
+        assert_eq!(StorageLayout::Sharded.paths("storage", &public_key), [
+            PathBuf::from("storage").join(shard).join(public_key.to_base64()),
+            PathBuf::from("storage").join(public_key.to_base64())
+        ]);
+    }
+}
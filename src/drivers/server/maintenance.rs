@@ -0,0 +1,326 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::http::client::HttpClient;
+use crate::rest_api::prelude::*;
+use crate::rest_api::types::Server as ServerApiRecord;
+use crate::time::timestamp;
+
+use super::health::check_servers_health;
+use super::prelude::*;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// Periodic jobs of the server driver.
+/// 
+/// Every job is disabled by default.
+pub struct MaintenanceConfig {
+    /// Delay between removals of the expired inbox messages.
+    /// 
+    /// Refer to `MessagesInbox::cleanup`.
+    pub inbox_cleanup: Option<Duration>,
+
+    /// Delay between removals of the clients which
+    /// weren't seen for longer than the second value.
+    /// 
+    /// Refer to `Router::cleanup`.
+    pub router_cleanup: Option<(Duration, Duration)>,
+
+    /// Periodic reachability checks of the known servers.
+    pub health_checks: Option<HealthCheckParams>,
+
+    /// Delay between announcements of the current
+    /// server to all the known servers.
+    pub announce: Option<Duration>
+}
+
+impl MaintenanceConfig {
+    #[inline]
+    pub fn with_inbox_cleanup(self, interval: Duration) -> Self {
+        Self {
+            inbox_cleanup: Some(interval),
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_router_cleanup(self, interval: Duration, older_than: Duration) -> Self {
+        Self {
+            router_cleanup: Some((interval, older_than)),
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_health_checks(self, params: HealthCheckParams) -> Self {
+        Self {
+            health_checks: Some(params),
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_announce(self, interval: Duration) -> Self {
+        Self {
+            announce: Some(interval),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+/// Handle of the spawned maintenance jobs.
+/// 
+/// Jobs are aborted when the handle is dropped.
+pub struct MaintenanceHandle {
+    tasks: Vec<JoinHandle<()>>
+}
+
+impl MaintenanceHandle {
+    #[inline]
+    /// Get amount of the running jobs.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    #[inline]
+    /// Abort all the jobs.
+    pub fn abort(self) {
+        drop(self);
+    }
+}
+
+impl Drop for MaintenanceHandle {
+    fn drop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+impl<R, T, I> ServerDriver<R, T, I>
+where
+    R: Router + Send + Sync + 'static,
+    T: Traversal + Send + Sync + 'static,
+    I: MessagesInbox + Send + Sync + 'static
+{
+    /// Spawn background tasks running the enabled
+    /// maintenance jobs with their intervals.
+    /// 
+    /// The first run of every job happens immediately.
+    /// Drop the returned handle to stop the jobs.
+    pub fn spawn_maintenance(self: Arc<Self>, http_client: impl HttpClient + 'static, config: MaintenanceConfig) -> MaintenanceHandle {
+        let mut handle = MaintenanceHandle::default();
+
+        if let Some(interval) = config.inbox_cleanup {
+            let driver = self.clone();
+
+            handle.tasks.push(spawn_job(interval, move || {
+                let driver = driver.clone();
+
+                async move {
+                    match driver.messages_inbox().cleanup().await {
+                        Ok(_removed) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::debug!(removed = _removed, "Removed expired inbox messages");
+                        }
+
+                        Err(_err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!("Failed to clean up messages inbox: {_err}");
+                        }
+                    }
+                }
+            }));
+        }
+
+        if let Some((interval, older_than)) = config.router_cleanup {
+            let driver = self.clone();
+
+            handle.tasks.push(spawn_job(interval, move || {
+                let driver = driver.clone();
+
+                async move {
+                    match driver.router().cleanup(older_than).await {
+                        Ok(_removed) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::debug!(removed = _removed, "Removed stale routing table records");
+                        }
+
+                        Err(_err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!("Failed to clean up routing table: {_err}");
+                        }
+                    }
+                }
+            }));
+        }
+
+        if let Some(params) = config.health_checks {
+            let driver = self.clone();
+            let http_client = http_client.clone();
+
+            handle.tasks.push(spawn_job(params.interval, move || {
+                let driver = driver.clone();
+                let http_client = http_client.clone();
+
+                async move {
+                    let (_healthy, _unhealthy) = check_servers_health(&http_client, &driver, params.timeout).await;
+
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(healthy = _healthy, unhealthy = _unhealthy, "Checked known servers health");
+                }
+            }));
+        }
+
+        if let Some(interval) = config.announce {
+            let driver = self.clone();
+
+            handle.tasks.push(spawn_job(interval, move || {
+                let driver = driver.clone();
+                let http_client = http_client.clone();
+
+                async move {
+                    let (_announced, _failed) = announce_server(&http_client, &driver).await;
+
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(announced = _announced, failed = _failed, "Announced server to known servers");
+                }
+            }));
+        }
+
+        handle
+    }
+}
+
+/// Spawn task running the job with given interval.
+fn spawn_job<F, Fut>(interval: Duration, job: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            interval.tick().await;
+
+            job().await;
+        }
+    })
+}
+
+/// Announce the current server to all the known servers.
+/// 
+/// Return amounts of servers which accepted
+/// and rejected the announcement.
+async fn announce_server<H, R, T, I>(http_client: &H, driver: &ServerDriver<R, T, I>) -> (u64, u64)
+where
+    H: HttpClient,
+    R: Router + Sync,
+    T: Traversal + Sync,
+    I: MessagesInbox + Sync
+{
+    let servers = match driver.router().servers().await {
+        Ok(servers) => servers,
+
+        Err(_err) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Failed to list servers for announcement: {_err}");
+
+            return (0, 0);
+        }
+    };
+
+    let secret_key = &driver.params().secret_key;
+    let local_server = ServerApiRecord::new(secret_key.public_key(), &driver.params().address);
+
+    let mut announced = 0;
+    let mut failed = 0;
+
+    for server in servers {
+        let request = AnnounceRequest::server_at(secret_key, local_server.clone(), timestamp());
+
+        let proof_seed = request.0.proof_seed;
+
+        let response = http_client.post_request::<AnnounceRequest, AnnounceResponse>(
+            format!("http://{}/api/v1/announce", server.address),
+            request
+        ).await;
+
+        match response {
+            Ok(response) if matches!(response.validate(proof_seed), Ok(true)) && matches!(response.0, Response::Success { .. }) => {
+                announced += 1;
+            }
+
+            _ => {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(server = server.address, "Failed to announce server");
+
+                failed += 1;
+            }
+        }
+    }
+
+    (announced, failed)
+}
+
+#[cfg(all(test, feature = "router-ram", feature = "traversal-bfs-recursion", feature = "inbox-ram"))]
+mod tests {
+    use crate::crypto::prelude::*;
+    use crate::http::client::tests::MockHttpClient;
+    use crate::drivers::server::router::ram::RamRouter;
+    use crate::drivers::server::traversal::bfs_recursion::BfsRecursionTraversal;
+    use crate::drivers::server::messages_inbox::ram::RamMessagesInbox;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn maintenance() -> Result<(), Box<dyn std::error::Error>> {
+        let online_secret = SecretKey::random();
+
+        let online = ServerApiRecord::new(online_secret.public_key(), "online.example.org");
+        let offline = ServerApiRecord::new(SecretKey::random().public_key(), "offline.example.org");
+
+        let http = MockHttpClient::new([
+            (String::from("http://online.example.org/api/v1/info"), InfoResponse::new(&online_secret).to_json()?)
+        ]);
+
+        let driver = Arc::new(ServerDriver::new(RamRouter::default(), BfsRecursionTraversal, RamMessagesInbox::default(), ServerParams::default()));
+
+        driver.router().index_servers(vec![online.clone(), offline.clone()]).await;
+
+        let config = MaintenanceConfig::default()
+            .with_inbox_cleanup(Duration::from_secs(60))
+            .with_health_checks(HealthCheckParams {
+                interval: Duration::from_secs(10),
+                timeout: Duration::from_secs(1)
+            });
+
+        let handle = driver.clone().spawn_maintenance(http.clone(), config);
+
+        assert_eq!(handle.len(), 2);
+
+        tokio::time::sleep(Duration::from_secs(15)).await;
+
+        assert!(!driver.router().server_health(&online.public_key).await?.is_unhealthy());
+        assert!(driver.router().server_health(&offline.public_key).await?.is_unhealthy());
+
+        // Both servers are checked twice
+        assert_eq!(http.requests().len(), 4);
+
+        handle.abort();
+
+        tokio::time::sleep(Duration::from_secs(60)).await;
+
+        assert_eq!(http.requests().len(), 4);
+
+        Ok(())
+    }
+}
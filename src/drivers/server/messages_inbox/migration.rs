@@ -0,0 +1,422 @@
+use std::time::Duration;
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::rest_api::prelude::*;
+
+use super::MessagesInbox;
+
+/// Lease of the messages being moved
+/// from the source inbox.
+const MIGRATION_LEASE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError<F, T> {
+    #[error("Failed to read source inbox: {0}")]
+    Source(#[source] F),
+
+    #[error("Failed to write destination inbox: {0}")]
+    Destination(#[source] T)
+}
+
+/// Move all the stored messages from one inbox to another.
+/// 
+/// Every channel of every receiver is copied to the
+/// destination inbox using `add_message_info`, so the
+/// messages keep their receiving time and order. Only
+/// the copied messages are removed from the source, so
+/// messages received during the migration are kept there,
+/// and retries of a failed migration don't copy the same
+/// message twice.
+/// 
+/// If the source inbox supports leases, messages are leased,
+/// copied and then acknowledged by their ids. Messages which
+/// weren't copied because of a failure become available again
+/// when their lease expires.
+/// 
+/// Otherwise messages are polled from the source before they're
+/// copied, and the not copied ones are returned back to the source
+/// if the destination inbox fails. In this case a crash in the
+/// middle of the migration can lose the polled channel.
+/// 
+/// Return amount of moved messages.
+pub async fn migrate<F, T>(from: &F, to: &T) -> Result<u64, MigrationError<F::Error, T::Error>>
+where
+    F: MessagesInbox + Sync,
+    T: MessagesInbox + Sync
+{
+    let mut moved = 0;
+
+    for receiver in from.list_receivers().await.map_err(MigrationError::Source)? {
+        let channels = from.list_channels(receiver.clone()).await
+            .map_err(MigrationError::Source)?;
+
+        for (channel, _) in channels {
+            let leased = from.lease_messages(receiver.clone(), channel.clone().into(), None, None, None, MIGRATION_LEASE).await
+                .map_err(MigrationError::Source)?;
+
+            let count = match leased {
+                Some((messages, _)) => migrate_leased(from, to, &receiver, messages).await?,
+                None => migrate_polled(from, to, &receiver, &channel).await?
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                receiver = receiver.to_base64(),
+                channel = channel.as_str(),
+                messages = count,
+                "Migrated inbox channel"
+            );
+
+            moved += count;
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::info!(moved, "Migrated inbox messages");
+
+    Ok(moved)
+}
+
+/// Copy leased messages and acknowledge the copied ones.
+async fn migrate_leased<F, T>(
+    from: &F,
+    to: &T,
+    receiver: &PublicKey,
+    messages: Vec<MessageInfo>
+) -> Result<u64, MigrationError<F::Error, T::Error>>
+where
+    F: MessagesInbox + Sync,
+    T: MessagesInbox + Sync
+{
+    let mut copied = Vec::with_capacity(messages.len());
+    let mut result = Ok(());
+
+    for info in messages {
+        let id = info.id;
+
+        if let Err(err) = to.add_message_info(receiver.clone(), info).await {
+            result = Err(MigrationError::Destination(err));
+
+            break;
+        }
+
+        copied.extend(id);
+    }
+
+    let count = copied.len() as u64;
+
+    // Remove copied messages even if the rest
+    // failed, so they're not copied again
+    if !copied.is_empty() {
+        from.ack_messages(receiver.clone(), copied).await
+            .map_err(MigrationError::Source)?;
+    }
+
+    result.map(|_| count)
+}
+
+/// Poll messages of the channel and copy them,
+/// returning the not copied ones on failure.
+async fn migrate_polled<F, T>(
+    from: &F,
+    to: &T,
+    receiver: &PublicKey,
+    channel: &ChannelName
+) -> Result<u64, MigrationError<F::Error, T::Error>>
+where
+    F: MessagesInbox + Sync,
+    T: MessagesInbox + Sync
+{
+    let (messages, _) = from.poll_messages(receiver.clone(), channel.clone().into(), None, None, None).await
+        .map_err(MigrationError::Source)?;
+
+    let mut messages = messages.into_iter();
+    let mut count = 0;
+
+    while let Some(info) = messages.next() {
+        if let Err(err) = to.add_message_info(receiver.clone(), info.clone()).await {
+            // Returned messages keep their receiving time, but are
+            // stored after the ones received during the migration
+            for info in std::iter::once(info).chain(messages) {
+                from.add_message_info(receiver.clone(), info).await
+                    .map_err(MigrationError::Source)?;
+            }
+
+            return Err(MigrationError::Destination(err));
+        }
+
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(all(test, feature = "inbox-ram", feature = "inbox-sqlite", feature = "inbox-stored-queue"))]
+mod tests {
+    use crate::crypto::prelude::*;
+
+    use crate::drivers::server::messages_inbox::InboxStats;
+    use crate::drivers::server::messages_inbox::ram::RamMessagesInbox;
+    use crate::drivers::server::messages_inbox::sqlite::SqliteMessagesInbox;
+    use crate::drivers::server::messages_inbox::stored_queue::StoredQueueMessagesInbox;
+
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    fn message_info(channel: &str, text: &str, received_at: u64) -> MessageInfo {
+        MessageInfo::new(
+            Sender::new(get_client(), get_server()),
+            ChannelName::new(channel).unwrap(),
+            Message::new(text, "sign", MessageEncoding::default()),
+            received_at
+        )
+    }
+
+    #[tokio::test]
+    async fn migrate() -> Result<(), Box<dyn std::error::Error>> {
+        let from = RamMessagesInbox::new();
+        let to = SqliteMessagesInbox::in_memory()?;
+
+        let receivers = [
+            SecretKey::random().public_key(),
+            SecretKey::random().public_key()
+        ];
+
+        for (i, receiver) in receivers.iter().enumerate() {
+            for channel in ["chat", "status"] {
+                for j in 0..3 {
+                    from.add_message_info(receiver.clone(), message_info(channel, &format!("message {i}-{j}"), 1700000000 + j)).await?;
+                }
+            }
+        }
+
+        assert_eq!(super::migrate(&from, &to).await?, 12);
+
+        assert!(from.list_receivers().await?.is_empty());
+
+        for (i, receiver) in receivers.iter().enumerate() {
+            let (messages, 0) = to.poll_messages(receiver.clone(), ChannelName::new("chat").unwrap().into(), None, None, None).await? else {
+                panic!("Poll failed");
+            };
+
+            // Order and receiving time are kept
+            assert_eq!(messages.iter().map(|info| info.message.content.clone()).collect::<Vec<_>>(), (0..3).map(|j| format!("message {i}-{j}")).collect::<Vec<_>>());
+            assert_eq!(messages.iter().map(|info| info.received_at).collect::<Vec<_>>(), [1700000000, 1700000001, 1700000002]);
+        }
+
+        // Nothing left to migrate
+        assert_eq!(super::migrate(&from, &to).await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrate_failure() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = std::env::temp_dir()
+            .join("inbox-migration-failure-test");
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        tokio::fs::create_dir(&temp).await?;
+
+        let from = RamMessagesInbox::new();
+
+        let to = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_max_message_size(16);
+
+        let receiver = SecretKey::random().public_key();
+
+        from.add_message_info(receiver.clone(), message_info("a", "small", 1700000000)).await?;
+        from.add_message_info(receiver.clone(), message_info("b", "small", 1700000000)).await?;
+        from.add_message_info(receiver.clone(), message_info("b", "too large message", 1700000000)).await?;
+
+        assert!(matches!(super::migrate(&from, &to).await, Err(MigrationError::Destination(_))));
+
+        // Only the not copied message is kept in the source inbox
+        assert_eq!(from.list_channels(receiver.clone()).await?, vec![(ChannelName::new("b").unwrap(), 1)]);
+        assert_eq!(to.list_channels(receiver.clone()).await?, vec![(ChannelName::new("a").unwrap(), 1), (ChannelName::new("b").unwrap(), 1)]);
+
+        // Retry doesn't copy the same messages twice
+        let retry = RamMessagesInbox::new();
+
+        assert_eq!(super::migrate(&from, &retry).await?, 1);
+        assert_eq!(retry.list_channels(receiver).await?, vec![(ChannelName::new("b").unwrap(), 1)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrate_leased_failure() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = std::env::temp_dir()
+            .join("inbox-migration-leased-failure-test");
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        tokio::fs::create_dir(&temp).await?;
+
+        let from = StoredQueueMessagesInbox::new(temp.join("from"), None).await?;
+
+        let to = StoredQueueMessagesInbox::new(temp.join("to"), None).await?
+            .with_max_message_size(16);
+
+        let receiver = SecretKey::random().public_key();
+
+        from.add_message_info(receiver.clone(), message_info("b", "small", 1700000000)).await?;
+        from.add_message_info(receiver.clone(), message_info("b", "too large message", 1700000000)).await?;
+
+        assert!(matches!(super::migrate(&from, &to).await, Err(MigrationError::Destination(_))));
+
+        // Copied message is acknowledged, the other one is still leased
+        assert_eq!(from.list_channels(receiver.clone()).await?, vec![(ChannelName::new("b").unwrap(), 1)]);
+        assert_eq!(to.list_channels(receiver).await?, vec![(ChannelName::new("b").unwrap(), 1)]);
+
+        Ok(())
+    }
+
+    /// Inbox receiving a new message right after
+    /// every read of the migrated channel.
+    struct LateArrival<I> {
+        inbox: I,
+        arrived: std::sync::atomic::AtomicU64
+    }
+
+    impl<I: MessagesInbox + Sync> LateArrival<I> {
+        fn new(inbox: I) -> Self {
+            Self {
+                inbox,
+                arrived: std::sync::atomic::AtomicU64::new(0)
+            }
+        }
+
+        async fn arrive(&self, receiver: PublicKey, channel: ChannelName) -> Result<(), I::Error> {
+            let i = self.arrived.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+            self.inbox.add_message_info(receiver, message_info(channel.as_str(), &format!("late {i}"), 1800000000 + i)).await?;
+
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl<I: MessagesInbox + Send + Sync> MessagesInbox for LateArrival<I> {
+        type Error = I::Error;
+
+        async fn add_message(&self, sender: Sender, receiver: PublicKey, channel: ChannelName, message: Message) -> Result<Option<u64>, Self::Error> {
+            self.inbox.add_message(sender, receiver, channel, message).await
+        }
+
+        async fn add_message_info(&self, receiver: PublicKey, info: MessageInfo) -> Result<Option<u64>, Self::Error> {
+            self.inbox.add_message_info(receiver, info).await
+        }
+
+        async fn poll_messages(
+            &self,
+            receiver: PublicKey,
+            channel: ChannelRule,
+            sender: Option<PublicKey>,
+            range: Option<std::ops::Range<u64>>,
+            limit: Option<u64>
+        ) -> Result<(Vec<MessageInfo>, u64), Self::Error> {
+            let polled = self.inbox.poll_messages(receiver.clone(), channel.clone(), sender, range, limit).await?;
+
+            self.arrive(receiver, ChannelName::new(channel.to_string()).unwrap()).await?;
+
+            Ok(polled)
+        }
+
+        async fn peek_messages(&self, receiver: PublicKey, channel: ChannelName, limit: Option<u64>) -> Result<Option<(Vec<MessageInfo>, u64)>, Self::Error> {
+            let peeked = self.inbox.peek_messages(receiver.clone(), channel.clone(), limit).await?;
+
+            self.arrive(receiver, channel).await?;
+
+            Ok(peeked)
+        }
+
+        async fn lease_messages(
+            &self,
+            receiver: PublicKey,
+            channel: ChannelRule,
+            sender: Option<PublicKey>,
+            range: Option<std::ops::Range<u64>>,
+            limit: Option<u64>,
+            lease: Duration
+        ) -> Result<Option<(Vec<MessageInfo>, u64)>, Self::Error> {
+            let leased = self.inbox.lease_messages(receiver.clone(), channel.clone(), sender, range, limit, lease).await?;
+
+            if leased.is_some() {
+                self.arrive(receiver, ChannelName::new(channel.to_string()).unwrap()).await?;
+            }
+
+            Ok(leased)
+        }
+
+        async fn ack_messages(&self, receiver: PublicKey, ids: Vec<u64>) -> Result<Option<u64>, Self::Error> {
+            self.inbox.ack_messages(receiver, ids).await
+        }
+
+        async fn list_receivers(&self) -> Result<Vec<PublicKey>, Self::Error> {
+            self.inbox.list_receivers().await
+        }
+
+        async fn list_channels(&self, receiver: PublicKey) -> Result<Vec<(ChannelName, u64)>, Self::Error> {
+            self.inbox.list_channels(receiver).await
+        }
+
+        async fn purge(&self, receiver: PublicKey, channel: Option<ChannelName>) -> Result<(), Self::Error> {
+            self.inbox.purge(receiver, channel).await
+        }
+
+        async fn stats(&self) -> Result<InboxStats, Self::Error> {
+            self.inbox.stats().await
+        }
+    }
+
+    async fn migrate_late_arrival<I: MessagesInbox + Send + Sync>(from: I) -> Result<(), Box<dyn std::error::Error>> where I::Error: 'static {
+        let from = LateArrival::new(from);
+        let to = RamMessagesInbox::new();
+
+        let receiver = SecretKey::random().public_key();
+
+        for j in 0..3 {
+            from.add_message_info(receiver.clone(), message_info("chat", &format!("message {j}"), 1700000000 + j)).await?;
+        }
+
+        assert_eq!(super::migrate(&from, &to).await?, 3);
+
+        let (migrated, 0) = to.poll_messages(receiver.clone(), ChannelName::new("chat").unwrap().into(), None, None, None).await? else {
+            panic!("Poll failed");
+        };
+
+        assert_eq!(migrated.iter().map(|info| info.message.content.clone()).collect::<Vec<_>>(), ["message 0", "message 1", "message 2"]);
+
+        // Message received during the migration is kept in the source
+        let (kept, _) = from.inbox.poll_messages(receiver, ChannelName::new("chat").unwrap().into(), None, None, None).await?;
+
+        assert_eq!(kept.iter().map(|info| info.message.content.clone()).collect::<Vec<_>>(), ["late 0"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrate_late_arrival_polled() -> Result<(), Box<dyn std::error::Error>> {
+        migrate_late_arrival(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn migrate_late_arrival_leased() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = std::env::temp_dir()
+            .join("inbox-migration-late-arrival-test");
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        migrate_late_arrival(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+}
@@ -1,35 +1,641 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_core::Stream;
+use futures_util::StreamExt;
+
+use serde_json::{json, Value as Json};
+
+use k256::sha2::{Sha256, Digest};
+
 use crate::crypto::asymmetric::PublicKey;
 
 use crate::rest_api::prelude::*;
 
+#[cfg(feature = "inbox-ram")]
+pub mod ram;
+
 #[cfg(feature = "inbox-stored-queue")]
 pub mod stored_queue;
 
+#[cfg(feature = "inbox-sqlite")]
+pub mod sqlite;
+
+#[cfg(feature = "inbox-stored-queue")]
+pub mod wal;
+
+pub mod migration;
+
+pub use migration::{migrate, MigrationError};
+
+#[cfg(all(test, any(feature = "inbox-ram", feature = "inbox-stored-queue", feature = "inbox-sqlite")))]
+pub(crate) mod test_suite;
+
+/// Get stable hash of the message used for deduplication.
+/// 
+/// Messages with the same sender's public key, channel,
+/// content and sign have the same hash, so the sends retried
+/// after timed out responses can be dropped by the inbox.
+pub fn message_hash(sender: &Sender, channel: &ChannelName, message: &Message) -> u64 {
+    let mut hasher = Sha256::new();
+
+    for part in [&sender.client.public_key.to_bytes()[..], channel.as_str().as_bytes(), message.content.as_bytes(), message.sign.as_bytes()] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+
+    let mut hash = [0; 8];
+
+    hash.copy_from_slice(&hasher.finalize()[..8]);
+
+    u64::from_be_bytes(hash)
+}
+
+#[cfg(any(feature = "inbox-ram", feature = "inbox-stored-queue", feature = "inbox-sqlite"))]
+/// Merge messages of multiple channels by their receiving
+/// time, keeping their order within each channel.
+/// 
+/// Take list of receiving times of every channel's messages
+/// and return channel index of each merged message.
+pub(crate) fn merge_channels(channels: &[Vec<u64>], limit: Option<u64>) -> Vec<usize> {
+    merge_prioritized(channels, &vec![0; channels.len()], limit)
+}
+
+#[cfg(any(feature = "inbox-ram", feature = "inbox-stored-queue", feature = "inbox-sqlite"))]
+/// Merge messages of multiple channels, taking messages
+/// of the channels with higher priority first.
+/// 
+/// Messages of the channels with the same priority are
+/// merged by their receiving time. Refer to `merge_channels`.
+pub(crate) fn merge_prioritized(channels: &[Vec<u64>], priorities: &[i64], limit: Option<u64>) -> Vec<usize> {
+    let limit = limit.unwrap_or(u64::MAX) as usize;
+
+    let mut taken = vec![0; channels.len()];
+    let mut merged = Vec::new();
+
+    while merged.len() < limit {
+        let next = channels.iter()
+            .enumerate()
+            .filter_map(|(i, times)| times.get(taken[i]).map(|time| (std::cmp::Reverse(priorities[i]), *time, i)))
+            .min();
+
+        let Some((_, _, i)) = next else {
+            break;
+        };
+
+        taken[i] += 1;
+
+        merged.push(i);
+    }
+
+    merged
+}
+
+#[cfg(any(feature = "inbox-ram", feature = "inbox-stored-queue", feature = "inbox-sqlite"))]
+/// Check if the message passes the sender
+/// and receiving time filters of the poll.
+/// 
+/// Refer to `MessagesInbox::poll_messages`.
+pub(crate) fn matches_filters(info: &MessageInfo, sender: Option<&PublicKey>, range: Option<&Range<u64>>) -> bool {
+    sender.is_none_or(|sender| &info.sender.client.public_key == sender) &&
+        range.is_none_or(|range| range.contains(&info.received_at))
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Priorities of the inbox channels.
+/// 
+/// When messages are polled from multiple channels, the
+/// channels with higher priority are drained first. Messages
+/// of the channels with the same priority are merged by
+/// their receiving time.
+pub struct ChannelPriorities {
+    /// Priority of the channels which are not listed.
+    pub default: i64,
+
+    /// Priorities of the listed channels.
+    pub channels: HashMap<ChannelName, i64>
+}
+
+impl ChannelPriorities {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    /// Set priority of the channel.
+    pub fn with_priority(mut self, channel: impl Into<ChannelName>, priority: i64) -> Self {
+        self.channels.insert(channel.into(), priority);
+
+        self
+    }
+
+    #[inline]
+    /// Set priority of the channels which are not listed.
+    pub fn with_default(self, priority: i64) -> Self {
+        Self {
+            default: priority,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Get priority of the channel.
+    pub fn get(&self, channel: &ChannelName) -> i64 {
+        self.channels.get(channel)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+impl AsJson for ChannelPriorities {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let channels = self.channels.iter()
+            .map(|(channel, priority)| (channel.to_string(), Json::from(*priority)))
+            .collect::<serde_json::Map<_, _>>();
+
+        Ok(json!({
+            "default": self.default,
+            "channels": channels
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let default = match json.get("default") {
+            Some(default) => default.as_i64().ok_or(AsJsonError::FieldValueInvalid("default"))?,
+            None => 0
+        };
+
+        let mut channels = HashMap::new();
+
+        if let Some(listed) = json.get("channels") {
+            let Some(listed) = listed.as_object() else {
+                return Err(AsJsonError::FieldValueInvalid("channels"));
+            };
+
+            for (channel, priority) in listed {
+                let channel = ChannelName::new(channel)
+                    .map_err(|_| AsJsonError::FieldValueInvalid("channels"))?;
+
+                let priority = priority.as_i64()
+                    .ok_or(AsJsonError::FieldValueInvalid("channels"))?;
+
+                channels.insert(channel, priority);
+            }
+        }
+
+        Ok(Self {
+            default,
+            channels
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Statistics of the messages queued in the inbox.
+pub struct InboxStats {
+    /// Total amount of stored messages.
+    pub messages: u64,
+
+    /// Total size of the stored messages.
+    /// 
+    /// Each inbox counts the size of its own
+    /// storage format, so the values of different
+    /// inboxes are not comparable.
+    pub bytes: u64,
+
+    /// Amount of receivers with stored messages.
+    pub receivers: u64,
+
+    /// Receiving timestamp of the oldest stored message.
+    pub oldest_message: Option<u64>
+}
+
+impl AsJson for InboxStats {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "messages": self.messages,
+            "bytes": self.bytes,
+            "receivers": self.receivers,
+            "oldest_message": self.oldest_message
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let field = |name: &'static str| json.get(name)
+            .and_then(Json::as_u64)
+            .ok_or(AsJsonError::FieldNotFound(name));
+
+        Ok(Self {
+            messages: field("messages")?,
+            bytes: field("bytes")?,
+            receivers: field("receivers")?,
+
+            oldest_message: match json.get("oldest_message") {
+                Some(Json::Null) | None => None,
+
+                Some(oldest) => Some(oldest.as_u64().ok_or(AsJsonError::FieldValueInvalid("oldest_message"))?)
+            }
+        })
+    }
+}
+
+/// Error returned by the inbox observer.
+pub type ObserverError = Box<dyn std::error::Error + Send + Sync>;
+
+#[async_trait::async_trait]
+/// InboxObserver is a struct that is notified
+/// about messages added to the inbox.
+/// 
+/// Observer is called after the message is stored,
+/// so it can be used to deliver push notifications.
+/// Errors of the observer are logged and don't fail
+/// adding the message.
+pub trait InboxObserver: Send + Sync {
+    /// Handle message stored for the receiver.
+    async fn on_message(&self, receiver: &PublicKey, channel: &ChannelName, message: &MessageInfo) -> Result<(), ObserverError>;
+}
+
+#[cfg(any(feature = "inbox-ram", feature = "inbox-stored-queue", feature = "inbox-sqlite"))]
+#[derive(Clone)]
+/// Shared observer of the messages inbox.
+pub(crate) struct SharedObserver(pub Arc<dyn InboxObserver>);
+
+#[cfg(any(feature = "inbox-ram", feature = "inbox-stored-queue", feature = "inbox-sqlite"))]
+impl SharedObserver {
+    /// Notify the observer about stored message
+    /// ignoring its errors.
+    pub async fn notify(&self, receiver: &PublicKey, channel: &ChannelName, message: &MessageInfo) {
+        if let Err(_err) = self.0.on_message(receiver, channel, message).await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                receiver = receiver.to_base64(),
+                channel = channel.as_str(),
+                "Inbox observer failed: {_err}"
+            );
+        }
+    }
+}
+
+#[cfg(any(feature = "inbox-ram", feature = "inbox-stored-queue", feature = "inbox-sqlite"))]
+impl std::fmt::Debug for SharedObserver {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedObserver")
+    }
+}
+
 #[async_trait::async_trait]
 /// MessagesQueue is a struct that stores messages
 /// sent by external clients and meant to be read
 /// by local clients.
+/// 
+/// # Ordering
+/// 
+/// Messages of the same (receiver, channel) pair are
+/// delivered in FIFO order: every poll, peek, lease and
+/// stream returns them in the order they were added,
+/// and limited reads return the oldest messages first.
+/// Messages of different channels have no relative order
+/// except the one given by their receiving time.
+/// 
+/// Implementations are expected to pass the generic
+/// `test_suite` conformance tests.
 pub trait MessagesInbox {
     type Error: std::error::Error + Send + Sync;
 
     /// Add new message to the inbox.
+    /// 
+    /// Return server-assigned id of the stored message,
+    /// which is set in the `id` field of the polled
+    /// `MessageInfo`.
+    /// 
+    /// Inbox may silently drop the message if the same
+    /// one was added recently, in which case `None` is
+    /// returned. Refer to `message_hash`.
     async fn add_message(
         &self,
         sender: Sender,
         receiver: PublicKey,
-        channel: String,
+        channel: ChannelName,
         message: Message
-    ) -> Result<(), Self::Error>;
+    ) -> Result<Option<u64>, Self::Error>;
+
+    /// Add new message to the inbox, validating
+    /// the channel name first.
+    /// 
+    /// Compatibility shim for the callers which pass
+    /// string channel names. Refer to `add_message`.
+    async fn add_message_to<T>(
+        &self,
+        sender: Sender,
+        receiver: PublicKey,
+        channel: T,
+        message: Message
+    ) -> Result<Option<u64>, Self::Error>
+    where
+        T: TryInto<ChannelName, Error = ChannelNameError> + Send,
+        Self::Error: From<ChannelNameError>
+    {
+        self.add_message(sender, receiver, channel.try_into()?, message).await
+    }
+
+    /// Add multiple messages to the inbox.
+    /// 
+    /// Messages sent to the same channel are added
+    /// in the given order. If an error occurred, some
+    /// of the messages can be already added.
+    /// 
+    /// Return ids of the messages in the given order.
+    /// Refer to `add_message`.
+    async fn add_messages(&self, entries: Vec<(Sender, PublicKey, ChannelName, Message)>) -> Result<Vec<Option<u64>>, Self::Error> {
+        let mut ids = Vec::with_capacity(entries.len());
+
+        for (sender, receiver, channel, message) in entries {
+            ids.push(self.add_message(sender, receiver, channel, message).await?);
+        }
+
+        Ok(ids)
+    }
+
+    /// Add message with already known info to the inbox.
+    /// 
+    /// Unlike `add_message`, the `received_at` timestamp
+    /// of the message info is kept, so this method can
+    /// be used to move messages between inboxes. The `id`
+    /// of the info is ignored and a new one is assigned.
+    /// 
+    /// Default implementation calls `add_message`, which
+    /// resets the receiving time.
+    async fn add_message_info(&self, receiver: PublicKey, info: MessageInfo) -> Result<Option<u64>, Self::Error> {
+        self.add_message(info.sender, receiver, info.channel, info.message).await
+    }
 
     /// Read client's inbox, applying given filters.
     /// 
-    /// Return list of read messages and number of remained.
+    /// Messages are read from all the channels matched
+    /// by the `channel` rule, ordered by their receiving time.
+    /// 
+    /// If `sender` is set, only messages sent by the client
+    /// with this public key are read. If `range` is set, only
+    /// messages received within this range of unix timestamps
+    /// are read. The rest are kept in the inbox and counted
+    /// as remained.
+    /// 
+    /// Return list of read messages and number of remained
+    /// in all the matched channels.
     /// 
     /// This method will remove read messages from the inbox.
     async fn poll_messages(
         &self,
         receiver: PublicKey,
-        channel: String,
+        channel: ChannelRule,
+        sender: Option<PublicKey>,
+        range: Option<Range<u64>>,
         limit: Option<u64>
     ) -> Result<(Vec<MessageInfo>, u64), Self::Error>;
+
+    /// Read client's inbox channel, validating
+    /// the channel name first.
+    /// 
+    /// Compatibility shim for the callers which pass
+    /// string channel names. Refer to `poll_messages`.
+    async fn poll_channel<T>(
+        &self,
+        receiver: PublicKey,
+        channel: T,
+        limit: Option<u64>
+    ) -> Result<(Vec<MessageInfo>, u64), Self::Error>
+    where
+        T: TryInto<ChannelName, Error = ChannelNameError> + Send,
+        Self::Error: From<ChannelNameError>
+    {
+        self.poll_messages(receiver, ChannelRule::Exact(channel.try_into()?), None, None, limit).await
+    }
+
+    /// Read client's inbox message by message.
+    /// 
+    /// Unlike `poll_messages`, the stream doesn't need to
+    /// hold all the polled messages at once, so it can be
+    /// used to drain large inboxes. The stream ends when
+    /// `limit` messages were read or the channel is empty.
+    /// 
+    /// Default implementation polls the messages in a single
+    /// batch using `poll_messages`.
+    /// 
+    /// This method will remove read messages from the inbox.
+    fn poll_messages_stream(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelName,
+        limit: Option<u64>
+    ) -> Pin<Box<dyn Stream<Item = Result<MessageInfo, Self::Error>> + Send + '_>> where Self: Sync {
+        let polled = self.poll_messages(receiver, channel.into(), None, None, limit);
+
+        Box::pin(futures_util::stream::once(polled).flat_map(|polled| {
+            let messages = match polled {
+                Ok((messages, _)) => messages.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(err)]
+            };
+
+            futures_util::stream::iter(messages)
+        }))
+    }
+
+    /// Read client's inbox without removing
+    /// the messages, applying given filters.
+    /// 
+    /// Return list of read messages and number of
+    /// messages left after them, or `None` if the
+    /// inbox doesn't support peeking.
+    async fn peek_messages(
+        &self,
+        _receiver: PublicKey,
+        _channel: ChannelName,
+        _limit: Option<u64>
+    ) -> Result<Option<(Vec<MessageInfo>, u64)>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Read client's inbox, leasing the read messages
+    /// instead of removing them.
+    /// 
+    /// Leased messages are skipped by other leasing polls
+    /// until the lease expires, so unacknowledged messages
+    /// become visible again. Read messages have their `id`
+    /// set, which must be passed to `ack_messages` to
+    /// remove them from the inbox.
+    /// 
+    /// Return list of leased messages and number of
+    /// not leased messages remained in all the matched
+    /// channels, or `None` if the inbox doesn't support
+    /// leases. In this case the messages should be polled
+    /// by `poll_messages`.
+    async fn lease_messages(
+        &self,
+        _receiver: PublicKey,
+        _channel: ChannelRule,
+        _sender: Option<PublicKey>,
+        _range: Option<Range<u64>>,
+        _limit: Option<u64>,
+        _lease: Duration
+    ) -> Result<Option<(Vec<MessageInfo>, u64)>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Remove leased messages with given ids from
+    /// the client's inbox.
+    /// 
+    /// Messages with expired leases can still be
+    /// acknowledged if they weren't removed from the
+    /// inbox. Unknown ids are ignored.
+    /// 
+    /// Return number of removed messages, or `None`
+    /// if the inbox doesn't support leases.
+    async fn ack_messages(&self, _receiver: PublicKey, _ids: Vec<u64>) -> Result<Option<u64>, Self::Error> {
+        Ok(None)
+    }
+
+    /// List clients which have pending messages.
+    async fn list_receivers(&self) -> Result<Vec<PublicKey>, Self::Error>;
+
+    /// List channels of the client's inbox
+    /// which have pending messages.
+    /// 
+    /// Return channel names with amounts of
+    /// their messages, sorted by name.
+    async fn list_channels(&self, receiver: PublicKey) -> Result<Vec<(ChannelName, u64)>, Self::Error>;
+
+    /// Remove messages stored for the client.
+    /// 
+    /// If `channel` is set, only messages of this channel
+    /// are removed. Otherwise all the client's channels
+    /// are removed.
+    async fn purge(&self, receiver: PublicKey, channel: Option<ChannelName>) -> Result<(), Self::Error>;
+
+    /// Get statistics of all the stored messages.
+    async fn stats(&self) -> Result<InboxStats, Self::Error>;
+
+    /// Remove expired messages.
+    /// 
+    /// This method is meant to be called periodically.
+    /// Return number of removed messages. Inboxes
+    /// which don't expire messages do nothing.
+    async fn cleanup(&self) -> Result<u64, Self::Error> {
+        Ok(0)
+    }
+
+    /// Read client's inbox in sealed form.
+    /// 
+    /// Return list of messages sealed to the receiver
+    /// and number of remained, or `None` if the inbox
+    /// doesn't store sealed messages. In this case
+    /// the messages are polled by `poll_messages` and
+    /// sealed on the fly.
+    /// 
+    /// This method will remove read messages from the inbox.
+    async fn poll_sealed_messages(
+        &self,
+        _receiver: PublicKey,
+        _channel: ChannelName,
+        _limit: Option<u64>
+    ) -> Result<Option<(Vec<SealedMessageInfo>, u64)>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Get status of the response sent to the client
+    /// when the inbox returned given error.
+    fn error_status(&self, _error: &Self::Error) -> ResponseStatus {
+        ResponseStatus::ServerError
+    }
+
+    /// Notify the given observer about
+    /// messages added to the inbox.
+    /// 
+    /// Inboxes which don't support observers ignore it.
+    fn set_observer(&mut self, _observer: Arc<dyn InboxObserver>) {}
+}
+
+#[cfg(all(test, any(feature = "inbox-ram", feature = "inbox-stored-queue", feature = "inbox-sqlite")))]
+mod tests {
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    #[test]
+    fn stats_json() -> Result<(), AsJsonError> {
+        let stats = InboxStats {
+            messages: 3,
+            bytes: 120,
+            receivers: 2,
+            oldest_message: Some(1700000000)
+        };
+
+        assert_eq!(InboxStats::from_json(&stats.to_json()?)?, stats);
+        assert_eq!(InboxStats::from_json(&InboxStats::default().to_json()?)?, InboxStats::default());
+
+        assert!(InboxStats::from_json(&json!({ "messages": 1 })).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_channels() {
+        let channels = [vec![1, 4], vec![2, 3], vec![], vec![1]];
+
+        assert_eq!(super::merge_channels(&channels, None), [0, 3, 1, 1, 0]);
+        assert_eq!(super::merge_channels(&channels, Some(3)), [0, 3, 1]);
+        assert!(super::merge_channels(&[], None).is_empty());
+    }
+
+    #[test]
+    fn merge_prioritized() {
+        let channels = [vec![1, 4], vec![2, 3], vec![], vec![1]];
+
+        // Channels with the same priority are merged by time
+        assert_eq!(super::merge_prioritized(&channels, &[0, 5, 5, 0], None), [1, 1, 0, 3, 0]);
+        assert_eq!(super::merge_prioritized(&channels, &[0, 5, 5, 0], Some(3)), [1, 1, 0]);
+        assert_eq!(super::merge_prioritized(&channels, &[-1, 0, 0, 1], None), [3, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn priorities_json() -> Result<(), AsJsonError> {
+        let priorities = ChannelPriorities::new()
+            .with_priority(ChannelName::new("presence").unwrap(), 10)
+            .with_priority(ChannelName::new("bulk").unwrap(), -5)
+            .with_default(1);
+
+        assert_eq!(priorities.get(&ChannelName::new("presence").unwrap()), 10);
+        assert_eq!(priorities.get(&ChannelName::new("chat").unwrap()), 1);
+
+        assert_eq!(ChannelPriorities::from_json(&priorities.to_json()?)?, priorities);
+        assert_eq!(ChannelPriorities::from_json(&json!({}))?, ChannelPriorities::default());
+
+        assert_eq!(ChannelPriorities::from_json(&json!({ "channels": { "acks": 3 } }))?, ChannelPriorities::new().with_priority(ChannelName::new("acks").unwrap(), 3));
+
+        assert!(ChannelPriorities::from_json(&json!({ "channels": { "acks": "high" } })).is_err());
+        assert!(ChannelPriorities::from_json(&json!({ "channels": { "": 1 } })).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn message_hash() {
+        let sender = Sender::new(get_client(), get_server());
+        let message = Message::new("message", "sign", MessageEncoding::default());
+
+        let hash = super::message_hash(&sender, &ChannelName::new("channel").unwrap(), &message);
+
+        assert_eq!(super::message_hash(&sender, &ChannelName::new("channel").unwrap(), &message.clone()), hash);
+
+        assert_ne!(super::message_hash(&sender, &ChannelName::new("channel 2").unwrap(), &message), hash);
+        assert_ne!(super::message_hash(&sender, &ChannelName::new("channel").unwrap(), &Message::new("message", "sign 2", MessageEncoding::default())), hash);
+        assert_ne!(super::message_hash(&sender, &ChannelName::new("channe").unwrap(), &Message::new("lmessage", "sign", MessageEncoding::default())), hash);
+    }
 }
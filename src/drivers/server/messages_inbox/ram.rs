@@ -0,0 +1,546 @@
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+use crate::time::timestamp;
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+use super::{MessagesInbox, InboxStats, InboxObserver, SharedObserver, merge_channels, matches_filters};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    InvalidChannel(#[from] ChannelNameError)
+}
+
+type Queues = HashMap<PublicKey, HashMap<ChannelName, VecDeque<MessageInfo>>>;
+
+#[derive(Default, Debug, Clone)]
+/// In-memory messages inbox.
+/// 
+/// Messages are lost when the inbox is dropped,
+/// so it's meant for tests and ephemeral relays.
+pub struct RamMessagesInbox {
+    queues: Arc<RwLock<Queues>>,
+
+    /// Observer notified about added messages.
+    observer: Option<SharedObserver>
+}
+
+impl RamMessagesInbox {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    /// Notify the given observer about added messages.
+    pub fn with_observer(mut self, observer: impl InboxObserver + 'static) -> Self {
+        self.set_observer(Arc::new(observer));
+
+        self
+    }
+
+    /// Get amount of stored messages
+    /// for the given receiver's channel.
+    pub async fn len(&self, receiver: &PublicKey, channel: &ChannelName) -> u64 {
+        self.queues.read().await
+            .get(receiver)
+            .and_then(|channels| channels.get(channel))
+            .map(|queue| queue.len() as u64)
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait::async_trait]
+impl MessagesInbox for RamMessagesInbox {
+    type Error = Error;
+
+    async fn add_message(
+        &self,
+        sender: Sender,
+        receiver: PublicKey,
+        channel: ChannelName,
+        message: Message
+    ) -> Result<Option<u64>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            sender = ?sender,
+            receiver = receiver.to_base64(),
+            channel = channel.as_str(),
+            "Adding new message"
+        );
+
+        self.add_message_info(receiver, MessageInfo::new(sender, channel, message, timestamp())).await
+    }
+
+    async fn add_message_info(&self, receiver: PublicKey, info: MessageInfo) -> Result<Option<u64>, Self::Error> {
+        info.channel.validate()?;
+
+        let message_id = safe_random_u64();
+
+        let message_info = info.with_id(message_id);
+        let channel = message_info.channel.clone();
+
+        self.queues.write().await
+            .entry(receiver.clone())
+            .or_default()
+            .entry(channel)
+            .or_default()
+            .push_back(message_info.clone());
+
+        if let Some(observer) = &self.observer {
+            observer.notify(&receiver, &message_info.channel, &message_info).await;
+        }
+
+        Ok(Some(message_id))
+    }
+
+    async fn poll_messages(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelRule,
+        sender: Option<PublicKey>,
+        range: Option<Range<u64>>,
+        limit: Option<u64>
+    ) -> Result<(Vec<MessageInfo>, u64), Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = %channel,
+            sender = sender.as_ref().map(PublicKey::to_base64),
+            ?range,
+            limit,
+            "Polling messages"
+        );
+
+        channel.validate()?;
+
+        let mut queues = self.queues.write().await;
+
+        let Some(channels) = queues.get_mut(&receiver) else {
+            return Ok((vec![], 0));
+        };
+
+        let mut names = channels.keys()
+            .filter(|name| channel.matches(name))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        names.sort();
+
+        // Indexes of the messages matching the filters
+        let candidates = names.iter()
+            .map(|name| channels[name].iter()
+                .enumerate()
+                .filter(|(_, info)| matches_filters(info, sender.as_ref(), range.as_ref()))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let times = names.iter()
+            .zip(&candidates)
+            .map(|(name, candidates)| candidates.iter()
+                .map(|i| channels[name][*i].received_at)
+                .collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let merged = merge_channels(&times, limit);
+
+        let mut polled = Vec::with_capacity(names.len());
+        let mut remaining = 0;
+
+        for (i, name) in names.iter().enumerate() {
+            let count = merged.iter()
+                .filter(|merged| **merged == i)
+                .count();
+
+            let Some(queue) = channels.get_mut(name) else {
+                continue;
+            };
+
+            let mut taken = VecDeque::with_capacity(count);
+            let mut kept = VecDeque::with_capacity(queue.len() - count);
+
+            for (j, message) in queue.drain(..).enumerate() {
+                if taken.len() < count && candidates[i][taken.len()] == j {
+                    taken.push_back(message);
+                } else {
+                    kept.push_back(message);
+                }
+            }
+
+            remaining += kept.len() as u64;
+
+            // Forget empty queues
+            if kept.is_empty() {
+                channels.remove(name);
+            } else {
+                *queue = kept;
+            }
+
+            polled.push(taken);
+        }
+
+        if channels.is_empty() {
+            queues.remove(&receiver);
+        }
+
+        let messages = merged.into_iter()
+            .filter_map(|i| polled[i].pop_front())
+            .collect();
+
+        Ok((messages, remaining))
+    }
+
+    async fn peek_messages(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelName,
+        limit: Option<u64>
+    ) -> Result<Option<(Vec<MessageInfo>, u64)>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = channel.as_str(),
+            limit,
+            "Peeking messages"
+        );
+
+        channel.validate()?;
+
+        let queues = self.queues.read().await;
+
+        let Some(queue) = queues.get(&receiver).and_then(|channels| channels.get(&channel)) else {
+            return Ok(Some((vec![], 0)));
+        };
+
+        let limit = limit.map(|limit| limit as usize)
+            .unwrap_or(usize::MAX)
+            .min(queue.len());
+
+        let messages = queue.iter()
+            .take(limit)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Ok(Some((messages, (queue.len() - limit) as u64)))
+    }
+
+    async fn list_receivers(&self) -> Result<Vec<PublicKey>, Self::Error> {
+        // Empty queues are forgotten
+        Ok(self.queues.read().await.keys().cloned().collect())
+    }
+
+    async fn list_channels(&self, receiver: PublicKey) -> Result<Vec<(ChannelName, u64)>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            "Listing channels"
+        );
+
+        let queues = self.queues.read().await;
+
+        let mut channels = queues.get(&receiver)
+            .map(|channels| channels.iter()
+                .map(|(channel, queue)| (channel.clone(), queue.len() as u64))
+                .collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        channels.sort();
+
+        Ok(channels)
+    }
+
+    async fn purge(&self, receiver: PublicKey, channel: Option<ChannelName>) -> Result<(), Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = channel.as_ref().map(ChannelName::as_str),
+            "Purging messages"
+        );
+
+        let mut queues = self.queues.write().await;
+
+        let Some(channel) = channel else {
+            queues.remove(&receiver);
+
+            return Ok(());
+        };
+
+        if let Some(channels) = queues.get_mut(&receiver) {
+            channels.remove(&channel);
+
+            // Forget empty queues
+            if channels.is_empty() {
+                queues.remove(&receiver);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn stats(&self) -> Result<InboxStats, Self::Error> {
+        let queues = self.queues.read().await;
+
+        let mut stats = InboxStats {
+            receivers: queues.len() as u64,
+            ..InboxStats::default()
+        };
+
+        for info in queues.values().flat_map(HashMap::values).flatten() {
+            stats.messages += 1;
+            stats.bytes += (info.message.content.len() + info.message.sign.len()) as u64;
+
+            stats.oldest_message = Some(stats.oldest_message.map_or(info.received_at, |oldest| oldest.min(info.received_at)));
+        }
+
+        Ok(stats)
+    }
+
+    fn set_observer(&mut self, observer: Arc<dyn InboxObserver>) {
+        self.observer = Some(SharedObserver(observer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::drivers::server::messages_inbox::test_suite::{send_poll_suite, peek_suite, sender_filter_suite, wildcard_suite, list_channels_suite, add_messages_suite, stats_suite, stream_suite, purge_suite, observer_suite, range_filter_suite, fifo_suite, limits_suite, empty_channels_suite, interleaved_channels_suite};
+
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn send_poll() -> Result<(), Error> {
+        let inbox = RamMessagesInbox::new();
+
+        send_poll_suite(inbox.clone()).await?;
+
+        // Empty queues are forgotten
+        assert!(inbox.queues.read().await.is_empty());
+
+        // Invalid channel names are rejected
+        assert!(inbox.poll_messages(SecretKey::random().public_key(), ChannelName::new_unchecked("").into(), None, None, None).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn string_channels() -> Result<(), Error> {
+        let inbox = RamMessagesInbox::new();
+
+        let sender_secret = SecretKey::random();
+        let receiver_secret = SecretKey::random();
+
+        let sender = Sender::new(get_client(), get_server());
+
+        let message = Message::create(
+            &sender_secret,
+            &receiver_secret.public_key(),
+            b"Hello, World!",
+            MessageEncoding::default(),
+            CompressionLevel::default()
+        ).unwrap();
+
+        assert!(inbox.add_message_to(sender.clone(), receiver_secret.public_key(), "default channel", message.clone()).await?.is_some());
+
+        assert!(matches!(
+            inbox.add_message_to(sender, receiver_secret.public_key(), "", message).await,
+            Err(Error::InvalidChannel(ChannelNameError::Empty))
+        ));
+
+        let (messages, remaining) = inbox.poll_channel(receiver_secret.public_key(), "default channel", None).await?;
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(remaining, 0);
+
+        assert!(matches!(
+            inbox.poll_channel(receiver_secret.public_key(), "amogus\0", None).await,
+            Err(Error::InvalidChannel(ChannelNameError::InvalidCharacter('\0')))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn peek() -> Result<(), Error> {
+        peek_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn sender_filter() -> Result<(), Error> {
+        sender_filter_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn wildcard() -> Result<(), Error> {
+        wildcard_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn list_channels() -> Result<(), Error> {
+        list_channels_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn add_messages() -> Result<(), Error> {
+        add_messages_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn stats() -> Result<(), Error> {
+        stats_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn stream() -> Result<(), Error> {
+        stream_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn purge() -> Result<(), Error> {
+        let inbox = RamMessagesInbox::new();
+
+        purge_suite(inbox.clone()).await?;
+
+        // Purged receivers are forgotten
+        assert_eq!(inbox.queues.read().await.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn range_filter() -> Result<(), Error> {
+        range_filter_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn observer() -> Result<(), Error> {
+        observer_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test]
+    async fn conformance() -> Result<(), Error> {
+        fifo_suite(RamMessagesInbox::new()).await?;
+        limits_suite(RamMessagesInbox::new()).await?;
+        empty_channels_suite(RamMessagesInbox::new()).await?;
+        interleaved_channels_suite(RamMessagesInbox::new()).await
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_send_poll() -> Result<(), Box<dyn std::error::Error>> {
+        const SENDERS: usize = 8;
+        const MESSAGES: usize = 50;
+
+        let inbox = RamMessagesInbox::new();
+
+        let receivers = (0..2)
+            .map(|_| SecretKey::random().public_key())
+            .collect::<Vec<_>>();
+
+        let mut tasks = Vec::new();
+
+        for i in 0..SENDERS {
+            let inbox = inbox.clone();
+            let receiver = receivers[i % receivers.len()].clone();
+
+            tasks.push(tokio::spawn(async move {
+                let sender = Sender::new(get_client(), get_server());
+
+                for j in 0..MESSAGES {
+                    let message = Message::new(format!("{i}-{j}"), "sign", MessageEncoding::default());
+
+                    inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), message).await?;
+
+                    tokio::task::yield_now().await;
+                }
+
+                Ok::<_, Error>(())
+            }));
+        }
+
+        let sent = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let mut pollers = Vec::new();
+
+        for receiver in receivers.clone() {
+            for _ in 0..2 {
+                let inbox = inbox.clone();
+                let receiver = receiver.clone();
+                let sent = sent.clone();
+
+                pollers.push(tokio::spawn(async move {
+                    let mut polled = Vec::new();
+
+                    loop {
+                        // Check the flag before polling so that
+                        // no messages are left after the last poll
+                        let finished = sent.load(std::sync::atomic::Ordering::Acquire);
+
+                        let (messages, _) = inbox.poll_messages(receiver.clone(), ChannelName::new("channel").unwrap().into(), None, None, Some(7)).await?;
+
+                        if messages.is_empty() {
+                            if finished {
+                                break;
+                            }
+
+                            tokio::task::yield_now().await;
+                        }
+
+                        polled.extend(messages.into_iter().map(|info| info.message.content));
+                    }
+
+                    Ok::<_, Error>((receiver, polled))
+                }));
+            }
+        }
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(30), async {
+            for task in tasks {
+                task.await??;
+            }
+
+            sent.store(true, std::sync::atomic::Ordering::Release);
+
+            let mut polled = HashMap::<PublicKey, Vec<String>>::new();
+
+            for poller in pollers {
+                let (receiver, messages) = poller.await??;
+
+                polled.entry(receiver).or_default().extend(messages);
+            }
+
+            Ok::<_, Box<dyn std::error::Error>>(polled)
+        }).await;
+
+        let Ok(polled) = result else {
+            panic!("Concurrent send and poll deadlocked");
+        };
+
+        let polled = polled?;
+
+        for receiver in &receivers {
+            assert_eq!(polled[receiver].len(), SENDERS * MESSAGES / receivers.len());
+        }
+
+        let mut all = polled.into_values()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        assert_eq!(all.len(), SENDERS * MESSAGES);
+
+        // Every message is polled exactly once
+        all.sort();
+        all.dedup();
+
+        assert_eq!(all.len(), SENDERS * MESSAGES);
+
+        Ok(())
+    }
+}
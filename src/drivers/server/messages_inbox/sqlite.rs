@@ -0,0 +1,532 @@
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{params, Connection};
+
+use crate::time::timestamp;
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+use super::{MessagesInbox, InboxStats, InboxObserver, SharedObserver};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error(transparent)]
+    Json(#[from] AsJsonError),
+
+    #[error(transparent)]
+    Serialize(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    InvalidChannel(#[from] ChannelNameError),
+
+    #[error("Database task panicked: {0}")]
+    Task(#[from] tokio::task::JoinError)
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS messages (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        sender      TEXT NOT NULL,
+        receiver    TEXT NOT NULL,
+        channel     TEXT NOT NULL,
+        message     TEXT NOT NULL,
+        received_at INTEGER NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS messages_inbox
+        ON messages (receiver, channel, received_at);
+";
+
+#[derive(Debug, Clone)]
+/// Messages inbox stored in a single SQLite database.
+pub struct SqliteMessagesInbox {
+    connection: Arc<Mutex<Connection>>,
+
+    /// Observer notified about added messages.
+    observer: Option<SharedObserver>
+}
+
+impl SqliteMessagesInbox {
+    /// Open database file, creating it if needed.
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+
+        let connection = tokio::task::spawn_blocking(move || {
+            let connection = Connection::open(path)?;
+
+            connection.pragma_update(None, "journal_mode", "WAL")?;
+            connection.execute_batch(SCHEMA)?;
+
+            Ok::<_, Error>(connection)
+        }).await??;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            observer: None
+        })
+    }
+
+    /// Open temporary in-memory database.
+    pub fn in_memory() -> Result<Self, Error> {
+        let connection = Connection::open_in_memory()?;
+
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            observer: None
+        })
+    }
+
+    #[inline]
+    /// Notify the given observer about added messages.
+    pub fn with_observer(mut self, observer: impl InboxObserver + 'static) -> Self {
+        self.set_observer(Arc::new(observer));
+
+        self
+    }
+
+    /// Run blocking database operation
+    /// on the tokio blocking threads pool.
+    async fn with_connection<T: Send + 'static>(
+        &self,
+        callback: impl FnOnce(&mut Connection) -> Result<T, Error> + Send + 'static
+    ) -> Result<T, Error> {
+        let connection = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock()
+                .expect("Failed to lock sqlite connection");
+
+            callback(&mut connection)
+        }).await?
+    }
+}
+
+/// Get SQL condition selecting the channels
+/// matched by the rule, and its parameter.
+fn channel_condition(channel: &ChannelRule) -> (&'static str, &str) {
+    match channel {
+        ChannelRule::Exact(name) => ("channel = ?2", name.as_str()),
+        ChannelRule::Prefix(prefix) => ("substr(channel, 1, length(?2)) = ?2", prefix.as_str())
+    }
+}
+
+/// Read first `limit` messages of the receiver's channels
+/// in order they were received.
+/// 
+/// Senders are stored as JSON, so messages are filtered
+/// by the sender's public key after they're read.
+fn select_messages(connection: &Connection, receiver: &str, channel: &ChannelRule, sender: Option<&PublicKey>, range: Option<&Range<u64>>, limit: i64) -> Result<(Vec<i64>, Vec<MessageInfo>), Error> {
+    let (condition, channel) = channel_condition(channel);
+
+    let mut select = connection.prepare_cached(&format!("
+        SELECT id, sender, message, received_at, channel FROM messages
+        WHERE receiver = ?1 AND {condition} AND received_at >= ?4 AND received_at < ?5
+        ORDER BY received_at, id
+        LIMIT ?3
+    "))?;
+
+    let query_limit = if sender.is_some() { -1 } else { limit };
+
+    let (after, before) = range.map(|range| (range.start, range.end))
+        .unwrap_or((0, u64::MAX));
+
+    let after = after.min(i64::MAX as u64) as i64;
+    let before = before.min(i64::MAX as u64) as i64;
+
+    let mut rows = select.query(params![receiver, channel, query_limit, after, before])?;
+
+    let mut ids = Vec::new();
+    let mut messages = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        if limit >= 0 && messages.len() as i64 >= limit {
+            break;
+        }
+
+        let message_sender = Sender::from_json(&serde_json::from_str(&row.get::<_, String>(1)?)?)?;
+
+        if sender.is_some_and(|sender| &message_sender.client.public_key != sender) {
+            continue;
+        }
+
+        let message = serde_json::from_str(&row.get::<_, String>(2)?)?;
+
+        let id = row.get::<_, i64>(0)?;
+
+        ids.push(id);
+
+        messages.push(MessageInfo::new(
+            message_sender,
+            // Names are validated before the messages are stored
+            ChannelName::new_unchecked(row.get::<_, String>(4)?),
+            Message::from_json(&message)?,
+            row.get::<_, i64>(3)? as u64
+        ).with_id(id as u64));
+    }
+
+    Ok((ids, messages))
+}
+
+fn count_messages(connection: &Connection, receiver: &str, channel: &ChannelRule) -> Result<u64, Error> {
+    let (condition, channel) = channel_condition(channel);
+
+    let count = connection.query_row(
+        &format!("SELECT COUNT(*) FROM messages WHERE receiver = ?1 AND {condition}"),
+        params![receiver, channel],
+        |row| row.get::<_, i64>(0)
+    )?;
+
+    Ok(count as u64)
+}
+
+#[async_trait::async_trait]
+impl MessagesInbox for SqliteMessagesInbox {
+    type Error = Error;
+
+    async fn add_message(
+        &self,
+        sender: Sender,
+        receiver: PublicKey,
+        channel: ChannelName,
+        message: Message
+    ) -> Result<Option<u64>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            sender = ?sender,
+            receiver = receiver.to_base64(),
+            channel = channel.as_str(),
+            "Adding new message"
+        );
+
+        self.add_message_info(receiver, MessageInfo::new(sender, channel, message, timestamp())).await
+    }
+
+    async fn add_message_info(&self, receiver: PublicKey, info: MessageInfo) -> Result<Option<u64>, Self::Error> {
+        info.channel.validate()?;
+
+        let mut message_info = info;
+
+        let sender = serde_json::to_string(&message_info.sender.to_json()?)?;
+        let message = serde_json::to_string(&message_info.message.to_json()?)?;
+
+        let channel = message_info.channel.clone();
+        let received_at = message_info.received_at;
+        let stored_receiver = receiver.to_base64();
+
+        let message_id = self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT INTO messages (sender, receiver, channel, message, received_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![sender, stored_receiver, channel.as_str(), message, received_at as i64]
+            )?;
+
+            Ok(connection.last_insert_rowid() as u64)
+        }).await?;
+
+        message_info.id = Some(message_id);
+
+        if let Some(observer) = &self.observer {
+            observer.notify(&receiver, &message_info.channel, &message_info).await;
+        }
+
+        Ok(Some(message_id))
+    }
+
+    async fn poll_messages(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelRule,
+        sender: Option<PublicKey>,
+        range: Option<Range<u64>>,
+        limit: Option<u64>
+    ) -> Result<(Vec<MessageInfo>, u64), Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = %channel,
+            sender = sender.as_ref().map(PublicKey::to_base64),
+            ?range,
+            limit,
+            "Polling messages"
+        );
+
+        channel.validate()?;
+
+        let receiver = receiver.to_base64();
+
+        // SQLite treats negative limit as no limit
+        let limit = limit.map(|limit| limit.min(i64::MAX as u64) as i64)
+            .unwrap_or(-1);
+
+        self.with_connection(move |connection| {
+            // Messages are deleted in the same transaction,
+            // so they're either read and removed or kept
+            let transaction = connection.transaction()?;
+
+            let (ids, messages) = select_messages(&transaction, &receiver, &channel, sender.as_ref(), range.as_ref(), limit)?;
+
+            {
+                let mut delete = transaction.prepare_cached("DELETE FROM messages WHERE id = ?1")?;
+
+                for id in &ids {
+                    delete.execute([id])?;
+                }
+            }
+
+            let remaining = count_messages(&transaction, &receiver, &channel)?;
+
+            transaction.commit()?;
+
+            Ok((messages, remaining))
+        }).await
+    }
+
+    async fn peek_messages(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelName,
+        limit: Option<u64>
+    ) -> Result<Option<(Vec<MessageInfo>, u64)>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = channel.as_str(),
+            limit,
+            "Peeking messages"
+        );
+
+        channel.validate()?;
+
+        let receiver = receiver.to_base64();
+        let channel = ChannelRule::from(channel);
+
+        let limit = limit.map(|limit| limit.min(i64::MAX as u64) as i64)
+            .unwrap_or(-1);
+
+        self.with_connection(move |connection| {
+            // Read both in one transaction to get consistent counters
+            let transaction = connection.transaction()?;
+
+            let (_, messages) = select_messages(&transaction, &receiver, &channel, None, None, limit)?;
+
+            let remaining = count_messages(&transaction, &receiver, &channel)? - messages.len() as u64;
+
+            transaction.commit()?;
+
+            Ok(Some((messages, remaining)))
+        }).await
+    }
+
+    async fn list_receivers(&self) -> Result<Vec<PublicKey>, Self::Error> {
+        self.with_connection(|connection| {
+            let mut select = connection.prepare_cached("SELECT DISTINCT receiver FROM messages")?;
+
+            let receivers = select.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // Skip records with invalid receivers
+            Ok(receivers.into_iter()
+                .filter_map(|receiver| PublicKey::from_base64(receiver).ok())
+                .collect())
+        }).await
+    }
+
+    async fn list_channels(&self, receiver: PublicKey) -> Result<Vec<(ChannelName, u64)>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            "Listing channels"
+        );
+
+        let receiver = receiver.to_base64();
+
+        self.with_connection(move |connection| {
+            let mut select = connection.prepare_cached("
+                SELECT channel, COUNT(*) FROM messages
+                WHERE receiver = ?1
+                GROUP BY channel
+                ORDER BY channel
+            ")?;
+
+            // Names are validated before the messages are stored
+            let channels = select.query_map([receiver], |row| {
+                Ok((ChannelName::new_unchecked(row.get::<_, String>(0)?), row.get::<_, i64>(1)? as u64))
+            })?;
+
+            Ok(channels.collect::<Result<Vec<_>, _>>()?)
+        }).await
+    }
+
+    async fn purge(&self, receiver: PublicKey, channel: Option<ChannelName>) -> Result<(), Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = channel.as_ref().map(ChannelName::as_str),
+            "Purging messages"
+        );
+
+        let receiver = receiver.to_base64();
+
+        self.with_connection(move |connection| {
+            match channel {
+                Some(channel) => connection.execute(
+                    "DELETE FROM messages WHERE receiver = ?1 AND channel = ?2",
+                    params![receiver, channel.as_str()]
+                )?,
+
+                None => connection.execute(
+                    "DELETE FROM messages WHERE receiver = ?1",
+                    params![receiver]
+                )?
+            };
+
+            Ok(())
+        }).await
+    }
+
+    async fn stats(&self) -> Result<InboxStats, Self::Error> {
+        self.with_connection(|connection| {
+            let stats = connection.query_row("
+                SELECT
+                    COUNT(*),
+                    COALESCE(SUM(length(sender) + length(message)), 0),
+                    COUNT(DISTINCT receiver),
+                    MIN(received_at)
+                FROM messages
+            ", [], |row| Ok(InboxStats {
+                messages: row.get::<_, i64>(0)? as u64,
+                bytes: row.get::<_, i64>(1)? as u64,
+                receivers: row.get::<_, i64>(2)? as u64,
+                oldest_message: row.get::<_, Option<i64>>(3)?.map(|oldest| oldest as u64)
+            }))?;
+
+            Ok(stats)
+        }).await
+    }
+
+    fn set_observer(&mut self, observer: Arc<dyn InboxObserver>) {
+        self.observer = Some(SharedObserver(observer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::drivers::server::messages_inbox::test_suite::{send_poll_suite, peek_suite, sender_filter_suite, wildcard_suite, list_channels_suite, add_messages_suite, stats_suite, stream_suite, purge_suite, observer_suite, range_filter_suite, fifo_suite, limits_suite, empty_channels_suite, interleaved_channels_suite};
+
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn send_poll() -> Result<(), Error> {
+        send_poll_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn peek() -> Result<(), Error> {
+        peek_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn sender_filter() -> Result<(), Error> {
+        sender_filter_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn wildcard() -> Result<(), Error> {
+        wildcard_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn list_channels() -> Result<(), Error> {
+        list_channels_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn add_messages() -> Result<(), Error> {
+        add_messages_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn stats() -> Result<(), Error> {
+        stats_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn stream() -> Result<(), Error> {
+        stream_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn purge() -> Result<(), Error> {
+        purge_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn range_filter() -> Result<(), Error> {
+        range_filter_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn observer() -> Result<(), Error> {
+        observer_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn conformance() -> Result<(), Error> {
+        fifo_suite(SqliteMessagesInbox::in_memory()?).await?;
+        limits_suite(SqliteMessagesInbox::in_memory()?).await?;
+        empty_channels_suite(SqliteMessagesInbox::in_memory()?).await?;
+        interleaved_channels_suite(SqliteMessagesInbox::in_memory()?).await
+    }
+
+    #[tokio::test]
+    async fn persist() -> Result<(), Error> {
+        let path = std::env::temp_dir()
+            .join("sqlite-messages-inbox-test.db");
+
+        for path in [path.clone(), path.with_extension("db-wal"), path.with_extension("db-shm")] {
+            if path.exists() {
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        {
+            let inbox = SqliteMessagesInbox::open(&path).await?;
+
+            for text in ["message 1", "message 2", "message 3"] {
+                let message = Message::new(text, "sign", MessageEncoding::default());
+
+                inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), message).await?;
+            }
+
+            let (poll, 2) = inbox.poll_messages(receiver.clone(), ChannelName::new("channel").unwrap().into(), None, None, Some(1)).await? else {
+                panic!("Test 1 failed");
+            };
+
+            assert_eq!(poll[0].message.content, "message 1");
+            assert_eq!(poll[0].sender, sender);
+        }
+
+        let inbox = SqliteMessagesInbox::open(&path).await?;
+
+        let (poll, 0) = inbox.poll_messages(receiver, ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Test 2 failed");
+        };
+
+        assert_eq!(poll.iter().map(|info| info.message.content.as_str()).collect::<Vec<_>>(), ["message 2", "message 3"]);
+
+        Ok(())
+    }
+}
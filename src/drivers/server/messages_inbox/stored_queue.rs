@@ -1,13 +1,29 @@
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use serde_json::Value as Json;
+use serde_json::{json, Value as Json};
+
+use futures_core::Stream;
+
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, KeyInit};
+use chacha20poly1305::aead::Aead;
+
+use rand_chacha::rand_core::RngCore;
 
 use crate::time::timestamp;
+use crate::crypto::utils::{random_generator, hmac_sha256};
 
 use crate::crypto::prelude::*;
 use crate::rest_api::prelude::*;
 
-use super::MessagesInbox;
+use crate::drivers::server::layout::StorageLayout;
+
+use super::{MessagesInbox, InboxStats, InboxObserver, SharedObserver, ChannelPriorities, message_hash, merge_prioritized, matches_filters};
+use super::wal::{WriteAheadLog, FsyncPolicy};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -18,211 +34,4865 @@ pub enum Error {
     Json(#[from] AsJsonError),
 
     #[error(transparent)]
-    Serialize(#[from] serde_json::Error)
+    Serialize(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    InvalidChannel(#[from] ChannelNameError),
+
+    #[error("Message is sealed to its receiver and can only be polled in sealed mode")]
+    SealedMessage,
+
+    #[error("Receiver's inbox quota exceeded: {messages} messages, {bytes} bytes stored")]
+    QuotaExceeded {
+        messages: u64,
+        bytes: u64
+    },
+
+    #[error("Channel is full: {messages} messages stored")]
+    ChannelFull {
+        messages: u64
+    },
+
+    #[error("Message is too large: {size} bytes, at most {max_size} allowed")]
+    MessageTooLarge {
+        size: usize,
+        max_size: usize
+    },
+
+    #[error("Storage contains unencrypted messages while the inbox is encrypted")]
+    UnencryptedStorage,
+
+    #[error("Storage contains encrypted messages while the inbox is not encrypted")]
+    EncryptedStorage,
+
+    #[error("Failed to encrypt or decrypt stored message: wrong storage key or corrupted file")]
+    Encryption
+}
+
+/// Files of the channel's folder which are not messages.
+const SERVICE_FILES: &[&str] = &["index", "index.tmp", "dedup", "dedup.tmp"];
+
+/// Prefix of the stored files encrypted by the storage key.
+const ENCRYPTED_MAGIC: &[u8] = b"HBENC\x01";
+
+/// Prefix of the encrypted channel folders' names.
+/// 
+/// It's always percent-encoded in the plain names,
+/// so encrypted folders can't be mixed up with them.
+const ENCRYPTED_FOLDER_PREFIX: char = '~';
+
+/// Maximal amount of receivers which usage
+/// is cached to check their quotas.
+const MAX_CACHED_USAGE: usize = 65536;
+
+/// Amount of messages after which the streamed poll
+/// rewrites the channel's index or consumes the messages
+/// read from the write-ahead log.
+const STREAM_BATCH: u64 = 100;
+
+#[derive(Clone, PartialEq, Eq)]
+/// Symmetric key used to encrypt stored messages
+/// and channel folders' names.
+struct StorageKey([u8; 32]);
+
+impl std::fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StorageKey(..)")
+    }
+}
+
+impl StorageKey {
+    #[inline]
+    fn new(server_secret: &SecretKey) -> Self {
+        Self(hmac_sha256(server_secret.serialize(), b"hyperborea stored queue inbox"))
+    }
+
+    /// Encrypt data with the given nonce.
+    fn encrypt_with(&self, nonce: [u8; 12], data: &[u8]) -> Result<Vec<u8>, Error> {
+        let encrypted = ChaCha20Poly1305::new(Key::from_slice(&self.0))
+            .encrypt(Nonce::from_slice(&nonce), data)
+            .map_err(|_| Error::Encryption)?;
+
+        Ok([&nonce[..], &encrypted].concat())
+    }
+
+    /// Decrypt data prefixed by its nonce.
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        if data.len() < 12 {
+            return Err(Error::Encryption);
+        }
+
+        ChaCha20Poly1305::new(Key::from_slice(&self.0))
+            .decrypt(Nonce::from_slice(&data[..12]), &data[12..])
+            .map_err(|_| Error::Encryption)
+    }
+
+    /// Encrypt stored file's content using random nonce.
+    fn encrypt_file(&self, content: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut nonce = [0; 12];
+
+        random_generator().fill_bytes(&mut nonce);
+
+        Ok([ENCRYPTED_MAGIC, &self.encrypt_with(nonce, content)?].concat())
+    }
+
+    /// Get encrypted name of the channel's folder.
+    /// 
+    /// The nonce is derived from the channel name,
+    /// so the same channels have the same folders.
+    fn encrypt_name(&self, channel: &ChannelName) -> String {
+        let mut nonce = [0; 12];
+
+        nonce.copy_from_slice(&hmac_sha256(self.0, channel.as_str())[..12]);
+
+        // Encryption of the short names can't fail
+        let encrypted = self.encrypt_with(nonce, channel.as_str().as_bytes())
+            .unwrap_or_default();
+
+        let mut name = String::from(ENCRYPTED_FOLDER_PREFIX);
+
+        for byte in encrypted {
+            name.push_str(&format!("{byte:02x}"));
+        }
+
+        name
+    }
+
+    /// Restore channel name from its encrypted folder's name.
+    fn decrypt_name(&self, name: &str) -> Result<ChannelName, Error> {
+        let Some(name) = name.strip_prefix(ENCRYPTED_FOLDER_PREFIX) else {
+            return Err(Error::UnencryptedStorage);
+        };
+
+        let encrypted = (0..name.len())
+            .step_by(2)
+            .map(|i| name.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error::Encryption)?;
+
+        let name = String::from_utf8(self.decrypt(&encrypted)?)
+            .map_err(|_| Error::Encryption)?;
+
+        Ok(ChannelName::new(name)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Limits of the messages stored for a single receiver
+/// in all of its channels.
+pub struct InboxQuota {
+    /// Maximal amount of stored messages.
+    pub max_messages: u64,
+
+    /// Maximal total size of the stored files in bytes.
+    pub max_bytes: u64
+}
+
+impl Default for InboxQuota {
+    /// Unlimited quota.
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_messages: u64::MAX,
+            max_bytes: u64::MAX
+        }
+    }
+}
+
+impl InboxQuota {
+    #[inline]
+    /// Check if the quota limits anything.
+    pub fn is_limited(&self) -> bool {
+        self.max_messages != u64::MAX || self.max_bytes != u64::MAX
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// What to do with new messages sent
+/// to the full channel.
+pub enum EvictionPolicy {
+    #[default]
+    /// Refuse new messages until the
+    /// stored ones are polled.
+    RejectNew,
+
+    /// Remove the oldest stored messages
+    /// to store the new one.
+    DropOldest
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// Summary of the storage compaction.
+/// 
+/// Refer to `StoredQueueMessagesInbox::compact` method.
+pub struct CompactionSummary {
+    /// Amount of checked channels.
+    pub channels: u64,
+
+    /// Amount of dropped index entries
+    /// which had no message files.
+    pub dangling_entries: u64,
+
+    /// Amount of removed message files
+    /// which were not referenced by the index.
+    pub orphaned_files: u64,
+
+    /// Total size of the removed files.
+    pub removed_bytes: u64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Archive of the polled messages.
+/// 
+/// Refer to `StoredQueueMessagesInbox::with_archive` method.
+pub struct ArchiveConfig {
+    /// Time for which polled messages
+    /// are kept in the archive.
+    pub retention: Duration
+}
+
+impl ArchiveConfig {
+    #[inline]
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Lease of the message polled by `lease_messages`.
+struct MessageLease {
+    channel: ChannelName,
+
+    /// Time until which the message is hidden
+    /// from the leasing polls.
+    until: Instant
+}
+
+type ChannelLocks = HashMap<(PublicKey, ChannelName), Arc<tokio::sync::Mutex<()>>>;
+
+/// State of the poll made by `poll_messages_stream`.
+struct StreamedPoll {
+    receiver: PublicKey,
+    channel: ChannelName,
+
+    /// Amount of messages left to read.
+    limit: u64,
+
+    /// Channel's folder and index read on the first poll.
+    index: Option<(PathBuf, Vec<u64>)>,
+
+    /// Position of the next message in the index.
+    position: usize,
+
+    /// Position up to which the index was rewritten.
+    flushed: usize,
+
+    /// Messages read from the write-ahead log
+    /// which weren't yielded yet.
+    buffered: VecDeque<(u64, MessageInfo)>,
+
+    /// Ids of the yielded messages which weren't
+    /// consumed from the write-ahead log yet.
+    yielded: Vec<u64>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Unencrypted metadata of the sealed message
+/// stored in the `<message id>.meta` sidecar file.
+struct SealedMetadata {
+    channel: ChannelName,
+    received_at: u64,
+    size: u64
+}
+
+impl SealedMetadata {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "channel": self.channel.to_json()?,
+            "received_at": self.received_at,
+            "size": self.size
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> {
+        Ok(Self {
+            channel: json.get("channel")
+                .map(ChannelName::from_json)
+                .ok_or_else(|| AsJsonError::FieldNotFound("channel"))??,
+
+            received_at: json.get("received_at")
+                .and_then(Json::as_u64)
+                .ok_or_else(|| AsJsonError::FieldNotFound("received_at"))?,
+
+            size: json.get("size")
+                .and_then(Json::as_u64)
+                .ok_or_else(|| AsJsonError::FieldNotFound("size"))?
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct StoredQueueMessagesInbox {
     /// Path to the messages inbox's folder.
-    pub storage_folder: PathBuf
+    pub storage_folder: PathBuf,
+
+    /// Layout of the receivers' folders.
+    pub layout: StorageLayout,
+
+    /// Store messages sealed to their receivers.
+    /// 
+    /// Sealed messages are encrypted by an ephemeral key
+    /// and the receiver's public key, so they can't be read
+    /// from the storage even with the server's secret key.
+    /// Such messages can only be polled in sealed mode.
+    pub sealed: bool,
+
+    /// Time after which stored messages expire.
+    /// 
+    /// Expired messages are skipped and removed on poll
+    /// and by the `cleanup_expired` method. Messages
+    /// stored in the write-ahead log never expire.
+    pub ttl: Option<Duration>,
+
+    /// Limits of the messages stored for each receiver.
+    /// 
+    /// Usage is calculated from the stored files so it
+    /// survives restarts. The quota is not applied to
+    /// the write-ahead log storage.
+    pub quota: InboxQuota,
+
+    /// Maximal amount of messages stored
+    /// in a single channel.
+    pub max_channel_messages: u64,
+
+    /// What to do with new messages when the channel
+    /// stores `max_channel_messages` messages.
+    pub eviction: EvictionPolicy,
+
+    /// Maximal size of the message's content in bytes.
+    /// 
+    /// Default is 8 MiB.
+    pub max_message_size: usize,
+
+    /// Priorities of the channels polled
+    /// by the wildcard channel rules.
+    pub priorities: ChannelPriorities,
+
+    /// Keep copies of the polled messages.
+    /// 
+    /// Refer to `with_archive` method.
+    pub archive: Option<ArchiveConfig>,
+
+    /// Time for which collected statistics are cached.
+    /// 
+    /// Statistics are collected by the storage walk,
+    /// so they shouldn't be recollected on each call
+    /// for large inboxes.
+    pub stats_cache: Option<Duration>,
+
+    /// Key used to encrypt stored messages.
+    /// 
+    /// Refer to `with_encryption` method.
+    storage_key: Option<StorageKey>,
+
+    /// Time within which the same messages
+    /// sent to the same channel are dropped.
+    /// 
+    /// Hashes of the recent messages are stored in the
+    /// `dedup` file of the channel's folder, so the window
+    /// is kept over restarts. Refer to `message_hash`.
+    pub dedup_window: Option<Duration>,
+
+    /// Lock used to add messages one by one
+    /// when deduplication is enabled.
+    dedup_lock: Arc<tokio::sync::Mutex<()>>,
+
+    /// Locks of the receivers' channels.
+    /// 
+    /// Refer to `lock_channel` method.
+    channel_locks: Arc<Mutex<ChannelLocks>>,
+
+    /// Cached amount of messages and bytes
+    /// stored for the receivers.
+    usage: Arc<Mutex<HashMap<PublicKey, (u64, u64)>>>,
+
+    /// Metadata of the stored sealed messages.
+    metadata: Arc<Mutex<HashMap<PathBuf, SealedMetadata>>>,
+
+    /// Last collected statistics and their collection time.
+    stats: Arc<Mutex<Option<(Instant, InboxStats)>>>,
+
+    /// Leases of the receivers' messages which
+    /// are not acknowledged yet.
+    /// 
+    /// Leases are kept in memory, so leased messages
+    /// become visible again after restart.
+    leases: Arc<Mutex<HashMap<(PublicKey, u64), MessageLease>>>,
+
+    /// Observer notified about added messages.
+    observer: Option<SharedObserver>,
+
+    /// Write-ahead log storing the messages
+    /// instead of the receivers' folders.
+    wal: Option<Arc<WriteAheadLog>>
 }
 
 impl StoredQueueMessagesInbox {
-    pub async fn new(storage_folder: impl Into<PathBuf>) -> std::io::Result<Self> {
+    #[inline]
+    /// Create new inbox in the given folder.
+    /// 
+    /// If `ttl` is set, messages not polled within
+    /// this time are dropped.
+    pub async fn new(storage_folder: impl Into<PathBuf>, ttl: Option<Duration>) -> std::io::Result<Self> {
+        Ok(Self {
+            ttl,
+            ..Self::new_with_layout(storage_folder, StorageLayout::Flat).await?
+        })
+    }
+
+    pub async fn new_with_layout(storage_folder: impl Into<PathBuf>, layout: StorageLayout) -> std::io::Result<Self> {
         let storage_folder = storage_folder.into();
 
         #[cfg(feature = "tracing")]
-        tracing::trace!("Building new StoredQueueMessagesInbox in {:?}", storage_folder);
+        tracing::trace!(?layout, "Building new StoredQueueMessagesInbox in {:?}", storage_folder);
 
         tokio::fs::create_dir_all(&storage_folder).await?;
 
         Ok(Self {
-            storage_folder
+            storage_folder,
+            layout,
+            sealed: false,
+            ttl: None,
+            quota: InboxQuota::default(),
+            max_channel_messages: u64::MAX,
+            eviction: EvictionPolicy::default(),
+            max_message_size: 8 * 1024 * 1024,
+            priorities: ChannelPriorities::default(),
+            archive: None,
+            stats_cache: None,
+            storage_key: None,
+            dedup_window: None,
+            dedup_lock: Arc::new(tokio::sync::Mutex::new(())),
+            channel_locks: Arc::new(Mutex::new(HashMap::new())),
+            usage: Arc::new(Mutex::new(HashMap::new())),
+            metadata: Arc::new(Mutex::new(HashMap::new())),
+            stats: Arc::new(Mutex::new(None)),
+            leases: Arc::new(Mutex::new(HashMap::new())),
+            observer: None,
+            wal: None
         })
     }
-}
 
-#[async_trait::async_trait]
-impl MessagesInbox for StoredQueueMessagesInbox {
-    type Error = Error;
+    /// Create new inbox which stores messages in
+    /// the write-ahead log.
+    /// 
+    /// Messages are appended to the per-shard logs in
+    /// the `wal` subfolder and flushed to the disk according
+    /// to the given policy. The log is replayed on creation.
+    /// 
+    /// Messages stored in the receivers' folders can be moved
+    /// to the log by `migrate_to_wal` method.
+    pub async fn new_wal(storage_folder: impl Into<PathBuf>, fsync: FsyncPolicy) -> std::io::Result<Self> {
+        let inbox = Self::new_with_layout(storage_folder, StorageLayout::Sharded).await?;
 
-    async fn add_message(
-        &self,
-        sender: Sender,
-        receiver: PublicKey,
-        channel: String,
-        message: Message
-    ) -> Result<(), Self::Error> {
-        #[cfg(feature = "tracing")]
-        tracing::debug!(
-            sender = ?sender,
-            receiver = receiver.to_base64(),
-            channel,
-            "Adding new message"
-        );
+        let wal = WriteAheadLog::open(inbox.storage_folder.join("wal"), fsync).await?;
 
-        let folder = self.storage_folder
-            .join(receiver.to_base64())
-            .join(&channel);
+        Ok(Self {
+            wal: Some(Arc::new(wal)),
+            ..inbox
+        })
+    }
 
-        tokio::fs::create_dir_all(&folder).await?;
+    #[inline]
+    /// Check if the inbox stores messages in the write-ahead log.
+    pub fn is_wal(&self) -> bool {
+        self.wal.is_some()
+    }
 
-        let mut index = tokio::fs::read(folder.join("index")).await
-            .unwrap_or(vec![]);
+    /// Rewrite the write-ahead log dropping consumed records.
+    /// 
+    /// Should be called periodically. Return number
+    /// of dropped records. Does nothing if the write-ahead
+    /// log is not used.
+    pub async fn compact_wal(&self) -> Result<u64, Error> {
+        match &self.wal {
+            Some(wal) => Ok(wal.compact().await?),
+            None => Ok(0)
+        }
+    }
 
-        let message_id = safe_random_u64();
+    /// Fix inconsistencies between the channels' indexes
+    /// and message files left by interrupted writes.
+    /// 
+    /// Index entries without message files are dropped,
+    /// and message files not referenced by the index are
+    /// removed if they weren't modified within the `grace`
+    /// period. Channels are locked one by one, so the inbox
+    /// can be compacted while serving requests.
+    /// 
+    /// Does nothing if the write-ahead log is used.
+    /// Refer to `compact_wal` method.
+    pub async fn compact(&self, grace: Duration) -> Result<CompactionSummary, Error> {
+        let mut summary = CompactionSummary::default();
 
-        index.extend_from_slice(&message_id.to_be_bytes());
+        if self.wal.is_some() {
+            return Ok(summary);
+        }
 
-        let message_info = MessageInfo {
-            sender,
-            channel,
-            message,
-            received_at: timestamp()
-        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?grace, "Compacting StoredQueueMessagesInbox");
 
-        let message_info = serde_json::to_vec(&message_info.to_json()?)?;
+        for (receiver, folder) in self.receiver_folders().await? {
+            let mut channels = tokio::fs::read_dir(&folder).await?;
 
-        tokio::fs::write(folder.join("index"), index).await?;
-        tokio::fs::write(folder.join(message_id.to_string()), message_info).await?;
+            while let Some(channel) = channels.next_entry().await? {
+                if !channel.file_type().await?.is_dir() {
+                    continue;
+                }
 
-        Ok(())
-    }
+                // Folders of unknown channels can't be locked
+                let Ok(Some(name)) = self.folder_channel(&channel.file_name()) else {
+                    continue;
+                };
+
+                let _guard = self.lock_channel(&receiver, &name).await;
+
+                self.compact_channel(&channel.path(), grace, &mut summary).await?;
+            }
+
+            self.invalidate_usage(&receiver);
+        }
 
-    async fn poll_messages(
-        &self,
-        receiver: PublicKey,
-        channel: String,
-        limit: Option<u64>
-    ) -> Result<(Vec<MessageInfo>, u64), Self::Error> {
         #[cfg(feature = "tracing")]
-        tracing::debug!(
-            receiver = receiver.to_base64(),
-            channel,
-            limit,
-            "Polling messages"
-        );
+        tracing::trace!(?summary, "Compacted StoredQueueMessagesInbox");
 
-        let folder = self.storage_folder
-            .join(receiver.to_base64())
-            .join(&channel);
+        Ok(summary)
+    }
 
-        if let Ok(index) = tokio::fs::read(folder.join("index")).await {
-            assert!(index.len() % 8 == 0);
+    /// Cross-check index of the locked channel
+    /// with its message files.
+    async fn compact_channel(&self, folder: &Path, grace: Duration, summary: &mut CompactionSummary) -> Result<(), Error> {
+        let index = Self::read_index(folder).await
+            .unwrap_or_default();
 
-            let mut bytes = [0; 8];
-            let mut limit = limit.unwrap_or(u64::MAX);
-            let mut shift = 0;
+        let mut files = HashSet::new();
+        let mut sidecars = HashSet::new();
 
-            let mut messages = Vec::new();
+        let mut entries = tokio::fs::read_dir(folder).await?;
 
-            for message_id in index.chunks(8) {
-                if limit == 0 {
-                    break;
-                }
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
 
-                bytes.copy_from_slice(message_id);
+            if let Ok(message_id) = name.parse::<u64>() {
+                files.insert(message_id);
+            }
 
-                let message_id = u64::from_be_bytes(bytes);
-                let message_path = folder.join(message_id.to_string());
+            else if let Some(message_id) = name.strip_suffix(".meta").and_then(|id| id.parse::<u64>().ok()) {
+                sidecars.insert(message_id);
+            }
+        }
 
-                if let Ok(message_info) = tokio::fs::read(&message_path).await {
-                    let message_info = serde_json::from_slice::<Json>(&message_info)?;
+        summary.channels += 1;
 
-                    messages.push(MessageInfo::from_json(&message_info)?);
+        let kept = index.iter()
+            .filter(|message_id| files.contains(message_id))
+            .copied()
+            .collect::<Vec<_>>();
 
-                    limit -= 1;
+        if kept.len() != index.len() {
+            summary.dangling_entries += (index.len() - kept.len()) as u64;
 
-                    tokio::fs::remove_file(message_path).await?;
-                }
+            Self::write_index(folder, &kept).await?;
+        }
+
+        // Sidecars without message files are orphaned too
+        let orphaned = files.union(&sidecars)
+            .filter(|message_id| !kept.contains(message_id))
+            .collect::<Vec<_>>();
 
-                shift += 8;
+        for message_id in orphaned {
+            let message_path = folder.join(message_id.to_string());
+
+            let mut size = 0;
+            let mut recent = false;
+
+            for path in [message_path.clone(), message_path.with_extension("meta")] {
+                match tokio::fs::metadata(&path).await {
+                    Ok(metadata) => {
+                        size += metadata.len();
+
+                        // Files modified in the future are kept too
+                        recent |= metadata.modified()?
+                            .elapsed()
+                            .map_or(true, |age| age < grace);
+                    }
+
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+                    Err(err) => return Err(err.into())
+                }
             }
 
-            let index = &index[shift..];
+            if recent {
+                continue;
+            }
 
-            tokio::fs::write(folder.join("index"), index).await?;
+            self.remove_message(&message_path).await?;
 
-            return Ok((
-                messages,
-                (index.len() / 8) as u64
-            ));
+            summary.orphaned_files += 1;
+            summary.removed_bytes += size;
         }
 
-        Ok((vec![], 0))
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::rest_api::types::client::tests::get_client;
-    use crate::rest_api::types::server::tests::get_server;
+    /// Move messages from the receivers' folders
+    /// to the write-ahead log.
+    /// 
+    /// Messages keep their ids so interrupted migration
+    /// can be safely restarted without duplicating them.
+    /// 
+    /// Return number of migrated messages. Does nothing
+    /// if the write-ahead log is not used.
+    pub async fn migrate_to_wal(&self) -> Result<u64, Error> {
+        let Some(wal) = &self.wal else {
+            return Ok(0);
+        };
 
-    use super::*;
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Migrating StoredQueueMessagesInbox to the write-ahead log");
 
-    #[tokio::test]
-    async fn send_poll() -> Result<(), Error> {
-        let temp = std::env::temp_dir()
-            .join("stored-queue-messages-inbox-test");
+        let mut migrated = 0;
 
-        if temp.exists() {
-            tokio::fs::remove_dir_all(&temp).await?;
-        }
+        for (receiver, folder) in self.receiver_folders().await? {
+            let mut channels = tokio::fs::read_dir(&folder).await?;
 
-        tokio::fs::create_dir(&temp).await?;
+            while let Some(channel) = channels.next_entry().await? {
+                let channel = channel.path();
 
-        let queue = StoredQueueMessagesInbox::new(&temp).await?;
+                for message_id in Self::read_index(&channel).await.unwrap_or_default() {
+                    let message_path = channel.join(message_id.to_string());
 
-        let sender_secret = SecretKey::random();
-        let receiver_secret = SecretKey::random();
+                    let Ok(content) = tokio::fs::read(&message_path).await else {
+                        continue;
+                    };
 
-        let sender = Sender::new(get_client(), get_server());
-        let receiver = get_client();
+                    let content = serde_json::from_slice::<Json>(&self.decrypt_stored(content)?)?;
 
-        let mut messages = Vec::with_capacity(5);
+                    // Restore sealed message info from its sidecar
+                    let (channel_name, info) = match self.read_metadata(&message_path).await? {
+                        Some(metadata) => {
+                            let sealed = json!({
+                                "channel": metadata.channel.to_json()?,
+                                "received_at": metadata.received_at,
+                                "sealed": content
+                            });
 
-        for message in [b"message 1", b"message 2", b"message 3", b"message 4", b"message 5"] {
-            let message = Message::create(
-                &sender_secret,
-                &receiver.public_key,
-                message,
-                MessageEncoding::default(),
-                CompressionLevel::default()
-            ).unwrap();
+                            (metadata.channel, sealed)
+                        }
 
-            messages.push(message.clone());
+                        None => (MessageInfo::from_json(&content)?.channel, content)
+                    };
 
-            queue.add_message(
-                sender.clone(),
-                receiver_secret.public_key(),
-                String::from("default channel"),
-                message
-            ).await?;
+                    if wal.add(message_id, receiver.clone(), channel_name, self.encrypt_stored(serde_json::to_vec(&info)?)?).await? {
+                        migrated += 1;
+                    }
+                }
+
+                wal.sync().await?;
+
+                tokio::fs::remove_dir_all(channel).await?;
+            }
+
+            tokio::fs::remove_dir_all(folder).await?;
         }
 
-        assert_eq!(queue.poll_messages(receiver_secret.public_key(), String::from("random channel"), None).await?, (vec![], 0));
-        assert_eq!(queue.poll_messages(receiver_secret.public_key(), String::from("random channel"), Some(100)).await?, (vec![], 0));
+        Ok(migrated)
+    }
 
-        let (poll, 4) = queue.poll_messages(receiver_secret.public_key(), String::from("default channel"), Some(1)).await? else {
-            panic!("Test 1 failed");
-        };
+    #[inline]
+    /// Create new inbox which stores messages
+    /// sealed to their receivers.
+    pub async fn new_sealed(storage_folder: impl Into<PathBuf>, layout: StorageLayout) -> std::io::Result<Self> {
+        Ok(Self {
+            sealed: true,
+            ..Self::new_with_layout(storage_folder, layout).await?
+        })
+    }
 
-        assert_eq!(poll[0].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 1");
+    #[inline]
+    /// Limit messages stored for each receiver.
+    pub fn with_quota(self, quota: InboxQuota) -> Self {
+        Self {
+            quota,
+            ..self
+        }
+    }
 
-        let (poll, 2) = queue.poll_messages(receiver_secret.public_key(), String::from("default channel"), Some(2)).await? else {
-            panic!("Test 2 failed");
-        };
+    #[inline]
+    /// Limit amount of messages stored in each channel.
+    /// 
+    /// The policy is applied when a message is sent
+    /// to the channel which already stores `max_messages`.
+    pub fn with_channel_limit(self, max_messages: u64, eviction: EvictionPolicy) -> Self {
+        Self {
+            max_channel_messages: max_messages,
+            eviction,
+            ..self
+        }
+    }
 
-        assert_eq!(poll[0].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 2");
-        assert_eq!(poll[1].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 3");
+    #[inline]
+    /// Reject messages with content larger than `max_size` bytes.
+    pub fn with_max_message_size(self, max_size: usize) -> Self {
+        Self {
+            max_message_size: max_size,
+            ..self
+        }
+    }
 
-        let (poll, 0) = queue.poll_messages(receiver_secret.public_key(), String::from("default channel"), None).await? else {
-            panic!("Test 3 failed");
-        };
+    #[inline]
+    /// Drain channels with higher priority first
+    /// when polling multiple channels.
+    pub fn with_priorities(self, priorities: ChannelPriorities) -> Self {
+        Self {
+            priorities,
+            ..self
+        }
+    }
 
-        assert_eq!(poll[0].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 4");
-        assert_eq!(poll[1].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 5");
+    #[inline]
+    /// Move polled messages to the archive
+    /// instead of removing them.
+    /// 
+    /// Consumed message files are moved to the
+    /// `archive/<receiver>/<channel>` folder and named
+    /// by their delivery timestamp and id. Archived
+    /// messages are removed by the `expire_archive` method
+    /// after the retention window. Messages stored in the
+    /// write-ahead log are not archived.
+    pub fn with_archive(self, archive: ArchiveConfig) -> Self {
+        Self {
+            archive: Some(archive),
+            ..self
+        }
+    }
 
-        Ok(())
+    #[inline]
+    /// Notify the given observer about added messages.
+    /// 
+    /// Observer is called after the messages are stored
+    /// and the channel is unlocked.
+    pub fn with_observer(mut self, observer: impl InboxObserver + 'static) -> Self {
+        self.set_observer(Arc::new(observer));
+
+        self
+    }
+
+    #[inline]
+    /// Cache collected statistics for the given time.
+    pub fn with_stats_cache(self, ttl: Duration) -> Self {
+        Self {
+            stats_cache: Some(ttl),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Encrypt stored messages by the key
+    /// derived from the server's secret key.
+    /// 
+    /// Message files, sealed messages' sidecars and
+    /// channel folders' names are encrypted, so senders
+    /// and channels can't be read from the storage.
+    /// Messages stored in the write-ahead log are
+    /// encrypted too, but the log keeps channel names
+    /// in plain.
+    /// 
+    /// Storage written without encryption can't
+    /// be read by the encrypted inbox.
+    pub fn with_encryption(self, server_secret: &SecretKey) -> Self {
+        Self {
+            storage_key: Some(StorageKey::new(server_secret)),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Check if the inbox encrypts stored messages.
+    pub fn is_encrypted(&self) -> bool {
+        self.storage_key.is_some()
+    }
+
+    /// Encrypt content of the stored file
+    /// if the inbox is encrypted.
+    fn encrypt_stored(&self, content: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match &self.storage_key {
+            Some(key) => key.encrypt_file(&content),
+            None => Ok(content)
+        }
+    }
+
+    /// Decrypt content of the stored file
+    /// if the inbox is encrypted.
+    /// 
+    /// Fail if the file's encryption doesn't match
+    /// the inbox's one.
+    fn decrypt_stored(&self, content: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let encrypted = content.strip_prefix(ENCRYPTED_MAGIC);
+
+        match (&self.storage_key, encrypted) {
+            (Some(key), Some(encrypted)) => key.decrypt(encrypted),
+            (Some(_), None) => Err(Error::UnencryptedStorage),
+            (None, Some(_)) => Err(Error::EncryptedStorage),
+            (None, None) => Ok(content)
+        }
+    }
+
+    #[inline]
+    /// Drop the same messages sent within the given window.
+    pub fn with_dedup_window(self, window: Duration) -> Self {
+        Self {
+            dedup_window: Some(window),
+            ..self
+        }
+    }
+
+    /// Read hashes of the recent messages
+    /// within the deduplication window.
+    async fn read_dedup(&self, folder: &Path) -> std::io::Result<Vec<(u64, u64)>> {
+        let Some(window) = self.dedup_window else {
+            return Ok(vec![]);
+        };
+
+        let records = match tokio::fs::read(folder.join("dedup")).await {
+            Ok(records) => records,
+
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err)
+        };
+
+        let deadline = timestamp().saturating_sub(window.as_secs());
+
+        let mut hash = [0; 8];
+        let mut added_at = [0; 8];
+
+        // Incomplete records are ignored
+        let records = records.chunks_exact(16)
+            .map(|record| {
+                hash.copy_from_slice(&record[..8]);
+                added_at.copy_from_slice(&record[8..]);
+
+                (u64::from_be_bytes(hash), u64::from_be_bytes(added_at))
+            })
+            .filter(|(_, added_at)| *added_at >= deadline)
+            .collect();
+
+        Ok(records)
+    }
+
+    /// Store hashes of the added messages,
+    /// forgetting the ones out of the window.
+    async fn remember_messages(&self, folder: &Path, mut records: Vec<(u64, u64)>, hashes: &[u64]) -> std::io::Result<()> {
+        let added_at = timestamp();
+
+        records.extend(hashes.iter().map(|hash| (*hash, added_at)));
+
+        let records = records.into_iter()
+            .flat_map(|(hash, added_at)| [hash.to_be_bytes(), added_at.to_be_bytes()])
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let temp_path = folder.join("dedup.tmp");
+
+        tokio::fs::create_dir_all(folder).await?;
+        tokio::fs::write(&temp_path, records).await?;
+        tokio::fs::rename(&temp_path, folder.join("dedup")).await
+    }
+
+    /// Get receivers' folders of both layouts.
+    async fn receiver_folders(&self) -> Result<Vec<(PublicKey, PathBuf)>, Error> {
+        let mut receivers = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.storage_folder).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+
+            if let Ok(receiver) = PublicKey::from_base64(name.as_ref()) {
+                receivers.push((receiver, entry.path()));
+            }
+
+            else if StorageLayout::is_shard(&name) {
+                let mut shard = tokio::fs::read_dir(entry.path()).await?;
+
+                while let Some(entry) = shard.next_entry().await? {
+                    if let Ok(receiver) = PublicKey::from_base64(entry.file_name().to_string_lossy().as_ref()) {
+                        receivers.push((receiver, entry.path()));
+                    }
+                }
+            }
+        }
+
+        Ok(receivers)
+    }
+
+    #[inline]
+    /// Calculate amount of messages and bytes
+    /// stored in all the receiver's channels.
+    async fn receiver_usage(&self, receiver: &PublicKey) -> Result<(u64, u64), Error> {
+        let mut usage = (0, 0);
+
+        for folder in self.layout.paths(&self.storage_folder, receiver) {
+            let (messages, bytes) = Self::folder_usage(&folder).await?;
+
+            usage.0 += messages;
+            usage.1 += bytes;
+        }
+
+        Ok(usage)
+    }
+
+    /// Calculate amount of messages and bytes
+    /// stored in the receiver's folder.
+    async fn folder_usage(folder: &Path) -> Result<(u64, u64), Error> {
+        let mut channels = match tokio::fs::read_dir(folder).await {
+            Ok(channels) => channels,
+
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok((0, 0)),
+            Err(err) => return Err(err.into())
+        };
+
+        let mut messages = 0;
+        let mut bytes = 0;
+
+        while let Some(channel) = channels.next_entry().await? {
+            if !channel.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let mut files = tokio::fs::read_dir(channel.path()).await?;
+
+            while let Some(file) = files.next_entry().await? {
+                let name = file.file_name();
+                let name = name.to_string_lossy();
+
+                if SERVICE_FILES.contains(&name.as_ref()) {
+                    continue;
+                }
+
+                bytes += file.metadata().await?.len();
+
+                if !name.ends_with(".meta") {
+                    messages += 1;
+                }
+            }
+        }
+
+        Ok((messages, bytes))
+    }
+
+    /// Reserve space for a new message of the given size
+    /// in the receiver's quota.
+    async fn reserve_quota(&self, receiver: &PublicKey, size: u64) -> Result<(), Error> {
+        if !self.quota.is_limited() {
+            return Ok(());
+        }
+
+        let cached = self.usage.lock()
+            .expect("Failed to lock inbox usage cache")
+            .contains_key(receiver);
+
+        if !cached {
+            let usage = self.receiver_usage(receiver).await?;
+
+            self.usage.lock()
+                .expect("Failed to lock inbox usage cache")
+                .entry(receiver.clone())
+                .or_insert(usage);
+        }
+
+        let mut cache = self.usage.lock()
+            .expect("Failed to lock inbox usage cache");
+
+        // Forget usage of some other receiver, it will be recalculated
+        if !cache.contains_key(receiver) && cache.len() >= MAX_CACHED_USAGE {
+            if let Some(forgotten) = cache.keys().next().cloned() {
+                cache.remove(&forgotten);
+            }
+        }
+
+        let usage = cache.entry(receiver.clone())
+            .or_default();
+
+        if usage.0 >= self.quota.max_messages || usage.1.saturating_add(size) > self.quota.max_bytes {
+            return Err(Error::QuotaExceeded {
+                messages: usage.0,
+                bytes: usage.1
+            });
+        }
+
+        usage.0 += 1;
+        usage.1 += size;
+
+        Ok(())
+    }
+
+    /// Return space reserved for a message which
+    /// failed to be stored to the receiver's quota.
+    fn release_quota(&self, receiver: &PublicKey, size: u64) {
+        if !self.quota.is_limited() {
+            return;
+        }
+
+        let mut cache = self.usage.lock()
+            .expect("Failed to lock inbox usage cache");
+
+        if let Some(usage) = cache.get_mut(receiver) {
+            usage.0 = usage.0.saturating_sub(1);
+            usage.1 = usage.1.saturating_sub(size);
+
+            // Forget receivers without messages
+            if *usage == (0, 0) {
+                cache.remove(receiver);
+            }
+        }
+    }
+
+    /// Lock the receiver's channel.
+    /// 
+    /// Channel's index is read, modified and written back
+    /// by every add and poll, so concurrent requests to the
+    /// same channel must be serialized to not lose its entries.
+    async fn lock_channel(&self, receiver: &PublicKey, channel: &ChannelName) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.channel_locks.lock()
+                .expect("Failed to lock channels locks");
+
+            let key = (receiver.clone(), channel.clone());
+
+            if !locks.contains_key(&key) {
+                // Forget locks which are not held or awaited
+                locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            }
+
+            locks.entry(key)
+                .or_default()
+                .clone()
+        };
+
+        lock.lock_owned().await
+    }
+
+    #[inline]
+    /// Forget cached usage of the receiver
+    /// so it's recalculated on the next message.
+    fn invalidate_usage(&self, receiver: &PublicKey) {
+        self.usage.lock()
+            .expect("Failed to lock inbox usage cache")
+            .remove(receiver);
+    }
+
+    /// Get path to the receiver's folder.
+    /// 
+    /// Channels of the receivers which weren't migrated
+    /// to the sharded layout yet can be stored in the
+    /// legacy flat folder as well.
+    pub fn receiver_folder(&self, receiver: &PublicKey) -> PathBuf {
+        self.layout.path(&self.storage_folder, receiver)
+    }
+
+    /// Get path to the receiver's channel folder.
+    /// 
+    /// Channel name is validated and converted to its
+    /// filesystem safe form. Folders created before the
+    /// channel names encoding are still served if their
+    /// raw names are safe to use, and channels which
+    /// weren't migrated to the sharded layout yet are
+    /// served from the legacy flat folder.
+    pub fn channel_folder(&self, receiver: &PublicKey, channel: &ChannelName) -> Result<PathBuf, Error> {
+        channel.validate()?;
+
+        let mut folders = self.layout.paths(&self.storage_folder, receiver)
+            .into_iter()
+            .map(|folder| self.folder_channel_path(&folder, channel));
+
+        let path = folders.next()
+            .expect("Receiver has no folders")?;
+
+        if !path.exists() {
+            for legacy in folders {
+                let legacy = legacy?;
+
+                if legacy.exists() {
+                    return Ok(legacy);
+                }
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Get path to the receiver's channel folder, merging
+    /// the legacy flat one into it if the channel is stored
+    /// in both layouts after an interrupted migration.
+    /// 
+    /// Must be called holding the channel's lock.
+    async fn open_channel(&self, receiver: &PublicKey, channel: &ChannelName) -> Result<PathBuf, Error> {
+        let path = self.channel_folder(receiver, channel)?;
+
+        if self.layout == StorageLayout::Sharded {
+            let legacy_folder = StorageLayout::Flat.path(&self.storage_folder, receiver);
+            let legacy = self.folder_channel_path(&legacy_folder, channel)?;
+
+            if legacy != path && legacy.exists() {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    receiver = receiver.to_base64(),
+                    channel = channel.as_str(),
+                    "Merging channel stored in both layouts"
+                );
+
+                self.merge_channel(&legacy, &path).await?;
+
+                // Fails if other channels are still not migrated
+                let _ = tokio::fs::remove_dir(legacy_folder).await;
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Get path to the channel folder
+    /// within the given receiver's folder.
+    fn folder_channel_path(&self, folder: &Path, channel: &ChannelName) -> Result<PathBuf, Error> {
+        if let Some(key) = &self.storage_key {
+            let path = folder.join(key.encrypt_name(channel));
+
+            // Don't silently ignore messages stored without encryption
+            if !path.exists() && folder.join(channel.to_fs_name()).exists() {
+                return Err(Error::UnencryptedStorage);
+            }
+
+            return Ok(path);
+        }
+
+        let path = folder.join(channel.to_fs_name());
+
+        if !path.exists() {
+            let raw = channel.as_str();
+
+            if raw != "." && raw != ".." && !raw.contains(['/', '\\']) && folder.join(raw).exists() {
+                return Ok(folder.join(raw));
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Add messages to the inbox keeping their receiving time.
+    /// 
+    /// Return ids of the messages in the given order.
+    async fn add_message_infos(&self, entries: Vec<(PublicKey, MessageInfo)>) -> Result<Vec<Option<u64>>, Error> {
+        let mut channels = Vec::<(PublicKey, ChannelName, Vec<MessageInfo>)>::new();
+        let mut positions = HashMap::new();
+
+        // Channel of each entry to return ids in the given order
+        let mut order = Vec::with_capacity(entries.len());
+
+        // Group messages by their channels keeping their order
+        for (receiver, info) in entries {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                sender = ?info.sender,
+                receiver = receiver.to_base64(),
+                channel = info.channel.as_str(),
+                "Adding new message"
+            );
+
+            let position = *positions.entry((receiver.clone(), info.channel.clone()))
+                .or_insert_with(|| {
+                    channels.push((receiver, info.channel.clone(), Vec::new()));
+
+                    channels.len() - 1
+                });
+
+            order.push(position);
+
+            channels[position].2.push(info);
+        }
+
+        // Messages are added one by one so concurrent
+        // retries of the same message are not both stored
+        let _guard = match self.dedup_window {
+            Some(_) => Some(self.dedup_lock.clone().lock_owned().await),
+            None => None
+        };
+
+        let mut added = Vec::with_capacity(channels.len());
+
+        for (receiver, channel, messages) in channels {
+            added.push(self.add_channel_messages(receiver, channel, messages).await?.into_iter());
+        }
+
+        let ids = order.into_iter()
+            .map(|position| added[position].next().flatten())
+            .collect();
+
+        Ok(ids)
+    }
+
+    /// Add messages to the receiver's channel.
+    /// 
+    /// Channel's index and dedup files are updated once
+    /// for all the messages. Messages stored before an
+    /// error are kept.
+    /// 
+    /// Return ids of the stored messages, or `None`
+    /// for the dropped duplicates.
+    async fn add_channel_messages(&self, receiver: PublicKey, channel: ChannelName, messages: Vec<MessageInfo>) -> Result<Vec<Option<u64>>, Error> {
+        let guard = self.lock_channel(&receiver, &channel).await;
+
+        let folder = self.open_channel(&receiver, &channel).await?;
+
+        let records = self.read_dedup(&folder).await?;
+
+        let mut hashes = Vec::new();
+        let mut result = Ok(());
+
+        // Current amount of messages is needed only to reject new ones
+        let mut stored = match self.eviction {
+            EvictionPolicy::RejectNew if self.max_channel_messages != u64::MAX => match &self.wal {
+                Some(wal) => wal.peek(&receiver, &channel, Some(0)).await?.1,
+
+                None => {
+                    let index = Self::read_index(&folder).await
+                        .unwrap_or_default();
+
+                    let (expired, _) = self.expired_prefix(&folder, &index).await?;
+
+                    (index.len() - expired) as u64
+                }
+            }
+
+            _ => 0
+        };
+
+        let mut ids = Vec::with_capacity(messages.len());
+        let mut added = Vec::with_capacity(messages.len());
+        let mut notified = Vec::new();
+
+        for message_info in messages {
+            let hash = message_hash(&message_info.sender, &channel, &message_info.message);
+
+            if self.dedup_window.is_some() && (records.iter().any(|(known, _)| *known == hash) || hashes.contains(&hash)) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(hash, "Dropping duplicate message");
+
+                ids.push(None);
+
+                continue;
+            }
+
+            if message_info.message.content.len() > self.max_message_size {
+                result = Err(Error::MessageTooLarge {
+                    size: message_info.message.content.len(),
+                    max_size: self.max_message_size
+                });
+
+                break;
+            }
+
+            // Check the channel limit before storing the message
+            if self.eviction == EvictionPolicy::RejectNew && stored >= self.max_channel_messages {
+                result = Err(Error::ChannelFull { messages: stored });
+
+                break;
+            }
+
+            let notified_info = self.observer.as_ref()
+                .map(|_| message_info.clone());
+
+            match self.store_message(&folder, &receiver, message_info).await {
+                Ok(message_id) => {
+                    ids.push(Some(message_id));
+                    added.push(message_id);
+                    notified.extend(notified_info.map(|info| info.with_id(message_id)));
+
+                    if self.dedup_window.is_some() {
+                        hashes.push(hash);
+                    }
+
+                    stored += 1;
+                }
+
+                Err(err) => {
+                    result = Err(err);
+
+                    break;
+                }
+            }
+        }
+
+        if added.is_empty() {
+            return result.map(|_| ids);
+        }
+
+        match &self.wal {
+            Some(wal) => {
+                // Evict the oldest messages of the full channel
+                if self.eviction == EvictionPolicy::DropOldest && self.max_channel_messages != u64::MAX {
+                    let (_, messages) = wal.peek(&receiver, &channel, Some(0)).await?;
+
+                    if messages > self.max_channel_messages {
+                        let (oldest, _) = wal.peek(&receiver, &channel, Some(messages - self.max_channel_messages)).await?;
+
+                        let ids = oldest.into_iter()
+                            .map(|(id, _)| id)
+                            .collect::<Vec<_>>();
+
+                        wal.consume(&receiver, &channel, &ids).await?;
+
+                        #[cfg(feature = "tracing")]
+                        tracing::info!(
+                            receiver = receiver.to_base64(),
+                            channel = channel.as_str(),
+                            evicted = ids.len(),
+                            "Evicted oldest messages of the full channel"
+                        );
+                    }
+                }
+            }
+
+            None => {
+                let indexed = async {
+                    let mut index = Self::read_index(&folder).await
+                        .unwrap_or_default();
+
+                    index.extend(&added);
+
+                    // Evict the oldest messages of the full channel
+                    if self.eviction == EvictionPolicy::DropOldest && index.len() as u64 > self.max_channel_messages {
+                        let evicted = index.len() - self.max_channel_messages as usize;
+
+                        // Files are removed first so that a crash
+                        // can't leave them out of the index
+                        for message_id in index.drain(..evicted) {
+                            self.remove_message(&folder.join(message_id.to_string())).await?;
+                        }
+
+                        self.invalidate_usage(&receiver);
+
+                        #[cfg(feature = "tracing")]
+                        tracing::info!(
+                            receiver = receiver.to_base64(),
+                            channel = channel.as_str(),
+                            evicted,
+                            "Evicted oldest messages of the full channel"
+                        );
+                    }
+
+                    Self::write_index(&folder, &index).await?;
+
+                    Ok::<_, Error>(())
+                }.await;
+
+                if let Err(err) = indexed {
+                    // Messages out of the index can't be read, so they're
+                    // removed and their reservations are recalculated
+                    for message_id in &added {
+                        let _ = self.remove_message(&folder.join(message_id.to_string())).await;
+                    }
+
+                    self.invalidate_usage(&receiver);
+
+                    return Err(err);
+                }
+            }
+        }
+
+        if !hashes.is_empty() {
+            self.remember_messages(&folder, records, &hashes).await?;
+        }
+
+        drop(guard);
+
+        if let Some(observer) = &self.observer {
+            for message_info in &notified {
+                observer.notify(&receiver, &channel, message_info).await;
+            }
+        }
+
+        result.map(|_| ids)
+    }
+
+    /// Store message in the write-ahead log or
+    /// in the channel's folder without updating
+    /// its index.
+    /// 
+    /// Return id of the stored message.
+    async fn store_message(&self, folder: &Path, receiver: &PublicKey, message_info: MessageInfo) -> Result<u64, Error> {
+        let message_id = safe_random_u64();
+
+        if let Some(wal) = &self.wal {
+            let info = if self.sealed {
+                SealedMessageInfo::seal(&message_info, receiver)?.to_json()?
+            } else {
+                message_info.to_json()?
+            };
+
+            wal.add(message_id, receiver.clone(), message_info.channel, self.encrypt_stored(serde_json::to_vec(&info)?)?).await?;
+
+            return Ok(message_id);
+        }
+
+        let message_path = folder.join(message_id.to_string());
+
+        if self.sealed {
+            let sealed = SealedMessageInfo::seal(&message_info, receiver)?;
+
+            let metadata = SealedMetadata {
+                channel: sealed.channel.clone(),
+                received_at: sealed.received_at,
+                size: sealed.size() as u64
+            };
+
+            // Only the encrypted part of the sealed info is stored
+            // in the message file, the rest goes to the sidecar
+            let content = self.encrypt_stored(serde_json::to_vec(&sealed.to_json()?["sealed"])?)?;
+            let metadata_content = self.encrypt_stored(serde_json::to_vec(&metadata.to_json()?)?)?;
+
+            let size = (content.len() + metadata_content.len()) as u64;
+
+            self.reserve_quota(receiver, size).await?;
+
+            let result = async {
+                tokio::fs::create_dir_all(folder).await?;
+                tokio::fs::write(message_path.with_extension("meta"), metadata_content).await?;
+                tokio::fs::write(&message_path, content).await
+            }.await;
+
+            if let Err(err) = result {
+                self.release_quota(receiver, size);
+
+                return Err(err.into());
+            }
+
+            self.metadata.lock()
+                .expect("Failed to lock sealed messages metadata cache")
+                .insert(message_path, metadata);
+        }
+
+        else {
+            let content = self.encrypt_stored(serde_json::to_vec(&message_info.to_json()?)?)?;
+
+            let size = content.len() as u64;
+
+            self.reserve_quota(receiver, size).await?;
+
+            let result = async {
+                tokio::fs::create_dir_all(folder).await?;
+                tokio::fs::write(message_path, content).await
+            }.await;
+
+            if let Err(err) = result {
+                self.release_quota(receiver, size);
+
+                return Err(err.into());
+            }
+        }
+
+        Ok(message_id)
+    }
+
+    /// Get names of the receiver's channels sorted by name.
+    /// 
+    /// Folders which names can't be decoded
+    /// are not listed.
+    async fn receiver_channels(&self, receiver: &PublicKey) -> Result<Vec<ChannelName>, Error> {
+        if let Some(wal) = &self.wal {
+            let mut channels = wal.channels(receiver).await;
+
+            channels.sort();
+
+            return Ok(channels);
+        }
+
+        let mut channels = Vec::new();
+
+        for folder in self.layout.paths(&self.storage_folder, receiver) {
+            if !folder.exists() {
+                continue;
+            }
+
+            let mut entries = tokio::fs::read_dir(folder).await?;
+
+            while let Some(entry) = entries.next_entry().await? {
+                if !entry.file_type().await?.is_dir() {
+                    continue;
+                }
+
+                if let Some(channel) = self.folder_channel(&entry.file_name())? {
+                    channels.push(channel);
+                }
+            }
+        }
+
+        channels.sort();
+        channels.dedup();
+
+        Ok(channels)
+    }
+
+    /// Get name of the channel stored
+    /// in the folder with the given name.
+    fn folder_channel(&self, name: &std::ffi::OsStr) -> Result<Option<ChannelName>, Error> {
+        let channel = match &self.storage_key {
+            Some(key) => Some(key.decrypt_name(&name.to_string_lossy())?),
+
+            None => name.to_str()
+                .and_then(ChannelName::from_fs_name)
+        };
+
+        Ok(channel.filter(ChannelName::is_valid))
+    }
+
+    /// Poll messages from the receiver's channel.
+    /// 
+    /// Must be called holding the channel's lock.
+    /// Refer to `poll_messages`.
+    async fn poll_locked(&self, receiver: &PublicKey, channel: &ChannelName, sender: Option<&PublicKey>, range: Option<&Range<u64>>, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
+        if let Some(wal) = &self.wal {
+            channel.validate()?;
+
+            // Filtered messages can be anywhere in the log
+            let peek_limit = if sender.is_some() || range.is_some() { None } else { limit };
+
+            let (stored, _) = wal.peek(receiver, channel, peek_limit).await?;
+
+            let limit = limit.unwrap_or(u64::MAX) as usize;
+
+            let mut ids = Vec::with_capacity(stored.len());
+            let mut messages = Vec::with_capacity(stored.len());
+
+            for (message_id, info) in stored {
+                if messages.len() >= limit {
+                    break;
+                }
+
+                let info = serde_json::from_slice::<Json>(&self.decrypt_stored(info)?)?;
+
+                if info.get("sealed").is_some() {
+                    return Err(Error::SealedMessage);
+                }
+
+                let info = MessageInfo::from_json(&info)?
+                    .with_id(message_id);
+
+                if !matches_filters(&info, sender, range) {
+                    continue;
+                }
+
+                messages.push(info);
+                ids.push(message_id);
+            }
+
+            // Consume messages only when all of them were read
+            let remaining = wal.consume(receiver, channel, &ids).await?;
+
+            return Ok((messages, remaining));
+        }
+
+        let folder = self.open_channel(receiver, channel).await?;
+
+        if let Some(index) = Self::read_index(&folder).await {
+            // Drop expired messages before polling
+            let (expired, expired_files) = self.expired_prefix(&folder, &index).await?;
+
+            for message_path in &expired_files {
+                self.remove_message(message_path).await?;
+            }
+
+            let index = index[expired..].to_vec();
+
+            let mut limit = limit.unwrap_or(u64::MAX);
+            let mut shift = 0;
+
+            let mut messages = Vec::new();
+            let mut read_files = Vec::new();
+
+            // Filtered out messages stay in the index
+            let mut kept = Vec::new();
+
+            for message_id in &index {
+                if limit == 0 {
+                    break;
+                }
+
+                let message_path = folder.join(message_id.to_string());
+
+                if let Ok(message_info) = tokio::fs::read(&message_path).await {
+                    let message_info = serde_json::from_slice::<Json>(&self.decrypt_stored(message_info)?)?;
+
+                    if message_info.get("content").is_some() && message_info.get("public_key").is_some() {
+                        return Err(Error::SealedMessage);
+                    }
+
+                    let message_info = MessageInfo::from_json(&message_info)?
+                        .with_id(*message_id);
+
+                    if !matches_filters(&message_info, sender, range) {
+                        kept.push(*message_id);
+                    }
+
+                    else {
+                        messages.push(message_info);
+                        read_files.push(message_path);
+
+                        limit -= 1;
+                    }
+                }
+
+                shift += 1;
+            }
+
+            // Remove files only when all the messages were read
+            for message_path in read_files {
+                self.consume_message(receiver, &message_path).await?;
+            }
+
+            kept.extend_from_slice(&index[shift..]);
+
+            let index = kept;
+
+            Self::write_index(&folder, &index).await?;
+
+            self.invalidate_usage(receiver);
+
+            return Ok((
+                messages,
+                index.len() as u64
+            ));
+        }
+
+        Ok((vec![], 0))
+    }
+
+    /// Peek messages from the receiver's channel.
+    /// 
+    /// Must be called holding the channel's lock.
+    /// Refer to `peek_messages`.
+    async fn peek_locked(&self, receiver: &PublicKey, channel: &ChannelName, limit: Option<u64>) -> Result<Option<(Vec<MessageInfo>, u64)>, Error> {
+        if let Some(wal) = &self.wal {
+            channel.validate()?;
+
+            let (stored, total) = wal.peek(receiver, channel, limit).await?;
+
+            let mut messages = Vec::with_capacity(stored.len());
+
+            for (message_id, info) in stored {
+                let info = serde_json::from_slice::<Json>(&self.decrypt_stored(info)?)?;
+
+                if info.get("sealed").is_some() {
+                    return Err(Error::SealedMessage);
+                }
+
+                messages.push(MessageInfo::from_json(&info)?.with_id(message_id));
+            }
+
+            let remaining = total - messages.len() as u64;
+
+            return Ok(Some((messages, remaining)));
+        }
+
+        let folder = self.open_channel(receiver, channel).await?;
+
+        let Some(index) = Self::read_index(&folder).await else {
+            return Ok(Some((vec![], 0)));
+        };
+
+        // Expired messages are removed on poll
+        let (expired, _) = self.expired_prefix(&folder, &index).await?;
+
+        let index = index[expired..].to_vec();
+
+        let mut limit = limit.unwrap_or(u64::MAX);
+        let mut shift = 0;
+
+        let mut messages = Vec::new();
+
+        // Same as poll_messages but neither files
+        // nor the index are modified
+        for message_id in &index {
+            if limit == 0 {
+                break;
+            }
+
+            if let Ok(message_info) = tokio::fs::read(folder.join(message_id.to_string())).await {
+                let message_info = serde_json::from_slice::<Json>(&self.decrypt_stored(message_info)?)?;
+
+                if message_info.get("content").is_some() && message_info.get("public_key").is_some() {
+                    return Err(Error::SealedMessage);
+                }
+
+                messages.push(MessageInfo::from_json(&message_info)?.with_id(*message_id));
+
+                limit -= 1;
+            }
+
+            shift += 1;
+        }
+
+        Ok(Some((
+            messages,
+            (index.len() - shift) as u64
+        )))
+    }
+
+    /// Poll messages from all the receiver's
+    /// channels matched by the rule.
+    /// 
+    /// Channels are peeked first to merge their messages
+    /// by the priority and receiving time, and then the merged
+    /// amount of messages is polled from each of them.
+    /// 
+    /// All the matched channels are locked in the same order
+    /// for the whole time, so the messages can't be added or
+    /// polled by other requests between peeking and polling.
+    async fn poll_matching(&self, receiver: PublicKey, rule: ChannelRule, sender: Option<PublicKey>, range: Option<Range<u64>>, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
+        rule.validate()?;
+
+        let channels = self.receiver_channels(&receiver).await?
+            .into_iter()
+            .filter(|channel| rule.matches(channel))
+            .collect::<Vec<_>>();
+
+        // Channels are sorted, so concurrent requests
+        // lock them in the same order
+        let mut guards = Vec::with_capacity(channels.len());
+
+        for channel in &channels {
+            guards.push(self.lock_channel(&receiver, channel).await);
+        }
+
+        let mut totals = Vec::with_capacity(channels.len());
+        let mut times = Vec::with_capacity(channels.len());
+
+        for channel in &channels {
+            let (messages, _) = self.peek_locked(&receiver, channel, None).await?
+                .unwrap_or_default();
+
+            totals.push(messages.len() as u64);
+
+            times.push(messages.iter()
+                .filter(|info| matches_filters(info, sender.as_ref(), range.as_ref()))
+                .map(|info| info.received_at)
+                .collect::<Vec<_>>());
+        }
+
+        let priorities = channels.iter()
+            .map(|channel| self.priorities.get(channel))
+            .collect::<Vec<_>>();
+
+        let merged = merge_prioritized(&times, &priorities, limit);
+
+        let mut polled = Vec::with_capacity(channels.len());
+        let mut remaining = 0;
+
+        for (i, channel) in channels.into_iter().enumerate() {
+            let count = merged.iter()
+                .filter(|merged| **merged == i)
+                .count() as u64;
+
+            if count == 0 {
+                remaining += totals[i];
+
+                polled.push(VecDeque::new());
+
+                continue;
+            }
+
+            let (messages, left) = self.poll_locked(&receiver, &channel, sender.as_ref(), range.as_ref(), Some(count)).await?;
+
+            remaining += left;
+
+            polled.push(VecDeque::from(messages));
+        }
+
+        let messages = merged.into_iter()
+            .filter_map(|i| polled[i].pop_front())
+            .collect();
+
+        Ok((messages, remaining))
+    }
+
+    /// Walk the storage to collect its statistics.
+    async fn collect_stats(&self) -> Result<InboxStats, Error> {
+        let mut stats = InboxStats::default();
+        let mut receivers = HashSet::new();
+
+        let mut oldest = |received_at: u64| {
+            stats.oldest_message = Some(stats.oldest_message.map_or(received_at, |oldest| oldest.min(received_at)));
+        };
+
+        if let Some(wal) = &self.wal {
+            for (receiver, channel, messages, bytes) in wal.queues().await {
+                if messages == 0 {
+                    continue;
+                }
+
+                // Messages are stored in order they were received
+                let (first, _) = wal.peek(&receiver, &channel, Some(1)).await?;
+
+                for (_, info) in first {
+                    let received_at = serde_json::from_slice::<Json>(&self.decrypt_stored(info)?)?
+                        .get("received_at")
+                        .and_then(Json::as_u64)
+                        .ok_or_else(|| AsJsonError::FieldNotFound("received_at"))?;
+
+                    oldest(received_at);
+                }
+
+                stats.messages += messages;
+                stats.bytes += bytes;
+
+                receivers.insert(receiver);
+            }
+        }
+
+        else {
+            for (receiver, folder) in self.receiver_folders().await? {
+                let (messages, bytes) = Self::folder_usage(&folder).await?;
+
+                if messages == 0 {
+                    continue;
+                }
+
+                let mut channels = tokio::fs::read_dir(&folder).await?;
+
+                while let Some(channel) = channels.next_entry().await? {
+                    let folder = channel.path();
+
+                    for message_id in Self::read_index(&folder).await.unwrap_or_default() {
+                        if let Some(received_at) = self.received_at(&folder.join(message_id.to_string())).await? {
+                            oldest(received_at);
+
+                            break;
+                        }
+                    }
+                }
+
+                stats.messages += messages;
+                stats.bytes += bytes;
+
+                receivers.insert(receiver);
+            }
+        }
+
+        stats.receivers = receivers.len() as u64;
+
+        Ok(stats)
+    }
+
+    /// Read all the stored messages of the channel
+    /// with their ids, skipping expired ones.
+    async fn read_channel(&self, receiver: &PublicKey, channel: &ChannelName) -> Result<Vec<(u64, MessageInfo)>, Error> {
+        if let Some(wal) = &self.wal {
+            let (stored, _) = wal.peek(receiver, channel, None).await?;
+
+            let mut messages = Vec::with_capacity(stored.len());
+
+            for (message_id, info) in stored {
+                let info = serde_json::from_slice::<Json>(&self.decrypt_stored(info)?)?;
+
+                if info.get("sealed").is_some() {
+                    return Err(Error::SealedMessage);
+                }
+
+                messages.push((message_id, MessageInfo::from_json(&info)?));
+            }
+
+            return Ok(messages);
+        }
+
+        let _guard = self.lock_channel(receiver, channel).await;
+
+        let folder = self.open_channel(receiver, channel).await?;
+
+        let Some(index) = Self::read_index(&folder).await else {
+            return Ok(vec![]);
+        };
+
+        // Expired messages are removed on poll
+        let (expired, _) = self.expired_prefix(&folder, &index).await?;
+
+        let mut messages = Vec::with_capacity(index.len() - expired);
+
+        for message_id in &index[expired..] {
+            if let Ok(message_info) = tokio::fs::read(folder.join(message_id.to_string())).await {
+                let message_info = serde_json::from_slice::<Json>(&self.decrypt_stored(message_info)?)?;
+
+                if message_info.get("content").is_some() && message_info.get("public_key").is_some() {
+                    return Err(Error::SealedMessage);
+                }
+
+                messages.push((*message_id, MessageInfo::from_json(&message_info)?));
+            }
+        }
+
+        Ok(messages)
+    }
+
+    /// Read the next message of the streamed poll.
+    /// 
+    /// Message files are removed right after they're read,
+    /// while the channel's index is rewritten by batches and
+    /// when the stream ends. Messages of the write-ahead log
+    /// are read and consumed by batches after they were yielded.
+    async fn next_streamed(&self, poll: &mut StreamedPoll) -> Result<Option<MessageInfo>, Error> {
+        // Channel is locked only while reading the message
+        // so the stream doesn't block the new ones from adding
+        let _guard = self.lock_channel(&poll.receiver, &poll.channel).await;
+
+        if let Some(wal) = &self.wal {
+            if poll.buffered.is_empty() {
+                if !poll.yielded.is_empty() {
+                    wal.consume(&poll.receiver, &poll.channel, &poll.yielded).await?;
+
+                    poll.yielded.clear();
+                }
+
+                if poll.limit == 0 {
+                    return Ok(None);
+                }
+
+                poll.channel.validate()?;
+
+                let (stored, _) = wal.peek(&poll.receiver, &poll.channel, Some(poll.limit.min(STREAM_BATCH))).await?;
+
+                for (message_id, info) in stored {
+                    let info = serde_json::from_slice::<Json>(&self.decrypt_stored(info)?)?;
+
+                    if info.get("sealed").is_some() {
+                        return Err(Error::SealedMessage);
+                    }
+
+                    poll.buffered.push_back((message_id, MessageInfo::from_json(&info)?.with_id(message_id)));
+                }
+            }
+
+            let Some((message_id, info)) = poll.buffered.pop_front() else {
+                return Ok(None);
+            };
+
+            poll.yielded.push(message_id);
+            poll.limit -= 1;
+
+            return Ok(Some(info));
+        }
+
+        if poll.index.is_none() {
+            let folder = self.open_channel(&poll.receiver, &poll.channel).await?;
+
+            let Some(index) = Self::read_index(&folder).await else {
+                return Ok(None);
+            };
+
+            // Drop expired messages before polling
+            let (expired, expired_files) = self.expired_prefix(&folder, &index).await?;
+
+            for message_path in &expired_files {
+                self.remove_message(message_path).await?;
+            }
+
+            poll.index = Some((folder, index));
+            poll.position = expired;
+        }
+
+        let Some((folder, index)) = &poll.index else {
+            return Ok(None);
+        };
+
+        while poll.limit > 0 && poll.position < index.len() {
+            let message_path = folder.join(index[poll.position].to_string());
+
+            let Ok(message_info) = tokio::fs::read(&message_path).await else {
+                poll.position += 1;
+
+                continue;
+            };
+
+            let message_info = serde_json::from_slice::<Json>(&self.decrypt_stored(message_info)?)?;
+
+            if message_info.get("content").is_some() && message_info.get("public_key").is_some() {
+                return Err(Error::SealedMessage);
+            }
+
+            let message_info = MessageInfo::from_json(&message_info)?
+                .with_id(index[poll.position]);
+
+            self.consume_message(&poll.receiver, &message_path).await?;
+
+            poll.position += 1;
+            poll.limit -= 1;
+
+            if (poll.position - poll.flushed) as u64 >= STREAM_BATCH {
+                self.flush_streamed(poll).await?;
+            }
+
+            return Ok(Some(message_info));
+        }
+
+        self.flush_streamed(poll).await?;
+
+        Ok(None)
+    }
+
+    /// Remove messages read by the streamed
+    /// poll from the channel's index.
+    async fn flush_streamed(&self, poll: &mut StreamedPoll) -> Result<(), Error> {
+        let Some((folder, index)) = &poll.index else {
+            return Ok(());
+        };
+
+        if poll.flushed == poll.position {
+            return Ok(());
+        }
+
+        let read = index[poll.flushed..poll.position].iter()
+            .collect::<HashSet<_>>();
+
+        // New messages could be added to the index
+        // since the stream has started
+        let mut current = Self::read_index(folder).await
+            .unwrap_or_default();
+
+        current.retain(|message_id| !read.contains(message_id));
+
+        Self::write_index(folder, &current).await?;
+
+        self.invalidate_usage(&poll.receiver);
+
+        poll.flushed = poll.position;
+
+        Ok(())
+    }
+
+    /// Read message ids from the channel's index.
+    /// 
+    /// Index truncated by a crash is recovered up
+    /// to the last complete record.
+    async fn read_index(folder: &Path) -> Option<Vec<u64>> {
+        let mut index = tokio::fs::read(folder.join("index")).await.ok()?;
+
+        if index.len() % 8 != 0 {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                ?folder,
+                size = index.len(),
+                "Messages index is corrupted, truncating to the last complete record"
+            );
+
+            index.truncate(index.len() - index.len() % 8);
+        }
+
+        let mut bytes = [0; 8];
+
+        let ids = index.chunks(8)
+            .map(|message_id| {
+                bytes.copy_from_slice(message_id);
+
+                u64::from_be_bytes(bytes)
+            })
+            .collect();
+
+        Some(ids)
+    }
+
+    /// Write remaining message ids to the channel's index.
+    /// 
+    /// The index is written to a temporary file which then
+    /// replaces the original one, so it's never left partially
+    /// written.
+    async fn write_index(folder: &Path, ids: &[u64]) -> std::io::Result<()> {
+        let index = ids.iter()
+            .flat_map(|message_id| message_id.to_be_bytes())
+            .collect::<Vec<_>>();
+
+        let temp_path = folder.join("index.tmp");
+
+        tokio::fs::write(&temp_path, index).await?;
+        tokio::fs::rename(&temp_path, folder.join("index")).await
+    }
+
+    /// Get sealed message's metadata from the cache
+    /// or from its sidecar file.
+    async fn read_metadata(&self, message_path: &Path) -> Result<Option<SealedMetadata>, Error> {
+        let cached = self.metadata.lock()
+            .expect("Failed to lock sealed messages metadata cache")
+            .remove(message_path);
+
+        if let Some(metadata) = cached {
+            return Ok(Some(metadata));
+        }
+
+        match tokio::fs::read(message_path.with_extension("meta")).await {
+            Ok(metadata) => Ok(Some(SealedMetadata::from_json(&serde_json::from_slice(&self.decrypt_stored(metadata)?)?)?)),
+
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into())
+        }
+    }
+
+    /// Get timestamp when the stored message was received.
+    /// 
+    /// Return `None` if the message doesn't exist.
+    async fn received_at(&self, message_path: &Path) -> Result<Option<u64>, Error> {
+        let cached = self.metadata.lock()
+            .expect("Failed to lock sealed messages metadata cache")
+            .get(message_path)
+            .map(|metadata| metadata.received_at);
+
+        if let Some(received_at) = cached {
+            return Ok(Some(received_at));
+        }
+
+        let path = if message_path.with_extension("meta").exists() {
+            message_path.with_extension("meta")
+        } else {
+            message_path.to_path_buf()
+        };
+
+        match tokio::fs::read(path).await {
+            Ok(info) => Ok(Some(serde_json::from_slice::<Json>(&self.decrypt_stored(info)?)?
+                .get("received_at")
+                .and_then(Json::as_u64)
+                .ok_or_else(|| AsJsonError::FieldNotFound("received_at"))?)),
+
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into())
+        }
+    }
+
+    /// Find expired messages at the beginning of the channel's index.
+    /// 
+    /// Messages are indexed in order they were received,
+    /// so the search stops at the first unexpired one.
+    /// 
+    /// Return number of index entries to skip
+    /// and paths to the expired messages.
+    async fn expired_prefix(&self, folder: &Path, index: &[u64]) -> Result<(usize, Vec<PathBuf>), Error> {
+        let Some(ttl) = self.ttl else {
+            return Ok((0, vec![]));
+        };
+
+        let deadline = timestamp().saturating_sub(ttl.as_secs());
+
+        let mut shift = 0;
+        let mut expired = Vec::new();
+
+        for message_id in index {
+            let message_path = folder.join(message_id.to_string());
+
+            match self.received_at(&message_path).await? {
+                Some(received_at) if received_at >= deadline => break,
+
+                Some(_) => expired.push(message_path),
+
+                // Missing messages are skipped anyway
+                None => ()
+            }
+
+            shift += 1;
+        }
+
+        Ok((shift, expired))
+    }
+
+    /// Remove message and its sealed metadata files.
+    async fn remove_message(&self, message_path: &Path) -> Result<(), Error> {
+        self.metadata.lock()
+            .expect("Failed to lock sealed messages metadata cache")
+            .remove(message_path);
+
+        for path in [message_path.to_path_buf(), message_path.with_extension("meta")] {
+            if let Err(err) = tokio::fs::remove_file(path).await {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(err.into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove polled message, or move it
+    /// to the archive if it's enabled.
+    async fn consume_message(&self, receiver: &PublicKey, message_path: &Path) -> Result<(), Error> {
+        if self.archive.is_none() {
+            return self.remove_message(message_path).await;
+        }
+
+        let (Some(channel), Some(message_id)) = (message_path.parent().and_then(Path::file_name), message_path.file_name()) else {
+            return self.remove_message(message_path).await;
+        };
+
+        let folder = self.storage_folder.join("archive")
+            .join(receiver.to_base64())
+            .join(channel);
+
+        tokio::fs::create_dir_all(&folder).await?;
+
+        let archived = folder.join(format!("{}-{}", timestamp(), message_id.to_string_lossy()));
+
+        self.metadata.lock()
+            .expect("Failed to lock sealed messages metadata cache")
+            .remove(message_path);
+
+        tokio::fs::rename(message_path, &archived).await?;
+
+        if let Err(err) = tokio::fs::rename(message_path.with_extension("meta"), archived.with_extension("meta")).await {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                return Err(err.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove archived messages delivered
+    /// before the retention window.
+    /// 
+    /// This method is meant to be called periodically.
+    /// Return number of removed messages. Does nothing
+    /// if the archive is not enabled.
+    pub async fn expire_archive(&self) -> Result<u64, Error> {
+        let Some(archive) = &self.archive else {
+            return Ok(0);
+        };
+
+        let folder = self.storage_folder.join("archive");
+
+        if !tokio::fs::try_exists(&folder).await? {
+            return Ok(0);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(retention = ?archive.retention, "Removing expired archived messages from StoredQueueMessagesInbox");
+
+        let expire_before = timestamp().saturating_sub(archive.retention.as_secs());
+
+        let mut removed = 0;
+        let mut receivers = tokio::fs::read_dir(&folder).await?;
+
+        while let Some(receiver) = receivers.next_entry().await? {
+            let Ok(public_key) = PublicKey::from_base64(receiver.file_name().to_string_lossy().as_ref()) else {
+                continue;
+            };
+
+            let mut channels = tokio::fs::read_dir(receiver.path()).await?;
+
+            while let Some(channel) = channels.next_entry().await? {
+                if !channel.file_type().await?.is_dir() {
+                    continue;
+                }
+
+                // Messages are archived under the channel's lock
+                let _guard = match self.folder_channel(&channel.file_name()) {
+                    Ok(Some(name)) => Some(self.lock_channel(&public_key, &name).await),
+                    _ => None
+                };
+
+                let mut entries = tokio::fs::read_dir(channel.path()).await?;
+
+                while let Some(entry) = entries.next_entry().await? {
+                    let name = entry.file_name();
+                    let name = name.to_string_lossy();
+
+                    let delivered_at = name.split_once('-')
+                        .and_then(|(delivered_at, _)| delivered_at.parse::<u64>().ok());
+
+                    if delivered_at.is_some_and(|delivered_at| delivered_at <= expire_before) {
+                        tokio::fs::remove_file(entry.path()).await?;
+
+                        if !name.ends_with(".meta") {
+                            removed += 1;
+                        }
+                    }
+                }
+
+                if tokio::fs::read_dir(channel.path()).await?.next_entry().await?.is_none() {
+                    tokio::fs::remove_dir(channel.path()).await?;
+                }
+            }
+
+            if tokio::fs::read_dir(receiver.path()).await?.next_entry().await?.is_none() {
+                tokio::fs::remove_dir(receiver.path()).await?;
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(removed, "Removed expired archived messages");
+
+        Ok(removed)
+    }
+
+    /// Remove expired messages from all the receivers'
+    /// channels, as well as empty channel folders.
+    /// 
+    /// This method is meant to be called periodically
+    /// to drop messages which are never polled.
+    /// 
+    /// Return number of removed messages. Does nothing
+    /// if messages TTL is not set.
+    pub async fn cleanup_expired(&self) -> Result<u64, Error> {
+        if self.ttl.is_none() {
+            return Ok(0);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Removing expired messages from StoredQueueMessagesInbox");
+
+        let mut removed = 0;
+
+        for (public_key, receiver) in self.receiver_folders().await? {
+            let mut channels = tokio::fs::read_dir(&receiver).await?;
+
+            while let Some(channel) = channels.next_entry().await? {
+                if !channel.file_type().await?.is_dir() {
+                    continue;
+                }
+
+                let _guard = match self.folder_channel(&channel.file_name()) {
+                    Ok(Some(name)) => Some(self.lock_channel(&public_key, &name).await),
+                    _ => None
+                };
+
+                let folder = channel.path();
+
+                let index = Self::read_index(&folder).await
+                    .unwrap_or_default();
+
+                let (shift, expired) = self.expired_prefix(&folder, &index).await?;
+
+                for message_path in &expired {
+                    self.remove_message(message_path).await?;
+                }
+
+                removed += expired.len() as u64;
+
+                // Keep recent messages hashes within the window
+                if index.len() == shift && !folder.join("dedup").exists() {
+                    tokio::fs::remove_dir_all(&folder).await?;
+                }
+
+                else if shift > 0 {
+                    Self::write_index(&folder, &index[shift..]).await?;
+                }
+            }
+
+            if tokio::fs::read_dir(&receiver).await?.next_entry().await?.is_none() {
+                tokio::fs::remove_dir(&receiver).await?;
+            }
+        }
+
+        self.usage.lock()
+            .expect("Failed to lock inbox usage cache")
+            .clear();
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(removed, "Removed expired messages");
+
+        Ok(removed)
+    }
+
+    /// Move receivers' folders from the legacy flat layout
+    /// to the shards.
+    /// 
+    /// Channels are moved one by one holding their locks,
+    /// and merging of the channels which already exist in the
+    /// shards skips already merged messages, so this method
+    /// is safe to interrupt and run again. Unmigrated
+    /// receivers are still served from the legacy folders.
+    /// 
+    /// Return number of migrated receivers. Does nothing
+    /// when the flat layout is used.
+    pub async fn migrate_layout(&self) -> Result<u64, Error> {
+        if self.layout != StorageLayout::Sharded {
+            return Ok(0);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Migrating StoredQueueMessagesInbox to the sharded layout");
+
+        let mut entries = tokio::fs::read_dir(&self.storage_folder).await?;
+        let mut migrated = 0;
+
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let name = entry.file_name();
+
+            let Some(receiver) = name.to_str().and_then(|name| PublicKey::from_base64(name).ok()) else {
+                continue;
+            };
+
+            let target = StorageLayout::Sharded.path(&self.storage_folder, &receiver);
+
+            tokio::fs::create_dir_all(&target).await?;
+
+            let legacy = entry.path();
+            let mut channels = tokio::fs::read_dir(&legacy).await?;
+
+            while let Some(channel) = channels.next_entry().await? {
+                let _guard = match self.folder_channel(&channel.file_name()) {
+                    Ok(Some(name)) => Some(self.lock_channel(&receiver, &name).await),
+                    _ => None
+                };
+
+                let target_channel = target.join(channel.file_name());
+
+                if target_channel.exists() {
+                    self.merge_channel(&channel.path(), &target_channel).await?;
+                }
+
+                else {
+                    tokio::fs::rename(channel.path(), target_channel).await?;
+                }
+            }
+
+            tokio::fs::remove_dir_all(&legacy).await?;
+
+            self.metadata.lock()
+                .expect("Failed to lock sealed messages metadata cache")
+                .retain(|message_path, _| !message_path.starts_with(&legacy));
+
+            self.invalidate_usage(&receiver);
+
+            #[cfg(feature = "tracing")]
+            tracing::trace!(receiver = receiver.to_base64(), "Migrated receiver's folder");
+
+            migrated += 1;
+        }
+
+        Ok(migrated)
+    }
+
+    /// Move messages of the legacy channel's folder
+    /// into the already existing sharded one.
+    /// 
+    /// Must be called holding the channel's lock.
+    /// Messages already listed in the target index
+    /// were merged by an interrupted call and are skipped.
+    async fn merge_channel(&self, legacy: &Path, target: &Path) -> Result<(), Error> {
+        let target_index = Self::read_index(target).await
+            .unwrap_or_default();
+
+        let merged = target_index.iter()
+            .copied()
+            .collect::<HashSet<_>>();
+
+        // Legacy messages were sent earlier so they go first
+        let mut index = Self::read_index(legacy).await
+            .unwrap_or_default();
+
+        index.retain(|message_id| !merged.contains(message_id));
+
+        let mut files = tokio::fs::read_dir(legacy).await?;
+
+        while let Some(file) = files.next_entry().await? {
+            if !file.file_name().to_str().is_some_and(|name| SERVICE_FILES.contains(&name)) {
+                tokio::fs::rename(file.path(), target.join(file.file_name())).await?;
+            }
+        }
+
+        index.extend(target_index);
+
+        Self::write_index(target, &index).await?;
+        tokio::fs::remove_dir_all(legacy).await?;
+
+        self.metadata.lock()
+            .expect("Failed to lock sealed messages metadata cache")
+            .retain(|message_path, _| !message_path.starts_with(legacy));
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MessagesInbox for StoredQueueMessagesInbox {
+    type Error = Error;
+
+    async fn add_message(
+        &self,
+        sender: Sender,
+        receiver: PublicKey,
+        channel: ChannelName,
+        message: Message
+    ) -> Result<Option<u64>, Self::Error> {
+        let ids = self.add_messages(vec![(sender, receiver, channel, message)]).await?;
+
+        Ok(ids.into_iter().next().flatten())
+    }
+
+    async fn add_messages(&self, entries: Vec<(Sender, PublicKey, ChannelName, Message)>) -> Result<Vec<Option<u64>>, Self::Error> {
+        let entries = entries.into_iter()
+            .map(|(sender, receiver, channel, message)| (receiver, MessageInfo::now(sender, channel, message)))
+            .collect();
+
+        self.add_message_infos(entries).await
+    }
+
+    async fn add_message_info(&self, receiver: PublicKey, info: MessageInfo) -> Result<Option<u64>, Self::Error> {
+        let info = MessageInfo {
+            id: None,
+            ..info
+        };
+
+        let ids = self.add_message_infos(vec![(receiver, info)]).await?;
+
+        Ok(ids.into_iter().next().flatten())
+    }
+
+    async fn poll_messages(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelRule,
+        sender: Option<PublicKey>,
+        range: Option<Range<u64>>,
+        limit: Option<u64>
+    ) -> Result<(Vec<MessageInfo>, u64), Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = %channel,
+            sender = sender.as_ref().map(PublicKey::to_base64),
+            ?range,
+            limit,
+            "Polling messages"
+        );
+
+        let channel = match channel {
+            ChannelRule::Exact(channel) => channel,
+            rule => return self.poll_matching(receiver, rule, sender, range, limit).await
+        };
+
+        let _guard = self.lock_channel(&receiver, &channel).await;
+
+        self.poll_locked(&receiver, &channel, sender.as_ref(), range.as_ref(), limit).await
+    }
+
+    fn poll_messages_stream(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelName,
+        limit: Option<u64>
+    ) -> Pin<Box<dyn Stream<Item = Result<MessageInfo, Self::Error>> + Send + '_>> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = channel.as_str(),
+            limit,
+            "Streaming messages"
+        );
+
+        let poll = StreamedPoll {
+            receiver,
+            channel,
+            limit: limit.unwrap_or(u64::MAX),
+            index: None,
+            position: 0,
+            flushed: 0,
+            buffered: VecDeque::new(),
+            yielded: Vec::new()
+        };
+
+        // Stream ends after the first error
+        Box::pin(futures_util::stream::unfold(Some(poll), move |poll| async move {
+            let mut poll = poll?;
+
+            match self.next_streamed(&mut poll).await {
+                Ok(Some(message)) => Some((Ok(message), Some(poll))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None))
+            }
+        }))
+    }
+
+    async fn peek_messages(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelName,
+        limit: Option<u64>
+    ) -> Result<Option<(Vec<MessageInfo>, u64)>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = channel.as_str(),
+            limit,
+            "Peeking messages"
+        );
+
+        let _guard = self.lock_channel(&receiver, &channel).await;
+
+        self.peek_locked(&receiver, &channel, limit).await
+    }
+
+    async fn lease_messages(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelRule,
+        sender: Option<PublicKey>,
+        range: Option<Range<u64>>,
+        limit: Option<u64>,
+        lease: Duration
+    ) -> Result<Option<(Vec<MessageInfo>, u64)>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = %channel,
+            sender = sender.as_ref().map(PublicKey::to_base64),
+            ?range,
+            limit,
+            ?lease,
+            "Leasing messages"
+        );
+
+        channel.validate()?;
+
+        let channels = match &channel {
+            ChannelRule::Exact(channel) => vec![channel.clone()],
+
+            rule => self.receiver_channels(&receiver).await?
+                .into_iter()
+                .filter(|channel| rule.matches(channel))
+                .collect()
+        };
+
+        let mut stored = Vec::with_capacity(channels.len());
+
+        for channel in &channels {
+            stored.push(self.read_channel(&receiver, channel).await?);
+        }
+
+        let now = Instant::now();
+
+        let mut leases = self.leases.lock()
+            .expect("Failed to lock messages leases");
+
+        // Forget leases of the removed messages
+        let stored_ids = stored.iter()
+            .flatten()
+            .map(|(message_id, _)| *message_id)
+            .collect::<HashSet<_>>();
+
+        leases.retain(|(lease_receiver, message_id), lease| {
+            lease_receiver != &receiver || !channels.contains(&lease.channel) || stored_ids.contains(message_id)
+        });
+
+        // Filtered out and leased messages are skipped
+        let candidates = stored.iter()
+            .map(|messages| messages.iter()
+                .filter(|(message_id, _)| leases.get(&(receiver.clone(), *message_id)).is_none_or(|lease| lease.until <= now))
+                .filter(|(_, info)| matches_filters(info, sender.as_ref(), range.as_ref()))
+                .collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let times = candidates.iter()
+            .map(|candidates| candidates.iter()
+                .map(|(_, info)| info.received_at)
+                .collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        let priorities = channels.iter()
+            .map(|channel| self.priorities.get(channel))
+            .collect::<Vec<_>>();
+
+        let mut taken = vec![0; channels.len()];
+        let mut messages = Vec::new();
+
+        for i in merge_prioritized(&times, &priorities, limit) {
+            let (message_id, info) = candidates[i][taken[i]];
+
+            taken[i] += 1;
+
+            leases.insert((receiver.clone(), *message_id), MessageLease {
+                channel: channels[i].clone(),
+                until: now + lease
+            });
+
+            messages.push(info.clone().with_id(*message_id));
+        }
+
+        let remaining = stored_ids.iter()
+            .filter(|message_id| leases.get(&(receiver.clone(), **message_id)).is_none_or(|lease| lease.until <= now))
+            .count() as u64;
+
+        Ok(Some((messages, remaining)))
+    }
+
+    async fn ack_messages(&self, receiver: PublicKey, ids: Vec<u64>) -> Result<Option<u64>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            ids = ids.len(),
+            "Acknowledging messages"
+        );
+
+        // Group acknowledged messages by their channels
+        let mut channels = HashMap::<ChannelName, Vec<u64>>::new();
+
+        {
+            let mut leases = self.leases.lock()
+                .expect("Failed to lock messages leases");
+
+            for message_id in ids {
+                if let Some(lease) = leases.remove(&(receiver.clone(), message_id)) {
+                    channels.entry(lease.channel)
+                        .or_default()
+                        .push(message_id);
+                }
+            }
+        }
+
+        let mut acknowledged = 0;
+
+        for (channel, ids) in channels {
+            let _guard = self.lock_channel(&receiver, &channel).await;
+
+            if let Some(wal) = &self.wal {
+                // Messages could be already polled without a lease
+                let (stored, _) = wal.peek(&receiver, &channel, None).await?;
+
+                let ids = stored.into_iter()
+                    .map(|(message_id, _)| message_id)
+                    .filter(|message_id| ids.contains(message_id))
+                    .collect::<Vec<_>>();
+
+                wal.consume(&receiver, &channel, &ids).await?;
+
+                acknowledged += ids.len() as u64;
+
+                continue;
+            }
+
+            let folder = self.open_channel(&receiver, &channel).await?;
+
+            let Some(index) = Self::read_index(&folder).await else {
+                continue;
+            };
+
+            let kept = index.iter()
+                .filter(|message_id| !ids.contains(message_id))
+                .copied()
+                .collect::<Vec<_>>();
+
+            for message_id in &ids {
+                let message_path = folder.join(message_id.to_string());
+
+                // Messages could be already polled without a lease
+                if message_path.exists() {
+                    self.consume_message(&receiver, &message_path).await?;
+                }
+            }
+
+            Self::write_index(&folder, &kept).await?;
+
+            self.invalidate_usage(&receiver);
+
+            acknowledged += (index.len() - kept.len()) as u64;
+        }
+
+        Ok(Some(acknowledged))
+    }
+
+    async fn list_channels(&self, receiver: PublicKey) -> Result<Vec<(ChannelName, u64)>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            "Listing channels"
+        );
+
+        let mut channels = Vec::new();
+
+        for channel in self.receiver_channels(&receiver).await? {
+            let messages = match &self.wal {
+                Some(wal) => wal.peek(&receiver, &channel, Some(0)).await?.1,
+
+                None => {
+                    let _guard = self.lock_channel(&receiver, &channel).await;
+
+                    let folder = self.open_channel(&receiver, &channel).await?;
+
+                    let index = Self::read_index(&folder).await
+                        .unwrap_or_default();
+
+                    // Expired messages are removed on poll
+                    let (expired, _) = self.expired_prefix(&folder, &index).await?;
+
+                    (index.len() - expired) as u64
+                }
+            };
+
+            if messages > 0 {
+                channels.push((channel, messages));
+            }
+        }
+
+        Ok(channels)
+    }
+
+    async fn purge(&self, receiver: PublicKey, channel: Option<ChannelName>) -> Result<(), Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = channel.as_ref().map(ChannelName::as_str),
+            "Purging messages"
+        );
+
+        let channels = match &channel {
+            Some(channel) => {
+                channel.validate()?;
+
+                vec![channel.clone()]
+            }
+
+            None => self.receiver_channels(&receiver).await?
+        };
+
+        let mut guards = Vec::with_capacity(channels.len());
+
+        for channel in &channels {
+            guards.push(self.lock_channel(&receiver, channel).await);
+        }
+
+        if let Some(wal) = &self.wal {
+            for channel in &channels {
+                let (stored, _) = wal.peek(&receiver, channel, None).await?;
+
+                let ids = stored.into_iter()
+                    .map(|(message_id, _)| message_id)
+                    .collect::<Vec<_>>();
+
+                wal.consume(&receiver, channel, &ids).await?;
+            }
+        }
+
+        else {
+            let folders = match &channel {
+                Some(channel) => vec![self.open_channel(&receiver, channel).await?],
+
+                // Legacy flat folder can be left by the sharded layout
+                None => vec![
+                    self.layout.path(&self.storage_folder, &receiver),
+                    StorageLayout::Flat.path(&self.storage_folder, &receiver)
+                ]
+            };
+
+            for folder in folders {
+                if let Err(err) = tokio::fs::remove_dir_all(&folder).await {
+                    if err.kind() != std::io::ErrorKind::NotFound {
+                        return Err(err.into());
+                    }
+                }
+
+                self.metadata.lock()
+                    .expect("Failed to lock sealed messages metadata cache")
+                    .retain(|message_path, _| !message_path.starts_with(&folder));
+            }
+
+            self.invalidate_usage(&receiver);
+        }
+
+        self.leases.lock()
+            .expect("Failed to lock messages leases")
+            .retain(|(lease_receiver, _), lease| {
+                lease_receiver != &receiver || channel.as_ref().is_some_and(|channel| &lease.channel != channel)
+            });
+
+        Ok(())
+    }
+
+    #[inline]
+    async fn cleanup(&self) -> Result<u64, Self::Error> {
+        Ok(self.cleanup_expired().await? + self.expire_archive().await?)
+    }
+
+    async fn stats(&self) -> Result<InboxStats, Self::Error> {
+        let cached = self.stats.lock()
+            .expect("Failed to lock inbox stats cache")
+            .filter(|(collected_at, _)| self.stats_cache.is_some_and(|ttl| collected_at.elapsed() < ttl));
+
+        if let Some((_, stats)) = cached {
+            return Ok(stats);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Collecting StoredQueueMessagesInbox stats");
+
+        let stats = self.collect_stats().await?;
+
+        if self.stats_cache.is_some() {
+            *self.stats.lock().expect("Failed to lock inbox stats cache") = Some((Instant::now(), stats));
+        }
+
+        Ok(stats)
+    }
+
+    async fn list_receivers(&self) -> Result<Vec<PublicKey>, Self::Error> {
+        let mut receivers = HashSet::new();
+
+        if let Some(wal) = &self.wal {
+            for (receiver, _, messages, _) in wal.queues().await {
+                if messages > 0 {
+                    receivers.insert(receiver);
+                }
+            }
+        }
+
+        else {
+            for (receiver, folder) in self.receiver_folders().await? {
+                if Self::folder_usage(&folder).await?.0 > 0 {
+                    receivers.insert(receiver);
+                }
+            }
+        }
+
+        Ok(receivers.into_iter().collect())
+    }
+
+    fn set_observer(&mut self, observer: Arc<dyn InboxObserver>) {
+        self.observer = Some(SharedObserver(observer));
+    }
+
+    fn error_status(&self, error: &Self::Error) -> ResponseStatus {
+        match error {
+            Error::QuotaExceeded { .. }   => ResponseStatus::ClientInboxFull,
+            Error::ChannelFull { .. }     => ResponseStatus::ClientInboxFull,
+            Error::MessageTooLarge { .. } => ResponseStatus::MessageTooLarge,
+            Error::InvalidChannel(_)      => ResponseStatus::InvalidChannelName,
+
+            _ => ResponseStatus::ServerError
+        }
+    }
+
+    async fn poll_sealed_messages(
+        &self,
+        receiver: PublicKey,
+        channel: ChannelName,
+        limit: Option<u64>
+    ) -> Result<Option<(Vec<SealedMessageInfo>, u64)>, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            receiver = receiver.to_base64(),
+            channel = channel.as_str(),
+            limit,
+            "Polling sealed messages"
+        );
+
+        let _guard = self.lock_channel(&receiver, &channel).await;
+
+        if let Some(wal) = &self.wal {
+            channel.validate()?;
+
+            let (stored, _) = wal.peek(&receiver, &channel, limit).await?;
+
+            let mut ids = Vec::with_capacity(stored.len());
+            let mut messages = Vec::with_capacity(stored.len());
+
+            for (message_id, info) in stored {
+                let info = serde_json::from_slice::<Json>(&self.decrypt_stored(info)?)?;
+
+                let sealed = if info.get("sealed").is_some() {
+                    SealedMessageInfo::from_json(&info)?
+                } else {
+                    SealedMessageInfo::seal(&MessageInfo::from_json(&info)?, &receiver)?
+                };
+
+                messages.push(sealed);
+                ids.push(message_id);
+            }
+
+            let remaining = wal.consume(&receiver, &channel, &ids).await?;
+
+            return Ok(Some((messages, remaining)));
+        }
+
+        let folder = self.open_channel(&receiver, &channel).await?;
+
+        let Some(index) = Self::read_index(&folder).await else {
+            return Ok(Some((vec![], 0)));
+        };
+
+        // Drop expired messages before polling
+        let (expired, expired_files) = self.expired_prefix(&folder, &index).await?;
+
+        for message_path in &expired_files {
+            self.remove_message(message_path).await?;
+        }
+
+        let index = index[expired..].to_vec();
+
+        let mut limit = limit.unwrap_or(u64::MAX);
+        let mut shift = 0;
+
+        let mut messages = Vec::new();
+        let mut read_files = Vec::new();
+
+        for message_id in &index {
+            if limit == 0 {
+                break;
+            }
+
+            let message_path = folder.join(message_id.to_string());
+
+            if let Ok(content) = tokio::fs::read(&message_path).await {
+                let content = serde_json::from_slice::<Json>(&self.decrypt_stored(content)?)?;
+
+                let sealed = match self.read_metadata(&message_path).await? {
+                    Some(metadata) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::trace!(
+                            message_id,
+                            size = metadata.size,
+                            "Read sealed message"
+                        );
+
+                        SealedMessageInfo::from_json(&json!({
+                            "channel": metadata.channel.to_json()?,
+                            "received_at": metadata.received_at,
+                            "sealed": content
+                        }))?
+                    }
+
+                    // Messages stored before sealing was enabled
+                    None => SealedMessageInfo::seal(&MessageInfo::from_json(&content)?, &receiver)?
+                };
+
+                messages.push(sealed);
+                read_files.push(message_path);
+
+                limit -= 1;
+            }
+
+            shift += 1;
+        }
+
+        // Remove files only when all the messages were read
+        for message_path in read_files {
+            self.consume_message(&receiver, &message_path).await?;
+        }
+
+        let index = &index[shift..];
+
+        Self::write_index(&folder, index).await?;
+
+        self.invalidate_usage(&receiver);
+
+        Ok(Some((
+            messages,
+            index.len() as u64
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use crate::drivers::server::messages_inbox::test_suite::{send_poll_suite, peek_suite, sender_filter_suite, wildcard_suite, list_channels_suite, add_messages_suite, stats_suite, stream_suite, purge_suite, observer_suite, range_filter_suite, fifo_suite, limits_suite, empty_channels_suite, interleaved_channels_suite};
+
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    async fn prepare_folder(name: &str) -> std::io::Result<PathBuf> {
+        let temp = std::env::temp_dir()
+            .join(name);
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        tokio::fs::create_dir(&temp).await?;
+
+        Ok(temp)
+    }
+
+    #[tokio::test]
+    async fn send_poll() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-test").await?;
+
+        send_poll_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn send_poll_sharded() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-sharded-test").await?;
+
+        send_poll_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn peek_sharded() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-peek-sharded-test").await?;
+
+        peek_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn sender_filter_sharded() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-sender-filter-sharded-test").await?;
+
+        sender_filter_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn wildcard_sharded() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-wildcard-sharded-test").await?;
+
+        wildcard_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn list_channels_sharded() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-list-channels-sharded-test").await?;
+
+        list_channels_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn add_messages_sharded() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-add-messages-sharded-test").await?;
+
+        add_messages_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn stats_sharded() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-stats-sharded-test").await?;
+
+        stats_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn send_poll_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-wal-test").await?;
+
+        send_poll_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    #[tokio::test]
+    async fn conformance() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-conformance-test").await?;
+
+        fifo_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await?;
+        limits_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await?;
+        empty_channels_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await?;
+        interleaved_channels_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn conformance_sharded() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-conformance-sharded-test").await?;
+
+        fifo_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await?;
+        limits_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await?;
+        empty_channels_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await?;
+        interleaved_channels_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn conformance_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-conformance-wal-test").await?;
+
+        fifo_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await?;
+        limits_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await?;
+        empty_channels_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await?;
+        interleaved_channels_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    #[tokio::test]
+    async fn peek() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-peek-test").await?;
+
+        peek_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn peek_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-peek-wal-test").await?;
+
+        peek_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    #[tokio::test]
+    async fn sender_filter() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-sender-filter-test").await?;
+
+        sender_filter_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn sender_filter_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-sender-filter-wal-test").await?;
+
+        sender_filter_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    #[tokio::test]
+    async fn wildcard() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-wildcard-test").await?;
+
+        wildcard_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn wildcard_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-wildcard-wal-test").await?;
+
+        wildcard_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    #[tokio::test]
+    async fn list_channels() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-list-channels-test").await?;
+
+        list_channels_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn list_channels_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-list-channels-wal-test").await?;
+
+        list_channels_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    #[tokio::test]
+    async fn add_messages() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-add-messages-test").await?;
+
+        add_messages_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_dedup_window(Duration::from_secs(60))
+            .with_channel_limit(3, EvictionPolicy::RejectNew);
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let entries = ["1", "2", "1", "3", "4", "5"].into_iter()
+            .map(|text| (sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), Message::new(text, "sign", MessageEncoding::default())))
+            .collect();
+
+        // Duplicates are dropped within the batch, and
+        // messages stored before the error are kept
+        assert!(matches!(inbox.add_messages(entries).await, Err(Error::ChannelFull { messages: 3 })));
+
+        let (messages, 0) = inbox.poll_messages(receiver, ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Batch wasn't stored");
+        };
+
+        assert_eq!(messages.into_iter().map(|info| info.message.content).collect::<Vec<_>>(), ["1", "2", "3"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_messages_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-add-messages-wal-test").await?;
+
+        add_messages_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    async fn poll_texts(inbox: &StoredQueueMessagesInbox, receiver: &SecretKey, sender: &PublicKey) -> Result<Vec<Vec<u8>>, Error> {
+        let (poll, 0) = inbox.poll_messages(receiver.public_key(), ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("All the messages must be polled");
+        };
+
+        Ok(poll.into_iter()
+            .map(|info| info.message.read(receiver, sender).unwrap())
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn wal_crash() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-wal-crash-test").await?;
+
+        let sender_secret = SecretKey::random();
+        let receiver_secret = SecretKey::random();
+
+        let sender = Sender::new(get_client(), get_server());
+
+        let add = |inbox: StoredQueueMessagesInbox, text: &'static [u8]| {
+            let message = Message::create(
+                &sender_secret,
+                &receiver_secret.public_key(),
+                text,
+                MessageEncoding::default(),
+                CompressionLevel::default()
+            ).unwrap();
+
+            let sender = sender.clone();
+            let receiver = receiver_secret.public_key();
+
+            async move {
+                inbox.add_message(sender, receiver, ChannelName::new("channel").unwrap(), message).await
+            }
+        };
+
+        let inbox = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?;
+
+        for text in [b"message 1", b"message 2", b"message 3", b"message 4", b"message 5"] {
+            add(inbox.clone(), text).await?;
+        }
+
+        let (poll, 3) = inbox.poll_messages(receiver_secret.public_key(), ChannelName::new("channel").unwrap().into(), None, None, Some(2)).await? else {
+            panic!("Failed to poll messages");
+        };
+
+        assert_eq!(poll.len(), 2);
+
+        let log_path = temp.join("wal")
+            .join(format!("{}.log", StorageLayout::shard(&receiver_secret.public_key())));
+
+        let acked = tokio::fs::read(&log_path).await?;
+
+        // Get a record of the message which wasn't acked
+        add(inbox, b"message 6").await?;
+
+        let unacked = tokio::fs::read(&log_path).await?;
+
+        let expected = [b"message 3".to_vec(), b"message 4".to_vec(), b"message 5".to_vec()];
+
+        // Crash at every byte of the record
+        for len in acked.len()..unacked.len() {
+            tokio::fs::write(&log_path, &unacked[..len]).await?;
+
+            let inbox = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?;
+
+            assert_eq!(poll_texts(&inbox, &receiver_secret, &sender_secret.public_key()).await?, expected);
+        }
+
+        // Replayed log accepts new messages after the torn tail
+        tokio::fs::write(&log_path, &unacked[..unacked.len() - 1]).await?;
+
+        let inbox = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?;
+
+        add(inbox, b"message 7").await?;
+
+        let inbox = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?;
+
+        assert_eq!(poll_texts(&inbox, &receiver_secret, &sender_secret.public_key()).await?, [
+            b"message 3".to_vec(),
+            b"message 4".to_vec(),
+            b"message 5".to_vec(),
+            b"message 7".to_vec()
+        ]);
+
+        // Polled messages are never replayed
+        let inbox = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?;
+
+        assert_eq!(inbox.poll_messages(receiver_secret.public_key(), ChannelName::new("channel").unwrap().into(), None, None, None).await?, (vec![], 0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn wal_compact() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-wal-compact-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Never).await?;
+
+        let sender_secret = SecretKey::random();
+        let receiver_secret = SecretKey::random();
+
+        let sender = Sender::new(get_client(), get_server());
+
+        for text in [b"message 1", b"message 2", b"message 3", b"message 4", b"message 5"] {
+            let message = Message::create(
+                &sender_secret,
+                &receiver_secret.public_key(),
+                text,
+                MessageEncoding::default(),
+                CompressionLevel::default()
+            ).unwrap();
+
+            inbox.add_message(sender.clone(), receiver_secret.public_key(), ChannelName::new("channel").unwrap(), message).await?;
+        }
+
+        inbox.poll_messages(receiver_secret.public_key(), ChannelName::new("channel").unwrap().into(), None, None, Some(3)).await?;
+
+        let log_path = temp.join("wal")
+            .join(format!("{}.log", StorageLayout::shard(&receiver_secret.public_key())));
+
+        let len = tokio::fs::metadata(&log_path).await?.len();
+
+        // 3 consumed messages and 1 consume record
+        assert_eq!(inbox.compact_wal().await?, 4);
+        assert_eq!(inbox.compact_wal().await?, 0);
+
+        assert!(tokio::fs::metadata(&log_path).await?.len() < len);
+
+        let (poll, 1) = inbox.poll_messages(receiver_secret.public_key(), ChannelName::new("channel").unwrap().into(), None, None, Some(1)).await? else {
+            panic!("Failed to poll compacted message");
+        };
+
+        assert_eq!(poll[0].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 4");
+
+        let inbox = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Never).await?;
+
+        assert_eq!(poll_texts(&inbox, &receiver_secret, &sender_secret.public_key()).await?, [b"message 5".to_vec()]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrate_to_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-wal-migrate-test").await?;
+
+        let flat = StoredQueueMessagesInbox::new(&temp, None).await?;
+        let sharded = StoredQueueMessagesInbox::new_sealed(&temp, StorageLayout::Sharded).await?;
+
+        let sender_secret = SecretKey::random();
+        let sender = Sender::new(get_client(), get_server());
+
+        let receivers = [SecretKey::random(), SecretKey::random()];
+
+        let message = |receiver: &SecretKey, text: &[u8]| Message::create(
+            &sender_secret,
+            &receiver.public_key(),
+            text,
+            MessageEncoding::default(),
+            CompressionLevel::default()
+        ).unwrap();
+
+        flat.add_message(sender.clone(), receivers[0].public_key(), ChannelName::new("channel").unwrap(), message(&receivers[0], b"message 1")).await?;
+        flat.add_message(sender.clone(), receivers[0].public_key(), ChannelName::new("channel").unwrap(), message(&receivers[0], b"message 2")).await?;
+        sharded.add_message(sender.clone(), receivers[1].public_key(), ChannelName::new("channel").unwrap(), message(&receivers[1], b"message 3")).await?;
+
+        let wal = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?;
+
+        assert_eq!(wal.migrate_to_wal().await?, 3);
+        assert_eq!(wal.migrate_to_wal().await?, 0);
+
+        assert!(!StorageLayout::Flat.path(&temp, &receivers[0].public_key()).exists());
+        assert!(!StorageLayout::Sharded.path(&temp, &receivers[1].public_key()).exists());
+
+        let wal = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?;
+
+        assert_eq!(poll_texts(&wal, &receivers[0], &sender_secret.public_key()).await?, [
+            b"message 1".to_vec(),
+            b"message 2".to_vec()
+        ]);
+
+        // Sealed messages stay sealed
+        let Some((poll, 0)) = wal.poll_sealed_messages(receivers[1].public_key(), ChannelName::new("channel").unwrap(), None).await? else {
+            panic!("Failed to poll sealed message");
+        };
+
+        assert_eq!(poll[0].open(&receivers[1])?.message.read(&receivers[1], &sender_secret.public_key()).unwrap(), b"message 3");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrate_layout() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-migrate-test").await?;
+
+        let flat = StoredQueueMessagesInbox::new(&temp, None).await?;
+        let sharded = StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?;
+
+        let sender_secret = SecretKey::random();
+        let sender = Sender::new(get_client(), get_server());
+
+        let receivers = [SecretKey::random(), SecretKey::random()];
+
+        let message = |receiver: &SecretKey, text: &[u8]| Message::create(
+            &sender_secret,
+            &receiver.public_key(),
+            text,
+            MessageEncoding::default(),
+            CompressionLevel::default()
+        ).unwrap();
+
+        // Half-migrated storage: first receiver is sharded, second is legacy
+
+        flat.add_message(sender.clone(), receivers[0].public_key(), ChannelName::new("channel").unwrap(), message(&receivers[0], b"message 1")).await?;
+
+        assert_eq!(sharded.migrate_layout().await?, 1);
+
+        flat.add_message(sender.clone(), receivers[1].public_key(), ChannelName::new("channel").unwrap(), message(&receivers[1], b"message 2")).await?;
+
+        assert!(StorageLayout::Sharded.path(&temp, &receivers[0].public_key()).exists());
+        assert!(StorageLayout::Flat.path(&temp, &receivers[1].public_key()).exists());
+
+        // Legacy folder is served transparently
+
+        sharded.add_message(sender.clone(), receivers[1].public_key(), ChannelName::new("channel").unwrap(), message(&receivers[1], b"message 3")).await?;
+
+        let (poll, 1) = sharded.poll_messages(receivers[1].public_key(), ChannelName::new("channel").unwrap().into(), None, None, Some(1)).await? else {
+            panic!("Test 1 failed");
+        };
+
+        assert_eq!(poll[0].message.read(&receivers[1], &sender_secret.public_key()).unwrap(), b"message 2");
+
+        let (poll, 0) = sharded.poll_messages(receivers[0].public_key(), ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Test 2 failed");
+        };
+
+        assert_eq!(poll[0].message.read(&receivers[0], &sender_secret.public_key()).unwrap(), b"message 1");
+
+        // Finish migration
+
+        assert_eq!(sharded.migrate_layout().await?, 1);
+        assert_eq!(sharded.migrate_layout().await?, 0);
+
+        assert!(!StorageLayout::Flat.path(&temp, &receivers[1].public_key()).exists());
+
+        let (poll, 0) = sharded.poll_messages(receivers[1].public_key(), ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Test 3 failed");
+        };
+
+        assert_eq!(poll[0].message.read(&receivers[1], &sender_secret.public_key()).unwrap(), b"message 3");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn half_migrated_layout() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-half-migrated-test").await?;
+
+        let flat = StoredQueueMessagesInbox::new(&temp, None).await?;
+        let sharded = StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?;
+
+        let sender_secret = SecretKey::random();
+        let sender = Sender::new(get_client(), get_server());
+
+        let receiver = SecretKey::random();
+
+        let message = |text: &[u8]| Message::create(
+            &sender_secret,
+            &receiver.public_key(),
+            text,
+            MessageEncoding::default(),
+            CompressionLevel::default()
+        ).unwrap();
+
+        // Channel "a" is legacy, "b" is sharded and "c" is stored in both layouts
+        sharded.add_message(sender.clone(), receiver.public_key(), ChannelName::new("b").unwrap(), message(b"message 1")).await?;
+        sharded.add_message(sender.clone(), receiver.public_key(), ChannelName::new("c").unwrap(), message(b"message 2")).await?;
+
+        flat.add_message(sender.clone(), receiver.public_key(), ChannelName::new("a").unwrap(), message(b"message 3")).await?;
+        flat.add_message(sender.clone(), receiver.public_key(), ChannelName::new("c").unwrap(), message(b"message 4")).await?;
+
+        let (peek, 0) = sharded.peek_messages(receiver.public_key(), ChannelName::new("c").unwrap(), None).await?.unwrap() else {
+            panic!("Failed to peek messages of both layouts");
+        };
+
+        assert_eq!(peek.len(), 2);
+
+        assert_eq!(sharded.list_channels(receiver.public_key()).await?, [
+            (ChannelName::new("a").unwrap(), 1),
+            (ChannelName::new("b").unwrap(), 1),
+            (ChannelName::new("c").unwrap(), 2)
+        ]);
+
+        let (poll, 0) = sharded.poll_messages(receiver.public_key(), ChannelRule::parse("*"), None, None, None).await? else {
+            panic!("Failed to poll messages of both layouts");
+        };
+
+        let mut texts = poll.iter()
+            .map(|info| info.message.read(&receiver, &sender_secret.public_key()).unwrap())
+            .collect::<Vec<_>>();
+
+        texts.sort();
+
+        assert_eq!(texts, [
+            b"message 1".to_vec(),
+            b"message 2".to_vec(),
+            b"message 3".to_vec(),
+            b"message 4".to_vec()
+        ]);
+
+        assert_eq!(sharded.migrate_layout().await?, 1);
+
+        assert!(!StorageLayout::Flat.path(&temp, &receiver.public_key()).exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrate_layout_resume() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-migrate-resume-test").await?;
+
+        let flat = StoredQueueMessagesInbox::new(&temp, None).await?;
+        let sharded = StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?;
+
+        let sender_secret = SecretKey::random();
+        let sender = Sender::new(get_client(), get_server());
+
+        let channel = ChannelName::new("channel").unwrap();
+        let receivers = [SecretKey::random(), SecretKey::random()];
+
+        for (i, receiver) in receivers.iter().enumerate() {
+            let message = |text: &[u8]| Message::create(
+                &sender_secret,
+                &receiver.public_key(),
+                text,
+                MessageEncoding::default(),
+                CompressionLevel::default()
+            ).unwrap();
+
+            // Same channel is stored in both layouts
+            sharded.add_message(sender.clone(), receiver.public_key(), channel.clone(), message(b"message 3")).await?;
+
+            flat.add_message(sender.clone(), receiver.public_key(), channel.clone(), message(b"message 1")).await?;
+            flat.add_message(sender.clone(), receiver.public_key(), channel.clone(), message(b"message 2")).await?;
+
+            let legacy = flat.channel_folder(&receiver.public_key(), &channel)?;
+            let target = sharded.channel_folder(&receiver.public_key(), &channel)?;
+
+            let legacy_index = StoredQueueMessagesInbox::read_index(&legacy).await.unwrap();
+
+            // First receiver's merge was interrupted after the target index
+            // was written, second one's after the first message was moved
+            let moved = if i == 0 { legacy_index.len() } else { 1 };
+
+            for message_id in &legacy_index[..moved] {
+                tokio::fs::rename(legacy.join(message_id.to_string()), target.join(message_id.to_string())).await?;
+            }
+
+            if i == 0 {
+                let target_index = StoredQueueMessagesInbox::read_index(&target).await.unwrap();
+
+                StoredQueueMessagesInbox::write_index(&target, &[legacy_index, target_index].concat()).await?;
+            }
+        }
+
+        assert_eq!(sharded.migrate_layout().await?, 2);
+        assert_eq!(sharded.migrate_layout().await?, 0);
+
+        for receiver in &receivers {
+            assert!(!StorageLayout::Flat.path(&temp, &receiver.public_key()).exists());
+
+            let (poll, 0) = sharded.poll_messages(receiver.public_key(), channel.clone().into(), None, None, None).await? else {
+                panic!("Failed to poll migrated messages");
+            };
+
+            let texts = poll.iter()
+                .map(|info| info.message.read(receiver, &sender_secret.public_key()).unwrap())
+                .collect::<Vec<_>>();
+
+            assert_eq!(texts, [
+                b"message 1".to_vec(),
+                b"message 2".to_vec(),
+                b"message 3".to_vec()
+            ]);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sealed() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-sealed-test").await?;
+
+        let plain = StoredQueueMessagesInbox::new(&temp, None).await?;
+        let sealed = StoredQueueMessagesInbox::new_sealed(&temp, StorageLayout::Flat).await?;
+
+        let sender_secret = SecretKey::random();
+        let receiver_secret = SecretKey::random();
+
+        let sender = Sender::new(get_client(), get_server());
+        let channel = ChannelName::new("channel").unwrap();
+
+        let message = |text: &[u8]| Message::create(
+            &sender_secret,
+            &receiver_secret.public_key(),
+            text,
+            MessageEncoding::default(),
+            CompressionLevel::default()
+        ).unwrap();
+
+        // Stored before sealing was enabled
+        plain.add_message(sender.clone(), receiver_secret.public_key(), channel.clone(), message(b"message 1")).await?;
+
+        sealed.add_message(sender.clone(), receiver_secret.public_key(), channel.clone(), message(b"message 2")).await?;
+        sealed.add_message(sender.clone(), receiver_secret.public_key(), channel.clone(), message(b"message 3")).await?;
+
+        assert!(matches!(
+            plain.poll_messages(receiver_secret.public_key(), channel.clone().into(), None, None, None).await,
+            Err(Error::SealedMessage)
+        ));
+
+        let Some((poll, 1)) = sealed.poll_sealed_messages(receiver_secret.public_key(), channel.clone(), Some(2)).await? else {
+            panic!("Test 1 failed");
+        };
+
+        for (sealed, text) in poll.iter().zip([b"message 1", b"message 2"]) {
+            let message_info = sealed.open(&receiver_secret)?;
+
+            assert_eq!(message_info.channel, channel);
+            assert_eq!(message_info.message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), text);
+        }
+
+        // Metadata is read from the sidecar file
+        // when it's not cached
+        let restarted = StoredQueueMessagesInbox::new_sealed(&temp, StorageLayout::Flat).await?;
+
+        let Some((poll, 0)) = restarted.poll_sealed_messages(receiver_secret.public_key(), channel.clone(), None).await? else {
+            panic!("Test 2 failed");
+        };
+
+        assert_eq!(poll[0].channel, channel);
+        assert_eq!(poll[0].open(&receiver_secret)?.message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 3");
+
+        // Both message and sidecar files are removed
+        let mut files = tokio::fs::read_dir(sealed.channel_folder(&receiver_secret.public_key(), &channel)?).await?;
+
+        while let Some(file) = files.next_entry().await? {
+            assert_eq!(file.file_name(), "index");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn channel_names() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-channels-test").await?;
+
+        let queue = StoredQueueMessagesInbox::new(temp.join("storage"), None).await?;
+
+        let sender_secret = SecretKey::random();
+        let receiver_secret = SecretKey::random();
+
+        let sender = Sender::new(get_client(), get_server());
+
+        for channel in ["канал 🦀", "../../escape", "..", "a/b\\c"] {
+            let message = Message::create(
+                &sender_secret,
+                &receiver_secret.public_key(),
+                channel.as_bytes(),
+                MessageEncoding::default(),
+                CompressionLevel::default()
+            ).unwrap();
+
+            queue.add_message(sender.clone(), receiver_secret.public_key(), ChannelName::new(channel).unwrap(), message).await?;
+
+            let (poll, 0) = queue.poll_messages(receiver_secret.public_key(), ChannelName::new(channel).unwrap().into(), None, None, None).await? else {
+                panic!("Failed to poll from {channel:?}");
+            };
+
+            assert_eq!(poll[0].channel.as_str(), channel);
+            assert_eq!(poll[0].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), channel.as_bytes());
+        }
+
+        // Nothing was written outside of the storage
+        let mut entries = tokio::fs::read_dir(&temp).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            assert_eq!(entry.file_name(), "storage");
+        }
+
+        let message = Message::new("content", "sign", MessageEncoding::default());
+
+        assert!(matches!(
+            queue.add_message(sender.clone(), receiver_secret.public_key(), ChannelName::new_unchecked("bad\0name"), message).await,
+            Err(Error::InvalidChannel(ChannelNameError::InvalidCharacter('\0')))
+        ));
+
+        Ok(())
+    }
+
+    /// Mark first `count` messages of the channel as received long ago.
+    async fn backdate(inbox: &StoredQueueMessagesInbox, receiver: &PublicKey, channel: &str, count: usize) -> Result<(), Error> {
+        let folder = inbox.channel_folder(receiver, &ChannelName::new(channel).unwrap())?;
+
+        for message_id in StoredQueueMessagesInbox::read_index(&folder).await.unwrap().into_iter().take(count) {
+            let path = folder.join(message_id.to_string());
+
+            let mut info = serde_json::from_slice::<Json>(&tokio::fs::read(&path).await?)?;
+
+            info["received_at"] = json!(0);
+
+            tokio::fs::write(path, serde_json::to_vec(&info)?).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ttl() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-ttl-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, Some(Duration::from_secs(60))).await?;
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        for (channel, text) in [("ttl", "message 1"), ("ttl", "message 2"), ("ttl", "message 3"), ("ttl", "message 4"), ("stale", "message 5")] {
+            let message = Message::new(text, "sign", MessageEncoding::default());
+
+            inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new(channel).unwrap(), message).await?;
+        }
+
+        backdate(&inbox, &receiver, "ttl", 2).await?;
+        backdate(&inbox, &receiver, "stale", 1).await?;
+
+        let texts = |messages: Vec<MessageInfo>| messages.into_iter()
+            .map(|info| info.message.content)
+            .collect::<Vec<_>>();
+
+        // Expired messages are skipped but kept by peeking
+        let Some((peek, 0)) = inbox.peek_messages(receiver.clone(), ChannelName::new("ttl").unwrap(), None).await? else {
+            panic!("Peek failed");
+        };
+
+        assert_eq!(texts(peek), ["message 3", "message 4"]);
+
+        let folder = inbox.channel_folder(&receiver, &ChannelName::new("ttl").unwrap())?;
+
+        assert_eq!(StoredQueueMessagesInbox::read_index(&folder).await.unwrap().len(), 4);
+
+        // Expired messages are removed and not counted as remaining
+        let (poll, 1) = inbox.poll_messages(receiver.clone(), ChannelName::new("ttl").unwrap().into(), None, None, Some(1)).await? else {
+            panic!("Poll failed");
+        };
+
+        assert_eq!(texts(poll), ["message 3"]);
+        assert_eq!(StoredQueueMessagesInbox::read_index(&folder).await.unwrap().len(), 1);
+
+        let mut files = tokio::fs::read_dir(&folder).await?;
+        let mut count = 0;
+
+        while files.next_entry().await?.is_some() {
+            count += 1;
+        }
+
+        // index and the last message
+        assert_eq!(count, 2);
+
+        // Never polled channels are removed by cleanup
+        assert_eq!(inbox.cleanup_expired().await?, 1);
+
+        assert!(!inbox.channel_folder(&receiver, &ChannelName::new("stale").unwrap())?.exists());
+        assert!(folder.exists());
+
+        assert_eq!(inbox.cleanup_expired().await?, 0);
+
+        backdate(&inbox, &receiver, "ttl", 1).await?;
+
+        assert_eq!(inbox.cleanup_expired().await?, 1);
+        assert!(!inbox.receiver_folder(&receiver).exists());
+
+        // Without TTL messages never expire
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?;
+
+        inbox.add_message(sender, receiver.clone(), ChannelName::new("ttl").unwrap(), Message::new("message", "sign", MessageEncoding::default())).await?;
+
+        backdate(&inbox, &receiver, "ttl", 1).await?;
+
+        assert_eq!(inbox.cleanup_expired().await?, 0);
+        assert_eq!(inbox.poll_messages(receiver, ChannelName::new("ttl").unwrap().into(), None, None, None).await?.0.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn corrupted_index() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-corrupted-index-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?;
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        for text in ["message 1", "message 2", "message 3"] {
+            let message = Message::new(text, "sign", MessageEncoding::default());
+
+            inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), message).await?;
+        }
+
+        let folder = inbox.channel_folder(&receiver, &ChannelName::new("channel").unwrap())?;
+
+        // Temporary files are replaced by the index
+        assert!(!folder.join("index.tmp").exists());
+
+        // Simulate crash in the middle of the last record's write
+        let index = tokio::fs::read(folder.join("index")).await?;
+
+        tokio::fs::write(folder.join("index"), &index[..20]).await?;
+
+        let (poll, 0) = inbox.poll_messages(receiver.clone(), ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Failed to poll from the corrupted index");
+        };
+
+        assert_eq!(poll.into_iter().map(|info| info.message.content).collect::<Vec<_>>(), ["message 1", "message 2"]);
+
+        // Index is usable after recovery
+        inbox.add_message(sender, receiver.clone(), ChannelName::new("channel").unwrap(), Message::new("message 4", "sign", MessageEncoding::default())).await?;
+
+        assert_eq!(tokio::fs::read(folder.join("index")).await?.len(), 8);
+
+        let (poll, 0) = inbox.poll_messages(receiver, ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Failed to poll after recovery");
+        };
+
+        assert_eq!(poll[0].message.content, "message 4");
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_add() -> Result<(), Box<dyn std::error::Error>> {
+        const MESSAGES: usize = 100;
+
+        let temp = prepare_folder("stored-queue-messages-inbox-concurrent-add-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?;
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let tasks = (0..MESSAGES)
+            .map(|i| {
+                let inbox = inbox.clone();
+                let receiver = receiver.clone();
+                let sender = sender.clone();
+
+                tokio::spawn(async move {
+                    inbox.add_message(sender, receiver, ChannelName::new("channel").unwrap(), Message::new(i.to_string(), "sign", MessageEncoding::default())).await
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for task in tasks {
+            task.await??;
+        }
+
+        let (messages, 0) = inbox.poll_messages(receiver, ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Failed to poll concurrently added messages");
+        };
+
+        let mut texts = messages.into_iter()
+            .map(|info| info.message.content)
+            .collect::<Vec<_>>();
+
+        texts.sort();
+        texts.dedup();
+
+        assert_eq!(texts.len(), MESSAGES);
+
+        // No message files are left out of the index
+        assert_eq!(inbox.stats().await?.messages, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_wildcard_poll() -> Result<(), Box<dyn std::error::Error>> {
+        const MESSAGES: usize = 200;
+        const POLLS: usize = 10;
+
+        let temp = prepare_folder("stored-queue-messages-inbox-concurrent-wildcard-poll-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?;
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        for i in 0..MESSAGES {
+            let channel = if i % 2 == 0 { "chat/a" } else { "chat/b" };
+
+            inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new(channel).unwrap(), Message::new(i.to_string(), "sign", MessageEncoding::default())).await?;
+        }
+
+        let wildcard = ChannelRule::parse("chat/*");
+
+        let tasks = (0..POLLS * 2)
+            .map(|i| {
+                let inbox = inbox.clone();
+                let receiver = receiver.clone();
+
+                let rule = if i % 2 == 0 {
+                    wildcard.clone()
+                } else {
+                    ChannelName::new("chat/a").unwrap().into()
+                };
+
+                tokio::spawn(async move {
+                    inbox.poll_messages(receiver, rule, None, None, Some(5)).await
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut texts = Vec::new();
+
+        for task in tasks {
+            let (messages, _) = task.await??;
+
+            // Polled channels are not changed by other requests
+            // between peeking and polling them
+            assert_eq!(messages.len(), 5);
+
+            texts.extend(messages.into_iter().map(|info| info.message.content));
+        }
+
+        let (messages, 0) = inbox.poll_messages(receiver, wildcard, None, None, None).await? else {
+            panic!("Failed to poll remaining messages");
+        };
+
+        texts.extend(messages.into_iter().map(|info| info.message.content));
+
+        texts.sort();
+        texts.dedup();
+
+        assert_eq!(texts.len(), MESSAGES);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn message_size() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-message-size-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_max_message_size(16);
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let send = |content: &str| inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), Message::new(content, "sign", MessageEncoding::default()));
+
+        send("small message").await?;
+
+        let err = send("too large message").await.unwrap_err();
+
+        assert!(matches!(err, Error::MessageTooLarge { size: 17, max_size: 16 }));
+        assert_eq!(inbox.error_status(&err), ResponseStatus::MessageTooLarge);
+
+        assert_eq!(inbox.list_channels(receiver).await?, [(ChannelName::new("channel").unwrap(), 1)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn range_filter() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-range-filter-test").await?;
+
+        range_filter_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn range_filter_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-range-filter-wal-test").await?;
+
+        range_filter_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    #[tokio::test]
+    async fn observer() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-observer-test").await?;
+
+        observer_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn observer_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-observer-wal-test").await?;
+
+        observer_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    #[tokio::test]
+    async fn archive() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-archive-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_archive(ArchiveConfig::new(Duration::from_secs(3600)));
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let channel = ChannelName::new("channel").unwrap();
+
+        for text in ["message 1", "message 2", "message 3", "message 4"] {
+            inbox.add_message(sender.clone(), receiver.clone(), channel.clone(), Message::new(text, "sign", MessageEncoding::default())).await?;
+        }
+
+        let archive = temp.join("archive")
+            .join(receiver.to_base64())
+            .join(inbox.channel_folder(&receiver, &channel)?.file_name().unwrap());
+
+        // Polls are not affected by the archive
+        let (messages, 2) = inbox.poll_messages(receiver.clone(), channel.clone().into(), None, None, Some(2)).await? else {
+            panic!("Test 1 failed");
+        };
+
+        assert_eq!(messages[0].message.content, "message 1");
+        assert_eq!(messages[1].message.content, "message 2");
+
+        let streamed = inbox.poll_messages_stream(receiver.clone(), channel.clone(), Some(1))
+            .collect::<Vec<_>>().await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(streamed[0].message.content, "message 3");
+
+        let Some((leased, 0)) = inbox.lease_messages(receiver.clone(), channel.clone().into(), None, None, None, Duration::from_secs(60)).await? else {
+            panic!("Test 2 failed");
+        };
+
+        assert_eq!(inbox.ack_messages(receiver.clone(), vec![leased[0].id.unwrap()]).await?, Some(1));
+
+        assert!(inbox.list_channels(receiver.clone()).await?.is_empty());
+        assert_eq!(inbox.poll_messages(receiver.clone(), channel.into(), None, None, None).await?, (vec![], 0));
+
+        // Consumed messages are kept in the archive
+        assert_eq!(std::fs::read_dir(&archive)?.count(), 4);
+
+        assert_eq!(inbox.expire_archive().await?, 0);
+
+        let expiring = StoredQueueMessagesInbox {
+            archive: Some(ArchiveConfig::new(Duration::ZERO)),
+            ..inbox.clone()
+        };
+
+        assert_eq!(expiring.expire_archive().await?, 4);
+
+        assert!(!temp.join("archive").join(receiver.to_base64()).exists());
+
+        // Archive can't be expired when it's not enabled
+        assert_eq!(StoredQueueMessagesInbox::new(&temp, None).await?.expire_archive().await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn compact() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-compact-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?;
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        for text in ["message 1", "message 2", "message 3"] {
+            inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), Message::new(text, "sign", MessageEncoding::default())).await?;
+        }
+
+        let folder = inbox.channel_folder(&receiver, &ChannelName::new("channel").unwrap())?;
+
+        let index = StoredQueueMessagesInbox::read_index(&folder).await
+            .expect("Channel index must exist");
+
+        // Simulate interrupted writes
+        tokio::fs::remove_file(folder.join(index[0].to_string())).await?;
+
+        tokio::fs::write(folder.join("123"), "orphaned message").await?;
+        tokio::fs::write(folder.join("456.meta"), "orphaned metadata").await?;
+
+        // Recent orphaned files are kept
+        assert_eq!(inbox.compact(Duration::from_secs(3600)).await?, CompactionSummary {
+            channels: 1,
+            dangling_entries: 1,
+            ..CompactionSummary::default()
+        });
+
+        assert_eq!(StoredQueueMessagesInbox::read_index(&folder).await, Some(index[1..].to_vec()));
+
+        let summary = inbox.compact(Duration::ZERO).await?;
+
+        assert_eq!(summary.dangling_entries, 0);
+        assert_eq!(summary.orphaned_files, 2);
+        assert_eq!(summary.removed_bytes, 33);
+
+        assert!(!folder.join("123").exists());
+        assert!(!folder.join("456.meta").exists());
+
+        let (messages, 0) = inbox.poll_messages(receiver, ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Failed to poll compacted channel");
+        };
+
+        assert_eq!(messages.len(), 2);
+
+        assert_eq!(inbox.compact(Duration::ZERO).await?, CompactionSummary {
+            channels: 1,
+            ..CompactionSummary::default()
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn quota() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-quota-test").await?;
+
+        let quota = InboxQuota {
+            max_messages: 3,
+            ..InboxQuota::default()
+        };
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_quota(quota);
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let send = |inbox: StoredQueueMessagesInbox, channel: &'static str| {
+            let sender = sender.clone();
+            let receiver = receiver.clone();
+
+            async move {
+                inbox.add_message(sender, receiver, ChannelName::new(channel).unwrap(), Message::new("message", "sign", MessageEncoding::default())).await
+            }
+        };
+
+        // Quota is shared by all the receiver's channels
+        send(inbox.clone(), "channel 1").await?;
+        send(inbox.clone(), "channel 1").await?;
+        send(inbox.clone(), "channel 2").await?;
+
+        let Err(err @ Error::QuotaExceeded { messages: 3, .. }) = send(inbox.clone(), "channel 2").await else {
+            panic!("Messages quota wasn't applied");
+        };
+
+        assert_eq!(inbox.error_status(&err), ResponseStatus::ClientInboxFull);
+
+        // Other receivers are not affected
+        inbox.add_message(sender.clone(), SecretKey::random().public_key(), ChannelName::new("channel 1").unwrap(), Message::new("message", "sign", MessageEncoding::default())).await?;
+
+        // Usage is restored from the stored files
+        let restarted = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_quota(quota);
+
+        assert!(matches!(send(restarted.clone(), "channel 3").await, Err(Error::QuotaExceeded { messages: 3, .. })));
+
+        // Polled messages free the quota
+        restarted.poll_messages(receiver.clone(), ChannelName::new("channel 1").unwrap().into(), None, None, Some(1)).await?;
+
+        send(restarted.clone(), "channel 3").await?;
+
+        assert!(matches!(send(restarted.clone(), "channel 3").await, Err(Error::QuotaExceeded { .. })));
+
+        // Size quota
+        let (_, bytes) = restarted.receiver_usage(&receiver).await?;
+
+        let limited = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_quota(InboxQuota {
+                max_bytes: bytes + 10,
+                ..InboxQuota::default()
+            });
+
+        assert!(matches!(send(limited, "channel 3").await, Err(Error::QuotaExceeded { messages: 3, bytes: stored }) if stored == bytes));
+
+        // No quota by default
+        send(StoredQueueMessagesInbox::new(&temp, None).await?, "channel 3").await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn quota_failed_store() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-quota-failed-store-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_quota(InboxQuota {
+                max_messages: 1,
+                ..InboxQuota::default()
+            });
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+        let channel = ChannelName::new("channel").unwrap();
+
+        let send = || inbox.add_message(sender.clone(), receiver.clone(), channel.clone(), Message::new("message", "sign", MessageEncoding::default()));
+
+        // Channel folder can't be created
+        let folder = inbox.channel_folder(&receiver, &channel)?;
+
+        tokio::fs::create_dir_all(inbox.receiver_folder(&receiver)).await?;
+        tokio::fs::write(&folder, b"").await?;
+
+        assert!(matches!(send().await, Err(Error::Io(_))));
+
+        // Reservation of the failed message is released
+        tokio::fs::remove_file(&folder).await?;
+
+        send().await?;
+
+        assert!(matches!(send().await, Err(Error::QuotaExceeded { messages: 1, .. })));
+
+        Ok(())
+    }
+
+    async fn eviction_suite(inbox: StoredQueueMessagesInbox) -> Result<(), Error> {
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let send = |inbox: &StoredQueueMessagesInbox, channel: &'static str, text: &'static str| {
+            let inbox = inbox.clone();
+            let sender = sender.clone();
+            let receiver = receiver.clone();
+
+            async move {
+                inbox.add_message(sender, receiver, ChannelName::new(channel).unwrap(), Message::new(text, "sign", MessageEncoding::default())).await
+            }
+        };
+
+        let texts = |messages: Vec<MessageInfo>| messages.into_iter()
+            .map(|info| info.message.content)
+            .collect::<Vec<_>>();
+
+        // Reject new messages
+        let rejecting = inbox.clone()
+            .with_channel_limit(2, EvictionPolicy::RejectNew);
+
+        send(&rejecting, "reject", "message 1").await?;
+        send(&rejecting, "reject", "message 2").await?;
+
+        let Err(err @ Error::ChannelFull { messages: 2 }) = send(&rejecting, "reject", "message 3").await else {
+            panic!("Channel limit wasn't applied");
+        };
+
+        assert_eq!(rejecting.error_status(&err), ResponseStatus::ClientInboxFull);
+
+        // Other channels are not affected
+        send(&rejecting, "other", "message 1").await?;
+
+        let (messages, 1) = rejecting.poll_messages(receiver.clone(), ChannelName::new("reject").unwrap().into(), None, None, Some(1)).await? else {
+            panic!("Remaining counter is wrong after rejection");
+        };
+
+        assert_eq!(texts(messages), ["message 1"]);
+
+        send(&rejecting, "reject", "message 4").await?;
+
+        let (messages, 0) = rejecting.poll_messages(receiver.clone(), ChannelName::new("reject").unwrap().into(), None, None, None).await? else {
+            panic!("Remaining counter is wrong after rejection");
+        };
+
+        assert_eq!(texts(messages), ["message 2", "message 4"]);
+
+        // Drop the oldest messages
+        let evicting = inbox.with_channel_limit(2, EvictionPolicy::DropOldest);
+
+        for text in ["message 1", "message 2", "message 3", "message 4"] {
+            send(&evicting, "evict", text).await?;
+        }
+
+        // Evicted messages' files are removed
+        if !evicting.is_wal() {
+            assert_eq!(evicting.receiver_usage(&receiver).await?.0, 3);
+        }
+
+        let (messages, 1) = evicting.poll_messages(receiver.clone(), ChannelName::new("evict").unwrap().into(), None, None, Some(1)).await? else {
+            panic!("Remaining counter is wrong after eviction");
+        };
+
+        assert_eq!(texts(messages), ["message 3"]);
+
+        send(&evicting, "evict", "message 5").await?;
+        send(&evicting, "evict", "message 6").await?;
+
+        let (messages, 0) = evicting.poll_messages(receiver.clone(), ChannelName::new("evict").unwrap().into(), None, None, None).await? else {
+            panic!("Remaining counter is wrong after eviction");
+        };
+
+        assert_eq!(texts(messages), ["message 5", "message 6"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn eviction() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-eviction-test").await?;
+
+        eviction_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn eviction_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-eviction-wal-test").await?;
+
+        eviction_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    /// Read names and contents of all the files
+    /// stored in the folder.
+    async fn read_storage(folder: &Path) -> std::io::Result<Vec<u8>> {
+        let mut storage = Vec::new();
+        let mut folders = vec![folder.to_path_buf()];
+
+        while let Some(folder) = folders.pop() {
+            let mut entries = tokio::fs::read_dir(folder).await?;
+
+            while let Some(entry) = entries.next_entry().await? {
+                storage.extend_from_slice(entry.file_name().as_encoded_bytes());
+
+                if entry.file_type().await?.is_dir() {
+                    folders.push(entry.path());
+                } else {
+                    storage.extend(tokio::fs::read(entry.path()).await?);
+                }
+            }
+        }
+
+        Ok(storage)
+    }
+
+    #[tokio::test]
+    async fn encryption() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-encryption-test").await?;
+
+        let server_secret = SecretKey::random();
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_encryption(&server_secret);
+
+        send_poll_suite(inbox.clone()).await?;
+        wildcard_suite(inbox.clone()).await?;
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("secret channel").unwrap(), Message::new("secret content", "sign", MessageEncoding::default())).await?;
+
+        // Neither channels nor senders are stored in plain
+        let storage = String::from_utf8_lossy(&read_storage(&temp).await?).to_string();
+
+        assert!(!storage.contains("secret"));
+        assert!(!storage.contains(&ChannelName::new("secret channel").unwrap().to_fs_name()));
+        assert!(!storage.contains(&sender.client.public_key.to_base64()));
+
+        assert_eq!(inbox.list_channels(receiver.clone()).await?, [(ChannelName::new("secret channel").unwrap(), 1)]);
+
+        // Storage can't be read with another key
+        let other = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_encryption(&SecretKey::random());
+
+        assert!(matches!(other.list_channels(receiver.clone()).await, Err(Error::Encryption)));
+
+        let (messages, 0) = inbox.poll_messages(receiver, ChannelName::new("secret channel").unwrap().into(), None, None, None).await? else {
+            panic!("Failed to poll encrypted messages");
+        };
+
+        assert_eq!(messages[0].message.content, "secret content");
+        assert_eq!(messages[0].sender, sender);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn encryption_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-encryption-wal-test").await?;
+
+        let server_secret = SecretKey::random();
+
+        let inbox = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?
+            .with_encryption(&server_secret);
+
+        send_poll_suite(inbox.clone()).await?;
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), Message::new("secret content", "sign", MessageEncoding::default())).await?;
+
+        let storage = String::from_utf8_lossy(&read_storage(&temp).await?).to_string();
+
+        assert!(!storage.contains("secret content"));
+        assert!(!storage.contains(&sender.client.public_key.to_base64()));
+
+        // Encrypted log can't be read without the key
+        let plain = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?;
+
+        assert!(matches!(plain.poll_messages(receiver.clone(), ChannelName::new("channel").unwrap().into(), None, None, None).await, Err(Error::EncryptedStorage)));
+
+        let restarted = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?
+            .with_encryption(&server_secret);
+
+        assert_eq!(restarted.poll_messages(receiver, ChannelName::new("channel").unwrap().into(), None, None, None).await?.0[0].message.content, "secret content");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unencrypted_storage() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-unencrypted-storage-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?;
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        inbox.add_message(sender, receiver.clone(), ChannelName::new("channel").unwrap(), Message::new("message", "sign", MessageEncoding::default())).await?;
+
+        let encrypted = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_encryption(&SecretKey::random());
+
+        assert!(matches!(encrypted.poll_messages(receiver.clone(), ChannelName::new("channel").unwrap().into(), None, None, None).await, Err(Error::UnencryptedStorage)));
+        assert!(matches!(encrypted.list_channels(receiver.clone()).await, Err(Error::UnencryptedStorage)));
+
+        // Messages are kept for the plain inbox
+        assert_eq!(inbox.poll_messages(receiver, ChannelName::new("channel").unwrap().into(), None, None, None).await?.0.len(), 1);
+
+        Ok(())
+    }
+
+    async fn lease_suite(inbox: StoredQueueMessagesInbox) -> Result<(), Error> {
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let lease = Duration::from_millis(500);
+
+        for text in ["message 1", "message 2", "message 3"] {
+            inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("lease").unwrap(), Message::new(text, "sign", MessageEncoding::default())).await?;
+        }
+
+        let texts = |messages: &[MessageInfo]| messages.iter()
+            .map(|info| info.message.content.clone())
+            .collect::<Vec<_>>();
+
+        let Some((leased, 1)) = inbox.lease_messages(receiver.clone(), ChannelName::new("lease").unwrap().into(), None, None, Some(2), lease).await? else {
+            panic!("Test 1 failed");
+        };
+
+        assert_eq!(texts(&leased), ["message 1", "message 2"]);
+        assert!(leased.iter().all(|info| info.id.is_some()));
+
+        // Leased messages are hidden from other leasing polls
+        let Some((next, 0)) = inbox.lease_messages(receiver.clone(), ChannelName::new("lease").unwrap().into(), None, None, None, lease).await? else {
+            panic!("Test 2 failed");
+        };
+
+        assert_eq!(texts(&next), ["message 3"]);
+
+        // Messages can only be acknowledged by their receiver
+        assert_eq!(inbox.ack_messages(SecretKey::random().public_key(), vec![leased[0].id.unwrap()]).await?, Some(0));
+
+        assert_eq!(inbox.ack_messages(receiver.clone(), vec![leased[0].id.unwrap()]).await?, Some(1));
+        assert_eq!(inbox.ack_messages(receiver.clone(), vec![leased[0].id.unwrap()]).await?, Some(0));
+
+        // Unacknowledged messages are still stored
+        assert_eq!(inbox.list_channels(receiver.clone()).await?, [(ChannelName::new("lease").unwrap(), 2)]);
+
+        tokio::time::sleep(lease + Duration::from_millis(100)).await;
+
+        // And become visible again when their leases expire
+        let Some((expired, 0)) = inbox.lease_messages(receiver.clone(), ChannelName::new("lease").unwrap().into(), None, None, None, lease).await? else {
+            panic!("Test 3 failed");
+        };
+
+        assert_eq!(texts(&expired), ["message 2", "message 3"]);
+        assert_eq!(expired[0].id, leased[1].id);
+
+        let ids = expired.iter()
+            .filter_map(|info| info.id)
+            .collect::<Vec<_>>();
+
+        assert_eq!(inbox.ack_messages(receiver.clone(), ids).await?, Some(2));
+        assert_eq!(inbox.poll_messages(receiver.clone(), ChannelName::new("lease").unwrap().into(), None, None, None).await?, (vec![], 0));
+
+        // Leases work with wildcard channels
+        inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("lease/a").unwrap(), Message::new("message 4", "sign", MessageEncoding::default())).await?;
+
+        let Some((leased, 0)) = inbox.lease_messages(receiver.clone(), ChannelRule::parse("lease*"), None, None, None, lease).await? else {
+            panic!("Test 4 failed");
+        };
+
+        assert_eq!(texts(&leased), ["message 4"]);
+        assert_eq!(inbox.ack_messages(receiver.clone(), vec![leased[0].id.unwrap()]).await?, Some(1));
+        assert!(inbox.list_channels(receiver).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stats() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-stats-test").await?;
+
+        stats_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await?;
+
+        // Statistics are cached for the given time
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_stats_cache(Duration::from_secs(60));
+
+        assert_eq!(inbox.stats().await?.messages, 0);
+
+        inbox.add_message(Sender::new(get_client(), get_server()), SecretKey::random().public_key(), ChannelName::new("channel").unwrap(), Message::new("message", "sign", MessageEncoding::default())).await?;
+
+        assert_eq!(inbox.stats().await?.messages, 0);
+        assert_eq!(inbox.collect_stats().await?.messages, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stats_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-stats-wal-test").await?;
+
+        stats_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    #[tokio::test]
+    async fn priorities() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-priorities-test").await?;
+
+        let priorities = ChannelPriorities::new()
+            .with_priority(ChannelName::new("presence").unwrap(), 10)
+            .with_priority(ChannelName::new("acks").unwrap(), 5)
+            .with_default(0);
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_priorities(priorities);
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        // Bulk data is sent before the control messages
+        for (channel, text) in [("bulk", "bulk 1"), ("acks", "ack 1"), ("bulk", "bulk 2"), ("presence", "presence 1"), ("acks", "ack 2"), ("presence", "presence 2")] {
+            inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new(channel).unwrap(), Message::new(text, "sign", MessageEncoding::default())).await?;
+        }
+
+        let texts = |messages: Vec<MessageInfo>| messages.into_iter()
+            .map(|info| info.message.content)
+            .collect::<Vec<_>>();
+
+        let (messages, 3) = inbox.poll_messages(receiver.clone(), ChannelRule::parse("*"), None, None, Some(3)).await? else {
+            panic!("Failed to poll prioritized messages");
+        };
+
+        assert_eq!(texts(messages), ["presence 1", "presence 2", "ack 1"]);
+
+        let (messages, 0) = inbox.poll_messages(receiver.clone(), ChannelRule::parse("*"), None, None, None).await? else {
+            panic!("Failed to poll remaining messages");
+        };
+
+        assert_eq!(texts(messages), ["ack 2", "bulk 1", "bulk 2"]);
+
+        // Leased messages are ordered the same way
+        for (channel, text) in [("bulk", "bulk 3"), ("presence", "presence 3")] {
+            inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new(channel).unwrap(), Message::new(text, "sign", MessageEncoding::default())).await?;
+        }
+
+        let Some((messages, 0)) = inbox.lease_messages(receiver, ChannelRule::parse("*"), None, None, None, Duration::from_secs(60)).await? else {
+            panic!("Failed to lease prioritized messages");
+        };
+
+        assert_eq!(texts(messages), ["presence 3", "bulk 3"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn purge() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-purge-test").await?;
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?;
+
+        purge_suite(inbox.clone()).await?;
+
+        // Purged receivers' folders are removed
+        assert_eq!(inbox.receiver_folders().await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn purge_sharded() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-purge-sharded-test").await?;
+
+        purge_suite(StoredQueueMessagesInbox::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn purge_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-purge-wal-test").await?;
+
+        purge_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await?;
+
+        // Purged messages are not replayed
+        let restarted = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?;
+
+        assert_eq!(restarted.stats().await?.receivers, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-stream-test").await?;
+
+        stream_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn stream_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-stream-wal-test").await?;
+
+        stream_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    /// Stream all the messages of the large channel,
+    /// checking that they're removed while streaming.
+    async fn stream_large_suite(inbox: StoredQueueMessagesInbox) -> Result<(), Error> {
+        const MESSAGES: usize = 10000;
+
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let entries = (0..MESSAGES)
+            .map(|i| (
+                sender.clone(),
+                receiver.clone(),
+                ChannelName::new("channel").unwrap(),
+                Message::new(i.to_string(), "sign", MessageEncoding::default())
+            ))
+            .collect();
+
+        inbox.add_messages(entries).await?;
+
+        let mut stream = inbox.poll_messages_stream(receiver.clone(), ChannelName::new("channel").unwrap(), None);
+        let mut polled = 0;
+
+        while let Some(info) = stream.next().await {
+            assert_eq!(info?.message.content, polled.to_string());
+
+            polled += 1;
+
+            // Read messages are removed before the stream ends
+            if polled == MESSAGES / 2 {
+                let (_, remaining) = inbox.peek_messages(receiver.clone(), ChannelName::new("channel").unwrap(), Some(0)).await?
+                    .expect("Stored queue inbox must support peeking");
+
+                assert!(remaining < MESSAGES as u64);
+            }
+        }
+
+        drop(stream);
+
+        assert_eq!(polled, MESSAGES);
+
+        assert_eq!(inbox.poll_messages(receiver.clone(), ChannelName::new("channel").unwrap().into(), None, None, None).await?, (vec![], 0));
+        assert!(inbox.list_channels(receiver).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_large() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-stream-large-test").await?;
+
+        stream_large_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn stream_large_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-stream-large-wal-test").await?;
+
+        stream_large_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Never).await?).await
+    }
+
+    #[tokio::test]
+    async fn lease() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-lease-test").await?;
+
+        lease_suite(StoredQueueMessagesInbox::new(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn lease_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-lease-wal-test").await?;
+
+        lease_suite(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?).await
+    }
+
+    async fn dedup_suite(inbox: StoredQueueMessagesInbox, restarted: impl std::future::Future<Output = std::io::Result<StoredQueueMessagesInbox>>) -> Result<(), Error> {
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let message = Message::new("message", "sign", MessageEncoding::default());
+
+        for _ in 0..2 {
+            inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), message.clone()).await?;
+        }
+
+        // Same message to another channel is not a duplicate
+        inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("other channel").unwrap(), message.clone()).await?;
+
+        let (poll, 0) = inbox.poll_messages(receiver.clone(), ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Failed to poll messages");
+        };
+
+        assert_eq!(poll.len(), 1);
+        assert_eq!(poll[0].message, message);
+
+        assert_eq!(inbox.poll_messages(receiver.clone(), ChannelName::new("other channel").unwrap().into(), None, None, None).await?.0.len(), 1);
+
+        // Window is kept over restarts even after the message was polled
+        let inbox = restarted.await?;
+
+        inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), message).await?;
+        inbox.add_message(sender, receiver.clone(), ChannelName::new("channel").unwrap(), Message::new("other message", "sign", MessageEncoding::default())).await?;
+
+        let (poll, 0) = inbox.poll_messages(receiver, ChannelName::new("channel").unwrap().into(), None, None, None).await? else {
+            panic!("Failed to poll messages after restart");
+        };
+
+        assert_eq!(poll.len(), 1);
+        assert_eq!(poll[0].message.content, "other message");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dedup() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-dedup-test").await?;
+        let window = Duration::from_secs(60);
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?
+            .with_dedup_window(window);
+
+        dedup_suite(inbox, async {
+            Ok(StoredQueueMessagesInbox::new(&temp, None).await?.with_dedup_window(window))
+        }).await?;
+
+        // Duplicates are kept without the window
+        let receiver = SecretKey::random().public_key();
+        let sender = Sender::new(get_client(), get_server());
+
+        let inbox = StoredQueueMessagesInbox::new(&temp, None).await?;
+
+        for _ in 0..2 {
+            inbox.add_message(sender.clone(), receiver.clone(), ChannelName::new("channel").unwrap(), Message::new("message", "sign", MessageEncoding::default())).await?;
+        }
+
+        assert_eq!(inbox.poll_messages(receiver, ChannelName::new("channel").unwrap().into(), None, None, None).await?.0.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dedup_wal() -> Result<(), Error> {
+        let temp = prepare_folder("stored-queue-messages-inbox-dedup-wal-test").await?;
+        let window = Duration::from_secs(60);
+
+        let inbox = StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?
+            .with_dedup_window(window);
+
+        dedup_suite(inbox, async {
+            Ok(StoredQueueMessagesInbox::new_wal(&temp, FsyncPolicy::Always).await?.with_dedup_window(window))
+        }).await
     }
 }
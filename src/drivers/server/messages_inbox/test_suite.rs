@@ -0,0 +1,603 @@
+//! Generic conformance tests of the `MessagesInbox` trait.
+//! 
+//! Every inbox implementation is expected to pass all
+//! the suites of this module. Suites take an empty inbox
+//! and check the trait contract using its public API only.
+
+use crate::crypto::prelude::*;
+
+use crate::rest_api::types::client::tests::get_client;
+use crate::rest_api::types::server::tests::get_server;
+
+use super::*;
+
+fn texts(messages: Vec<MessageInfo>) -> Vec<String> {
+    messages.into_iter()
+        .map(|info| info.message.content)
+        .collect()
+}
+
+/// Check that the inbox returns messages in order,
+/// respects the limit and counts remaining messages.
+pub async fn send_poll_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let sender_secret = SecretKey::random();
+    let receiver_secret = SecretKey::random();
+
+    let sender = Sender::new(get_client(), get_server());
+    let receiver = get_client();
+
+    let mut messages = Vec::with_capacity(5);
+    let mut ids = Vec::with_capacity(5);
+
+    for message in [b"message 1", b"message 2", b"message 3", b"message 4", b"message 5"] {
+        let message = Message::create(
+            &sender_secret,
+            &receiver.public_key,
+            message,
+            MessageEncoding::default(),
+            CompressionLevel::default()
+        ).unwrap();
+
+        messages.push(message.clone());
+
+        let id = queue.add_message(
+            sender.clone(),
+            receiver_secret.public_key(),
+            ChannelName::new("default channel").unwrap(),
+            message
+        ).await?;
+
+        assert!(id.is_some());
+
+        ids.push(id);
+    }
+
+    assert_eq!(queue.poll_messages(receiver_secret.public_key(), ChannelName::new("random channel").unwrap().into(), None, None, None).await?, (vec![], 0));
+    assert_eq!(queue.poll_messages(receiver_secret.public_key(), ChannelName::new("random channel").unwrap().into(), None, None, Some(100)).await?, (vec![], 0));
+
+    let (poll, 4) = queue.poll_messages(receiver_secret.public_key(), ChannelName::new("default channel").unwrap().into(), None, None, Some(1)).await? else {
+        panic!("Test 1 failed");
+    };
+
+    assert_eq!(poll[0].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 1");
+    assert_eq!(poll[0].id, ids[0]);
+
+    let (poll, 2) = queue.poll_messages(receiver_secret.public_key(), ChannelName::new("default channel").unwrap().into(), None, None, Some(2)).await? else {
+        panic!("Test 2 failed");
+    };
+
+    assert_eq!(poll[0].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 2");
+    assert_eq!(poll[1].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 3");
+    assert_eq!(poll[1].id, ids[2]);
+
+    let (poll, 0) = queue.poll_messages(receiver_secret.public_key(), ChannelName::new("default channel").unwrap().into(), None, None, None).await? else {
+        panic!("Test 3 failed");
+    };
+
+    assert_eq!(poll[0].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 4");
+    assert_eq!(poll[1].message.read(&receiver_secret, &sender_secret.public_key()).unwrap(), b"message 5");
+    assert_eq!(poll[1].id, ids[4]);
+
+    Ok(())
+}
+
+/// Check that peeked messages are kept in the inbox.
+pub async fn peek_suite<T: MessagesInbox + Sync>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    for text in ["message 1", "message 2", "message 3"] {
+        let message = Message::new(text, "sign", MessageEncoding::default());
+
+        queue.add_message(sender.clone(), receiver.clone(), ChannelName::new("peek channel").unwrap(), message).await?;
+    }
+
+    assert_eq!(queue.peek_messages(receiver.clone(), ChannelName::new("random channel").unwrap(), None).await?, Some((vec![], 0)));
+
+    for _ in 0..2 {
+        let Some((peek, 1)) = queue.peek_messages(receiver.clone(), ChannelName::new("peek channel").unwrap(), Some(2)).await? else {
+            panic!("Peek failed");
+        };
+
+        assert_eq!(texts(peek), ["message 1", "message 2"]);
+    }
+
+    let (poll, 0) = queue.poll_messages(receiver.clone(), ChannelName::new("peek channel").unwrap().into(), None, None, None).await? else {
+        panic!("Poll after peek failed");
+    };
+
+    assert_eq!(texts(poll), ["message 1", "message 2", "message 3"]);
+
+    assert_eq!(queue.peek_messages(receiver, ChannelName::new("peek channel").unwrap(), None).await?, Some((vec![], 0)));
+
+    Ok(())
+}
+
+/// Check that messages of other senders are kept
+/// in order and counted as remaining.
+pub async fn sender_filter_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+
+    let alice = Sender::new(get_client(), get_server());
+    let bob = Sender::new(get_client(), get_server());
+
+    for (sender, text) in [(&alice, "alice 1"), (&bob, "bob 1"), (&alice, "alice 2"), (&bob, "bob 2"), (&alice, "alice 3")] {
+        let message = Message::new(text, "sign", MessageEncoding::default());
+
+        queue.add_message(sender.clone(), receiver.clone(), ChannelName::new("filter channel").unwrap(), message).await?;
+    }
+
+    let poll = |sender: &Sender, limit| queue.poll_messages(
+        receiver.clone(),
+        ChannelName::new("filter channel").unwrap().into(),
+        Some(sender.client.public_key.clone()),
+        None,
+        limit
+    );
+
+    let (messages, 4) = poll(&bob, Some(1)).await? else {
+        panic!("Test 1 failed");
+    };
+
+    assert_eq!(texts(messages), ["bob 1"]);
+
+    let (messages, 1) = poll(&alice, None).await? else {
+        panic!("Test 2 failed");
+    };
+
+    assert_eq!(texts(messages), ["alice 1", "alice 2", "alice 3"]);
+
+    let (messages, 1) = poll(&alice, None).await? else {
+        panic!("Test 3 failed");
+    };
+
+    assert!(messages.is_empty());
+
+    let (messages, 0) = queue.poll_messages(receiver, ChannelName::new("filter channel").unwrap().into(), None, None, None).await? else {
+        panic!("Test 4 failed");
+    };
+
+    assert_eq!(texts(messages), ["bob 2"]);
+
+    Ok(())
+}
+
+/// Check that wildcard polls merge messages
+/// of the matched channels by receiving time.
+/// 
+/// Messages are added with a pause so that
+/// their receiving times are different.
+pub async fn wildcard_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    for (channel, text) in [("chat/bob", "bob 1"), ("chat/alice", "alice 1"), ("status", "status 1"), ("chat/bob", "bob 2")] {
+        let message = Message::new(text, "sign", MessageEncoding::default());
+
+        queue.add_message(sender.clone(), receiver.clone(), ChannelName::new(channel).unwrap(), message).await?;
+
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    }
+
+    let texts = |messages: &[MessageInfo]| messages.iter()
+        .map(|info| (info.channel.as_str().to_string(), info.message.content.clone()))
+        .collect::<Vec<_>>();
+
+    let (messages, 1) = queue.poll_messages(receiver.clone(), ChannelRule::parse("chat/*"), None, None, Some(2)).await? else {
+        panic!("Test 1 failed");
+    };
+
+    assert_eq!(texts(&messages), [
+        (String::from("chat/bob"), String::from("bob 1")),
+        (String::from("chat/alice"), String::from("alice 1"))
+    ]);
+
+    let (messages, 0) = queue.poll_messages(receiver.clone(), ChannelRule::parse("*"), None, None, None).await? else {
+        panic!("Test 2 failed");
+    };
+
+    assert_eq!(texts(&messages), [
+        (String::from("status"), String::from("status 1")),
+        (String::from("chat/bob"), String::from("bob 2"))
+    ]);
+
+    assert_eq!(queue.poll_messages(receiver, ChannelRule::parse("chat/*"), None, None, None).await?, (vec![], 0));
+
+    Ok(())
+}
+
+/// Check that channels are listed with
+/// amounts of their pending messages.
+pub async fn list_channels_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    assert!(queue.list_channels(receiver.clone()).await?.is_empty());
+
+    for channel in ["status", "chat/bob", "status", "chat/alice"] {
+        let message = Message::new(channel, "sign", MessageEncoding::default());
+
+        queue.add_message(sender.clone(), receiver.clone(), ChannelName::new(channel).unwrap(), message).await?;
+    }
+
+    assert_eq!(queue.list_channels(receiver.clone()).await?, [
+        (ChannelName::new("chat/alice").unwrap(), 1),
+        (ChannelName::new("chat/bob").unwrap(), 1),
+        (ChannelName::new("status").unwrap(), 2)
+    ]);
+
+    // Other receivers' channels are not listed
+    assert!(queue.list_channels(SecretKey::random().public_key()).await?.is_empty());
+
+    queue.poll_messages(receiver.clone(), ChannelName::new("chat/bob").unwrap().into(), None, None, None).await?;
+    queue.poll_messages(receiver.clone(), ChannelName::new("status").unwrap().into(), None, None, Some(1)).await?;
+
+    assert_eq!(queue.list_channels(receiver).await?, [
+        (ChannelName::new("chat/alice").unwrap(), 1),
+        (ChannelName::new("status").unwrap(), 1)
+    ]);
+
+    Ok(())
+}
+
+/// Check that batched messages are added
+/// keeping their order within each channel.
+pub async fn add_messages_suite<T: MessagesInbox + Sync>(queue: T) -> Result<(), T::Error> {
+    let alice = SecretKey::random().public_key();
+    let bob = SecretKey::random().public_key();
+
+    let sender = Sender::new(get_client(), get_server());
+
+    let entries = [(&alice, "chat", "1"), (&bob, "chat", "2"), (&alice, "status", "3"), (&alice, "chat", "4"), (&bob, "chat", "5")]
+        .into_iter()
+        .map(|(receiver, channel, text)| (
+            sender.clone(),
+            receiver.clone(),
+            ChannelName::new(channel).unwrap(),
+            Message::new(text, "sign", MessageEncoding::default())
+        ))
+        .collect();
+
+    queue.add_messages(entries).await?;
+
+    let texts = |(messages, _): (Vec<MessageInfo>, u64)| messages.into_iter()
+        .map(|info| info.message.content)
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts(queue.poll_messages(alice.clone(), ChannelName::new("chat").unwrap().into(), None, None, None).await?), ["1", "4"]);
+    assert_eq!(texts(queue.poll_messages(alice, ChannelName::new("status").unwrap().into(), None, None, None).await?), ["3"]);
+    assert_eq!(texts(queue.poll_messages(bob, ChannelName::new("chat").unwrap().into(), None, None, None).await?), ["2", "5"]);
+
+    queue.add_messages(vec![]).await?;
+
+    Ok(())
+}
+
+/// Check that the inbox counts stored
+/// messages and their receivers.
+pub async fn stats_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let sender = Sender::new(get_client(), get_server());
+
+    let alice = SecretKey::random().public_key();
+    let bob = SecretKey::random().public_key();
+
+    assert_eq!(queue.stats().await?, InboxStats::default());
+
+    let started_at = crate::time::timestamp();
+
+    for (receiver, channel) in [(&alice, "chat"), (&alice, "status"), (&bob, "chat")] {
+        queue.add_message(sender.clone(), receiver.clone(), ChannelName::new(channel).unwrap(), Message::new("message", "sign", MessageEncoding::default())).await?;
+    }
+
+    let stats = queue.stats().await?;
+
+    assert_eq!(stats.messages, 3);
+    assert_eq!(stats.receivers, 2);
+    assert!(stats.bytes > 0);
+    assert!(stats.oldest_message.is_some_and(|oldest| oldest >= started_at && oldest <= crate::time::timestamp()));
+
+    queue.poll_messages(alice, ChannelRule::parse("*"), None, None, None).await?;
+
+    let polled = queue.stats().await?;
+
+    assert_eq!(polled.messages, 1);
+    assert_eq!(polled.receivers, 1);
+    assert!(polled.bytes < stats.bytes);
+
+    queue.poll_messages(bob, ChannelName::new("chat").unwrap().into(), None, None, None).await?;
+
+    assert_eq!(queue.stats().await?, InboxStats::default());
+
+    Ok(())
+}
+
+/// Check that purged messages are removed only
+/// from the given receiver's channels.
+pub async fn purge_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let sender = Sender::new(get_client(), get_server());
+
+    let alice = SecretKey::random().public_key();
+    let bob = SecretKey::random().public_key();
+
+    for (receiver, channel) in [(&alice, "chat"), (&alice, "chat"), (&alice, "status"), (&bob, "chat")] {
+        queue.add_message(sender.clone(), receiver.clone(), ChannelName::new(channel).unwrap(), Message::new("message", "sign", MessageEncoding::default())).await?;
+    }
+
+    queue.purge(alice.clone(), Some(ChannelName::new("status").unwrap())).await?;
+
+    assert_eq!(queue.list_channels(alice.clone()).await?, [(ChannelName::new("chat").unwrap(), 2)]);
+
+    queue.purge(alice.clone(), None).await?;
+
+    assert!(queue.list_channels(alice.clone()).await?.is_empty());
+    assert_eq!(queue.poll_messages(alice.clone(), ChannelName::new("chat").unwrap().into(), None, None, None).await?, (vec![], 0));
+
+    // Unknown receivers and channels are ignored
+    queue.purge(alice, None).await?;
+    queue.purge(bob.clone(), Some(ChannelName::new("random channel").unwrap())).await?;
+
+    assert_eq!(queue.list_channels(bob).await?, [(ChannelName::new("chat").unwrap(), 1)]);
+
+    Ok(())
+}
+
+/// Check that messages received outside of the time
+/// range are kept in the inbox and counted as remained.
+pub async fn range_filter_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    queue.add_message(sender.clone(), receiver.clone(), ChannelName::new("range channel").unwrap(), Message::new("old message", "sign", MessageEncoding::default())).await?;
+
+    // Timestamps have seconds precision
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    let middle = crate::time::timestamp();
+
+    queue.add_message(sender, receiver.clone(), ChannelName::new("range channel").unwrap(), Message::new("new message", "sign", MessageEncoding::default())).await?;
+
+    let (messages, 2) = queue.poll_messages(receiver.clone(), ChannelName::new("range channel").unwrap().into(), None, Some(0..middle - 100), None).await? else {
+        panic!("Test 1 failed");
+    };
+
+    assert!(messages.is_empty());
+
+    let (messages, 1) = queue.poll_messages(receiver.clone(), ChannelRule::parse("range*"), None, Some(middle..u64::MAX), None).await? else {
+        panic!("Test 2 failed");
+    };
+
+    assert_eq!(texts(messages), ["new message"]);
+
+    let (messages, 0) = queue.poll_messages(receiver, ChannelName::new("range channel").unwrap().into(), None, Some(0..middle), None).await? else {
+        panic!("Test 3 failed");
+    };
+
+    assert_eq!(texts(messages), ["old message"]);
+
+    Ok(())
+}
+
+#[derive(Default, Clone)]
+/// Observer remembering notified messages
+/// and failing for the `failing` channel.
+pub struct TestObserver(pub Arc<std::sync::Mutex<Vec<(PublicKey, ChannelName, String)>>>);
+
+#[async_trait::async_trait]
+impl InboxObserver for TestObserver {
+    async fn on_message(&self, receiver: &PublicKey, channel: &ChannelName, message: &MessageInfo) -> Result<(), ObserverError> {
+        self.0.lock()
+            .expect("Failed to lock observed messages")
+            .push((receiver.clone(), channel.clone(), message.message.content.clone()));
+
+        if channel.as_str() == "failing" {
+            return Err("Observer failed".into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that the observer is notified about added
+/// messages and its errors don't fail adding them.
+pub async fn observer_suite<T: MessagesInbox + Sync>(mut queue: T) -> Result<(), T::Error> {
+    let observer = TestObserver::default();
+
+    queue.set_observer(Arc::new(observer.clone()));
+
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    let message = |text: &str| Message::new(text, "sign", MessageEncoding::default());
+
+    queue.add_message(sender.clone(), receiver.clone(), ChannelName::new("chat").unwrap(), message("message 1")).await?;
+    queue.add_message(sender.clone(), receiver.clone(), ChannelName::new("failing").unwrap(), message("message 2")).await?;
+
+    queue.add_messages(vec![(sender, receiver.clone(), ChannelName::new("chat").unwrap(), message("message 3"))]).await?;
+
+    assert_eq!(*observer.0.lock().unwrap(), [
+        (receiver.clone(), ChannelName::new("chat").unwrap(), String::from("message 1")),
+        (receiver.clone(), ChannelName::new("failing").unwrap(), String::from("message 2")),
+        (receiver.clone(), ChannelName::new("chat").unwrap(), String::from("message 3"))
+    ]);
+
+    assert_eq!(queue.list_channels(receiver).await?, [
+        (ChannelName::new("chat").unwrap(), 2),
+        (ChannelName::new("failing").unwrap(), 1)
+    ]);
+
+    Ok(())
+}
+
+/// Check that streamed messages are read in order,
+/// respecting the limit, and removed from the inbox.
+pub async fn stream_suite<T: MessagesInbox + Sync>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    for text in ["message 1", "message 2", "message 3", "message 4", "message 5"] {
+        queue.add_message(sender.clone(), receiver.clone(), ChannelName::new("stream channel").unwrap(), Message::new(text, "sign", MessageEncoding::default())).await?;
+    }
+
+    assert!(queue.poll_messages_stream(receiver.clone(), ChannelName::new("random channel").unwrap(), None).next().await.is_none());
+
+    let mut texts = Vec::new();
+    let mut stream = queue.poll_messages_stream(receiver.clone(), ChannelName::new("stream channel").unwrap(), Some(2));
+
+    while let Some(info) = stream.next().await {
+        texts.push(info?.message.content);
+    }
+
+    drop(stream);
+
+    assert_eq!(texts, ["message 1", "message 2"]);
+
+    let mut stream = queue.poll_messages_stream(receiver.clone(), ChannelName::new("stream channel").unwrap(), None);
+
+    while let Some(info) = stream.next().await {
+        texts.push(info?.message.content);
+    }
+
+    drop(stream);
+
+    assert_eq!(texts, ["message 1", "message 2", "message 3", "message 4", "message 5"]);
+
+    assert_eq!(queue.poll_messages(receiver, ChannelName::new("stream channel").unwrap().into(), None, None, None).await?, (vec![], 0));
+
+    Ok(())
+}
+
+/// Check that messages of the same channel are polled
+/// in FIFO order across multiple limited polls.
+pub async fn fifo_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    let expected = (1..=20)
+        .map(|i| format!("message {i}"))
+        .collect::<Vec<_>>();
+
+    for text in &expected {
+        let message = Message::new(text, "sign", MessageEncoding::default());
+
+        queue.add_message(sender.clone(), receiver.clone(), ChannelName::new("fifo channel").unwrap(), message).await?;
+    }
+
+    let mut polled = Vec::with_capacity(expected.len());
+
+    for remaining in [13, 6, 0] {
+        let (poll, poll_remaining) = queue.poll_messages(receiver.clone(), ChannelName::new("fifo channel").unwrap().into(), None, None, Some(7)).await?;
+
+        assert_eq!(poll_remaining, remaining);
+
+        polled.extend(texts(poll));
+    }
+
+    assert_eq!(polled, expected);
+
+    Ok(())
+}
+
+/// Check that polls respect the limit and count
+/// remaining messages precisely.
+pub async fn limits_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    for text in ["message 1", "message 2", "message 3", "message 4"] {
+        let message = Message::new(text, "sign", MessageEncoding::default());
+
+        queue.add_message(sender.clone(), receiver.clone(), ChannelName::new("limits channel").unwrap(), message).await?;
+    }
+
+    let (poll, 3) = queue.poll_messages(receiver.clone(), ChannelName::new("limits channel").unwrap().into(), None, None, Some(1)).await? else {
+        panic!("Limit 1 poll failed");
+    };
+
+    assert_eq!(texts(poll), ["message 1"]);
+
+    // Limit larger than the inbox returns all the messages
+    let (poll, 0) = queue.poll_messages(receiver.clone(), ChannelName::new("limits channel").unwrap().into(), None, None, Some(100)).await? else {
+        panic!("Limit 100 poll failed");
+    };
+
+    assert_eq!(texts(poll), ["message 2", "message 3", "message 4"]);
+
+    Ok(())
+}
+
+/// Check that empty, drained and unknown channels
+/// are polled without errors.
+pub async fn empty_channels_suite<T: MessagesInbox>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    // Unknown receiver
+    assert_eq!(queue.poll_messages(receiver.clone(), ChannelName::new("empty channel").unwrap().into(), None, None, None).await?, (vec![], 0));
+    assert_eq!(queue.poll_messages(receiver.clone(), ChannelName::new("empty channel").unwrap().into(), None, None, Some(10)).await?, (vec![], 0));
+
+    let message = Message::new("message", "sign", MessageEncoding::default());
+
+    queue.add_message(sender, receiver.clone(), ChannelName::new("drained channel").unwrap(), message).await?;
+
+    // Unknown channel of the known receiver
+    assert_eq!(queue.poll_messages(receiver.clone(), ChannelName::new("empty channel").unwrap().into(), None, None, None).await?, (vec![], 0));
+
+    let (poll, 0) = queue.poll_messages(receiver.clone(), ChannelName::new("drained channel").unwrap().into(), None, None, None).await? else {
+        panic!("Poll failed");
+    };
+
+    assert_eq!(texts(poll), ["message"]);
+
+    // Drained channel
+    assert_eq!(queue.poll_messages(receiver, ChannelName::new("drained channel").unwrap().into(), None, None, None).await?, (vec![], 0));
+
+    Ok(())
+}
+
+/// Check that interleaved channels keep their own
+/// FIFO order and remaining counts.
+pub async fn interleaved_channels_suite<T: MessagesInbox + Sync>(queue: T) -> Result<(), T::Error> {
+    let receiver = SecretKey::random().public_key();
+    let sender = Sender::new(get_client(), get_server());
+
+    for i in 1..=3 {
+        for channel in ["channel a", "channel b"] {
+            let message = Message::new(format!("{channel} message {i}"), "sign", MessageEncoding::default());
+
+            queue.add_message(sender.clone(), receiver.clone(), ChannelName::new(channel).unwrap(), message).await?;
+        }
+    }
+
+    // Batches are interleaved the same way
+    let entries = (4..=5)
+        .flat_map(|i| ["channel a", "channel b"].map(|channel| (i, channel)))
+        .map(|(i, channel)| (
+            sender.clone(),
+            receiver.clone(),
+            ChannelName::new(channel).unwrap(),
+            Message::new(format!("{channel} message {i}"), "sign", MessageEncoding::default())
+        ))
+        .collect();
+
+    queue.add_messages(entries).await?;
+
+    let (poll, 3) = queue.poll_messages(receiver.clone(), ChannelName::new("channel b").unwrap().into(), None, None, Some(2)).await? else {
+        panic!("Channel b poll failed");
+    };
+
+    assert_eq!(texts(poll), ["channel b message 1", "channel b message 2"]);
+
+    let (poll, 0) = queue.poll_messages(receiver.clone(), ChannelName::new("channel a").unwrap().into(), None, None, None).await? else {
+        panic!("Channel a poll failed");
+    };
+
+    assert_eq!(texts(poll), (1..=5).map(|i| format!("channel a message {i}")).collect::<Vec<_>>());
+
+    let (poll, 0) = queue.poll_messages(receiver, ChannelName::new("channel b").unwrap().into(), None, None, None).await? else {
+        panic!("Channel b poll failed");
+    };
+
+    assert_eq!(texts(poll), (3..=5).map(|i| format!("channel b message {i}")).collect::<Vec<_>>());
+
+    Ok(())
+}
@@ -0,0 +1,665 @@
+//! Write-ahead log of the stored messages inbox.
+//! 
+//! Every accepted message is appended as a single framed
+//! and checksummed record to the log of the receiver's shard.
+//! Polled messages are marked by appending a consume record,
+//! so the log can always be replayed into the same state.
+//! 
+//! Record frame format:
+//! 
+//! ```text
+//! [payload length: u32][payload crc32: u32][payload]
+//! ```
+//! 
+//! Incomplete or corrupted frames at the end of the log
+//! (torn writes) are dropped during replay.
+
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::SeekFrom;
+use std::time::Duration;
+
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::crypto::asymmetric::PublicKey;
+
+use crate::rest_api::types::ChannelName;
+
+use crate::drivers::server::layout::StorageLayout;
+
+const HEADER_LEN: usize = 8;
+
+const RECORD_ADD: u8 = 0;
+const RECORD_CONSUME: u8 = 1;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// When appended records are flushed to the disk.
+pub enum FsyncPolicy {
+    /// Sync the log after each appended record.
+    /// 
+    /// Accepted messages are never lost.
+    #[default]
+    Always,
+
+    /// Sync the log on append if the last sync happened
+    /// more than given time ago.
+    /// 
+    /// Messages accepted within this time window
+    /// can be lost on power failure.
+    Interval(Duration),
+
+    /// Never sync the log, relying on the OS.
+    Never
+}
+
+/// Calculate CRC-32 (IEEE) checksum of the data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg());
+        }
+    }
+
+    !crc
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Record {
+    Add {
+        id: u64,
+        receiver: PublicKey,
+        channel: ChannelName,
+        info: Vec<u8>
+    },
+
+    Consume {
+        receiver: PublicKey,
+        channel: ChannelName,
+        ids: Vec<u64>
+    }
+}
+
+impl Record {
+    fn encode_key(payload: &mut Vec<u8>, receiver: &PublicKey, channel: &ChannelName) {
+        let channel = channel.as_str().as_bytes();
+
+        payload.extend_from_slice(&receiver.to_bytes());
+        payload.extend_from_slice(&(channel.len() as u16).to_be_bytes());
+        payload.extend_from_slice(channel);
+    }
+
+    fn decode_key(payload: &[u8]) -> Option<(PublicKey, ChannelName, &[u8])> {
+        let receiver = PublicKey::from_bytes(payload.get(..33)?).ok()?;
+
+        let channel_len = u16::from_be_bytes(payload.get(33..35)?.try_into().ok()?) as usize;
+        let channel = std::str::from_utf8(payload.get(35..35 + channel_len)?).ok()?;
+
+        // Names are validated before the messages are logged
+        Some((receiver, ChannelName::new_unchecked(channel), &payload[35 + channel_len..]))
+    }
+
+    /// Encode the record into a frame.
+    fn to_frame(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+
+        match self {
+            Self::Add { id, receiver, channel, info } => {
+                payload.push(RECORD_ADD);
+                payload.extend_from_slice(&id.to_be_bytes());
+
+                Self::encode_key(&mut payload, receiver, channel);
+
+                payload.extend_from_slice(info);
+            }
+
+            Self::Consume { receiver, channel, ids } => {
+                payload.push(RECORD_CONSUME);
+
+                Self::encode_key(&mut payload, receiver, channel);
+
+                for id in ids {
+                    payload.extend_from_slice(&id.to_be_bytes());
+                }
+            }
+        }
+
+        let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+
+        frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&crc32(&payload).to_be_bytes());
+        frame.extend_from_slice(&payload);
+
+        frame
+    }
+
+    /// Decode record from the frame.
+    /// 
+    /// Return decoded record and length of the frame,
+    /// or `None` if the frame is incomplete or corrupted.
+    fn from_frame(frame: &[u8]) -> Option<(Self, usize)> {
+        let len = u32::from_be_bytes(frame.get(..4)?.try_into().ok()?) as usize;
+        let crc = u32::from_be_bytes(frame.get(4..8)?.try_into().ok()?);
+
+        let payload = frame.get(HEADER_LEN..HEADER_LEN + len)?;
+
+        if crc32(payload) != crc {
+            return None;
+        }
+
+        let record = match *payload.first()? {
+            RECORD_ADD => {
+                let id = u64::from_be_bytes(payload.get(1..9)?.try_into().ok()?);
+
+                let (receiver, channel, info) = Self::decode_key(&payload[9..])?;
+
+                Self::Add {
+                    id,
+                    receiver,
+                    channel,
+                    info: info.to_vec()
+                }
+            }
+
+            RECORD_CONSUME => {
+                let (receiver, channel, ids) = Self::decode_key(&payload[1..])?;
+
+                if ids.len() % 8 != 0 {
+                    return None;
+                }
+
+                Self::Consume {
+                    receiver,
+                    channel,
+                    ids: ids.chunks(8)
+                        .map(|id| u64::from_be_bytes(id.try_into().unwrap()))
+                        .collect()
+                }
+            }
+
+            _ => return None
+        };
+
+        Some((record, HEADER_LEN + len))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    id: u64,
+    offset: u64,
+    len: u64
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    entries: VecDeque<Entry>,
+    ids: HashSet<u64>
+}
+
+#[derive(Debug)]
+struct Shard {
+    file: File,
+    len: u64,
+    records: u64
+}
+
+#[derive(Debug)]
+struct State {
+    shards: HashMap<String, Shard>,
+    queues: HashMap<(PublicKey, ChannelName), Queue>,
+    last_sync: Instant
+}
+
+#[derive(Debug)]
+pub struct WriteAheadLog {
+    folder: PathBuf,
+    fsync: FsyncPolicy,
+    state: Mutex<State>
+}
+
+impl WriteAheadLog {
+    /// Open the log stored in the given folder,
+    /// replaying all its records.
+    pub async fn open(folder: impl Into<PathBuf>, fsync: FsyncPolicy) -> std::io::Result<Self> {
+        let folder = folder.into();
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(?fsync, "Opening messages inbox write-ahead log in {:?}", folder);
+
+        tokio::fs::create_dir_all(&folder).await?;
+
+        let mut state = State {
+            shards: HashMap::new(),
+            queues: HashMap::new(),
+            last_sync: Instant::now()
+        };
+
+        let mut entries = tokio::fs::read_dir(&folder).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+
+            if path.extension().and_then(|ext| ext.to_str()) != Some("log") {
+                continue;
+            }
+
+            let Some(shard) = path.file_stem().and_then(|name| name.to_str()).map(String::from) else {
+                continue;
+            };
+
+            Self::replay(&path, &shard, &mut state).await?;
+        }
+
+        Ok(Self {
+            folder,
+            fsync,
+            state: Mutex::new(state)
+        })
+    }
+
+    /// Replay records of the shard's log.
+    async fn replay(path: &Path, shard: &str, state: &mut State) -> std::io::Result<()> {
+        let log = tokio::fs::read(path).await?;
+
+        let mut offset = 0;
+        let mut records = 0;
+
+        while offset < log.len() {
+            let Some((record, len)) = Record::from_frame(&log[offset..]) else {
+                break;
+            };
+
+            match record {
+                Record::Add { id, receiver, channel, .. } => {
+                    let queue = state.queues.entry((receiver, channel)).or_default();
+
+                    if queue.ids.insert(id) {
+                        queue.entries.push_back(Entry {
+                            id,
+                            offset: offset as u64,
+                            len: len as u64
+                        });
+                    }
+                }
+
+                Record::Consume { receiver, channel, ids } => {
+                    if let Some(queue) = state.queues.get_mut(&(receiver, channel)) {
+                        let ids = ids.into_iter().collect::<HashSet<_>>();
+
+                        queue.entries.retain(|entry| !ids.contains(&entry.id));
+                        queue.ids.retain(|id| !ids.contains(id));
+                    }
+                }
+            }
+
+            offset += len;
+            records += 1;
+        }
+
+        let file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(path).await?;
+
+        // Drop torn tail so new records are appended
+        // right after the last valid one
+        if offset < log.len() {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                shard,
+                valid = offset,
+                total = log.len(),
+                "Dropping torn tail of the write-ahead log"
+            );
+
+            file.set_len(offset as u64).await?;
+            file.sync_all().await?;
+        }
+
+        state.queues.retain(|_, queue| !queue.entries.is_empty());
+
+        state.shards.insert(shard.to_string(), Shard {
+            file,
+            len: offset as u64,
+            records
+        });
+
+        Ok(())
+    }
+
+    #[inline]
+    fn shard_path(&self, shard: &str) -> PathBuf {
+        self.folder.join(format!("{shard}.log"))
+    }
+
+    /// Append frame to the shard's log.
+    /// 
+    /// Return offset of the appended frame.
+    async fn append(&self, state: &mut State, shard: &str, frame: &[u8]) -> std::io::Result<u64> {
+        if !state.shards.contains_key(shard) {
+            let file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.shard_path(shard)).await?;
+
+            state.shards.insert(shard.to_string(), Shard {
+                len: file.metadata().await?.len(),
+                file,
+                records: 0
+            });
+        }
+
+        let sync = match self.fsync {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Interval(interval) => state.last_sync.elapsed() >= interval,
+            FsyncPolicy::Never => false
+        };
+
+        let log = state.shards.get_mut(shard).unwrap();
+        let offset = log.len;
+
+        log.file.write_all(frame).await?;
+        log.file.flush().await?;
+
+        if sync {
+            log.file.sync_data().await?;
+        }
+
+        log.len += frame.len() as u64;
+        log.records += 1;
+
+        if sync {
+            state.last_sync = Instant::now();
+        }
+
+        Ok(offset)
+    }
+
+    /// Append message to the log.
+    /// 
+    /// Messages with already stored ids are ignored.
+    /// Return `true` if the message was appended.
+    pub async fn add(&self, id: u64, receiver: PublicKey, channel: ChannelName, info: Vec<u8>) -> std::io::Result<bool> {
+        let mut state = self.state.lock().await;
+
+        let key = (receiver, channel);
+
+        if state.queues.get(&key).is_some_and(|queue| queue.ids.contains(&id)) {
+            return Ok(false);
+        }
+
+        let (receiver, channel) = key;
+
+        let shard = StorageLayout::shard(&receiver);
+
+        let frame = Record::Add {
+            id,
+            receiver: receiver.clone(),
+            channel: channel.clone(),
+            info
+        }.to_frame();
+
+        let offset = self.append(&mut state, &shard, &frame).await?;
+
+        let queue = state.queues.entry((receiver, channel)).or_default();
+
+        queue.ids.insert(id);
+        queue.entries.push_back(Entry {
+            id,
+            offset,
+            len: frame.len() as u64
+        });
+
+        Ok(true)
+    }
+
+    /// Read stored messages without consuming them.
+    /// 
+    /// Return list of message ids with their content
+    /// and total number of stored messages.
+    pub async fn peek(&self, receiver: &PublicKey, channel: &ChannelName, limit: Option<u64>) -> std::io::Result<(Vec<(u64, Vec<u8>)>, u64)> {
+        let state = self.state.lock().await;
+
+        let Some(queue) = state.queues.get(&(receiver.clone(), channel.clone())) else {
+            return Ok((vec![], 0));
+        };
+
+        let limit = limit.unwrap_or(u64::MAX).min(queue.entries.len() as u64) as usize;
+
+        let mut file = File::open(self.shard_path(&StorageLayout::shard(receiver))).await?;
+        let mut messages = Vec::with_capacity(limit);
+
+        for entry in queue.entries.iter().take(limit) {
+            let mut frame = vec![0; entry.len as usize];
+
+            file.seek(SeekFrom::Start(entry.offset)).await?;
+            file.read_exact(&mut frame).await?;
+
+            let Some((Record::Add { id, info, .. }, _)) = Record::from_frame(&frame) else {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Write-ahead log record is corrupted"));
+            };
+
+            messages.push((id, info));
+        }
+
+        Ok((messages, queue.entries.len() as u64))
+    }
+
+    /// Mark messages with given ids as consumed.
+    /// 
+    /// Return number of remaining messages.
+    pub async fn consume(&self, receiver: &PublicKey, channel: &ChannelName, ids: &[u64]) -> std::io::Result<u64> {
+        let mut state = self.state.lock().await;
+
+        let key = (receiver.clone(), channel.clone());
+
+        if !ids.is_empty() {
+            let frame = Record::Consume {
+                receiver: receiver.clone(),
+                channel: channel.clone(),
+                ids: ids.to_vec()
+            }.to_frame();
+
+            self.append(&mut state, &StorageLayout::shard(receiver), &frame).await?;
+        }
+
+        let Some(queue) = state.queues.get_mut(&key) else {
+            return Ok(0);
+        };
+
+        let ids = ids.iter().collect::<HashSet<_>>();
+
+        queue.entries.retain(|entry| !ids.contains(&entry.id));
+        queue.ids.retain(|id| !ids.contains(id));
+
+        let remaining = queue.entries.len() as u64;
+
+        if remaining == 0 {
+            state.queues.remove(&key);
+        }
+
+        Ok(remaining)
+    }
+
+    /// Get names of the receiver's channels
+    /// which have stored messages.
+    pub async fn channels(&self, receiver: &PublicKey) -> Vec<ChannelName> {
+        self.state.lock().await
+            .queues.keys()
+            .filter(|(queue_receiver, _)| queue_receiver == receiver)
+            .map(|(_, channel)| channel.clone())
+            .collect()
+    }
+
+    /// Get receivers and channels of all the stored
+    /// queues with amounts of their messages and bytes.
+    pub async fn queues(&self) -> Vec<(PublicKey, ChannelName, u64, u64)> {
+        self.state.lock().await
+            .queues.iter()
+            .map(|((receiver, channel), queue)| (
+                receiver.clone(),
+                channel.clone(),
+                queue.entries.len() as u64,
+                queue.entries.iter().map(|entry| entry.len).sum()
+            ))
+            .collect()
+    }
+
+    /// Sync all the shards' logs to the disk.
+    pub async fn sync(&self) -> std::io::Result<()> {
+        let mut state = self.state.lock().await;
+
+        for shard in state.shards.values() {
+            shard.file.sync_data().await?;
+        }
+
+        state.last_sync = Instant::now();
+
+        Ok(())
+    }
+
+    /// Rewrite shards' logs dropping consumed records.
+    /// 
+    /// Each log is rewritten into a temporary file which
+    /// atomically replaces the original one, so this method
+    /// is safe to interrupt.
+    /// 
+    /// Return number of dropped records.
+    pub async fn compact(&self) -> std::io::Result<u64> {
+        let mut state = self.state.lock().await;
+        let mut dropped = 0;
+
+        let shards = state.shards.keys().cloned().collect::<Vec<_>>();
+
+        for shard in shards {
+            // Collect live entries of the shard in the log order
+            let mut live = state.queues.iter()
+                .filter(|((receiver, _), _)| StorageLayout::shard(receiver) == shard)
+                .flat_map(|(_, queue)| queue.entries.iter().copied())
+                .collect::<Vec<_>>();
+
+            let records = state.shards[&shard].records;
+
+            if live.len() as u64 == records {
+                continue;
+            }
+
+            live.sort_by_key(|entry| entry.offset);
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                shard,
+                records,
+                live = live.len(),
+                "Compacting write-ahead log"
+            );
+
+            let path = self.shard_path(&shard);
+            let temp_path = path.with_extension("log.compact");
+
+            let mut source = File::open(&path).await?;
+            let mut target = File::create(&temp_path).await?;
+
+            let mut offsets = HashMap::with_capacity(live.len());
+            let mut len = 0;
+
+            for entry in &live {
+                let mut frame = vec![0; entry.len as usize];
+
+                source.seek(SeekFrom::Start(entry.offset)).await?;
+                source.read_exact(&mut frame).await?;
+
+                target.write_all(&frame).await?;
+
+                offsets.insert(entry.offset, len);
+
+                len += entry.len;
+            }
+
+            target.flush().await?;
+            target.sync_all().await?;
+
+            drop(target);
+
+            tokio::fs::rename(&temp_path, &path).await?;
+
+            let file = tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(&path).await?;
+
+            for ((receiver, _), queue) in state.queues.iter_mut() {
+                if StorageLayout::shard(receiver) == shard {
+                    for entry in queue.entries.iter_mut() {
+                        entry.offset = offsets[&entry.offset];
+                    }
+                }
+            }
+
+            dropped += records - live.len() as u64;
+
+            state.shards.insert(shard, Shard {
+                file,
+                len,
+                records: live.len() as u64
+            });
+        }
+
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::asymmetric::SecretKey;
+
+    use super::*;
+
+    #[test]
+    fn crc32() {
+        assert_eq!(super::crc32(b""), 0);
+        assert_eq!(super::crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn frames() {
+        let receiver = SecretKey::random().public_key();
+        let channel = ChannelName::new("channel").unwrap();
+
+        let records = [
+            Record::Add {
+                id: 123,
+                receiver: receiver.clone(),
+                channel: channel.clone(),
+                info: b"Hello, World!".to_vec()
+            },
+            Record::Consume {
+                receiver,
+                channel,
+                ids: vec![1, 2, 3]
+            }
+        ];
+
+        for record in records {
+            let frame = record.to_frame();
+
+            assert_eq!(Record::from_frame(&frame), Some((record, frame.len())));
+
+            // Torn frames are never decoded
+            for len in 0..frame.len() {
+                assert_eq!(Record::from_frame(&frame[..len]), None);
+            }
+
+            let mut corrupted = frame.clone();
+
+            *corrupted.last_mut().unwrap() ^= 1;
+
+            assert_eq!(Record::from_frame(&corrupted), None);
+        }
+    }
+}
@@ -3,29 +3,196 @@ mod params;
 #[allow(clippy::module_inception)]
 mod server;
 
+pub mod layout;
 pub mod router;
 pub mod traversal;
 pub mod messages_inbox;
+pub mod reputation;
+pub mod usage;
+pub mod blacklist;
+pub mod bootstrap;
+pub mod replay;
+pub mod rate_limit;
+pub mod receipts;
 
-pub use params::ServerParams;
+#[cfg(feature = "long-poll")]
+pub mod waiters;
+
+#[cfg(any(feature = "health-checks", feature = "maintenance"))]
+pub(crate) mod health;
+
+#[cfg(feature = "maintenance")]
+pub mod maintenance;
+
+pub use params::{
+    ServerParams,
+    AnnounceFanout,
+    AnnounceFanoutParams,
+    AnnounceLimits,
+    WebhookEventKind,
+    WebhookFilter,
+    Webhook,
+    WebhooksParams,
+    HealthCheckParams,
+    BootstrapParams,
+    StatsPrivacy,
+    ReplayProtection,
+    LegacyRequests,
+    UnsignedBodies
+};
 pub use server::ServerDriver;
 
 pub mod prelude {
     pub use super::{
         ServerDriver,
-        ServerParams
+        ServerParams,
+        AnnounceFanout,
+        AnnounceFanoutParams,
+        AnnounceLimits,
+        WebhookEventKind,
+        WebhookFilter,
+        Webhook,
+        WebhooksParams,
+        HealthCheckParams,
+        BootstrapParams,
+        StatsPrivacy,
+        ReplayProtection,
+        LegacyRequests,
+        UnsignedBodies
+    };
+
+    pub use super::layout::StorageLayout;
+
+    pub use super::router::{
+        Router,
+        RouterLimits,
+        RouterEviction,
+        RouterRecord,
+        RouterEvent,
+        RouterObserver,
+        RouterSnapshot,
+        SnapshotImport,
+        RouterMetrics,
+        HintStats,
+        ServerHealth,
+        DEFAULT_AVAILABILITY_TIMEOUT
+    };
+    pub use super::traversal::{Traversal, VisitedServer};
+
+    #[cfg(feature = "maintenance")]
+    pub use super::maintenance::{MaintenanceConfig, MaintenanceHandle};
+    pub use super::messages_inbox::{
+        MessagesInbox,
+        InboxStats,
+        InboxObserver,
+        ObserverError,
+        ChannelPriorities
+    };
+
+    pub use super::reputation::{
+        ReputationProvider,
+        ReputationPolicy,
+        ReputationAction,
+        Incident
+    };
+
+    pub use super::usage::{
+        UsageTracker,
+        UsageEvent,
+        Usage
     };
 
-    pub use super::router::Router;
-    pub use super::traversal::Traversal;
-    pub use super::messages_inbox::MessagesInbox;
+    pub use super::blacklist::{
+        Blacklist,
+        BlacklistError
+    };
+
+    pub use super::bootstrap::{
+        BootstrapSummary,
+        BootstrapError
+    };
+
+    pub use super::replay::{
+        NonceCache,
+        ReplayError
+    };
+
+    pub use super::rate_limit::{
+        RateLimiter,
+        RateLimits,
+        RateLimit,
+        RateLimitExceeded
+    };
+
+    pub use super::receipts::{
+        PendingReceipts,
+        PendingReceipt
+    };
+
+    #[cfg(feature = "long-poll")]
+    pub use super::waiters::{
+        InboxWaiters,
+        InboxWaiter
+    };
 
     #[cfg(feature = "router-global-table")]
     pub use super::router::global_table::GlobalTableRouter;
 
+    #[cfg(feature = "router-ram")]
+    pub use super::router::ram::RamRouter;
+
+    #[cfg(feature = "router-stored")]
+    pub use super::router::stored::StoredRouter;
+
     #[cfg(feature = "traversal-bfs-recursion")]
     pub use super::traversal::bfs_recursion::BfsRecursionTraversal;
 
+    #[cfg(feature = "traversal-bfs")]
+    pub use super::traversal::bfs::{BfsTraversal, TraversalLookup};
+
+    #[cfg(feature = "traversal-bfs")]
+    pub use super::traversal::cache::{TraversalCache, RouteCache};
+
+    #[cfg(feature = "traversal-bfs")]
+    pub use super::traversal::observer::{
+        TraversalObserver,
+        TraversalEvent,
+        NoopTraversalObserver
+    };
+
+    #[cfg(feature = "traversal-parallel")]
+    pub use super::traversal::parallel::{ParallelTraversal, TraversalSummary, TraversalError};
+
+    #[cfg(feature = "traversal-parallel")]
+    pub use super::traversal::strategy::{StrategyTraversal, TraversalStrategy};
+
+    #[cfg(feature = "inbox-ram")]
+    pub use super::messages_inbox::ram::RamMessagesInbox;
+
     #[cfg(feature = "inbox-stored-queue")]
     pub use super::messages_inbox::stored_queue::StoredQueueMessagesInbox;
+
+    #[cfg(feature = "inbox-stored-queue")]
+    pub use super::messages_inbox::stored_queue::InboxQuota;
+
+    #[cfg(feature = "inbox-stored-queue")]
+    pub use super::messages_inbox::stored_queue::EvictionPolicy;
+
+    #[cfg(feature = "inbox-stored-queue")]
+    pub use super::messages_inbox::stored_queue::CompactionSummary;
+
+    #[cfg(feature = "inbox-stored-queue")]
+    pub use super::messages_inbox::stored_queue::ArchiveConfig;
+
+    #[cfg(feature = "inbox-stored-queue")]
+    pub use super::messages_inbox::wal::FsyncPolicy;
+
+    #[cfg(feature = "inbox-sqlite")]
+    pub use super::messages_inbox::sqlite::SqliteMessagesInbox;
+
+    #[cfg(feature = "reputation-decaying")]
+    pub use super::reputation::decaying::DecayingReputation;
+
+    #[cfg(feature = "usage-hourly")]
+    pub use super::usage::hourly::HourlyUsage;
 }
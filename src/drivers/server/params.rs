@@ -1,4 +1,11 @@
-use crate::crypto::asymmetric::SecretKey;
+use std::time::Duration;
+
+use crate::address::Address;
+use crate::crypto::asymmetric::{SecretKey, PublicKey};
+use crate::discovery::DiscoveryParams;
+use crate::http::server::{BodyLimits, CorsPolicy};
+
+use super::reputation::ReputationPolicy;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ServerParams {
@@ -8,14 +15,466 @@ pub struct ServerParams {
     /// 
     /// This is needed when we perform requests
     /// from the server as a `server(addresss)` client.
-    pub address: String
+    pub address: String,
+
+    /// Automatic announcement of the connected
+    /// local clients to other known servers.
+    pub announce_fanout: AnnounceFanoutParams,
+
+    /// Limits applied to the entries of the
+    /// incoming announce requests.
+    pub announce_limits: AnnounceLimits,
+
+    /// Actions applied to the keys with low reputation.
+    /// 
+    /// Used only when the server driver has
+    /// a reputation provider.
+    pub reputation: ReputationPolicy,
+
+    /// Notifications about server events
+    /// sent to external HTTP endpoints.
+    pub webhooks: WebhooksParams,
+
+    /// Lease messages returned by the plain polls
+    /// instead of removing them from the inbox.
+    /// 
+    /// Leased messages are removed only when the client
+    /// acknowledges them by the `POST /api/v1/ack` request,
+    /// and are returned again by the next polls after the
+    /// lease expires. If not set, or the inbox doesn't
+    /// support leases, polled messages are removed
+    /// immediately, as expected by the clients
+    /// which never acknowledge them.
+    pub poll_lease: Option<Duration>,
+
+    /// Maximal time the long polls wait for new
+    /// messages when the client's inbox is empty.
+    /// 
+    /// Longer waits requested by the clients are
+    /// shortened to this time. Polls never wait if
+    /// `None`. Default is 30 seconds.
+    /// 
+    /// Used only with the `long-poll` feature.
+    pub max_poll_wait: Option<Duration>,
+
+    /// Maximal size of the sent message's
+    /// content in bytes.
+    /// 
+    /// Larger messages are rejected before they're
+    /// added to the inbox. Default is 8 MiB.
+    pub max_message_size: usize,
+
+    /// Maximal amount of the messages sent
+    /// by a single `POST /api/v1/send_batch` request.
+    /// 
+    /// Larger batches are rejected entirely. Default is 64.
+    pub max_batch_size: usize,
+
+    /// Maximal amount of the clients searched
+    /// by a single `POST /api/v1/lookup_batch` request.
+    /// 
+    /// Larger batches are rejected entirely. Default is 64.
+    pub max_lookup_batch_size: usize,
+
+    /// Maximal sizes of the POST requests' bodies
+    /// in bytes, with per-route overrides.
+    /// 
+    /// Larger bodies are rejected before they're
+    /// deserialized. Default is 16 MiB for all the routes.
+    pub body_limits: BodyLimits,
+
+    /// Cross-origin requests allowed from the
+    /// browser-based clients.
+    /// 
+    /// Server answers `OPTIONS` preflight requests
+    /// and attaches the `Access-Control-Allow-*` headers
+    /// to all its responses. Disabled if `None`,
+    /// which is the default.
+    pub cors: Option<CorsPolicy>,
+
+    /// Advertisement of the server in the local
+    /// network over mDNS.
+    /// 
+    /// Used only with the `mdns` feature.
+    pub local_discovery: Option<DiscoveryParams>,
+
+    /// Periodic reachability checks of the known servers.
+    /// 
+    /// Used only with the `health-checks` feature.
+    pub health_checks: Option<HealthCheckParams>,
+
+    /// Servers contacted on startup to fill
+    /// the empty routing table.
+    pub bootstrap: BootstrapParams,
+
+    /// Statistics hidden from the
+    /// `GET /api/v1/stats` responses.
+    pub stats_privacy: StatsPrivacy,
+
+    /// Rejection of the expired and replayed
+    /// signed requests. Disabled if `None`.
+    pub replay_protection: Option<ReplayProtection>,
+
+    /// Handling of the signed requests
+    /// without body signature.
+    /// 
+    /// Unsigned bodies are rejected by default. Accepting
+    /// them lets old clients connect, but allows a downgrade:
+    /// anyone forwarding a request can strip its `body_sign`
+    /// and change the body, e.g. the message or its receiver,
+    /// and the request is still accepted by its proof.
+    pub unsigned_bodies: UnsignedBodies,
+
+    /// Maximal lifetime of the local clients'
+    /// connection certificates.
+    /// 
+    /// Certificates expire after this time since their
+    /// connection tokens were made, even if they don't
+    /// have expiration date. Clients should renew them
+    /// by connecting again. Certificates never expire
+    /// by the server if `None`.
+    pub certificate_lifetime: Option<Duration>,
+
+    /// Proof of work difficulty of the new clients'
+    /// connections, in leading zero bits.
+    /// 
+    /// New clients must solve the challenge advertised by
+    /// the `GET /api/v1/info` response before they're
+    /// connected. Already connected clients renew their
+    /// certificates without it. Clients are not challenged
+    /// if it's 0, which is the default.
+    pub connect_difficulty: u32
 }
 
 impl Default for ServerParams {
     fn default() -> Self {
         Self {
             secret_key: SecretKey::random(),
-            address: String::from("127.0.0.1:8001")
+            address: String::from("127.0.0.1:8001"),
+            announce_fanout: AnnounceFanoutParams::default(),
+            announce_limits: AnnounceLimits::default(),
+            reputation: ReputationPolicy::default(),
+            webhooks: WebhooksParams::default(),
+            poll_lease: None,
+            max_poll_wait: Some(Duration::from_secs(30)),
+            max_message_size: 8 * 1024 * 1024,
+            max_batch_size: 64,
+            max_lookup_batch_size: 64,
+            body_limits: BodyLimits::default(),
+            cors: None,
+            local_discovery: None,
+            health_checks: None,
+            bootstrap: BootstrapParams::default(),
+            stats_privacy: StatsPrivacy::default(),
+            replay_protection: Some(ReplayProtection::default()),
+            unsigned_bodies: UnsignedBodies::default(),
+            certificate_lifetime: None,
+            connect_difficulty: 0
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Selection of servers to which connected
+/// local clients are announced.
+pub enum AnnounceFanout {
+    /// Don't announce connected clients.
+    #[default]
+    Disabled,
+
+    /// Announce connected clients to the given
+    /// amount of random known servers.
+    Random(usize),
+
+    /// Announce connected clients to all the known servers.
+    All
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnnounceFanoutParams {
+    /// Servers to which connected clients are announced.
+    pub mode: AnnounceFanout,
+
+    /// Maximal amount of simultaneously performed
+    /// announce requests.
+    pub concurrency: usize,
+
+    /// Amount of additional attempts to announce
+    /// the client if the request has failed.
+    pub retries: u32,
+
+    /// Delay before the first retry. It is doubled
+    /// after each next failed attempt.
+    pub backoff: Duration,
+
+    /// Reconnections of the same client within this
+    /// time window are not announced again.
+    /// 
+    /// Records received from other servers are
+    /// not forwarded again within this window as well.
+    pub dedupe_window: Duration,
+
+    /// Maximal amount of servers which can forward
+    /// the same announce.
+    /// 
+    /// Announced records received from other servers
+    /// are forwarded to the servers selected by `mode`
+    /// until they pass this amount of hops. Received
+    /// announces are not forwarded if it's 0.
+    pub hop_limit: u32
+}
+
+impl Default for AnnounceFanoutParams {
+    fn default() -> Self {
+        Self {
+            mode: AnnounceFanout::Disabled,
+            concurrency: 8,
+            retries: 3,
+            backoff: Duration::from_millis(500),
+            dedupe_window: Duration::from_secs(60),
+            hop_limit: 3
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnnounceLimits {
+    /// Maximal size of the serialized
+    /// announced client record in bytes.
+    pub max_client_size: usize,
+
+    /// Maximal size of the serialized
+    /// announced server record in bytes.
+    pub max_server_size: usize,
+
+    /// Maximal amount of entries
+    /// in a bulk announce request.
+    pub max_entries: usize
+}
+
+impl Default for AnnounceLimits {
+    fn default() -> Self {
+        Self {
+            max_client_size: 4096,
+            max_server_size: 1024,
+            max_entries: 1000
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Type of the server event reported by webhooks.
+pub enum WebhookEventKind {
+    /// Local client has connected to the server.
+    ClientConnected,
+
+    /// Remote client was announced to the server.
+    ClientAnnounced,
+
+    /// Remote server was announced to the server.
+    ServerAnnounced,
+
+    /// Message was sent to the server's inbox.
+    MessageReceived
+}
+
+impl WebhookEventKind {
+    /// Get name of the event used in webhook payloads.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ClientConnected => "client_connected",
+            Self::ClientAnnounced => "client_announced",
+            Self::ServerAnnounced => "server_announced",
+            Self::MessageReceived => "message_received"
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebhookFilter {
+    /// Events reported by the webhook.
+    /// 
+    /// Empty list means all the events.
+    pub events: Vec<WebhookEventKind>,
+
+    /// Keys involved in the reported events: receivers
+    /// and senders of the messages, announced clients
+    /// and servers.
+    /// 
+    /// Empty list means any keys.
+    pub keys: Vec<PublicKey>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Webhook {
+    /// Events reported by this webhook.
+    pub filter: WebhookFilter,
+
+    /// URL to which events are sent by POST requests.
+    pub url: String,
+
+    /// Shared secret used to sign the events.
+    /// 
+    /// HMAC-SHA256 of the request body is sent
+    /// in the `X-Hyperborea-Signature` header.
+    pub secret: Vec<u8>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebhooksParams {
+    /// List of the webhooks.
+    pub hooks: Vec<Webhook>,
+
+    /// Amount of additional delivery attempts
+    /// if the request has failed.
+    pub retries: u32,
+
+    /// Delay before the first retry. It is doubled
+    /// after each next failed attempt.
+    pub backoff: Duration
+}
+
+impl Default for WebhooksParams {
+    fn default() -> Self {
+        Self {
+            hooks: vec![],
+            retries: 5,
+            backoff: Duration::from_secs(1)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HealthCheckParams {
+    /// Delay between checks of all the known servers.
+    pub interval: Duration,
+
+    /// Time after which not responding server
+    /// is marked as unhealthy.
+    pub timeout: Duration
+}
+
+impl Default for HealthCheckParams {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5 * 60),
+            timeout: Duration::from_secs(10)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BootstrapParams {
+    /// Addresses of the bootstrap servers.
+    /// 
+    /// Only `http://<address>` and raw addresses
+    /// are supported.
+    pub servers: Vec<Address>,
+
+    /// Amount of retries of the unreachable
+    /// bootstrap servers.
+    pub retries: usize,
+
+    /// Delay before the first retry.
+    /// 
+    /// Doubled after every failed retry.
+    pub backoff: Duration,
+
+    /// Maximal delay between retries.
+    pub max_backoff: Duration
+}
+
+impl Default for BootstrapParams {
+    fn default() -> Self {
+        Self {
+            servers: vec![],
+            retries: 8,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5 * 60)
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Statistics hidden from the `GET /api/v1/stats` responses.
+/// 
+/// Uptime and the server's public key are always returned.
+pub struct StatsPrivacy {
+    /// Hide amount of the connected clients.
+    pub hide_clients: bool,
+
+    /// Hide amount of the known servers.
+    pub hide_servers: bool,
+
+    /// Hide statistics of the messages inbox.
+    pub hide_inbox: bool
+}
+
+impl StatsPrivacy {
+    /// Hide all the optional statistics.
+    pub const PRIVATE: Self = Self {
+        hide_clients: true,
+        hide_servers: true,
+        hide_inbox: true
+    };
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Handling of the old format requests
+/// which don't have a timestamp.
+pub enum LegacyRequests {
+    /// Accept legacy requests for the migration period.
+    /// 
+    /// They're still checked by the nonce cache,
+    /// but can be replayed once forgotten by it.
+    #[default]
+    Accept,
+
+    /// Reject legacy requests.
+    Reject
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Handling of the requests made by old clients
+/// whose bodies are not signed.
+/// 
+/// Bodies of such requests can be changed by
+/// anyone who forwards them to the server.
+pub enum UnsignedBodies {
+    /// Accept unsigned bodies for the migration period.
+    Accept,
+
+    /// Reject requests without body signature.
+    #[default]
+    Reject
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Protection from the captured signed requests
+/// which are sent to the server again.
+pub struct ReplayProtection {
+    /// Maximal difference between the request's
+    /// timestamp and the server's time.
+    pub window: Duration,
+
+    /// Maximal amount of the remembered (public key,
+    /// proof seed) pairs of the recent requests.
+    /// 
+    /// Pairs are forgotten once their requests
+    /// are outside of the window. New requests are
+    /// rejected while the cache is full.
+    pub nonce_cache_size: usize,
+
+    /// Handling of the requests without timestamp.
+    pub legacy_requests: LegacyRequests
+}
+
+impl Default for ReplayProtection {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(5 * 60),
+            nonce_cache_size: 65536,
+            legacy_requests: LegacyRequests::default()
         }
     }
 }
@@ -0,0 +1,423 @@
+use std::collections::{HashMap, BTreeMap};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::crypto::asymmetric::PublicKey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Token bucket parameters.
+pub struct RateLimit {
+    /// Maximal amount of tokens in the bucket.
+    /// 
+    /// This is the amount of requests which can
+    /// be sent at once after a long break.
+    pub capacity: u64,
+
+    /// Time needed to add one token to the bucket.
+    pub refill_interval: Duration
+}
+
+impl RateLimit {
+    #[inline]
+    pub fn new(capacity: u64, refill_interval: Duration) -> Self {
+        Self {
+            capacity,
+            refill_interval
+        }
+    }
+
+    #[inline]
+    /// Allow `requests` per second with
+    /// bursts of the same size.
+    pub fn per_second(requests: u64) -> Self {
+        Self {
+            capacity: requests,
+            refill_interval: Duration::from_secs(1) / requests.clamp(1, u32::MAX as u64) as u32
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Limits of the `RateLimiter`.
+pub struct RateLimits {
+    /// Limit of the signed `POST` requests
+    /// of every client public key.
+    pub per_key: RateLimit,
+
+    /// Limit of the `GET` requests sent
+    /// from every IP address.
+    pub per_ip: RateLimit,
+
+    /// Limits overriding `per_key` and `per_ip`
+    /// for the given routes, e.g. `/api/v1/announce`.
+    /// 
+    /// Overridden routes have their own buckets
+    /// not shared with the other routes.
+    pub routes: BTreeMap<String, RateLimit>,
+
+    /// Maximal amount of the remembered buckets.
+    /// 
+    /// Buckets of the least recently seen
+    /// keys are forgotten first.
+    pub max_keys: usize
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            per_key: RateLimit::per_second(20),
+            per_ip: RateLimit::per_second(50),
+            routes: BTreeMap::new(),
+            max_keys: 65536
+        }
+    }
+}
+
+impl RateLimits {
+    #[inline]
+    /// Override limit of the given route.
+    pub fn with_route(mut self, route: impl ToString, limit: RateLimit) -> Self {
+        self.routes.insert(route.to_string(), limit);
+
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+#[error("Rate limit exceeded, retry after {retry_after:?}")]
+pub struct RateLimitExceeded {
+    /// Time after which the next request is allowed.
+    pub retry_after: Duration
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BucketOwner {
+    PublicKey(PublicKey),
+    Ip(IpAddr)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BucketKey {
+    /// Route with overridden limit.
+    route: Option<String>,
+    owner: BucketOwner
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: u64,
+    updated_at: Instant,
+
+    /// Value of the limiter's clock when
+    /// the bucket was used last time.
+    used_at: u64
+}
+
+impl Bucket {
+    /// Refill the bucket and take one token from it.
+    fn take(&mut self, limit: &RateLimit, now: Instant) -> Result<(), RateLimitExceeded> {
+        if limit.refill_interval.is_zero() {
+            return Ok(());
+        }
+
+        // Capacity could be lowered since the last use
+        self.tokens = self.tokens.min(limit.capacity);
+
+        let elapsed = now.saturating_duration_since(self.updated_at);
+        let refilled = elapsed.as_nanos() / limit.refill_interval.as_nanos();
+
+        if refilled > 0 {
+            self.tokens = self.tokens.saturating_add(refilled.min(u64::MAX as u128) as u64);
+
+            if self.tokens >= limit.capacity {
+                self.tokens = limit.capacity;
+                self.updated_at = now;
+            } else {
+                // Keep the time already spent on the next token
+                self.updated_at += limit.refill_interval * refilled.min(u32::MAX as u128) as u32;
+            }
+        }
+
+        if self.tokens == 0 {
+            let elapsed = now.saturating_duration_since(self.updated_at);
+
+            return Err(RateLimitExceeded {
+                retry_after: limit.refill_interval.saturating_sub(elapsed)
+            });
+        }
+
+        self.tokens -= 1;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct RateLimiterState {
+    buckets: HashMap<BucketKey, Bucket>,
+
+    /// Buckets' keys ordered by their last use.
+    lru: BTreeMap<u64, BucketKey>,
+
+    clock: u64
+}
+
+#[derive(Debug, Clone)]
+/// Token bucket rate limiter of the server's requests.
+/// 
+/// Signed requests are limited by their public keys,
+/// and unsigned ones by the IP addresses they were
+/// sent from. Clones of the limiter share the same
+/// buckets and limits.
+pub struct RateLimiter {
+    limits: Arc<RwLock<RateLimits>>,
+    state: Arc<Mutex<RateLimiterState>>
+}
+
+impl RateLimiter {
+    #[inline]
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits: Arc::new(RwLock::new(limits)),
+            state: Arc::new(Mutex::new(RateLimiterState::default()))
+        }
+    }
+
+    #[inline]
+    pub fn limits(&self) -> RateLimits {
+        self.limits.read()
+            .expect("Failed to lock rate limiter")
+            .clone()
+    }
+
+    #[inline]
+    /// Replace limits of the limiter.
+    /// 
+    /// Remembered buckets are kept, so clients
+    /// don't get a fresh burst after the update.
+    pub fn set_limits(&self, limits: RateLimits) {
+        *self.limits.write().expect("Failed to lock rate limiter") = limits;
+    }
+
+    #[inline]
+    /// Take a token from the bucket of the given
+    /// public key for the request to the given route.
+    pub fn check_key(&self, route: &str, key: &PublicKey) -> Result<(), RateLimitExceeded> {
+        self.check_key_at(route, key, Instant::now())
+    }
+
+    #[inline]
+    /// Take a token from the bucket of the given
+    /// IP address for the request to the given route.
+    pub fn check_ip(&self, route: &str, address: IpAddr) -> Result<(), RateLimitExceeded> {
+        self.check_ip_at(route, address, Instant::now())
+    }
+
+    #[inline]
+    /// Same as `check_key`, but with given current time.
+    pub fn check_key_at(&self, route: &str, key: &PublicKey, now: Instant) -> Result<(), RateLimitExceeded> {
+        self.check(route, BucketOwner::PublicKey(key.clone()), |limits| limits.per_key, now)
+    }
+
+    #[inline]
+    /// Same as `check_ip`, but with given current time.
+    pub fn check_ip_at(&self, route: &str, address: IpAddr, now: Instant) -> Result<(), RateLimitExceeded> {
+        self.check(route, BucketOwner::Ip(address), |limits| limits.per_ip, now)
+    }
+
+    fn check(&self, route: &str, owner: BucketOwner, default: impl FnOnce(&RateLimits) -> RateLimit, now: Instant) -> Result<(), RateLimitExceeded> {
+        let limits = self.limits.read()
+            .expect("Failed to lock rate limiter");
+
+        let (key, limit) = match limits.routes.get(route) {
+            Some(limit) => (BucketKey { route: Some(route.to_string()), owner }, *limit),
+            None => (BucketKey { route: None, owner }, default(&limits))
+        };
+
+        let max_keys = limits.max_keys;
+
+        drop(limits);
+
+        let mut state = self.state.lock()
+            .expect("Failed to lock rate limiter");
+
+        state.clock += 1;
+
+        let clock = state.clock;
+
+        let mut bucket = match state.buckets.get(&key) {
+            Some(bucket) => {
+                let bucket = *bucket;
+
+                state.lru.remove(&bucket.used_at);
+
+                bucket
+            }
+
+            None => {
+                // Forget the least recently used buckets
+                while !state.buckets.is_empty() && state.buckets.len() >= max_keys {
+                    let Some((_, key)) = state.lru.pop_first() else {
+                        break;
+                    };
+
+                    state.buckets.remove(&key);
+                }
+
+                Bucket {
+                    tokens: limit.capacity,
+                    updated_at: now,
+                    used_at: clock
+                }
+            }
+        };
+
+        let result = bucket.take(&limit, now);
+
+        bucket.used_at = clock;
+
+        if max_keys > 0 {
+            state.lru.insert(clock, key.clone());
+            state.buckets.insert(key, bucket);
+        }
+
+        result
+    }
+
+    #[inline]
+    /// Get amount of the remembered buckets.
+    pub fn len(&self) -> usize {
+        self.state.lock()
+            .expect("Failed to lock rate limiter")
+            .buckets.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for RateLimiter {
+    #[inline]
+    fn default() -> Self {
+        Self::new(RateLimits::default())
+    }
+}
+
+impl PartialEq for RateLimiter {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Eq for RateLimiter {}
+
+impl std::hash::Hash for RateLimiter {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.state) as *const () as usize).hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn check() {
+        let limiter = RateLimiter::new(RateLimits {
+            per_key: RateLimit::new(2, Duration::from_secs(1)),
+            per_ip: RateLimit::new(1, Duration::from_secs(10)),
+            routes: BTreeMap::new(),
+            max_keys: 16
+        }.with_route("/api/v1/announce", RateLimit::new(1, Duration::from_secs(60))));
+
+        let key = SecretKey::random().public_key();
+        let now = Instant::now();
+
+        // Bursts are limited by the bucket capacity
+        assert!(limiter.check_key_at("/api/v1/send", &key, now).is_ok());
+        assert!(limiter.check_key_at("/api/v1/poll", &key, now).is_ok());
+
+        assert_eq!(
+            limiter.check_key_at("/api/v1/send", &key, now + Duration::from_millis(400)),
+            Err(RateLimitExceeded { retry_after: Duration::from_millis(600) })
+        );
+
+        // Buckets are refilled over time
+        assert!(limiter.check_key_at("/api/v1/send", &key, now + Duration::from_secs(1)).is_ok());
+        assert!(limiter.check_key_at("/api/v1/send", &key, now + Duration::from_secs(1)).is_err());
+
+        // Overridden routes have their own buckets
+        assert!(limiter.check_key_at("/api/v1/announce", &key, now).is_ok());
+        assert!(limiter.check_key_at("/api/v1/announce", &key, now + Duration::from_secs(30)).is_err());
+
+        // Keys and addresses don't share buckets
+        let address = IpAddr::from([127, 0, 0, 1]);
+
+        assert!(limiter.check_ip_at("/api/v1/info", address, now).is_ok());
+        assert!(limiter.check_ip_at("/api/v1/info", address, now).is_err());
+        assert!(limiter.check_key_at("/api/v1/send", &SecretKey::random().public_key(), now).is_ok());
+
+        assert_eq!(limiter.len(), 4);
+    }
+
+    #[test]
+    fn eviction() {
+        let limiter = RateLimiter::new(RateLimits {
+            per_key: RateLimit::new(1, Duration::from_secs(60)),
+            max_keys: 2,
+            ..RateLimits::default()
+        });
+
+        let keys = (0..3)
+            .map(|_| SecretKey::random().public_key())
+            .collect::<Vec<_>>();
+
+        let now = Instant::now();
+
+        assert!(limiter.check_key_at("/api/v1/send", &keys[0], now).is_ok());
+        assert!(limiter.check_key_at("/api/v1/send", &keys[1], now).is_ok());
+
+        // Recently used keys are kept
+        assert!(limiter.check_key_at("/api/v1/send", &keys[0], now).is_err());
+        assert!(limiter.check_key_at("/api/v1/send", &keys[2], now).is_ok());
+
+        assert_eq!(limiter.len(), 2);
+
+        assert!(limiter.check_key_at("/api/v1/send", &keys[0], now).is_err());
+
+        // Idle key's bucket was forgotten
+        assert!(limiter.check_key_at("/api/v1/send", &keys[1], now).is_ok());
+    }
+
+    #[test]
+    fn set_limits() {
+        let limiter = RateLimiter::new(RateLimits {
+            per_key: RateLimit::new(3, Duration::from_secs(60)),
+            ..RateLimits::default()
+        });
+
+        let key = SecretKey::random().public_key();
+        let now = Instant::now();
+
+        assert!(limiter.check_key_at("/api/v1/send", &key, now).is_ok());
+
+        // Clones share the limits
+        limiter.clone().set_limits(RateLimits {
+            per_key: RateLimit::new(1, Duration::from_secs(60)),
+            ..RateLimits::default()
+        });
+
+        assert_eq!(limiter.limits().per_key.capacity, 1);
+
+        // Remaining tokens are capped by the new capacity
+        assert!(limiter.check_key_at("/api/v1/send", &key, now).is_ok());
+        assert!(limiter.check_key_at("/api/v1/send", &key, now).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Message whose sender waits for the `polled` receipt.
+pub struct PendingReceipt {
+    pub sender: PublicKey,
+    pub channel: ChannelName,
+
+    /// Encoding of the original message
+    /// used by the receipt message.
+    pub encoding: MessageEncoding
+}
+
+#[derive(Debug, Default)]
+struct PendingReceiptsState {
+    pending: HashMap<(PublicKey, u64), PendingReceipt>,
+
+    /// Pending messages in order of insertion.
+    order: VecDeque<(PublicKey, u64)>
+}
+
+#[derive(Debug, Clone)]
+/// Messages stored in the server's inbox which
+/// should be reported to their senders once polled.
+/// 
+/// Messages are identified by their receivers and
+/// server-assigned ids. The oldest messages are
+/// forgotten when there are too many of them.
+/// 
+/// Clones share the same messages.
+pub struct PendingReceipts {
+    state: Arc<Mutex<PendingReceiptsState>>,
+    capacity: usize
+}
+
+impl PendingReceipts {
+    #[inline]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PendingReceiptsState::default())),
+            capacity
+        }
+    }
+
+    /// Remember message sent to the given receiver.
+    pub fn insert(&self, receiver: PublicKey, message_id: u64, receipt: PendingReceipt) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock()
+            .expect("Failed to lock pending receipts");
+
+        while state.pending.len() >= self.capacity {
+            let Some(key) = state.order.pop_front() else {
+                break;
+            };
+
+            state.pending.remove(&key);
+        }
+
+        let key = (receiver, message_id);
+
+        if state.pending.insert(key.clone(), receipt).is_none() {
+            state.order.push_back(key);
+        }
+    }
+
+    /// Forget message sent to the given receiver
+    /// and return its pending receipt.
+    pub fn take(&self, receiver: &PublicKey, message_id: u64) -> Option<PendingReceipt> {
+        let mut state = self.state.lock()
+            .expect("Failed to lock pending receipts");
+
+        let key = (receiver.clone(), message_id);
+
+        let receipt = state.pending.remove(&key)?;
+
+        state.order.retain(|pending| pending != &key);
+
+        Some(receipt)
+    }
+
+    #[inline]
+    /// Get amount of the pending receipts.
+    pub fn len(&self) -> usize {
+        self.state.lock()
+            .expect("Failed to lock pending receipts")
+            .pending.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for PendingReceipts {
+    #[inline]
+    fn default() -> Self {
+        Self::new(65536)
+    }
+}
+
+impl PartialEq for PendingReceipts {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Eq for PendingReceipts {}
+
+impl std::hash::Hash for PendingReceipts {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.state) as *const () as usize).hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn insert_take() {
+        let receipts = PendingReceipts::new(2);
+
+        let receiver = SecretKey::random().public_key();
+
+        let receipt = PendingReceipt {
+            sender: SecretKey::random().public_key(),
+            channel: ChannelName::new("channel").unwrap(),
+            encoding: MessageEncoding::default()
+        };
+
+        receipts.insert(receiver.clone(), 1, receipt.clone());
+        receipts.insert(receiver.clone(), 2, receipt.clone());
+
+        assert_eq!(receipts.take(&receiver, 1), Some(receipt.clone()));
+        assert_eq!(receipts.take(&receiver, 1), None);
+
+        // Oldest messages are forgotten
+        receipts.insert(receiver.clone(), 3, receipt.clone());
+        receipts.insert(receiver.clone(), 4, receipt.clone());
+
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(receipts.take(&receiver, 2), None);
+        assert_eq!(receipts.take(&receiver, 4), Some(receipt));
+    }
+}
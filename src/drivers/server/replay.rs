@@ -0,0 +1,255 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::rest_api::status::ResponseStatus;
+
+use super::params::{ReplayProtection, LegacyRequests};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Request has no timestamp")]
+    MissingTimestamp,
+
+    #[error("Request timestamp {timestamp} is outside of the allowed {window} seconds window")]
+    Expired {
+        timestamp: u64,
+        window: u64
+    },
+
+    #[error("Request was already received")]
+    Replayed,
+
+    #[error("Too many recent requests are remembered")]
+    CacheFull
+}
+
+impl ReplayError {
+    /// Get response status of the rejected request.
+    pub fn status(&self) -> ResponseStatus {
+        match self {
+            Self::CacheFull => ResponseStatus::RateLimited,
+            _ => ResponseStatus::RequestExpired
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct NonceCacheState {
+    seen: HashSet<(PublicKey, u64)>,
+
+    /// Remembered pairs in order of insertion
+    /// with the time after which they can be forgotten.
+    order: VecDeque<((PublicKey, u64), u64)>
+}
+
+#[derive(Default, Debug, Clone)]
+/// Recently received (public key, proof seed) pairs
+/// of the signed requests.
+/// 
+/// Clones of the cache share the same pairs.
+pub struct NonceCache(Arc<Mutex<NonceCacheState>>);
+
+impl NonceCache {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check that the request with given header
+    /// is neither expired nor replayed, and remember
+    /// its proof seed.
+    /// 
+    /// Pairs are never forgotten before their requests
+    /// are expired, so new requests are rejected while
+    /// the cache is full.
+    /// 
+    /// - `now` must contain current UTC timestamp
+    ///   of the server, in seconds.
+    pub fn check(
+        &self,
+        params: &ReplayProtection,
+        public_key: &PublicKey,
+        proof_seed: u64,
+        timestamp: Option<u64>,
+        now: u64
+    ) -> Result<(), ReplayError> {
+        let window = params.window.as_secs();
+
+        let forget_at = match timestamp {
+            Some(timestamp) if timestamp.abs_diff(now) > window => {
+                return Err(ReplayError::Expired {
+                    timestamp,
+                    window
+                });
+            }
+
+            // Replays of the forgotten request are expired
+            Some(timestamp) => timestamp.saturating_add(window),
+
+            None if params.legacy_requests == LegacyRequests::Reject => {
+                return Err(ReplayError::MissingTimestamp);
+            }
+
+            None => now.saturating_add(window)
+        };
+
+        if params.nonce_cache_size == 0 {
+            return Ok(());
+        }
+
+        let mut state = self.0.lock()
+            .expect("Failed to lock nonce cache");
+
+        // Pairs are ordered by insertion rather than
+        // by expiration, so the front ones may be alive
+        while let Some((_, pair_forget_at)) = state.order.front() {
+            if *pair_forget_at >= now {
+                break;
+            }
+
+            if let Some((pair, _)) = state.order.pop_front() {
+                state.seen.remove(&pair);
+            }
+        }
+
+        let pair = (public_key.clone(), proof_seed);
+
+        if state.seen.contains(&pair) {
+            return Err(ReplayError::Replayed);
+        }
+
+        if state.order.len() >= params.nonce_cache_size {
+            let NonceCacheState { seen, order } = &mut *state;
+
+            order.retain(|(pair, pair_forget_at)| {
+                let alive = *pair_forget_at >= now;
+
+                if !alive {
+                    seen.remove(pair);
+                }
+
+                alive
+            });
+
+            // Forgetting alive pairs would allow their replays
+            if order.len() >= params.nonce_cache_size {
+                return Err(ReplayError::CacheFull);
+            }
+        }
+
+        state.seen.insert(pair.clone());
+
+        state.order.push_back((pair, forget_at));
+
+        Ok(())
+    }
+
+    #[inline]
+    /// Get amount of the remembered pairs.
+    pub fn len(&self) -> usize {
+        self.0.lock()
+            .expect("Failed to lock nonce cache")
+            .order.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl PartialEq for NonceCache {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for NonceCache {}
+
+impl std::hash::Hash for NonceCache {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::crypto::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn check() {
+        let params = ReplayProtection {
+            window: Duration::from_secs(60),
+            nonce_cache_size: 2,
+            legacy_requests: LegacyRequests::Accept
+        };
+
+        let cache = NonceCache::new();
+        let key = SecretKey::random().public_key();
+
+        assert_eq!(cache.check(&params, &key, 1, Some(1000), 1000), Ok(()));
+        assert_eq!(cache.check(&params, &key, 1, Some(1000), 1010), Err(ReplayError::Replayed));
+
+        // Timestamps outside of the window
+        assert_eq!(cache.check(&params, &key, 2, Some(900), 1000), Err(ReplayError::Expired { timestamp: 900, window: 60 }));
+        assert_eq!(cache.check(&params, &key, 2, Some(1100), 1000), Err(ReplayError::Expired { timestamp: 1100, window: 60 }));
+
+        // Legacy requests
+        assert_eq!(cache.check(&params, &key, 3, None, 1000), Ok(()));
+        assert_eq!(cache.check(&params, &key, 3, None, 1000), Err(ReplayError::Replayed));
+
+        let strict = ReplayProtection {
+            legacy_requests: LegacyRequests::Reject,
+            ..params
+        };
+
+        assert_eq!(cache.check(&strict, &key, 4, None, 1000), Err(ReplayError::MissingTimestamp));
+
+        // Alive pairs are not forgotten when the cache is full
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.check(&params, &key, 5, Some(1000), 1000), Err(ReplayError::CacheFull));
+        assert_eq!(cache.check(&params, &key, 1, Some(1000), 1000), Err(ReplayError::Replayed));
+
+        // Expired pairs are forgotten
+        assert_eq!(cache.check(&params, &key, 6, Some(1100), 1100), Ok(()));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn full_cache_replay() {
+        let params = ReplayProtection {
+            window: Duration::from_secs(60),
+            nonce_cache_size: 16,
+            legacy_requests: LegacyRequests::Reject
+        };
+
+        let cache = NonceCache::new();
+
+        let victim = SecretKey::random().public_key();
+        let attacker = SecretKey::random().public_key();
+
+        // Captured request
+        assert_eq!(cache.check(&params, &victim, 1, Some(1000), 1000), Ok(()));
+
+        // Attacker fills the cache with fresh seeds
+        for seed in 0..15 {
+            assert_eq!(cache.check(&params, &attacker, seed, Some(1010), 1010), Ok(()));
+        }
+
+        assert_eq!(cache.check(&params, &attacker, 15, Some(1010), 1010), Err(ReplayError::CacheFull));
+
+        // Replay within the window is still rejected
+        assert_eq!(cache.check(&params, &victim, 1, Some(1000), 1020), Err(ReplayError::Replayed));
+
+        // Expired pairs free the cache
+        assert_eq!(cache.check(&params, &attacker, 16, Some(1065), 1065), Ok(()));
+        assert_eq!(cache.check(&params, &victim, 1, Some(1000), 1065), Err(ReplayError::Expired { timestamp: 1000, window: 60 }));
+    }
+}
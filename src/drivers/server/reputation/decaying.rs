@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::crypto::asymmetric::PublicKey;
+
+use super::{ReputationProvider, Incident};
+
+#[derive(Debug)]
+/// In-memory reputation provider with scores
+/// decaying back to neutral over time.
+pub struct DecayingReputation {
+    /// Time in which penalties are halved.
+    pub half_life: Duration,
+
+    scores: Mutex<HashMap<PublicKey, (f32, Instant)>>
+}
+
+impl Default for DecayingReputation {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Duration::from_secs(600))
+    }
+}
+
+impl DecayingReputation {
+    #[inline]
+    pub fn new(half_life: Duration) -> Self {
+        Self {
+            half_life,
+            scores: Mutex::new(HashMap::new())
+        }
+    }
+
+    /// Decay score stored at given time.
+    fn decay(&self, score: f32, stored_at: Instant, now: Instant) -> f32 {
+        let elapsed = now.duration_since(stored_at).as_secs_f32();
+
+        score * 0.5_f32.powf(elapsed / self.half_life.as_secs_f32().max(f32::EPSILON))
+    }
+}
+
+#[async_trait::async_trait]
+impl ReputationProvider for DecayingReputation {
+    async fn score(&self, key: &PublicKey) -> f32 {
+        let mut scores = self.scores.lock().await;
+
+        let now = Instant::now();
+
+        let Some((score, stored_at)) = scores.get(key).copied() else {
+            return 0.0;
+        };
+
+        let score = self.decay(score, stored_at, now);
+
+        // Forget keys which became neutral
+        if score > -0.01 {
+            scores.remove(key);
+
+            return 0.0;
+        }
+
+        score
+    }
+
+    async fn report(&self, key: &PublicKey, incident: Incident) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            key = key.to_base64(),
+            ?incident,
+            "Reporting incident"
+        );
+
+        let mut scores = self.scores.lock().await;
+
+        let now = Instant::now();
+
+        let score = scores.get(key)
+            .map(|(score, stored_at)| self.decay(*score, *stored_at, now))
+            .unwrap_or(0.0);
+
+        scores.insert(key.clone(), (score - incident.penalty(), now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::asymmetric::SecretKey;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn decay() {
+        let reputation = DecayingReputation::new(Duration::from_secs(60));
+
+        let key = SecretKey::random().public_key();
+
+        assert_eq!(reputation.score(&key).await, 0.0);
+
+        reputation.report(&key, Incident::OversizedMessage).await;
+        reputation.report(&key, Incident::OversizedMessage).await;
+
+        assert_eq!(reputation.score(&key).await, -4.0);
+
+        tokio::time::advance(Duration::from_secs(60)).await;
+
+        assert!((reputation.score(&key).await + 2.0).abs() < 0.001);
+
+        tokio::time::advance(Duration::from_secs(3600)).await;
+
+        assert_eq!(reputation.score(&key).await, 0.0);
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use crate::crypto::asymmetric::PublicKey;
+
+#[cfg(feature = "reputation-decaying")]
+pub mod decaying;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Misbehavior of a remote key noticed by the server.
+pub enum Incident {
+    /// Request signature or proof validation failed.
+    InvalidSignature,
+
+    /// Sent message exceeded the allowed size.
+    OversizedMessage,
+
+    /// Request contained an invalid channel name.
+    InvalidChannel,
+
+    /// Announced record couldn't be validated.
+    InvalidAnnounce
+}
+
+impl Incident {
+    /// Default score penalty of the incident.
+    pub fn penalty(&self) -> f32 {
+        match self {
+            Self::InvalidSignature => 1.0,
+            Self::OversizedMessage => 2.0,
+            Self::InvalidChannel   => 0.5,
+            Self::InvalidAnnounce  => 1.0
+        }
+    }
+}
+
+#[async_trait::async_trait]
+/// ReputationProvider is a struct that keeps track of
+/// remote keys' behavior and scores them.
+/// 
+/// Neutral score is `0.0`, misbehaving keys get
+/// negative scores.
+pub trait ReputationProvider: Send + Sync {
+    /// Get current score of the key.
+    async fn score(&self, key: &PublicKey) -> f32;
+
+    /// Report misbehavior of the key.
+    async fn report(&self, key: &PublicKey, incident: Incident);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Action chosen by the reputation policy.
+pub enum ReputationAction {
+    /// Process the request as usual.
+    Allow,
+
+    /// Reject the request.
+    Reject
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReputationPolicy {
+    /// Requests of the keys with lower
+    /// score are rejected.
+    pub reject_threshold: f32
+}
+
+impl Default for ReputationPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            reject_threshold: -10.0
+        }
+    }
+}
+
+impl Eq for ReputationPolicy {}
+
+impl std::hash::Hash for ReputationPolicy {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.reject_threshold.to_bits().hash(state);
+    }
+}
+
+impl ReputationPolicy {
+    /// Choose action for the key with given score.
+    pub fn action(&self, score: f32) -> ReputationAction {
+        if score < self.reject_threshold {
+            ReputationAction::Reject
+        }
+
+        else {
+            ReputationAction::Allow
+        }
+    }
+}
+
+#[derive(Clone)]
+/// Shared reputation provider of the server driver.
+pub(crate) struct SharedReputation(pub Arc<dyn ReputationProvider>);
+
+impl std::fmt::Debug for SharedReputation {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedReputation")
+    }
+}
+
+impl PartialEq for SharedReputation {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedReputation {}
+
+impl std::hash::Hash for SharedReputation {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
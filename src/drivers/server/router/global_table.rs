@@ -1,4 +1,6 @@
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use serde_json::{json, Value as Json};
 
@@ -7,7 +9,22 @@ use crate::rest_api::prelude::*;
 
 use crate::time::timestamp;
 
-use super::Router;
+use crate::drivers::server::layout::StorageLayout;
+
+use super::{
+    Router,
+    RouterRecord,
+    RouterEvent,
+    RouterObserver,
+    RouterMetrics,
+    ServerHealth,
+    HealthTable,
+    AliasTable,
+    MetricsCounters,
+    SharedObserver,
+    notify,
+    DEFAULT_AVAILABILITY_TIMEOUT
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -24,26 +41,245 @@ pub enum Error {
 #[derive(Debug, Clone)]
 /// Global Table Router stores all the record in a separate
 /// files within the given folder.
+/// 
+/// Clients which weren't seen for `availability_timeout`
+/// are returned as unavailable by the lookup methods.
 pub struct GlobalTableRouter {
     /// Path to the routing table's folder.
-    pub storage_folder: PathBuf
+    pub storage_folder: PathBuf,
+
+    /// Layout of the records' files.
+    pub layout: StorageLayout,
+
+    /// Time after which clients without requests
+    /// to the server are not available.
+    pub availability_timeout: Duration,
+
+    /// Observer notified about changes of the table.
+    observer: Option<SharedObserver>,
+
+    /// Health statuses of the known servers.
+    /// 
+    /// Statuses are kept in memory, so servers
+    /// are unchecked after the router is created.
+    health: HealthTable,
+
+    /// Aliases of the local clients.
+    /// 
+    /// Aliases are kept in memory, so clients
+    /// must reconnect to register them after
+    /// the router is created.
+    aliases: AliasTable,
+
+    /// Lookups and announces counters.
+    metrics: MetricsCounters
 }
 
 impl GlobalTableRouter {
+    #[inline]
     pub async fn new(storage_folder: impl Into<PathBuf>) -> std::io::Result<Self> {
+        Self::new_with_layout(storage_folder, StorageLayout::Flat).await
+    }
+
+    pub async fn new_with_layout(storage_folder: impl Into<PathBuf>, layout: StorageLayout) -> std::io::Result<Self> {
         let storage_folder = storage_folder.into();
 
         #[cfg(feature = "tracing")]
-        tracing::trace!("Building new GlobalTableRouter in {:?}", storage_folder);
+        tracing::trace!(?layout, "Building new GlobalTableRouter in {:?}", storage_folder);
 
         tokio::fs::create_dir_all(storage_folder.join("local")).await?;
         tokio::fs::create_dir_all(storage_folder.join("remote")).await?;
         tokio::fs::create_dir_all(storage_folder.join("servers")).await?;
 
         Ok(Self {
-            storage_folder
+            storage_folder,
+            layout,
+            availability_timeout: DEFAULT_AVAILABILITY_TIMEOUT,
+            observer: None,
+            health: HealthTable::default(),
+            aliases: AliasTable::default(),
+            metrics: MetricsCounters::default()
         })
     }
+
+    #[inline]
+    /// Change time after which clients without
+    /// requests to the server are not available.
+    pub fn with_availability_timeout(self, timeout: Duration) -> Self {
+        Self {
+            availability_timeout: timeout,
+            ..self
+        }
+    }
+
+    /// Get last seen time of the record.
+    /// 
+    /// Legacy records are seen when indexed.
+    fn seen_at(record: &Json) -> u64 {
+        record.get("seen_at")
+            .or_else(|| record.get("indexed_at"))
+            .and_then(Json::as_u64)
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn is_available(&self, record: &Json) -> bool {
+        timestamp().saturating_sub(Self::seen_at(record)) < self.availability_timeout.as_secs()
+    }
+
+    /// Remove record of the given public key from the table's sub-folder.
+    async fn remove_record(&self, folder: &str, public_key: &PublicKey) {
+        let folder = self.storage_folder.join(folder);
+
+        // Record can be stored in both layouts during migration
+        let _ = tokio::fs::remove_file(StorageLayout::Flat.path(&folder, public_key)).await;
+        let _ = tokio::fs::remove_file(StorageLayout::Sharded.path(&folder, public_key)).await;
+    }
+
+    /// Write record of the given public key to the table's sub-folder.
+    /// 
+    /// Legacy flat record is removed when the sharded layout is used.
+    async fn write_record(&self, folder: &str, public_key: &PublicKey, record: Json) -> Result<(), Error> {
+        let folder = self.storage_folder.join(folder);
+        let path = self.layout.path(&folder, public_key);
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::write(path, serde_json::to_vec(&record)?).await?;
+
+        if self.layout == StorageLayout::Sharded {
+            let _ = tokio::fs::remove_file(StorageLayout::Flat.path(&folder, public_key)).await;
+        }
+
+        Ok(())
+    }
+
+    /// Read record of the given public key from the table's sub-folder.
+    /// 
+    /// Return `None` if the record is missing or invalid.
+    async fn read_record(&self, folder: &str, public_key: &PublicKey) -> Option<Json> {
+        let folder = self.storage_folder.join(folder);
+
+        for layout in [self.layout, StorageLayout::Flat] {
+            if let Ok(record) = tokio::fs::read(layout.path(&folder, public_key)).await {
+                return serde_json::from_slice(&record).ok();
+            }
+        }
+
+        None
+    }
+
+    /// Read local client of the given public key.
+    async fn find_local_client(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> Result<Option<(Client, bool)>, Error> {
+        let Some(record) = self.read_record("local", public_key).await else {
+            return Ok(None);
+        };
+
+        let client = Client::from_json(&record["client"])?;
+
+        if client_type.is_some_and(|client_type| client_type != client.info.client_type) {
+            return Ok(None);
+        }
+
+        Ok(Some((client, self.is_available(&record))))
+    }
+
+    /// Read remote client of the given public key.
+    async fn find_remote_client(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> Result<Option<(Client, Server, bool)>, Error> {
+        let Some(record) = self.read_record("remote", public_key).await else {
+            return Ok(None);
+        };
+
+        let client = Client::from_json(&record["client"])?;
+        let server = Server::from_json(&record["server"])?;
+
+        if client_type.is_some_and(|client_type| client_type != client.info.client_type) {
+            return Ok(None);
+        }
+
+        Ok(Some((client, server, self.is_available(&record))))
+    }
+
+    /// Read all the records from the table's sub-folder.
+    /// 
+    /// Both flat and sharded records are read.
+    async fn read_records(&self, folder: &str) -> Result<Vec<Json>, Error> {
+        let mut records = Vec::new();
+        let mut folders = vec![self.storage_folder.join(folder)];
+
+        while let Some(folder) = folders.pop() {
+            let mut entries = tokio::fs::read_dir(folder).await?;
+
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_dir() {
+                    folders.push(entry.path());
+
+                    continue;
+                }
+
+                let entry = tokio::fs::read(entry.path()).await?;
+
+                records.push(serde_json::from_slice::<Json>(&entry)?);
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Move records from the legacy flat layout to the shards.
+    /// 
+    /// Records are moved one by one using filesystem renames
+    /// so this method is safe to interrupt. Unmigrated records
+    /// are still served from the legacy files.
+    /// 
+    /// Return number of migrated records. Does nothing
+    /// when the flat layout is used.
+    pub async fn migrate_layout(&self) -> Result<u64, Error> {
+        if self.layout != StorageLayout::Sharded {
+            return Ok(0);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Migrating GlobalTableRouter to the sharded layout");
+
+        let mut migrated = 0;
+
+        for folder in ["local", "remote", "servers"] {
+            let folder = self.storage_folder.join(folder);
+
+            let mut entries = tokio::fs::read_dir(&folder).await?;
+
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_dir() {
+                    continue;
+                }
+
+                let name = entry.file_name();
+
+                let Some(public_key) = name.to_str().and_then(|name| PublicKey::from_base64(name).ok()) else {
+                    continue;
+                };
+
+                let target = StorageLayout::Sharded.path(&folder, &public_key);
+
+                // Sharded record is always newer than the legacy one
+                if target.exists() {
+                    tokio::fs::remove_file(entry.path()).await?;
+                }
+
+                else {
+                    tokio::fs::create_dir_all(folder.join(StorageLayout::shard(&public_key))).await?;
+                    tokio::fs::rename(entry.path(), target).await?;
+                }
+
+                migrated += 1;
+            }
+        }
+
+        Ok(migrated)
+    }
 }
 
 #[async_trait::async_trait]
@@ -51,78 +287,161 @@ impl Router for GlobalTableRouter {
     type Error = Error;
 
     async fn index_local_client(&self, client: Client) -> Result<bool, Self::Error> {
-        let path = self.storage_folder
-            .join("local")
-            .join(client.public_key.to_base64());
-
-        let client = json!({
+        let record = json!({
             "indexed_at": timestamp(),
+            "seen_at": timestamp(),
             "client": client.to_json()?
         });
 
-        tokio::fs::write(path, serde_json::to_vec(&client)?).await?;
+        self.write_record("local", &client.public_key, record).await?;
+
+        notify(&self.observer, [RouterEvent::ClientIndexed { client, server: None }]).await;
 
         Ok(true)
     }
 
     async fn index_remote_client(&self, client: Client, server: Server) -> Result<bool, Self::Error> {
-        let path = self.storage_folder
-            .join("remote")
-            .join(client.public_key.to_base64());
-
         let record = json!({
             "indexed_at": timestamp(),
+            "seen_at": timestamp(),
             "client": client.to_json()?,
             "server": server.to_json()?
         });
 
-        tokio::fs::write(path, serde_json::to_vec(&record)?).await?;
+        self.write_record("remote", &client.public_key, record).await?;
+
+        self.metrics.client_announce();
+
+        notify(&self.observer, [RouterEvent::ClientIndexed { client, server: Some(server) }]).await;
 
         Ok(true)
     }
 
     async fn index_server(&self, server: Server) -> Result<bool, Self::Error> {
-        let path = self.storage_folder
-            .join("servers")
-            .join(server.public_key.to_base64());
+        self.update_server_address(server, timestamp()).await
+    }
 
-        let server = json!({
-            "indexed_at": timestamp(),
+    async fn update_server_address(&self, server: Server, indexed_at: u64) -> Result<bool, Self::Error> {
+        // Ignore announces older than the stored record
+        if let Some(record) = self.read_record("servers", &server.public_key).await {
+            if record.get("indexed_at").and_then(Json::as_u64).is_some_and(|stored_at| stored_at > indexed_at) {
+                return Ok(false);
+            }
+        }
+
+        let record = json!({
+            "indexed_at": indexed_at,
             "server": server.to_json()?
         });
 
-        tokio::fs::write(path, serde_json::to_vec(&server)?).await?;
+        self.write_record("servers", &server.public_key, record).await?;
+
+        self.metrics.server_announce();
+
+        notify(&self.observer, [RouterEvent::ServerIndexed { server }]).await;
 
         Ok(true)
     }
 
     async fn disconnect(&self, public_key: &PublicKey) -> Result<(), Self::Error> {
-        let public_key = public_key.to_base64();
-
         // We're just deleting the record but could also mark them
         // as unavailable. Right now I decided to delete them because:
         // 1. It's faster and easier to implement
         // 2. Current implementations generally ignore availability
         //    flag thus changing it doesn't make a weather
-        let _ = tokio::fs::remove_file(self.storage_folder.join("local").join(&public_key)).await;
-        let _ = tokio::fs::remove_file(self.storage_folder.join("remote").join(&public_key)).await;
-        let _ = tokio::fs::remove_file(self.storage_folder.join("servers").join(&public_key)).await;
+        let mut events = Vec::new();
+
+        self.health.remove(public_key);
+        self.aliases.remove(public_key);
+
+        // Removed records are only read for the observer
+        if self.observer.is_some() {
+            if let Some(record) = self.read_record("local", public_key).await {
+                if let Ok(client) = Client::from_json(&record["client"]) {
+                    events.push(RouterEvent::ClientDisconnected { client, server: None });
+                }
+            }
+
+            if let Some(record) = self.read_record("remote", public_key).await {
+                if let (Ok(client), Ok(server)) = (Client::from_json(&record["client"]), Server::from_json(&record["server"])) {
+                    events.push(RouterEvent::ClientDisconnected { client, server: Some(server) });
+                }
+            }
+
+            if let Some(record) = self.read_record("servers", public_key).await {
+                if let Ok(server) = Server::from_json(&record["server"]) {
+                    events.push(RouterEvent::ServerDisconnected { server });
+                }
+            }
+        }
+
+        for folder in ["local", "remote", "servers"] {
+            self.remove_record(folder, public_key).await;
+        }
+
+        notify(&self.observer, events).await;
 
         Ok(())
     }
 
-    async fn local_clients(&self) -> Result<Vec<Client>, Self::Error> {
-        let mut clients = Vec::new();
+    async fn touch_local_client(&self, public_key: &PublicKey) -> Result<bool, Self::Error> {
+        let Some(mut record) = self.read_record("local", public_key).await else {
+            return Ok(false);
+        };
+
+        let seen_at = timestamp();
+
+        // Don't rewrite the record on every request
+        if Self::seen_at(&record) < seen_at {
+            record["seen_at"] = Json::from(seen_at);
+
+            self.write_record("local", public_key, record).await?;
+        }
+
+        Ok(true)
+    }
+
+    async fn cleanup(&self, older_than: Duration) -> Result<u64, Self::Error> {
+        let min_seen_at = timestamp().saturating_sub(older_than.as_secs());
+
+        let mut events = Vec::new();
+
+        for record in self.read_records("local").await? {
+            if Self::seen_at(&record) < min_seen_at {
+                let client = Client::from_json(&record["client"])?;
+
+                self.remove_record("local", &client.public_key).await;
+
+                events.push(RouterEvent::RecordExpired {
+                    record: RouterRecord::LocalClient(client)
+                });
+            }
+        }
 
-        let folder = self.storage_folder
-            .join("local");
+        for record in self.read_records("remote").await? {
+            if Self::seen_at(&record) < min_seen_at {
+                let client = Client::from_json(&record["client"])?;
+                let server = Server::from_json(&record["server"])?;
 
-        let mut entries = tokio::fs::read_dir(folder).await?;
+                self.remove_record("remote", &client.public_key).await;
 
-        while let Some(entry) = entries.next_entry().await? {
-            let entry = tokio::fs::read(entry.path()).await?;
-            let record = serde_json::from_slice::<Json>(&entry)?;
+                events.push(RouterEvent::RecordExpired {
+                    record: RouterRecord::RemoteClient(client, server)
+                });
+            }
+        }
+
+        let removed = events.len() as u64;
 
+        notify(&self.observer, events).await;
+
+        Ok(removed)
+    }
+
+    async fn local_clients(&self) -> Result<Vec<Client>, Self::Error> {
+        let mut clients = Vec::new();
+
+        for record in self.read_records("local").await? {
             let client = Client::from_json(&record["client"])?;
 
             clients.push(client);
@@ -134,15 +453,7 @@ impl Router for GlobalTableRouter {
     async fn remote_clients(&self) -> Result<Vec<(Client, Server)>, Self::Error> {
         let mut clients = Vec::new();
 
-        let folder = self.storage_folder
-            .join("remote");
-
-        let mut entries = tokio::fs::read_dir(folder).await?;
-
-        while let Some(entry) = entries.next_entry().await? {
-            let entry = tokio::fs::read(entry.path()).await?;
-            let record = serde_json::from_slice::<Json>(&entry)?;
-
+        for record in self.read_records("remote").await? {
             let client = Client::from_json(&record["client"])?;
             let server = Server::from_json(&record["server"])?;
 
@@ -155,15 +466,7 @@ impl Router for GlobalTableRouter {
     async fn servers(&self) -> Result<Vec<Server>, Self::Error> {
         let mut servers = Vec::new();
 
-        let folder = self.storage_folder
-            .join("servers");
-
-        let mut entries = tokio::fs::read_dir(folder).await?;
-
-        while let Some(entry) = entries.next_entry().await? {
-            let entry = tokio::fs::read(entry.path()).await?;
-            let record = serde_json::from_slice::<Json>(&entry)?;
-
+        for record in self.read_records("servers").await? {
             let server = Server::from_json(&record["server"])?;
 
             servers.push(server);
@@ -171,6 +474,53 @@ impl Router for GlobalTableRouter {
 
         Ok(servers)
     }
+
+    async fn lookup_local_client(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> Result<Option<(Client, bool)>, Self::Error> {
+        let found = self.find_local_client(public_key, client_type).await?;
+
+        self.metrics.lookup(&found);
+
+        Ok(found)
+    }
+
+    async fn lookup_remote_client(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> Result<Option<(Client, Server, bool)>, Self::Error> {
+        let found = self.find_remote_client(public_key, client_type).await?;
+
+        self.metrics.lookup(&found);
+
+        Ok(found)
+    }
+
+    async fn metrics(&self) -> Result<RouterMetrics, Self::Error> {
+        Ok(self.metrics.metrics(
+            self.read_records("local").await?.len() as u64,
+            self.read_records("remote").await?.len() as u64,
+            self.read_records("servers").await?.len() as u64
+        ))
+    }
+
+    async fn mark_server_health(&self, public_key: &PublicKey, health: ServerHealth) -> Result<(), Self::Error> {
+        self.health.set(public_key, health);
+
+        Ok(())
+    }
+
+    async fn server_health(&self, public_key: &PublicKey) -> Result<ServerHealth, Self::Error> {
+        Ok(self.health.get(public_key))
+    }
+
+    async fn register_alias(&self, alias: &ClientAlias, public_key: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(self.aliases.register(alias, public_key))
+    }
+
+    async fn resolve_alias(&self, alias: &ClientAlias) -> Result<Option<PublicKey>, Self::Error> {
+        Ok(self.aliases.resolve(alias))
+    }
+
+    #[inline]
+    fn set_observer(&mut self, observer: Arc<dyn RouterObserver>) {
+        self.observer = Some(SharedObserver(observer));
+    }
 }
 
 #[cfg(test)]
@@ -178,12 +528,13 @@ mod tests {
     use crate::rest_api::types::client::tests::get_client;
     use crate::rest_api::types::server::tests::get_server;
 
+    use crate::drivers::server::router::tests::{observer_suite, snapshot_suite, address_update_suite, metrics_suite, alias_suite};
+
     use super::*;
 
-    #[tokio::test]
-    async fn index_lookup() -> std::io::Result<()> {
+    async fn prepare_folder(name: &str) -> std::io::Result<PathBuf> {
         let temp = std::env::temp_dir()
-            .join("global-table-router-test");
+            .join(name);
 
         if temp.exists() {
             tokio::fs::remove_dir_all(&temp).await?;
@@ -191,8 +542,10 @@ mod tests {
 
         tokio::fs::create_dir(&temp).await?;
 
-        let table = GlobalTableRouter::new(&temp).await?;
+        Ok(temp)
+    }
 
+    async fn index_lookup_suite(table: GlobalTableRouter) -> std::io::Result<()> {
         let local = vec![get_client(); 32];
         let remote = vec![(get_client(), get_server()); 32];
         let servers = vec![get_server(); 32];
@@ -249,4 +602,160 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn index_lookup() -> std::io::Result<()> {
+        let temp = prepare_folder("global-table-router-test").await?;
+
+        index_lookup_suite(GlobalTableRouter::new(&temp).await?).await
+    }
+
+    #[tokio::test]
+    async fn index_lookup_sharded() -> std::io::Result<()> {
+        let temp = prepare_folder("global-table-router-sharded-test").await?;
+
+        index_lookup_suite(GlobalTableRouter::new_with_layout(&temp, StorageLayout::Sharded).await?).await
+    }
+
+    #[tokio::test]
+    async fn migrate_layout() -> Result<(), Error> {
+        let temp = prepare_folder("global-table-router-migrate-test").await?;
+
+        let flat = GlobalTableRouter::new(&temp).await?;
+        let sharded = GlobalTableRouter::new_with_layout(&temp, StorageLayout::Sharded).await?;
+
+        let clients = [get_client(), get_client()];
+
+        // Half-migrated storage: first client is sharded, second is legacy
+
+        flat.index_local_client(clients[0].clone()).await?;
+
+        assert_eq!(sharded.migrate_layout().await?, 1);
+
+        flat.index_local_client(clients[1].clone()).await?;
+
+        for client in &clients {
+            assert_eq!(sharded.lookup_local_client(&client.public_key, None).await?, Some((client.clone(), true)));
+        }
+
+        assert_eq!(sharded.local_clients().await?.len(), 2);
+
+        // Finish migration
+
+        assert_eq!(sharded.migrate_layout().await?, 1);
+        assert_eq!(sharded.migrate_layout().await?, 0);
+
+        assert_eq!(sharded.local_clients().await?.len(), 2);
+
+        sharded.disconnect(&clients[0].public_key).await?;
+
+        assert_eq!(sharded.local_clients().await?, vec![clients[1].clone()]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn health() -> Result<(), Error> {
+        let temp = prepare_folder("global-table-health-test").await?;
+
+        let router = GlobalTableRouter::new(temp).await?;
+
+        let servers = [get_server(), get_server()];
+
+        for server in &servers {
+            router.index_server(server.clone()).await?;
+        }
+
+        router.mark_server_health(&servers[0].public_key, ServerHealth::Unhealthy).await?;
+        router.mark_server_health(&servers[1].public_key, ServerHealth::Healthy).await?;
+
+        assert_eq!(router.server_health(&servers[0].public_key).await?, ServerHealth::Unhealthy);
+
+        // Unhealthy servers are not hinted
+        let hints = router.lookup_remote_client_hint(&get_client().public_key, None).await?;
+
+        assert_eq!(hints.into_iter().map(|hint| hint.server).collect::<Vec<_>>(), [servers[1].clone()]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn observer() -> Result<(), Error> {
+        let temp = prepare_folder("global-table-observer-test").await?;
+
+        observer_suite(GlobalTableRouter::new(temp).await?).await
+    }
+
+    #[tokio::test]
+    async fn snapshot() -> Result<(), Error> {
+        let from = prepare_folder("global-table-snapshot-from-test").await?;
+        let to = prepare_folder("global-table-snapshot-to-test").await?;
+
+        snapshot_suite(GlobalTableRouter::new(from).await?, GlobalTableRouter::new(to).await?).await
+    }
+
+    #[tokio::test]
+    async fn address_update() -> Result<(), Error> {
+        let temp = prepare_folder("global-table-address-update-test").await?;
+
+        address_update_suite(GlobalTableRouter::new(temp).await?).await
+    }
+
+    #[tokio::test]
+    async fn metrics() -> Result<(), Error> {
+        let temp = prepare_folder("global-table-metrics-test").await?;
+
+        metrics_suite(GlobalTableRouter::new(temp).await?).await
+    }
+
+    #[tokio::test]
+    async fn aliases() -> Result<(), Error> {
+        let temp = prepare_folder("global-table-aliases-test").await?;
+
+        alias_suite(GlobalTableRouter::new(temp).await?).await
+    }
+
+    #[tokio::test]
+    async fn last_seen() -> Result<(), Error> {
+        let temp = prepare_folder("global-table-last-seen-test").await?;
+
+        let router = GlobalTableRouter::new(&temp).await?;
+
+        let clients = [get_client(), get_client(), get_client()];
+        let remote = (get_client(), get_server());
+
+        router.index_local_client(clients[0].clone()).await?;
+
+        // Legacy records without last seen time
+        for client in &clients[1..] {
+            router.write_record("local", &client.public_key, json!({
+                "indexed_at": timestamp() - 3600,
+                "client": client.to_json()?
+            })).await?;
+        }
+
+        router.write_record("remote", &remote.0.public_key, json!({
+            "indexed_at": timestamp() - 3600,
+            "client": remote.0.to_json()?,
+            "server": remote.1.to_json()?
+        })).await?;
+
+        // Clients without requests are not available
+        assert_eq!(router.lookup_local_client(&clients[0].public_key, None).await?, Some((clients[0].clone(), true)));
+        assert_eq!(router.lookup_local_client(&clients[1].public_key, None).await?, Some((clients[1].clone(), false)));
+        assert_eq!(router.lookup_remote_client(&remote.0.public_key, None).await?, Some((remote.0.clone(), remote.1.clone(), false)));
+
+        assert!(router.touch_local_client(&clients[1].public_key).await?);
+        assert!(!router.touch_local_client(&remote.0.public_key).await?);
+
+        assert_eq!(router.lookup_local_client(&clients[1].public_key, None).await?, Some((clients[1].clone(), true)));
+
+        assert_eq!(router.cleanup(Duration::from_secs(600)).await?, 2);
+
+        assert_eq!(router.local_clients().await?.len(), 2);
+        assert_eq!(router.lookup_local_client(&clients[2].public_key, None).await?, None);
+        assert!(router.remote_clients().await?.is_empty());
+
+        Ok(())
+    }
 }
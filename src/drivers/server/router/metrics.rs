@@ -0,0 +1,184 @@
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+use std::sync::Arc;
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Metrics of the routing table.
+/// 
+/// Counters are cumulative since the router was
+/// created and are never reset by reading them.
+pub struct RouterMetrics {
+    /// Amount of stored local clients.
+    pub local_clients: u64,
+
+    /// Amount of stored remote clients.
+    pub remote_clients: u64,
+
+    /// Amount of stored servers.
+    pub servers: u64,
+
+    /// Amount of local and remote client
+    /// lookups which found the client.
+    pub lookup_hits: u64,
+
+    /// Amount of local and remote client
+    /// lookups which didn't find the client.
+    pub lookup_misses: u64,
+
+    /// Amount of indexed remote clients announces.
+    pub client_announces: u64,
+
+    /// Amount of indexed servers announces.
+    pub server_announces: u64
+}
+
+impl RouterMetrics {
+    /// Part of the client lookups which found the client.
+    /// 
+    /// Return `None` if there were no lookups.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.lookup_hits + self.lookup_misses;
+
+        if lookups == 0 {
+            return None;
+        }
+
+        Some(self.lookup_hits as f64 / lookups as f64)
+    }
+}
+
+impl AsJson for RouterMetrics {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "local_clients": self.local_clients,
+            "remote_clients": self.remote_clients,
+            "servers": self.servers,
+            "lookups": {
+                "hits": self.lookup_hits,
+                "misses": self.lookup_misses
+            },
+            "announces": {
+                "clients": self.client_announces,
+                "servers": self.server_announces
+            }
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let field = |json: &Json, name: &str, path: &'static str| json.get(name)
+            .and_then(Json::as_u64)
+            .ok_or(AsJsonError::FieldNotFound(path));
+
+        let Some(lookups) = json.get("lookups") else {
+            return Err(AsJsonError::FieldNotFound("lookups"));
+        };
+
+        let Some(announces) = json.get("announces") else {
+            return Err(AsJsonError::FieldNotFound("announces"));
+        };
+
+        Ok(Self {
+            local_clients: field(json, "local_clients", "local_clients")?,
+            remote_clients: field(json, "remote_clients", "remote_clients")?,
+            servers: field(json, "servers", "servers")?,
+            lookup_hits: field(lookups, "hits", "lookups.hits")?,
+            lookup_misses: field(lookups, "misses", "lookups.misses")?,
+            client_announces: field(announces, "clients", "announces.clients")?,
+            server_announces: field(announces, "servers", "announces.servers")?
+        })
+    }
+}
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+#[derive(Default, Debug)]
+struct Counters {
+    lookup_hits: AtomicU64,
+    lookup_misses: AtomicU64,
+    client_announces: AtomicU64,
+    server_announces: AtomicU64
+}
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+#[derive(Default, Debug, Clone)]
+/// Cumulative counters of the router's metrics.
+pub(crate) struct MetricsCounters(Arc<Counters>);
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+impl MetricsCounters {
+    /// Count client lookup by whether it found the client.
+    pub fn lookup<T>(&self, result: &Option<T>) {
+        let counter = match result {
+            Some(_) => &self.0.lookup_hits,
+            None => &self.0.lookup_misses
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn client_announce(&self) {
+        self.0.client_announces.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn server_announce(&self) {
+        self.0.server_announces.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Build metrics with the given tables sizes.
+    pub fn metrics(&self, local_clients: u64, remote_clients: u64, servers: u64) -> RouterMetrics {
+        RouterMetrics {
+            local_clients,
+            remote_clients,
+            servers,
+            lookup_hits: self.0.lookup_hits.load(Ordering::Relaxed),
+            lookup_misses: self.0.lookup_misses.load(Ordering::Relaxed),
+            client_announces: self.0.client_announces.load(Ordering::Relaxed),
+            server_announces: self.0.server_announces.load(Ordering::Relaxed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let metrics = RouterMetrics {
+            local_clients: 1,
+            remote_clients: 2,
+            servers: 3,
+            lookup_hits: 4,
+            lookup_misses: 5,
+            client_announces: 6,
+            server_announces: 7
+        };
+
+        assert_eq!(RouterMetrics::from_json(&metrics.to_json()?)?, metrics);
+
+        assert!(matches!(RouterMetrics::from_json(&json!({})), Err(AsJsonError::FieldNotFound("lookups"))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn hit_rate() {
+        assert_eq!(RouterMetrics::default().hit_rate(), None);
+
+        let metrics = RouterMetrics {
+            lookup_hits: 3,
+            lookup_misses: 1,
+            ..RouterMetrics::default()
+        };
+
+        assert_eq!(metrics.hit_rate(), Some(0.75));
+    }
+}
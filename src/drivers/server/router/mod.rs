@@ -1,9 +1,367 @@
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+use std::collections::HashMap;
+
+use std::sync::Arc;
+use std::time::Duration;
+
 use crate::crypto::asymmetric::PublicKey;
 use crate::rest_api::prelude::*;
 
+use super::messages_inbox::ObserverError;
+
+mod snapshot;
+mod metrics;
+
 #[cfg(feature = "router-global-table")]
 pub mod global_table;
 
+#[cfg(feature = "router-ram")]
+pub mod ram;
+
+#[cfg(feature = "router-stored")]
+pub mod stored;
+
+pub use snapshot::{RouterSnapshot, SnapshotImport};
+pub use metrics::RouterMetrics;
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+pub(crate) use metrics::MetricsCounters;
+
+/// Default time after which clients without
+/// requests to the server are not available.
+pub const DEFAULT_AVAILABILITY_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Activity of the server used to rank lookup hints.
+pub struct HintStats {
+    /// Time since the server or its clients were announced.
+    pub last_seen: Duration,
+
+    /// Amount of announces of the server and its clients.
+    pub announces: u64,
+
+    /// Amount of successful lookups of the server's clients.
+    pub lookups: u64
+}
+
+impl HintStats {
+    /// Score of the server as a lookup hint.
+    /// 
+    /// Recently seen servers get up to 1000 points, losing
+    /// half of them per hour of silence. Every announce adds
+    /// 10 points and every successful lookup adds 50 points,
+    /// both capped at 100 events.
+    /// 
+    /// ```rust
+    /// use std::time::Duration;
+    /// 
+    /// use hyperborealib::drivers::server::router::HintStats;
+    /// 
+    /// let fresh = HintStats::default();
+    /// 
+    /// let stale = HintStats {
+    ///     last_seen: Duration::from_secs(3600),
+    ///     ..HintStats::default()
+    /// };
+    /// 
+    /// assert_eq!(fresh.score(), 1000);
+    /// assert_eq!(stale.score(), 500);
+    /// ```
+    pub fn score(&self) -> u64 {
+        let freshness = 1000 * 3600 / (3600 + self.last_seen.as_secs());
+
+        freshness + 10 * self.announces.min(100) + 50 * self.lookups.min(100)
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Reachability of the known server.
+pub enum ServerHealth {
+    /// Server wasn't checked yet.
+    #[default]
+    Unknown,
+
+    /// Server has responded with its indexed public key.
+    Healthy,
+
+    /// Server is not reachable or responded
+    /// with another public key.
+    Unhealthy
+}
+
+impl ServerHealth {
+    #[inline]
+    pub fn is_unhealthy(&self) -> bool {
+        self == &Self::Unhealthy
+    }
+}
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+#[derive(Default, Debug, Clone)]
+/// In-memory health statuses of the known servers.
+pub(crate) struct HealthTable(Arc<std::sync::RwLock<HashMap<PublicKey, ServerHealth>>>);
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+impl HealthTable {
+    pub fn get(&self, public_key: &PublicKey) -> ServerHealth {
+        self.0.read()
+            .expect("Failed to lock servers health table")
+            .get(public_key)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set(&self, public_key: &PublicKey, health: ServerHealth) {
+        self.0.write()
+            .expect("Failed to lock servers health table")
+            .insert(public_key.clone(), health);
+    }
+
+    pub fn remove(&self, public_key: &PublicKey) {
+        self.0.write()
+            .expect("Failed to lock servers health table")
+            .remove(public_key);
+    }
+}
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+#[derive(Default, Debug, Clone)]
+/// In-memory aliases of the local clients.
+pub(crate) struct AliasTable(Arc<std::sync::RwLock<HashMap<ClientAlias, PublicKey>>>);
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+impl AliasTable {
+    pub fn register(&self, alias: &ClientAlias, public_key: &PublicKey) -> bool {
+        let mut aliases = self.0.write()
+            .expect("Failed to lock clients aliases table");
+
+        if aliases.get(alias).is_some_and(|owner| owner != public_key) {
+            return false;
+        }
+
+        aliases.retain(|_, owner| owner != public_key);
+        aliases.insert(alias.clone(), public_key.clone());
+
+        true
+    }
+
+    pub fn resolve(&self, alias: &ClientAlias) -> Option<PublicKey> {
+        self.0.read()
+            .expect("Failed to lock clients aliases table")
+            .get(alias)
+            .cloned()
+    }
+
+    pub fn remove(&self, public_key: &PublicKey) {
+        self.0.write()
+            .expect("Failed to lock clients aliases table")
+            .retain(|_, owner| owner != public_key);
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// What to do with new records of the full routing table.
+pub enum RouterEviction {
+    /// Remove the least recently indexed or looked up record.
+    #[default]
+    LeastRecentlyUsed,
+
+    /// Reject new records until some are disconnected.
+    Disabled
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Capacity limits of the routing table.
+/// 
+/// Limits protect the server from peers announcing
+/// lots of fake clients and servers. Re-indexing of
+/// already stored records is always allowed.
+pub struct RouterLimits {
+    /// Maximal amount of local clients' records.
+    pub max_local_clients: Option<usize>,
+
+    /// Maximal amount of remote clients' records.
+    pub max_remote_clients: Option<usize>,
+
+    /// Maximal amount of servers' records.
+    pub max_servers: Option<usize>,
+
+    pub eviction: RouterEviction
+}
+
+impl RouterLimits {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_max_local_clients(self, max_records: usize) -> Self {
+        Self {
+            max_local_clients: Some(max_records),
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_max_remote_clients(self, max_records: usize) -> Self {
+        Self {
+            max_remote_clients: Some(max_records),
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_max_servers(self, max_records: usize) -> Self {
+        Self {
+            max_servers: Some(max_records),
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_eviction(self, eviction: RouterEviction) -> Self {
+        Self {
+            eviction,
+            ..self
+        }
+    }
+}
+
+#[cfg(any(feature = "router-ram", feature = "router-stored"))]
+/// Make room for the record with the given public key.
+/// 
+/// Return the evicted records, or amount of stored
+/// records if the table is full and eviction is disabled.
+pub(crate) fn make_room<T>(
+    table: &mut HashMap<PublicKey, T>,
+    public_key: &PublicKey,
+    max_records: Option<usize>,
+    eviction: RouterEviction,
+    last_used: impl Fn(&T) -> u64
+) -> Result<Vec<(PublicKey, T)>, usize> {
+    let Some(max_records) = max_records else {
+        return Ok(vec![]);
+    };
+
+    if table.len() < max_records || table.contains_key(public_key) {
+        return Ok(vec![]);
+    }
+
+    if eviction == RouterEviction::Disabled {
+        return Err(table.len());
+    }
+
+    let mut evicted = Vec::new();
+
+    // Limits could be lowered after the table was filled
+    while table.len() >= max_records.max(1) {
+        let Some(public_key) = table.iter()
+            .min_by_key(|(_, record)| last_used(record))
+            .map(|(public_key, _)| public_key.clone())
+        else {
+            break;
+        };
+
+        if let Some(record) = table.remove(&public_key) {
+            evicted.push((public_key, record));
+        }
+    }
+
+    Ok(evicted)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Record of the routing table.
+pub enum RouterRecord {
+    LocalClient(Client),
+    RemoteClient(Client, Server),
+    Server(Server)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Change of the routing table.
+pub enum RouterEvent {
+    /// Client was indexed. Server is set
+    /// for the remote clients.
+    ClientIndexed {
+        client: Client,
+        server: Option<Server>
+    },
+
+    /// Client was disconnected. Server is
+    /// set for the remote clients.
+    ClientDisconnected {
+        client: Client,
+        server: Option<Server>
+    },
+
+    /// Server was indexed.
+    ServerIndexed {
+        server: Server
+    },
+
+    /// Server was disconnected.
+    ServerDisconnected {
+        server: Server
+    },
+
+    /// Record was removed by the router itself,
+    /// e.g. because its time to live has passed
+    /// or it was evicted from the full table.
+    RecordExpired {
+        record: RouterRecord
+    }
+}
+
+#[async_trait::async_trait]
+/// RouterObserver is a struct that is notified
+/// about changes of the routing table.
+/// 
+/// Observer is called after the table is updated,
+/// so it can be used to mirror the table somewhere
+/// else. Errors of the observer are logged and don't
+/// fail the routing operation.
+pub trait RouterObserver: Send + Sync {
+    /// Handle change of the routing table.
+    async fn on_event(&self, event: &RouterEvent) -> Result<(), ObserverError>;
+}
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+#[derive(Clone)]
+/// Shared observer of the routing table.
+pub(crate) struct SharedObserver(pub Arc<dyn RouterObserver>);
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+impl SharedObserver {
+    /// Notify the observer about the table change
+    /// ignoring its errors.
+    pub async fn notify(&self, event: RouterEvent) {
+        if let Err(_err) = self.0.on_event(&event).await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(?event, "Router observer failed: {_err}");
+        }
+    }
+}
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+impl std::fmt::Debug for SharedObserver {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedObserver")
+    }
+}
+
+#[cfg(any(feature = "router-global-table", feature = "router-ram", feature = "router-stored"))]
+/// Notify optional observer about the table changes.
+pub(crate) async fn notify(observer: &Option<SharedObserver>, events: impl IntoIterator<Item = RouterEvent>) {
+    if let Some(observer) = observer {
+        for event in events {
+            observer.notify(event).await;
+        }
+    }
+}
+
 #[async_trait::async_trait]
 /// Router is a struct that implements network clients
 /// and servers indexing, listing and lookup operations.
@@ -27,6 +385,78 @@ pub trait Router {
     /// This method will return whether the server was indexed.
     async fn index_server(&self, server: Server) -> Result<bool, Self::Error>;
 
+    /// Update address of the server announced at the given time.
+    /// 
+    /// Servers are identified by their public keys, so the stored
+    /// record is replaced only if it's not newer than the announce.
+    /// Records indexed by `index_server` are dated by the time of
+    /// indexing. Unknown servers are indexed.
+    /// 
+    /// This method will return whether the server was indexed.
+    /// Default implementation always indexes the server.
+    async fn update_server_address(&self, server: Server, _announced_at: u64) -> Result<bool, Self::Error> {
+        self.index_server(server).await
+    }
+
+    /// Mark the local client as seen right now.
+    /// 
+    /// Called on every authenticated request of the client.
+    /// Clients which weren't seen for a long time are returned
+    /// as unavailable by the lookup methods.
+    /// 
+    /// This method will return whether the client was found.
+    /// Routers without last seen tracking always return `false`.
+    async fn touch_local_client(&self, _public_key: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    /// Remove records of the local and remote clients
+    /// which weren't seen for longer than `older_than`.
+    /// 
+    /// Remote clients are seen when they're announced.
+    /// 
+    /// Return amount of removed records. Routers without
+    /// last seen tracking don't remove anything.
+    async fn cleanup(&self, _older_than: Duration) -> Result<u64, Self::Error> {
+        Ok(0)
+    }
+
+    /// Index multiple remote clients in the routing table.
+    /// 
+    /// Return result of indexing for each client in the
+    /// same order. Failure of one client doesn't prevent
+    /// indexing of the others.
+    /// 
+    /// Default implementation indexes clients one by one.
+    /// Routers can override it to batch the writes.
+    async fn index_remote_clients(&self, clients: Vec<(Client, Server)>) -> Vec<Result<bool, Self::Error>> {
+        let mut results = Vec::with_capacity(clients.len());
+
+        for (client, server) in clients {
+            results.push(self.index_remote_client(client, server).await);
+        }
+
+        results
+    }
+
+    /// Index multiple servers in the routing table.
+    /// 
+    /// Return result of indexing for each server in the
+    /// same order. Failure of one server doesn't prevent
+    /// indexing of the others.
+    /// 
+    /// Default implementation indexes servers one by one.
+    /// Routers can override it to batch the writes.
+    async fn index_servers(&self, servers: Vec<Server>) -> Vec<Result<bool, Self::Error>> {
+        let mut results = Vec::with_capacity(servers.len());
+
+        for server in servers {
+            results.push(self.index_server(server).await);
+        }
+
+        results
+    }
+
     /// Mark connected client or server as disconnected.
     /// 
     /// Depending on implementation this method can either
@@ -37,6 +467,32 @@ pub trait Router {
     /// Get list of all connected local clients.
     async fn local_clients(&self) -> Result<Vec<Client>, Self::Error>;
 
+    /// Get page of the connected local clients
+    /// of the given type.
+    /// 
+    /// Clients are sorted by their base64 encoded public
+    /// keys. Return the page and total amount of the
+    /// matching clients.
+    async fn local_clients_filtered(&self, client_type: Option<ClientType>, offset: u64, limit: Option<u64>) -> Result<(Vec<Client>, u64), Self::Error> {
+        let mut clients = self.local_clients().await?
+            .into_iter()
+            .filter(|client| client_type.is_none() || client_type == Some(client.info.client_type))
+            .map(|client| (client.public_key.to_base64(), client))
+            .collect::<Vec<_>>();
+
+        clients.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let total = clients.len() as u64;
+
+        let clients = clients.into_iter()
+            .skip(offset as usize)
+            .take(limit.map(|limit| limit as usize).unwrap_or(usize::MAX))
+            .map(|(_, client)| client)
+            .collect();
+
+        Ok((clients, total))
+    }
+
     /// Get list of all known remote clients and their servers.
     async fn remote_clients(&self) -> Result<Vec<(Client, Server)>, Self::Error>;
 
@@ -49,6 +505,29 @@ pub trait Router {
             .collect::<Vec<_>>())
     }
 
+    /// Get page of the known servers.
+    /// 
+    /// Servers are sorted by their base64 encoded public
+    /// keys. Return the page and total amount of the servers.
+    async fn servers_page(&self, offset: u64, limit: Option<u64>) -> Result<(Vec<Server>, u64), Self::Error> {
+        let mut servers = self.servers().await?
+            .into_iter()
+            .map(|server| (server.public_key.to_base64(), server))
+            .collect::<Vec<_>>();
+
+        servers.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let total = servers.len() as u64;
+
+        let servers = servers.into_iter()
+            .skip(offset as usize)
+            .take(limit.map(|limit| limit as usize).unwrap_or(usize::MAX))
+            .map(|(_, server)| server)
+            .collect();
+
+        Ok((servers, total))
+    }
+
     /// Lookup local client in the routing table.
     /// 
     /// Router can return optional availability field.
@@ -74,8 +553,51 @@ pub trait Router {
     }
 
     /// Get list of servers which can know the client with given public key.
-    async fn lookup_remote_client_hint(&self, _public_key: &PublicKey, _client_type: Option<ClientType>) -> Result<Vec<Server>, Self::Error> {
-        self.servers().await
+    /// 
+    /// Servers are ranked by their scores in descending order.
+    /// By default all the known servers which are not marked
+    /// as unhealthy are returned unscored.
+    async fn lookup_remote_client_hint(&self, _public_key: &PublicKey, _client_type: Option<ClientType>) -> Result<Vec<ServerHint>, Self::Error> {
+        let mut hints = Vec::new();
+
+        for server in self.servers().await? {
+            if !self.server_health(&server.public_key).await?.is_unhealthy() {
+                hints.push(ServerHint::from(server));
+            }
+        }
+
+        Ok(hints)
+    }
+
+    /// Update reachability status of the known server.
+    /// 
+    /// Routers which don't support health statuses ignore it.
+    async fn mark_server_health(&self, _public_key: &PublicKey, _health: ServerHealth) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Get reachability status of the known server.
+    async fn server_health(&self, _public_key: &PublicKey) -> Result<ServerHealth, Self::Error> {
+        Ok(ServerHealth::Unknown)
+    }
+
+    /// Register alias of the local client.
+    /// 
+    /// Every client can have a single alias, so the previous
+    /// alias of the client is freed. Aliases are freed when
+    /// their clients are disconnected.
+    /// 
+    /// This method will return `false` if the alias is
+    /// registered by another client. Routers which don't
+    /// support aliases always return `false`.
+    async fn register_alias(&self, _alias: &ClientAlias, _public_key: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    /// Get public key of the local client
+    /// which registered the given alias.
+    async fn resolve_alias(&self, _alias: &ClientAlias) -> Result<Option<PublicKey>, Self::Error> {
+        Ok(None)
     }
 
     /// Lookup server in the routing table.
@@ -88,4 +610,305 @@ pub trait Router {
             .cloned()
             .map(|server| (server, true)))
     }
+
+    /// Get metrics of the routing table.
+    /// 
+    /// Default implementation counts stored records
+    /// and doesn't track lookups and announces.
+    async fn metrics(&self) -> Result<RouterMetrics, Self::Error> {
+        Ok(RouterMetrics {
+            local_clients: self.local_clients().await?.len() as u64,
+            remote_clients: self.remote_clients().await?.len() as u64,
+            servers: self.servers().await?.len() as u64,
+            ..RouterMetrics::default()
+        })
+    }
+
+    /// Dump the whole routing table.
+    async fn export_snapshot(&self) -> Result<RouterSnapshot, Self::Error> {
+        Ok(RouterSnapshot::new(
+            self.local_clients().await?,
+            self.remote_clients().await?,
+            self.servers().await?
+        ))
+    }
+
+    /// Index all the records of the given routing table dump.
+    /// 
+    /// - `server_public` must contain public key of the
+    ///   server to which local clients have made their
+    ///   connection certificates.
+    /// 
+    /// Clients with invalid certificates are skipped.
+    async fn import_snapshot(&self, snapshot: RouterSnapshot, server_public: &PublicKey) -> Result<SnapshotImport, Self::Error> {
+        let mut result = SnapshotImport::default();
+
+        let servers = snapshot.servers.len() as u64;
+
+        for indexed in self.index_servers(snapshot.servers).await {
+            indexed?;
+        }
+
+        result.imported += servers;
+
+        for client in snapshot.local_clients {
+            if !client.certificate.validate(&client.public_key, server_public).unwrap_or(false) {
+                result.skipped += 1;
+
+                continue;
+            }
+
+            self.index_local_client(client).await?;
+
+            result.imported += 1;
+        }
+
+        let (valid, invalid): (Vec<_>, Vec<_>) = snapshot.remote_clients.into_iter()
+            .partition(|(client, server)| {
+                client.certificate.validate(&client.public_key, &server.public_key).unwrap_or(false)
+            });
+
+        result.skipped += invalid.len() as u64;
+        result.imported += valid.len() as u64;
+
+        for indexed in self.index_remote_clients(valid).await {
+            indexed?;
+        }
+
+        Ok(result)
+    }
+
+    /// Notify the given observer about changes
+    /// of the routing table.
+    /// 
+    /// Routers which don't support observers ignore it.
+    fn set_observer(&mut self, _observer: Arc<dyn RouterObserver>) {}
+
+    /// Get response status of the router error.
+    /// 
+    /// Returned to the clients whose records
+    /// failed to be indexed.
+    fn error_status(&self, _error: &Self::Error) -> ResponseStatus {
+        ResponseStatus::ServerError
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    #[derive(Default, Clone)]
+    /// Observer storing all the received events
+    /// and failing on every indexed server.
+    pub struct TestObserver(pub Arc<std::sync::Mutex<Vec<RouterEvent>>>);
+
+    #[async_trait::async_trait]
+    impl RouterObserver for TestObserver {
+        async fn on_event(&self, event: &RouterEvent) -> Result<(), ObserverError> {
+            self.0.lock()
+                .expect("Failed to lock observed events")
+                .push(event.clone());
+
+            if matches!(event, RouterEvent::ServerIndexed { .. }) {
+                return Err("Observer failed".into());
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Check that the observer is notified about indexed
+    /// and disconnected records and its errors don't fail
+    /// the routing operations.
+    pub async fn observer_suite<T: Router + Sync>(mut router: T) -> Result<(), T::Error> {
+        let observer = TestObserver::default();
+
+        router.set_observer(Arc::new(observer.clone()));
+
+        let local = get_client();
+        let remote = (get_client(), get_server());
+        let server = get_server();
+
+        router.index_local_client(local.clone()).await?;
+        router.index_remote_client(remote.0.clone(), remote.1.clone()).await?;
+        router.index_server(server.clone()).await?;
+
+        assert!(router.lookup_server(&server.public_key).await?.is_some());
+
+        router.disconnect(&local.public_key).await?;
+        router.disconnect(&remote.0.public_key).await?;
+        router.disconnect(&server.public_key).await?;
+
+        // Unknown records are not reported
+        router.disconnect(&get_client().public_key).await?;
+
+        assert_eq!(*observer.0.lock().unwrap(), [
+            RouterEvent::ClientIndexed { client: local.clone(), server: None },
+            RouterEvent::ClientIndexed { client: remote.0.clone(), server: Some(remote.1.clone()) },
+            RouterEvent::ServerIndexed { server: server.clone() },
+            RouterEvent::ClientDisconnected { client: local, server: None },
+            RouterEvent::ClientDisconnected { client: remote.0, server: Some(remote.1) },
+            RouterEvent::ServerDisconnected { server }
+        ]);
+
+        Ok(())
+    }
+
+    /// Check that the exported routing table is imported
+    /// to another router skipping invalid clients.
+    pub async fn snapshot_suite<T: Router + Sync>(from: T, to: T) -> Result<(), T::Error> {
+        use crate::crypto::prelude::*;
+
+        let server_public = SecretKey::random().public_key();
+
+        let local_secret = SecretKey::random();
+        let remote_secret = SecretKey::random();
+
+        let local = Client::new(
+            local_secret.public_key(),
+            ConnectionCertificate::new(&local_secret, server_public.clone()),
+            ClientInfo::thin()
+        );
+
+        let remote_server = get_server();
+
+        let remote = Client::new(
+            remote_secret.public_key(),
+            ConnectionCertificate::new(&remote_secret, remote_server.public_key.clone()),
+            ClientInfo::thick("127.0.0.1:8001")
+        );
+
+        let server = get_server();
+
+        // Random certificates of the test clients are invalid
+        let invalid_local = get_client();
+        let invalid_remote = (get_client(), get_server());
+
+        from.index_local_client(local.clone()).await?;
+        from.index_local_client(invalid_local.clone()).await?;
+        from.index_remote_client(remote.clone(), remote_server.clone()).await?;
+        from.index_remote_client(invalid_remote.0.clone(), invalid_remote.1.clone()).await?;
+        from.index_server(server.clone()).await?;
+
+        let snapshot = from.export_snapshot().await?;
+
+        assert_eq!(snapshot.local_clients.len(), 2);
+        assert_eq!(snapshot.remote_clients.len(), 2);
+
+        let snapshot = RouterSnapshot::from_json(&snapshot.to_json().unwrap()).unwrap();
+        let records = snapshot.len() as u64;
+
+        let result = to.import_snapshot(snapshot, &server_public).await?;
+
+        assert_eq!(result.skipped, 2);
+        assert_eq!(result.imported, records - 2);
+
+        assert!(to.lookup_local_client(&local.public_key, None).await?.is_some());
+        assert!(to.lookup_local_client(&invalid_local.public_key, None).await?.is_none());
+
+        assert!(to.lookup_remote_client(&remote.public_key, None).await?.is_some());
+        assert!(to.lookup_remote_client(&invalid_remote.0.public_key, None).await?.is_none());
+
+        assert!(to.lookup_server(&server.public_key).await?.is_some());
+
+        Ok(())
+    }
+
+    /// Check that the router counts its records,
+    /// client lookups and indexed announces.
+    pub async fn metrics_suite<T: Router + Sync>(router: T) -> Result<(), T::Error> {
+        let local = get_client();
+        let remote = get_client();
+
+        router.index_local_client(local.clone()).await?;
+        router.index_remote_client(remote.clone(), get_server()).await?;
+        router.index_server(get_server()).await?;
+
+        router.lookup_local_client(&local.public_key, None).await?;
+        router.lookup_remote_client(&remote.public_key, None).await?;
+        router.lookup_remote_client(&local.public_key, None).await?;
+
+        let metrics = router.metrics().await?;
+
+        assert_eq!(metrics, RouterMetrics {
+            local_clients: 1,
+            remote_clients: 1,
+            servers: 1,
+            lookup_hits: 2,
+            lookup_misses: 1,
+            client_announces: 1,
+            server_announces: 1
+        });
+
+        // Counters are cumulative
+        router.disconnect(&local.public_key).await?;
+
+        assert_eq!(router.metrics().await?.lookup_hits, 2);
+        assert_eq!(router.metrics().await?.local_clients, 0);
+
+        Ok(())
+    }
+
+    /// Check that aliases are unique, resolved
+    /// to their clients and freed on disconnect.
+    pub async fn alias_suite<T: Router + Sync>(router: T) -> Result<(), T::Error> {
+        let alias = ClientAlias::new("alice").unwrap();
+        let renamed = ClientAlias::new("alice-2").unwrap();
+
+        let client = get_client();
+        let another = get_client();
+
+        assert!(router.resolve_alias(&alias).await?.is_none());
+
+        assert!(router.register_alias(&alias, &client.public_key).await?);
+        assert!(router.register_alias(&alias, &client.public_key).await?);
+
+        // Aliases are unique
+        assert!(!router.register_alias(&alias, &another.public_key).await?);
+
+        assert_eq!(router.resolve_alias(&alias).await?, Some(client.public_key.clone()));
+
+        // New alias frees the previous one
+        assert!(router.register_alias(&renamed, &client.public_key).await?);
+        assert!(router.register_alias(&alias, &another.public_key).await?);
+
+        assert_eq!(router.resolve_alias(&renamed).await?, Some(client.public_key.clone()));
+        assert_eq!(router.resolve_alias(&alias).await?, Some(another.public_key.clone()));
+
+        router.disconnect(&client.public_key).await?;
+
+        assert!(router.resolve_alias(&renamed).await?.is_none());
+
+        Ok(())
+    }
+
+    /// Check that servers are stored once per public key
+    /// and their addresses are updated by newer announces.
+    pub async fn address_update_suite<T: Router + Sync>(router: T) -> Result<(), T::Error> {
+        let server = get_server();
+        let moved = Server::new(server.public_key.clone(), "127.0.0.1:8002");
+        let stale = Server::new(server.public_key.clone(), "127.0.0.1:8003");
+        let late = Server::new(server.public_key.clone(), "127.0.0.1:8004");
+
+        assert!(router.update_server_address(server.clone(), 1000).await?);
+        assert!(router.update_server_address(moved.clone(), 2000).await?);
+
+        assert_eq!(router.servers().await?.len(), 1);
+
+        // Older announces are ignored
+        assert!(!router.update_server_address(stale, 1500).await?);
+
+        assert_eq!(router.lookup_server(&server.public_key).await?.map(|(server, _)| server), Some(moved));
+
+        // Announces without timestamp are dated by the indexing time
+        assert!(router.index_server(server.clone()).await?);
+        assert!(!router.update_server_address(late, 2000).await?);
+
+        assert_eq!(router.lookup_server(&server.public_key).await?.map(|(server, _)| server.address), Some(server.address));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,966 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tokio::sync::RwLock;
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+use crate::time::timestamp;
+
+use super::{
+    Router,
+    RouterLimits,
+    RouterEviction,
+    RouterRecord,
+    RouterEvent,
+    RouterObserver,
+    RouterMetrics,
+    HintStats,
+    ServerHealth,
+    HealthTable,
+    AliasTable,
+    MetricsCounters,
+    SharedObserver,
+    make_room,
+    notify,
+    DEFAULT_AVAILABILITY_TIMEOUT
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Routing table is full: {records} records stored")]
+    TableFull {
+        records: usize
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Record<T> {
+    value: T,
+    indexed_at: Instant,
+
+    /// Time of the last index or request.
+    seen_at: Instant,
+
+    /// Tick of the last index or lookup.
+    used: u64
+}
+
+impl<T> Record<T> {
+    #[inline]
+    fn new(value: T, used: u64) -> Self {
+        Self {
+            value,
+            indexed_at: Instant::now(),
+            seen_at: Instant::now(),
+            used
+        }
+    }
+
+    #[inline]
+    fn is_alive(&self, ttl: Option<Duration>) -> bool {
+        ttl.is_none_or(|ttl| self.indexed_at.elapsed() < ttl)
+    }
+}
+
+type Records<T> = Arc<RwLock<HashMap<PublicKey, Record<T>>>>;
+
+#[derive(Debug, Clone, Copy)]
+/// Activity of the known server.
+struct Activity {
+    seen_at: Instant,
+    announces: u64,
+    lookups: u64,
+
+    /// UTC timestamp of the newest server announce.
+    announced_at: u64
+}
+
+impl Default for Activity {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            seen_at: Instant::now(),
+            announces: 0,
+            lookups: 0,
+            announced_at: 0
+        }
+    }
+}
+
+impl Activity {
+    #[inline]
+    fn stats(&self) -> HintStats {
+        HintStats {
+            last_seen: self.seen_at.elapsed(),
+            announces: self.announces,
+            lookups: self.lookups
+        }
+    }
+}
+
+/// Read alive records of the table.
+async fn alive<T: Clone>(records: &Records<T>, ttl: Option<Duration>) -> Vec<T> {
+    records.read().await
+        .values()
+        .filter(|record| record.is_alive(ttl))
+        .map(|record| record.value.clone())
+        .collect()
+}
+
+/// Count alive records of the table.
+async fn count<T>(records: &Records<T>, ttl: Option<Duration>) -> u64 {
+    records.read().await
+        .values()
+        .filter(|record| record.is_alive(ttl))
+        .count() as u64
+}
+
+/// Read alive record of the table, marking it as used.
+/// 
+/// Return the record and whether it was seen
+/// within the availability timeout.
+async fn lookup<T: Clone>(records: &Records<T>, ttl: Option<Duration>, availability_timeout: Duration, used: u64, public_key: &PublicKey) -> Option<(T, bool)> {
+    records.write().await
+        .get_mut(public_key)
+        .filter(|record| record.is_alive(ttl))
+        .map(|record| {
+            record.used = used;
+
+            (record.value.clone(), record.seen_at.elapsed() < availability_timeout)
+        })
+}
+
+/// Remove expired records from the table.
+fn remove_expired<T>(records: &mut HashMap<PublicKey, Record<T>>, ttl: Option<Duration>) -> Vec<T> {
+    remove_where(records, |record| !record.is_alive(ttl))
+}
+
+/// Remove records matching the predicate from the table.
+fn remove_where<T>(records: &mut HashMap<PublicKey, Record<T>>, predicate: impl Fn(&Record<T>) -> bool) -> Vec<T> {
+    let expired = records.iter()
+        .filter(|(_, record)| predicate(record))
+        .map(|(public_key, _)| public_key.clone())
+        .collect::<Vec<_>>();
+
+    expired.iter()
+        .filter_map(|public_key| records.remove(public_key))
+        .map(|record| record.value)
+        .collect()
+}
+
+/// Insert new record to the table, removing expired ones
+/// and evicting the least recently used one if it's full.
+/// 
+/// Return removed records.
+async fn insert<T>(
+    records: &Records<T>,
+    ttl: Option<Duration>,
+    max_records: Option<usize>,
+    eviction: RouterEviction,
+    used: u64,
+    public_key: PublicKey,
+    value: T
+) -> Result<Vec<T>, Error> {
+    let mut records = records.write().await;
+
+    let mut removed = Vec::new();
+
+    if ttl.is_some() {
+        removed = remove_expired(&mut records, ttl);
+    }
+
+    let evicted = make_room(&mut records, &public_key, max_records, eviction, |record| record.used)
+        .map_err(|records| Error::TableFull { records })?;
+
+    removed.extend(evicted.into_iter().map(|(_, record)| record.value));
+
+    records.insert(public_key, Record::new(value, used));
+
+    Ok(removed)
+}
+
+/// Get events of the removed records.
+fn expired<T>(removed: Vec<T>, record: impl Fn(T) -> RouterRecord) -> impl Iterator<Item = RouterEvent> {
+    removed.into_iter().map(move |value| RouterEvent::RecordExpired {
+        record: record(value)
+    })
+}
+
+#[derive(Debug, Clone)]
+/// In-memory routing table.
+/// 
+/// Records are lost when the router is dropped, so
+/// it's meant for tests and ephemeral relays. Every
+/// kind of records can have its own time to live,
+/// after which the record is not returned anymore.
+/// Records are re-indexed by clients' connections
+/// and servers' announcements.
+/// 
+/// Tables can be limited in size. Full tables evict
+/// the least recently indexed or looked up records.
+/// 
+/// Clients which weren't seen for `availability_timeout`
+/// are returned as unavailable by the lookup methods.
+/// 
+/// Lookup hints are ranked by servers' activity.
+/// Refer to `HintStats::score`. Unhealthy servers
+/// are not returned as hints.
+pub struct RamRouter {
+    local: Records<Client>,
+    remote: Records<(Client, Server)>,
+    servers: Records<Server>,
+
+    /// Activity of the known servers.
+    activity: Arc<RwLock<HashMap<PublicKey, Activity>>>,
+
+    /// Health statuses of the known servers.
+    health: HealthTable,
+
+    /// Aliases of the local clients.
+    aliases: AliasTable,
+
+    /// Lookups and announces counters.
+    metrics: MetricsCounters,
+
+    /// Logical clock of the records usage.
+    clock: Arc<AtomicU64>,
+
+    /// Observer notified about changes of the tables.
+    observer: Option<SharedObserver>,
+
+    /// Capacity limits of the routing tables.
+    pub limits: RouterLimits,
+
+    /// Time to live of the local clients' records.
+    pub local_ttl: Option<Duration>,
+
+    /// Time to live of the remote clients' records.
+    pub remote_ttl: Option<Duration>,
+
+    /// Time to live of the servers' records.
+    pub server_ttl: Option<Duration>,
+
+    /// Time after which clients without requests
+    /// to the server are not available.
+    pub availability_timeout: Duration
+}
+
+impl Default for RamRouter {
+    fn default() -> Self {
+        Self {
+            local: Records::default(),
+            remote: Records::default(),
+            servers: Records::default(),
+            activity: Arc::default(),
+            health: HealthTable::default(),
+            aliases: AliasTable::default(),
+            metrics: MetricsCounters::default(),
+            clock: Arc::default(),
+            observer: None,
+            limits: RouterLimits::default(),
+            local_ttl: None,
+            remote_ttl: None,
+            server_ttl: None,
+            availability_timeout: DEFAULT_AVAILABILITY_TIMEOUT
+        }
+    }
+}
+
+impl RamRouter {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    /// Expire local clients' records after the given duration.
+    pub fn with_local_ttl(self, ttl: Duration) -> Self {
+        Self {
+            local_ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Expire remote clients' records after the given duration.
+    pub fn with_remote_ttl(self, ttl: Duration) -> Self {
+        Self {
+            remote_ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Expire servers' records after the given duration.
+    pub fn with_server_ttl(self, ttl: Duration) -> Self {
+        Self {
+            server_ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Change time after which clients without
+    /// requests to the server are not available.
+    pub fn with_availability_timeout(self, timeout: Duration) -> Self {
+        Self {
+            availability_timeout: timeout,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Limit size of the routing tables.
+    pub fn with_limits(self, limits: RouterLimits) -> Self {
+        Self {
+            limits,
+            ..self
+        }
+    }
+
+    #[inline]
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Update activity of the given server.
+    async fn record_activity(&self, server: &PublicKey, update: impl FnOnce(&mut Activity)) {
+        update(self.activity.write().await.entry(server.clone()).or_default());
+    }
+
+    /// Forget activity of the servers which are
+    /// not stored in the routing table anymore.
+    async fn prune_activity(&self) {
+        let servers = self.servers.read().await;
+        let remote = self.remote.read().await;
+
+        self.activity.write().await.retain(|public_key, _| {
+            servers.contains_key(public_key) || remote.values().any(|record| &record.value.1.public_key == public_key)
+        });
+    }
+
+    /// Get activity statistics of the given server.
+    pub async fn hint_stats(&self, server: &PublicKey) -> Option<HintStats> {
+        self.activity.read().await
+            .get(server)
+            .map(Activity::stats)
+    }
+
+    /// Remove expired records from the table.
+    /// 
+    /// Expired records are never returned, and are removed
+    /// when new records of the same kind are indexed. This
+    /// method can be used to release memory of idle routers.
+    /// 
+    /// Return number of removed records.
+    pub async fn expire(&self) -> u64 {
+        let local = remove_expired(&mut *self.local.write().await, self.local_ttl);
+        let remote = remove_expired(&mut *self.remote.write().await, self.remote_ttl);
+        let servers = remove_expired(&mut *self.servers.write().await, self.server_ttl);
+
+        let removed = local.len() + remote.len() + servers.len();
+
+        let events = expired(local, RouterRecord::LocalClient)
+            .chain(expired(remote, |(client, server)| RouterRecord::RemoteClient(client, server)))
+            .chain(expired(servers, RouterRecord::Server));
+
+        notify(&self.observer, events).await;
+
+        self.prune_activity().await;
+
+        removed as u64
+    }
+}
+
+#[async_trait::async_trait]
+impl Router for RamRouter {
+    type Error = Error;
+
+    async fn index_local_client(&self, client: Client) -> Result<bool, Self::Error> {
+        let removed = insert(&self.local, self.local_ttl, self.limits.max_local_clients, self.limits.eviction, self.tick(), client.public_key.clone(), client.clone()).await?;
+
+        let events = expired(removed, RouterRecord::LocalClient)
+            .chain([RouterEvent::ClientIndexed { client, server: None }]);
+
+        notify(&self.observer, events).await;
+
+        Ok(true)
+    }
+
+    async fn index_remote_client(&self, client: Client, server: Server) -> Result<bool, Self::Error> {
+        let removed = insert(&self.remote, self.remote_ttl, self.limits.max_remote_clients, self.limits.eviction, self.tick(), client.public_key.clone(), (client.clone(), server.clone())).await?;
+
+        self.record_activity(&server.public_key, |activity| {
+            activity.seen_at = Instant::now();
+            activity.announces += 1;
+        }).await;
+
+        self.metrics.client_announce();
+
+        let events = expired(removed, |(client, server)| RouterRecord::RemoteClient(client, server))
+            .chain([RouterEvent::ClientIndexed { client, server: Some(server) }]);
+
+        notify(&self.observer, events).await;
+
+        Ok(true)
+    }
+
+    async fn index_server(&self, server: Server) -> Result<bool, Self::Error> {
+        self.update_server_address(server, timestamp()).await
+    }
+
+    async fn update_server_address(&self, server: Server, announced_at: u64) -> Result<bool, Self::Error> {
+        if self.servers.read().await.contains_key(&server.public_key) {
+            let stored_at = self.activity.read().await
+                .get(&server.public_key)
+                .map(|activity| activity.announced_at)
+                .unwrap_or_default();
+
+            // Ignore announces older than the stored record
+            if stored_at > announced_at {
+                return Ok(false);
+            }
+        }
+
+        let removed = insert(&self.servers, self.server_ttl, self.limits.max_servers, self.limits.eviction, self.tick(), server.public_key.clone(), server.clone()).await?;
+
+        self.record_activity(&server.public_key, |activity| {
+            activity.seen_at = Instant::now();
+            activity.announces += 1;
+            activity.announced_at = activity.announced_at.max(announced_at);
+        }).await;
+
+        self.metrics.server_announce();
+
+        let events = expired(removed, RouterRecord::Server)
+            .chain([RouterEvent::ServerIndexed { server }]);
+
+        notify(&self.observer, events).await;
+
+        Ok(true)
+    }
+
+    async fn disconnect(&self, public_key: &PublicKey) -> Result<(), Self::Error> {
+        let local = self.local.write().await.remove(public_key);
+        let remote = self.remote.write().await.remove(public_key);
+        let server = self.servers.write().await.remove(public_key);
+
+        self.aliases.remove(public_key);
+
+        if server.is_some() {
+            self.activity.write().await.remove(public_key);
+            self.health.remove(public_key);
+        }
+
+        let events = local.map(|record| RouterEvent::ClientDisconnected { client: record.value, server: None }).into_iter()
+            .chain(remote.map(|record| RouterEvent::ClientDisconnected { client: record.value.0, server: Some(record.value.1) }))
+            .chain(server.map(|record| RouterEvent::ServerDisconnected { server: record.value }));
+
+        notify(&self.observer, events).await;
+
+        Ok(())
+    }
+
+    async fn touch_local_client(&self, public_key: &PublicKey) -> Result<bool, Self::Error> {
+        let mut local = self.local.write().await;
+
+        let Some(record) = local.get_mut(public_key) else {
+            return Ok(false);
+        };
+
+        record.seen_at = Instant::now();
+
+        Ok(true)
+    }
+
+    async fn cleanup(&self, older_than: Duration) -> Result<u64, Self::Error> {
+        let is_stale = |seen_at: Instant| seen_at.elapsed() >= older_than;
+
+        let local = remove_where(&mut *self.local.write().await, |record| is_stale(record.seen_at));
+        let remote = remove_where(&mut *self.remote.write().await, |record| is_stale(record.seen_at));
+
+        let removed = local.len() + remote.len();
+
+        let events = expired(local, RouterRecord::LocalClient)
+            .chain(expired(remote, |(client, server)| RouterRecord::RemoteClient(client, server)));
+
+        notify(&self.observer, events).await;
+
+        self.prune_activity().await;
+
+        Ok(removed as u64)
+    }
+
+    async fn local_clients(&self) -> Result<Vec<Client>, Self::Error> {
+        Ok(alive(&self.local, self.local_ttl).await)
+    }
+
+    async fn remote_clients(&self) -> Result<Vec<(Client, Server)>, Self::Error> {
+        Ok(alive(&self.remote, self.remote_ttl).await)
+    }
+
+    async fn servers(&self) -> Result<Vec<Server>, Self::Error> {
+        Ok(alive(&self.servers, self.server_ttl).await)
+    }
+
+    async fn lookup_local_client(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> Result<Option<(Client, bool)>, Self::Error> {
+        let found = lookup(&self.local, self.local_ttl, self.availability_timeout, self.tick(), public_key).await
+            .filter(|(client, _)| client_type.is_none() || client_type == Some(client.info.client_type));
+
+        self.metrics.lookup(&found);
+
+        Ok(found)
+    }
+
+    async fn lookup_remote_client(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> Result<Option<(Client, Server, bool)>, Self::Error> {
+        let found = lookup(&self.remote, self.remote_ttl, self.availability_timeout, self.tick(), public_key).await
+            .filter(|((client, _), _)| client_type.is_none() || client_type == Some(client.info.client_type))
+            .map(|((client, server), available)| (client, server, available));
+
+        if let Some((_, server, _)) = &found {
+            self.record_activity(&server.public_key, |activity| activity.lookups += 1).await;
+        }
+
+        self.metrics.lookup(&found);
+
+        Ok(found)
+    }
+
+    async fn lookup_remote_client_hint(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> Result<Vec<ServerHint>, Self::Error> {
+        let known = self.lookup_remote_client(public_key, client_type).await?;
+
+        let activity = self.activity.read().await;
+
+        let hint = |server: Server| {
+            let score = activity.get(&server.public_key)
+                .map(|activity| activity.stats().score())
+                .unwrap_or_default();
+
+            ServerHint::new(server, score)
+        };
+
+        // Server of the known remote client is the best hint
+        if let Some((_, server, _)) = known {
+            if !self.health.get(&server.public_key).is_unhealthy() {
+                return Ok(vec![hint(server)]);
+            }
+        }
+
+        let mut hints = alive(&self.servers, self.server_ttl).await
+            .into_iter()
+            .filter(|server| !self.health.get(&server.public_key).is_unhealthy())
+            .map(hint)
+            .collect::<Vec<_>>();
+
+        hints.sort_by_key(|hint| std::cmp::Reverse(hint.score));
+
+        Ok(hints)
+    }
+
+    async fn metrics(&self) -> Result<RouterMetrics, Self::Error> {
+        Ok(self.metrics.metrics(
+            count(&self.local, self.local_ttl).await,
+            count(&self.remote, self.remote_ttl).await,
+            count(&self.servers, self.server_ttl).await
+        ))
+    }
+
+    async fn lookup_server(&self, public_key: &PublicKey) -> Result<Option<(Server, bool)>, Self::Error> {
+        // Servers are always available while indexed
+        Ok(lookup(&self.servers, self.server_ttl, Duration::MAX, self.tick(), public_key).await
+            .map(|(server, _)| (server, true)))
+    }
+
+    async fn mark_server_health(&self, public_key: &PublicKey, health: ServerHealth) -> Result<(), Self::Error> {
+        self.health.set(public_key, health);
+
+        Ok(())
+    }
+
+    async fn server_health(&self, public_key: &PublicKey) -> Result<ServerHealth, Self::Error> {
+        Ok(self.health.get(public_key))
+    }
+
+    async fn register_alias(&self, alias: &ClientAlias, public_key: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(self.aliases.register(alias, public_key))
+    }
+
+    async fn resolve_alias(&self, alias: &ClientAlias) -> Result<Option<PublicKey>, Self::Error> {
+        Ok(self.aliases.resolve(alias))
+    }
+
+    #[inline]
+    fn set_observer(&mut self, observer: Arc<dyn RouterObserver>) {
+        self.observer = Some(SharedObserver(observer));
+    }
+
+    fn error_status(&self, error: &Self::Error) -> ResponseStatus {
+        match error {
+            Error::TableFull { .. } => ResponseStatus::RoutingTableFull
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use crate::drivers::server::router::tests::{TestObserver, observer_suite, snapshot_suite, address_update_suite, metrics_suite, alias_suite};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn index_lookup() -> Result<(), Error> {
+        let router = RamRouter::new();
+
+        let local = get_client();
+        let remote = (get_client(), get_server());
+        let server = get_server();
+
+        router.index_local_client(local.clone()).await?;
+        router.index_remote_client(remote.0.clone(), remote.1.clone()).await?;
+        router.index_server(server.clone()).await?;
+
+        assert_eq!(router.lookup_local_client(&local.public_key, Some(local.info.client_type)).await?, Some((local.clone(), true)));
+        assert_eq!(router.lookup_remote_client(&remote.0.public_key, None).await?, Some((remote.0.clone(), remote.1.clone(), true)));
+        assert_eq!(router.lookup_server(&server.public_key).await?, Some((server.clone(), true)));
+
+        assert_eq!(router.local_clients().await?, vec![local.clone()]);
+        assert_eq!(router.remote_clients().await?, vec![remote.clone()]);
+        assert_eq!(router.servers().await?, vec![server.clone()]);
+
+        // Local clients are not remote ones
+        assert_eq!(router.lookup_remote_client(&local.public_key, None).await?, None);
+
+        // Known remote client's server is the only hint
+        assert_eq!(router.lookup_remote_client_hint(&remote.0.public_key, None).await?.into_iter().map(|hint| hint.server).collect::<Vec<_>>(), vec![remote.1.clone()]);
+        assert_eq!(router.lookup_remote_client_hint(&local.public_key, None).await?.into_iter().map(|hint| hint.server).collect::<Vec<_>>(), vec![server]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hint_scores() -> Result<(), Error> {
+        let router = RamRouter::new();
+
+        let servers = [get_server(), get_server(), get_server()];
+
+        for server in &servers {
+            router.index_server(server.clone()).await?;
+        }
+
+        // Frequently announced server
+        router.index_server(servers[1].clone()).await?;
+
+        // Server hosting a successfully looked up client
+        let client = get_client();
+
+        router.index_remote_client(client.clone(), servers[2].clone()).await?;
+        router.lookup_remote_client(&client.public_key, None).await?;
+
+        assert_eq!(router.hint_stats(&servers[2].public_key).await.map(|stats| (stats.announces, stats.lookups)), Some((2, 1)));
+
+        let hints = router.lookup_remote_client_hint(&get_client().public_key, None).await?;
+
+        assert_eq!(hints.iter().map(|hint| hint.server.clone()).collect::<Vec<_>>(), [servers[2].clone(), servers[1].clone(), servers[0].clone()]);
+        assert!(hints[0].score > hints[1].score && hints[1].score > hints[2].score);
+
+        // Activity of the disconnected servers is forgotten
+        router.disconnect(&servers[0].public_key).await?;
+
+        assert!(router.hint_stats(&servers[0].public_key).await.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn health() -> Result<(), Error> {
+        let router = RamRouter::new();
+
+        let remote = (get_client(), get_server());
+        let server = get_server();
+
+        router.index_remote_client(remote.0.clone(), remote.1.clone()).await?;
+        router.index_server(server.clone()).await?;
+        router.index_server(remote.1.clone()).await?;
+
+        assert_eq!(router.server_health(&server.public_key).await?, ServerHealth::Unknown);
+
+        router.mark_server_health(&server.public_key, ServerHealth::Unhealthy).await?;
+        router.mark_server_health(&remote.1.public_key, ServerHealth::Unhealthy).await?;
+
+        // Unhealthy servers are not hinted
+        assert!(router.lookup_remote_client_hint(&get_client().public_key, None).await?.is_empty());
+        assert!(router.lookup_remote_client_hint(&remote.0.public_key, None).await?.is_empty());
+
+        router.mark_server_health(&server.public_key, ServerHealth::Healthy).await?;
+
+        let hints = router.lookup_remote_client_hint(&remote.0.public_key, None).await?;
+
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].server, server);
+
+        router.disconnect(&server.public_key).await?;
+
+        assert_eq!(router.server_health(&server.public_key).await?, ServerHealth::Unknown);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn disconnect() -> Result<(), Error> {
+        let router = RamRouter::new();
+
+        let clients = [get_client(), get_client()];
+        let server = get_server();
+
+        for client in &clients {
+            router.index_local_client(client.clone()).await?;
+        }
+
+        router.index_server(server.clone()).await?;
+
+        router.disconnect(&clients[0].public_key).await?;
+        router.disconnect(&server.public_key).await?;
+
+        assert_eq!(router.lookup_local_client(&clients[0].public_key, None).await?, None);
+        assert_eq!(router.local_clients().await?, vec![clients[1].clone()]);
+        assert!(router.servers().await?.is_empty());
+
+        // Disconnected clients can connect again
+        router.index_local_client(clients[0].clone()).await?;
+
+        assert_eq!(router.local_clients().await?.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiry() -> Result<(), Error> {
+        let router = RamRouter::new()
+            .with_remote_ttl(Duration::from_millis(200))
+            .with_server_ttl(Duration::from_millis(200));
+
+        let local = get_client();
+        let remote = (get_client(), get_server());
+        let servers = [get_server(), get_server()];
+
+        router.index_local_client(local.clone()).await?;
+        router.index_remote_client(remote.0.clone(), remote.1.clone()).await?;
+        router.index_server(servers[0].clone()).await?;
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // Records without TTL never expire
+        assert_eq!(router.lookup_local_client(&local.public_key, None).await?, Some((local.clone(), true)));
+
+        assert_eq!(router.lookup_remote_client(&remote.0.public_key, None).await?, None);
+        assert_eq!(router.lookup_server(&servers[0].public_key).await?, None);
+        assert!(router.remote_clients().await?.is_empty());
+
+        // Re-indexed records are alive again
+        router.index_server(servers[0].clone()).await?;
+        router.index_server(servers[1].clone()).await?;
+
+        assert_eq!(router.servers().await?.len(), 2);
+
+        assert_eq!(router.expire().await, 1);
+        assert_eq!(router.expire().await, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lru_eviction() -> Result<(), Error> {
+        let router = RamRouter::new()
+            .with_limits(RouterLimits::new()
+                .with_max_local_clients(2)
+                .with_max_servers(2));
+
+        let clients = [get_client(), get_client(), get_client()];
+        let servers = [get_server(), get_server(), get_server()];
+
+        router.index_local_client(clients[0].clone()).await?;
+        router.index_local_client(clients[1].clone()).await?;
+
+        router.index_server(servers[0].clone()).await?;
+        router.index_server(servers[1].clone()).await?;
+
+        // Interleave lookups and announces so the oldest
+        // indexed records are the most recently used ones
+        router.lookup_local_client(&clients[0].public_key, None).await?;
+        router.index_server(servers[0].clone()).await?;
+
+        router.index_local_client(clients[2].clone()).await?;
+        router.index_server(servers[2].clone()).await?;
+
+        assert!(router.lookup_local_client(&clients[0].public_key, None).await?.is_some());
+        assert!(router.lookup_local_client(&clients[1].public_key, None).await?.is_none());
+        assert!(router.lookup_local_client(&clients[2].public_key, None).await?.is_some());
+
+        assert!(router.lookup_server(&servers[0].public_key).await?.is_some());
+        assert!(router.lookup_server(&servers[1].public_key).await?.is_none());
+        assert!(router.lookup_server(&servers[2].public_key).await?.is_some());
+
+        // Client 2 was looked up after client 0
+        router.index_local_client(clients[1].clone()).await?;
+
+        assert!(router.lookup_local_client(&clients[0].public_key, None).await?.is_none());
+        assert_eq!(router.local_clients().await?.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn table_full() -> Result<(), Error> {
+        let router = RamRouter::new()
+            .with_limits(RouterLimits::new()
+                .with_max_remote_clients(1)
+                .with_eviction(RouterEviction::Disabled));
+
+        let remote = [(get_client(), get_server()), (get_client(), get_server())];
+
+        router.index_remote_client(remote[0].0.clone(), remote[0].1.clone()).await?;
+
+        let result = router.index_remote_client(remote[1].0.clone(), remote[1].1.clone()).await;
+
+        assert!(matches!(result, Err(Error::TableFull { records: 1 })));
+        assert_eq!(router.error_status(&result.unwrap_err()), ResponseStatus::RoutingTableFull);
+
+        // Stored records can be re-indexed
+        router.index_remote_client(remote[0].0.clone(), remote[1].1.clone()).await?;
+
+        assert_eq!(router.remote_clients().await?, vec![(remote[0].0.clone(), remote[1].1.clone())]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn observer() -> Result<(), Error> {
+        observer_suite(RamRouter::new()).await
+    }
+
+    #[tokio::test]
+    async fn snapshot() -> Result<(), Error> {
+        snapshot_suite(RamRouter::new(), RamRouter::new()).await
+    }
+
+    #[tokio::test]
+    async fn address_update() -> Result<(), Error> {
+        address_update_suite(RamRouter::new()).await
+    }
+
+    #[tokio::test]
+    async fn metrics() -> Result<(), Error> {
+        metrics_suite(RamRouter::new()).await
+    }
+
+    #[tokio::test]
+    async fn aliases() -> Result<(), Error> {
+        alias_suite(RamRouter::new()).await
+    }
+
+    #[tokio::test]
+    async fn observer_expired() -> Result<(), Error> {
+        let observer = TestObserver::default();
+
+        let mut router = RamRouter::new()
+            .with_local_ttl(Duration::from_millis(200))
+            .with_limits(RouterLimits::new().with_max_remote_clients(1));
+
+        router.set_observer(Arc::new(observer.clone()));
+
+        let clients = [get_client(), get_client()];
+        let remote = [(get_client(), get_server()), (get_client(), get_server())];
+
+        router.index_local_client(clients[0].clone()).await?;
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // Expired record is removed on the next index
+        router.index_local_client(clients[1].clone()).await?;
+
+        for (client, server) in &remote {
+            router.index_remote_client(client.clone(), server.clone()).await?;
+        }
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(router.expire().await, 1);
+
+        assert_eq!(*observer.0.lock().unwrap(), [
+            RouterEvent::ClientIndexed { client: clients[0].clone(), server: None },
+            RouterEvent::RecordExpired { record: RouterRecord::LocalClient(clients[0].clone()) },
+            RouterEvent::ClientIndexed { client: clients[1].clone(), server: None },
+            RouterEvent::ClientIndexed { client: remote[0].0.clone(), server: Some(remote[0].1.clone()) },
+            RouterEvent::RecordExpired { record: RouterRecord::RemoteClient(remote[0].0.clone(), remote[0].1.clone()) },
+            RouterEvent::ClientIndexed { client: remote[1].0.clone(), server: Some(remote[1].1.clone()) },
+            RouterEvent::RecordExpired { record: RouterRecord::LocalClient(clients[1].clone()) }
+        ]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bulk_index() -> Result<(), Error> {
+        let router = RamRouter::new()
+            .with_limits(RouterLimits::new()
+                .with_max_servers(2)
+                .with_eviction(RouterEviction::Disabled));
+
+        let remote = vec![(get_client(), get_server()), (get_client(), get_server()), (get_client(), get_server())];
+        let servers = [get_server(), get_server(), get_server()];
+
+        let results = router.index_remote_clients(remote.clone()).await;
+
+        assert!(results.iter().all(|result| matches!(result, Ok(true))));
+        assert_eq!(router.remote_clients().await?.len(), 3);
+
+        // Failed records don't prevent indexing of the others
+        let results = router.index_servers(servers.to_vec()).await;
+
+        assert!(matches!(results[..], [Ok(true), Ok(true), Err(Error::TableFull { records: 2 })]));
+        assert!(router.lookup_server(&servers[1].public_key).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn last_seen() -> Result<(), Error> {
+        let router = RamRouter::new()
+            .with_availability_timeout(Duration::from_millis(200));
+
+        let clients = [get_client(), get_client()];
+        let remote = (get_client(), get_server());
+
+        for client in &clients {
+            router.index_local_client(client.clone()).await?;
+        }
+
+        router.index_remote_client(remote.0.clone(), remote.1.clone()).await?;
+
+        assert_eq!(router.lookup_local_client(&clients[0].public_key, None).await?, Some((clients[0].clone(), true)));
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert!(router.touch_local_client(&clients[0].public_key).await?);
+        assert!(!router.touch_local_client(&remote.0.public_key).await?);
+
+        // Clients without requests are not available
+        assert_eq!(router.lookup_local_client(&clients[0].public_key, None).await?, Some((clients[0].clone(), true)));
+        assert_eq!(router.lookup_local_client(&clients[1].public_key, None).await?, Some((clients[1].clone(), false)));
+        assert_eq!(router.lookup_remote_client(&remote.0.public_key, None).await?, Some((remote.0.clone(), remote.1.clone(), false)));
+
+        assert_eq!(router.cleanup(Duration::from_millis(200)).await?, 2);
+
+        assert_eq!(router.local_clients().await?, vec![clients[0].clone()]);
+        assert!(router.remote_clients().await?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+/// Dump of the whole routing table.
+/// 
+/// Can be used to debug the server or to
+/// seed the routing table of a fresh one.
+pub struct RouterSnapshot {
+    pub local_clients: Vec<Client>,
+    pub remote_clients: Vec<(Client, Server)>,
+    pub servers: Vec<Server>
+}
+
+impl RouterSnapshot {
+    #[inline]
+    pub fn new(local_clients: Vec<Client>, remote_clients: Vec<(Client, Server)>, servers: Vec<Server>) -> Self {
+        Self {
+            local_clients,
+            remote_clients,
+            servers
+        }
+    }
+
+    #[inline]
+    /// Total amount of the stored records.
+    pub fn len(&self) -> usize {
+        self.local_clients.len() + self.remote_clients.len() + self.servers.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl AsJson for RouterSnapshot {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "local_clients": self.local_clients.to_json()?,
+            "remote_clients": self.remote_clients.iter()
+                .map(|(client, server)| {
+                    Ok(json!({
+                        "client": client.to_json()?,
+                        "server": server.to_json()?
+                    }))
+                })
+                .collect::<Result<Vec<_>, AsJsonError>>()?,
+            "servers": self.servers.to_json()?
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(local_clients) = json.get("local_clients") else {
+            return Err(AsJsonError::FieldNotFound("local_clients"));
+        };
+
+        let Some(remote_clients) = json.get("remote_clients").and_then(Json::as_array) else {
+            return Err(AsJsonError::FieldNotFound("remote_clients"));
+        };
+
+        let Some(servers) = json.get("servers") else {
+            return Err(AsJsonError::FieldNotFound("servers"));
+        };
+
+        let remote_clients = remote_clients.iter()
+            .map(|record| {
+                let Some(client) = record.get("client") else {
+                    return Err(AsJsonError::FieldNotFound("remote_clients.client"));
+                };
+
+                let Some(server) = record.get("server") else {
+                    return Err(AsJsonError::FieldNotFound("remote_clients.server"));
+                };
+
+                Ok((Client::from_json(client)?, Server::from_json(server)?))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            local_clients: Vec::from_json(local_clients)?,
+            remote_clients,
+            servers: Vec::from_json(servers)?
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Result of the routing table snapshot import.
+pub struct SnapshotImport {
+    /// Amount of indexed records.
+    pub imported: u64,
+
+    /// Amount of records skipped because
+    /// of invalid connection certificates.
+    pub skipped: u64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let snapshot = RouterSnapshot::new(
+            vec![get_client(), get_client()],
+            vec![(get_client(), get_server())],
+            vec![get_server()]
+        );
+
+        assert_eq!(snapshot.len(), 4);
+        assert_eq!(RouterSnapshot::from_json(&snapshot.to_json()?)?, snapshot);
+
+        Ok(())
+    }
+}
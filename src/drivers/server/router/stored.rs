@@ -0,0 +1,1082 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value as Json};
+
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+use crate::time::timestamp;
+
+use super::{
+    Router,
+    RouterLimits,
+    RouterRecord,
+    RouterEvent,
+    RouterObserver,
+    RouterMetrics,
+    ServerHealth,
+    HealthTable,
+    AliasTable,
+    MetricsCounters,
+    SharedObserver,
+    make_room,
+    notify,
+    DEFAULT_AVAILABILITY_TIMEOUT
+};
+
+/// Minimal time between journaled requests of the client.
+/// 
+/// Requests are frequent, so only some of them are
+/// written to the disk. Restored last seen time can
+/// be older than the real one by this amount.
+const SEEN_JOURNAL_PERIOD: u64 = 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] AsJsonError),
+
+    #[error(transparent)]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("Routing table is full: {records} records stored")]
+    TableFull {
+        records: usize
+    }
+}
+
+#[derive(Debug)]
+struct Record<T> {
+    value: T,
+    indexed_at: u64,
+
+    /// Time of the last index or request.
+    seen_at: u64,
+
+    /// Last seen time written to the journal.
+    journaled_seen_at: u64,
+
+    /// Tick of the last index or lookup.
+    used: u64
+}
+
+impl<T> Record<T> {
+    #[inline]
+    fn is_available(&self, timeout: Duration) -> bool {
+        timestamp().saturating_sub(self.seen_at) < timeout.as_secs()
+    }
+}
+
+#[derive(Default, Debug)]
+struct Tables {
+    local: HashMap<PublicKey, Record<Client>>,
+    remote: HashMap<PublicKey, Record<(Client, Server)>>,
+    servers: HashMap<PublicKey, Record<Server>>,
+
+    /// Logical clock of the records usage.
+    clock: u64
+}
+
+impl Tables {
+    #[inline]
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+
+        self.clock
+    }
+
+    #[inline]
+    fn record<T>(&mut self, value: T, indexed_at: u64) -> Record<T> {
+        Record {
+            value,
+            indexed_at,
+            seen_at: indexed_at,
+            journaled_seen_at: indexed_at,
+            used: self.tick()
+        }
+    }
+
+    /// Apply journal operation to the tables.
+    fn apply(&mut self, operation: &Json) -> Result<(), AsJsonError> {
+        let indexed_at = || operation.get("indexed_at")
+            .and_then(Json::as_u64)
+            .ok_or(AsJsonError::FieldNotFound("indexed_at"));
+
+        // Compacted records keep their last seen time
+        fn seen_at<T>(operation: &Json, record: &mut Record<T>) {
+            if let Some(seen_at) = operation.get("seen_at").and_then(Json::as_u64) {
+                record.seen_at = seen_at;
+                record.journaled_seen_at = seen_at;
+            }
+        }
+
+        let public_key = || operation.get("public_key")
+            .and_then(Json::as_str)
+            .and_then(|public_key| PublicKey::from_base64(public_key).ok())
+            .ok_or(AsJsonError::FieldValueInvalid("public_key"));
+
+        match operation.get("op").and_then(Json::as_str) {
+            Some("local") => {
+                let client = Client::from_json(&operation["client"])?;
+                let mut record = self.record(client, indexed_at()?);
+
+                seen_at(operation, &mut record);
+
+                self.local.insert(record.value.public_key.clone(), record);
+            }
+
+            Some("remote") => {
+                let client = Client::from_json(&operation["client"])?;
+                let server = Server::from_json(&operation["server"])?;
+                let mut record = self.record((client, server), indexed_at()?);
+
+                seen_at(operation, &mut record);
+
+                self.remote.insert(record.value.0.public_key.clone(), record);
+            }
+
+            Some("server") => {
+                let server = Server::from_json(&operation["server"])?;
+                let record = self.record(server, indexed_at()?);
+
+                self.servers.insert(record.value.public_key.clone(), record);
+            }
+
+            Some("seen") => {
+                let seen_at = operation.get("seen_at")
+                    .and_then(Json::as_u64)
+                    .ok_or(AsJsonError::FieldNotFound("seen_at"))?;
+
+                if let Some(record) = self.local.get_mut(&public_key()?) {
+                    record.seen_at = seen_at;
+                    record.journaled_seen_at = seen_at;
+                }
+            }
+
+            Some("disconnect") => {
+                self.remove(&public_key()?);
+            }
+
+            Some("evict") => {
+                let public_key = public_key()?;
+
+                match operation.get("table").and_then(Json::as_str) {
+                    Some("local") => { self.local.remove(&public_key); }
+                    Some("remote") => { self.remote.remove(&public_key); }
+                    Some("server") => { self.servers.remove(&public_key); }
+
+                    _ => return Err(AsJsonError::FieldValueInvalid("table"))
+                }
+            }
+
+            _ => return Err(AsJsonError::FieldValueInvalid("op"))
+        }
+
+        Ok(())
+    }
+
+    /// Remove records of the public key from all the tables.
+    /// 
+    /// Return events of the removed records.
+    fn remove(&mut self, public_key: &PublicKey) -> Vec<RouterEvent> {
+        let local = self.local.remove(public_key);
+        let remote = self.remote.remove(public_key);
+        let server = self.servers.remove(public_key);
+
+        local.map(|record| RouterEvent::ClientDisconnected { client: record.value, server: None }).into_iter()
+            .chain(remote.map(|record| RouterEvent::ClientDisconnected { client: record.value.0, server: Some(record.value.1) }))
+            .chain(server.map(|record| RouterEvent::ServerDisconnected { server: record.value }))
+            .collect()
+    }
+
+    /// Encode all the records as journal operations.
+    /// 
+    /// Operations are sorted by the records usage
+    /// so the eviction order is kept on reload.
+    fn operations(&self) -> Result<Vec<Json>, AsJsonError> {
+        let mut operations = Vec::with_capacity(self.local.len() + self.remote.len() + self.servers.len());
+
+        for record in self.local.values() {
+            operations.push((record.used, json!({
+                "op": "local",
+                "indexed_at": record.indexed_at,
+                "seen_at": record.seen_at,
+                "client": record.value.to_json()?
+            })));
+        }
+
+        for record in self.remote.values() {
+            operations.push((record.used, json!({
+                "op": "remote",
+                "indexed_at": record.indexed_at,
+                "seen_at": record.seen_at,
+                "client": record.value.0.to_json()?,
+                "server": record.value.1.to_json()?
+            })));
+        }
+
+        for record in self.servers.values() {
+            operations.push((record.used, json!({
+                "op": "server",
+                "indexed_at": record.indexed_at,
+                "server": record.value.to_json()?
+            })));
+        }
+
+        operations.sort_by_key(|(used, _)| *used);
+
+        Ok(operations.into_iter()
+            .map(|(_, operation)| operation)
+            .collect())
+    }
+}
+
+/// Encode eviction of the records as journal operations.
+fn evictions<'a, T>(table: &'a str, evicted: &'a [(PublicKey, Record<T>)]) -> impl Iterator<Item = Json> + 'a {
+    evicted.iter().map(move |(public_key, _)| json!({
+        "op": "evict",
+        "table": table,
+        "public_key": public_key.to_base64()
+    }))
+}
+
+/// Get events of the evicted records.
+fn expired<T>(evicted: Vec<(PublicKey, Record<T>)>, record: impl Fn(T) -> RouterRecord) -> impl Iterator<Item = RouterEvent> {
+    evicted.into_iter().map(move |(_, evicted)| RouterEvent::RecordExpired {
+        record: record(evicted.value)
+    })
+}
+
+#[derive(Default, Debug)]
+struct Journal {
+    /// Operations waiting to be written.
+    pending: Vec<Json>,
+
+    /// Whether the pending operations flush is scheduled.
+    scheduled: bool
+}
+
+#[derive(Debug, Clone)]
+/// Routing table kept in memory and journaled to the disk.
+/// 
+/// Every index and disconnect operation is appended to the
+/// journal file, and the table is restored from it by the
+/// `load` method, so the server doesn't forget announced
+/// clients and servers on restart.
+/// 
+/// Operations are written in batches after `flush_delay`
+/// so a burst of announces doesn't thrash the disk. Call
+/// `flush` before dropping the router to write the last
+/// operations immediately.
+/// 
+/// Tables can be limited in size. Full tables evict
+/// the least recently indexed or looked up records.
+/// Lookups are not journaled, so reloaded records
+/// are ordered by their last indexing.
+/// 
+/// Clients which weren't seen for `availability_timeout`
+/// are returned as unavailable by the lookup methods.
+pub struct StoredRouter {
+    /// Path to the router's journal file.
+    pub journal_path: PathBuf,
+
+    /// Delay before writing indexed records to the disk.
+    pub flush_delay: Duration,
+
+    /// Capacity limits of the routing tables.
+    pub limits: RouterLimits,
+
+    /// Time after which clients without requests
+    /// to the server are not available.
+    pub availability_timeout: Duration,
+
+    tables: Arc<RwLock<Tables>>,
+    journal: Arc<Mutex<Journal>>,
+
+    /// Observer notified about changes of the tables.
+    observer: Option<SharedObserver>,
+
+    /// Health statuses of the known servers.
+    /// 
+    /// Statuses are not journaled, so servers
+    /// are unchecked after the router is loaded.
+    health: HealthTable,
+
+    /// Aliases of the local clients.
+    /// 
+    /// Aliases are not journaled, so clients must
+    /// reconnect to register them after the router
+    /// is loaded.
+    aliases: AliasTable,
+
+    /// Lookups and announces counters.
+    /// 
+    /// Counters are not journaled and
+    /// start from zero on every load.
+    metrics: MetricsCounters
+}
+
+impl StoredRouter {
+    /// Load routing table from the given folder,
+    /// creating it if needed.
+    /// 
+    /// Records not seen longer than `max_age` ago
+    /// are dropped. The journal is compacted to
+    /// contain only the loaded records.
+    pub async fn load(storage_folder: impl Into<PathBuf>, max_age: Option<Duration>) -> Result<Self, Error> {
+        let storage_folder = storage_folder.into();
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(?max_age, "Loading StoredRouter from {:?}", storage_folder);
+
+        tokio::fs::create_dir_all(&storage_folder).await?;
+
+        let journal_path = storage_folder.join("journal");
+
+        let mut tables = Tables::default();
+
+        match tokio::fs::read_to_string(&journal_path).await {
+            Ok(journal) => {
+                for line in journal.lines().filter(|line| !line.is_empty()) {
+                    // Last operation can be partially written on crash
+                    let applied = serde_json::from_str::<Json>(line)
+                        .map_err(Error::from)
+                        .and_then(|operation| Ok(tables.apply(&operation)?));
+
+                    if let Err(_err) = applied {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("Skipping invalid router journal record: {_err}");
+                    }
+                }
+            }
+
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err.into())
+        }
+
+        // Drop stale records
+        if let Some(max_age) = max_age {
+            let min_seen_at = timestamp().saturating_sub(max_age.as_secs());
+
+            tables.local.retain(|_, record| record.seen_at >= min_seen_at);
+            tables.remote.retain(|_, record| record.seen_at >= min_seen_at);
+            tables.servers.retain(|_, record| record.seen_at >= min_seen_at);
+        }
+
+        // Compact the journal
+        let mut compacted = Vec::new();
+
+        for operation in tables.operations()? {
+            compacted.extend(serde_json::to_vec(&operation)?);
+            compacted.push(b'\n');
+        }
+
+        let temp_path = journal_path.with_extension("tmp");
+
+        tokio::fs::write(&temp_path, compacted).await?;
+        tokio::fs::rename(&temp_path, &journal_path).await?;
+
+        Ok(Self {
+            journal_path,
+            flush_delay: Duration::from_secs(1),
+            limits: RouterLimits::default(),
+            availability_timeout: DEFAULT_AVAILABILITY_TIMEOUT,
+            tables: Arc::new(RwLock::new(tables)),
+            journal: Arc::new(Mutex::new(Journal::default())),
+            observer: None,
+            health: HealthTable::default(),
+            aliases: AliasTable::default(),
+            metrics: MetricsCounters::default()
+        })
+    }
+
+    #[inline]
+    /// Change delay before writing indexed records to the disk.
+    pub fn with_flush_delay(self, delay: Duration) -> Self {
+        Self {
+            flush_delay: delay,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Change time after which clients without
+    /// requests to the server are not available.
+    pub fn with_availability_timeout(self, timeout: Duration) -> Self {
+        Self {
+            availability_timeout: timeout,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Limit size of the routing tables.
+    /// 
+    /// Already loaded tables are shrunk on the
+    /// next indexing of the new records.
+    pub fn with_limits(self, limits: RouterLimits) -> Self {
+        Self {
+            limits,
+            ..self
+        }
+    }
+
+    /// Write pending operations to the journal.
+    pub async fn flush(&self) -> Result<(), Error> {
+        let mut journal = self.journal.lock().await;
+
+        journal.scheduled = false;
+
+        if journal.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut records = Vec::new();
+
+        for operation in &journal.pending {
+            records.extend(serde_json::to_vec(operation)?);
+            records.push(b'\n');
+        }
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.journal_path).await?;
+
+        file.write_all(&records).await?;
+        file.flush().await?;
+
+        journal.pending.clear();
+
+        Ok(())
+    }
+
+    /// Add operations to the journal and schedule their flush.
+    async fn journal(&self, operations: impl IntoIterator<Item = Json>) {
+        let mut journal = self.journal.lock().await;
+
+        journal.pending.extend(operations);
+
+        if journal.scheduled {
+            return;
+        }
+
+        journal.scheduled = true;
+
+        let router = self.clone();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(router.flush_delay).await;
+
+            if let Err(_err) = router.flush().await {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("Failed to write router journal: {_err}");
+            }
+        });
+    }
+}
+
+#[async_trait::async_trait]
+impl Router for StoredRouter {
+    type Error = Error;
+
+    async fn index_local_client(&self, client: Client) -> Result<bool, Self::Error> {
+        let indexed_at = timestamp();
+
+        let operation = json!({
+            "op": "local",
+            "indexed_at": indexed_at,
+            "client": client.to_json()?
+        });
+
+        let evicted = {
+            let mut tables = self.tables.write().await;
+
+            let evicted = make_room(&mut tables.local, &client.public_key, self.limits.max_local_clients, self.limits.eviction, |record| record.used)
+                .map_err(|records| Error::TableFull { records })?;
+
+            let record = tables.record(client.clone(), indexed_at);
+
+            tables.local.insert(record.value.public_key.clone(), record);
+
+            evicted
+        };
+
+        self.journal(evictions("local", &evicted).chain([operation])).await;
+
+        let events = expired(evicted, RouterRecord::LocalClient)
+            .chain([RouterEvent::ClientIndexed { client, server: None }]);
+
+        notify(&self.observer, events).await;
+
+        Ok(true)
+    }
+
+    async fn index_remote_client(&self, client: Client, server: Server) -> Result<bool, Self::Error> {
+        let indexed_at = timestamp();
+
+        let operation = json!({
+            "op": "remote",
+            "indexed_at": indexed_at,
+            "client": client.to_json()?,
+            "server": server.to_json()?
+        });
+
+        let evicted = {
+            let mut tables = self.tables.write().await;
+
+            let evicted = make_room(&mut tables.remote, &client.public_key, self.limits.max_remote_clients, self.limits.eviction, |record| record.used)
+                .map_err(|records| Error::TableFull { records })?;
+
+            let record = tables.record((client.clone(), server.clone()), indexed_at);
+
+            tables.remote.insert(record.value.0.public_key.clone(), record);
+
+            evicted
+        };
+
+        self.journal(evictions("remote", &evicted).chain([operation])).await;
+
+        self.metrics.client_announce();
+
+        let events = expired(evicted, |(client, server)| RouterRecord::RemoteClient(client, server))
+            .chain([RouterEvent::ClientIndexed { client, server: Some(server) }]);
+
+        notify(&self.observer, events).await;
+
+        Ok(true)
+    }
+
+    async fn index_server(&self, server: Server) -> Result<bool, Self::Error> {
+        self.update_server_address(server, timestamp()).await
+    }
+
+    async fn update_server_address(&self, server: Server, indexed_at: u64) -> Result<bool, Self::Error> {
+        let operation = json!({
+            "op": "server",
+            "indexed_at": indexed_at,
+            "server": server.to_json()?
+        });
+
+        let evicted = {
+            let mut tables = self.tables.write().await;
+
+            // Ignore announces older than the stored record
+            if tables.servers.get(&server.public_key).is_some_and(|record| record.indexed_at > indexed_at) {
+                return Ok(false);
+            }
+
+            let evicted = make_room(&mut tables.servers, &server.public_key, self.limits.max_servers, self.limits.eviction, |record| record.used)
+                .map_err(|records| Error::TableFull { records })?;
+
+            let record = tables.record(server.clone(), indexed_at);
+
+            tables.servers.insert(record.value.public_key.clone(), record);
+
+            evicted
+        };
+
+        self.journal(evictions("server", &evicted).chain([operation])).await;
+
+        self.metrics.server_announce();
+
+        let events = expired(evicted, RouterRecord::Server)
+            .chain([RouterEvent::ServerIndexed { server }]);
+
+        notify(&self.observer, events).await;
+
+        Ok(true)
+    }
+
+    async fn disconnect(&self, public_key: &PublicKey) -> Result<(), Self::Error> {
+        let events = self.tables.write().await.remove(public_key);
+
+        self.health.remove(public_key);
+        self.aliases.remove(public_key);
+
+        self.journal([json!({
+            "op": "disconnect",
+            "public_key": public_key.to_base64()
+        })]).await;
+
+        notify(&self.observer, events).await;
+
+        Ok(())
+    }
+
+    async fn touch_local_client(&self, public_key: &PublicKey) -> Result<bool, Self::Error> {
+        let seen_at = timestamp();
+
+        let journaled = {
+            let mut tables = self.tables.write().await;
+
+            let Some(record) = tables.local.get_mut(public_key) else {
+                return Ok(false);
+            };
+
+            record.seen_at = seen_at;
+
+            let journaled = seen_at >= record.journaled_seen_at + SEEN_JOURNAL_PERIOD;
+
+            if journaled {
+                record.journaled_seen_at = seen_at;
+            }
+
+            journaled
+        };
+
+        if journaled {
+            self.journal([json!({
+                "op": "seen",
+                "public_key": public_key.to_base64(),
+                "seen_at": seen_at
+            })]).await;
+        }
+
+        Ok(true)
+    }
+
+    async fn cleanup(&self, older_than: Duration) -> Result<u64, Self::Error> {
+        let min_seen_at = timestamp().saturating_sub(older_than.as_secs());
+
+        fn remove_stale<T>(table: &mut HashMap<PublicKey, Record<T>>, min_seen_at: u64) -> Vec<(PublicKey, Record<T>)> {
+            let stale = table.iter()
+                .filter(|(_, record)| record.seen_at < min_seen_at)
+                .map(|(public_key, _)| public_key.clone())
+                .collect::<Vec<_>>();
+
+            stale.into_iter()
+                .filter_map(|public_key| table.remove(&public_key).map(|record| (public_key, record)))
+                .collect()
+        }
+
+        let (local, remote) = {
+            let mut tables = self.tables.write().await;
+
+            (remove_stale(&mut tables.local, min_seen_at), remove_stale(&mut tables.remote, min_seen_at))
+        };
+
+        let removed = local.len() + remote.len();
+
+        self.journal(evictions("local", &local).chain(evictions("remote", &remote))).await;
+
+        let events = expired(local, RouterRecord::LocalClient)
+            .chain(expired(remote, |(client, server)| RouterRecord::RemoteClient(client, server)));
+
+        notify(&self.observer, events).await;
+
+        Ok(removed as u64)
+    }
+
+    async fn local_clients(&self) -> Result<Vec<Client>, Self::Error> {
+        Ok(self.tables.read().await.local.values()
+            .map(|record| record.value.clone())
+            .collect())
+    }
+
+    async fn remote_clients(&self) -> Result<Vec<(Client, Server)>, Self::Error> {
+        Ok(self.tables.read().await.remote.values()
+            .map(|record| record.value.clone())
+            .collect())
+    }
+
+    async fn servers(&self) -> Result<Vec<Server>, Self::Error> {
+        Ok(self.tables.read().await.servers.values()
+            .map(|record| record.value.clone())
+            .collect())
+    }
+
+    async fn lookup_local_client(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> Result<Option<(Client, bool)>, Self::Error> {
+        let mut tables = self.tables.write().await;
+
+        let used = tables.tick();
+
+        let found = tables.local.get_mut(public_key)
+            .filter(|record| client_type.is_none() || client_type == Some(record.value.info.client_type))
+            .map(|record| {
+                record.used = used;
+
+                (record.value.clone(), record.is_available(self.availability_timeout))
+            });
+
+        self.metrics.lookup(&found);
+
+        Ok(found)
+    }
+
+    async fn lookup_remote_client(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> Result<Option<(Client, Server, bool)>, Self::Error> {
+        let mut tables = self.tables.write().await;
+
+        let used = tables.tick();
+
+        let found = tables.remote.get_mut(public_key)
+            .filter(|record| client_type.is_none() || client_type == Some(record.value.0.info.client_type))
+            .map(|record| {
+                record.used = used;
+
+                (record.value.0.clone(), record.value.1.clone(), record.is_available(self.availability_timeout))
+            });
+
+        self.metrics.lookup(&found);
+
+        Ok(found)
+    }
+
+    async fn metrics(&self) -> Result<RouterMetrics, Self::Error> {
+        let tables = self.tables.read().await;
+
+        Ok(self.metrics.metrics(
+            tables.local.len() as u64,
+            tables.remote.len() as u64,
+            tables.servers.len() as u64
+        ))
+    }
+
+    async fn lookup_server(&self, public_key: &PublicKey) -> Result<Option<(Server, bool)>, Self::Error> {
+        let mut tables = self.tables.write().await;
+
+        let used = tables.tick();
+
+        Ok(tables.servers.get_mut(public_key)
+            .map(|record| {
+                record.used = used;
+
+                (record.value.clone(), true)
+            }))
+    }
+
+    async fn mark_server_health(&self, public_key: &PublicKey, health: ServerHealth) -> Result<(), Self::Error> {
+        self.health.set(public_key, health);
+
+        Ok(())
+    }
+
+    async fn server_health(&self, public_key: &PublicKey) -> Result<ServerHealth, Self::Error> {
+        Ok(self.health.get(public_key))
+    }
+
+    async fn register_alias(&self, alias: &ClientAlias, public_key: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(self.aliases.register(alias, public_key))
+    }
+
+    async fn resolve_alias(&self, alias: &ClientAlias) -> Result<Option<PublicKey>, Self::Error> {
+        Ok(self.aliases.resolve(alias))
+    }
+
+    #[inline]
+    fn set_observer(&mut self, observer: Arc<dyn RouterObserver>) {
+        self.observer = Some(SharedObserver(observer));
+    }
+
+    fn error_status(&self, error: &Self::Error) -> ResponseStatus {
+        match error {
+            Error::TableFull { .. } => ResponseStatus::RoutingTableFull,
+            _ => ResponseStatus::ServerError
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use crate::drivers::server::router::RouterEviction;
+    use crate::drivers::server::router::tests::{observer_suite, snapshot_suite, address_update_suite, metrics_suite, alias_suite};
+
+    use super::*;
+
+    async fn prepare_folder(name: &str) -> std::io::Result<PathBuf> {
+        let temp = std::env::temp_dir()
+            .join(name);
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        tokio::fs::create_dir(&temp).await?;
+
+        Ok(temp)
+    }
+
+    #[tokio::test]
+    async fn reload() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-reload-test").await?;
+
+        let clients = [get_client(), get_client()];
+        let remote = (get_client(), get_server());
+        let server = get_server();
+
+        let router = StoredRouter::load(&temp, None).await?;
+
+        for client in &clients {
+            router.index_local_client(client.clone()).await?;
+        }
+
+        router.index_remote_client(remote.0.clone(), remote.1.clone()).await?;
+        router.index_server(server.clone()).await?;
+
+        router.disconnect(&clients[1].public_key).await?;
+
+        router.flush().await?;
+
+        drop(router);
+
+        let router = StoredRouter::load(&temp, None).await?;
+
+        assert_eq!(router.lookup_local_client(&clients[0].public_key, Some(clients[0].info.client_type)).await?, Some((clients[0].clone(), true)));
+        assert_eq!(router.lookup_local_client(&clients[1].public_key, None).await?, None);
+        assert_eq!(router.lookup_remote_client(&remote.0.public_key, None).await?, Some((remote.0, remote.1, true)));
+        assert_eq!(router.lookup_server(&server.public_key).await?, Some((server, true)));
+
+        // Journal is compacted on load
+        assert_eq!(tokio::fs::read_to_string(temp.join("journal")).await?.lines().count(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn debounce() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-debounce-test").await?;
+
+        let router = StoredRouter::load(&temp, None).await?
+            .with_flush_delay(Duration::from_millis(200));
+
+        let clients = vec![get_client(); 8];
+
+        for client in &clients {
+            router.index_local_client(client.clone()).await?;
+        }
+
+        // Burst of operations is not written yet
+        assert_eq!(tokio::fs::read_to_string(temp.join("journal")).await?, "");
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        assert_eq!(tokio::fs::read_to_string(temp.join("journal")).await?.lines().count(), 8);
+
+        let router = StoredRouter::load(&temp, None).await?;
+
+        for client in clients {
+            assert_eq!(router.lookup_local_client(&client.public_key, None).await?, Some((client, true)));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stale_records() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-stale-test").await?;
+
+        let stale = get_client();
+        let fresh = get_client();
+
+        let journal = [
+            json!({ "op": "local", "indexed_at": timestamp() - 3600, "client": stale.to_json()? }),
+            json!({ "op": "local", "indexed_at": timestamp(), "client": fresh.to_json()? })
+        ];
+
+        let mut records = journal.iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n");
+
+        // Partially written operation
+        records.push_str("\n{\"op\": \"loc");
+
+        tokio::fs::write(temp.join("journal"), records).await?;
+
+        let router = StoredRouter::load(&temp, Some(Duration::from_secs(60))).await?;
+
+        assert_eq!(router.local_clients().await?, vec![fresh]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lru_eviction() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-lru-test").await?;
+
+        let limits = RouterLimits::new()
+            .with_max_remote_clients(2)
+            .with_max_servers(2);
+
+        let router = StoredRouter::load(&temp, None).await?
+            .with_limits(limits);
+
+        let remote = [(get_client(), get_server()), (get_client(), get_server()), (get_client(), get_server())];
+        let servers = [get_server(), get_server(), get_server()];
+
+        router.index_remote_client(remote[0].0.clone(), remote[0].1.clone()).await?;
+        router.index_remote_client(remote[1].0.clone(), remote[1].1.clone()).await?;
+
+        router.index_server(servers[0].clone()).await?;
+        router.index_server(servers[1].clone()).await?;
+
+        // Interleave lookups and announces so the oldest
+        // indexed records are the most recently used ones
+        router.lookup_remote_client(&remote[0].0.public_key, None).await?;
+        router.index_server(servers[0].clone()).await?;
+
+        router.index_remote_client(remote[2].0.clone(), remote[2].1.clone()).await?;
+        router.index_server(servers[2].clone()).await?;
+
+        assert!(router.lookup_remote_client(&remote[0].0.public_key, None).await?.is_some());
+        assert!(router.lookup_remote_client(&remote[1].0.public_key, None).await?.is_none());
+        assert!(router.lookup_server(&servers[0].public_key).await?.is_some());
+        assert!(router.lookup_server(&servers[1].public_key).await?.is_none());
+
+        // Remote client 2 was looked up after remote client 0
+        router.lookup_remote_client(&remote[2].0.public_key, None).await?;
+        router.index_remote_client(remote[1].0.clone(), remote[1].1.clone()).await?;
+
+        assert!(router.lookup_remote_client(&remote[0].0.public_key, None).await?.is_none());
+
+        router.flush().await?;
+
+        drop(router);
+
+        // Evictions are journaled
+        let router = StoredRouter::load(&temp, None).await?
+            .with_limits(limits);
+
+        assert!(router.lookup_remote_client(&remote[0].0.public_key, None).await?.is_none());
+        assert!(router.lookup_server(&servers[1].public_key).await?.is_none());
+
+        assert_eq!(router.remote_clients().await?.len(), 2);
+        assert_eq!(router.servers().await?.len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn table_full() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-full-test").await?;
+
+        let router = StoredRouter::load(&temp, None).await?
+            .with_limits(RouterLimits::new()
+                .with_max_local_clients(1)
+                .with_eviction(RouterEviction::Disabled));
+
+        let clients = [get_client(), get_client()];
+
+        router.index_local_client(clients[0].clone()).await?;
+
+        let result = router.index_local_client(clients[1].clone()).await;
+
+        assert!(matches!(result, Err(Error::TableFull { records: 1 })));
+        assert_eq!(router.error_status(&result.unwrap_err()), ResponseStatus::RoutingTableFull);
+
+        assert_eq!(router.local_clients().await?, vec![clients[0].clone()]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn observer() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-observer-test").await?;
+
+        observer_suite(StoredRouter::load(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn snapshot() -> Result<(), Error> {
+        let from = prepare_folder("stored-router-snapshot-from-test").await?;
+        let to = prepare_folder("stored-router-snapshot-to-test").await?;
+
+        snapshot_suite(StoredRouter::load(&from, None).await?, StoredRouter::load(&to, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn address_update() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-address-update-test").await?;
+
+        address_update_suite(StoredRouter::load(&temp, None).await?).await?;
+
+        let server = get_server();
+        let router = StoredRouter::load(&temp, None).await?;
+
+        router.update_server_address(server.clone(), 2000).await?;
+        router.flush().await?;
+
+        // Announce time is restored from the journal
+        let router = StoredRouter::load(&temp, None).await?;
+
+        assert!(!router.update_server_address(server, 1000).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn metrics() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-metrics-test").await?;
+
+        metrics_suite(StoredRouter::load(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn aliases() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-aliases-test").await?;
+
+        alias_suite(StoredRouter::load(&temp, None).await?).await
+    }
+
+    #[tokio::test]
+    async fn last_seen() -> Result<(), Error> {
+        let temp = prepare_folder("stored-router-last-seen-test").await?;
+
+        let clients = [get_client(), get_client()];
+        let remote = (get_client(), get_server());
+
+        let journal = [
+            json!({ "op": "local", "indexed_at": timestamp() - 3600, "client": clients[0].to_json()? }),
+            json!({ "op": "local", "indexed_at": timestamp() - 3600, "client": clients[1].to_json()? }),
+            json!({ "op": "remote", "indexed_at": timestamp() - 3600, "client": remote.0.to_json()?, "server": remote.1.to_json()? }),
+            json!({ "op": "seen", "public_key": clients[0].public_key.to_base64(), "seen_at": timestamp() - 60 })
+        ];
+
+        let records = journal.iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n");
+
+        tokio::fs::write(temp.join("journal"), records).await?;
+
+        let router = StoredRouter::load(&temp, None).await?;
+
+        // Clients without requests are not available
+        assert_eq!(router.lookup_local_client(&clients[0].public_key, None).await?, Some((clients[0].clone(), true)));
+        assert_eq!(router.lookup_local_client(&clients[1].public_key, None).await?, Some((clients[1].clone(), false)));
+        assert_eq!(router.lookup_remote_client(&remote.0.public_key, None).await?, Some((remote.0.clone(), remote.1.clone(), false)));
+
+        assert!(router.touch_local_client(&clients[1].public_key).await?);
+        assert!(!router.touch_local_client(&remote.0.public_key).await?);
+
+        assert_eq!(router.lookup_local_client(&clients[1].public_key, None).await?, Some((clients[1].clone(), true)));
+
+        assert_eq!(router.cleanup(Duration::from_secs(600)).await?, 1);
+
+        router.flush().await?;
+
+        drop(router);
+
+        // Last seen time and cleanup are journaled
+        let router = StoredRouter::load(&temp, None).await?;
+
+        assert_eq!(router.local_clients().await?.len(), 2);
+        assert!(router.remote_clients().await?.is_empty());
+
+        assert_eq!(router.lookup_local_client(&clients[1].public_key, None).await?, Some((clients[1].clone(), true)));
+
+        Ok(())
+    }
+}
@@ -1,14 +1,40 @@
+use std::sync::Arc;
+
+use crate::address::Address;
+use crate::crypto::asymmetric::PublicKey;
 use crate::drivers::ClientDriver;
+use crate::http::client::HttpClient;
 use crate::rest_api::prelude::*;
 
-use super::params::ServerParams;
+use super::params::{ServerParams, UnsignedBodies, ReplayProtection, LegacyRequests};
+use super::messages_inbox::{InboxStats, InboxObserver};
+use super::router::{RouterObserver, RouterSnapshot, SnapshotImport};
+use super::reputation::{ReputationProvider, ReputationAction, Incident, SharedReputation};
+use super::usage::{UsageTracker, UsageEvent, Usage, SharedUsage};
+use super::blacklist::Blacklist;
+use super::replay::{NonceCache, ReplayError};
+use super::rate_limit::{RateLimiter, RateLimitExceeded};
+use super::receipts::{PendingReceipts, PendingReceipt};
+use super::bootstrap::{BootstrapSummary, bootstrap_server};
+
+#[cfg(feature = "long-poll")]
+use super::waiters::InboxWaiters;
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ServerDriver<Router, Traversal, MessagesInbox> {
     router: Router,
     traversal: Traversal,
     messages_inbox: MessagesInbox,
-    params: ServerParams
+    params: ServerParams,
+    reputation: Option<SharedReputation>,
+    usage: Option<SharedUsage>,
+    blacklist: Blacklist,
+    nonces: NonceCache,
+    rate_limiter: Option<RateLimiter>,
+    receipts: PendingReceipts,
+
+    #[cfg(feature = "long-poll")]
+    waiters: InboxWaiters
 }
 
 impl<Router, Traversal, MessagesInbox> ServerDriver<Router, Traversal, MessagesInbox>
@@ -23,10 +49,84 @@ where
             router,
             traversal,
             messages_inbox,
-            params
+            params,
+            reputation: None,
+            usage: None,
+            blacklist: Blacklist::default(),
+            nonces: NonceCache::default(),
+            rate_limiter: None,
+            receipts: PendingReceipts::default(),
+
+            #[cfg(feature = "long-poll")]
+            waiters: InboxWaiters::default()
         }
     }
 
+    #[inline]
+    /// Score remote keys by the given reputation provider.
+    /// 
+    /// Requests of the keys with low scores are handled
+    /// according to the `ServerParams::reputation` policy.
+    pub fn with_reputation(mut self, reputation: impl ReputationProvider + 'static) -> Self {
+        self.reputation = Some(SharedReputation(Arc::new(reputation)));
+
+        self
+    }
+
+    #[inline]
+    /// Account messages processed for each client
+    /// by the given usage tracker.
+    pub fn with_usage(mut self, usage: impl UsageTracker + 'static) -> Self {
+        self.usage = Some(SharedUsage(Arc::new(usage)));
+
+        self
+    }
+
+    #[inline]
+    /// Reject requests and announces of the
+    /// keys stored in the given blacklist.
+    /// 
+    /// Blacklist is shared with its clones, so keys can
+    /// be banned and unbanned while the server is running.
+    pub fn with_blacklist(mut self, blacklist: Blacklist) -> Self {
+        self.blacklist = blacklist;
+
+        self
+    }
+
+    #[inline]
+    /// Throttle requests by the given rate limiter.
+    /// 
+    /// Rate limiter is shared with its clones, so its
+    /// buckets can be inspected while the server is running.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+
+        self
+    }
+
+    #[inline]
+    /// Notify the given observer about messages
+    /// added to the server's inbox.
+    /// 
+    /// Refer to `MessagesInbox::set_observer`.
+    pub fn with_inbox_observer(mut self, observer: impl InboxObserver + 'static) -> Self {
+        self.messages_inbox.set_observer(Arc::new(observer));
+
+        self
+    }
+
+    #[inline]
+    /// Notify the given observer about changes
+    /// of the server's routing table.
+    /// 
+    /// Refer to `Router::set_observer`.
+    pub fn with_router_observer(mut self, observer: impl RouterObserver + 'static) -> Self {
+        self.router.set_observer(Arc::new(observer));
+
+        self
+    }
+
     #[inline]
     pub fn router(&self) -> &Router {
         &self.router
@@ -47,6 +147,470 @@ where
         &self.params
     }
 
+    #[inline]
+    pub fn reputation(&self) -> Option<&dyn ReputationProvider> {
+        self.reputation.as_ref().map(|reputation| reputation.0.as_ref())
+    }
+
+    #[inline]
+    pub fn blacklist(&self) -> &Blacklist {
+        &self.blacklist
+    }
+
+    #[inline]
+    /// Check if the given key is banned by the server.
+    pub fn is_blacklisted(&self, key: &PublicKey) -> bool {
+        self.blacklist.contains(key)
+    }
+
+    #[inline]
+    /// Check that the signed request is neither
+    /// expired nor replayed.
+    /// 
+    /// Always succeeds if replay protection is disabled.
+    /// Refer to `ServerParams::replay_protection`.
+    pub fn check_replay<T>(&self, request: &Request<T>) -> Result<(), ReplayError> {
+        let Some(params) = &self.params.replay_protection else {
+            return Ok(());
+        };
+
+        self.nonces.check(params, &request.public_key, request.proof_seed, request.timestamp, crate::time::timestamp())
+    }
+
+    #[inline]
+    /// Same as `check_replay`, but never skipped.
+    /// 
+    /// Used by the admin API: requests without timestamp
+    /// are rejected, and the default window is used
+    /// if replay protection is disabled.
+    pub fn check_admin_replay<T>(&self, request: &Request<T>) -> Result<(), ReplayError> {
+        let params = ReplayProtection {
+            legacy_requests: LegacyRequests::Reject,
+            ..self.params.replay_protection.unwrap_or_default()
+        };
+
+        self.nonces.check(&params, &request.public_key, request.proof_seed, request.timestamp, crate::time::timestamp())
+    }
+
+    #[inline]
+    /// Check if the signed request's body is signed
+    /// as well, or unsigned bodies are accepted.
+    /// 
+    /// Refer to `ServerParams::unsigned_bodies`.
+    pub fn is_body_accepted<T>(&self, request: &Request<T>) -> bool {
+        request.is_body_signed() || self.params.unsigned_bodies == UnsignedBodies::Accept
+    }
+
+    #[inline]
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+
+    #[inline]
+    /// Take a token from the rate limiter's bucket
+    /// of the given key for the signed request
+    /// to the given route.
+    /// 
+    /// Always succeeds if there's no rate limiter.
+    pub fn check_rate_limit(&self, route: &str, key: &PublicKey) -> Result<(), RateLimitExceeded> {
+        match &self.rate_limiter {
+            Some(rate_limiter) => rate_limiter.check_key(route, key),
+            None => Ok(())
+        }
+    }
+
+    #[inline]
+    pub fn pending_receipts(&self) -> &PendingReceipts {
+        &self.receipts
+    }
+
+    #[cfg(feature = "long-poll")]
+    #[inline]
+    pub fn inbox_waiters(&self) -> &InboxWaiters {
+        &self.waiters
+    }
+
+    #[inline]
+    /// Wake up long polls waiting for
+    /// the given receiver's messages.
+    /// 
+    /// Does nothing without the `long-poll` feature.
+    pub fn notify_receiver(&self, receiver: &PublicKey) {
+        #[cfg(feature = "long-poll")]
+        self.waiters.notify(receiver);
+
+        #[cfg(not(feature = "long-poll"))]
+        let _ = receiver;
+    }
+
+    #[cfg(feature = "long-poll")]
+    #[inline]
+    /// Get time the poll should wait for new messages,
+    /// bounded by the `ServerParams::max_poll_wait`.
+    /// 
+    /// Return `None` if the poll shouldn't wait.
+    pub fn poll_wait(&self, wait_ms: Option<u64>) -> Option<std::time::Duration> {
+        let wait = std::time::Duration::from_millis(wait_ms?)
+            .min(self.params.max_poll_wait?);
+
+        (!wait.is_zero()).then_some(wait)
+    }
+
+    #[cfg(feature = "long-poll")]
+    /// Wait up to the given time until the receiver
+    /// has messages in the channels matched by the rule.
+    /// 
+    /// Return immediately if there are such messages
+    /// already, or the inbox can't list them.
+    pub async fn wait_messages(&self, receiver: &PublicKey, channel: &ChannelRule, timeout: std::time::Duration) where MessagesInbox: Sync {
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        let waiter = self.waiters.waiter(receiver);
+
+        loop {
+            let notified = waiter.notified();
+
+            tokio::pin!(notified);
+
+            // Subscribe before checking the inbox so
+            // messages sent in between are not missed
+            notified.as_mut().enable();
+
+            match self.messages_inbox.list_channels(receiver.clone()).await {
+                Ok(channels) => {
+                    if channels.iter().any(|(name, count)| *count > 0 && channel.matches(name)) {
+                        return;
+                    }
+                }
+
+                Err(_err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(receiver = receiver.to_base64(), "Failed to list inbox channels: {_err}");
+
+                    return;
+                }
+            }
+
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Store delivery receipt of the message
+    /// in the inbox for its sender.
+    /// 
+    /// Receipt is sent to the `DeliveryReceipt::CHANNEL`
+    /// channel by the server itself. Errors are logged and
+    /// ignored so they don't fail the sender's request.
+    pub async fn issue_receipt(
+        &self,
+        sender: &PublicKey,
+        receiver: PublicKey,
+        channel: ChannelName,
+        message_id: Option<u64>,
+        status: DeliveryStatus,
+        encoding: MessageEncoding
+    ) where MessagesInbox: Sync {
+        let secret = &self.params.secret_key;
+
+        let receipt = DeliveryReceipt::new(secret, message_id, receiver, channel, status);
+
+        let message = match receipt.to_message(secret, sender, encoding) {
+            Ok(message) => message,
+
+            Err(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(sender = sender.to_base64(), "Failed to create delivery receipt: {_err}");
+
+                return;
+            }
+        };
+
+        let certificate = ConnectionCertificate::new(secret, secret.public_key());
+
+        let client = Client::new(secret.public_key(), certificate, ClientInfo::server(&self.params.address));
+        let server = Server::new(secret.public_key(), &self.params.address);
+
+        let result = self.messages_inbox.add_message(
+            Sender::new(client, server),
+            sender.clone(),
+            ChannelName::new_unchecked(DeliveryReceipt::CHANNEL),
+            message
+        ).await;
+
+        match result {
+            Ok(_) => self.notify_receiver(sender),
+
+            Err(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(sender = sender.to_base64(), "Failed to store delivery receipt: {_err}");
+            }
+        }
+    }
+
+    /// Issue receipts of the sent message.
+    /// 
+    /// `delivered` receipt is issued right away, and
+    /// `polled` one is issued when the message is polled
+    /// if the inbox has assigned an id to it.
+    pub async fn issue_delivery_receipts(
+        &self,
+        sender: &PublicKey,
+        receiver: PublicKey,
+        channel: ChannelName,
+        message_id: Option<u64>,
+        encoding: MessageEncoding
+    ) where MessagesInbox: Sync {
+        if let Some(id) = message_id {
+            self.receipts.insert(receiver.clone(), id, PendingReceipt {
+                sender: sender.clone(),
+                channel: channel.clone(),
+                encoding
+            });
+        }
+
+        self.issue_receipt(sender, receiver, channel, message_id, DeliveryStatus::Delivered, encoding).await;
+    }
+
+    /// Issue `polled` receipts of the messages
+    /// polled by the given receiver.
+    pub async fn issue_polled_receipts(&self, receiver: &PublicKey, messages: &[MessageInfo]) where MessagesInbox: Sync {
+        // Don't lock the receipts if no one waits for them
+        if self.receipts.is_empty() {
+            return;
+        }
+
+        for id in messages.iter().filter_map(|message| message.id) {
+            if let Some(pending) = self.receipts.take(receiver, id) {
+                self.issue_receipt(
+                    &pending.sender,
+                    receiver.clone(),
+                    pending.channel,
+                    Some(id),
+                    DeliveryStatus::Polled,
+                    pending.encoding
+                ).await;
+            }
+        }
+    }
+
+    /// Get features and limits of the server
+    /// advertised by the `GET /api/v1/info` response.
+    pub fn capabilities(&self) -> ServerCapabilities {
+        let mut capabilities = ServerCapabilities::new()
+            .with_capability(ServerCapabilities::SEND_BATCH, self.params.max_batch_size > 0)
+            .with_capability(ServerCapabilities::LOOKUP_BATCH, self.params.max_lookup_batch_size > 0)
+            .with_capability(ServerCapabilities::ACK_POLL, self.params.poll_lease.is_some())
+            .with_capability(ServerCapabilities::REPLAY_PROTECTION, self.params.replay_protection.is_some())
+            .with_capability(ServerCapabilities::RATE_LIMITS, self.rate_limiter.is_some())
+            .with_capability(ServerCapabilities::DELIVERY_RECEIPTS, true)
+            .with_limit(ServerCapabilities::MAX_MESSAGE_SIZE, self.params.max_message_size as u64)
+            .with_limit(ServerCapabilities::MAX_BATCH_SIZE, self.params.max_batch_size as u64)
+            .with_limit(ServerCapabilities::MAX_LOOKUP_BATCH_SIZE, self.params.max_lookup_batch_size as u64);
+
+        #[cfg(feature = "long-poll")]
+        if let Some(wait) = self.params.max_poll_wait {
+            capabilities = capabilities
+                .with_capability(ServerCapabilities::LONG_POLL, true)
+                .with_limit(ServerCapabilities::MAX_POLL_WAIT, wait.as_millis() as u64);
+        }
+
+        if let Some(lifetime) = self.params.certificate_lifetime {
+            capabilities = capabilities.with_limit(ServerCapabilities::CERTIFICATE_LIFETIME, lifetime.as_secs());
+        }
+
+        if let Some(lease) = self.params.poll_lease {
+            capabilities = capabilities.with_limit(ServerCapabilities::POLL_LEASE, lease.as_secs());
+        }
+
+        if self.params.connect_difficulty > 0 {
+            capabilities = capabilities.with_limit(ServerCapabilities::CONNECT_DIFFICULTY, self.params.connect_difficulty as u64);
+        }
+
+        capabilities
+    }
+
+    #[inline]
+    pub fn usage_tracker(&self) -> Option<&dyn UsageTracker> {
+        self.usage.as_ref().map(|usage| usage.0.as_ref())
+    }
+
+    /// Count message processed for the given key.
+    /// 
+    /// Does nothing if there's no usage tracker.
+    pub fn record_usage(&self, key: &PublicKey, event: UsageEvent, bytes: u64) {
+        if let Some(usage) = self.usage_tracker() {
+            usage.record(key, event, bytes);
+        }
+    }
+
+    /// Get resources used by the given key.
+    /// 
+    /// Return empty usage if there's no usage tracker.
+    pub fn usage(&self, key: &PublicKey) -> Usage {
+        self.usage_tracker()
+            .map(|usage| usage.usage(key))
+            .unwrap_or_default()
+    }
+
+    /// Get `n` keys which used the most resources.
+    /// 
+    /// Return empty list if there's no usage tracker.
+    pub fn top_usage(&self, n: usize) -> Vec<(PublicKey, Usage)> {
+        self.usage_tracker()
+            .map(|usage| usage.top_usage(n))
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    /// Dump the whole routing table of the server.
+    pub async fn export_router_snapshot(&self) -> Result<RouterSnapshot, Router::Error> where Router: Sync {
+        self.router.export_snapshot().await
+    }
+
+    #[inline]
+    /// Index records of the given routing table dump.
+    /// 
+    /// Local clients whose certificates are not
+    /// made for this server are skipped.
+    pub async fn import_router_snapshot(&self, snapshot: RouterSnapshot) -> Result<SnapshotImport, Router::Error> where Router: Sync {
+        self.router.import_snapshot(snapshot, &self.params.secret_key.public_key()).await
+    }
+
+    #[inline]
+    /// Contact servers listed in the `ServerParams::bootstrap`
+    /// and index them and their known servers.
+    /// 
+    /// Refer to `bootstrap_from`.
+    pub async fn bootstrap(&self, http_client: impl HttpClient) -> BootstrapSummary
+    where
+        Router: Sync,
+        Traversal: Sync,
+        MessagesInbox: Sync
+    {
+        self.bootstrap_from(http_client, &self.params.bootstrap.servers).await
+    }
+
+    /// Contact given bootstrap servers and index
+    /// them and their known servers.
+    /// 
+    /// Every server is requested by `GET /api/v1/info`
+    /// and `GET /api/v1/servers`. Failed servers don't
+    /// stop the bootstrap and are listed in the
+    /// returned summary.
+    pub async fn bootstrap_from(&self, http_client: impl HttpClient, servers: &[Address]) -> BootstrapSummary
+    where
+        Router: Sync,
+        Traversal: Sync,
+        MessagesInbox: Sync
+    {
+        let mut summary = BootstrapSummary::default();
+
+        for address in servers {
+            match bootstrap_server(http_client.clone(), self, address).await {
+                Ok((server, indexed)) => {
+                    summary.servers.push(server);
+                    summary.indexed += indexed;
+                }
+
+                Err(err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(?address, "Failed to bootstrap from the server: {err}");
+
+                    summary.failed.push((address.clone(), err));
+                }
+            }
+        }
+
+        summary
+    }
+
+    #[inline]
+    /// Get statistics of the messages queued in the inbox.
+    pub async fn inbox_stats(&self) -> Result<InboxStats, MessagesInbox::Error> {
+        self.messages_inbox.stats().await
+    }
+
+    /// Choose action for the request of the given key.
+    /// 
+    /// Always allow requests if there's no reputation provider.
+    pub async fn check_reputation(&self, key: &PublicKey) -> ReputationAction {
+        match self.reputation() {
+            Some(reputation) => self.params.reputation.action(reputation.score(key).await),
+            None => ReputationAction::Allow
+        }
+    }
+
+    /// Get connection certificate of the local client.
+    /// 
+    /// Return `None` if the client is not connected to this server.
+    pub async fn client_certificate(&self, key: &PublicKey) -> Option<ConnectionCertificate> where Router: Sync {
+        match self.router.lookup_local_client(key, None).await {
+            Ok(Some((client, _))) => Some(client.certificate),
+            _ => None
+        }
+    }
+
+    #[inline]
+    /// Get connection certificate scope of the local client.
+    /// 
+    /// Return `None` if the client is not connected to this
+    /// server or its certificate is not scoped.
+    pub async fn client_scope(&self, key: &PublicKey) -> Option<CertificateScope> where Router: Sync {
+        self.client_certificate(key).await
+            .and_then(|certificate| certificate.scope)
+    }
+
+    /// Get UTC timestamp after which the given
+    /// connection certificate is not accepted.
+    /// 
+    /// Certificates made for this server expire after
+    /// the `ServerParams::certificate_lifetime` or their
+    /// own expiration date, whichever comes first.
+    /// 
+    /// Return `None` if the certificate never expires.
+    pub fn certificate_expires_at(&self, certificate: &ConnectionCertificate) -> Option<u64> {
+        let lifetime = self.params.certificate_lifetime
+            .filter(|_| certificate.token.public_key == self.params.secret_key.public_key())
+            .map(|lifetime| certificate.token.auth_date.saturating_add(lifetime.as_secs()));
+
+        match (certificate.expires_at, lifetime) {
+            (Some(expires_at), Some(lifetime)) => Some(expires_at.min(lifetime)),
+            (expires_at, lifetime) => expires_at.or(lifetime)
+        }
+    }
+
+    #[inline]
+    /// Check if the given connection certificate
+    /// is expired right now.
+    /// 
+    /// Refer to `certificate_expires_at`.
+    pub fn is_certificate_expired(&self, certificate: &ConnectionCertificate) -> bool {
+        self.certificate_expires_at(certificate)
+            .is_some_and(|expires_at| crate::time::timestamp() >= expires_at)
+    }
+
+    /// Mark the local client as seen right now.
+    /// 
+    /// Errors of the router are logged and ignored
+    /// so they don't fail the client's request.
+    pub async fn touch_client(&self, key: &PublicKey) where Router: Sync {
+        if let Err(_err) = self.router.touch_local_client(key).await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(client = key.to_base64(), "Failed to update client's last seen time: {_err}");
+        }
+    }
+
+    /// Report misbehavior of the given key.
+    /// 
+    /// Does nothing if there's no reputation provider.
+    pub async fn report_incident(&self, key: &PublicKey, incident: Incident) {
+        if let Some(reputation) = self.reputation() {
+            reputation.report(key, incident).await;
+        }
+    }
+
     /// Make `server` client driver from the current server
     pub fn as_client(&self) -> ClientDriver {
         ClientDriver::new(
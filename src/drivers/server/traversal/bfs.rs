@@ -0,0 +1,535 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::crypto::asymmetric::{PublicKey, SecretKey};
+use crate::http::client::HttpClient;
+use crate::rest_api::middleware::{Client as ClientMiddleware, Error as MiddlewareError};
+use crate::rest_api::prelude::*;
+use crate::rest_api::types::Server as ServerApiRecord;
+
+use super::cache::{TraversalCache, RouteCache};
+use super::observer::{TraversalObserver, TraversalEvent, Notifier};
+use super::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Client found by the breadth-first traversal.
+pub struct TraversalLookup {
+    pub client: Client,
+
+    /// Server to which the client is connected.
+    pub server: ServerApiRecord,
+
+    /// Availability of the client decided
+    /// by the server which found it.
+    pub available: bool,
+
+    /// Servers requested on the way to the one which
+    /// found the client, starting from the local
+    /// router's known server and ending with it.
+    pub path: Vec<ServerApiRecord>
+}
+
+#[derive(Clone)]
+/// Breadth-first traversal of the network servers.
+/// 
+/// The first level of the traversal is the servers known
+/// by the local router. Every next level is made from the
+/// servers returned by the previous level's servers and
+/// not visited before.
+pub struct BfsTraversal {
+    /// Maximal amount of the requested levels.
+    pub max_depth: usize,
+
+    /// Maximal amount of the servers requested on each level.
+    pub max_fanout: usize,
+
+    /// Cache of the negative traversal results.
+    pub cache: Option<TraversalCache>,
+
+    /// Cache of the successful lookup routes.
+    pub routes: Option<RouteCache>,
+
+    /// Observer of the traversal's progress.
+    pub observer: Option<Arc<dyn TraversalObserver>>
+}
+
+impl std::fmt::Debug for BfsTraversal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BfsTraversal")
+            .field("max_depth", &self.max_depth)
+            .field("max_fanout", &self.max_fanout)
+            .field("cache", &self.cache)
+            .field("routes", &self.routes)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+
+impl Default for BfsTraversal {
+    #[inline]
+    fn default() -> Self {
+        Self::new(4, 16)
+    }
+}
+
+impl BfsTraversal {
+    #[inline]
+    pub fn new(max_depth: usize, max_fanout: usize) -> Self {
+        Self {
+            max_depth,
+            max_fanout,
+            cache: None,
+            routes: None,
+            observer: None
+        }
+    }
+
+    #[inline]
+    pub fn with_max_depth(self, max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_max_fanout(self, max_fanout: usize) -> Self {
+        Self {
+            max_fanout,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Skip lookups which recently didn't find the client
+    /// and servers which recently didn't respond.
+    pub fn with_cache(self, cache: TraversalCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Request servers which last found the clients
+    /// before doing the full traversal.
+    pub fn with_routes(self, routes: RouteCache) -> Self {
+        Self {
+            routes: Some(routes),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Send progress events of every traversal to the observer.
+    pub fn with_observer(self, observer: impl TraversalObserver + 'static) -> Self {
+        Self {
+            observer: Some(Arc::new(observer)),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Search the given client in the network.
+    /// 
+    /// Every requested server is asked about the client
+    /// before its known servers are requested. Traversal
+    /// is stopped by the first server which found it.
+    /// 
+    /// Unlike `discover`, discovered servers are not
+    /// indexed by the local router.
+    /// 
+    /// Return `None` if the client wasn't found within
+    /// the traversal's depth.
+    pub async fn lookup<R, T, I>(
+        &self,
+        http_client: impl HttpClient,
+        server: &ServerDriver<R, T, I>,
+        client_public: &PublicKey,
+        client_type: Option<ClientType>
+    ) -> Option<TraversalLookup>
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.lookup_visited(http_client, server, client_public, client_type).await.0
+    }
+
+    #[inline]
+    /// Search the given client in the network and
+    /// return all the requested servers.
+    /// 
+    /// Refer to `lookup`.
+    pub async fn lookup_visited<R, T, I>(
+        &self,
+        http_client: impl HttpClient,
+        server: &ServerDriver<R, T, I>,
+        client_public: &PublicKey,
+        client_type: Option<ClientType>
+    ) -> (Option<TraversalLookup>, Vec<VisitedServer>)
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.walk(http_client, server, Some((client_public, client_type))).await
+    }
+
+    #[inline]
+    /// Index all the servers reachable within the
+    /// traversal's depth by the local router.
+    /// 
+    /// Return all the requested servers.
+    pub async fn discover<R, T, I>(&self, http_client: impl HttpClient, server: &ServerDriver<R, T, I>) -> Vec<VisitedServer>
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.walk(http_client, server, None).await.1
+    }
+
+    async fn walk<R, T, I>(
+        &self,
+        http_client: impl HttpClient,
+        server: &ServerDriver<R, T, I>,
+        target: Option<(&PublicKey, Option<ClientType>)>
+    ) -> (Option<TraversalLookup>, Vec<VisitedServer>)
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        if let (Some(cache), Some((client_public, client_type))) = (&self.cache, target) {
+            if cache.is_missing(client_public, client_type) {
+                return (None, vec![]);
+            }
+        }
+
+        let Ok(known_servers) = server.router().servers().await else {
+            return (None, vec![]);
+        };
+
+        let client = ClientMiddleware::new(http_client.clone(), server.as_client());
+        let notifier = Notifier::new(self.observer.as_ref());
+
+        // The local server must not be requested
+        let mut visited = HashSet::from([
+            server.params().secret_key.public_key()
+        ]);
+
+        let mut visits = Vec::new();
+
+        // Every server is stored with the path leading to it
+        let mut frontier = known_servers.into_iter()
+            .map(|remote_server| (remote_server, vec![]))
+            .collect::<Vec<_>>();
+
+        // Try the last successful route first
+        if let (Some(routes), Some((client_public, client_type))) = (&self.routes, target) {
+            if let Some(route) = routes.route(client_public) {
+                let started_at = Instant::now();
+
+                let response = lookup(&http_client, &server.params().secret_key, &route, client_public, client_type).await
+                    .inspect_err(|err| self.remember_error(&notifier, &route, err))
+                    .ok();
+
+                let visit = VisitedServer::new(route.clone(), 0, started_at.elapsed());
+
+                notify_queried(&notifier, &visit);
+
+                visits.push(visit);
+                visited.insert(route.public_key.clone());
+
+                match visit_route(routes, client_public, route, response) {
+                    RouteVisit::Found(found) => {
+                        notifier.notify(|| TraversalEvent::ClientFound(found.client.clone(), found.server.clone()));
+
+                        return (Some(found), visits);
+                    }
+
+                    RouteVisit::Missed(hints) => {
+                        notify_hints(&notifier, &hints);
+
+                        frontier.extend(hints);
+                    }
+                }
+            }
+        }
+
+        for depth in 0..self.max_depth {
+            let mut next_frontier = Vec::new();
+            let mut requested = 0;
+
+            for (remote_server, mut path) in frontier.drain(..) {
+                if requested >= self.max_fanout {
+                    break;
+                }
+
+                if !visited.insert(remote_server.public_key.clone()) {
+                    continue;
+                }
+
+                // Don't request servers which failed health checks
+                let health = server.router()
+                    .server_health(&remote_server.public_key).await
+                    .unwrap_or_default();
+
+                if health.is_unhealthy() || self.is_unreachable(&remote_server) {
+                    continue;
+                }
+
+                requested += 1;
+
+                path.push(remote_server.clone());
+
+                let started_at = Instant::now();
+
+                // Ask about the client before going deeper
+                let mut hints = Vec::new();
+
+                if let Some((client_public, client_type)) = target {
+                    let response = lookup(&http_client, &server.params().secret_key, &remote_server, client_public, client_type).await
+                        .inspect_err(|err| self.remember_error(&notifier, &remote_server, err))
+                        .ok();
+
+                    match response {
+                        Some(LookupResponseBody::Local { client, available }) => {
+                            let visit = VisitedServer::new(remote_server.clone(), depth, started_at.elapsed());
+
+                            notify_queried(&notifier, &visit);
+                            notifier.notify(|| TraversalEvent::ClientFound(client.clone(), remote_server.clone()));
+
+                            visits.push(visit);
+
+                            self.remember_route(client_public, &remote_server, available);
+
+                            return (Some(TraversalLookup {
+                                client,
+                                server: remote_server,
+                                available,
+                                path
+                            }), visits);
+                        }
+
+                        Some(LookupResponseBody::Remote { client, server, available }) => {
+                            let visit = VisitedServer::new(remote_server.clone(), depth, started_at.elapsed());
+
+                            notify_queried(&notifier, &visit);
+                            notifier.notify(|| TraversalEvent::ClientFound(client.clone(), server.clone()));
+
+                            visits.push(visit);
+
+                            self.remember_route(client_public, &remote_server, available);
+
+                            return (Some(TraversalLookup {
+                                client,
+                                server,
+                                available,
+                                path
+                            }), visits);
+                        }
+
+                        Some(LookupResponseBody::Hint { servers }) => {
+                            hints = servers.into_iter()
+                                .map(|hint| hint.server)
+                                .collect();
+
+                            if !hints.is_empty() {
+                                notifier.notify(|| TraversalEvent::Hint(hints.clone()));
+                            }
+                        }
+
+                        None => ()
+                    }
+                }
+
+                // Servers of the last level won't be requested anyway
+                if depth + 1 < self.max_depth {
+                    match client.get_servers(&remote_server.address).await {
+                        Ok(servers) => hints.extend(servers),
+                        Err(err) => self.remember_error(&notifier, &remote_server, &err)
+                    }
+                }
+
+                for hint in hints {
+                    if !visited.contains(&hint.public_key) {
+                        next_frontier.push((hint, path.clone()));
+                    }
+                }
+
+                let visit = VisitedServer::new(remote_server.clone(), depth, started_at.elapsed());
+
+                notify_queried(&notifier, &visit);
+
+                visits.push(visit);
+
+                if target.is_none() {
+                    let _ = server.router().index_server(remote_server).await;
+                }
+            }
+
+            if next_frontier.is_empty() {
+                break;
+            }
+
+            frontier = next_frontier;
+        }
+
+        if let (Some(cache), Some((client_public, client_type))) = (&self.cache, target) {
+            cache.remember_missing(client_public.clone(), client_type);
+        }
+
+        (None, visits)
+    }
+
+    #[inline]
+    fn is_unreachable(&self, remote_server: &ServerApiRecord) -> bool {
+        self.cache.as_ref()
+            .map(|cache| cache.is_unreachable(&remote_server.public_key))
+            .unwrap_or(false)
+    }
+
+    #[inline]
+    fn remember_route(&self, client_public: &PublicKey, remote_server: &ServerApiRecord, available: bool) {
+        if let Some(routes) = &self.routes {
+            if available {
+                routes.remember_route(client_public.clone(), remote_server.clone());
+            }
+        }
+    }
+
+    fn remember_error(&self, notifier: &Notifier, remote_server: &ServerApiRecord, err: &MiddlewareError) {
+        notifier.notify(|| TraversalEvent::Failed(remote_server.address.clone(), err.to_string()));
+
+        if let Some(cache) = &self.cache {
+            if is_unreachable_error(err) {
+                cache.remember_unreachable(remote_server.public_key.clone());
+            }
+        }
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+/// Result of the last successful route's request.
+pub(super) enum RouteVisit {
+    Found(TraversalLookup),
+
+    /// Route's server didn't find the client.
+    /// Contains its hints paired with the path.
+    Missed(Vec<(ServerApiRecord, Vec<ServerApiRecord>)>)
+}
+
+/// Process lookup response of the last successful
+/// route's server and forget the route if the server
+/// failed or didn't find the available client.
+pub(super) fn visit_route(
+    routes: &RouteCache,
+    client_public: &PublicKey,
+    route: ServerApiRecord,
+    response: Option<LookupResponseBody>
+) -> RouteVisit {
+    match response {
+        Some(LookupResponseBody::Local { client, available: true }) => RouteVisit::Found(TraversalLookup {
+            client,
+            server: route.clone(),
+            available: true,
+            path: vec![route]
+        }),
+
+        Some(LookupResponseBody::Remote { client, server, available: true }) => RouteVisit::Found(TraversalLookup {
+            client,
+            server,
+            available: true,
+            path: vec![route]
+        }),
+
+        Some(LookupResponseBody::Hint { servers }) => {
+            routes.invalidate(client_public);
+
+            RouteVisit::Missed(servers.into_iter()
+                .map(|hint| (hint.server, vec![route.clone()]))
+                .collect())
+        }
+
+        _ => {
+            routes.invalidate(client_public);
+
+            RouteVisit::Missed(vec![])
+        }
+    }
+}
+
+#[inline]
+pub(super) fn notify_hints(notifier: &Notifier, hints: &[(ServerApiRecord, Vec<ServerApiRecord>)]) {
+    if !hints.is_empty() {
+        notifier.notify(|| TraversalEvent::Hint(hints.iter().map(|(hint, _)| hint.clone()).collect()));
+    }
+}
+
+#[inline]
+pub(super) fn notify_queried(notifier: &Notifier, visit: &VisitedServer) {
+    notifier.notify(|| TraversalEvent::ServerQueried(
+        visit.server.public_key.clone(),
+        visit.server.address.clone(),
+        visit.elapsed
+    ));
+}
+
+#[inline]
+/// Check if the error is caused by the failed
+/// connection rather than the server's response.
+pub(super) fn is_unreachable_error(err: &MiddlewareError) -> bool {
+    matches!(err, MiddlewareError::Other(_))
+}
+
+/// Send lookup request to the remote server.
+/// 
+/// Responses which are not signed by the
+/// remote server's key are rejected.
+pub(super) async fn lookup(
+    http_client: &impl HttpClient,
+    secret_key: &SecretKey,
+    remote_server: &ServerApiRecord,
+    client_public: &PublicKey,
+    client_type: Option<ClientType>
+) -> Result<LookupResponseBody, MiddlewareError> {
+    let request = LookupRequest::new(secret_key, client_public.clone(), client_type);
+
+    let proof_seed = request.0.proof_seed;
+
+    let response = http_client.post_request::<LookupRequest, LookupResponse>(
+        format!("http://{}/api/v1/lookup", remote_server.address),
+        request
+    ).await?;
+
+    if !response.validate(proof_seed)? {
+        return Err(MiddlewareError::InvalidProofSeedSignature);
+    }
+
+    match response.0 {
+        Response::Success { public_key, response, .. } if public_key == remote_server.public_key => Ok(response),
+
+        Response::Success { .. } => Err(MiddlewareError::InvalidProofSeedSignature),
+
+        Response::Error { status, code, reason, .. } => Err(MiddlewareError::RequestFailed {
+            status,
+            code,
+            reason
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Traversal for BfsTraversal {
+    async fn traverse<R, T, I>(&self, http_client: impl HttpClient, server: &ServerDriver<R, T, I>)
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.discover(http_client, server).await;
+    }
+}
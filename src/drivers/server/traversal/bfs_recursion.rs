@@ -1,4 +1,5 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::time::Instant;
 
 use crate::http::client::HttpClient;
 use crate::rest_api::middleware::Client as ClientMiddleware;
@@ -8,28 +9,128 @@ use super::*;
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct BfsRecursionTraversal;
 
-#[async_trait::async_trait]
-impl Traversal for BfsRecursionTraversal {
-    async fn traverse<R, T, I>(&self, http_client: impl HttpClient, server: &ServerDriver<R, T, I>)
+impl BfsRecursionTraversal {
+    /// Request known servers of every reachable
+    /// server and index them by the router.
+    /// 
+    /// Every server is requested at most once, even if
+    /// servers know each other cyclically. Servers are
+    /// identified by their public keys because the same
+    /// server can be announced with different addresses.
+    /// 
+    /// Return all the requested servers.
+    pub async fn discover<R, T, I>(&self, http_client: impl HttpClient, server: &ServerDriver<R, T, I>) -> Vec<VisitedServer>
     where
         R: Router + Sync,
         T: Traversal + Sync,
         I: MessagesInbox + Sync
     {
+        let mut visits = Vec::new();
+
         if let Ok(remote_servers) = server.router().servers().await {
             let client = ClientMiddleware::new(http_client, server.as_client());
 
-            let mut remote_servers = VecDeque::from(remote_servers);
+            // The local server must not be requested
+            let mut visited = HashSet::from([
+                server.params().secret_key.public_key()
+            ]);
+
+            let mut remote_servers = remote_servers.into_iter()
+                .map(|remote_server| (remote_server, 0))
+                .collect::<VecDeque<_>>();
+
+            while let Some((remote_server, depth)) = remote_servers.pop_front() {
+                if !visited.insert(remote_server.public_key.clone()) {
+                    continue;
+                }
+
+                // Don't request servers which failed health checks
+                let health = server.router()
+                    .server_health(&remote_server.public_key).await
+                    .unwrap_or_default();
+
+                if health.is_unhealthy() {
+                    continue;
+                }
+
+                let started_at = Instant::now();
 
-            while let Some(remote_server) = remote_servers.pop_front() {
                 if let Ok(mut response) = client.get_servers(&remote_server.address).await {
-                    for remote_server in response.drain(..) {
-                        remote_servers.push_back(remote_server);
+                    for known_server in response.drain(..) {
+                        if !visited.contains(&known_server.public_key) {
+                            remote_servers.push_back((known_server, depth + 1));
+                        }
                     }
                 }
 
+                visits.push(VisitedServer::new(remote_server.clone(), depth, started_at.elapsed()));
+
                 let _ = server.router().index_server(remote_server).await;
             }
         }
+
+        visits
+    }
+}
+
+#[async_trait::async_trait]
+impl Traversal for BfsRecursionTraversal {
+    async fn traverse<R, T, I>(&self, http_client: impl HttpClient, server: &ServerDriver<R, T, I>)
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.discover(http_client, server).await;
+    }
+}
+
+#[cfg(all(test, feature = "router-ram", feature = "inbox-ram"))]
+mod tests {
+    use crate::crypto::prelude::*;
+    use crate::http::client::tests::MockHttpClient;
+    use crate::rest_api::prelude::*;
+    use crate::rest_api::types::Server as ServerApiRecord;
+    use crate::drivers::server::router::ram::RamRouter;
+    use crate::drivers::server::messages_inbox::ram::RamMessagesInbox;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn servers_cycle() -> Result<(), Box<dyn std::error::Error>> {
+        let servers = (0..3)
+            .map(|i| ServerApiRecord::new(SecretKey::random().public_key(), format!("server-{i}.example.org")))
+            .collect::<Vec<_>>();
+
+        // First server is announced by the last one with another address
+        let alias = ServerApiRecord::new(servers[0].public_key.clone(), "alias.example.org");
+
+        let servers_url = |server: &ServerApiRecord| format!("http://{}/api/v1/servers", server.address);
+
+        let http = MockHttpClient::new([
+            (servers_url(&servers[0]), ServersResponse::new(vec![servers[1].clone()]).to_json()?),
+            (servers_url(&servers[1]), ServersResponse::new(vec![servers[2].clone()]).to_json()?),
+            (servers_url(&servers[2]), ServersResponse::new(vec![alias.clone()]).to_json()?),
+            (servers_url(&alias), ServersResponse::new(vec![servers[1].clone()]).to_json()?)
+        ]);
+
+        let driver = ServerDriver::new(RamRouter::default(), BfsRecursionTraversal, RamMessagesInbox::default(), ServerParams::default());
+
+        driver.router().index_server(servers[0].clone()).await?;
+
+        let visited = BfsRecursionTraversal.discover(http.clone(), &driver).await;
+
+        assert_eq!(http.requests().len(), 3);
+
+        assert_eq!(visited.len(), 3);
+        assert_eq!(visited.iter().map(|visit| visit.depth).collect::<Vec<_>>(), [0, 1, 2]);
+
+        for (visit, server) in visited.iter().zip(&servers) {
+            assert_eq!(&visit.server, server);
+        }
+
+        assert_eq!(driver.router().servers().await?.len(), 3);
+
+        Ok(())
     }
 }
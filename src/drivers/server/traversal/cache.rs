@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::rest_api::prelude::*;
+use crate::rest_api::types::Server as ServerApiRecord;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    /// Lookup which didn't find the client.
+    Missing(PublicKey, Option<ClientType>),
+
+    /// Server which didn't respond.
+    Unreachable(PublicKey)
+}
+
+impl CacheKey {
+    #[inline]
+    fn public_key(&self) -> &PublicKey {
+        match self {
+            Self::Missing(public_key, _) => public_key,
+            Self::Unreachable(public_key) => public_key
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry<V> {
+    value: V,
+    cached_at: Instant,
+
+    /// Sequence number of the last access.
+    used: u64
+}
+
+#[derive(Debug)]
+struct CacheState<K, V = ()> {
+    ttl: Duration,
+    capacity: usize,
+    entries: HashMap<K, CacheEntry<V>>,
+    uses: u64
+}
+
+impl<K: Clone + Eq + Hash, V> CacheState<K, V> {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: HashMap::new(),
+            uses: 0
+        }
+    }
+
+    fn get(&mut self, key: &K, now: Instant) -> Option<&V> {
+        let expired = now.duration_since(self.entries.get(key)?.cached_at) >= self.ttl;
+
+        if expired {
+            self.entries.remove(key);
+
+            return None;
+        }
+
+        self.uses += 1;
+
+        let entry = self.entries.get_mut(key)?;
+
+        entry.used = self.uses;
+
+        Some(&entry.value)
+    }
+
+    #[inline]
+    fn contains(&mut self, key: &K, now: Instant) -> bool {
+        self.get(key, now).is_some()
+    }
+
+    fn insert(&mut self, key: K, value: V, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            // Expired entries are dropped first
+            self.entries.retain(|_, entry| now.duration_since(entry.cached_at) < self.ttl);
+
+            if self.entries.len() >= self.capacity {
+                let least_used = self.entries.iter()
+                    .min_by_key(|(_, entry)| entry.used)
+                    .map(|(key, _)| key.clone());
+
+                if let Some(least_used) = least_used {
+                    self.entries.remove(&least_used);
+                }
+            }
+        }
+
+        self.uses += 1;
+
+        self.entries.insert(key, CacheEntry {
+            value,
+            cached_at: now,
+            used: self.uses
+        });
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Cache of the negative traversal results.
+/// 
+/// Remembers lookups which didn't find the client and servers
+/// which didn't respond, so repeated traversals don't request
+/// the same servers until the entries expire. When the cache
+/// is full the least recently used entry is replaced.
+/// 
+/// Clones of the cache share the same entries.
+pub struct TraversalCache(Arc<Mutex<CacheState<CacheKey>>>);
+
+impl TraversalCache {
+    /// Create new cache storing at most `capacity`
+    /// entries for `ttl` time.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(CacheState::new(ttl, capacity))))
+    }
+
+    #[inline]
+    fn state(&self) -> std::sync::MutexGuard<'_, CacheState<CacheKey>> {
+        self.0.lock().expect("Failed to lock traversal cache")
+    }
+
+    #[inline]
+    /// Check if the lookup of the given client
+    /// recently didn't find it.
+    pub fn is_missing(&self, public_key: &PublicKey, client_type: Option<ClientType>) -> bool {
+        self.state().contains(&CacheKey::Missing(public_key.clone(), client_type), Instant::now())
+    }
+
+    #[inline]
+    /// Remember that the lookup of the given client didn't find it.
+    pub fn remember_missing(&self, public_key: PublicKey, client_type: Option<ClientType>) {
+        self.state().insert(CacheKey::Missing(public_key, client_type), (), Instant::now());
+    }
+
+    #[inline]
+    /// Check if the given server recently didn't respond.
+    pub fn is_unreachable(&self, public_key: &PublicKey) -> bool {
+        self.state().contains(&CacheKey::Unreachable(public_key.clone()), Instant::now())
+    }
+
+    #[inline]
+    /// Remember that the given server didn't respond.
+    pub fn remember_unreachable(&self, public_key: PublicKey) {
+        self.state().insert(CacheKey::Unreachable(public_key), (), Instant::now());
+    }
+
+    /// Forget all the entries of the given key.
+    /// 
+    /// Both the client's lookups and the server with
+    /// this key will be requested by the next traversal.
+    /// 
+    /// Return `false` if there were no entries.
+    pub fn invalidate(&self, public_key: &PublicKey) -> bool {
+        let mut state = self.state();
+
+        let len = state.entries.len();
+
+        state.entries.retain(|key, _| key.public_key() != public_key);
+
+        state.entries.len() != len
+    }
+
+    #[inline]
+    /// Forget all the entries.
+    pub fn clear(&self) {
+        self.state().entries.clear();
+    }
+
+    #[inline]
+    /// Get amount of the stored entries,
+    /// including the expired ones.
+    pub fn len(&self) -> usize {
+        self.state().entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Cache of the successful lookup routes.
+/// 
+/// Remembers servers which last found the clients, so
+/// repeated lookups of the same client request these
+/// servers first and do the full traversal only if
+/// they don't find it anymore. Routes are forgotten
+/// once they expire, the server fails to respond or
+/// reports the client as unavailable. When the cache
+/// is full the least recently used route is replaced.
+/// 
+/// Clones of the cache share the same routes.
+pub struct RouteCache(Arc<Mutex<CacheState<PublicKey, ServerApiRecord>>>);
+
+impl RouteCache {
+    /// Create new cache storing at most `capacity`
+    /// routes for `ttl` time.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(CacheState::new(ttl, capacity))))
+    }
+
+    #[inline]
+    fn state(&self) -> std::sync::MutexGuard<'_, CacheState<PublicKey, ServerApiRecord>> {
+        self.0.lock().expect("Failed to lock route cache")
+    }
+
+    #[inline]
+    /// Get server which last found the given client.
+    pub fn route(&self, public_key: &PublicKey) -> Option<ServerApiRecord> {
+        self.state().get(public_key, Instant::now()).cloned()
+    }
+
+    #[inline]
+    /// Remember that the given server found the client.
+    pub fn remember_route(&self, public_key: PublicKey, server: ServerApiRecord) {
+        self.state().insert(public_key, server, Instant::now());
+    }
+
+    #[inline]
+    /// Forget route to the given client.
+    /// 
+    /// Return `false` if there was no route.
+    pub fn invalidate(&self, public_key: &PublicKey) -> bool {
+        self.state().entries.remove(public_key).is_some()
+    }
+
+    #[inline]
+    /// Forget all the routes.
+    pub fn clear(&self) {
+        self.state().entries.clear();
+    }
+
+    #[inline]
+    /// Get amount of the stored routes,
+    /// including the expired ones.
+    pub fn len(&self) -> usize {
+        self.state().entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::asymmetric::SecretKey;
+
+    use super::*;
+
+    #[test]
+    fn expiration() {
+        let cache = TraversalCache::new(Duration::from_secs(60), 8);
+        let key = CacheKey::Unreachable(SecretKey::random().public_key());
+
+        let now = Instant::now();
+
+        cache.state().insert(key.clone(), (), now);
+
+        assert!(cache.state().contains(&key, now + Duration::from_secs(59)));
+        assert!(!cache.state().contains(&key, now + Duration::from_secs(60)));
+
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn eviction() {
+        let cache = TraversalCache::new(Duration::from_secs(60), 2);
+
+        let keys = (0..3)
+            .map(|_| SecretKey::random().public_key())
+            .collect::<Vec<_>>();
+
+        cache.remember_missing(keys[0].clone(), None);
+        cache.remember_missing(keys[1].clone(), None);
+
+        // Make the first key recently used
+        assert!(cache.is_missing(&keys[0], None));
+
+        cache.remember_missing(keys[2].clone(), None);
+
+        assert_eq!(cache.len(), 2);
+
+        assert!(cache.is_missing(&keys[0], None));
+        assert!(!cache.is_missing(&keys[1], None));
+        assert!(cache.is_missing(&keys[2], None));
+
+        // Client types are cached separately
+        assert!(!cache.is_missing(&keys[0], Some(ClientType::Thin)));
+    }
+
+    #[test]
+    fn invalidate() {
+        let cache = TraversalCache::new(Duration::from_secs(60), 8);
+        let key = SecretKey::random().public_key();
+
+        cache.remember_missing(key.clone(), None);
+        cache.remember_missing(key.clone(), Some(ClientType::Server));
+        cache.remember_unreachable(key.clone());
+        cache.remember_unreachable(SecretKey::random().public_key());
+
+        assert!(cache.invalidate(&key));
+        assert!(!cache.invalidate(&key));
+
+        assert!(!cache.is_missing(&key, None));
+        assert!(!cache.is_unreachable(&key));
+
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn routes() {
+        let routes = RouteCache::new(Duration::from_secs(60), 1);
+
+        let clients = (0..2)
+            .map(|_| SecretKey::random().public_key())
+            .collect::<Vec<_>>();
+
+        let server = ServerApiRecord::new(SecretKey::random().public_key(), "example.org");
+
+        assert!(routes.route(&clients[0]).is_none());
+
+        routes.remember_route(clients[0].clone(), server.clone());
+
+        assert_eq!(routes.route(&clients[0]), Some(server.clone()));
+
+        routes.remember_route(clients[1].clone(), server.clone());
+
+        assert_eq!(routes.len(), 1);
+        assert!(routes.route(&clients[0]).is_none());
+
+        assert!(routes.invalidate(&clients[1]));
+        assert!(!routes.invalidate(&clients[1]));
+
+        assert!(routes.is_empty());
+    }
+}
@@ -1,10 +1,52 @@
+use std::time::Duration;
+
 use crate::http::client::HttpClient;
+use crate::rest_api::types::Server as ServerApiRecord;
 
 use super::prelude::*;
 
 #[cfg(feature = "traversal-bfs-recursion")]
 pub mod bfs_recursion;
 
+#[cfg(feature = "traversal-bfs")]
+pub mod bfs;
+
+#[cfg(feature = "traversal-bfs")]
+pub mod cache;
+
+#[cfg(feature = "traversal-bfs")]
+pub mod observer;
+
+#[cfg(feature = "traversal-parallel")]
+pub mod parallel;
+
+#[cfg(feature = "traversal-parallel")]
+pub mod strategy;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Server requested by the traversal.
+pub struct VisitedServer {
+    pub server: ServerApiRecord,
+
+    /// Level of the traversal on which
+    /// the server was requested.
+    pub depth: usize,
+
+    /// Time spent on the server's requests.
+    pub elapsed: Duration
+}
+
+impl VisitedServer {
+    #[inline]
+    pub fn new(server: ServerApiRecord, depth: usize, elapsed: Duration) -> Self {
+        Self {
+            server,
+            depth,
+            elapsed
+        }
+    }
+}
+
 #[async_trait::async_trait]
 /// Traversal is a struct that implements network servers
 /// searching. It is called manually by the dev and intended
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::rest_api::prelude::*;
+use crate::rest_api::types::Server as ServerApiRecord;
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Progress event of the traversal.
+pub enum TraversalEvent {
+    /// Server was requested. Sent for every requested
+    /// server, including the ones which failed.
+    ServerQueried(PublicKey, String, Duration),
+
+    /// Client was found on the given server.
+    ClientFound(Client, ServerApiRecord),
+
+    /// Requested server hinted servers which may
+    /// know about the client.
+    Hint(Vec<ServerApiRecord>),
+
+    /// Request to the server with given address failed.
+    Failed(String, String)
+}
+
+#[async_trait::async_trait]
+/// Observer of the traversal's progress.
+/// 
+/// Events are sent to the observer in background in order
+/// of their appearance, so slow observers don't block the
+/// traversal itself. Default implementation ignores them.
+pub trait TraversalObserver: Send + Sync {
+    async fn on_event(&self, _event: TraversalEvent) {}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// Observer ignoring all the events.
+pub struct NoopTraversalObserver;
+
+impl TraversalObserver for NoopTraversalObserver {}
+
+#[async_trait::async_trait]
+impl<T: TraversalObserver + ?Sized> TraversalObserver for Arc<T> {
+    #[inline]
+    async fn on_event(&self, event: TraversalEvent) {
+        self.as_ref().on_event(event).await;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Sender of the traversal events to the observer's
+/// background task. Task is finished once all the
+/// notifier's clones are dropped.
+pub(super) struct Notifier(Option<UnboundedSender<TraversalEvent>>);
+
+impl Notifier {
+    pub fn new(observer: Option<&Arc<dyn TraversalObserver>>) -> Self {
+        let Some(observer) = observer.cloned() else {
+            return Self(None);
+        };
+
+        let (sender, mut receiver) = unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                observer.on_event(event).await;
+            }
+        });
+
+        Self(Some(sender))
+    }
+
+    #[inline]
+    /// Send the event to the observer.
+    /// 
+    /// Event is not made if there's no observer.
+    pub fn notify(&self, event: impl FnOnce() -> TraversalEvent) {
+        if let Some(sender) = &self.0 {
+            let _ = sender.send(event());
+        }
+    }
+}
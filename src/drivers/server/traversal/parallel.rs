@@ -0,0 +1,535 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::stream::{self, StreamExt};
+
+use crate::crypto::asymmetric::{PublicKey, SecretKey};
+use crate::http::client::HttpClient;
+use crate::rest_api::middleware::{Client as ClientMiddleware, Error as MiddlewareError};
+use crate::rest_api::prelude::*;
+use crate::rest_api::types::Server as ServerApiRecord;
+
+use super::bfs::{lookup, visit_route, notify_queried, notify_hints, is_unreachable_error, TraversalLookup, RouteVisit};
+use super::cache::{TraversalCache, RouteCache};
+use super::observer::{TraversalObserver, TraversalEvent, Notifier};
+use super::*;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TraversalError {
+    #[error("Server didn't respond in time")]
+    Timeout,
+
+    #[error(transparent)]
+    Request(#[from] MiddlewareError)
+}
+
+impl TraversalError {
+    #[inline]
+    /// Check if the server didn't respond at all.
+    pub fn is_unreachable(&self) -> bool {
+        match self {
+            Self::Timeout => true,
+            Self::Request(err) => is_unreachable_error(err)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+/// Results of the parallel traversal.
+pub struct TraversalSummary {
+    /// Client found by the traversal.
+    /// 
+    /// Always `None` for the servers discovery.
+    pub found: Option<TraversalLookup>,
+
+    /// Amount of the requested servers.
+    pub requested: usize,
+
+    /// Servers which responded or failed, in order
+    /// of their responses.
+    /// 
+    /// Every server is requested at most once.
+    pub visited: Vec<VisitedServer>,
+
+    /// Servers which failed to respond.
+    /// 
+    /// Requests cancelled after the client
+    /// was found are not listed here.
+    pub errors: Vec<(ServerApiRecord, TraversalError)>
+}
+
+#[derive(Clone)]
+/// Breadth-first traversal requesting servers of
+/// every level concurrently.
+/// 
+/// Refer to `BfsTraversal`.
+pub struct ParallelTraversal {
+    /// Maximal amount of the simultaneously requested servers.
+    pub concurrency: usize,
+
+    /// Maximal time of every single request.
+    pub timeout: Duration,
+
+    /// Maximal amount of the requested levels.
+    pub max_depth: usize,
+
+    /// Maximal amount of the servers requested on each level.
+    pub max_fanout: usize,
+
+    /// Cache of the negative traversal results.
+    pub cache: Option<TraversalCache>,
+
+    /// Cache of the successful lookup routes.
+    pub routes: Option<RouteCache>,
+
+    /// Observer of the traversal's progress.
+    pub observer: Option<Arc<dyn TraversalObserver>>
+}
+
+impl std::fmt::Debug for ParallelTraversal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParallelTraversal")
+            .field("concurrency", &self.concurrency)
+            .field("timeout", &self.timeout)
+            .field("max_depth", &self.max_depth)
+            .field("max_fanout", &self.max_fanout)
+            .field("cache", &self.cache)
+            .field("routes", &self.routes)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+
+impl Default for ParallelTraversal {
+    #[inline]
+    fn default() -> Self {
+        Self::new(8, Duration::from_secs(5))
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+/// Result of the single server's requests.
+enum Visit {
+    Found(TraversalLookup),
+
+    Visited {
+        server: ServerApiRecord,
+        path: Vec<ServerApiRecord>,
+        servers: Vec<ServerApiRecord>,
+        error: Option<TraversalError>
+    }
+}
+
+impl ParallelTraversal {
+    #[inline]
+    pub fn new(concurrency: usize, timeout: Duration) -> Self {
+        Self {
+            concurrency,
+            timeout,
+            max_depth: 4,
+            max_fanout: 64,
+            cache: None,
+            routes: None,
+            observer: None
+        }
+    }
+
+    #[inline]
+    pub fn with_max_depth(self, max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_max_fanout(self, max_fanout: usize) -> Self {
+        Self {
+            max_fanout,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Skip lookups which recently didn't find the client
+    /// and servers which recently didn't respond.
+    /// 
+    /// Skipped servers are not listed in the summary.
+    pub fn with_cache(self, cache: TraversalCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Request servers which last found the clients
+    /// before doing the full traversal.
+    pub fn with_routes(self, routes: RouteCache) -> Self {
+        Self {
+            routes: Some(routes),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Send progress events of every traversal to the observer.
+    pub fn with_observer(self, observer: impl TraversalObserver + 'static) -> Self {
+        Self {
+            observer: Some(Arc::new(observer)),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Search the given client in the network.
+    /// 
+    /// Outstanding requests are cancelled as soon as
+    /// any server finds the client. Failed servers
+    /// don't stop the traversal and are listed in
+    /// the returned summary.
+    /// 
+    /// Unlike `discover`, discovered servers are not
+    /// indexed by the local router.
+    pub async fn lookup<R, T, I>(
+        &self,
+        http_client: impl HttpClient,
+        server: &ServerDriver<R, T, I>,
+        client_public: &PublicKey,
+        client_type: Option<ClientType>
+    ) -> TraversalSummary
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.walk(http_client, server, Some((client_public, client_type)), false).await
+    }
+
+    #[inline]
+    /// Index all the servers reachable within the
+    /// traversal's depth by the local router.
+    pub async fn discover<R, T, I>(&self, http_client: impl HttpClient, server: &ServerDriver<R, T, I>) -> TraversalSummary
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.walk(http_client, server, None, false).await
+    }
+
+    /// Request servers level by level.
+    /// 
+    /// Lookups with `hints_only` don't request known servers
+    /// of the visited ones and follow only their hints.
+    pub(super) async fn walk<R, T, I>(
+        &self,
+        http_client: impl HttpClient,
+        server: &ServerDriver<R, T, I>,
+        target: Option<(&PublicKey, Option<ClientType>)>,
+        hints_only: bool
+    ) -> TraversalSummary
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        let mut summary = TraversalSummary::default();
+
+        if let (Some(cache), Some((client_public, client_type))) = (&self.cache, target) {
+            if cache.is_missing(client_public, client_type) {
+                return summary;
+            }
+        }
+
+        let Ok(known_servers) = server.router().servers().await else {
+            return summary;
+        };
+
+        let client = ClientMiddleware::new(http_client.clone(), server.as_client());
+        let notifier = Notifier::new(self.observer.as_ref());
+
+        // The local server must not be requested
+        let mut visited = HashSet::from([
+            server.params().secret_key.public_key()
+        ]);
+
+        // Every server is stored with the path leading to it
+        let mut frontier = known_servers.into_iter()
+            .map(|remote_server| (remote_server, vec![]))
+            .collect::<Vec<_>>();
+
+        // Try the last successful route first
+        if let (Some(routes), Some((client_public, client_type))) = (&self.routes, target) {
+            if let Some(route) = routes.route(client_public) {
+                let started_at = Instant::now();
+
+                let response = tokio::time::timeout(
+                    self.timeout,
+                    lookup(&http_client, &server.params().secret_key, &route, client_public, client_type)
+                ).await;
+
+                let response = match response {
+                    Ok(Ok(response)) => Some(response),
+                    Ok(Err(err)) => self.route_error(&mut summary, &notifier, &route, TraversalError::from(err)),
+                    Err(_) => self.route_error(&mut summary, &notifier, &route, TraversalError::Timeout)
+                };
+
+                let visit = VisitedServer::new(route.clone(), 0, started_at.elapsed());
+
+                notify_queried(&notifier, &visit);
+
+                summary.requested += 1;
+                summary.visited.push(visit);
+
+                visited.insert(route.public_key.clone());
+
+                match visit_route(routes, client_public, route, response) {
+                    RouteVisit::Found(found) => {
+                        notifier.notify(|| TraversalEvent::ClientFound(found.client.clone(), found.server.clone()));
+
+                        summary.found = Some(found);
+
+                        return summary;
+                    }
+
+                    RouteVisit::Missed(hints) => {
+                        notify_hints(&notifier, &hints);
+
+                        frontier.extend(hints);
+                    }
+                }
+            }
+        }
+
+        for depth in 0..self.max_depth {
+            let mut level = Vec::new();
+
+            for (remote_server, path) in frontier.drain(..) {
+                if level.len() >= self.max_fanout {
+                    break;
+                }
+
+                if !visited.insert(remote_server.public_key.clone()) {
+                    continue;
+                }
+
+                // Don't request servers which failed health checks
+                let health = server.router()
+                    .server_health(&remote_server.public_key).await
+                    .unwrap_or_default();
+
+                let unreachable = self.cache.as_ref()
+                    .map(|cache| cache.is_unreachable(&remote_server.public_key))
+                    .unwrap_or(false);
+
+                if !health.is_unhealthy() && !unreachable {
+                    level.push((remote_server, path));
+                }
+            }
+
+            summary.requested += level.len();
+
+            // Servers of the last level won't be requested anyway
+            let expand = depth + 1 < self.max_depth && !(hints_only && target.is_some());
+
+            let mut visits = stream::iter(level)
+                .map(|(remote_server, path)| self.visit(&http_client, &client, &notifier, &server.params().secret_key, remote_server, path, target, expand))
+                .buffer_unordered(self.concurrency.max(1));
+
+            let mut next_frontier = Vec::new();
+
+            while let Some((visit, elapsed)) = visits.next().await {
+                match visit {
+                    // Dropping the stream cancels all the outstanding requests
+                    Visit::Found(found) => {
+                        if let Some(remote_server) = found.path.last() {
+                            let visit = VisitedServer::new(remote_server.clone(), depth, elapsed);
+
+                            notify_queried(&notifier, &visit);
+
+                            summary.visited.push(visit);
+
+                            if let (Some(routes), Some((client_public, _))) = (&self.routes, target) {
+                                if found.available {
+                                    routes.remember_route(client_public.clone(), remote_server.clone());
+                                }
+                            }
+                        }
+
+                        notifier.notify(|| TraversalEvent::ClientFound(found.client.clone(), found.server.clone()));
+
+                        summary.found = Some(found);
+
+                        return summary;
+                    }
+
+                    Visit::Visited { server: remote_server, path, servers, error } => {
+                        let visit = VisitedServer::new(remote_server.clone(), depth, elapsed);
+
+                        notify_queried(&notifier, &visit);
+
+                        summary.visited.push(visit);
+
+                        if let Some(error) = error {
+                            notifier.notify(|| TraversalEvent::Failed(remote_server.address.clone(), error.to_string()));
+
+                            if let Some(cache) = &self.cache {
+                                if error.is_unreachable() {
+                                    cache.remember_unreachable(remote_server.public_key.clone());
+                                }
+                            }
+
+                            summary.errors.push((remote_server, error));
+
+                            continue;
+                        }
+
+                        for hint in servers {
+                            if !visited.contains(&hint.public_key) {
+                                next_frontier.push((hint, path.clone()));
+                            }
+                        }
+
+                        if target.is_none() {
+                            let _ = server.router().index_server(remote_server).await;
+                        }
+                    }
+                }
+            }
+
+            if next_frontier.is_empty() {
+                break;
+            }
+
+            frontier = next_frontier;
+        }
+
+        if let (Some(cache), Some((client_public, client_type))) = (&self.cache, target) {
+            cache.remember_missing(client_public.clone(), client_type);
+        }
+
+        summary
+    }
+
+    /// Remember the error of the last successful route's server.
+    fn route_error(
+        &self,
+        summary: &mut TraversalSummary,
+        notifier: &Notifier,
+        route: &ServerApiRecord,
+        error: TraversalError
+    ) -> Option<LookupResponseBody> {
+        notifier.notify(|| TraversalEvent::Failed(route.address.clone(), error.to_string()));
+
+        if let Some(cache) = &self.cache {
+            if error.is_unreachable() {
+                cache.remember_unreachable(route.public_key.clone());
+            }
+        }
+
+        summary.errors.push((route.clone(), error));
+
+        None
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn visit<C: HttpClient>(
+        &self,
+        http_client: &C,
+        client: &ClientMiddleware<C>,
+        notifier: &Notifier,
+        secret_key: &SecretKey,
+        remote_server: ServerApiRecord,
+        mut path: Vec<ServerApiRecord>,
+        target: Option<(&PublicKey, Option<ClientType>)>,
+        expand: bool
+    ) -> (Visit, Duration) {
+        let started_at = Instant::now();
+
+        path.push(remote_server.clone());
+
+        let mut servers = Vec::new();
+
+        // Ask about the client before going deeper
+        if let Some((client_public, client_type)) = target {
+            let response = tokio::time::timeout(
+                self.timeout,
+                lookup(http_client, secret_key, &remote_server, client_public, client_type)
+            ).await;
+
+            let error = match response {
+                Ok(Ok(LookupResponseBody::Local { client, available })) => {
+                    return (Visit::Found(TraversalLookup {
+                        client,
+                        server: remote_server,
+                        available,
+                        path
+                    }), started_at.elapsed());
+                }
+
+                Ok(Ok(LookupResponseBody::Remote { client, server, available })) => {
+                    return (Visit::Found(TraversalLookup {
+                        client,
+                        server,
+                        available,
+                        path
+                    }), started_at.elapsed());
+                }
+
+                Ok(Ok(LookupResponseBody::Hint { servers: hints })) => {
+                    servers.extend(hints.into_iter().map(|hint| hint.server));
+
+                    if !servers.is_empty() {
+                        notifier.notify(|| TraversalEvent::Hint(servers.clone()));
+                    }
+
+                    None
+                }
+
+                Ok(Err(err)) => Some(TraversalError::from(err)),
+                Err(_) => Some(TraversalError::Timeout)
+            };
+
+            // Don't wait for the failed server twice
+            if error.is_some() {
+                return (Visit::Visited {
+                    server: remote_server,
+                    path,
+                    servers,
+                    error
+                }, started_at.elapsed());
+            }
+        }
+
+        let mut error = None;
+
+        if expand {
+            match tokio::time::timeout(self.timeout, client.get_servers(&remote_server.address)).await {
+                Ok(Ok(remote_servers)) => servers.extend(remote_servers),
+                Ok(Err(err)) => error = Some(TraversalError::from(err)),
+                Err(_) => error = Some(TraversalError::Timeout)
+            }
+        }
+
+        (Visit::Visited {
+            server: remote_server,
+            path,
+            servers,
+            error
+        }, started_at.elapsed())
+    }
+}
+
+#[async_trait::async_trait]
+impl Traversal for ParallelTraversal {
+    async fn traverse<R, T, I>(&self, http_client: impl HttpClient, server: &ServerDriver<R, T, I>)
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.discover(http_client, server).await;
+    }
+}
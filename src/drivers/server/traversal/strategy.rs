@@ -0,0 +1,241 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::http::client::HttpClient;
+use crate::rest_api::prelude::*;
+
+use super::cache::{TraversalCache, RouteCache};
+use super::observer::TraversalObserver;
+use super::parallel::{ParallelTraversal, TraversalSummary};
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Search algorithm of the `StrategyTraversal`.
+pub enum TraversalStrategy {
+    /// Request servers one by one, level by level.
+    /// 
+    /// Refer to `BfsTraversal`.
+    Bfs {
+        /// Maximal amount of the requested levels.
+        depth: usize,
+
+        /// Maximal amount of the servers requested on each level.
+        fan_out: usize
+    },
+
+    /// Request servers of every level concurrently.
+    /// 
+    /// Refer to `ParallelTraversal`.
+    Parallel {
+        /// Maximal amount of the simultaneously requested servers.
+        concurrency: usize
+    },
+
+    /// Request known servers of the local router and
+    /// then follow only the lookup hints of the requested
+    /// servers, without requesting their known servers.
+    /// 
+    /// Hints are not returned when the network servers
+    /// are discovered, so discovery requests known
+    /// servers within `max_hops` instead.
+    HintFollowing {
+        /// Maximal amount of the followed hints
+        /// from the local router's known servers.
+        max_hops: usize
+    }
+}
+
+impl Default for TraversalStrategy {
+    #[inline]
+    fn default() -> Self {
+        Self::Bfs {
+            depth: 4,
+            fan_out: 16
+        }
+    }
+}
+
+#[derive(Clone)]
+/// Traversal with the algorithm selected at runtime.
+/// 
+/// All the strategies share the same execution engine
+/// with the `ParallelTraversal`: responses are validated
+/// the same way, every server is requested at most once
+/// and failed servers are listed in the summary.
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use std::time::Duration;
+/// 
+/// use hyperborealib::drivers::server::prelude::*;
+/// 
+/// // Shallow and fast lookups for the local network
+/// let lan = StrategyTraversal::new(TraversalStrategy::Parallel { concurrency: 16 })
+///     .with_timeout(Duration::from_millis(500));
+/// 
+/// // Deep and persistent lookups for the global network
+/// let mesh = StrategyTraversal::new(TraversalStrategy::HintFollowing { max_hops: 8 })
+///     .with_timeout(Duration::from_secs(30));
+/// 
+/// assert_ne!(lan.strategy, mesh.strategy);
+/// ```
+pub struct StrategyTraversal {
+    pub strategy: TraversalStrategy,
+
+    /// Maximal time of every single request.
+    pub timeout: Duration,
+
+    /// Cache of the negative traversal results.
+    pub cache: Option<TraversalCache>,
+
+    /// Cache of the successful lookup routes.
+    pub routes: Option<RouteCache>,
+
+    /// Observer of the traversal's progress.
+    pub observer: Option<Arc<dyn TraversalObserver>>
+}
+
+impl std::fmt::Debug for StrategyTraversal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StrategyTraversal")
+            .field("strategy", &self.strategy)
+            .field("timeout", &self.timeout)
+            .field("cache", &self.cache)
+            .field("routes", &self.routes)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
+}
+
+impl Default for StrategyTraversal {
+    #[inline]
+    fn default() -> Self {
+        Self::new(TraversalStrategy::default())
+    }
+}
+
+impl StrategyTraversal {
+    #[inline]
+    pub fn new(strategy: TraversalStrategy) -> Self {
+        Self {
+            strategy,
+            timeout: Duration::from_secs(5),
+            cache: None,
+            routes: None,
+            observer: None
+        }
+    }
+
+    #[inline]
+    pub fn with_strategy(self, strategy: TraversalStrategy) -> Self {
+        Self {
+            strategy,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Skip lookups which recently didn't find the client
+    /// and servers which recently didn't respond.
+    pub fn with_cache(self, cache: TraversalCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Request servers which last found the clients
+    /// before doing the full traversal.
+    pub fn with_routes(self, routes: RouteCache) -> Self {
+        Self {
+            routes: Some(routes),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Send progress events of every traversal to the observer.
+    pub fn with_observer(self, observer: impl TraversalObserver + 'static) -> Self {
+        Self {
+            observer: Some(Arc::new(observer)),
+            ..self
+        }
+    }
+
+    /// Get execution engine configured for the current strategy.
+    fn engine(&self) -> ParallelTraversal {
+        let engine = match self.strategy {
+            TraversalStrategy::Bfs { depth, fan_out } => ParallelTraversal::new(1, self.timeout)
+                .with_max_depth(depth)
+                .with_max_fanout(fan_out),
+
+            TraversalStrategy::Parallel { concurrency } => ParallelTraversal::new(concurrency, self.timeout),
+
+            TraversalStrategy::HintFollowing { max_hops } => ParallelTraversal::new(1, self.timeout)
+                .with_max_depth(max_hops + 1)
+        };
+
+        ParallelTraversal {
+            cache: self.cache.clone(),
+            routes: self.routes.clone(),
+            observer: self.observer.clone(),
+            ..engine
+        }
+    }
+
+    #[inline]
+    /// Search the given client in the network.
+    /// 
+    /// Discovered servers are not indexed by the local router.
+    pub async fn lookup<R, T, I>(
+        &self,
+        http_client: impl HttpClient,
+        server: &ServerDriver<R, T, I>,
+        client_public: &PublicKey,
+        client_type: Option<ClientType>
+    ) -> TraversalSummary
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        let hints_only = matches!(self.strategy, TraversalStrategy::HintFollowing { .. });
+
+        self.engine().walk(http_client, server, Some((client_public, client_type)), hints_only).await
+    }
+
+    #[inline]
+    /// Index all the servers reachable within the
+    /// traversal's depth by the local router.
+    pub async fn discover<R, T, I>(&self, http_client: impl HttpClient, server: &ServerDriver<R, T, I>) -> TraversalSummary
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.engine().walk(http_client, server, None, false).await
+    }
+}
+
+#[async_trait::async_trait]
+impl Traversal for StrategyTraversal {
+    async fn traverse<R, T, I>(&self, http_client: impl HttpClient, server: &ServerDriver<R, T, I>)
+    where
+        R: Router + Sync,
+        T: Traversal + Sync,
+        I: MessagesInbox + Sync
+    {
+        self.discover(http_client, server).await;
+    }
+}
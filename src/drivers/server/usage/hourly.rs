@@ -0,0 +1,327 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde_json::{json, Value as Json};
+
+use tokio::time::Instant;
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::rest_api::AsJson;
+
+use super::{UsageTracker, UsageEvent, Usage};
+
+/// Amount of independently locked counters maps.
+const SHARDS: usize = 16;
+
+const BUCKET_SECS: u64 = 3600;
+
+type Buckets = VecDeque<(u64, Usage)>;
+
+#[derive(Debug)]
+/// In-memory usage tracker with counters
+/// grouped in hourly buckets.
+/// 
+/// Buckets older than `retention` hours are dropped.
+/// Counters can be persisted to a file with the
+/// `flush` method and loaded back by `open`.
+pub struct HourlyUsage {
+    /// Amount of stored hourly buckets.
+    pub retention: u64,
+
+    /// UTC timestamp and time of the tracker's creation
+    /// used to get current hour.
+    origin: (u64, Instant),
+
+    path: Option<PathBuf>,
+
+    shards: Vec<Mutex<HashMap<PublicKey, Buckets>>>
+}
+
+impl Default for HourlyUsage {
+    #[inline]
+    fn default() -> Self {
+        Self::new(24)
+    }
+}
+
+impl HourlyUsage {
+    pub fn new(retention: u64) -> Self {
+        Self {
+            retention: retention.max(1),
+            origin: (crate::time::timestamp(), Instant::now()),
+            path: None,
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect()
+        }
+    }
+
+    /// Open tracker persisted in the given file.
+    /// 
+    /// Create empty tracker if the file doesn't exist.
+    pub async fn open(path: impl Into<PathBuf>, retention: u64) -> std::io::Result<Self> {
+        let path: PathBuf = path.into();
+
+        let mut tracker = Self::new(retention);
+
+        if path.exists() {
+            tracker.load(&path).await?;
+        }
+
+        tracker.path = Some(path);
+
+        Ok(tracker)
+    }
+
+    /// Get index of the current hour.
+    fn current_hour(&self) -> u64 {
+        let elapsed = Instant::now().duration_since(self.origin.1).as_secs();
+
+        (self.origin.0 + elapsed) / BUCKET_SECS
+    }
+
+    #[inline]
+    fn shard(&self, key: &PublicKey) -> &Mutex<HashMap<PublicKey, Buckets>> {
+        &self.shards[key.to_bytes()[1] as usize % SHARDS]
+    }
+
+    /// Remove outdated buckets.
+    fn prune(&self, buckets: &mut Buckets, hour: u64) {
+        while buckets.front().is_some_and(|(bucket, _)| bucket + self.retention <= hour) {
+            buckets.pop_front();
+        }
+    }
+
+    /// Sum counters of the buckets within retention time.
+    fn total(&self, buckets: &Buckets, hour: u64) -> Usage {
+        let mut usage = Usage::default();
+
+        for (_, bucket) in buckets.iter().filter(|(bucket, _)| bucket + self.retention > hour) {
+            usage.merge(bucket);
+        }
+
+        usage
+    }
+
+    async fn load(&self, path: &Path) -> std::io::Result<()> {
+        let invalid = |err: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Invalid usage file: {err}"));
+
+        let json = serde_json::from_slice::<Json>(&tokio::fs::read(path).await?)?;
+
+        let Some(records) = json.get("usage").and_then(Json::as_array) else {
+            return Err(invalid("usage records not found"));
+        };
+
+        for record in records {
+            let key = record.get("key")
+                .and_then(Json::as_str)
+                .and_then(|key| PublicKey::from_base64(key).ok())
+                .ok_or_else(|| invalid("invalid key"))?;
+
+            let hour = record.get("hour")
+                .and_then(Json::as_u64)
+                .ok_or_else(|| invalid("invalid hour"))?;
+
+            let usage = record.get("counters")
+                .ok_or_else(|| invalid("counters not found"))
+                .and_then(|counters| Usage::from_json(counters).map_err(|err| invalid(&err.to_string())))?;
+
+            let mut shard = self.shard(&key).lock().unwrap();
+
+            let buckets = shard.entry(key).or_default();
+
+            match buckets.iter_mut().find(|(bucket, _)| *bucket == hour) {
+                Some((_, bucket)) => bucket.merge(&usage),
+                None => buckets.push_back((hour, usage))
+            }
+
+            buckets.make_contiguous().sort_by_key(|(bucket, _)| *bucket);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl UsageTracker for HourlyUsage {
+    fn record(&self, key: &PublicKey, event: UsageEvent, bytes: u64) {
+        let hour = self.current_hour();
+
+        let mut shard = self.shard(key).lock().unwrap();
+
+        let buckets = shard.entry(key.clone()).or_default();
+
+        match buckets.back_mut() {
+            Some((bucket, usage)) if *bucket == hour => usage.record(event, bytes),
+
+            _ => {
+                let mut usage = Usage::default();
+
+                usage.record(event, bytes);
+
+                buckets.push_back((hour, usage));
+            }
+        }
+
+        self.prune(buckets, hour);
+    }
+
+    fn usage(&self, key: &PublicKey) -> Usage {
+        let hour = self.current_hour();
+
+        let shard = self.shard(key).lock().unwrap();
+
+        shard.get(key)
+            .map(|buckets| self.total(buckets, hour))
+            .unwrap_or_default()
+    }
+
+    fn top_usage(&self, n: usize) -> Vec<(PublicKey, Usage)> {
+        let hour = self.current_hour();
+
+        let mut usage = Vec::new();
+
+        for shard in &self.shards {
+            let mut shard = shard.lock().unwrap();
+
+            shard.retain(|key, buckets| {
+                self.prune(buckets, hour);
+
+                if buckets.is_empty() {
+                    return false;
+                }
+
+                usage.push((key.clone(), self.total(buckets, hour)));
+
+                true
+            });
+        }
+
+        // Sort keys with the same usage by their
+        // public keys to keep the order stable
+        usage.sort_by(|(a_key, a), (b_key, b)| {
+            b.total_bytes().cmp(&a.total_bytes())
+                .then_with(|| a_key.to_bytes().cmp(&b_key.to_bytes()))
+        });
+
+        usage.truncate(n);
+
+        usage
+    }
+
+    async fn flush(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let hour = self.current_hour();
+
+        let mut records = Vec::new();
+
+        for shard in &self.shards {
+            let shard = shard.lock().unwrap();
+
+            for (key, buckets) in shard.iter() {
+                for (bucket, usage) in buckets.iter().filter(|(bucket, _)| bucket + self.retention > hour) {
+                    records.push(json!({
+                        "key": key.to_base64(),
+                        "hour": bucket,
+                        "counters": usage.to_json().map_err(std::io::Error::other)?
+                    }));
+                }
+            }
+        }
+
+        let temp_path = path.with_extension("tmp");
+
+        tokio::fs::write(&temp_path, serde_json::to_vec(&json!({
+            "usage": records
+        }))?).await?;
+
+        tokio::fs::rename(&temp_path, path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::crypto::asymmetric::SecretKey;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn rollover() {
+        let usage = HourlyUsage::new(2);
+
+        let key = SecretKey::random().public_key();
+
+        usage.record(&key, UsageEvent::Sent, 100);
+
+        tokio::time::advance(Duration::from_secs(BUCKET_SECS)).await;
+
+        usage.record(&key, UsageEvent::Sent, 50);
+
+        assert_eq!(usage.usage(&key).sent_messages, 2);
+        assert_eq!(usage.usage(&key).sent_bytes, 150);
+
+        // The first bucket is out of the retention window
+        tokio::time::advance(Duration::from_secs(BUCKET_SECS)).await;
+
+        assert_eq!(usage.usage(&key).sent_messages, 1);
+        assert_eq!(usage.usage(&key).sent_bytes, 50);
+
+        tokio::time::advance(Duration::from_secs(BUCKET_SECS)).await;
+
+        assert_eq!(usage.usage(&key), Usage::default());
+        assert!(usage.top_usage(10).is_empty());
+    }
+
+    #[tokio::test]
+    async fn top_usage() {
+        let usage = HourlyUsage::default();
+
+        let keys = (0..5)
+            .map(|_| SecretKey::random().public_key())
+            .collect::<Vec<_>>();
+
+        for (i, key) in keys.iter().enumerate() {
+            for _ in 0..=i {
+                usage.record(key, UsageEvent::Received, 10);
+            }
+        }
+
+        let top = usage.top_usage(2);
+
+        assert_eq!(top.len(), 2);
+
+        assert_eq!(top[0].0, keys[4]);
+        assert_eq!(top[0].1.received_bytes, 50);
+
+        assert_eq!(top[1].0, keys[3]);
+        assert_eq!(top[1].1.received_messages, 4);
+    }
+
+    #[tokio::test]
+    async fn persist() -> std::io::Result<()> {
+        let path = std::env::temp_dir().join("hourly-usage-test.json");
+
+        if path.exists() {
+            tokio::fs::remove_file(&path).await?;
+        }
+
+        let key = SecretKey::random().public_key();
+
+        let usage = HourlyUsage::open(&path, 24).await?;
+
+        usage.record(&key, UsageEvent::Sent, 10);
+        usage.record(&key, UsageEvent::Polled, 20);
+
+        usage.flush().await?;
+
+        let restored = HourlyUsage::open(&path, 24).await?;
+
+        assert_eq!(restored.usage(&key), usage.usage(&key));
+
+        Ok(())
+    }
+}
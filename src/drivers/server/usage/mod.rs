@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use serde_json::{json, Value as Json};
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::rest_api::{AsJson, AsJsonError};
+
+#[cfg(feature = "usage-hourly")]
+pub mod hourly;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Message processed by the server on behalf of a client.
+pub enum UsageEvent {
+    /// Client has sent a message through the server.
+    Sent,
+
+    /// Message was sent to the client and stored
+    /// in the server's inbox.
+    Received,
+
+    /// Client has polled a message from the inbox.
+    Polled
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Resources used by a client.
+pub struct Usage {
+    pub sent_messages: u64,
+    pub sent_bytes: u64,
+
+    pub received_messages: u64,
+    pub received_bytes: u64,
+
+    pub polled_messages: u64,
+    pub polled_bytes: u64
+}
+
+impl Usage {
+    /// Count single message of the given size.
+    pub fn record(&mut self, event: UsageEvent, bytes: u64) {
+        let (messages, total) = match event {
+            UsageEvent::Sent     => (&mut self.sent_messages, &mut self.sent_bytes),
+            UsageEvent::Received => (&mut self.received_messages, &mut self.received_bytes),
+            UsageEvent::Polled   => (&mut self.polled_messages, &mut self.polled_bytes)
+        };
+
+        *messages += 1;
+        *total += bytes;
+    }
+
+    /// Add counters of another usage to the current one.
+    pub fn merge(&mut self, other: &Self) {
+        self.sent_messages += other.sent_messages;
+        self.sent_bytes += other.sent_bytes;
+
+        self.received_messages += other.received_messages;
+        self.received_bytes += other.received_bytes;
+
+        self.polled_messages += other.polled_messages;
+        self.polled_bytes += other.polled_bytes;
+    }
+
+    #[inline]
+    /// Get total amount of processed bytes.
+    pub fn total_bytes(&self) -> u64 {
+        self.sent_bytes + self.received_bytes + self.polled_bytes
+    }
+}
+
+impl AsJson for Usage {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "sent": {
+                "messages": self.sent_messages,
+                "bytes": self.sent_bytes
+            },
+            "received": {
+                "messages": self.received_messages,
+                "bytes": self.received_bytes
+            },
+            "polled": {
+                "messages": self.polled_messages,
+                "bytes": self.polled_bytes
+            }
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let get = |field: &'static str| {
+            let Some(counters) = json.get(field) else {
+                return Err(AsJsonError::FieldNotFound(field));
+            };
+
+            let messages = counters.get("messages")
+                .and_then(Json::as_u64)
+                .ok_or(AsJsonError::FieldValueInvalid(field))?;
+
+            let bytes = counters.get("bytes")
+                .and_then(Json::as_u64)
+                .ok_or(AsJsonError::FieldValueInvalid(field))?;
+
+            Ok((messages, bytes))
+        };
+
+        let (sent_messages, sent_bytes) = get("sent")?;
+        let (received_messages, received_bytes) = get("received")?;
+        let (polled_messages, polled_bytes) = get("polled")?;
+
+        Ok(Self {
+            sent_messages,
+            sent_bytes,
+            received_messages,
+            received_bytes,
+            polled_messages,
+            polled_bytes
+        })
+    }
+}
+
+#[async_trait::async_trait]
+/// UsageTracker is a struct that accounts messages
+/// processed by the server for each client.
+/// 
+/// Recording is performed on every send and poll
+/// request, so it must be cheap.
+pub trait UsageTracker: Send + Sync {
+    /// Count single message of the given size.
+    fn record(&self, key: &PublicKey, event: UsageEvent, bytes: u64);
+
+    /// Get usage of the key within the tracked window.
+    fn usage(&self, key: &PublicKey) -> Usage;
+
+    /// Get `n` keys with the largest amount of processed
+    /// bytes within the tracked window.
+    fn top_usage(&self, n: usize) -> Vec<(PublicKey, Usage)>;
+
+    /// Persist the counters if supported.
+    async fn flush(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+/// Shared usage tracker of the server driver.
+pub(crate) struct SharedUsage(pub Arc<dyn UsageTracker>);
+
+impl std::fmt::Debug for SharedUsage {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedUsage")
+    }
+}
+
+impl PartialEq for SharedUsage {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedUsage {}
+
+impl std::hash::Hash for SharedUsage {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage() -> Result<(), AsJsonError> {
+        let mut usage = Usage::default();
+
+        usage.record(UsageEvent::Sent, 10);
+        usage.record(UsageEvent::Sent, 5);
+        usage.record(UsageEvent::Polled, 7);
+
+        assert_eq!(usage.sent_messages, 2);
+        assert_eq!(usage.sent_bytes, 15);
+        assert_eq!(usage.polled_messages, 1);
+        assert_eq!(usage.total_bytes(), 22);
+
+        let mut merged = usage;
+
+        merged.merge(&usage);
+
+        assert_eq!(merged.sent_messages, 4);
+        assert_eq!(merged.total_bytes(), 44);
+
+        assert_eq!(Usage::from_json(&usage.to_json()?)?, usage);
+
+        Ok(())
+    }
+}
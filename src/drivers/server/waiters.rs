@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Weak, Mutex};
+
+use tokio::sync::Notify;
+
+use crate::crypto::asymmetric::PublicKey;
+
+#[derive(Debug, Default, Clone)]
+/// Long polls waiting for new messages
+/// in the server's inbox.
+/// 
+/// Polls are keyed by their receivers only. Every waiting
+/// poll of the receiver is woken up when a message is sent
+/// to it, and checks its channels in the inbox itself.
+/// 
+/// Clones share the same waiters.
+pub struct InboxWaiters {
+    waiters: Arc<Mutex<HashMap<PublicKey, Weak<Notify>>>>
+}
+
+impl InboxWaiters {
+    /// Get waiter of the given receiver's messages.
+    /// 
+    /// Receiver is forgotten when all its
+    /// waiters are dropped.
+    pub fn waiter(&self, receiver: &PublicKey) -> InboxWaiter {
+        let mut waiters = self.waiters.lock()
+            .expect("Failed to lock inbox waiters");
+
+        let notify = match waiters.get(receiver).and_then(Weak::upgrade) {
+            Some(notify) => notify,
+
+            None => {
+                let notify = Arc::new(Notify::new());
+
+                waiters.insert(receiver.clone(), Arc::downgrade(&notify));
+
+                notify
+            }
+        };
+
+        InboxWaiter {
+            receiver: receiver.clone(),
+            notify,
+            waiters: self.clone()
+        }
+    }
+
+    /// Wake up all the polls waiting
+    /// for the given receiver's messages.
+    pub fn notify(&self, receiver: &PublicKey) {
+        let notify = self.waiters.lock()
+            .expect("Failed to lock inbox waiters")
+            .get(receiver)
+            .and_then(Weak::upgrade);
+
+        if let Some(notify) = notify {
+            notify.notify_waiters();
+        }
+    }
+
+    #[inline]
+    /// Get amount of the receivers with waiting polls.
+    pub fn len(&self) -> usize {
+        self.waiters.lock()
+            .expect("Failed to lock inbox waiters")
+            .len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl PartialEq for InboxWaiters {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.waiters, &other.waiters)
+    }
+}
+
+impl Eq for InboxWaiters {}
+
+impl std::hash::Hash for InboxWaiters {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.waiters) as *const () as usize).hash(state);
+    }
+}
+
+#[derive(Debug)]
+/// Waiter of the receiver's messages.
+/// 
+/// Refer to `InboxWaiters::waiter`.
+pub struct InboxWaiter {
+    receiver: PublicKey,
+    notify: Arc<Notify>,
+    waiters: InboxWaiters
+}
+
+impl InboxWaiter {
+    #[inline]
+    /// Get notification of the next message.
+    /// 
+    /// Notification must be enabled before the inbox is
+    /// checked so messages sent in between are not missed.
+    pub fn notified(&self) -> tokio::sync::futures::Notified<'_> {
+        self.notify.notified()
+    }
+}
+
+impl Drop for InboxWaiter {
+    fn drop(&mut self) {
+        let mut waiters = self.waiters.waiters.lock()
+            .expect("Failed to lock inbox waiters");
+
+        // Other waiters can't be made while the lock is held
+        if Arc::strong_count(&self.notify) == 1 {
+            waiters.remove(&self.receiver);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::crypto::prelude::*;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn notify() {
+        let waiters = InboxWaiters::default();
+
+        let receiver = SecretKey::random().public_key();
+
+        let waiter = waiters.waiter(&receiver);
+        let other = waiters.waiter(&SecretKey::random().public_key());
+
+        assert_eq!(waiters.len(), 2);
+
+        {
+            let notified = waiter.notified();
+            let other_notified = other.notified();
+
+            tokio::pin!(notified, other_notified);
+
+            notified.as_mut().enable();
+            other_notified.as_mut().enable();
+
+            waiters.notify(&receiver);
+
+            assert!(tokio::time::timeout(Duration::from_millis(100), notified).await.is_ok());
+            assert!(tokio::time::timeout(Duration::from_millis(100), other_notified).await.is_err());
+        }
+
+        // Receivers are forgotten with their waiters
+        drop(other);
+
+        assert_eq!(waiters.len(), 1);
+
+        let clone = waiters.waiter(&receiver);
+
+        drop(waiter);
+
+        assert_eq!(waiters.len(), 1);
+
+        drop(clone);
+
+        assert!(waiters.is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+//! Admission control of the incoming HTTP requests.
+//! 
+//! Server processes at most `max_in_flight` requests
+//! simultaneously. Other requests wait in a bounded queue
+//! for at most `max_wait` time, and everything above the
+//! queue's capacity is rejected immediately, before the
+//! request's body is read.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AdmissionParams {
+    /// Maximal amount of simultaneously processed requests.
+    pub max_in_flight: usize,
+
+    /// Maximal amount of requests waiting for processing.
+    pub max_queue: usize,
+
+    /// Maximal time a request can wait in the queue.
+    pub max_wait: Duration,
+
+    /// Time after which rejected clients should
+    /// retry their requests.
+    /// 
+    /// Sent in the `Retry-After` header.
+    pub retry_after: Duration
+}
+
+impl Default for AdmissionParams {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 256,
+            max_queue: 1024,
+            max_wait: Duration::from_secs(10),
+            retry_after: Duration::from_secs(1)
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Admission control metrics.
+pub struct AdmissionStats {
+    /// Amount of currently processed requests.
+    pub in_flight: u64,
+
+    /// Amount of currently queued requests.
+    pub queued: u64,
+
+    /// Total amount of admitted requests.
+    pub admitted: u64,
+
+    /// Total amount of rejected requests.
+    pub rejected: u64
+}
+
+#[derive(Debug)]
+struct State {
+    params: AdmissionParams,
+    in_flight: usize,
+    queued: usize
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: Mutex<State>,
+    notify: Notify,
+    admitted: AtomicU64,
+    rejected: AtomicU64
+}
+
+impl Inner {
+    #[inline]
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("Failed to lock admission control state")
+    }
+
+    /// Take a processing slot if there's a free one.
+    fn try_admit(self: &Arc<Self>, state: &mut State) -> Option<AdmissionPermit> {
+        if state.in_flight >= state.params.max_in_flight {
+            return None;
+        }
+
+        state.in_flight += 1;
+
+        self.admitted.fetch_add(1, Ordering::Relaxed);
+
+        Some(AdmissionPermit {
+            inner: self.clone()
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Shared admission control handle.
+/// 
+/// All the clones of the handle control the same
+/// server, so its limits can be tuned at runtime.
+pub struct AdmissionControl(Arc<Inner>);
+
+impl Default for AdmissionControl {
+    #[inline]
+    fn default() -> Self {
+        Self::new(AdmissionParams::default())
+    }
+}
+
+impl AdmissionControl {
+    pub fn new(params: AdmissionParams) -> Self {
+        Self(Arc::new(Inner {
+            state: Mutex::new(State {
+                params,
+                in_flight: 0,
+                queued: 0
+            }),
+            notify: Notify::new(),
+            admitted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0)
+        }))
+    }
+
+    #[inline]
+    pub fn params(&self) -> AdmissionParams {
+        self.0.lock().params
+    }
+
+    /// Change admission limits.
+    /// 
+    /// New limits are applied to the queued requests
+    /// as well. Already processed requests are never
+    /// interrupted.
+    pub fn set_params(&self, params: AdmissionParams) {
+        self.0.lock().params = params;
+
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn stats(&self) -> AdmissionStats {
+        let state = self.0.lock();
+
+        AdmissionStats {
+            in_flight: state.in_flight as u64,
+            queued: state.queued as u64,
+            admitted: self.0.admitted.load(Ordering::Relaxed),
+            rejected: self.0.rejected.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Wait for a processing slot.
+    /// 
+    /// Return `None` if the queue is full or the request
+    /// waited for too long. Otherwise the slot is taken
+    /// until the returned permit is dropped.
+    pub async fn acquire(&self) -> Option<AdmissionPermit> {
+        let deadline = {
+            let mut state = self.0.lock();
+
+            if let Some(permit) = self.0.try_admit(&mut state) {
+                return Some(permit);
+            }
+
+            if state.queued >= state.params.max_queue {
+                self.0.rejected.fetch_add(1, Ordering::Relaxed);
+
+                return None;
+            }
+
+            state.queued += 1;
+
+            Instant::now() + state.params.max_wait
+        };
+
+        loop {
+            // Subscribe before checking the state
+            // so we don't miss released slots
+            let notified = self.0.notify.notified();
+
+            {
+                let mut state = self.0.lock();
+
+                if let Some(permit) = self.0.try_admit(&mut state) {
+                    state.queued -= 1;
+
+                    return Some(permit);
+                }
+            }
+
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                self.0.lock().queued -= 1;
+                self.0.rejected.fetch_add(1, Ordering::Relaxed);
+
+                return None;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Taken processing slot.
+/// 
+/// The slot is released when the permit is dropped.
+pub struct AdmissionPermit {
+    inner: Arc<Inner>
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        self.inner.lock().in_flight -= 1;
+
+        self.inner.notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn acquire() {
+        let admission = AdmissionControl::new(AdmissionParams {
+            max_in_flight: 2,
+            max_queue: 1,
+            max_wait: Duration::from_millis(100),
+            ..AdmissionParams::default()
+        });
+
+        let first = admission.acquire().await.unwrap();
+        let _second = admission.acquire().await.unwrap();
+
+        // Queue is full
+        let queued = tokio::spawn({
+            let admission = admission.clone();
+
+            async move {
+                admission.acquire().await
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert_eq!(admission.stats().queued, 1);
+        assert!(admission.acquire().await.is_none());
+
+        // Released slot is taken by the queued request
+        drop(first);
+
+        let _queued = queued.await.unwrap().unwrap();
+
+        // Queued request waited for too long
+        assert!(admission.acquire().await.is_none());
+
+        // Limits are tuned at runtime
+        admission.set_params(AdmissionParams {
+            max_in_flight: 3,
+            ..admission.params()
+        });
+
+        assert!(admission.acquire().await.is_some());
+
+        assert_eq!(admission.stats(), AdmissionStats {
+            in_flight: 2,
+            queued: 0,
+            admitted: 4,
+            rejected: 2
+        });
+    }
+}
@@ -16,6 +16,34 @@ pub trait HttpClient: Clone + Send + Sync {
     /// Send HTTP POST request with JSON body
     async fn post(&self, url: impl AsRef<str> + Send, body: Json) -> Result<Response, Box<dyn std::error::Error + Send + Sync>>;
 
+    /// Send HTTP POST request with raw JSON body bytes
+    /// and additional headers
+    /// 
+    /// Response body is `None` if it's not a valid JSON.
+    /// 
+    /// Default implementation parses the body and sends
+    /// it using `post`, so the headers are not sent and
+    /// the body may be serialized differently.
+    async fn post_raw(&self, url: impl AsRef<str> + Send, body: Vec<u8>, headers: Vec<(String, String)>) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+        let _ = headers;
+
+        let body = serde_json::from_slice(&body)?;
+
+        self.post(url, body).await
+    }
+
+    #[inline]
+    /// Check if the request failed with the given
+    /// error because it has exceeded the client's timeout.
+    /// 
+    /// Used to tell expired long polls from other
+    /// transport errors. Default is `false`.
+    fn is_timeout(&self, error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+        let _ = error;
+
+        false
+    }
+
     /// Perform GET REST API request
     async fn get_request<T: AsJson>(&self, url: impl AsRef<str> + Send) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
         #[cfg(feature = "tracing")]
@@ -87,9 +115,35 @@ impl Default for ReqwestHttpClient {
     }
 }
 
+#[cfg(feature = "client-reqwest")]
+impl ReqwestHttpClient {
+    #[inline]
+    /// Use the given `reqwest` client, e.g.
+    /// one with configured timeouts.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self(client)
+    }
+}
+
+#[cfg(feature = "client-reqwest")]
+impl From<reqwest::Client> for ReqwestHttpClient {
+    #[inline]
+    fn from(client: reqwest::Client) -> Self {
+        Self(client)
+    }
+}
+
 #[cfg(feature = "client-reqwest")]
 #[async_trait::async_trait]
 impl HttpClient for ReqwestHttpClient {
+    fn is_timeout(&self, error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+        // Errors are boxed before they're converted to trait objects
+        error.downcast_ref::<Box<reqwest::Error>>()
+            .map(|error| error.as_ref())
+            .or_else(|| error.downcast_ref::<reqwest::Error>())
+            .is_some_and(reqwest::Error::is_timeout)
+    }
+
     async fn get(&self, url: impl AsRef<str> + Send) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
         let response = self.0.get(url.as_ref())
             .send().await
@@ -122,4 +176,96 @@ impl HttpClient for ReqwestHttpClient {
             body: Some(body)
         })
     }
+
+    async fn post_raw(&self, url: impl AsRef<str> + Send, body: Vec<u8>, headers: Vec<(String, String)>) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+        let mut request = self.0.post(url.as_ref())
+            .header("Content-Type", "application/json")
+            .body(body);
+
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        let response = request.send().await
+            .map_err(Box::new)?;
+
+        let status = response.status();
+
+        let body = response.bytes().await
+            .map_err(Box::new)?;
+
+        Ok(Response {
+            status: status.as_u16(),
+            body: serde_json::from_slice(&body).ok()
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[derive(Default, Debug, Clone)]
+    /// HTTP client returning canned responses.
+    /// 
+    /// Requests of unknown URLs fail as unreachable.
+    pub struct MockHttpClient {
+        responses: Arc<HashMap<String, Json>>,
+        requests: Arc<Mutex<Vec<String>>>
+    }
+
+    impl MockHttpClient {
+        pub fn new(responses: impl IntoIterator<Item = (String, Json)>) -> Self {
+            Self {
+                responses: Arc::new(responses.into_iter().collect()),
+                requests: Arc::new(Mutex::new(Vec::new()))
+            }
+        }
+
+        /// Get URLs of all the performed requests.
+        pub fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpClient for MockHttpClient {
+        async fn get(&self, url: impl AsRef<str> + Send) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+            self.requests.lock().unwrap().push(url.as_ref().to_string());
+
+            match self.responses.get(url.as_ref()) {
+                Some(body) => Ok(Response {
+                    status: 200,
+                    body: Some(body.clone())
+                }),
+
+                None => Err(format!("Failed to connect to {}", url.as_ref()).into())
+            }
+        }
+
+        async fn post(&self, url: impl AsRef<str> + Send, _body: Json) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+            self.get(url).await
+        }
+    }
+
+    #[tokio::test]
+    async fn post_raw() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let client = MockHttpClient::new([
+            (String::from("http://example.org/api"), serde_json::json!({ "status": 100 }))
+        ]);
+
+        let response = client.post_raw("http://example.org/api", b"{\"hello\":\"world\"}".to_vec(), vec![]).await?;
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, Some(serde_json::json!({ "status": 100 })));
+
+        // Default implementation can't send non-JSON bodies
+        assert!(client.post_raw("http://example.org/api", b"hello".to_vec(), vec![]).await.is_err());
+        assert_eq!(client.requests().len(), 1);
+
+        Ok(())
+    }
 }
@@ -1,11 +1,21 @@
 pub mod client;
 pub mod server;
 
+#[cfg(feature = "server-axum")]
+pub mod admission;
+
 pub use client::HttpClient;
-pub use server::HttpServer;
+pub use server::{HttpServer, BodyLimits, CorsPolicy};
 
 #[cfg(feature = "client-reqwest")]
 pub use client::ReqwestHttpClient;
 
 #[cfg(feature = "server-axum")]
 pub use server::AxumHttpServer;
+
+#[cfg(feature = "server-axum")]
+pub use admission::{
+    AdmissionControl,
+    AdmissionParams,
+    AdmissionStats
+};
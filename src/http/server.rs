@@ -1,3 +1,6 @@
+use std::collections::{HashMap, BTreeMap};
+use std::time::Duration;
+
 use std::net::{
     SocketAddr,
     ToSocketAddrs
@@ -8,12 +11,226 @@ use tokio::net::TcpListener;
 
 #[cfg(feature = "server-axum")]
 use axum::{
-    extract::ConnectInfo,
-    body::Bytes as HttpBody
+    extract::{ConnectInfo, State, Request, Query},
+    middleware::Next,
+    routing::MethodRouter,
+    response::IntoResponse,
+    http::{Method, HeaderValue, StatusCode},
+    body::Body as HttpBody
 };
 
+#[cfg(feature = "server-axum")]
+use super::admission::AdmissionControl;
+
 use crate::rest_api::AsJson;
 
+use crate::rest_api::response::Response;
+use crate::rest_api::status::ResponseStatus;
+use crate::rest_api::error_code::ErrorCode;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Maximal sizes of the POST requests' bodies in bytes.
+/// 
+/// Larger bodies are rejected before they're
+/// deserialized with the `RequestBodyTooLarge`
+/// response status.
+pub struct BodyLimits {
+    /// Limit of the routes without override.
+    pub default: usize,
+
+    /// Limits of the specific routes.
+    pub routes: BTreeMap<String, usize>
+}
+
+impl BodyLimits {
+    #[inline]
+    pub fn new(default: usize) -> Self {
+        Self {
+            default,
+            routes: BTreeMap::new()
+        }
+    }
+
+    #[inline]
+    /// Override limit of the given route.
+    pub fn with_route(mut self, route: impl ToString, limit: usize) -> Self {
+        self.routes.insert(route.to_string(), limit);
+
+        self
+    }
+
+    #[inline]
+    /// Get body size limit of the given route.
+    /// 
+    /// ```rust
+    /// use hyperborealib::http::server::BodyLimits;
+    /// 
+    /// let limits = BodyLimits::new(1024)
+    ///     .with_route("/api/v1/send", 4096);
+    /// 
+    /// assert_eq!(limits.limit("/api/v1/send"), 4096);
+    /// assert_eq!(limits.limit("/api/v1/announce"), 1024);
+    /// ```
+    pub fn limit(&self, route: &str) -> usize {
+        self.routes.get(route)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Build error response for the body
+    /// exceeding the given limit.
+    pub fn error_response(limit: usize) -> Response<()> {
+        Response::error(
+            ResponseStatus::RequestBodyTooLarge,
+            ErrorCode::QuotaExceeded,
+            format!("Request body exceeds {limit} bytes")
+        )
+    }
+}
+
+impl Default for BodyLimits {
+    #[inline]
+    fn default() -> Self {
+        Self::new(16 * 1024 * 1024)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Cross-origin requests allowed by the server.
+/// 
+/// Browsers send `OPTIONS` preflight requests before the
+/// cross-origin requests and block the responses without
+/// proper `Access-Control-Allow-*` headers.
+pub struct CorsPolicy {
+    /// Origins allowed to send requests, e.g.
+    /// `https://example.org`. Any origin is
+    /// allowed if it contains `*`.
+    pub allowed_origins: Vec<String>,
+
+    /// Methods allowed by the preflight responses.
+    pub allowed_methods: Vec<String>,
+
+    /// Request headers allowed by the preflight responses.
+    pub allowed_headers: Vec<String>,
+
+    /// Time browsers can cache the preflight responses for.
+    pub max_age: Option<Duration>
+}
+
+impl CorsPolicy {
+    /// Allow `GET` and `POST` requests
+    /// from the given origins.
+    pub fn new<T: ToString>(origins: impl IntoIterator<Item = T>) -> Self {
+        Self {
+            allowed_origins: origins.into_iter()
+                .map(|origin| origin.to_string())
+                .collect(),
+
+            allowed_methods: vec![
+                String::from("GET"),
+                String::from("POST")
+            ],
+
+            allowed_headers: vec![
+                String::from("Content-Type")
+            ],
+
+            max_age: Some(Duration::from_secs(3600))
+        }
+    }
+
+    #[inline]
+    /// Allow requests from any origin.
+    pub fn any() -> Self {
+        Self::new(["*"])
+    }
+
+    #[inline]
+    /// Set methods allowed by the preflight responses.
+    pub fn with_methods<T: ToString>(mut self, methods: impl IntoIterator<Item = T>) -> Self {
+        self.allowed_methods = methods.into_iter()
+            .map(|method| method.to_string())
+            .collect();
+
+        self
+    }
+
+    #[inline]
+    /// Set request headers allowed by the preflight responses.
+    pub fn with_headers<T: ToString>(mut self, headers: impl IntoIterator<Item = T>) -> Self {
+        self.allowed_headers = headers.into_iter()
+            .map(|header| header.to_string())
+            .collect();
+
+        self
+    }
+
+    #[inline]
+    /// Set time browsers can cache the preflight
+    /// responses for. Default is 1 hour.
+    pub fn with_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+
+        self
+    }
+
+    #[inline]
+    /// Check if requests from the origin are allowed.
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        self.allowed_origins.iter()
+            .any(|allowed| allowed == "*" || allowed == origin)
+    }
+
+    /// Get CORS headers of the response
+    /// to the request from the given origin.
+    /// 
+    /// No headers are returned for the origins
+    /// which are not allowed.
+    /// 
+    /// ```rust
+    /// use hyperborealib::http::server::CorsPolicy;
+    /// 
+    /// let cors = CorsPolicy::new(["https://example.org"]);
+    /// 
+    /// let headers = cors.headers("https://example.org", false);
+    /// 
+    /// assert!(headers.contains(&("Access-Control-Allow-Origin", String::from("https://example.org"))));
+    /// 
+    /// let headers = cors.headers("https://example.org", true);
+    /// 
+    /// assert!(headers.contains(&("Access-Control-Allow-Methods", String::from("GET, POST"))));
+    /// assert!(headers.contains(&("Access-Control-Max-Age", String::from("3600"))));
+    /// 
+    /// assert!(cors.headers("https://example.com", true).is_empty());
+    /// ```
+    pub fn headers(&self, origin: &str, preflight: bool) -> Vec<(&'static str, String)> {
+        if !self.is_allowed(origin) {
+            return Vec::new();
+        }
+
+        let mut headers = Vec::with_capacity(5);
+
+        // Responses differ between origins unless all are allowed
+        if self.allowed_origins.iter().any(|allowed| allowed == "*") {
+            headers.push(("Access-Control-Allow-Origin", String::from("*")));
+        } else {
+            headers.push(("Access-Control-Allow-Origin", origin.to_string()));
+            headers.push(("Vary", String::from("Origin")));
+        }
+
+        if preflight {
+            headers.push(("Access-Control-Allow-Methods", self.allowed_methods.join(", ")));
+            headers.push(("Access-Control-Allow-Headers", self.allowed_headers.join(", ")));
+
+            if let Some(max_age) = self.max_age {
+                headers.push(("Access-Control-Max-Age", max_age.as_secs().to_string()));
+            }
+        }
+
+        headers
+    }
+}
+
 #[async_trait::async_trait]
 pub trait HttpServer {
     /// Add GET request route
@@ -23,6 +240,16 @@ pub trait HttpServer {
         callback: impl FnOnce(SocketAddr) -> F + Clone + Send + Sync + 'static
     );
 
+    /// Add GET request route with URL query parameters
+    /// 
+    /// If callback returns an error then it is sent
+    /// with `400 Bad Request` HTTP status.
+    async fn get_with_query<T: AsJson, F: std::future::Future<Output = Result<T, String>> + Send>(
+        &mut self,
+        path: impl AsRef<str> + Send,
+        callback: impl FnOnce(SocketAddr, HashMap<String, String>) -> F + Clone + Send + Sync + 'static
+    );
+
     /// Add POST request route
     async fn post<T: AsJson, F: AsJson, R: std::future::Future<Output = F> + Send>(
         &mut self,
@@ -30,13 +257,155 @@ pub trait HttpServer {
         callback: impl FnOnce(SocketAddr, T) -> R + Clone + Send + Sync + 'static
     );
 
+    /// Set maximal sizes of the POST requests' bodies.
+    /// 
+    /// Applied to the routes added after this call.
+    /// Default implementation ignores the limits, so
+    /// servers which don't override it accept bodies
+    /// of any size.
+    fn set_body_limits(&mut self, _limits: BodyLimits) {}
+
+    /// Answer `OPTIONS` preflight requests and attach
+    /// CORS headers to the responses, including the ones
+    /// rejected by the server. Disabled if `None`.
+    /// 
+    /// Default implementation ignores the policy, so
+    /// servers which don't override it don't support
+    /// cross-origin requests from the browsers.
+    fn set_cors(&mut self, _cors: Option<CorsPolicy>) {}
+
     /// Run the server with specified GET and POST routes
     async fn serve(self, address: impl ToSocketAddrs + Send) -> Result<(), Box<dyn std::error::Error>>;
+
+    #[cfg(feature = "server-axum")]
+    /// Get admission control of the incoming requests.
+    /// 
+    /// Returned handle can be used to tune the server's
+    /// limits at runtime. Default implementation returns
+    /// `None`, meaning the server admits all the requests.
+    fn admission_control(&self) -> Option<&AdmissionControl> {
+        None
+    }
 }
 
 #[cfg(feature = "server-axum")]
 #[derive(Default, Debug, Clone)]
-pub struct AxumHttpServer(Option<axum::Router>);
+pub struct AxumHttpServer {
+    router: Option<axum::Router>,
+    admission: AdmissionControl,
+    body_limits: BodyLimits,
+    cors: Option<CorsPolicy>
+}
+
+#[cfg(feature = "server-axum")]
+impl AxumHttpServer {
+    #[inline]
+    /// Create new server with given admission control.
+    pub fn new(admission: AdmissionControl) -> Self {
+        Self {
+            router: None,
+            admission,
+            body_limits: BodyLimits::default(),
+            cors: None
+        }
+    }
+}
+
+#[cfg(feature = "server-axum")]
+/// Reject incoming request if the server is overloaded.
+/// 
+/// This is called before the request's body is read.
+async fn admission_layer(State(admission): State<AdmissionControl>, request: Request, next: Next) -> axum::response::Response {
+    let Some(permit) = admission.acquire().await else {
+        let retry_after = admission.params().retry_after;
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(?retry_after, "Server is overloaded, rejecting request");
+
+        let body = Response::<()>::error(ResponseStatus::RateLimited, ErrorCode::RateLimited, "Server is overloaded")
+            .to_json()
+            .map(|body| body.to_string())
+            .unwrap_or_default();
+
+        return axum::http::Response::builder()
+            .status(429)
+            .header("Content-Type", "text/json")
+            .header("Retry-After", retry_after.as_secs_f64().ceil().to_string())
+            .body(axum::body::Body::from(body))
+            .unwrap();
+    };
+
+    let response = next.run(request).await;
+
+    drop(permit);
+
+    response
+}
+
+#[cfg(feature = "server-axum")]
+/// Answer preflight requests and attach CORS headers
+/// to the responses to the cross-origin requests.
+/// 
+/// Only `OPTIONS` requests of the allowed origins with the
+/// `Access-Control-Request-Method` header are answered here.
+/// Other requests, including the preflights of the origins
+/// which are not allowed, are passed to the router.
+/// 
+/// This is called before the admission control, so
+/// preflights don't take its permits and rejected
+/// requests have CORS headers as well.
+async fn cors_layer(State(cors): State<CorsPolicy>, request: Request, next: Next) -> axum::response::Response {
+    let origin = request.headers()
+        .get("Origin")
+        .and_then(|origin| origin.to_str().ok())
+        .map(String::from);
+
+    let preflight = request.method() == Method::OPTIONS &&
+        request.headers().contains_key("Access-Control-Request-Method") &&
+        origin.as_deref().is_some_and(|origin| cors.is_allowed(origin));
+
+    let mut response = if preflight {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        next.run(request).await
+    };
+
+    if let Some(origin) = origin {
+        for (name, value) in cors.headers(&origin, preflight) {
+            // Vary is appended below to keep the router's values
+            if name == "Vary" {
+                continue;
+            }
+
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+    }
+
+    // Responses depend on the origin even when it's not allowed,
+    // so caches must not reuse them for other origins
+    let varies = response.headers()
+        .get_all("Vary")
+        .iter()
+        .any(|value| value.to_str().is_ok_and(|value| value.contains("Origin")));
+
+    if !varies {
+        response.headers_mut().append("Vary", HeaderValue::from_static("Origin"));
+    }
+
+    response
+}
+
+#[cfg(feature = "server-axum")]
+impl AxumHttpServer {
+    /// Add the route to the router.
+    fn route(&mut self, path: &str, route: MethodRouter) {
+        let router = self.router.take().unwrap_or_default();
+
+        self.router = Some(router.route(path, route));
+    }
+}
 
 #[cfg(feature = "server-axum")]
 #[async_trait::async_trait]
@@ -46,9 +415,7 @@ impl HttpServer for AxumHttpServer {
         path: impl AsRef<str> + Send,
         callback: impl FnOnce(SocketAddr) -> F + Clone + Send + Sync + 'static
     ) {
-        let router = self.0.take().unwrap_or_default();
-
-        self.0 = Some(router.route(path.as_ref(), axum::routing::get(move |ConnectInfo(client_address): ConnectInfo<SocketAddr>| async move {
+        self.route(path.as_ref(), axum::routing::get(move |ConnectInfo(client_address): ConnectInfo<SocketAddr>| async move {
             let response = callback(client_address).await;
 
             match response.to_json() {
@@ -66,7 +433,48 @@ impl HttpServer for AxumHttpServer {
                         .unwrap()
                 }
             }
-        })));
+        }));
+    }
+
+    async fn get_with_query<T: AsJson, F: std::future::Future<Output = Result<T, String>> + Send>(
+        &mut self,
+        path: impl AsRef<str> + Send,
+        callback: impl FnOnce(SocketAddr, HashMap<String, String>) -> F + Clone + Send + Sync + 'static
+    ) {
+        self.route(path.as_ref(), axum::routing::get(move |ConnectInfo(client_address): ConnectInfo<SocketAddr>, Query(query): Query<HashMap<String, String>>| async move {
+            let response = match callback(client_address, query).await {
+                Ok(response) => response,
+
+                Err(err) => {
+                    let body = Response::<()>::error(ResponseStatus::InvalidRequestStructure, ErrorCode::InvalidRequest, err)
+                        .to_json()
+                        .map(|body| body.to_string())
+                        .unwrap_or_default();
+
+                    return axum::http::Response::builder()
+                        .status(400)
+                        .header("Content-Type", "text/json")
+                        .body(body)
+                        .unwrap();
+                }
+            };
+
+            match response.to_json() {
+                Ok(response) => {
+                    axum::http::Response::builder()
+                        .header("Content-Type", "text/json")
+                        .body(response.to_string())
+                        .unwrap()
+                }
+
+                Err(err) => {
+                    axum::http::Response::builder()
+                        .status(500)
+                        .body(format!("Failed to serialize response as JSON: {err}"))
+                        .unwrap()
+                }
+            }
+        }));
     }
 
     async fn post<T: AsJson, F: AsJson, R: std::future::Future<Output = F> + Send>(
@@ -74,9 +482,27 @@ impl HttpServer for AxumHttpServer {
         path: impl AsRef<str> + Send,
         callback: impl FnOnce(SocketAddr, T) -> R + Clone + Send + Sync + 'static
     ) {
-        let router = self.0.take().unwrap_or_default();
+        let limit = self.body_limits.limit(path.as_ref());
+
+        self.route(path.as_ref(), axum::routing::post(move |ConnectInfo(client_address): ConnectInfo<SocketAddr>, body: HttpBody| async move {
+            // Reading stops once the limit is exceeded, so large
+            // bodies are never stored in memory entirely
+            let Ok(body) = axum::body::to_bytes(body, limit).await else {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(?client_address, limit, "Request body is too large");
+
+                let body = BodyLimits::error_response(limit)
+                    .to_json()
+                    .map(|body| body.to_string())
+                    .unwrap_or_default();
+
+                return axum::http::Response::builder()
+                    .status(413)
+                    .header("Content-Type", "text/json")
+                    .body(body)
+                    .unwrap();
+            };
 
-        self.0 = Some(router.route(path.as_ref(), axum::routing::post(move |ConnectInfo(client_address): ConnectInfo<SocketAddr>, body: HttpBody| async move {
             let json = match serde_json::from_slice::<serde_json::Value>(&body) {
                 Ok(json) => json,
                 Err(err) => {
@@ -114,13 +540,19 @@ impl HttpServer for AxumHttpServer {
                         .unwrap()
                 }
             }
-        })));
+        }));
     }
 
     async fn serve(mut self, address: impl ToSocketAddrs + Send) -> Result<(), Box<dyn std::error::Error>> {
-        let router = self.0.take()
+        let mut router = self.router.take()
             .unwrap_or_default()
-            .into_make_service_with_connect_info::<SocketAddr>();
+            .layer(axum::middleware::from_fn_with_state(self.admission.clone(), admission_layer));
+
+        if let Some(cors) = self.cors.take() {
+            router = router.layer(axum::middleware::from_fn_with_state(cors, cors_layer));
+        }
+
+        let router = router.into_make_service_with_connect_info::<SocketAddr>();
 
         let Some(address) = address.to_socket_addrs()?.next() else {
             return Err("Failed to resolve server address".into());
@@ -132,4 +564,246 @@ impl HttpServer for AxumHttpServer {
 
         Ok(())
     }
+
+    #[inline]
+    fn set_body_limits(&mut self, limits: BodyLimits) {
+        self.body_limits = limits;
+    }
+
+    #[inline]
+    fn set_cors(&mut self, cors: Option<CorsPolicy>) {
+        self.cors = cors;
+    }
+
+    #[inline]
+    fn admission_control(&self) -> Option<&AdmissionControl> {
+        Some(&self.admission)
+    }
+}
+
+#[cfg(all(test, feature = "server-axum", feature = "client-reqwest"))]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{Duration, Instant};
+
+    use crate::http::AdmissionParams;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn admission_control() -> Result<(), Box<dyn std::error::Error>> {
+        let processed = Arc::new(AtomicU64::new(0));
+
+        let admission = AdmissionControl::new(AdmissionParams {
+            max_in_flight: 4,
+            max_queue: 6,
+            max_wait: Duration::from_secs(10),
+            retry_after: Duration::from_secs(2)
+        });
+
+        let mut server = AxumHttpServer::new(admission.clone());
+
+        server.get("/slow", {
+            let processed = processed.clone();
+
+            move |_| async move {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+
+                processed.fetch_add(1, Ordering::Relaxed);
+            }
+        }).await;
+
+        tokio::spawn(async move {
+            let _ = server.serve("127.0.0.1:48471").await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = reqwest::Client::new();
+
+        let requests = (0..50).map(|_| {
+            let client = client.clone();
+
+            tokio::spawn(async move {
+                let started_at = Instant::now();
+
+                let response = client.get("http://127.0.0.1:48471/slow")
+                    .send().await
+                    .unwrap();
+
+                let retry_after = response.headers()
+                    .get("Retry-After")
+                    .map(|value| value.to_str().unwrap().to_string());
+
+                (response.status().as_u16(), retry_after, started_at.elapsed())
+            })
+        }).collect::<Vec<_>>();
+
+        let mut rejected = 0;
+
+        for request in requests {
+            let (status, retry_after, elapsed) = request.await?;
+
+            if status == 429 {
+                assert_eq!(retry_after.as_deref(), Some("2"));
+
+                // Rejected before the handler was called
+                assert!(elapsed < Duration::from_millis(500));
+
+                rejected += 1;
+            }
+
+            else {
+                assert_eq!(status, 200);
+            }
+        }
+
+        assert_eq!(processed.load(Ordering::Relaxed), 10);
+        assert_eq!(rejected, 40);
+
+        let stats = admission.stats();
+
+        assert_eq!(stats.admitted, 10);
+        assert_eq!(stats.rejected, 40);
+        assert_eq!(stats.in_flight, 0);
+        assert_eq!(stats.queued, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cors() -> Result<(), Box<dyn std::error::Error>> {
+        async fn serve(port: u16, cors: Option<CorsPolicy>) {
+            let mut server = AxumHttpServer::default();
+
+            server.set_cors(cors);
+
+            server.post("/echo", |_, request: serde_json::Value| async move {
+                request
+            }).await;
+
+            tokio::spawn(async move {
+                let _ = server.serve(("127.0.0.1", port)).await;
+            });
+        }
+
+        serve(48544, Some(CorsPolicy::new(["https://example.org"]).with_max_age(Some(Duration::from_secs(600))))).await;
+        serve(48545, None).await;
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = reqwest::Client::new();
+
+        let preflight = |port: u16, origin: &'static str| client.request(reqwest::Method::OPTIONS, format!("http://127.0.0.1:{port}/echo"))
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .header("Access-Control-Request-Headers", "Content-Type")
+            .send();
+
+        let header = |response: &reqwest::Response, name: &str| response.headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap().to_string());
+
+        // Preflight of the allowed origin
+        let response = preflight(48544, "https://example.org").await?;
+
+        assert!(response.status().is_success());
+        assert_eq!(header(&response, "Access-Control-Allow-Origin").as_deref(), Some("https://example.org"));
+        assert_eq!(header(&response, "Access-Control-Allow-Methods").as_deref(), Some("GET, POST"));
+        assert_eq!(header(&response, "Access-Control-Allow-Headers").as_deref(), Some("Content-Type"));
+        assert_eq!(header(&response, "Access-Control-Max-Age").as_deref(), Some("600"));
+
+        // Headers of the actual request
+        let response = client.post("http://127.0.0.1:48544/echo")
+            .header("Origin", "https://example.org")
+            .json(&serde_json::json!({ "hello": "world" }))
+            .send().await?;
+
+        assert_eq!(header(&response, "Access-Control-Allow-Origin").as_deref(), Some("https://example.org"));
+        assert!(header(&response, "Access-Control-Allow-Methods").is_none());
+        assert_eq!(header(&response, "Vary").as_deref(), Some("Origin"));
+        assert_eq!(response.json::<serde_json::Value>().await?["hello"], "world");
+
+        // Other origins are not allowed
+        let response = preflight(48544, "https://example.com").await?;
+
+        assert_eq!(response.status(), 405);
+        assert!(header(&response, "Access-Control-Allow-Origin").is_none());
+        assert_eq!(header(&response, "Vary").as_deref(), Some("Origin"));
+
+        // OPTIONS requests without the requested method are not preflights
+        let response = client.request(reqwest::Method::OPTIONS, "http://127.0.0.1:48544/echo")
+            .header("Origin", "https://example.org")
+            .send().await?;
+
+        assert_eq!(response.status(), 405);
+        assert!(header(&response, "Access-Control-Allow-Methods").is_none());
+        assert_eq!(header(&response, "Vary").as_deref(), Some("Origin"));
+
+        let response = client.request(reqwest::Method::OPTIONS, "http://127.0.0.1:48544/echo")
+            .header("Access-Control-Request-Method", "POST")
+            .send().await?;
+
+        assert_eq!(response.status(), 405);
+        assert!(header(&response, "Access-Control-Allow-Origin").is_none());
+
+        // CORS is disabled by default
+        let response = preflight(48545, "https://example.org").await?;
+
+        assert_eq!(response.status(), 405);
+        assert!(header(&response, "Access-Control-Allow-Origin").is_none());
+
+        // Overloaded server
+        let admission = AdmissionControl::new(AdmissionParams {
+            max_in_flight: 1,
+            max_queue: 0,
+            max_wait: Duration::from_secs(10),
+            retry_after: Duration::from_secs(1)
+        });
+
+        let mut server = AxumHttpServer::new(admission.clone());
+
+        server.set_cors(Some(CorsPolicy::new(["https://example.org"])));
+
+        server.get("/slow", |_| async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }).await;
+
+        tokio::spawn(async move {
+            let _ = server.serve("127.0.0.1:48546").await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let slow = tokio::spawn(client.get("http://127.0.0.1:48546/slow").send());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Rejected requests have CORS headers
+        let response = client.get("http://127.0.0.1:48546/slow")
+            .header("Origin", "https://example.org")
+            .send().await?;
+
+        assert_eq!(response.status(), 429);
+        assert_eq!(header(&response, "Access-Control-Allow-Origin").as_deref(), Some("https://example.org"));
+
+        // Preflights are answered without admission
+        let response = client.request(reqwest::Method::OPTIONS, "http://127.0.0.1:48546/slow")
+            .header("Origin", "https://example.org")
+            .header("Access-Control-Request-Method", "GET")
+            .send().await?;
+
+        assert_eq!(response.status(), 204);
+        assert_eq!(header(&response, "Access-Control-Allow-Origin").as_deref(), Some("https://example.org"));
+
+        assert_eq!(slow.await??.status(), 200);
+
+        let stats = admission.stats();
+
+        assert_eq!(stats.admitted, 1);
+        assert_eq!(stats.rejected, 1);
+
+        Ok(())
+    }
 }
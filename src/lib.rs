@@ -5,6 +5,13 @@ pub mod http;
 pub mod port_forward;
 pub mod drivers;
 pub mod rest_api;
+pub mod discovery;
+
+#[cfg(feature = "simulation")]
+pub mod simulation;
+
+#[cfg(feature = "test_utils")]
+pub mod test_utils;
 
 pub const STANDARD_VERSION: u64 = 1;
 pub const LIBRARY_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -74,7 +74,7 @@ impl PortForwarder for UpnpPortForwarder {
             .collect::<Vec<_>>();
 
         delete_ports(configs)
-            .collect::<Result<_, _>>()?;
+            .collect::<Result<(), _>>()?;
 
         Ok(true)
     }
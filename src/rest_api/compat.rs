@@ -0,0 +1,188 @@
+//! Wire format compatibility shims.
+//! 
+//! Every intentional change of the v1 wire format must
+//! be registered here as a `Shim` which translates payloads
+//! between the legacy and the current formats. `wire_fixtures`
+//! test suite applies these shims before comparing today's
+//! serialized values with the committed v1 fixtures, so any
+//! unregistered change fails the build.
+
+use serde_json::Value as Json;
+
+use super::status::ResponseStatus;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Shim {
+    /// Short name of the shim.
+    pub name: &'static str,
+
+    /// Human readable description of the changed field.
+    pub description: &'static str,
+
+    /// Names of the wire fixtures this shim applies to.
+    /// 
+    /// Empty list means that the shim applies to all of them.
+    pub fixtures: &'static [&'static str],
+
+    /// Translate legacy v1 payload into the current format.
+    pub upgrade: fn(&mut Json),
+
+    /// Translate current payload into the legacy v1 format.
+    pub downgrade: fn(&mut Json)
+}
+
+impl Shim {
+    #[inline]
+    /// Check if the shim should be applied to the given fixture.
+    pub fn applies_to(&self, fixture: &str) -> bool {
+        self.fixtures.is_empty() || self.fixtures.contains(&fixture)
+    }
+}
+
+/// List of all the registered shims.
+pub const SHIMS: &[Shim] = &[
+    Shim {
+        name: "response-status-codes",
+        description: "Response status codes added after v1 are translated to the closest v1 codes",
+        fixtures: &[],
+        upgrade: identity,
+        downgrade: downgrade_status
+    }
+];
+
+/// Translate legacy v1 payload of the given fixture
+/// into the current format.
+pub fn upgrade(fixture: &str, json: &mut Json) {
+    for shim in SHIMS.iter().filter(|shim| shim.applies_to(fixture)) {
+        (shim.upgrade)(json);
+    }
+}
+
+/// Translate current payload of the given fixture
+/// into the legacy v1 format.
+pub fn downgrade(fixture: &str, json: &mut Json) {
+    for shim in SHIMS.iter().rev().filter(|shim| shim.applies_to(fixture)) {
+        (shim.downgrade)(json);
+    }
+}
+
+fn identity(_json: &mut Json) {}
+
+/// Get the closest status known to the v1 peers.
+pub fn v1_status(status: ResponseStatus) -> ResponseStatus {
+    match status {
+        ResponseStatus::InvalidChannelName => ResponseStatus::InvalidRequestStructure,
+        ResponseStatus::RateLimited => ResponseStatus::ServerError,
+        ResponseStatus::TooManyRequests => ResponseStatus::ServerError,
+        ResponseStatus::ReputationTooLow => ResponseStatus::RequestValidationFailed,
+        ResponseStatus::Unauthorized => ResponseStatus::RequestValidationFailed,
+        ResponseStatus::Forbidden => ResponseStatus::RequestValidationFailed,
+        ResponseStatus::BatchTooLarge => ResponseStatus::InvalidRequestStructure,
+        ResponseStatus::RequestExpired => ResponseStatus::RequestValidationFailed,
+        ResponseStatus::CertificateExpired => ResponseStatus::RequestValidationFailed,
+        ResponseStatus::AnnounceRecordTooLarge => ResponseStatus::InvalidRequestStructure,
+        ResponseStatus::TooManyAnnounceEntries => ResponseStatus::InvalidRequestStructure,
+        ResponseStatus::RoutingTableFull => ResponseStatus::ServerError,
+        ResponseStatus::InvalidClientAlias => ResponseStatus::InvalidRequestStructure,
+        ResponseStatus::ClientAliasTaken => ResponseStatus::RequestValidationFailed,
+        ResponseStatus::InsufficientWork => ResponseStatus::RequestValidationFailed,
+
+        status => status
+    }
+}
+
+/// Replace response's `status` code with the closest
+/// one known to the v1 peers.
+pub fn downgrade_status(json: &mut Json) {
+    let Some(status) = json.get_mut("status") else {
+        return;
+    };
+
+    if let Some(code) = status.as_u64().and_then(ResponseStatus::from_code) {
+        *status = Json::from(v1_status(code).to_code());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn response_status_codes() {
+        let mut response = json!({
+            "standard": 1,
+            "status": 323,
+            "reason": "Invalid channel name"
+        });
+
+        upgrade("send_response_error", &mut response);
+
+        assert_eq!(response["status"], 323);
+
+        downgrade("send_response_error", &mut response);
+
+        assert_eq!(response["status"], 300);
+
+        let mut response = json!({
+            "standard": 1,
+            "status": 311,
+            "reason": "Client not found"
+        });
+
+        downgrade("lookup_response_error", &mut response);
+
+        assert_eq!(response["status"], 311);
+
+        let mut response = json!({
+            "standard": 1,
+            "status": 201,
+            "reason": "Server is overloaded"
+        });
+
+        downgrade("poll_response_error", &mut response);
+
+        assert_eq!(response["status"], 200);
+
+        let mut response = json!({
+            "standard": 1,
+            "status": 331,
+            "reason": "Too many announce entries"
+        });
+
+        downgrade("announce_response_error", &mut response);
+
+        assert_eq!(response["status"], 300);
+
+        let mut response = json!({
+            "standard": 1,
+            "status": 332,
+            "reason": "Routing table is full"
+        });
+
+        downgrade("announce_response_error", &mut response);
+
+        assert_eq!(response["status"], 200);
+
+        let mut response = json!({
+            "standard": 1,
+            "status": 304,
+            "reason": "Public key is blacklisted"
+        });
+
+        downgrade("connect_response_error", &mut response);
+
+        assert_eq!(response["status"], 301);
+
+        let mut response = json!({
+            "standard": 1,
+            "status": 341,
+            "reason": "Client alias is already taken"
+        });
+
+        downgrade("connect_response_error", &mut response);
+
+        assert_eq!(response["status"], 301);
+    }
+}
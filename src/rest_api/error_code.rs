@@ -0,0 +1,185 @@
+use super::status::ResponseStatus;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Machine-readable category of the error response.
+/// 
+/// Unlike the response status, error codes are coarse
+/// and stable, so clients can decide how to handle the
+/// error without parsing its reason.
+/// 
+/// Codes are serialized as strings. Codes unknown
+/// to this version of the library are kept in
+/// the `Other` variant.
+pub enum ErrorCode {
+    /// Request is malformed or contains invalid values.
+    InvalidRequest,
+
+    /// Request's signature or certificate is invalid.
+    ValidationFailed,
+
+    /// Request is too old or was already received.
+    RequestExpired,
+
+    /// Connection certificate is expired and should
+    /// be renewed by connecting to the server again.
+    CertificateExpired,
+
+    /// Requester is not allowed to perform the request.
+    Forbidden,
+
+    /// Requested client is not found.
+    NotFound,
+
+    /// Requested resource is already taken by another client.
+    Conflict,
+
+    /// Request exceeds one of the server's limits.
+    QuotaExceeded,
+
+    /// Requester should slow down.
+    RateLimited,
+
+    /// Server has failed to process the request in time.
+    Timeout,
+
+    /// Server has failed to process the request.
+    Internal,
+
+    /// Code unknown to this version of the library.
+    Other(String)
+}
+
+impl ErrorCode {
+    /// Get stable string representation of the code.
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// assert_eq!(ErrorCode::CertificateExpired.name(), "certificate_expired");
+    /// assert_eq!(ErrorCode::Other(String::from("future_code")).name(), "future_code");
+    /// ```
+    pub fn name(&self) -> &str {
+        match self {
+            Self::InvalidRequest     => "invalid_request",
+            Self::ValidationFailed   => "validation_failed",
+            Self::RequestExpired     => "request_expired",
+            Self::CertificateExpired => "certificate_expired",
+            Self::Forbidden          => "forbidden",
+            Self::NotFound           => "not_found",
+            Self::Conflict           => "conflict",
+            Self::QuotaExceeded      => "quota_exceeded",
+            Self::RateLimited        => "rate_limited",
+            Self::Timeout            => "timeout",
+            Self::Internal           => "internal",
+
+            Self::Other(name) => name
+        }
+    }
+
+    /// Parse code from its string representation.
+    /// 
+    /// Unknown codes are parsed as `Other`.
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// assert_eq!(ErrorCode::from_name("not_found"), ErrorCode::NotFound);
+    /// assert_eq!(ErrorCode::from_name("future_code"), ErrorCode::Other(String::from("future_code")));
+    /// ```
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "invalid_request"     => Self::InvalidRequest,
+            "validation_failed"   => Self::ValidationFailed,
+            "request_expired"     => Self::RequestExpired,
+            "certificate_expired" => Self::CertificateExpired,
+            "forbidden"           => Self::Forbidden,
+            "not_found"           => Self::NotFound,
+            "conflict"            => Self::Conflict,
+            "quota_exceeded"      => Self::QuotaExceeded,
+            "rate_limited"        => Self::RateLimited,
+            "timeout"             => Self::Timeout,
+            "internal"            => Self::Internal,
+
+            name => Self::Other(name.to_string())
+        }
+    }
+}
+
+impl From<ResponseStatus> for ErrorCode {
+    /// Get the closest error code of the response status.
+    /// 
+    /// Used for the error responses of the servers
+    /// which don't send error codes.
+    fn from(status: ResponseStatus) -> Self {
+        match status {
+            ResponseStatus::Success |
+            ResponseStatus::ServerError => Self::Internal,
+
+            ResponseStatus::RateLimited |
+            ResponseStatus::TooManyRequests => Self::RateLimited,
+
+            ResponseStatus::InvalidRequestStructure |
+            ResponseStatus::InvalidChannelName |
+            ResponseStatus::InvalidClientAlias => Self::InvalidRequest,
+
+            ResponseStatus::RequestValidationFailed |
+            ResponseStatus::InsufficientWork => Self::ValidationFailed,
+            ResponseStatus::RequestExpired => Self::RequestExpired,
+            ResponseStatus::CertificateExpired => Self::CertificateExpired,
+
+            ResponseStatus::ReputationTooLow |
+            ResponseStatus::Unauthorized |
+            ResponseStatus::Forbidden => Self::Forbidden,
+
+            ResponseStatus::ClientNotFound |
+            ResponseStatus::ClientNotConnected => Self::NotFound,
+
+            ResponseStatus::ClientAliasTaken => Self::Conflict,
+
+            ResponseStatus::BatchTooLarge |
+            ResponseStatus::RequestBodyTooLarge |
+            ResponseStatus::ClientInboxFull |
+            ResponseStatus::MessageTooLarge |
+            ResponseStatus::AnnounceRecordTooLarge |
+            ResponseStatus::TooManyAnnounceEntries |
+            ResponseStatus::RoutingTableFull => Self::QuotaExceeded,
+
+            ResponseStatus::ClientLookupTimeout => Self::Timeout
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let codes = [
+            ErrorCode::InvalidRequest,
+            ErrorCode::ValidationFailed,
+            ErrorCode::RequestExpired,
+            ErrorCode::CertificateExpired,
+            ErrorCode::Forbidden,
+            ErrorCode::NotFound,
+            ErrorCode::Conflict,
+            ErrorCode::QuotaExceeded,
+            ErrorCode::RateLimited,
+            ErrorCode::Timeout,
+            ErrorCode::Internal,
+            ErrorCode::Other(String::from("future_code"))
+        ];
+
+        for code in codes {
+            assert_eq!(ErrorCode::from_name(code.name()), code);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::net::{SocketAddr, IpAddr};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde_json::{json, Value as Json};
+
+use crate::crypto::asymmetric::PublicKey;
+use crate::time::timestamp;
+
+use crate::rest_api::{AsJson, AsJsonError};
+use crate::rest_api::status::ResponseStatus;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Record of the request processed by the server middleware.
+pub struct AccessLogEntry {
+    /// Path of the requested route, e.g. `/api/v1/send`.
+    pub route: String,
+
+    pub client_address: IpAddr,
+
+    /// Public key of the signed request's sender.
+    pub public_key: Option<PublicKey>,
+
+    /// Status of the response.
+    /// 
+    /// Not set for the responses without status,
+    /// e.g. `GET /api/v1/info`.
+    pub status: Option<ResponseStatus>,
+
+    /// Time spent processing the request.
+    pub latency: Duration,
+
+    /// UTC timestamp of the request's
+    /// processing end, in seconds.
+    pub timestamp: u64
+}
+
+impl AccessLogEntry {
+    /// Create entry of the request finished now.
+    /// 
+    /// Response's status is read from its JSON.
+    pub fn new(route: impl ToString, client_address: SocketAddr, public_key: Option<PublicKey>, response: &Json, latency: Duration) -> Self {
+        Self {
+            route: route.to_string(),
+            client_address: client_address.ip(),
+            public_key,
+            status: response.get("status")
+                .and_then(Json::as_u64)
+                .and_then(ResponseStatus::from_code),
+            latency,
+            timestamp: timestamp()
+        }
+    }
+}
+
+impl AsJson for AccessLogEntry {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let mut entry = json!({
+            "route": self.route,
+            "client": {
+                "address": self.client_address.to_string()
+            },
+            "latency": self.latency.as_micros() as u64,
+            "timestamp": self.timestamp
+        });
+
+        if let Some(public_key) = &self.public_key {
+            entry["client"]["public_key"] = Json::from(public_key.to_base64());
+        }
+
+        if let Some(status) = self.status {
+            entry["status"] = Json::from(status.to_code());
+        }
+
+        Ok(entry)
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(route) = json.get("route").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("route"));
+        };
+
+        let Some(client) = json.get("client") else {
+            return Err(AsJsonError::FieldNotFound("client"));
+        };
+
+        let Some(client_address) = client.get("address").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("client.address"));
+        };
+
+        let public_key = match client.get("public_key") {
+            Some(public_key) => Some(PublicKey::from_base64(public_key.as_str().ok_or(AsJsonError::FieldValueInvalid("client.public_key"))?)?),
+            None => None
+        };
+
+        let status = match json.get("status") {
+            Some(status) => Some(status.as_u64()
+                .and_then(ResponseStatus::from_code)
+                .ok_or(AsJsonError::FieldValueInvalid("status"))?),
+
+            None => None
+        };
+
+        let Some(latency) = json.get("latency").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("latency"));
+        };
+
+        let Some(timestamp) = json.get("timestamp").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("timestamp"));
+        };
+
+        Ok(Self {
+            route: route.to_string(),
+            client_address: client_address.parse()
+                .map_err(|_| AsJsonError::FieldValueInvalid("client.address"))?,
+            public_key,
+            status,
+            latency: Duration::from_micros(latency),
+            timestamp
+        })
+    }
+}
+
+#[async_trait::async_trait]
+/// Sink of the server middleware's access log.
+/// 
+/// Called after every request is processed,
+/// including the rejected ones.
+pub trait AccessLog: Send + Sync {
+    async fn record(&self, entry: AccessLogEntry);
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// Access log writing entries as `tracing` events.
+/// 
+/// Does nothing without the `tracing` feature.
+pub struct TracingAccessLog;
+
+#[async_trait::async_trait]
+impl AccessLog for TracingAccessLog {
+    async fn record(&self, entry: AccessLogEntry) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            route = entry.route,
+            client_address = %entry.client_address,
+            public_key = entry.public_key.as_ref().map(PublicKey::to_base64),
+            status = entry.status.map(|status| status.to_code()),
+            latency = ?entry.latency,
+            "Request processed"
+        );
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = entry;
+    }
+}
+
+#[derive(Default, Clone)]
+/// Shared access log of the server middleware.
+/// 
+/// Requests are not recorded until the log is set.
+pub struct AccessLogHook {
+    log: Arc<RwLock<Option<Arc<dyn AccessLog>>>>
+}
+
+impl AccessLogHook {
+    #[inline]
+    /// Set access log replacing the current one.
+    pub fn set(&self, log: Arc<dyn AccessLog>) {
+        *self.log.write().expect("Failed to lock access log") = Some(log);
+    }
+
+    #[inline]
+    pub fn get(&self) -> Option<Arc<dyn AccessLog>> {
+        self.log.read()
+            .expect("Failed to lock access log")
+            .clone()
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.log.read()
+            .expect("Failed to lock access log")
+            .is_some()
+    }
+}
+
+impl std::fmt::Debug for AccessLogHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessLogHook")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::asymmetric::SecretKey;
+
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let response = json!({
+            "status": ResponseStatus::ClientNotFound.to_code()
+        });
+
+        let mut entry = AccessLogEntry::new(
+            "/api/v1/lookup",
+            "127.0.0.1:8001".parse().unwrap(),
+            Some(SecretKey::random().public_key()),
+            &response,
+            Duration::from_micros(1500)
+        );
+
+        assert_eq!(entry.status, Some(ResponseStatus::ClientNotFound));
+        assert_eq!(AccessLogEntry::from_json(&entry.to_json()?)?, entry);
+
+        entry.public_key = None;
+        entry.status = None;
+
+        let json = entry.to_json()?;
+
+        assert!(json["client"].get("public_key").is_none());
+        assert!(json.get("status").is_none());
+        assert_eq!(AccessLogEntry::from_json(&json)?, entry);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,886 @@
+//! Administrative REST API.
+//! 
+//! Admin routes (`/admin/v1/...`) are served by a separate
+//! HTTP server instance, so they're never reachable from
+//! the public API listener.
+//! 
+//! Admin requests are checked by the server driver's replay
+//! cache even if replay protection of the public API is disabled.
+//! 
+//! Switching validation profiles is not exposed yet: the server
+//! driver has no runtime validation profile to switch.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value as Json};
+
+use crate::crypto::prelude::*;
+
+use crate::http::server::HttpServer;
+
+use crate::drivers::server::prelude::*;
+
+use crate::rest_api::prelude::*;
+
+#[cfg(feature = "server-axum")]
+use crate::http::{AdmissionControl, AdmissionParams, AdmissionStats};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Authentication method of the admin API.
+pub enum AdminAuth {
+    /// Static bearer token sent with every request.
+    Token(String),
+
+    /// Requests signed by one of the allowlisted keys.
+    Keys(Vec<PublicKey>)
+}
+
+impl AdminAuth {
+    /// Check if the request is sent by an admin.
+    pub fn authorize<T: AsJson>(&self, request: &AdminRequest<T>) -> bool {
+        match self {
+            Self::Token(token) => request.token.as_ref()
+                .is_some_and(|request_token| constant_time_eq(token.as_bytes(), request_token.as_bytes())),
+
+            Self::Keys(keys) => {
+                keys.contains(&request.request.public_key) &&
+                    request.request.validate().unwrap_or(false)
+            }
+        }
+    }
+}
+
+/// Compare bytes without leaking the position
+/// of the first difference.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter()
+        .zip(b)
+        .fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// `POST /admin/v1/...` request.
+pub struct AdminRequest<T> {
+    /// Bearer token used with `AdminAuth::Token`.
+    pub token: Option<String>,
+
+    pub request: Request<T>
+}
+
+impl<T: AsJson> AdminRequest<T> {
+    /// Create request signed by the admin's key.
+    pub fn signed(admin_secret: &SecretKey, request: T) -> Self {
+        Self {
+            token: None,
+            request: Request::new(admin_secret, request)
+        }
+    }
+
+    /// Create request authenticated by the bearer token.
+    pub fn with_token(token: impl ToString, request: T) -> Self {
+        Self {
+            token: Some(token.to_string()),
+            request: Request::new(&SecretKey::random(), request)
+        }
+    }
+}
+
+impl<T: AsJson> AsJson for AdminRequest<T> {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let mut request = json!({
+            "request": self.request.to_json()?
+        });
+
+        if let Some(token) = &self.token {
+            request["token"] = Json::String(token.clone());
+        }
+
+        Ok(request)
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(request) = json.get("request") else {
+            return Err(AsJsonError::FieldNotFound("request"));
+        };
+
+        let token = match json.get("token") {
+            Some(token) => Some(token.as_str()
+                .ok_or(AsJsonError::FieldValueInvalid("token"))?
+                .to_string()),
+
+            None => None
+        };
+
+        Ok(Self {
+            token,
+            request: Request::from_json(request)?
+        })
+    }
+}
+
+pub type AdminResponse<T> = Response<T>;
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+/// `POST /admin/v1/stats` response.
+pub struct AdminStats {
+    pub local_clients: u64,
+    pub remote_clients: u64,
+    pub servers: u64,
+
+    /// Resources used by the local clients.
+    /// 
+    /// Empty if the server driver has no usage tracker.
+    pub usage: Vec<(PublicKey, Usage)>,
+
+    #[cfg(feature = "server-axum")]
+    /// Admission control metrics of the public listener.
+    /// 
+    /// Zeros if the HTTP server has no admission control.
+    pub admission: AdmissionStats
+}
+
+impl AsJson for AdminStats {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let usage = self.usage.iter()
+            .map(|(public_key, usage)| Ok::<_, AsJsonError>(json!({
+                "public_key": public_key.to_base64(),
+                "usage": usage.to_json()?
+            })))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        #[allow(unused_mut)]
+        let mut stats = json!({
+            "local_clients": self.local_clients,
+            "remote_clients": self.remote_clients,
+            "servers": self.servers,
+            "usage": usage
+        });
+
+        #[cfg(feature = "server-axum")]
+        {
+            stats["admission"] = json!({
+                "in_flight": self.admission.in_flight,
+                "queued": self.admission.queued,
+                "admitted": self.admission.admitted,
+                "rejected": self.admission.rejected
+            });
+        }
+
+        Ok(stats)
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let get = |json: &Json, field: &'static str| json.get(field)
+            .and_then(Json::as_u64)
+            .ok_or(AsJsonError::FieldNotFound(field));
+
+        let usage = match json.get("usage").and_then(Json::as_array) {
+            Some(usage) => usage.iter()
+                .map(|record| {
+                    let public_key = record.get("public_key")
+                        .and_then(Json::as_str)
+                        .ok_or(AsJsonError::FieldValueInvalid("usage"))?;
+
+                    let usage = record.get("usage")
+                        .ok_or(AsJsonError::FieldValueInvalid("usage"))?;
+
+                    Ok::<_, AsJsonError>((PublicKey::from_base64(public_key)?, Usage::from_json(usage)?))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+
+            // Servers without usage tracking support
+            None => vec![]
+        };
+
+        Ok(Self {
+            local_clients: get(json, "local_clients")?,
+            remote_clients: get(json, "remote_clients")?,
+            servers: get(json, "servers")?,
+            usage,
+
+            #[cfg(feature = "server-axum")]
+            admission: {
+                let admission = json.get("admission")
+                    .ok_or(AsJsonError::FieldNotFound("admission"))?;
+
+                AdmissionStats {
+                    in_flight: get(admission, "in_flight")?,
+                    queued: get(admission, "queued")?,
+                    admitted: get(admission, "admitted")?,
+                    rejected: get(admission, "rejected")?
+                }
+            }
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+/// `POST /admin/v1/routing` response.
+pub struct RoutingExport {
+    pub local_clients: Vec<Client>,
+    pub remote_clients: Vec<(Client, Server)>,
+    pub servers: Vec<Server>
+}
+
+impl AsJson for RoutingExport {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let remote_clients = self.remote_clients.iter()
+            .map(|(client, server)| Ok::<_, AsJsonError>(json!({
+                "client": client.to_json()?,
+                "server": server.to_json()?
+            })))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(json!({
+            "local_clients": self.local_clients.to_json()?,
+            "remote_clients": remote_clients,
+            "servers": self.servers.to_json()?
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(remote_clients) = json.get("remote_clients").and_then(Json::as_array) else {
+            return Err(AsJsonError::FieldNotFound("remote_clients"));
+        };
+
+        let remote_clients = remote_clients.iter()
+            .map(|record| {
+                let client = record.get("client")
+                    .ok_or(AsJsonError::FieldNotFound("remote_clients[].client"))?;
+
+                let server = record.get("server")
+                    .ok_or(AsJsonError::FieldNotFound("remote_clients[].server"))?;
+
+                Ok::<_, AsJsonError>((Client::from_json(client)?, Server::from_json(server)?))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            local_clients: json.get("local_clients")
+                .map(Vec::from_json)
+                .ok_or(AsJsonError::FieldNotFound("local_clients"))??,
+
+            remote_clients,
+
+            servers: json.get("servers")
+                .map(Vec::from_json)
+                .ok_or(AsJsonError::FieldNotFound("servers"))??
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// `POST /admin/v1/purge` request.
+pub struct AdminPurge {
+    /// Client whose inbox should be purged.
+    pub receiver: PublicKey,
+
+    /// Purge only the given channel
+    /// instead of the whole inbox.
+    pub channel: Option<ChannelName>
+}
+
+impl AsJson for AdminPurge {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let mut purge = json!({
+            "receiver": self.receiver.to_base64()
+        });
+
+        if let Some(channel) = &self.channel {
+            purge["channel"] = channel.to_json()?;
+        }
+
+        Ok(purge)
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(receiver) = json.get("receiver").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("receiver"));
+        };
+
+        Ok(Self {
+            receiver: PublicKey::from_base64(receiver)?,
+            channel: json.get("channel")
+                .map(ChannelName::from_json)
+                .transpose()?
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// `POST /admin/v1/block` request.
+pub struct AdminBlock {
+    /// Client or server key.
+    pub public_key: PublicKey,
+
+    /// Add the key to the blacklist
+    /// if true, remove otherwise.
+    pub blocked: bool
+}
+
+impl AsJson for AdminBlock {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "public_key": self.public_key.to_base64(),
+            "blocked": self.blocked
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(public_key) = json.get("public_key").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("public_key"));
+        };
+
+        Ok(Self {
+            public_key: PublicKey::from_base64(public_key)?,
+            blocked: json.get("blocked")
+                .and_then(Json::as_bool)
+                .ok_or(AsJsonError::FieldNotFound("blocked"))?
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// `POST /admin/v1/maintenance` request.
+/// 
+/// Runs the selected maintenance jobs once.
+/// Refer to `MaintenanceConfig`.
+pub struct AdminMaintenance {
+    /// Remove expired inbox messages.
+    pub inbox_cleanup: bool,
+
+    /// Remove clients which weren't seen
+    /// for longer than the given time.
+    pub router_cleanup: Option<Duration>
+}
+
+impl AsJson for AdminMaintenance {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let mut maintenance = json!({
+            "inbox_cleanup": self.inbox_cleanup
+        });
+
+        if let Some(older_than) = self.router_cleanup {
+            maintenance["router_cleanup"] = json!(older_than.as_secs());
+        }
+
+        Ok(maintenance)
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let router_cleanup = match json.get("router_cleanup") {
+            Some(older_than) => Some(older_than.as_u64()
+                .map(Duration::from_secs)
+                .ok_or(AsJsonError::FieldValueInvalid("router_cleanup"))?),
+
+            None => None
+        };
+
+        Ok(Self {
+            inbox_cleanup: json.get("inbox_cleanup")
+                .and_then(Json::as_bool)
+                .ok_or(AsJsonError::FieldNotFound("inbox_cleanup"))?,
+
+            router_cleanup
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// `POST /admin/v1/maintenance` response.
+pub struct MaintenanceReport {
+    /// Amount of removed inbox messages.
+    pub inbox_removed: u64,
+
+    /// Amount of removed routing table records.
+    pub router_removed: u64
+}
+
+impl AsJson for MaintenanceReport {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "inbox_removed": self.inbox_removed,
+            "router_removed": self.router_removed
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let get = |field: &'static str| json.get(field)
+            .and_then(Json::as_u64)
+            .ok_or(AsJsonError::FieldNotFound(field));
+
+        Ok(Self {
+            inbox_removed: get("inbox_removed")?,
+            router_removed: get("router_removed")?
+        })
+    }
+}
+
+impl AsJson for RateLimit {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "capacity": self.capacity,
+            "refill_interval": self.refill_interval.as_millis() as u64
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let get = |field: &'static str| json.get(field)
+            .and_then(Json::as_u64)
+            .ok_or(AsJsonError::FieldNotFound(field));
+
+        Ok(Self {
+            capacity: get("capacity")?,
+            refill_interval: Duration::from_millis(get("refill_interval")?)
+        })
+    }
+}
+
+impl AsJson for RateLimits {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let routes = self.routes.iter()
+            .map(|(route, limit)| Ok::<_, AsJsonError>((route.clone(), limit.to_json()?)))
+            .collect::<Result<serde_json::Map<_, _>, _>>()?;
+
+        Ok(json!({
+            "per_key": self.per_key.to_json()?,
+            "per_ip": self.per_ip.to_json()?,
+            "routes": routes,
+            "max_keys": self.max_keys
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let get = |field: &'static str| json.get(field)
+            .ok_or(AsJsonError::FieldNotFound(field));
+
+        let routes = get("routes")?
+            .as_object()
+            .ok_or(AsJsonError::FieldValueInvalid("routes"))?
+            .iter()
+            .map(|(route, limit)| Ok::<_, AsJsonError>((route.clone(), RateLimit::from_json(limit)?)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            per_key: RateLimit::from_json(get("per_key")?)?,
+            per_ip: RateLimit::from_json(get("per_ip")?)?,
+            routes,
+            max_keys: get("max_keys")?
+                .as_u64()
+                .ok_or(AsJsonError::FieldValueInvalid("max_keys"))? as usize
+        })
+    }
+}
+
+#[cfg(feature = "server-axum")]
+impl AsJson for AdmissionParams {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "max_in_flight": self.max_in_flight,
+            "max_queue": self.max_queue,
+            "max_wait": self.max_wait.as_millis() as u64,
+            "retry_after": self.retry_after.as_millis() as u64
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let get = |field: &'static str| json.get(field)
+            .and_then(Json::as_u64)
+            .ok_or(AsJsonError::FieldNotFound(field));
+
+        Ok(Self {
+            max_in_flight: get("max_in_flight")? as usize,
+            max_queue: get("max_queue")? as usize,
+            max_wait: Duration::from_millis(get("max_wait")?),
+            retry_after: Duration::from_millis(get("retry_after")?)
+        })
+    }
+}
+
+/// Build successful admin response.
+fn success<T>(secret_key: &SecretKey, proof_seed: u64, response: T) -> AdminResponse<T> {
+    Response::success(
+        ResponseStatus::Success,
+        secret_key.public_key(),
+        secret_key.create_signature(proof_seed.to_be_bytes()),
+        response
+    )
+}
+
+#[inline]
+fn unauthorized<T>() -> AdminResponse<T> {
+    Response::error(ResponseStatus::RequestValidationFailed, ErrorCode::ValidationFailed, "Admin authentication failed")
+}
+
+/// Check that the request is sent by an admin
+/// and is neither expired nor replayed.
+/// 
+/// Return error response if the request is rejected.
+fn check_request<RouterExt, TraversalExt, MessagesInboxExt, T, U>(
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    auth: &AdminAuth,
+    request: &AdminRequest<T>
+) -> Option<AdminResponse<U>>
+where
+    RouterExt: Router,
+    TraversalExt: Traversal,
+    MessagesInboxExt: MessagesInbox,
+    T: AsJson
+{
+    if !auth.authorize(request) {
+        return Some(unauthorized());
+    }
+
+    driver.check_admin_replay(&request.request).err()
+        .map(|err| Response::error(err.status(), ErrorCode::from(err.status()), err.to_string()))
+}
+
+/// Register admin routes on the given HTTP server.
+pub(crate) async fn register<RouterExt, TraversalExt, MessagesInboxExt>(
+    http_server: &mut impl HttpServer,
+    driver: Arc<ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>>,
+    #[cfg(feature = "server-axum")] admission: Option<AdmissionControl>,
+    auth: AdminAuth
+)
+where
+    RouterExt: Router + Send + Sync + 'static,
+    TraversalExt: Traversal + Send + Sync + 'static,
+    MessagesInboxExt: MessagesInbox + Send + Sync + 'static
+{
+    let auth = Arc::new(auth);
+
+    http_server.post::<AdminRequest<()>, AdminResponse<AdminStats>, _>("/admin/v1/stats", {
+        let driver = driver.clone();
+        let auth = auth.clone();
+
+        #[cfg(feature = "server-axum")]
+        let admission = admission.clone();
+
+        |_client_address, request: AdminRequest<()>| async move {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?_client_address, "POST /admin/v1/stats");
+
+            if let Some(response) = check_request(&driver, &auth, &request) {
+                return response;
+            }
+
+            let router = driver.router();
+
+            let local_clients = router.local_clients().await.unwrap_or_default();
+
+            let usage = match driver.usage_tracker() {
+                Some(_) => local_clients.iter()
+                    .map(|client| (client.public_key.clone(), driver.usage(&client.public_key)))
+                    .collect(),
+
+                None => vec![]
+            };
+
+            let stats = AdminStats {
+                local_clients: local_clients.len() as u64,
+                remote_clients: router.remote_clients().await.map(|clients| clients.len()).unwrap_or_default() as u64,
+                servers: router.servers().await.map(|servers| servers.len()).unwrap_or_default() as u64,
+                usage,
+
+                #[cfg(feature = "server-axum")]
+                admission: admission.as_ref()
+                    .map(AdmissionControl::stats)
+                    .unwrap_or_default()
+            };
+
+            success(&driver.params().secret_key, request.request.proof_seed, stats)
+        }
+    }).await;
+
+    http_server.post::<AdminRequest<()>, AdminResponse<RoutingExport>, _>("/admin/v1/routing", {
+        let driver = driver.clone();
+        let auth = auth.clone();
+
+        |_client_address, request: AdminRequest<()>| async move {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?_client_address, "POST /admin/v1/routing");
+
+            if let Some(response) = check_request(&driver, &auth, &request) {
+                return response;
+            }
+
+            let router = driver.router();
+
+            let export = async {
+                Ok::<_, RouterExt::Error>(RoutingExport {
+                    local_clients: router.local_clients().await?,
+                    remote_clients: router.remote_clients().await?,
+                    servers: router.servers().await?
+                })
+            };
+
+            match export.await {
+                Ok(export) => success(&driver.params().secret_key, request.request.proof_seed, export),
+
+                Err(err) => Response::error(
+                    ResponseStatus::ServerError,
+                    ErrorCode::Internal,
+                    format!("Failed to export routing table: {err}")
+                )
+            }
+        }
+    }).await;
+
+    http_server.post::<AdminRequest<String>, AdminResponse<()>, _>("/admin/v1/disconnect", {
+        let driver = driver.clone();
+        let auth = auth.clone();
+
+        |_client_address, request: AdminRequest<String>| async move {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?_client_address, "POST /admin/v1/disconnect");
+
+            if let Some(response) = check_request(&driver, &auth, &request) {
+                return response;
+            }
+
+            let public_key = match PublicKey::from_base64(&request.request.request) {
+                Ok(public_key) => public_key,
+
+                Err(err) => return Response::error(
+                    ResponseStatus::InvalidRequestStructure,
+                    ErrorCode::InvalidRequest,
+                    format!("Invalid client public key: {err}")
+                )
+            };
+
+            match driver.router().disconnect(&public_key).await {
+                Ok(()) => success(&driver.params().secret_key, request.request.proof_seed, ()),
+
+                Err(err) => Response::error(
+                    ResponseStatus::ServerError,
+                    ErrorCode::Internal,
+                    format!("Failed to disconnect client: {err}")
+                )
+            }
+        }
+    }).await;
+
+    http_server.post::<AdminRequest<AdminPurge>, AdminResponse<()>, _>("/admin/v1/purge", {
+        let driver = driver.clone();
+        let auth = auth.clone();
+
+        |_client_address, request: AdminRequest<AdminPurge>| async move {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?_client_address, "POST /admin/v1/purge");
+
+            if let Some(response) = check_request(&driver, &auth, &request) {
+                return response;
+            }
+
+            let AdminPurge { receiver, channel } = request.request.request;
+
+            match driver.messages_inbox().purge(receiver, channel).await {
+                Ok(()) => success(&driver.params().secret_key, request.request.proof_seed, ()),
+
+                Err(err) => Response::error(
+                    ResponseStatus::ServerError,
+                    ErrorCode::Internal,
+                    format!("Failed to purge messages inbox: {err}")
+                )
+            }
+        }
+    }).await;
+
+    http_server.post::<AdminRequest<AdminBlock>, AdminResponse<()>, _>("/admin/v1/block", {
+        let driver = driver.clone();
+        let auth = auth.clone();
+
+        |_client_address, request: AdminRequest<AdminBlock>| async move {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?_client_address, "POST /admin/v1/block");
+
+            if let Some(response) = check_request(&driver, &auth, &request) {
+                return response;
+            }
+
+            let AdminBlock { public_key, blocked } = request.request.request;
+
+            if blocked {
+                driver.blacklist().add(public_key);
+            } else {
+                driver.blacklist().remove(&public_key);
+            }
+
+            success(&driver.params().secret_key, request.request.proof_seed, ())
+        }
+    }).await;
+
+    http_server.post::<AdminRequest<AdminMaintenance>, AdminResponse<MaintenanceReport>, _>("/admin/v1/maintenance", {
+        let driver = driver.clone();
+        let auth = auth.clone();
+
+        |_client_address, request: AdminRequest<AdminMaintenance>| async move {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?_client_address, "POST /admin/v1/maintenance");
+
+            if let Some(response) = check_request(&driver, &auth, &request) {
+                return response;
+            }
+
+            let maintenance = request.request.request;
+
+            let mut report = MaintenanceReport::default();
+
+            if maintenance.inbox_cleanup {
+                match driver.messages_inbox().cleanup().await {
+                    Ok(removed) => report.inbox_removed = removed,
+
+                    Err(err) => return Response::error(
+                        ResponseStatus::ServerError,
+                        ErrorCode::Internal,
+                        format!("Failed to clean up messages inbox: {err}")
+                    )
+                }
+            }
+
+            if let Some(older_than) = maintenance.router_cleanup {
+                match driver.router().cleanup(older_than).await {
+                    Ok(removed) => report.router_removed = removed,
+
+                    Err(err) => return Response::error(
+                        ResponseStatus::ServerError,
+                        ErrorCode::Internal,
+                        format!("Failed to clean up routing table: {err}")
+                    )
+                }
+            }
+
+            success(&driver.params().secret_key, request.request.proof_seed, report)
+        }
+    }).await;
+
+    http_server.post::<AdminRequest<RateLimits>, AdminResponse<()>, _>("/admin/v1/rate_limits", {
+        let driver = driver.clone();
+        let auth = auth.clone();
+
+        |_client_address, request: AdminRequest<RateLimits>| async move {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(?_client_address, "POST /admin/v1/rate_limits");
+
+            if let Some(response) = check_request(&driver, &auth, &request) {
+                return response;
+            }
+
+            let Some(rate_limiter) = driver.rate_limiter() else {
+                return Response::error(
+                    ResponseStatus::InvalidRequestStructure,
+                    ErrorCode::InvalidRequest,
+                    "Server has no rate limiter"
+                );
+            };
+
+            rate_limiter.set_limits(request.request.request);
+
+            success(&driver.params().secret_key, request.request.proof_seed, ())
+        }
+    }).await;
+
+    // Servers without admission control admit all the requests
+    #[cfg(feature = "server-axum")]
+    if let Some(admission) = admission {
+        http_server.post::<AdminRequest<AdmissionParams>, AdminResponse<()>, _>("/admin/v1/admission", {
+            let driver = driver.clone();
+            let auth = auth.clone();
+
+            |_client_address, request: AdminRequest<AdmissionParams>| async move {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(?_client_address, "POST /admin/v1/admission");
+
+                if let Some(response) = check_request(&driver, &auth, &request) {
+                    return response;
+                }
+
+                admission.set_params(request.request.request);
+
+                success(&driver.params().secret_key, request.request.proof_seed, ())
+            }
+        }).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let request = AdminRequest::with_token("token", String::from("hello"));
+
+        assert_eq!(AdminRequest::from_json(&request.to_json()?)?, request);
+
+        let export = RoutingExport {
+            local_clients: vec![get_client()],
+            remote_clients: vec![(get_client(), get_server())],
+            servers: vec![get_server(), get_server()]
+        };
+
+        assert_eq!(RoutingExport::from_json(&export.to_json()?)?, export);
+
+        let mut usage = Usage::default();
+
+        usage.record(UsageEvent::Sent, 128);
+
+        let stats = AdminStats {
+            local_clients: 1,
+            remote_clients: 2,
+            servers: 3,
+            usage: vec![(get_client().public_key, usage)],
+            ..AdminStats::default()
+        };
+
+        assert_eq!(AdminStats::from_json(&stats.to_json()?)?, stats);
+
+        let purge = AdminPurge {
+            receiver: get_client().public_key,
+            channel: Some(ChannelName::new("hello").unwrap())
+        };
+
+        assert_eq!(AdminPurge::from_json(&purge.to_json()?)?, purge);
+
+        let block = AdminBlock {
+            public_key: get_server().public_key,
+            blocked: true
+        };
+
+        assert_eq!(AdminBlock::from_json(&block.to_json()?)?, block);
+
+        let maintenance = AdminMaintenance {
+            inbox_cleanup: true,
+            router_cleanup: Some(Duration::from_secs(60))
+        };
+
+        assert_eq!(AdminMaintenance::from_json(&maintenance.to_json()?)?, maintenance);
+
+        let limits = RateLimits::default()
+            .with_route("/api/v1/announce", RateLimit::new(1, Duration::from_secs(60)));
+
+        assert_eq!(RateLimits::from_json(&limits.to_json()?)?, limits);
+
+        Ok(())
+    }
+
+    #[test]
+    fn authorize() {
+        let admin = SecretKey::random();
+
+        let token = AdminAuth::Token(String::from("secret"));
+        let keys = AdminAuth::Keys(vec![admin.public_key()]);
+
+        assert!(token.authorize(&AdminRequest::with_token("secret", ())));
+        assert!(!token.authorize(&AdminRequest::with_token("secrets", ())));
+        assert!(!token.authorize(&AdminRequest::signed(&admin, ())));
+
+        assert!(keys.authorize(&AdminRequest::signed(&admin, ())));
+        assert!(!keys.authorize(&AdminRequest::signed(&SecretKey::random(), ())));
+        assert!(!keys.authorize(&AdminRequest::with_token("secret", ())));
+    }
+}
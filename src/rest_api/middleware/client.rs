@@ -1,7 +1,9 @@
+use std::ops::Range;
 use std::sync::Arc;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeMap, HashSet, VecDeque};
 
 use crate::crypto::asymmetric::PublicKey;
+use crate::crypto::compression::CompressionLevel;
 use crate::http::client::HttpClient;
 use crate::drivers::ClientDriver;
 
@@ -11,9 +13,26 @@ use crate::rest_api::prelude::{
     Server as ServerApiRecord
 };
 
-use crate::address::resolve as resolve_uri;
+use crate::address::{Address, resolve as resolve_uri};
 
-use super::Error;
+#[cfg(feature = "mdns")]
+use crate::discovery::{DiscoveryParams, DiscoveredServer, discover_local_with};
+
+use super::{Error, SequenceCounters, MessageReorderer, OrderedEvent};
+use super::{AutoReconnect, ReconnectEvent, SharedCertificate};
+use super::FailoverClient;
+
+#[cfg(feature = "retry")]
+use super::RetryPolicy;
+
+#[cfg(feature = "timeouts")]
+use super::Timeouts;
+
+#[cfg(feature = "session")]
+use super::ClientSession;
+
+#[cfg(feature = "outbox")]
+use super::{Outbox, OutboxError};
 
 #[derive(Debug, Clone, Hash)]
 /// Client HTTP middleware
@@ -22,7 +41,18 @@ use super::Error;
 /// to the servers from the name of inner client driver.
 pub struct Client<T> {
     http_client: Arc<T>,
-    driver: Arc<ClientDriver>
+    driver: Arc<ClientDriver>,
+    auto_reconnect: AutoReconnect,
+
+    /// Maximal amount of the nonces tried to solve
+    /// the servers' proof of work challenges.
+    max_work: u64,
+
+    #[cfg(feature = "retry")]
+    retry_policy: RetryPolicy,
+
+    #[cfg(feature = "timeouts")]
+    timeouts: Timeouts
 }
 
 impl<T: HttpClient + Send + Sync> Client<T> {
@@ -37,7 +67,15 @@ impl<T: HttpClient + Send + Sync> Client<T> {
 
         Self {
             http_client: Arc::new(http_client),
-            driver: Arc::new(client_driver)
+            driver: Arc::new(client_driver),
+            auto_reconnect: AutoReconnect::disabled(),
+            max_work: 1 << 24,
+
+            #[cfg(feature = "retry")]
+            retry_policy: RetryPolicy::none(),
+
+            #[cfg(feature = "timeouts")]
+            timeouts: Timeouts::default()
         }
     }
 
@@ -61,6 +99,149 @@ impl<T: HttpClient + Send + Sync> Client<T> {
         &self.driver
     }
 
+    #[inline]
+    /// Reconnect to the server when it forgets the
+    /// client's session. Refer to `AutoReconnect`.
+    /// 
+    /// Inherited by the connected clients.
+    pub fn with_auto_reconnect(self, auto_reconnect: AutoReconnect) -> Self {
+        Self {
+            auto_reconnect,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn auto_reconnect(&self) -> &AutoReconnect {
+        &self.auto_reconnect
+    }
+
+    #[inline]
+    /// Try at most the given amount of nonces to solve
+    /// the servers' proof of work challenges.
+    /// 
+    /// Connection fails if the challenge is not solved.
+    /// Default is `2^24`, enough for the difficulty of
+    /// about 20 leading zero bits.
+    pub fn with_max_work(self, max_work: u64) -> Self {
+        Self {
+            max_work,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn max_work(&self) -> u64 {
+        self.max_work
+    }
+
+    #[cfg(feature = "retry")]
+    #[inline]
+    /// Retry failed requests using the given policy.
+    /// 
+    /// The policy is inherited by the connected clients.
+    /// Requests are not retried by default.
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "retry")]
+    #[inline]
+    /// Get copy of the middleware retrying failed
+    /// requests using the given policy.
+    /// 
+    /// Used to override the policy for a single call.
+    pub fn retrying(&self, retry_policy: RetryPolicy) -> Self {
+        self.clone().with_retry_policy(retry_policy)
+    }
+
+    #[cfg(feature = "retry")]
+    #[inline]
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    #[cfg(feature = "timeouts")]
+    #[inline]
+    /// Bound requests by the given timeouts.
+    /// 
+    /// Timeouts are inherited by the connected clients.
+    /// Requests are bounded by 30 seconds by default.
+    pub fn with_timeouts(self, timeouts: Timeouts) -> Self {
+        Self {
+            timeouts,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "timeouts")]
+    #[inline]
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    #[inline]
+    /// Run the operation using the retry policy.
+    async fn retry<R, F, Fut>(&self, name: &str, operation: F) -> Result<R, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<R, Error>>
+    {
+        #[cfg(feature = "retry")]
+        {
+            self.retry_policy.run(name, operation).await
+        }
+
+        #[cfg(not(feature = "retry"))]
+        {
+            let mut operation = operation;
+            let _ = name;
+
+            operation().await
+        }
+    }
+
+    /// Run the request to the given URL bounded by the timeouts
+    /// and extended by the given waiting time.
+    async fn timeout<R>(&self, url: &str, wait: std::time::Duration, request: impl std::future::Future<Output = Result<R, Error>>) -> Result<R, Error> {
+        #[cfg(feature = "timeouts")]
+        {
+            self.timeouts.run(url, wait, request).await
+        }
+
+        #[cfg(not(feature = "timeouts"))]
+        {
+            let _ = (url, wait);
+
+            request.await
+        }
+    }
+
+    /// Perform `GET` request bounded by the timeouts.
+    async fn get_request<Resp: AsJson>(&self, url: impl AsRef<str> + Send) -> Result<Resp, Error> {
+        let url = url.as_ref();
+
+        self.timeout(url, std::time::Duration::ZERO, async {
+            Ok(self.http_client.get_request(url).await?)
+        }).await
+    }
+
+    /// Perform `POST` request bounded by the timeouts.
+    async fn post_request<Req, Resp>(&self, url: impl AsRef<str> + Send, request: Req) -> Result<Resp, Error>
+    where
+        Req: AsJson + Send,
+        Resp: AsJson
+    {
+        let url = url.as_ref();
+
+        self.timeout(url, std::time::Duration::ZERO, async {
+            Ok(self.http_client.post_request(url, request).await?)
+        }).await
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
         server_address
     )))]
@@ -75,7 +256,7 @@ impl<T: HttpClient + Send + Sync> Client<T> {
         tracing::debug!("Sending GET /api/v1/info request");
 
         // Send get info request
-        let response = self.http_client.get_request::<InfoResponse>(
+        let response = self.get_request::<InfoResponse>(
             format!("http://{server_address}/api/v1/info")
         ).await?;
 
@@ -87,6 +268,89 @@ impl<T: HttpClient + Send + Sync> Client<T> {
         Ok(response)
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        server_address
+    )))]
+    /// Request features and limits of the server.
+    /// 
+    /// This method will perform `GET /api/v1/info` request.
+    /// 
+    /// Return empty capabilities if the server
+    /// doesn't advertise them.
+    pub async fn get_capabilities(&self, server_address: impl std::fmt::Display) -> Result<ServerCapabilities, Error> {
+        let info = self.get_info(server_address).await?;
+
+        Ok(info.capabilities.unwrap_or_default())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        server_address
+    )))]
+    /// Get statistics of the server.
+    /// 
+    /// This method will perform `GET /api/v1/stats` request.
+    /// 
+    /// - `server_address` must contain address of the server
+    ///   from which we want to request the statistics.
+    pub async fn get_stats(&self, server_address: impl std::fmt::Display) -> Result<StatsResponse, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending GET /api/v1/stats request");
+
+        let response = self.get_request::<StatsResponse>(
+            format!("http://{server_address}/api/v1/stats")
+        ).await?;
+
+        if !response.validate()? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        Ok(response)
+    }
+
+    #[cfg(feature = "mdns")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        server = ?server
+    )))]
+    /// Verify that the server found in the local network
+    /// owns the advertised public key.
+    /// 
+    /// This method will perform `GET /api/v1/info` request.
+    pub async fn verify_discovered(&self, server: &DiscoveredServer) -> Result<bool, Error> {
+        let info = self.get_info(server.socket_address()).await?;
+
+        Ok(info.public_key == server.public_key)
+    }
+
+    #[cfg(feature = "mdns")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        params = ?params
+    )))]
+    /// Find servers in the local network over mDNS.
+    /// 
+    /// Found servers are verified with the `GET /api/v1/info`
+    /// request, and those which failed the verification are
+    /// skipped. Returned records can be used as bootstrap
+    /// servers when no other addresses are known.
+    pub async fn discover_local_servers(&self, params: &DiscoveryParams, timeout: std::time::Duration) -> Result<Vec<ServerApiRecord>, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Discovering local servers");
+
+        let mut servers = Vec::new();
+
+        for server in discover_local_with(params, timeout).await? {
+            match self.verify_discovered(&server).await {
+                Ok(true) => servers.push(ServerApiRecord::new(server.public_key.clone(), server.socket_address())),
+
+                _result => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(?server, ?_result, "Local server verification failed");
+                }
+            }
+        }
+
+        Ok(servers)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
         server_address
     )))]
@@ -101,7 +365,7 @@ impl<T: HttpClient + Send + Sync> Client<T> {
         tracing::debug!("Sending GET /api/v1/clients request");
 
         // Send get clients request
-        let response = self.http_client.get_request::<ClientsResponse>(
+        let response = self.get_request::<ClientsResponse>(
             format!("http://{server_address}/api/v1/clients")
         ).await?;
 
@@ -122,180 +386,1886 @@ impl<T: HttpClient + Send + Sync> Client<T> {
         tracing::debug!("Sending GET /api/v1/servers request");
 
         // Send get servers request
-        let response = self.http_client.get_request::<ServersResponse>(
+        let response = self.get_request::<ServersResponse>(
             format!("http://{server_address}/api/v1/servers")
         ).await?;
 
         Ok(response.servers)
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
-        server_address
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        server_address,
+        ?request
     )))]
-    /// Connect to the server
+    /// Request a page of the local server's clients list.
     /// 
-    /// This method will call `get_info` method to request
-    /// the server's public key and then call `connect_to` method.
+    /// This method will perform `GET /api/v1/clients` request
+    /// with pagination parameters.
     /// 
-    /// - `server_address` must contain address of the server
-    ///   to which we want to connect.
-    pub async fn connect(&self, server_address: impl std::fmt::Display + Clone) -> Result<ConnectedClient<T>, Error> {
-        let server_info = self.get_info(server_address.clone()).await?;
+    /// Returned page contains continuation token
+    /// of the next page if there's one.
+    pub async fn get_clients_page(&self, server_address: impl std::fmt::Display, request: &PageRequest) -> Result<Page<ClientApiRecord>, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending GET /api/v1/clients request");
 
-        self.connect_to(server_address, server_info.public_key).await
+        let response = self.get_request::<ClientsResponse>(
+            format!("http://{server_address}/api/v1/clients{}", request.to_query())
+        ).await?;
+
+        Ok(Page::new(response.clients, response.next))
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
         server_address,
-        server_public = server_public.to_base64()
+        ?client_type,
+        ?request
     )))]
-    /// Connect to the server with expected public key
+    /// Request a page of the local server's clients
+    /// of the given type.
     /// 
-    /// This method will perform `POST /api/v1/connect` request.
+    /// This method will perform `GET /api/v1/clients` request
+    /// with filtering and pagination parameters.
     /// 
-    /// In this method we expect that the given server has
-    /// given public key. We need it to create connection
-    /// certificate.
-    pub async fn connect_to(&self, server_address: impl std::fmt::Display, server_public: PublicKey) -> Result<ConnectedClient<T>, Error> {
+    /// Returned response contains total amount of the
+    /// matching clients if the server supports it.
+    pub async fn get_clients_filtered(&self, server_address: impl std::fmt::Display, client_type: ClientType, request: &PageRequest) -> Result<ClientsResponse, Error> {
         #[cfg(feature = "tracing")]
-        tracing::debug!("Sending POST /api/v1/connect request");
+        tracing::debug!("Sending GET /api/v1/clients request");
 
-        // Prepare connect request
-        let request = ConnectRequest::new(
-            self.driver.secret_key(),
-            server_public.clone(),
-            self.driver.info().clone()
-        );
+        let mut query = request.to_query();
 
-        let proof_seed = request.0.proof_seed;
-        let certificate = request.0.request.certificate.clone();
+        query.push(if query.is_empty() { '?' } else { '&' });
+        query.push_str(&format!("client_type={client_type}"));
 
-        // Send request
-        let response = self.http_client.post_request::<ConnectRequest, ConnectResponse>(
-            format!("http://{server_address}/api/v1/connect"),
-            request
+        let response = self.get_request::<ClientsResponse>(
+            format!("http://{server_address}/api/v1/clients{query}")
         ).await?;
 
-        // Validate response
-        if !response.validate(proof_seed)? {
-            return Err(Error::InvalidProofSeedSignature);
-        }
-
-        // Check response status
-        match response.0 {
-            Response::Success { .. } => {
-                let client = ConnectedClient {
-                    http_client: self.http_client.clone(),
-                    driver: self.driver.clone(),
-                    connected_server: ServerApiRecord {
-                        public_key: server_public,
-                        address: server_address.to_string()
-                    },
-                    connection_certificate: certificate
-                };
+        Ok(response)
+    }
 
-                Ok(client)
-            }
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        server_address,
+        ?request
+    )))]
+    /// Request a page of the servers list known to given server.
+    /// 
+    /// This method will perform `GET /api/v1/servers` request
+    /// with pagination parameters.
+    /// 
+    /// Returned page contains continuation token
+    /// of the next page if there's one.
+    pub async fn get_servers_page(&self, server_address: impl std::fmt::Display, request: &PageRequest) -> Result<Page<ServerApiRecord>, Error> {
+        let response = self.get_servers_paged(server_address, request).await?;
 
-            Response::Error { status, reason, .. } => {
-                Err(Error::RequestFailed {
-                    status,
-                    reason
-                })
-            }
-        }
+        Ok(Page::new(response.servers, response.next))
     }
-}
 
-#[derive(Debug, Clone, Hash)]
-/// Connected client HTTP middleware
-/// 
-/// This struct is used to perform HTTP REST API requests
-/// to the servers from the name of inner client driver.
-/// 
-/// This struct is derived from the `Client` middlewire
-/// and provides advanced methods that depend on connection
-/// certificate.
-pub struct ConnectedClient<T> {
-    http_client: Arc<T>,
-    driver: Arc<ClientDriver>,
-    connected_server: ServerApiRecord,
-    connection_certificate: ConnectionCertificate
-}
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        server_address,
+        ?request
+    )))]
+    /// Request a page of the servers list known to given server.
+    /// 
+    /// This method will perform `GET /api/v1/servers` request
+    /// with pagination parameters.
+    /// 
+    /// Returned response contains total amount of the
+    /// known servers if the server supports it.
+    pub async fn get_servers_paged(&self, server_address: impl std::fmt::Display, request: &PageRequest) -> Result<ServersResponse, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending GET /api/v1/servers request");
 
-impl<T: HttpClient> ConnectedClient<T> {
-    #[inline]
-    pub fn http_client(&self) -> Arc<T> {
-        self.http_client.clone()
-    }
+        let response = self.get_request::<ServersResponse>(
+            format!("http://{server_address}/api/v1/servers{}", request.to_query())
+        ).await?;
 
-    #[inline]
-    pub fn http_client_ref(&self) -> &T {
-        &self.http_client
+        Ok(response)
     }
 
-    #[inline]
-    pub fn driver(&self) -> Arc<ClientDriver> {
-        self.driver.clone()
-    }
+    /// Request all the local server's clients
+    /// page by page.
+    /// 
+    /// Pages are walked using continuation tokens so the
+    /// list stays consistent if it's changed meanwhile.
+    pub async fn walk_clients(&self, server_address: impl std::fmt::Display, page_size: u64) -> Result<Vec<ClientApiRecord>, Error> {
+        let mut request = PageRequest::first(page_size);
+        let mut clients = Vec::new();
 
-    #[inline]
-    pub fn driver_ref(&self) -> &ClientDriver {
-        &self.driver
-    }
+        loop {
+            let page = self.get_clients_page(&server_address, &request).await?;
 
-    #[inline]
-    pub fn connected_server(&self) -> &ServerApiRecord {
-        &self.connected_server
-    }
+            clients.extend(page.items);
 
-    #[inline]
-    pub fn connection_certificate(&self) -> &ConnectionCertificate {
-        &self.connection_certificate
+            match page.next {
+                Some(token) => request = PageRequest::next(token, Some(page_size)),
+                None => return Ok(clients)
+            }
+        }
     }
 
-    /// Construct new `Client` struct from the protocol's paper.
+    /// Request all the servers known to given server
+    /// page by page.
     /// 
-    /// Service function used by other methods in this struct.
-    pub fn get_client(&self) -> ClientApiRecord {
-        ClientApiRecord::new(
-            self.driver.secret_key().public_key(),
-            self.connection_certificate.clone(),
-            self.driver.info().clone()
-        )
+    /// Pages are walked using continuation tokens so the
+    /// list stays consistent if it's changed meanwhile.
+    pub async fn walk_servers(&self, server_address: impl std::fmt::Display, page_size: u64) -> Result<Vec<ServerApiRecord>, Error> {
+        let mut pages = self.servers_pages(server_address, page_size);
+        let mut servers = Vec::new();
+
+        while let Some(page) = pages.next_page().await? {
+            servers.extend(page);
+        }
+
+        Ok(servers)
     }
 
-    /// Get disconnected variant of the client middleware.
+    #[inline]
+    /// Get iterator over the pages of the servers
+    /// list known to given server.
     /// 
-    /// This method doesn't actually close the connection to the server.
-    /// It just constructs a copy of the original client from which
-    /// the current struct was made to perform a different set of methods.
-    pub fn disconnected(&self) -> Client<T> {
-        Client {
-            http_client: self.http_client.clone(),
-            driver: self.driver.clone()
+    /// Pages are requested lazily by the
+    /// `ServersPages::next_page` method.
+    pub fn servers_pages(&self, server_address: impl std::fmt::Display, page_size: u64) -> ServersPages<'_, T> {
+        ServersPages {
+            client: self,
+            server_address: server_address.to_string(),
+            page_size,
+            request: Some(PageRequest::first(page_size)),
+            total: None
         }
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
-    /// Disconnect from the remote server.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        server_address
+    )))]
+    /// Connect to the server
     /// 
-    /// This method will perform `POST /api/v1/disconnect` request.
-    pub async fn disconnect(self) -> Result<Client<T>, Error> {
-        #[cfg(feature = "tracing")]
-        tracing::debug!("Sending POST /api/v1/disconnect request");
-
-        // Prepare disconnect request
-        let request = DisconnectRequest::new(
+    /// This method will call `get_info` method to request
+    /// the server's public key and then call `connect_to` method.
+    /// 
+    /// The highest standard version supported by both the
+    /// server and this library is used by the connected client.
+    /// Legacy servers are expected to support only the version
+    /// of their `GET /api/v1/info` response.
+    /// 
+    /// If the server limits lifetime of the connection
+    /// certificates then the certificate will expire
+    /// after it, and should be renewed by the
+    /// `ConnectedClient::renew` method.
+    /// 
+    /// - `server_address` must contain address of the server
+    ///   to which we want to connect.
+    /// 
+    /// Connection is retried using the middleware's retry policy.
+    pub async fn connect(&self, server_address: impl std::fmt::Display + Clone) -> Result<ConnectedClient<T>, Error> {
+        self.retry("connect", || self.connect_once(server_address.clone())).await
+    }
+
+    async fn connect_once(&self, server_address: impl std::fmt::Display + Clone) -> Result<ConnectedClient<T>, Error> {
+        let server_info = self.get_info(server_address.clone()).await?;
+
+        // Choose the highest standard supported by both sides
+        let server_versions = server_info.supported_versions();
+
+        let Some(standard) = ProtocolVersions::SUPPORTED.negotiate(&server_versions) else {
+            return Err(Error::UnsupportedStandard(server_versions));
+        };
+
+        let request = match server_info.certificate_lifetime {
+            Some(lifetime) => {
+                let certificate = ConnectionCertificate::new_expiring(
+                    self.driver.secret_key(),
+                    server_info.public_key.clone(),
+                    None,
+                    lifetime
+                );
+
+                ConnectRequest(Request::new(
+                    self.driver.secret_key(),
+                    ConnectRequestBody::from_certificate(self.driver.info().clone(), certificate)
+                ))
+            }
+
+            None => ConnectRequest::new(
+                self.driver.secret_key(),
+                server_info.public_key.clone(),
+                self.driver.info().clone()
+            )
+        };
+
+        let difficulty = server_info.connect_difficulty.unwrap_or(0);
+
+        self.send_connect(server_address, server_info.public_key, request.with_standard(standard), difficulty).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        servers = ?servers
+    )))]
+    /// Connect to the first reachable bootstrap server
+    /// 
+    /// Servers are tried in the given order by the `connect`
+    /// method. Only `http://<address>` and raw addresses
+    /// are supported, other ones are skipped.
+    /// 
+    /// Return error of the last tried server if
+    /// none of them accepted the connection.
+    pub async fn connect_bootstrap(&self, servers: &[Address]) -> Result<ConnectedClient<T>, Error> {
+        let mut last_error = None;
+
+        for server_address in servers.iter().filter_map(Address::server_address) {
+            match self.connect(server_address).await {
+                Ok(client) => return Ok(client),
+
+                Err(err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(server_address, "Failed to connect to the bootstrap server: {err}");
+
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| Error::Other("No supported bootstrap servers given".into())))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        servers = ?servers
+    )))]
+    /// Connect to the first reachable server of the
+    /// list and switch to the next ones when it fails.
+    /// 
+    /// Only `http://<address>` and raw addresses
+    /// are supported, other ones are skipped.
+    /// Refer to `FailoverClient` for details.
+    pub async fn connect_failover(&self, servers: &[Address]) -> Result<FailoverClient<T>, Error> {
+        FailoverClient::connect(self.clone(), servers).await
+    }
+
+    #[cfg(feature = "session")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        server_address
+    )))]
+    /// Connect to the server and open the session
+    /// disconnected from the server when dropped.
+    /// 
+    /// This method will perform `GET /api/v1/info` request
+    /// to get the server's capabilities and then connect
+    /// to it using the `connect` method.
+    pub async fn connect_session(&self, server_address: impl std::fmt::Display + Clone) -> Result<ClientSession<T>, Error> where T: 'static {
+        let capabilities = self.get_capabilities(server_address.clone()).await?;
+        let client = self.connect(server_address).await?;
+
+        Ok(ClientSession::new(client, capabilities))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        server_address,
+        server_public = server_public.to_base64()
+    )))]
+    /// Connect to the server with expected public key
+    /// 
+    /// This method will perform `POST /api/v1/connect` request.
+    /// 
+    /// In this method we expect that the given server has
+    /// given public key. We need it to create connection
+    /// certificate.
+    pub async fn connect_to(&self, server_address: impl std::fmt::Display, server_public: PublicKey) -> Result<ConnectedClient<T>, Error> {
+        let request = ConnectRequest::new(
+            self.driver.secret_key(),
+            server_public.clone(),
+            self.driver.info().clone()
+        );
+
+        self.send_connect(server_address, server_public, request, 0).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        server_address,
+        server_public = server_public.to_base64()
+    )))]
+    /// Connect to the server with the connection
+    /// certificate restricted to the given scope.
+    /// 
+    /// This method will perform `POST /api/v1/connect` request.
+    /// 
+    /// Server will reject requests of the connected client
+    /// which are not allowed by the scope.
+    pub async fn connect_scoped(&self, server_address: impl std::fmt::Display, server_public: PublicKey, scope: CertificateScope) -> Result<ConnectedClient<T>, Error> {
+        let request = ConnectRequest::scoped(
+            self.driver.secret_key(),
+            server_public.clone(),
+            self.driver.info().clone(),
+            scope
+        );
+
+        self.send_connect(server_address, server_public, request, 0).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        server_address,
+        server_public = server_public.to_base64(),
+        alias = alias.to_string()
+    )))]
+    /// Connect to the server registering the given alias.
+    /// 
+    /// This method will perform `POST /api/v1/connect` request.
+    /// 
+    /// Other clients can find this client by sending alias
+    /// lookup requests to this server. Server will reject
+    /// the connection if the alias is already taken.
+    pub async fn connect_with_alias(&self, server_address: impl std::fmt::Display, server_public: PublicKey, alias: ClientAlias) -> Result<ConnectedClient<T>, Error> {
+        // Alias must be set before the body is signed
+        let body = ConnectRequestBody::new(
+            self.driver.secret_key(),
+            server_public.clone(),
+            self.driver.info().clone()
+        ).with_alias(alias);
+
+        let request = ConnectRequest(Request::new(self.driver.secret_key(), body));
+
+        self.send_connect(server_address, server_public, request, 0).await
+    }
+
+    /// Solve proof of work challenge of the connect request.
+    /// 
+    /// Nonces are tried in batches, yielding to other
+    /// tasks between them. The request is returned
+    /// as is if the difficulty is 0.
+    /// 
+    /// Refer to `ConnectRequest::solve_work`.
+    pub async fn solve_work(&self, request: ConnectRequest, difficulty: u32) -> Result<ConnectRequest, Error> {
+        const BATCH: u64 = 4096;
+
+        if difficulty == 0 {
+            return Ok(request);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(difficulty, "Solving proof of work challenge");
+
+        let mut start = 0;
+
+        while start < self.max_work {
+            let end = start.saturating_add(BATCH).min(self.max_work);
+
+            if let Some(nonce) = ConnectRequest::find_nonce(&request.0.public_key, request.0.proof_seed, difficulty, start..end) {
+                return Ok(request.with_nonce(self.driver.secret_key(), nonce));
+            }
+
+            yield_now().await;
+
+            start = end;
+        }
+
+        Err(Error::WorkNotSolved {
+            difficulty,
+            iterations: self.max_work
+        })
+    }
+
+    /// Send connect request solving the proof of work
+    /// challenge of the given difficulty.
+    /// 
+    /// If the server requires the work which wasn't
+    /// expected then the request is made again with
+    /// a new proof seed and the advertised difficulty.
+    /// 
+    /// Return the last sent request and its response.
+    async fn post_connect(&self, server_address: &str, request: ConnectRequest, difficulty: u32) -> Result<(ConnectRequest, ConnectResponse), Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/connect request");
+
+        let url = format!("http://{server_address}/api/v1/connect");
+
+        let request = self.solve_work(request, difficulty).await?;
+
+        let response = self.post_request::<ConnectRequest, ConnectResponse>(&url, request.clone()).await?;
+
+        let Response::Error { status: ResponseStatus::InsufficientWork, .. } = &response.0 else {
+            return Ok((request, response));
+        };
+
+        let difficulty = self.get_info(server_address).await?
+            .connect_difficulty
+            .unwrap_or(0);
+
+        if difficulty == 0 {
+            return Ok((request, response));
+        }
+
+        // Used proof seeds are rejected by the replay protection
+        let standard = request.0.standard;
+
+        let request = Request::extended(self.driver.secret_key(), request.0.request, request.0.extensions)
+            .with_standard(standard);
+
+        let request = ConnectRequest(request);
+
+        let request = self.solve_work(request, difficulty).await?;
+
+        let response = self.post_request::<ConnectRequest, ConnectResponse>(&url, request.clone()).await?;
+
+        Ok((request, response))
+    }
+
+    async fn send_connect(&self, server_address: impl std::fmt::Display, server_public: PublicKey, request: ConnectRequest, difficulty: u32) -> Result<ConnectedClient<T>, Error> {
+        let server_address = server_address.to_string();
+
+        // Send request
+        let (request, response) = self.post_connect(&server_address, request, difficulty).await?;
+
+        let standard = request.0.standard;
+        let proof_seed = request.0.proof_seed;
+        let certificate = request.0.request.certificate;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        match response.0 {
+            Response::Success { .. } => {
+                let client = ConnectedClient {
+                    http_client: self.http_client.clone(),
+                    driver: self.driver.clone(),
+                    connected_server: ServerApiRecord {
+                        public_key: server_public,
+                        address: server_address
+                    },
+                    connection_certificate: SharedCertificate::new(certificate),
+                    standard,
+                    sequences: SequenceCounters::default(),
+                    auto_reconnect: self.auto_reconnect.clone(),
+                    max_work: self.max_work,
+
+                    #[cfg(feature = "retry")]
+                    retry_policy: self.retry_policy,
+
+                    #[cfg(feature = "timeouts")]
+                    timeouts: self.timeouts.clone(),
+
+                    #[cfg(feature = "outbox")]
+                    outbox: None
+                };
+
+                Ok(client)
+            }
+
+            Response::Error { status, code, reason, .. } => {
+                Err(Error::RequestFailed {
+                    status,
+                    code,
+                    reason
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Iterator over the pages of the servers
+/// list known to some server.
+/// 
+/// Pages are walked using continuation tokens so the
+/// list stays consistent if it's changed meanwhile.
+/// 
+/// Refer to `Client::servers_pages`.
+pub struct ServersPages<'a, T> {
+    client: &'a Client<T>,
+    server_address: String,
+    page_size: u64,
+    request: Option<PageRequest>,
+    total: Option<u64>
+}
+
+impl<T: HttpClient + Send + Sync> ServersPages<'_, T> {
+    /// Request the next page of the servers list.
+    /// 
+    /// Return `None` when all the pages were returned.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<ServerApiRecord>>, Error> {
+        let Some(request) = self.request.take() else {
+            return Ok(None);
+        };
+
+        let response = self.client.get_servers_paged(&self.server_address, &request).await?;
+
+        self.total = response.total.or(self.total);
+
+        self.request = response.next
+            .map(|token| PageRequest::next(token, Some(self.page_size)));
+
+        Ok(Some(response.servers))
+    }
+
+    #[inline]
+    /// Get total amount of the servers reported
+    /// by the last requested page.
+    /// 
+    /// Return `None` if no pages were requested yet or
+    /// the server doesn't report the total amount.
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+/// Connected client HTTP middleware
+/// 
+/// This struct is used to perform HTTP REST API requests
+/// to the servers from the name of inner client driver.
+/// 
+/// This struct is derived from the `Client` middlewire
+/// and provides advanced methods that depend on connection
+/// certificate.
+pub struct ConnectedClient<T> {
+    http_client: Arc<T>,
+    driver: Arc<ClientDriver>,
+    connected_server: ServerApiRecord,
+    connection_certificate: SharedCertificate,
+
+    /// Standard version negotiated with the server.
+    standard: u64,
+
+    sequences: SequenceCounters,
+    auto_reconnect: AutoReconnect,
+    max_work: u64,
+
+    #[cfg(feature = "retry")]
+    retry_policy: RetryPolicy,
+
+    #[cfg(feature = "timeouts")]
+    timeouts: Timeouts,
+
+    #[cfg(feature = "outbox")]
+    outbox: Option<Outbox>
+}
+
+impl<T: HttpClient> ConnectedClient<T> {
+    #[inline]
+    pub fn http_client(&self) -> Arc<T> {
+        self.http_client.clone()
+    }
+
+    #[inline]
+    pub fn http_client_ref(&self) -> &T {
+        &self.http_client
+    }
+
+    #[inline]
+    pub fn driver(&self) -> Arc<ClientDriver> {
+        self.driver.clone()
+    }
+
+    #[inline]
+    pub fn driver_ref(&self) -> &ClientDriver {
+        &self.driver
+    }
+
+    #[inline]
+    pub fn connected_server(&self) -> &ServerApiRecord {
+        &self.connected_server
+    }
+
+    #[inline]
+    /// Get current connection certificate.
+    /// 
+    /// Certificate is replaced when the
+    /// client is renewed or reconnected.
+    pub fn connection_certificate(&self) -> ConnectionCertificate {
+        self.connection_certificate.get()
+    }
+
+    #[inline]
+    /// Get standard version used by the requests
+    /// to the connected server.
+    pub fn standard(&self) -> u64 {
+        self.standard
+    }
+
+    #[inline]
+    /// Reconnect to the server when it forgets the
+    /// client's session. Refer to `AutoReconnect`.
+    pub fn with_auto_reconnect(self, auto_reconnect: AutoReconnect) -> Self {
+        Self {
+            auto_reconnect,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn auto_reconnect(&self) -> &AutoReconnect {
+        &self.auto_reconnect
+    }
+
+    #[cfg(feature = "retry")]
+    #[inline]
+    /// Retry failed requests using the given policy.
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "retry")]
+    #[inline]
+    /// Get copy of the middleware retrying failed
+    /// requests using the given policy.
+    /// 
+    /// Used to override the policy for a single call.
+    /// The copy shares sequence numbers of the sent messages.
+    pub fn retrying(&self, retry_policy: RetryPolicy) -> Self {
+        self.clone().with_retry_policy(retry_policy)
+    }
+
+    #[cfg(feature = "retry")]
+    #[inline]
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    #[cfg(feature = "timeouts")]
+    #[inline]
+    /// Bound requests by the given timeouts.
+    pub fn with_timeouts(self, timeouts: Timeouts) -> Self {
+        Self {
+            timeouts,
+            ..self
+        }
+    }
+
+    #[cfg(feature = "timeouts")]
+    #[inline]
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    #[cfg(feature = "outbox")]
+    #[inline]
+    /// Queue messages failed to be sent by the `send_to`
+    /// method because of the transient errors.
+    /// 
+    /// Queued messages are sent by the outbox's flush.
+    pub fn with_outbox(self, outbox: Outbox) -> Self {
+        Self {
+            outbox: Some(outbox),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "outbox")]
+    #[inline]
+    pub fn outbox(&self) -> Option<&Outbox> {
+        self.outbox.as_ref()
+    }
+
+    #[inline]
+    /// Run the operation using the retry policy.
+    async fn retry<R, F, Fut>(&self, name: &str, operation: F) -> Result<R, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<R, Error>>
+    {
+        #[cfg(feature = "retry")]
+        {
+            self.retry_policy.run(name, operation).await
+        }
+
+        #[cfg(not(feature = "retry"))]
+        {
+            let mut operation = operation;
+            let _ = name;
+
+            operation().await
+        }
+    }
+
+    /// Run the request to the given URL bounded by the timeouts
+    /// and extended by the given waiting time.
+    async fn timeout<R>(&self, url: &str, wait: std::time::Duration, request: impl std::future::Future<Output = Result<R, Error>>) -> Result<R, Error> {
+        #[cfg(feature = "timeouts")]
+        {
+            self.timeouts.run(url, wait, request).await
+        }
+
+        #[cfg(not(feature = "timeouts"))]
+        {
+            let _ = (url, wait);
+
+            request.await
+        }
+    }
+
+    /// Perform `GET` request bounded by the timeouts.
+    async fn get_request<Resp: AsJson>(&self, url: impl AsRef<str> + Send) -> Result<Resp, Error> {
+        let url = url.as_ref();
+
+        self.timeout(url, std::time::Duration::ZERO, async {
+            Ok(self.http_client.get_request(url).await?)
+        }).await
+    }
+
+    /// Run the operation using the retry policy and replay
+    /// it after reconnection if the server has forgotten
+    /// the client's session.
+    async fn call<R, F, Fut>(&self, name: &str, mut operation: F) -> Result<R, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<R, Error>>
+    {
+        let mut reconnects = 0;
+
+        loop {
+            match self.retry(name, &mut operation).await {
+                Err(err) if reconnects < self.auto_reconnect.max_reconnects && AutoReconnect::is_session_lost(&err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(name, "Server has forgotten the client's session, reconnecting: {err}");
+
+                    reconnects += 1;
+
+                    self.auto_reconnect.notify(ReconnectEvent::Reconnecting);
+
+                    if let Err(err) = self.reconnect().await {
+                        self.auto_reconnect.notify(ReconnectEvent::Failed);
+
+                        return Err(err);
+                    }
+
+                    self.auto_reconnect.notify(ReconnectEvent::Reconnected);
+                }
+
+                result => return result
+            }
+        }
+    }
+
+    #[inline]
+    /// Take next sequence number of the messages
+    /// sent to the receiver's channel.
+    pub fn next_sequence(&self, receiver_public: &PublicKey, channel: impl Into<ChannelName>) -> u64 {
+        self.sequences.next(receiver_public, &channel.into())
+    }
+
+    /// Perform `POST` request made by
+    /// the negotiated standard version.
+    async fn post_request<Req, Resp>(&self, url: impl AsRef<str> + Send, request: Req) -> Result<Resp, Error>
+    where
+        Req: AsJson + VersionedRequest + Send,
+        Resp: AsJson
+    {
+        self.post_request_waiting(url, request, std::time::Duration::ZERO).await
+    }
+
+    /// Perform `POST` request which can be held by
+    /// the server for the given time, e.g. a long poll.
+    async fn post_request_waiting<Req, Resp>(&self, url: impl AsRef<str> + Send, request: Req, wait: std::time::Duration) -> Result<Resp, Error>
+    where
+        Req: AsJson + VersionedRequest + Send,
+        Resp: AsJson
+    {
+        let url = url.as_ref();
+        let request = request.with_standard(ProtocolVersion(self.standard));
+
+        self.timeout(url, wait, async {
+            Ok(self.http_client.post_request(url, request).await?)
+        }).await
+    }
+
+    /// Construct new `Client` struct from the protocol's paper.
+    /// 
+    /// Service function used by other methods in this struct.
+    pub fn get_client(&self) -> ClientApiRecord {
+        ClientApiRecord::new(
+            self.driver.secret_key().public_key(),
+            self.connection_certificate.get(),
+            self.driver.info().clone()
+        )
+    }
+
+    /// Get disconnected variant of the client middleware.
+    /// 
+    /// This method doesn't actually close the connection to the server.
+    /// It just constructs a copy of the original client from which
+    /// the current struct was made to perform a different set of methods.
+    pub fn disconnected(&self) -> Client<T> {
+        Client {
+            http_client: self.http_client.clone(),
+            driver: self.driver.clone(),
+            auto_reconnect: self.auto_reconnect.clone(),
+            max_work: self.max_work,
+
+            #[cfg(feature = "retry")]
+            retry_policy: self.retry_policy,
+
+            #[cfg(feature = "timeouts")]
+            timeouts: self.timeouts.clone()
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    /// Disconnect from the remote server.
+    /// 
+    /// This method will perform `POST /api/v1/disconnect` request.
+    pub async fn disconnect(self) -> Result<Client<T>, Error> {
+        let request = DisconnectRequest::new(
             self.driver.secret_key()
         );
 
+        self.send_disconnect(request).await
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    /// Disconnect from the remote server, removing
+    /// all the messages queued for the client.
+    /// 
+    /// This method will perform `POST /api/v1/disconnect` request.
+    pub async fn disconnect_purging(self) -> Result<Client<T>, Error> {
+        let request = DisconnectRequest::purge_inbox(
+            self.driver.secret_key()
+        );
+
+        self.send_disconnect(request).await
+    }
+
+    async fn send_disconnect(self, request: DisconnectRequest) -> Result<Client<T>, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/disconnect request");
+
+        let proof_seed = request.0.proof_seed;
+
+        // Send request to resolved address
+        // We don't need to resolve our local server's address
+        let response = self.post_request::<DisconnectRequest, DisconnectResponse>(
+            format!("http://{}/api/v1/disconnect", &self.connected_server.address),
+            request
+        ).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        if let Response::Error { status, code, reason, .. } = response.0 {
+            return Err(Error::RequestFailed {
+                status,
+                code,
+                reason
+            });
+        }
+
+        Ok(Client {
+            http_client: self.http_client,
+            driver: self.driver,
+            auto_reconnect: self.auto_reconnect,
+            max_work: self.max_work,
+
+            #[cfg(feature = "retry")]
+            retry_policy: self.retry_policy,
+
+            #[cfg(feature = "timeouts")]
+            timeouts: self.timeouts.clone()
+        })
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    /// Renew connection certificate of the client.
+    /// 
+    /// This method will perform `POST /api/v1/connect` request
+    /// with a fresh certificate with the same scope and lifetime.
+    /// Messages queued for the client are kept by the server.
+    pub async fn renew(&mut self) -> Result<(), Error> {
+        self.reconnect().await
+    }
+
+    /// Connect to the same server again with a fresh
+    /// certificate with the same scope and lifetime.
+    async fn reconnect(&self) -> Result<(), Error> {
+        let certificate = self.connection_certificate.get();
+
+        let lifetime = certificate.expires_at
+            .map(|expires_at| expires_at.saturating_sub(certificate.token.auth_date));
+
+        let scope = certificate.scope;
+        let server_public = self.connected_server.public_key.clone();
+
+        let certificate = match (lifetime, scope) {
+            (Some(lifetime), scope) => ConnectionCertificate::new_expiring(self.driver.secret_key(), server_public, scope, lifetime),
+            (None, Some(scope)) => ConnectionCertificate::new_scoped(self.driver.secret_key(), server_public, scope),
+            (None, None) => ConnectionCertificate::new(self.driver.secret_key(), server_public)
+        };
+
+        let request = ConnectRequest(Request::new(
+            self.driver.secret_key(),
+            ConnectRequestBody::from_certificate(self.driver.info().clone(), certificate.clone())
+        ));
+
+        // Renewals are not challenged, but the server
+        // could forget the client
+        let (request, response) = self.disconnected()
+            .post_connect(&self.connected_server.address, request, 0).await?;
+
+        let proof_seed = request.0.proof_seed;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        if let Response::Error { status, code, reason, .. } = response.0 {
+            return Err(Error::RequestFailed {
+                status,
+                code,
+                reason
+            });
+        }
+
+        self.connection_certificate.set(certificate);
+
+        Ok(())
+    }
+
+    #[inline]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    /// Tell the remote server that the client is still connected.
+    /// 
+    /// This method will perform `POST /api/v1/heartbeat` request.
+    pub async fn heartbeat(&self) -> Result<(), Error> {
+        self.call("heartbeat", || self.send_heartbeat()).await
+    }
+
+    async fn send_heartbeat(&self) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/heartbeat request");
+
+        let request = HeartbeatRequest::new(self.driver.secret_key());
+
+        let proof_seed = request.0.proof_seed;
+
+        let url = format!("http://{}/api/v1/heartbeat", &self.connected_server.address);
+
+        let response = self.timeout(&url, std::time::Duration::ZERO, async {
+            Ok(self.http_client.post_request::<HeartbeatRequest, HeartbeatResponse>(&url, request).await?)
+        }).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        if let Response::Error { status, code, reason, .. } = response.0 {
+            return Err(Error::RequestFailed {
+                status,
+                code,
+                reason
+            });
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "heartbeat")]
+    /// Spawn background task sending heartbeats
+    /// to the remote server with given interval.
+    /// 
+    /// The first heartbeat is sent immediately.
+    /// Failed heartbeats are logged and ignored.
+    /// Drop the returned handle to stop the task.
+    pub fn spawn_heartbeat(&self, interval: std::time::Duration) -> HeartbeatHandle where T: 'static {
+        let client = self.clone();
+
+        HeartbeatHandle(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+
+            loop {
+                interval.tick().await;
+
+                if let Err(_err) = client.send_heartbeat().await {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(server = client.connected_server.address, "Failed to send heartbeat: {_err}");
+                }
+            }
+        }))
+    }
+
+    #[cfg(feature = "subscribe")]
+    /// Spawn background task polling messages of the
+    /// channel from the connected server's inbox and
+    /// calling the handler with each of them.
+    /// 
+    /// Messages are decrypted and verified before they're
+    /// handled. Next messages are polled only when the
+    /// handler has finished with the previous ones, so
+    /// slow handlers leave them in the server's inbox.
+    /// 
+    /// Failed polls and unreadable messages are reported
+    /// by the returned handle. Drop or cancel the handle
+    /// to stop the task.
+    /// 
+    /// Subscriptions to different channels are
+    /// independent. Subscriptions to the same channel
+    /// share its messages.
+    pub fn subscribe<F, Fut>(&self, channel: impl Into<ChannelName>, params: SubscriptionParams, handler: F) -> Subscription
+    where
+        T: 'static,
+        F: Fn(SubscriptionMessage) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send
+    {
+        Subscription::spawn(self.clone(), channel.into(), params, handler)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        server
+    )))]
+    /// Announce remote server server about yourself.
+    /// 
+    /// This method will perform `POST /api/v1/announce` request.
+    /// 
+    /// - `server` should contain address of the server
+    ///   you want to announce about the current client.
+    pub async fn announce(&self, server: impl AsRef<str>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/announce request");
+
+        // Prepare announce request
+        let request = AnnounceRequest::client(
+            self.driver.secret_key(),
+            self.get_client(),
+            self.connected_server.clone()
+        );
+
+        let proof_seed = request.0.proof_seed;
+
+        // Send request to resolved address
+        let response = self.post_request::<AnnounceRequest, AnnounceResponse>(
+            format!("{}/api/v1/announce", resolve_uri(server, self).await?),
+            request
+        ).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        if let Response::Error { status, code, reason, .. } = response.0 {
+            return Err(Error::RequestFailed {
+                status,
+                code,
+                reason
+            });
+        }
+
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        client_public = client_public.to_base64(),
+        client_type = ?client_type
+    )))]
+    /// Lookup given client.
+    /// 
+    /// This method will perform `POST /api/v1/lookup` request.
+    /// 
+    /// - `client_public` must be a public key of the client
+    ///   you need to find.
+    /// 
+    /// - `client_type` is an optional filter of the type
+    ///   of the client you need to find.
+    /// 
+    /// This method will return a tuple of client and server
+    /// info and whether the client is available. Availability
+    /// of the client is decided by the server from which you
+    /// have obtained this info. You should not use this info
+    /// if you don't trust this server.
+    /// 
+    /// This method will keep requesting servers until no more
+    /// hints returned or needed client is found. Hints of every
+    /// server are requested in order of their scores.
+    /// 
+    /// Lookup is retried using the middleware's retry policy.
+    pub async fn lookup(&self, client_public: PublicKey, client_type: Option<ClientType>) -> Result<Option<(ClientApiRecord, ServerApiRecord, bool)>, Error> {
+        self.retry("lookup", || self.lookup_once(client_public.clone(), client_type)).await
+    }
+
+    async fn lookup_once(&self, client_public: PublicKey, client_type: Option<ClientType>) -> Result<Option<(ClientApiRecord, ServerApiRecord, bool)>, Error> {
+        // Prepare lookup request
+        let request = LookupRequest::new(self.driver.secret_key(), client_public, client_type);
+
+        let proof_seed = request.0.proof_seed;
+
+        // Queue of search hints
+        // We don't need to resolve our local server's address
+        let mut queue = VecDeque::from([
+            self.connected_server.address.clone()
+        ]);
+
+        // Store used servers to prevent infinite lookup loops
+        let mut used_servers = HashSet::new();
+
+        while let Some(server_address) = queue.pop_front() {
+            // Skip server if it was already used
+            if used_servers.contains(&server_address) {
+                continue;
+            }
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(server_address, "Sending POST /api/v1/lookup request");
+
+            // Send lookup request
+            let response = self.post_request::<LookupRequest, LookupResponse>(
+                // FIXME: causes some weird ass issue with "infinite async recursion"
+                format!("http://{server_address}/api/v1/lookup"), // resolve_uri(&server_address, self).await?
+                request.clone()
+            ).await?;
+
+            // Validate response
+            if !response.validate(proof_seed)? {
+                // Skip execution and go to the next server
+                continue;
+            }
+
+            // Process successful response
+            if let Response::Success { public_key, response, .. } = response.0 {
+                match response {
+                    LookupResponseBody::Local { client, available } => {
+                        let server = ServerApiRecord::new(public_key, &server_address);
+
+                        return Ok(Some((client, server, available)));
+                    }
+
+                    LookupResponseBody::Remote { client, server, available } => {
+                        return Ok(Some((client, server, available)));
+                    }
+
+                    LookupResponseBody::Hint { mut servers } => {
+                        // Try the best scored hints first
+                        servers.sort_by_key(|hint| std::cmp::Reverse(hint.score));
+
+                        for hint in servers.drain(..) {
+                            queue.push_back(hint.server.address);
+                        }
+                    }
+                }
+            }
+
+            used_servers.insert(server_address);
+        }
+
+        Ok(None)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        server_address,
+        alias = alias.to_string()
+    )))]
+    /// Find client by its alias.
+    /// 
+    /// This method will perform `POST /api/v1/lookup` request.
+    /// 
+    /// - `server_address` must contain address of the server
+    ///   on which the needed client registered its alias.
+    /// 
+    /// - `alias` must contain alias of the needed client.
+    /// 
+    /// - `client_type` is an optional filter of the type
+    ///   of the client you need to find.
+    /// 
+    /// Return `None` if there's no connected client
+    /// with this alias. Refer to `lookup` method.
+    pub async fn lookup_alias(&self, server_address: impl std::fmt::Display, alias: ClientAlias, client_type: Option<ClientType>) -> Result<Option<(ClientApiRecord, ServerApiRecord, bool)>, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/lookup request");
+
+        let request = LookupRequest::alias(self.driver.secret_key(), alias, client_type);
+
+        let proof_seed = request.0.proof_seed;
+
+        let response = self.post_request::<LookupRequest, LookupResponse>(
+            format!("http://{server_address}/api/v1/lookup"),
+            request
+        ).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        match response.0 {
+            Response::Success { public_key, response: LookupResponseBody::Local { client, available }, .. } => {
+                Ok(Some((client, ServerApiRecord::new(public_key, server_address), available)))
+            }
+
+            Response::Success { response: LookupResponseBody::Remote { client, server, available }, .. } => {
+                Ok(Some((client, server, available)))
+            }
+
+            Response::Success { .. } => Ok(None),
+
+            Response::Error { status: ResponseStatus::ClientNotFound, .. } => Ok(None),
+
+            Response::Error { status, code, reason, .. } => Err(Error::RequestFailed {
+                status,
+                code,
+                reason
+            })
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        server_address,
+        entries = clients.len()
+    )))]
+    /// Search multiple clients in the routing table
+    /// of the given server.
+    /// 
+    /// This method will perform `POST /api/v1/lookup_batch` request.
+    /// 
+    /// - `server_address` must contain address of the requested server.
+    /// 
+    /// - `clients` must contain public keys of the needed
+    ///   clients and optional filters of their types.
+    /// 
+    /// Return results of the clients in the given order.
+    /// Unlike `lookup` method, hints are not followed.
+    pub async fn lookup_batch(&self, server_address: impl std::fmt::Display, clients: Vec<(PublicKey, Option<ClientType>)>) -> Result<Vec<LookupBatchResult>, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/lookup_batch request");
+
+        let request = LookupBatchRequest::new(self.driver.secret_key(), clients);
+
+        let proof_seed = request.0.proof_seed;
+
+        let response = self.post_request::<LookupBatchRequest, LookupBatchResponse>(
+            format!("http://{server_address}/api/v1/lookup_batch"),
+            request
+        ).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        match response.0 {
+            Response::Success { response, .. } => Ok(response.results),
+
+            Response::Error { status, code, reason, .. } => Err(Error::RequestFailed {
+                status,
+                code,
+                reason
+            })
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        receiver_server,
+        receiver_public = receiver_public.to_base64(),
+        channel = channel.to_string(),
+        message = format!("{}: {}", message.encoding, message.content)
+    )))]
+    /// Send a message to remote client.
+    /// 
+    /// This method will perform `POST /api/v1/send` request.
+    /// 
+    /// - `receiver_server` must contain address of the server to which
+    ///   the receiver is connected (from which it polls the messages).
+    /// 
+    /// - `receiver_public` must be a public key of the message receiver.
+    /// 
+    /// - `channel` must be a string name of the channel to which
+    ///   we want to send this message. Channels allow to differ
+    ///   messages streams for different applications and their
+    ///   parts (modules).
+    /// 
+    /// - `message` should contain the message you want to send.
+    /// 
+    /// Return id assigned to the message by the receiver's server
+    /// if it supports message ids and the message wasn't dropped
+    /// as a duplicate.
+    /// 
+    /// Request is retried using the middleware's retry policy.
+    pub async fn send(&self, receiver_server: impl AsRef<str>, receiver_public: PublicKey, channel: impl ToString, message: Message) -> Result<Option<u64>, Error> {
+        self.send_message(receiver_server, receiver_public, channel, message, false).await
+    }
+
+    #[inline]
+    /// Send a message to remote client and request
+    /// its delivery receipts.
+    /// 
+    /// Receipts are stored by the receiver's server
+    /// and can be read by the `poll_receipts` method
+    /// if the receiver is connected to the same server.
+    /// 
+    /// Refer to `send` and `DeliveryReceipt` for details.
+    pub async fn send_with_receipt(&self, receiver_server: impl AsRef<str>, receiver_public: PublicKey, channel: impl ToString, message: Message) -> Result<Option<u64>, Error> {
+        self.send_message(receiver_server, receiver_public, channel, message, true).await
+    }
+
+    async fn send_message(&self, receiver_server: impl AsRef<str>, receiver_public: PublicKey, channel: impl ToString, message: Message, receipt: bool) -> Result<Option<u64>, Error> {
+        let receiver_server = receiver_server.as_ref();
+        let channel = ChannelName::new(channel.to_string())?;
+
+        self.call("send", || {
+            self.send_message_once(receiver_server, receiver_public.clone(), &channel, message.clone(), receipt)
+        }).await
+    }
+
+    async fn send_message_once(&self, receiver_server: &str, receiver_public: PublicKey, channel: &ChannelName, message: Message, receipt: bool) -> Result<Option<u64>, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/send request");
+
+        // Prepare send message request
+        let client = ClientApiRecord::new(
+            self.driver.secret_key().public_key(),
+            self.connection_certificate.get(),
+            ClientInfo::thin()
+        );
+
+        let sender = Sender::new(client, self.connected_server.clone());
+
+        let request = SendRequest(Request::new(
+            self.driver.secret_key(),
+            SendRequestBody::new(sender, receiver_public, channel, message)
+                .with_receipt(receipt)
+        ));
+
+        let proof_seed = request.0.proof_seed;
+
+        // Send request
+        let response = self.post_request::<SendRequest, SendResponse>(
+            format!("{}/api/v1/send", resolve_uri(receiver_server, self).await?),
+            request
+        ).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        match response.0 {
+            Response::Success { response, .. } => Ok(response.id),
+
+            Response::Error { status, code, reason, .. } => Err(Error::RequestFailed {
+                status,
+                code,
+                reason
+            })
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        receiver_server = receiver_server.as_ref(),
+        entries = messages.len()
+    )))]
+    /// Send multiple messages to remote clients
+    /// connected to the same server.
+    /// 
+    /// This method will perform `POST /api/v1/send_batch` request.
+    /// 
+    /// - `messages` must contain receivers' public keys,
+    ///   channels and the messages themselves.
+    /// 
+    /// Return results of the messages in the given order.
+    /// Rejected messages don't fail the whole request.
+    pub async fn send_batch(
+        &self,
+        receiver_server: impl AsRef<str>,
+        messages: Vec<(PublicKey, ChannelName, Message)>
+    ) -> Result<Vec<SendBatchResult>, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/send_batch request");
+
+        // Prepare send batch request
+        let client = ClientApiRecord::new(
+            self.driver.secret_key().public_key(),
+            self.connection_certificate.get(),
+            ClientInfo::thin()
+        );
+
+        let sender = Sender::new(client, self.connected_server.clone());
+
+        let entries = messages.into_iter()
+            .map(|(receiver_public, channel, message)| SendRequestBody::new(sender.clone(), receiver_public, channel, message))
+            .collect::<Vec<_>>();
+
+        let request = SendBatchRequest::new(self.driver.secret_key(), entries);
+
         let proof_seed = request.0.proof_seed;
 
-        // Send request to resolved address
+        // Send request
+        let response = self.post_request::<SendBatchRequest, SendBatchResponse>(
+            format!("{}/api/v1/send_batch", resolve_uri(receiver_server, self).await?),
+            request
+        ).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        match response.0 {
+            Response::Success { response, .. } => Ok(response.results),
+
+            Response::Error { status, code, reason, .. } => Err(Error::RequestFailed {
+                status,
+                code,
+                reason
+            })
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(
+        receiver_server = receiver_server.as_ref(),
+        entries = messages.len()
+    )))]
+    /// Send multiple messages to remote clients
+    /// connected to the same server.
+    /// 
+    /// Messages are sent by the `POST /api/v1/send_batch`
+    /// requests of the allowed size if the receiver's server
+    /// advertises their support, or one by one by the
+    /// `POST /api/v1/send` requests otherwise.
+    /// 
+    /// Return results of the messages in the given order.
+    /// Rejected messages don't fail the whole sending.
+    pub async fn send_many(
+        &self,
+        receiver_server: impl AsRef<str>,
+        mut messages: Vec<(PublicKey, ChannelName, Message)>
+    ) -> Result<Vec<SendBatchResult>, Error> {
+        let receiver_server = resolve_uri(receiver_server, self).await?;
+
+        let info = self.get_request::<InfoResponse>(
+            format!("{receiver_server}/api/v1/info")
+        ).await?;
+
+        if !info.validate()? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        let capabilities = info.capabilities.unwrap_or_default();
+
+        let mut results = Vec::with_capacity(messages.len());
+
+        if capabilities.send_batch() {
+            let batch_size = capabilities.max_batch_size()
+                .map(|size| size as usize)
+                .filter(|size| *size > 0)
+                .unwrap_or(messages.len())
+                .max(1);
+
+            while !messages.is_empty() {
+                let batch = messages.drain(..batch_size.min(messages.len())).collect();
+
+                results.extend(self.send_batch(&receiver_server, batch).await?);
+            }
+
+            return Ok(results);
+        }
+
+        // Fall back to sequential sends for legacy servers
+        for (receiver_public, channel, message) in messages {
+            let result = match self.send(&receiver_server, receiver_public, channel, message).await {
+                Ok(id) => SendBatchResult::Sent { id },
+
+                Err(Error::RequestFailed { status, reason, .. }) => SendBatchResult::rejected(status, reason),
+
+                Err(err) => return Err(err)
+            };
+
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    /// Send a sequenced message to remote client.
+    /// 
+    /// Message is created from the given data with the next
+    /// sequence number of the (receiver, channel) stream
+    /// so the receiver can restore the messages order.
+    /// 
+    /// Return sequence number of the sent message.
+    pub async fn send_sequenced(
+        &self,
+        receiver_server: impl AsRef<str>,
+        receiver_public: PublicKey,
+        channel: impl ToString,
+        data: impl AsRef<[u8]>,
+        encoding: MessageEncoding,
+        level: CompressionLevel
+    ) -> Result<u64, Error> {
+        let channel = ChannelName::new(channel.to_string())?;
+
+        let sequence = self.next_sequence(&receiver_public, &channel);
+
+        let message = Message::create_sequenced(
+            self.driver.secret_key(),
+            &receiver_public,
+            data,
+            sequence,
+            encoding,
+            level
+        ).map_err(|err| Error::Other(Box::new(err)))?;
+
+        self.send(receiver_server, receiver_public, channel, message).await?;
+
+        Ok(sequence)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        channel = channel.to_string()
+    )))]
+    /// Find the receiver and send it a message.
+    /// 
+    /// - `target` must contain the receiver's public key
+    ///   or its hyperborea client address.
+    /// 
+    /// - `payload` must contain data of the message. It's signed
+    ///   and encrypted by the client's secret key.
+    /// 
+    /// Receiver's server is found by the `lookup` method
+    /// which follows the servers' hints, and then the
+    /// message is sent to it by the `send` method.
+    /// 
+    /// Return the receiver and the server which
+    /// accepted the message.
+    /// 
+    /// If the client has an outbox, messages failed to be
+    /// sent because of the transient errors are queued and
+    /// the `SendToError::Queued` error is returned.
+    pub async fn send_to(
+        &self,
+        target: impl Into<Address>,
+        channel: impl ToString,
+        payload: impl AsRef<[u8]>,
+        encoding: MessageEncoding
+    ) -> Result<SendToResult, SendToError> {
+        let (receiver_public, client_type) = match target.into() {
+            Address::Hyperborea { public_key, client_type } => (public_key, client_type),
+
+            address => return Err(SendToError::InvalidTarget(address))
+        };
+
+        let channel = ChannelName::new(channel.to_string())?;
+
+        let message = Message::create(
+            self.driver.secret_key(),
+            &receiver_public,
+            payload,
+            encoding,
+            CompressionLevel::default()
+        )?;
+
+        #[cfg(not(feature = "outbox"))]
+        {
+            self.send_to_receiver(receiver_public, client_type, channel, message).await
+        }
+
+        #[cfg(feature = "outbox")]
+        {
+            let result = self.send_to_receiver(receiver_public.clone(), client_type, channel.clone(), message.clone()).await;
+
+            let Some(outbox) = &self.outbox else {
+                return result;
+            };
+
+            match result {
+                Err(err) if err.source_error().is_some_and(|source| outbox.is_transient(source)) => {
+                    let sender = Sender::new(self.get_client(), self.connected_server.clone());
+
+                    match outbox.enqueue(sender, receiver_public, channel, message).await {
+                        Ok(()) => Err(SendToError::Queued(Box::new(err))),
+
+                        Err(outbox_err) => Err(SendToError::QueueFailed {
+                            source: Box::new(err),
+                            outbox: outbox_err
+                        })
+                    }
+                }
+
+                result => result
+            }
+        }
+    }
+
+    async fn send_to_receiver(&self, receiver_public: PublicKey, client_type: Option<ClientType>, channel: ChannelName, message: Message) -> Result<SendToResult, SendToError> {
+        // Find the receiver's server
+        let (receiver, server, available) = self.lookup(receiver_public.clone(), client_type).await
+            .map_err(SendToError::LookupFailed)?
+            .ok_or(SendToError::ReceiverNotFound(receiver_public))?;
+
+        let result = self.send(
+            format!("http://{}", server.address),
+            receiver.public_key.clone(),
+            channel,
+            message
+        ).await;
+
+        match result {
+            Ok(id) => Ok(SendToResult {
+                receiver,
+                server,
+                available,
+                id
+            }),
+
+            Err(err) => Err(SendToError::SendFailed {
+                server,
+                source: err
+            })
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
+        channel = channel.to_string(),
+        limit
+    )))]
+    /// Poll (read and delete) messages from the server's inbox.
+    /// 
+    /// This method will perform `POST /api/v1/poll` request.
+    /// 
+    /// - `channel` must contain string name of the messages channel.
+    /// 
+    /// - `limit` should contain amount of messages you want to poll,
+    ///   or `None` if all the stored. This param is used by the
+    ///   remote server which may not respect it, so you should
+    ///   consider implementing few manual checks if you don't trust
+    ///   the server you connected to.
+    /// 
+    /// This method will return vector of polled messages and
+    /// amount of remaining messages in the server's inbox.
+    /// 
+    /// Request is retried using the middleware's retry policy.
+    pub async fn poll(&self, channel: impl ToString, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
+        let channel = ChannelName::new(channel.to_string())?;
+
+        self.call("poll", || self.poll_once(PollRequestBody::new(&channel, limit))).await
+    }
+
+    /// Poll messages from the connected server's inbox,
+    /// waiting up to the given time for new messages
+    /// if there are none yet.
+    /// 
+    /// Server responds as soon as a message arrives, and
+    /// bounds the waiting time by its own maximum. Servers
+    /// without long polls support respond immediately.
+    /// 
+    /// HTTP client's timeout should be longer than the
+    /// waiting time. Timed out requests are reported as
+    /// empty polls rather than failed ones, and aren't
+    /// retried.
+    pub async fn poll_wait(&self, channel: impl ToString, limit: Option<u64>, wait: std::time::Duration) -> Result<(Vec<MessageInfo>, u64), Error> {
+        let channel = ChannelName::new(channel.to_string())?;
+
+        self.call("poll_wait", || async {
+            let body = PollRequestBody::new(&channel, limit)
+                .with_wait(wait);
+
+            match self.poll_once(body).await {
+                Err(Error::Other(err)) if self.http_client.is_timeout(err.as_ref()) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(?wait, "Long poll request timed out");
+
+                    Ok((Vec::new(), 0))
+                }
+
+                result => result
+            }
+        }).await
+    }
+
+    async fn poll_once(&self, body: PollRequestBody) -> Result<(Vec<MessageInfo>, u64), Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/poll request");
+
+        // Long polls are held by the server up to the waiting time
+        let wait = std::time::Duration::from_millis(body.wait_ms.unwrap_or(0));
+
+        // Prepare poll request
+        let request = PollRequest(Request::new(self.driver.secret_key(), body));
+
+        let proof_seed = request.0.proof_seed;
+
+        // Send request
         // We don't need to resolve our local server's address
-        let response = self.http_client.post_request::<DisconnectRequest, DisconnectResponse>(
-            format!("http://{}/api/v1/disconnect", &self.connected_server.address),
+        let response = self.post_request_waiting::<PollRequest, PollResponse>(
+            format!("http://{}/api/v1/poll", &self.connected_server.address),
+            request,
+            wait
+        ).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        match response.0 {
+            Response::Success { response, .. } => {
+                Ok((response.messages, response.remaining))
+            }
+
+            Response::Error { status, code, reason, .. } => {
+                Err(Error::RequestFailed {
+                    status,
+                    code,
+                    reason
+                })
+            }
+        }
+    }
+
+    /// Poll delivery receipts of the sent messages
+    /// from the connected server's inbox.
+    /// 
+    /// Receipts not signed by the server
+    /// which sent them are rejected.
+    /// 
+    /// Refer to `send_with_receipt`.
+    pub async fn poll_receipts(&self, limit: Option<u64>) -> Result<(Vec<DeliveryReceipt>, u64), Error> {
+        let (messages, remaining) = self.poll(DeliveryReceipt::CHANNEL, limit).await?;
+
+        let receipts = messages.iter()
+            .map(|message| DeliveryReceipt::read(message, self.driver.secret_key()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::Other(Box::new(err)))?;
+
+        Ok((receipts, remaining))
+    }
+
+    /// Poll messages from all the channels
+    /// matched by the pattern.
+    /// 
+    /// Pattern ending with `*` matches all the channels
+    /// starting with the rest of it, e.g. `chat/*`.
+    /// Polled messages are ordered by their receiving time,
+    /// and their `channel` field contains name of the channel
+    /// they were sent to.
+    pub async fn poll_wildcard(&self, pattern: impl ToString, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
+        let response = self.poll_wildcard_body(pattern, limit).await?;
+
+        Ok((response.messages, response.remaining))
+    }
+
+    /// Poll messages from all the channels
+    /// matched by the pattern.
+    /// 
+    /// Same as `poll_wildcard`, but return amounts of
+    /// remaining messages of every matched channel instead
+    /// of the total one, so the next poll can be prioritized.
+    /// Channels without remaining messages are not listed.
+    pub async fn poll_wildcard_by_channel(&self, pattern: impl ToString, limit: Option<u64>) -> Result<(Vec<MessageInfo>, BTreeMap<String, u64>), Error> {
+        let response = self.poll_wildcard_body(pattern, limit).await?;
+
+        Ok((response.messages, response.remaining_by_channel))
+    }
+
+    async fn poll_wildcard_body(&self, pattern: impl ToString, limit: Option<u64>) -> Result<PollResponseBody, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending wildcard POST /api/v1/poll request");
+
+        // Prepare poll request
+        let request = PollRequest::wildcard(self.driver.secret_key(), ChannelName::new(pattern.to_string())?, limit);
+
+        let proof_seed = request.0.proof_seed;
+
+        // Send request
+        let response = self.post_request::<PollRequest, PollResponse>(
+            format!("http://{}/api/v1/poll", &self.connected_server.address),
+            request
+        ).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        match response.0 {
+            Response::Success { response, .. } => Ok(response),
+
+            Response::Error { status, code, reason, .. } => {
+                Err(Error::RequestFailed {
+                    status,
+                    code,
+                    reason
+                })
+            }
+        }
+    }
+
+    /// Poll messages sent by the given client only.
+    /// 
+    /// Messages of other senders stay in the server's
+    /// inbox and are counted in the remaining amount.
+    pub async fn poll_from(&self, channel: impl ToString, sender: PublicKey, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending filtered POST /api/v1/poll request");
+
+        // Prepare poll request
+        let request = PollRequest(Request::new(
+            self.driver.secret_key(),
+            PollRequestBody::new(ChannelName::new(channel.to_string())?, limit).with_sender(sender)
+        ));
+
+        let proof_seed = request.0.proof_seed;
+
+        // Send request
+        let response = self.post_request::<PollRequest, PollResponse>(
+            format!("http://{}/api/v1/poll", &self.connected_server.address),
+            request
+        ).await?;
+
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
+
+        // Check response status
+        match response.0 {
+            Response::Success { response, .. } => {
+                Ok((response.messages, response.remaining))
+            }
+
+            Response::Error { status, code, reason, .. } => {
+                Err(Error::RequestFailed {
+                    status,
+                    code,
+                    reason
+                })
+            }
+        }
+    }
+
+    /// Poll messages received within the given
+    /// range of unix timestamps only.
+    /// 
+    /// Messages outside of the range stay in the server's
+    /// inbox and are counted in the remaining amount.
+    pub async fn poll_range(&self, channel: impl ToString, range: Range<u64>, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?range, "Sending filtered POST /api/v1/poll request");
+
+        // Prepare poll request
+        let request = PollRequest(Request::new(
+            self.driver.secret_key(),
+            PollRequestBody::new(ChannelName::new(channel.to_string())?, limit)
+                .with_after(range.start)
+                .with_before(range.end)
+        ));
+
+        let proof_seed = request.0.proof_seed;
+
+        // Send request
+        let response = self.post_request::<PollRequest, PollResponse>(
+            format!("http://{}/api/v1/poll", &self.connected_server.address),
             request
         ).await?;
 
@@ -305,44 +2275,38 @@ impl<T: HttpClient> ConnectedClient<T> {
         }
 
         // Check response status
-        if let Response::Error { status, reason, .. } = response.0 {
-            return Err(Error::RequestFailed {
-                status,
-                reason
-            });
-        }
+        match response.0 {
+            Response::Success { response, .. } => {
+                Ok((response.messages, response.remaining))
+            }
 
-        Ok(Client {
-            http_client: self.http_client,
-            driver: self.driver
-        })
+            Response::Error { status, code, reason, .. } => {
+                Err(Error::RequestFailed {
+                    status,
+                    code,
+                    reason
+                })
+            }
+        }
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
-        server
-    )))]
-    /// Announce remote server server about yourself.
-    /// 
-    /// This method will perform `POST /api/v1/announce` request.
+    /// List channels of the connected server's inbox
+    /// which have pending messages for this client.
     /// 
-    /// - `server` should contain address of the server
-    ///   you want to announce about the current client.
-    pub async fn announce(&self, server: impl AsRef<str>) -> Result<(), Error> {
+    /// This method will perform `POST /api/v1/channels` request
+    /// and return channel names with amounts of their messages.
+    pub async fn list_channels(&self) -> Result<Vec<(ChannelName, u64)>, Error> {
         #[cfg(feature = "tracing")]
-        tracing::debug!("Sending POST /api/v1/announce request");
+        tracing::debug!("Sending POST /api/v1/channels request");
 
-        // Prepare announce request
-        let request = AnnounceRequest::client(
-            self.driver.secret_key(),
-            self.get_client(),
-            self.connected_server.clone()
-        );
+        // Prepare channels request
+        let request = ChannelsRequest::new(self.driver.secret_key());
 
         let proof_seed = request.0.proof_seed;
 
-        // Send request to resolved address
-        let response = self.http_client.post_request::<AnnounceRequest, AnnounceResponse>(
-            format!("{}/api/v1/announce", resolve_uri(server, self).await?),
+        // Send request
+        let response = self.post_request::<ChannelsRequest, ChannelsResponse>(
+            format!("http://{}/api/v1/channels", &self.connected_server.address),
             request
         ).await?;
 
@@ -352,149 +2316,78 @@ impl<T: HttpClient> ConnectedClient<T> {
         }
 
         // Check response status
-        if let Response::Error { status, reason, .. } = response.0 {
-            return Err(Error::RequestFailed {
-                status,
-                reason
-            });
-        }
+        match response.0 {
+            Response::Success { response, .. } => Ok(response.channels),
 
-        Ok(())
+            Response::Error { status, code, reason, .. } => {
+                Err(Error::RequestFailed {
+                    status,
+                    code,
+                    reason
+                })
+            }
+        }
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
-        client_public = client_public.to_base64(),
-        client_type = ?client_type
-    )))]
-    /// Lookup given client.
-    /// 
-    /// This method will perform `POST /api/v1/lookup` request.
-    /// 
-    /// - `client_public` must be a public key of the client
-    ///   you need to find.
+    /// Acknowledge messages leased by the connected
+    /// server's inbox so they're removed from it.
     /// 
-    /// - `client_type` is an optional filter of the type
-    ///   of the client you need to find.
-    /// 
-    /// This method will return a tuple of client and server
-    /// info and whether the client is available. Availability
-    /// of the client is decided by the server from which you
-    /// have obtained this info. You should not use this info
-    /// if you don't trust this server.
+    /// Messages are leased when the server has the
+    /// `poll_lease` param set, and have their `id` set.
     /// 
-    /// This method will keep requesting servers until no more
-    /// hints returned or needed client is found.
-    pub async fn lookup(&self, client_public: PublicKey, client_type: Option<ClientType>) -> Result<Option<(ClientApiRecord, ServerApiRecord, bool)>, Error> {
-        // Prepare lookup request
-        let request = LookupRequest::new(self.driver.secret_key(), client_public, client_type);
-
-        let proof_seed = request.0.proof_seed;
-
-        // Queue of search hints
-        // We don't need to resolve our local server's address
-        let mut queue = VecDeque::from([
-            self.connected_server.address.clone()
-        ]);
-
-        // Store used servers to prevent infinite lookup loops
-        let mut used_servers = HashSet::new();
-
-        while let Some(server_address) = queue.pop_front() {
-            // Skip server if it was already used
-            if used_servers.contains(&server_address) {
-                continue;
-            }
-
-            #[cfg(feature = "tracing")]
-            tracing::debug!(server_address, "Sending POST /api/v1/lookup request");
+    /// This method will perform `POST /api/v1/ack` request
+    /// and return amount of removed messages.
+    pub async fn ack(&self, ids: impl IntoIterator<Item = u64>) -> Result<u64, Error> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Sending POST /api/v1/ack request");
 
-            // Send lookup request
-            let response = self.http_client.post_request::<LookupRequest, LookupResponse>(
-                // FIXME: causes some weird ass issue with "infinite async recursion"
-                format!("http://{server_address}/api/v1/lookup"), // resolve_uri(&server_address, self).await?
-                request.clone()
-            ).await?;
+        // Prepare ack request
+        let request = AckRequest::new(self.driver.secret_key(), ids);
 
-            // Validate response
-            if !response.validate(proof_seed)? {
-                // Skip execution and go to the next server
-                continue;
-            }
+        let proof_seed = request.0.proof_seed;
 
-            // Process successful response
-            if let Response::Success { public_key, response, .. } = response.0 {
-                match response {
-                    LookupResponseBody::Local { client, available } => {
-                        let server = ServerApiRecord::new(public_key, &server_address);
+        // Send request
+        let response = self.post_request::<AckRequest, AckResponse>(
+            format!("http://{}/api/v1/ack", &self.connected_server.address),
+            request
+        ).await?;
 
-                        return Ok(Some((client, server, available)));
-                    }
+        // Validate response
+        if !response.validate(proof_seed)? {
+            return Err(Error::InvalidProofSeedSignature);
+        }
 
-                    LookupResponseBody::Remote { client, server, available } => {
-                        return Ok(Some((client, server, available)));
-                    }
+        // Check response status
+        match response.0 {
+            Response::Success { response, .. } => Ok(response.acknowledged),
 
-                    LookupResponseBody::Hint { mut servers } => {
-                        for server in servers.drain(..) {
-                            queue.push_back(server.address);
-                        }
-                    }
-                }
+            Response::Error { status, code, reason, .. } => {
+                Err(Error::RequestFailed {
+                    status,
+                    code,
+                    reason
+                })
             }
-
-            used_servers.insert(server_address);
         }
-
-        Ok(None)
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
-        receiver_server,
-        receiver_public = receiver_public.to_base64(),
-        channel = channel.to_string(),
-        message = format!("{}: {}", message.encoding, message.content)
-    )))]
-    /// Send a message to remote client.
-    /// 
-    /// This method will perform `POST /api/v1/send` request.
+    /// Read messages without removing them
+    /// from the connected server's inbox.
     /// 
-    /// - `receiver_server` must contain address of the server to which
-    ///   the receiver is connected (from which it polls the messages).
-    /// 
-    /// - `receiver_public` must be a public key of the message receiver.
-    /// 
-    /// - `channel` must be a string name of the channel to which
-    ///   we want to send this message. Channels allow to differ
-    ///   messages streams for different applications and their
-    ///   parts (modules).
-    /// 
-    /// - `message` should contain the message you want to send.
-    pub async fn send(&self, receiver_server: impl AsRef<str>, receiver_public: PublicKey, channel: impl ToString, message: Message) -> Result<(), Error> {
+    /// Fails if the server's inbox doesn't
+    /// support peeking.
+    pub async fn peek(&self, channel: impl ToString, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
         #[cfg(feature = "tracing")]
-        tracing::debug!("Sending POST /api/v1/send request");
-
-        // Prepare send message request
-        let client = ClientApiRecord::new(
-            self.driver.secret_key().public_key(),
-            self.connection_certificate.clone(),
-            ClientInfo::thin()
-        );
-
-        let sender = Sender::new(client, self.connected_server.clone());
+        tracing::debug!("Sending peek POST /api/v1/poll request");
 
-        let request = SendRequest::new(
-            self.driver.secret_key(),
-            sender,
-            receiver_public,
-            channel,
-            message
-        );
+        // Prepare poll request
+        let request = PollRequest::peek(self.driver.secret_key(), ChannelName::new(channel.to_string())?, limit);
 
         let proof_seed = request.0.proof_seed;
 
         // Send request
-        let response = self.http_client.post_request::<SendRequest, SendResponse>(
-            format!("{}/api/v1/send", resolve_uri(receiver_server, self).await?),
+        let response = self.post_request::<PollRequest, PollResponse>(
+            format!("http://{}/api/v1/poll", &self.connected_server.address),
             request
         ).await?;
 
@@ -504,46 +2397,37 @@ impl<T: HttpClient> ConnectedClient<T> {
         }
 
         // Check response status
-        if let Response::Error { status, reason, .. } = response.0 {
-            return Err(Error::RequestFailed {
-                status,
-                reason
-            });
-        }
+        match response.0 {
+            Response::Success { response, .. } => {
+                Ok((response.messages, response.remaining))
+            }
 
-        Ok(())
+            Response::Error { status, code, reason, .. } => {
+                Err(Error::RequestFailed {
+                    status,
+                    code,
+                    reason
+                })
+            }
+        }
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(ret, skip_all, fields(
-        channel = channel.to_string(),
-        limit
-    )))]
-    /// Poll (read and delete) messages from the server's inbox.
+    /// Poll messages sealed to this client.
     /// 
-    /// This method will perform `POST /api/v1/poll` request.
-    /// 
-    /// - `channel` must contain string name of the messages channel.
-    /// 
-    /// - `limit` should contain amount of messages you want to poll,
-    ///   or `None` if all the stored. This param is used by the
-    ///   remote server which may not respect it, so you should
-    ///   consider implementing few manual checks if you don't trust
-    ///   the server you connected to.
-    /// 
-    /// This method will return vector of polled messages and
-    /// amount of remaining messages in the server's inbox.
-    pub async fn poll(&self, channel: impl ToString, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
+    /// Unlike `poll`, the messages are encrypted by
+    /// the server for this client only, so they're
+    /// opened here using the client's secret key.
+    pub async fn poll_sealed(&self, channel: impl ToString, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
         #[cfg(feature = "tracing")]
-        tracing::debug!("Sending POST /api/v1/poll request");
+        tracing::debug!("Sending sealed POST /api/v1/poll request");
 
         // Prepare poll request
-        let request = PollRequest::new(self.driver.secret_key(), channel, limit);
+        let request = PollRequest::sealed(self.driver.secret_key(), ChannelName::new(channel.to_string())?, limit);
 
         let proof_seed = request.0.proof_seed;
 
         // Send request
-        // We don't need to resolve our local server's address
-        let response = self.http_client.post_request::<PollRequest, PollResponse>(
+        let response = self.post_request::<PollRequest, PollResponse>(
             format!("http://{}/api/v1/poll", &self.connected_server.address),
             request
         ).await?;
@@ -556,15 +2440,149 @@ impl<T: HttpClient> ConnectedClient<T> {
         // Check response status
         match response.0 {
             Response::Success { response, .. } => {
-                Ok((response.messages, response.remaining))
+                let messages = response.sealed_messages.iter()
+                    .map(|sealed| sealed.open(self.driver.secret_key()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| Error::Other(Box::new(err)))?;
+
+                Ok((messages, response.remaining))
             }
 
-            Response::Error { status, reason, .. } => {
+            Response::Error { status, code, reason, .. } => {
                 Err(Error::RequestFailed {
                     status,
+                    code,
                     reason
                 })
             }
         }
     }
+
+    /// Poll messages and restore their order.
+    /// 
+    /// Polled messages are passed through the reorderer, so
+    /// out-of-order sequenced messages are buffered until the
+    /// missing ones arrive or the gap times out.
+    /// 
+    /// This method will return released events and
+    /// amount of remaining messages in the server's inbox.
+    pub async fn poll_ordered(&self, channel: impl ToString, limit: Option<u64>, reorderer: &mut MessageReorderer) -> Result<(Vec<OrderedEvent>, u64), Error> {
+        let (messages, remaining) = self.poll(channel, limit).await?;
+
+        let mut events = Vec::with_capacity(messages.len());
+
+        for message in messages {
+            events.extend(reorderer.push(message));
+        }
+
+        events.extend(reorderer.flush());
+
+        Ok((events, remaining))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Message sent by the `ConnectedClient::send_to` method.
+pub struct SendToResult {
+    /// Receiver of the message.
+    pub receiver: ClientApiRecord,
+
+    /// Server which accepted the message.
+    pub server: ServerApiRecord,
+
+    /// Availability of the receiver reported by the lookup.
+    pub available: bool,
+
+    /// Id assigned to the message by the server.
+    pub id: Option<u64>
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Failure of the `ConnectedClient::send_to` method.
+pub enum SendToError {
+    #[error("Address is not a hyperborea client: {0:?}")]
+    InvalidTarget(Address),
+
+    #[error("Failed to lookup the receiver: {0}")]
+    LookupFailed(#[source] Error),
+
+    #[error("Receiver is not found: {}", .0.to_base64())]
+    ReceiverNotFound(PublicKey),
+
+    #[error("Failed to create message: {0}")]
+    MessageFailed(#[from] MessagesError),
+
+    #[error(transparent)]
+    InvalidChannelName(#[from] ChannelNameError),
+
+    #[error("Server {} failed to accept the message: {source}", server.address)]
+    SendFailed {
+        server: ServerApiRecord,
+        source: Error
+    },
+
+    #[cfg(feature = "outbox")]
+    #[error("Message is queued in the outbox: {0}")]
+    Queued(#[source] Box<SendToError>),
+
+    #[cfg(feature = "outbox")]
+    #[error("Failed to queue message in the outbox: {outbox}, sending failed: {source}")]
+    QueueFailed {
+        source: Box<SendToError>,
+        outbox: OutboxError
+    }
+}
+
+impl SendToError {
+    /// Get error of the client middleware's
+    /// request which has failed.
+    pub fn source_error(&self) -> Option<&Error> {
+        match self {
+            Self::LookupFailed(err) |
+            Self::SendFailed { source: err, .. } => Some(err),
+
+            _ => None
+        }
+    }
+}
+
+#[cfg(feature = "heartbeat")]
+#[derive(Debug)]
+/// Handle of the background heartbeat task.
+/// 
+/// Task is aborted when the handle is dropped.
+pub struct HeartbeatHandle(tokio::task::JoinHandle<()>);
+
+#[cfg(feature = "heartbeat")]
+impl HeartbeatHandle {
+    #[inline]
+    /// Stop sending heartbeats.
+    pub fn abort(self) {
+        drop(self);
+    }
+}
+
+#[cfg(feature = "heartbeat")]
+impl Drop for HeartbeatHandle {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Let other tasks run without depending on the runtime.
+async fn yield_now() {
+    let mut yielded = false;
+
+    std::future::poll_fn(|context| {
+        if yielded {
+            return std::task::Poll::Ready(());
+        }
+
+        yielded = true;
+
+        context.waker().wake_by_ref();
+
+        std::task::Poll::Pending
+    }).await
 }
@@ -0,0 +1,324 @@
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+use crate::address::Address;
+use crate::crypto::asymmetric::PublicKey;
+use crate::http::client::HttpClient;
+
+use crate::rest_api::prelude::{
+    *,
+    Client as ClientApiRecord,
+    Server as ServerApiRecord
+};
+
+use super::{Client, ConnectedClient, Error};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Switch of the failover client to the next server.
+pub struct FailoverEvent {
+    /// Server which has failed.
+    pub previous: ServerApiRecord,
+
+    /// Server the client is connected to now.
+    pub current: ServerApiRecord
+}
+
+#[derive(Debug, Clone)]
+struct ActiveServer<T> {
+    /// Index of the server in the failover list.
+    index: usize,
+
+    client: ConnectedClient<T>
+}
+
+#[derive(Clone)]
+/// Client middleware connected to one of
+/// the ordered list of servers.
+/// 
+/// Calls failed because of the active server's failures
+/// are repeated, and when they fail too many times in a
+/// row the client connects to the next reachable server
+/// of the list and replays them there. Connection
+/// certificates are issued for every server separately.
+/// 
+/// Clones share the active server.
+pub struct FailoverClient<T> {
+    client: Client<T>,
+    servers: Arc<Vec<String>>,
+    active: Arc<RwLock<ActiveServer<T>>>,
+
+    /// Amount of the failures in a row
+    /// before switching to the next server.
+    max_failures: u32,
+
+    callback: Option<Arc<dyn Fn(FailoverEvent) + Send + Sync>>
+}
+
+impl<T: HttpClient> FailoverClient<T> {
+    /// Connect to the first reachable server.
+    /// 
+    /// Refer to `Client::connect_failover`.
+    pub(crate) async fn connect(client: Client<T>, servers: &[Address]) -> Result<Self, Error> {
+        let servers = servers.iter()
+            .filter_map(Address::server_address)
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        let mut last_error = None;
+
+        for (index, server_address) in servers.iter().enumerate() {
+            match client.connect(server_address).await {
+                Ok(connected) => {
+                    return Ok(Self {
+                        active: Arc::new(RwLock::new(ActiveServer {
+                            index,
+                            client: connected
+                        })),
+                        client,
+                        servers: Arc::new(servers),
+                        max_failures: 2,
+                        callback: None
+                    });
+                }
+
+                Err(err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(server_address, "Failed to connect to the failover server: {err}");
+
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| Error::Other("No supported failover servers given".into())))
+    }
+
+    #[inline]
+    /// Switch to the next server after the given amount
+    /// of failed calls in a row. Default is 2.
+    pub fn with_max_failures(self, max_failures: u32) -> Self {
+        Self {
+            max_failures: max_failures.max(1),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Call the given function every time the client
+    /// switches to the next server, e.g. to announce
+    /// the client by the new server.
+    pub fn with_callback(self, callback: impl Fn(FailoverEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Some(Arc::new(callback)),
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Get addresses of the failover servers in order.
+    pub fn servers(&self) -> &[String] {
+        &self.servers
+    }
+
+    #[inline]
+    /// Get client connected to the active server.
+    pub fn connected(&self) -> ConnectedClient<T> {
+        self.active.read()
+            .expect("Failed to lock active server")
+            .client.clone()
+    }
+
+    #[inline]
+    /// Get the server the client is connected to.
+    pub fn active_server(&self) -> ServerApiRecord {
+        self.active.read()
+            .expect("Failed to lock active server")
+            .client.connected_server()
+            .clone()
+    }
+
+    /// Check if the call failed because
+    /// of the active server's failure.
+    pub fn is_server_failure(error: &Error) -> bool {
+        matches!(error,
+            Error::Other(_) |
+            Error::Timeout { .. } |
+            Error::RequestFailed { status: ResponseStatus::ServerError, .. }
+        )
+    }
+
+    /// Run the operation using the active server, switching
+    /// to the next servers when it fails too many times.
+    /// 
+    /// Every server is tried at most once per call.
+    pub async fn call<R, F, Fut>(&self, name: &str, mut operation: F) -> Result<R, Error>
+    where
+        F: FnMut(ConnectedClient<T>) -> Fut,
+        Fut: Future<Output = Result<R, Error>>
+    {
+        let mut failures = 0;
+        let mut failovers = 0;
+
+        loop {
+            let (index, client) = {
+                let active = self.active.read()
+                    .expect("Failed to lock active server");
+
+                (active.index, active.client.clone())
+            };
+
+            match operation(client).await {
+                Err(err) if Self::is_server_failure(&err) => {
+                    failures += 1;
+
+                    if failures < self.max_failures {
+                        continue;
+                    }
+
+                    if failovers + 1 >= self.servers.len() {
+                        return Err(err);
+                    }
+
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(name, server = self.servers[index], "Server has failed, switching to the next one: {err}");
+
+                    #[cfg(not(feature = "tracing"))]
+                    let _ = name;
+
+                    failovers += self.failover(index, err).await?;
+                    failures = 0;
+                }
+
+                result => return result
+            }
+        }
+    }
+
+    /// Connect to the next reachable server after
+    /// the failed one and return amount of skipped
+    /// servers, including the failed one.
+    async fn failover(&self, failed: usize, error: Error) -> Result<usize, Error> {
+        let mut last_error = error;
+
+        for skipped in 1..self.servers.len() {
+            let index = (failed + skipped) % self.servers.len();
+
+            let connected = match self.client.connect(&self.servers[index]).await {
+                Ok(connected) => connected,
+
+                Err(err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(server_address = self.servers[index], "Failed to connect to the failover server: {err}");
+
+                    last_error = err;
+
+                    continue;
+                }
+            };
+
+            let current = connected.connected_server().clone();
+
+            let previous = {
+                let mut active = self.active.write()
+                    .expect("Failed to lock active server");
+
+                // Another call has switched the server already
+                if active.index != failed {
+                    return Ok(skipped);
+                }
+
+                let previous = std::mem::replace(&mut active.client, connected);
+
+                active.index = index;
+
+                previous
+            };
+
+            let event = FailoverEvent {
+                previous: previous.connected_server().clone(),
+                current
+            };
+
+            // The failed server is likely unreachable
+            if let Err(_err) = previous.disconnect().await {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(server_address = self.servers[failed], "Failed to disconnect from the failed server: {_err}");
+            }
+
+            if let Some(callback) = &self.callback {
+                callback(event);
+            }
+
+            return Ok(skipped);
+        }
+
+        Err(last_error)
+    }
+
+    /// Send heartbeat to the active server.
+    /// 
+    /// Refer to `ConnectedClient::heartbeat`.
+    pub async fn heartbeat(&self) -> Result<(), Error> {
+        self.call("heartbeat", |client| async move {
+            client.heartbeat().await
+        }).await
+    }
+
+    /// Lookup client using the active server.
+    /// 
+    /// Refer to `ConnectedClient::lookup`.
+    pub async fn lookup(&self, client_public: PublicKey, client_type: Option<ClientType>) -> Result<Option<(ClientApiRecord, ServerApiRecord, bool)>, Error> {
+        self.call("lookup", |client| {
+            let client_public = client_public.clone();
+
+            async move {
+                client.lookup(client_public, client_type).await
+            }
+        }).await
+    }
+
+    /// Poll messages from the active server's inbox.
+    /// 
+    /// Refer to `ConnectedClient::poll`.
+    pub async fn poll(&self, channel: impl ToString, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
+        let channel = channel.to_string();
+
+        self.call("poll", |client| {
+            let channel = channel.clone();
+
+            async move {
+                client.poll(channel, limit).await
+            }
+        }).await
+    }
+
+    /// Send message using the active server.
+    /// 
+    /// Refer to `ConnectedClient::send`.
+    pub async fn send(&self, receiver_server: impl AsRef<str>, receiver_public: PublicKey, channel: impl ToString, message: Message) -> Result<Option<u64>, Error> {
+        let receiver_server = receiver_server.as_ref();
+        let channel = channel.to_string();
+
+        self.call("send", |client| {
+            let receiver_public = receiver_public.clone();
+            let channel = channel.clone();
+            let message = message.clone();
+
+            async move {
+                client.send(receiver_server, receiver_public, channel, message).await
+            }
+        }).await
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for FailoverClient<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FailoverClient")
+            .field("client", &self.client)
+            .field("servers", &self.servers)
+            .field("active", &self.active)
+            .field("max_failures", &self.max_failures)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
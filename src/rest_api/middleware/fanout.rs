@@ -0,0 +1,306 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::time::Instant;
+
+use rand_chacha::rand_core::RngCore;
+
+use tokio::sync::Semaphore;
+
+use crate::crypto::prelude::*;
+use crate::http::client::HttpClient;
+
+use crate::drivers::server::{AnnounceFanout, AnnounceFanoutParams};
+
+use crate::rest_api::prelude::{
+    *,
+    Client as ClientApiRecord,
+    Server as ServerApiRecord
+};
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Announce fan-out metrics.
+pub struct AnnounceFanoutStats {
+    /// Amount of announce requests queued.
+    pub queued: u64,
+
+    /// Amount of connections and received records which
+    /// were not announced because of the dedupe window.
+    pub deduplicated: u64,
+
+    /// Amount of announce requests queued to forward
+    /// records received from other servers.
+    pub forwarded: u64,
+
+    /// Amount of successfully performed announce requests.
+    pub delivered: u64,
+
+    /// Amount of retried announce requests.
+    pub retried: u64,
+
+    /// Amount of announce requests failed after all the retries.
+    pub failed: u64
+}
+
+#[derive(Default, Debug)]
+struct Counters {
+    queued: AtomicU64,
+    deduplicated: AtomicU64,
+    forwarded: AtomicU64,
+    delivered: AtomicU64,
+    retried: AtomicU64,
+    failed: AtomicU64
+}
+
+#[derive(Debug)]
+/// Background announcer of the connected local clients
+/// and the records received from other servers.
+pub(crate) struct AnnounceFanoutWorker<T> {
+    http_client: T,
+    params: AnnounceFanoutParams,
+    secret_key: SecretKey,
+    server: ServerApiRecord,
+
+    /// Recently announced (record, server) keys.
+    recent: Mutex<HashMap<(PublicKey, PublicKey), Instant>>,
+    semaphore: Arc<Semaphore>,
+    counters: Arc<Counters>
+}
+
+impl<T: HttpClient + 'static> AnnounceFanoutWorker<T> {
+    pub fn new(http_client: T, params: AnnounceFanoutParams, secret_key: SecretKey, address: impl ToString) -> Self {
+        Self {
+            http_client,
+            params,
+            server: ServerApiRecord::new(secret_key.public_key(), address),
+            secret_key,
+            recent: Mutex::new(HashMap::new()),
+            semaphore: Arc::new(Semaphore::new(params.concurrency.max(1))),
+            counters: Arc::new(Counters::default())
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.params.mode != AnnounceFanout::Disabled
+    }
+
+    pub fn stats(&self) -> AnnounceFanoutStats {
+        AnnounceFanoutStats {
+            queued: self.counters.queued.load(Ordering::Relaxed),
+            deduplicated: self.counters.deduplicated.load(Ordering::Relaxed),
+            forwarded: self.counters.forwarded.load(Ordering::Relaxed),
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            retried: self.counters.retried.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Check that the record wasn't announced within the
+    /// dedupe window and remember it as announced.
+    fn check_dedupe(&self, key: (PublicKey, PublicKey)) -> bool {
+        let mut recent = self.recent.lock()
+            .expect("Failed to lock announce fan-out dedupe table");
+
+        let now = Instant::now();
+
+        recent.retain(|_, announced_at| now.duration_since(*announced_at) < self.params.dedupe_window);
+
+        if recent.contains_key(&key) {
+            return false;
+        }
+
+        recent.insert(key, now);
+
+        true
+    }
+
+    /// Select servers to announce the record to.
+    fn select_servers(&self, mut servers: Vec<ServerApiRecord>, exclude: &[&PublicKey]) -> Vec<ServerApiRecord> {
+        servers.retain(|server| server.public_key != self.server.public_key && !exclude.contains(&&server.public_key));
+
+        match self.params.mode {
+            AnnounceFanout::Disabled => vec![],
+            AnnounceFanout::All => servers,
+
+            AnnounceFanout::Random(amount) => {
+                let mut rand = random_generator();
+
+                // Partial Fisher-Yates shuffle
+                let amount = amount.min(servers.len());
+
+                for i in 0..amount {
+                    let j = i + (rand.next_u64() % (servers.len() - i) as u64) as usize;
+
+                    servers.swap(i, j);
+                }
+
+                servers.truncate(amount);
+
+                servers
+            }
+        }
+    }
+
+    /// Announce connected local client to the selected
+    /// known servers in background.
+    /// 
+    /// Return amount of queued announce requests.
+    pub fn announce(&self, client: ClientApiRecord, servers: Vec<ServerApiRecord>) -> usize {
+        if !self.is_enabled() {
+            return 0;
+        }
+
+        let entry = AnnounceRequestBody::client(client, self.server.clone());
+
+        self.fan_out(entry, servers, &[])
+    }
+
+    /// Forward announced record received from
+    /// another server to the selected known
+    /// servers in background.
+    /// 
+    /// Records which passed the hop limit are not
+    /// forwarded. Return amount of queued announce requests.
+    pub fn forward(&self, entry: AnnounceRequestBody, announcer: &PublicKey, servers: Vec<ServerApiRecord>) -> usize {
+        if !self.is_enabled() || entry.hops() >= self.params.hop_limit {
+            return 0;
+        }
+
+        let hops = entry.hops() + 1;
+
+        let queued = match &entry {
+            AnnounceRequestBody::Client { server, .. } |
+            AnnounceRequestBody::Server { server, .. } => {
+                let server = server.public_key.clone();
+
+                self.fan_out(entry.with_hops(hops), servers, &[announcer, &server])
+            }
+
+            AnnounceRequestBody::Bulk { .. } => 0
+        };
+
+        self.counters.forwarded.fetch_add(queued as u64, Ordering::Relaxed);
+
+        queued
+    }
+
+    /// Announce the record to the selected
+    /// known servers in background.
+    fn fan_out(&self, entry: AnnounceRequestBody, servers: Vec<ServerApiRecord>, exclude: &[&PublicKey]) -> usize {
+        let key = match &entry {
+            AnnounceRequestBody::Client { client, server, .. } => (client.public_key.clone(), server.public_key.clone()),
+            AnnounceRequestBody::Server { server, .. } => (server.public_key.clone(), server.public_key.clone()),
+            AnnounceRequestBody::Bulk { .. } => return 0
+        };
+
+        if !self.check_dedupe(key.clone()) {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                record_public = key.0.to_base64(),
+                "Record was announced recently, skipping fan-out"
+            );
+
+            self.counters.deduplicated.fetch_add(1, Ordering::Relaxed);
+
+            return 0;
+        }
+
+        let servers = self.select_servers(servers, exclude);
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            record_public = key.0.to_base64(),
+            hops = entry.hops(),
+            servers = servers.len(),
+            "Queueing announce fan-out"
+        );
+
+        for target in &servers {
+            self.counters.queued.fetch_add(1, Ordering::Relaxed);
+
+            let http_client = self.http_client.clone();
+            let params = self.params;
+            let secret_key = self.secret_key.clone();
+            let entry = entry.clone();
+            let target = target.clone();
+            let semaphore = self.semaphore.clone();
+            let counters = self.counters.clone();
+
+            tokio::spawn(async move {
+                let Ok(_permit) = semaphore.acquire_owned().await else {
+                    return;
+                };
+
+                let mut backoff = params.backoff;
+
+                for attempt in 0..=params.retries {
+                    if attempt > 0 {
+                        counters.retried.fetch_add(1, Ordering::Relaxed);
+
+                        tokio::time::sleep(backoff).await;
+
+                        backoff *= 2;
+                    }
+
+                    match Self::send_announce(&http_client, &secret_key, &entry, &target).await {
+                        Ok(()) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::trace!(
+                                target = target.address,
+                                "Announced record"
+                            );
+
+                            counters.delivered.fetch_add(1, Ordering::Relaxed);
+
+                            return;
+                        }
+
+                        Err(_err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(
+                                target = target.address,
+                                attempt,
+                                "Failed to announce record: {_err}"
+                            );
+                        }
+                    }
+                }
+
+                counters.failed.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        servers.len()
+    }
+
+    async fn send_announce(
+        http_client: &T,
+        secret_key: &SecretKey,
+        entry: &AnnounceRequestBody,
+        target: &ServerApiRecord
+    ) -> Result<(), super::Error> {
+        let request = AnnounceRequest(Request::new(secret_key, entry.clone()));
+
+        let proof_seed = request.0.proof_seed;
+
+        let response = http_client.post_request::<AnnounceRequest, AnnounceResponse>(
+            format!("http://{}/api/v1/announce", target.address),
+            request
+        ).await?;
+
+        if !response.validate(proof_seed)? {
+            return Err(super::Error::InvalidProofSeedSignature);
+        }
+
+        if let Response::Error { status, code, reason, .. } = response.0 {
+            return Err(super::Error::RequestFailed {
+                status,
+                code,
+                reason
+            });
+        }
+
+        Ok(())
+    }
+}
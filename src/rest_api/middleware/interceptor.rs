@@ -0,0 +1,372 @@
+use std::net::{SocketAddr, IpAddr};
+use std::sync::{Arc, RwLock, Mutex};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::future::Future;
+use std::time::Instant;
+
+use serde_json::Value as Json;
+
+use crate::http::server::HttpServer;
+use crate::drivers::server::rate_limit::RateLimiter;
+use crate::crypto::asymmetric::PublicKey;
+
+use crate::rest_api::AsJson;
+use crate::rest_api::response::Response;
+use crate::rest_api::status::ResponseStatus;
+use crate::rest_api::error_code::ErrorCode;
+
+use super::access_log::{AccessLogHook, AccessLogEntry};
+
+#[async_trait::async_trait]
+/// Hooks called around the server middleware's
+/// request handlers.
+/// 
+/// Interceptors can be used to add custom logic
+/// (authorization, accounting, responses rewriting)
+/// to the server without modifying its handlers.
+/// 
+/// Routes are identified by their paths,
+/// e.g. `/api/v1/announce`.
+pub trait Interceptor: Send + Sync {
+    /// Called before the request is processed by the handler.
+    /// 
+    /// `request` contains JSON body of the `POST` request,
+    /// URL query parameters of the `GET` request as
+    /// a JSON object, or `null` if the route has none.
+    /// Body fields unknown to the server are kept in it.
+    /// Refer to `Request::extensions`.
+    /// 
+    /// Return `ControlFlow::Break` to send the given
+    /// response without calling the handler.
+    async fn before_request(&self, route: &str, client_address: SocketAddr, request: &Json) -> ControlFlow<Response<Json>> {
+        let _ = (route, client_address, request);
+
+        ControlFlow::Continue(())
+    }
+
+    /// Called after the handler with its JSON response.
+    /// 
+    /// Not called for the short-circuited requests.
+    async fn after_response(&self, route: &str, response: &mut Json) {
+        let _ = (route, response);
+    }
+}
+
+#[derive(Default, Clone)]
+/// Shared list of the server middleware's interceptors.
+/// 
+/// `before_request` hooks are called in the order
+/// interceptors were added, and `after_response`
+/// hooks in the reverse order.
+pub struct Interceptors {
+    interceptors: Arc<RwLock<Vec<Arc<dyn Interceptor>>>>
+}
+
+impl Interceptors {
+    #[inline]
+    /// Add new interceptor to the end of the list.
+    pub fn push(&self, interceptor: Arc<dyn Interceptor>) {
+        self.interceptors.write()
+            .expect("Failed to lock interceptors")
+            .push(interceptor);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.interceptors.read()
+            .expect("Failed to lock interceptors")
+            .len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    fn list(&self) -> Vec<Arc<dyn Interceptor>> {
+        self.interceptors.read()
+            .expect("Failed to lock interceptors")
+            .clone()
+    }
+
+    /// Call `before_request` hooks until one of them
+    /// returns a response.
+    pub async fn before_request(&self, route: &str, client_address: SocketAddr, request: &Json) -> ControlFlow<Response<Json>> {
+        for interceptor in self.list() {
+            interceptor.before_request(route, client_address, request).await?;
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Call `after_response` hooks.
+    pub async fn after_response(&self, route: &str, response: &mut Json) {
+        for interceptor in self.list().into_iter().rev() {
+            interceptor.after_response(route, response).await;
+        }
+    }
+}
+
+impl std::fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Interceptors")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+/// Interceptor counting requests sent from every IP address.
+/// 
+/// Clones share the same counters, so a clone can be kept
+/// to read them after the interceptor is added to the server.
+/// 
+/// ```rust
+/// use hyperborealib::rest_api::prelude::*;
+/// 
+/// let counter = RequestCounter::default();
+/// 
+/// assert_eq!(counter.total(), 0);
+/// assert_eq!(counter.get("127.0.0.1".parse().unwrap()), 0);
+/// ```
+pub struct RequestCounter {
+    requests: Arc<Mutex<HashMap<IpAddr, u64>>>
+}
+
+impl RequestCounter {
+    /// Get amount of requests sent from the given IP address.
+    pub fn get(&self, address: IpAddr) -> u64 {
+        self.requests.lock()
+            .expect("Failed to lock requests counter")
+            .get(&address)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Get amount of requests sent from all the IP addresses.
+    pub fn total(&self) -> u64 {
+        self.requests.lock()
+            .expect("Failed to lock requests counter")
+            .values()
+            .sum()
+    }
+
+    /// Get amounts of requests sent from every IP address.
+    pub fn counts(&self) -> HashMap<IpAddr, u64> {
+        self.requests.lock()
+            .expect("Failed to lock requests counter")
+            .clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl Interceptor for RequestCounter {
+    async fn before_request(&self, _route: &str, client_address: SocketAddr, _request: &Json) -> ControlFlow<Response<Json>> {
+        *self.requests.lock()
+            .expect("Failed to lock requests counter")
+            .entry(client_address.ip())
+            .or_default() += 1;
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Serialize handler's response and pass it
+/// through the `after_response` hooks.
+async fn intercept_response(interceptors: &Interceptors, route: &str, response: Result<Json, crate::rest_api::AsJsonError>) -> Json {
+    let mut response = match response {
+        Ok(response) => response,
+
+        Err(err) => return short_circuit(Response::error(
+            ResponseStatus::ServerError,
+            ErrorCode::Internal,
+            format!("Failed to serialize response as JSON: {err}")
+        ))
+    };
+
+    interceptors.after_response(route, &mut response).await;
+
+    response
+}
+
+#[inline]
+fn short_circuit(response: Response<Json>) -> Json {
+    response.to_json().unwrap_or_default()
+}
+
+/// Take a token from the rate limiter's bucket
+/// of the client's IP address.
+fn check_ip_rate_limit(rate_limiter: Option<&RateLimiter>, route: &str, client_address: SocketAddr) -> ControlFlow<Response<Json>> {
+    if let Some(Err(err)) = rate_limiter.map(|rate_limiter| rate_limiter.check_ip(route, client_address.ip())) {
+        return ControlFlow::Break(Response::error(ResponseStatus::TooManyRequests, ErrorCode::RateLimited, err.to_string()));
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Record processed request in the access log if it's set.
+async fn record_access(access_log: &AccessLogHook, route: &str, client_address: SocketAddr, public_key: Option<PublicKey>, response: &Json, started_at: Instant) {
+    if let Some(access_log) = access_log.get() {
+        access_log.record(AccessLogEntry::new(route, client_address, public_key, response, started_at.elapsed())).await;
+    }
+}
+
+/// Wrapper of the HTTP server calling interceptors
+/// around the registered routes handlers.
+/// 
+/// `GET` requests are throttled by the rate limiter
+/// before the interceptors are called. Signed `POST`
+/// requests are throttled by their handlers.
+/// 
+/// Every processed request is recorded
+/// in the access log.
+pub(crate) struct InterceptedRoutes<'a, HttpServerExt> {
+    http_server: &'a mut HttpServerExt,
+    interceptors: Interceptors,
+    rate_limiter: Option<RateLimiter>,
+    access_log: AccessLogHook
+}
+
+impl<'a, HttpServerExt: HttpServer> InterceptedRoutes<'a, HttpServerExt> {
+    #[inline]
+    pub fn new(
+        http_server: &'a mut HttpServerExt,
+        interceptors: Interceptors,
+        rate_limiter: Option<RateLimiter>,
+        access_log: AccessLogHook
+    ) -> Self {
+        Self {
+            http_server,
+            interceptors,
+            rate_limiter,
+            access_log
+        }
+    }
+
+    /// Add GET request route.
+    /// 
+    /// Refer to `HttpServer::get`.
+    pub async fn get<T: AsJson, F: Future<Output = T> + Send>(
+        &mut self,
+        path: impl AsRef<str> + Send,
+        callback: impl FnOnce(SocketAddr) -> F + Clone + Send + Sync + 'static
+    ) {
+        let route = path.as_ref().to_string();
+        let interceptors = self.interceptors.clone();
+        let rate_limiter = self.rate_limiter.clone();
+        let access_log = self.access_log.clone();
+
+        self.http_server.get(path, move |client_address| async move {
+            let started_at = Instant::now();
+
+            let response = async {
+                if let ControlFlow::Break(response) = check_ip_rate_limit(rate_limiter.as_ref(), &route, client_address) {
+                    return short_circuit(response);
+                }
+
+                if let ControlFlow::Break(response) = interceptors.before_request(&route, client_address, &Json::Null).await {
+                    return short_circuit(response);
+                }
+
+                let response = callback(client_address).await.to_json();
+
+                intercept_response(&interceptors, &route, response).await
+            }.await;
+
+            record_access(&access_log, &route, client_address, None, &response, started_at).await;
+
+            response
+        }).await;
+    }
+
+    /// Add GET request route with URL query parameters.
+    /// 
+    /// Refer to `HttpServer::get_with_query`.
+    pub async fn get_with_query<T: AsJson, F: Future<Output = Result<T, String>> + Send>(
+        &mut self,
+        path: impl AsRef<str> + Send,
+        callback: impl FnOnce(SocketAddr, HashMap<String, String>) -> F + Clone + Send + Sync + 'static
+    ) {
+        let route = path.as_ref().to_string();
+        let interceptors = self.interceptors.clone();
+        let rate_limiter = self.rate_limiter.clone();
+        let access_log = self.access_log.clone();
+
+        self.http_server.get_with_query(path, move |client_address, query| async move {
+            let started_at = Instant::now();
+
+            let response = async {
+                if let ControlFlow::Break(response) = check_ip_rate_limit(rate_limiter.as_ref(), &route, client_address) {
+                    return Ok(short_circuit(response));
+                }
+
+                let request = Json::Object(query.iter()
+                    .map(|(key, value)| (key.clone(), Json::String(value.clone())))
+                    .collect());
+
+                if let ControlFlow::Break(response) = interceptors.before_request(&route, client_address, &request).await {
+                    return Ok(short_circuit(response));
+                }
+
+                let response = callback(client_address, query).await?.to_json();
+
+                Ok(intercept_response(&interceptors, &route, response).await)
+            }.await;
+
+            // Invalid queries are rejected with the structure error
+            let logged = match &response {
+                Ok(response) => response.clone(),
+                Err(_) => serde_json::json!({ "status": ResponseStatus::InvalidRequestStructure.to_code() })
+            };
+
+            record_access(&access_log, &route, client_address, None, &logged, started_at).await;
+
+            response
+        }).await;
+    }
+
+    /// Add POST request route.
+    /// 
+    /// Refer to `HttpServer::post`.
+    pub async fn post<T: AsJson + Send + 'static, F: AsJson, R: Future<Output = F> + Send>(
+        &mut self,
+        path: impl AsRef<str> + Send,
+        callback: impl FnOnce(SocketAddr, T) -> R + Clone + Send + Sync + 'static
+    ) {
+        let route = path.as_ref().to_string();
+        let interceptors = self.interceptors.clone();
+        let access_log = self.access_log.clone();
+
+        self.http_server.post::<T, Json, _>(path, move |client_address, request: T| async move {
+            let started_at = Instant::now();
+
+            // Don't serialize the request back
+            // if there's no one to read it
+            let json = (!interceptors.is_empty() || access_log.is_enabled())
+                .then(|| request.to_json().unwrap_or_default());
+
+            let public_key = json.as_ref()
+                .and_then(|json| json.get("public_key"))
+                .and_then(Json::as_str)
+                .and_then(|public_key| PublicKey::from_base64(public_key).ok());
+
+            let response = async {
+                if let Some(json) = &json {
+                    if let ControlFlow::Break(response) = interceptors.before_request(&route, client_address, json).await {
+                        return short_circuit(response);
+                    }
+                }
+
+                let response = callback(client_address, request).await.to_json();
+
+                intercept_response(&interceptors, &route, response).await
+            }.await;
+
+            record_access(&access_log, &route, client_address, public_key, &response, started_at).await;
+
+            response
+        }).await;
+    }
+}
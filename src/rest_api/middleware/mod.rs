@@ -1,12 +1,110 @@
 use crate::crypto::Error as CryptographyError;
 use crate::rest_api::ValidationError;
 use crate::rest_api::status::ResponseStatus;
+use crate::rest_api::error_code::ErrorCode;
+use crate::rest_api::version::ProtocolVersions;
+use crate::rest_api::types::ChannelNameError;
 
 mod client;
 mod server;
+mod ordering;
+mod interceptor;
+mod access_log;
+mod reconnect;
+mod failover;
+
+#[cfg(feature = "retry")]
+mod retry;
+
+#[cfg(feature = "subscribe")]
+mod subscription;
+
+#[cfg(feature = "timeouts")]
+mod timeouts;
+
+#[cfg(feature = "session")]
+mod session;
+
+#[cfg(feature = "outbox")]
+mod outbox;
+
+#[cfg(feature = "announce-fanout")]
+mod fanout;
+
+#[cfg(feature = "webhooks")]
+mod webhooks;
+
+#[cfg(feature = "admin-api")]
+mod admin;
 
 pub use client::*;
 pub use server::*;
+pub use ordering::*;
+pub use interceptor::{Interceptor, Interceptors, RequestCounter};
+
+pub use reconnect::{AutoReconnect, ReconnectEvent};
+pub use failover::{FailoverClient, FailoverEvent};
+
+pub(crate) use reconnect::SharedCertificate;
+
+pub use access_log::{
+    AccessLog,
+    AccessLogEntry,
+    AccessLogHook,
+    TracingAccessLog
+};
+
+#[cfg(feature = "retry")]
+pub use retry::RetryPolicy;
+
+#[cfg(feature = "timeouts")]
+pub use timeouts::Timeouts;
+
+#[cfg(feature = "session")]
+pub use session::ClientSession;
+
+#[cfg(feature = "outbox")]
+pub use outbox::{
+    Outbox,
+    OutboxParams,
+    OutboxEntry,
+    OutboxEvent,
+    OutboxFlush,
+    OutboxFlusher,
+    OutboxError
+};
+
+#[cfg(feature = "subscribe")]
+pub use subscription::{
+    Subscription,
+    SubscriptionParams,
+    SubscriptionMessage,
+    SubscriptionError
+};
+
+#[cfg(feature = "announce-fanout")]
+pub use fanout::AnnounceFanoutStats;
+
+#[cfg(feature = "admin-api")]
+pub use admin::{
+    AdminAuth,
+    AdminRequest,
+    AdminResponse,
+    AdminStats,
+    RoutingExport,
+    AdminPurge,
+    AdminBlock,
+    AdminMaintenance,
+    MaintenanceReport
+};
+
+#[cfg(feature = "webhooks")]
+pub use webhooks::{
+    WebhookEvent,
+    WebhookStats,
+    fingerprint,
+    webhook_signature
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -22,12 +120,35 @@ pub enum Error {
     #[error(transparent)]
     SignatureValidationError(#[from] ValidationError),
 
-    #[error("Request failed. Status: {status:?}, reason: {reason}")]
+    #[error("Request failed. Status: {status:?}, code: {code}, reason: {reason}")]
     RequestFailed {
         status: ResponseStatus,
+        code: ErrorCode,
         reason: String
     },
 
+    #[error("Request to {route} timed out after {elapsed:?}")]
+    Timeout {
+        route: String,
+        elapsed: std::time::Duration
+    },
+
+    #[error("Failed to solve proof of work of difficulty {difficulty} in {iterations} iterations")]
+    WorkNotSolved {
+        difficulty: u32,
+        iterations: u64
+    },
+
+    #[error("Server supports only standard versions from {} to {}", .0.min, .0.max)]
+    UnsupportedStandard(ProtocolVersions),
+
+    #[error(transparent)]
+    InvalidChannelName(#[from] ChannelNameError),
+
+    #[cfg(feature = "mdns")]
+    #[error(transparent)]
+    DiscoveryError(#[from] crate::discovery::DiscoveryError),
+
     #[error(transparent)]
     Other(#[from] Box<dyn std::error::Error + Send + Sync>)
 }
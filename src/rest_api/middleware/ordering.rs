@@ -0,0 +1,288 @@
+use std::collections::{HashMap, BTreeMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::crypto::asymmetric::PublicKey;
+
+use crate::rest_api::types::{MessageInfo, ChannelName};
+
+#[derive(Default, Debug, Clone)]
+/// Sequence numbers of the sent messages.
+/// 
+/// Numbers are maintained per (receiver, channel)
+/// pair and start from 1.
+pub struct SequenceCounters(Arc<Mutex<HashMap<(PublicKey, ChannelName), u64>>>);
+
+impl SequenceCounters {
+    /// Take next sequence number of the stream.
+    pub fn next(&self, receiver: &PublicKey, channel: &ChannelName) -> u64 {
+        let mut counters = self.0.lock()
+            .expect("Failed to lock sequence counters");
+
+        let counter = counters.entry((receiver.clone(), channel.clone()))
+            .or_insert(0);
+
+        *counter += 1;
+
+        *counter
+    }
+}
+
+impl PartialEq for SequenceCounters {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SequenceCounters {}
+
+impl std::hash::Hash for SequenceCounters {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as usize).hash(state);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReorderParams {
+    /// Maximal distance between the expected and the
+    /// received sequence numbers.
+    /// 
+    /// When a message outside of this window is received,
+    /// all the missing messages before it are reported
+    /// as a gap immediately.
+    pub window: u64,
+
+    /// Time after which missing messages are
+    /// reported as a gap.
+    pub gap_timeout: Duration
+}
+
+impl Default for ReorderParams {
+    fn default() -> Self {
+        Self {
+            window: 64,
+            gap_timeout: Duration::from_secs(5)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[allow(clippy::large_enum_variant)]
+pub enum OrderedEvent {
+    /// Message released in order.
+    Message(MessageInfo),
+
+    /// Messages with sequence numbers `from..=to`
+    /// didn't arrive in time and were skipped.
+    GapDetected {
+        sender: PublicKey,
+        channel: ChannelName,
+        from: u64,
+        to: u64
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Stream {
+    next: u64,
+    buffer: BTreeMap<u64, MessageInfo>,
+    waiting_since: Option<Instant>
+}
+
+impl Default for Stream {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            next: 1,
+            buffer: BTreeMap::new(),
+            waiting_since: None
+        }
+    }
+}
+
+impl Stream {
+    /// Release buffered messages following the expected one.
+    fn release(&mut self, events: &mut Vec<OrderedEvent>) {
+        let next = self.next;
+
+        while let Some(message) = self.buffer.remove(&self.next) {
+            events.push(OrderedEvent::Message(message));
+
+            self.next += 1;
+        }
+
+        // Restart gap timer only if the stream has moved
+        if self.buffer.is_empty() {
+            self.waiting_since = None;
+        }
+
+        else if self.waiting_since.is_none() || self.next != next {
+            self.waiting_since = Some(Instant::now());
+        }
+    }
+
+    /// Skip missing messages up to the first buffered one.
+    fn skip_gap(&mut self, sender: &PublicKey, channel: &ChannelName, events: &mut Vec<OrderedEvent>) {
+        if let Some(first) = self.buffer.keys().next().copied() {
+            events.push(OrderedEvent::GapDetected {
+                sender: sender.clone(),
+                channel: channel.clone(),
+                from: self.next,
+                to: first - 1
+            });
+
+            self.next = first;
+
+            self.release(events);
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+/// Restores order of the sequenced incoming messages.
+/// 
+/// Messages are ordered per (sender, channel) pair.
+/// Messages without sequence numbers are released
+/// immediately, and messages with already released
+/// or skipped sequence numbers are dropped.
+pub struct MessageReorderer {
+    pub params: ReorderParams,
+
+    streams: HashMap<(PublicKey, ChannelName), Stream>
+}
+
+impl MessageReorderer {
+    #[inline]
+    pub fn new(params: ReorderParams) -> Self {
+        Self {
+            params,
+            streams: HashMap::new()
+        }
+    }
+
+    /// Process received message.
+    /// 
+    /// Return events released by this message.
+    pub fn push(&mut self, message: MessageInfo) -> Vec<OrderedEvent> {
+        let Some(sequence) = message.message.sequence else {
+            return vec![OrderedEvent::Message(message)];
+        };
+
+        let sender = message.sender.client.public_key.clone();
+        let channel = message.channel.clone();
+
+        let stream = self.streams.entry((sender.clone(), channel.clone()))
+            .or_default();
+
+        let mut events = Vec::new();
+
+        if sequence < stream.next {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(sequence, next = stream.next, "Dropping outdated sequenced message");
+
+            return events;
+        }
+
+        stream.buffer.insert(sequence, message);
+
+        stream.release(&mut events);
+
+        // Don't wait for the messages outside of the window
+        while stream.buffer.last_key_value().is_some_and(|(last, _)| *last >= stream.next + self.params.window) {
+            stream.skip_gap(&sender, &channel, &mut events);
+        }
+
+        events
+    }
+
+    /// Report gaps which weren't filled in time
+    /// and release messages following them.
+    pub fn flush(&mut self) -> Vec<OrderedEvent> {
+        let mut events = Vec::new();
+
+        for ((sender, channel), stream) in self.streams.iter_mut() {
+            let expired = stream.waiting_since
+                .is_some_and(|since| since.elapsed() >= self.params.gap_timeout);
+
+            if expired {
+                stream.skip_gap(sender, channel, &mut events);
+            }
+        }
+
+        events
+    }
+
+    /// Amount of buffered out-of-order messages.
+    pub fn buffered(&self) -> usize {
+        self.streams.values()
+            .map(|stream| stream.buffer.len())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::prelude::*;
+
+    use crate::rest_api::types::message_info::tests::get_message_info;
+
+    use super::*;
+
+    fn get_sequenced(info: &MessageInfo, sequence: Option<u64>) -> MessageInfo {
+        let mut info = info.clone();
+
+        info.message.sequence = sequence;
+
+        info
+    }
+
+    fn sequences(events: &[OrderedEvent]) -> Vec<Option<u64>> {
+        events.iter()
+            .filter_map(|event| match event {
+                OrderedEvent::Message(info) => Some(info.message.sequence),
+                OrderedEvent::GapDetected { .. } => None
+            })
+            .collect()
+    }
+
+    #[test]
+    fn counters() {
+        let counters = SequenceCounters::default();
+
+        let receiver = SecretKey::random().public_key();
+
+        assert_eq!(counters.next(&receiver, &ChannelName::new("a").unwrap()), 1);
+        assert_eq!(counters.clone().next(&receiver, &ChannelName::new("a").unwrap()), 2);
+        assert_eq!(counters.next(&receiver, &ChannelName::new("b").unwrap()), 1);
+    }
+
+    #[test]
+    fn window() {
+        let mut reorderer = MessageReorderer::new(ReorderParams {
+            window: 4,
+            gap_timeout: Duration::from_secs(3600)
+        });
+
+        let info = get_message_info();
+
+        // Legacy messages are passed through
+        assert_eq!(sequences(&reorderer.push(get_sequenced(&info, None))), [None]);
+
+        assert!(reorderer.push(get_sequenced(&info, Some(2))).is_empty());
+        assert_eq!(sequences(&reorderer.push(get_sequenced(&info, Some(1)))), [Some(1), Some(2)]);
+
+        // Message outside of the window
+        assert!(reorderer.push(get_sequenced(&info, Some(4))).is_empty());
+
+        let events = reorderer.push(get_sequenced(&info, Some(8)));
+
+        assert!(matches!(events[0], OrderedEvent::GapDetected { from: 3, to: 3, .. }));
+        assert_eq!(sequences(&events), [Some(4)]);
+        assert_eq!(reorderer.buffered(), 1);
+
+        // Outdated message
+        assert!(reorderer.push(get_sequenced(&info, Some(3))).is_empty());
+    }
+}
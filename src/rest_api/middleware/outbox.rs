@@ -0,0 +1,548 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, Notify};
+
+use crate::time::timestamp;
+use crate::crypto::asymmetric::PublicKey;
+use crate::http::client::HttpClient;
+use crate::drivers::server::messages_inbox::MessagesInbox;
+
+use crate::rest_api::prelude::*;
+
+use super::{ConnectedClient, RetryPolicy, SendToResult, Error};
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OutboxError {
+    #[error("Outbox storage failed: {0}")]
+    Storage(#[source] BoxedError),
+
+    #[error("Outbox storage doesn't support peeking messages")]
+    PeekUnsupported
+}
+
+#[async_trait::async_trait]
+/// Messages inbox used to store the outbox's messages.
+/// 
+/// Queued messages are stored as sent to their receivers,
+/// so every (receiver, channel) pair keeps its FIFO order.
+trait OutboxStorage: Send + Sync {
+    async fn add(&self, receiver: PublicKey, info: MessageInfo) -> Result<(), BoxedError>;
+    async fn peek(&self, receiver: PublicKey, channel: ChannelName, limit: Option<u64>) -> Result<Option<Vec<MessageInfo>>, BoxedError>;
+    async fn remove_first(&self, receiver: PublicKey, channel: ChannelName) -> Result<(), BoxedError>;
+    async fn receivers(&self) -> Result<Vec<PublicKey>, BoxedError>;
+    async fn channels(&self, receiver: PublicKey) -> Result<Vec<(ChannelName, u64)>, BoxedError>;
+    async fn purge(&self, receiver: PublicKey, channel: Option<ChannelName>) -> Result<(), BoxedError>;
+}
+
+#[async_trait::async_trait]
+impl<I> OutboxStorage for I
+where
+    I: MessagesInbox + Send + Sync,
+    I::Error: 'static
+{
+    async fn add(&self, receiver: PublicKey, info: MessageInfo) -> Result<(), BoxedError> {
+        self.add_message_info(receiver, info).await?;
+
+        Ok(())
+    }
+
+    async fn peek(&self, receiver: PublicKey, channel: ChannelName, limit: Option<u64>) -> Result<Option<Vec<MessageInfo>>, BoxedError> {
+        let messages = self.peek_messages(receiver, channel, limit).await?;
+
+        Ok(messages.map(|(messages, _)| messages))
+    }
+
+    async fn remove_first(&self, receiver: PublicKey, channel: ChannelName) -> Result<(), BoxedError> {
+        self.poll_messages(receiver, channel.into(), None, None, Some(1)).await?;
+
+        Ok(())
+    }
+
+    async fn receivers(&self) -> Result<Vec<PublicKey>, BoxedError> {
+        Ok(self.list_receivers().await?)
+    }
+
+    async fn channels(&self, receiver: PublicKey) -> Result<Vec<(ChannelName, u64)>, BoxedError> {
+        Ok(self.list_channels(receiver).await?)
+    }
+
+    async fn purge(&self, receiver: PublicKey, channel: Option<ChannelName>) -> Result<(), BoxedError> {
+        Ok(MessagesInbox::purge(self, receiver, channel).await?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+/// Params of the client middleware's outbox.
+pub struct OutboxParams {
+    /// Messages not delivered within this time
+    /// are dropped. Not dropped if `None`.
+    /// 
+    /// Default is 1 day.
+    pub max_age: Option<Duration>,
+
+    /// Policy used to check if the failed sending can
+    /// be repeated and to get delays between flushes
+    /// of the failed messages. Amount of attempts
+    /// is not limited by the outbox.
+    pub retry_policy: RetryPolicy
+}
+
+impl OutboxParams {
+    #[inline]
+    pub fn with_max_age(self, max_age: Option<Duration>) -> Self {
+        Self {
+            max_age,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self
+        }
+    }
+}
+
+impl Default for OutboxParams {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_age: Some(Duration::from_secs(24 * 60 * 60)),
+            retry_policy: RetryPolicy::default()
+                .with_base_delay(Duration::from_secs(1))
+                .with_max_delay(Duration::from_secs(60))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Message waiting in the outbox.
+pub struct OutboxEntry {
+    pub receiver: PublicKey,
+    pub channel: ChannelName,
+
+    /// Message encrypted for the receiver.
+    pub message: Message,
+
+    /// UTC timestamp of the message's queueing.
+    pub queued_at: u64
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Event of the client middleware's outbox.
+pub enum OutboxEvent {
+    /// Message has failed to be sent and was queued.
+    Queued {
+        receiver: PublicKey,
+        channel: ChannelName
+    },
+
+    /// Queued message was finally delivered.
+    Delivered {
+        receiver: PublicKey,
+        channel: ChannelName,
+        queued_at: u64,
+        result: Box<SendToResult>
+    },
+
+    /// Queued message was not delivered within
+    /// the maximal age and was dropped.
+    Expired {
+        receiver: PublicKey,
+        channel: ChannelName,
+        queued_at: u64
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// Result of the outbox's flush.
+pub struct OutboxFlush {
+    /// Amount of delivered messages.
+    pub delivered: u64,
+
+    /// Amount of dropped expired messages.
+    pub expired: u64,
+
+    /// Amount of messages left in the outbox
+    /// because their channels have failed.
+    pub remaining: u64
+}
+
+#[derive(Clone)]
+/// Queue of the client's messages failed to be
+/// sent because of the transient errors.
+/// 
+/// Messages are stored in a messages inbox keyed by
+/// their receivers and channels, and are flushed in the
+/// order they were queued. When a message of a channel
+/// fails to be sent again the rest of the channel's
+/// messages are kept to preserve their order.
+/// 
+/// Refer to `ConnectedClient::with_outbox`.
+/// 
+/// Clones share the same queue.
+pub struct Outbox {
+    storage: Arc<dyn OutboxStorage>,
+    params: OutboxParams,
+    callback: Option<Arc<dyn Fn(OutboxEvent) + Send + Sync>>,
+
+    /// Notified when new messages are queued.
+    queued: Arc<Notify>,
+
+    /// Lock preventing concurrent flushes
+    /// from sending the same messages.
+    flush_lock: Arc<Mutex<()>>
+}
+
+impl Outbox {
+    /// Create outbox storing messages in the given inbox.
+    /// 
+    /// Inbox must support peeking messages.
+    pub fn new<I>(inbox: I) -> Self
+    where
+        I: MessagesInbox + Send + Sync + 'static,
+        I::Error: 'static
+    {
+        Self {
+            storage: Arc::new(inbox),
+            params: OutboxParams::default(),
+            callback: None,
+            queued: Arc::new(Notify::new()),
+            flush_lock: Arc::new(Mutex::new(()))
+        }
+    }
+
+    #[cfg(feature = "inbox-ram")]
+    #[inline]
+    /// Create outbox storing messages in memory.
+    pub fn memory() -> Self {
+        Self::new(crate::drivers::server::prelude::RamMessagesInbox::new())
+    }
+
+    #[cfg(feature = "inbox-stored-queue")]
+    /// Create outbox storing messages in the given folder.
+    /// 
+    /// Messages queued before restart are kept.
+    pub async fn stored(folder: impl Into<std::path::PathBuf>) -> std::io::Result<Self> {
+        let inbox = crate::drivers::server::prelude::StoredQueueMessagesInbox::new(folder, None).await?;
+
+        Ok(Self::new(inbox))
+    }
+
+    #[inline]
+    pub fn with_params(self, params: OutboxParams) -> Self {
+        Self {
+            params,
+            ..self
+        }
+    }
+
+    #[inline]
+    /// Call the given function on every outbox event.
+    pub fn with_callback(self, callback: impl Fn(OutboxEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Some(Arc::new(callback)),
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn params(&self) -> &OutboxParams {
+        &self.params
+    }
+
+    /// Check if the message failed to be
+    /// sent with the given error can be queued.
+    pub fn is_transient(&self, error: &Error) -> bool {
+        self.params.retry_policy.is_retryable(error)
+    }
+
+    fn notify(&self, event: OutboxEvent) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?event, "Outbox event");
+
+        if let Some(callback) = &self.callback {
+            callback(event);
+        }
+    }
+
+    /// Queue message to the receiver.
+    /// 
+    /// - `sender` must contain the client's record
+    ///   and the server it is connected to.
+    pub async fn enqueue(&self, sender: Sender, receiver: PublicKey, channel: ChannelName, message: Message) -> Result<(), OutboxError> {
+        let info = MessageInfo::new(sender, channel.clone(), message, timestamp());
+
+        self.storage.add(receiver.clone(), info).await
+            .map_err(OutboxError::Storage)?;
+
+        self.notify(OutboxEvent::Queued {
+            receiver,
+            channel
+        });
+
+        self.queued.notify_waiters();
+
+        Ok(())
+    }
+
+    /// List queued messages.
+    /// 
+    /// Messages of the same receiver's
+    /// channel are listed in order.
+    pub async fn entries(&self) -> Result<Vec<OutboxEntry>, OutboxError> {
+        let mut entries = Vec::new();
+
+        for receiver in self.storage.receivers().await.map_err(OutboxError::Storage)? {
+            let channels = self.storage.channels(receiver.clone()).await
+                .map_err(OutboxError::Storage)?;
+
+            for (channel, _) in channels {
+                let messages = self.storage.peek(receiver.clone(), channel, None).await
+                    .map_err(OutboxError::Storage)?
+                    .ok_or(OutboxError::PeekUnsupported)?;
+
+                entries.extend(messages.into_iter().map(|info| OutboxEntry {
+                    receiver: receiver.clone(),
+                    channel: info.channel,
+                    message: info.message,
+                    queued_at: info.received_at
+                }));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Get amount of queued messages.
+    pub async fn len(&self) -> Result<u64, OutboxError> {
+        let mut len = 0;
+
+        for receiver in self.storage.receivers().await.map_err(OutboxError::Storage)? {
+            len += self.storage.channels(receiver).await
+                .map_err(OutboxError::Storage)?
+                .into_iter()
+                .map(|(_, messages)| messages)
+                .sum::<u64>();
+        }
+
+        Ok(len)
+    }
+
+    #[inline]
+    pub async fn is_empty(&self) -> Result<bool, OutboxError> {
+        Ok(self.len().await? == 0)
+    }
+
+    /// Remove queued messages without sending them.
+    /// 
+    /// If `receiver` is set, only its messages are removed.
+    /// If `channel` is set, only messages of this channel
+    /// are removed.
+    pub async fn purge(&self, receiver: Option<PublicKey>, channel: Option<ChannelName>) -> Result<(), OutboxError> {
+        let receivers = match receiver {
+            Some(receiver) => vec![receiver],
+
+            None => self.storage.receivers().await
+                .map_err(OutboxError::Storage)?
+        };
+
+        for receiver in receivers {
+            self.storage.purge(receiver, channel.clone()).await
+                .map_err(OutboxError::Storage)?;
+        }
+
+        Ok(())
+    }
+
+    /// Try to send all the queued messages
+    /// using the given client.
+    /// 
+    /// Receivers are looked up again, so messages are
+    /// delivered even if their receivers were moved to
+    /// other servers while they were queued.
+    pub async fn flush<T: HttpClient>(&self, client: &ConnectedClient<T>) -> Result<OutboxFlush, OutboxError> {
+        let _lock = self.flush_lock.lock().await;
+
+        let mut flush = OutboxFlush::default();
+
+        for receiver in self.storage.receivers().await.map_err(OutboxError::Storage)? {
+            let channels = self.storage.channels(receiver.clone()).await
+                .map_err(OutboxError::Storage)?;
+
+            for (channel, mut remaining) in channels {
+                while remaining > 0 {
+                    let Some(info) = self.storage.peek(receiver.clone(), channel.clone(), Some(1)).await
+                        .map_err(OutboxError::Storage)?
+                        .ok_or(OutboxError::PeekUnsupported)?
+                        .pop() else {
+                            break;
+                        };
+
+                    let expired = self.params.max_age
+                        .is_some_and(|max_age| timestamp().saturating_sub(info.received_at) > max_age.as_secs());
+
+                    if expired {
+                        self.storage.remove_first(receiver.clone(), channel.clone()).await
+                            .map_err(OutboxError::Storage)?;
+
+                        remaining -= 1;
+                        flush.expired += 1;
+
+                        self.notify(OutboxEvent::Expired {
+                            receiver: receiver.clone(),
+                            channel: channel.clone(),
+                            queued_at: info.received_at
+                        });
+
+                        continue;
+                    }
+
+                    match Self::deliver(client, &receiver, &info).await {
+                        Ok(result) => {
+                            self.storage.remove_first(receiver.clone(), channel.clone()).await
+                                .map_err(OutboxError::Storage)?;
+
+                            remaining -= 1;
+                            flush.delivered += 1;
+
+                            self.notify(OutboxEvent::Delivered {
+                                receiver: receiver.clone(),
+                                channel: channel.clone(),
+                                queued_at: info.received_at,
+                                result: Box::new(result)
+                            });
+                        }
+
+                        // Keep the rest of the channel to preserve order
+                        Err(_err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::debug!(receiver = receiver.to_base64(), channel = channel.as_str(), "Failed to flush queued message: {_err}");
+
+                            flush.remaining += remaining;
+
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(flush)
+    }
+
+    async fn deliver<T: HttpClient>(client: &ConnectedClient<T>, receiver: &PublicKey, info: &MessageInfo) -> Result<SendToResult, Error> {
+        let (receiver, server, available) = client.lookup(receiver.clone(), None).await?
+            .ok_or_else(|| Error::Other(format!("Receiver {} is not found", receiver.to_base64()).into()))?;
+
+        let id = client.send(
+            format!("http://{}", server.address),
+            receiver.public_key.clone(),
+            info.channel.clone(),
+            info.message.clone()
+        ).await?;
+
+        Ok(SendToResult {
+            receiver,
+            server,
+            available,
+            id
+        })
+    }
+
+    /// Spawn task flushing the outbox using the given client.
+    /// 
+    /// The outbox is flushed when new messages are queued.
+    /// Failed messages are flushed again with delays
+    /// given by the params' retry policy.
+    pub fn spawn_flusher<T: HttpClient + 'static>(&self, client: ConnectedClient<T>) -> OutboxFlusher {
+        let outbox = self.clone();
+
+        OutboxFlusher(tokio::spawn(async move {
+            let mut failures = 0;
+
+            loop {
+                let queued = outbox.queued.notified();
+
+                tokio::pin!(queued);
+
+                // Messages queued during the flush are not missed
+                queued.as_mut().enable();
+
+                let remaining = match outbox.flush(&client).await {
+                    Ok(flush) => flush.remaining,
+
+                    Err(_err) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("Failed to flush outbox: {_err}");
+
+                        1
+                    }
+                };
+
+                if remaining == 0 {
+                    failures = 0;
+
+                    queued.await;
+                }
+
+                else {
+                    failures += 1;
+
+                    tokio::time::sleep(outbox.params.retry_policy.delay(failures)).await;
+                }
+            }
+        }))
+    }
+}
+
+impl std::fmt::Debug for Outbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Outbox")
+            .field("params", &self.params)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+impl PartialEq for Outbox {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.queued, &other.queued)
+    }
+}
+
+impl Eq for Outbox {}
+
+impl std::hash::Hash for Outbox {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.queued) as *const () as usize).hash(state);
+    }
+}
+
+#[derive(Debug)]
+/// Handle of the background outbox flushing task.
+/// 
+/// Task is aborted when the handle is dropped.
+pub struct OutboxFlusher(tokio::task::JoinHandle<()>);
+
+impl OutboxFlusher {
+    #[inline]
+    /// Stop flushing the outbox.
+    pub fn abort(self) {
+        drop(self);
+    }
+}
+
+impl Drop for OutboxFlusher {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
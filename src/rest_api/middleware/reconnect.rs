@@ -0,0 +1,142 @@
+use std::sync::{Arc, RwLock};
+
+use crate::rest_api::types::ConnectionCertificate;
+use crate::rest_api::status::ResponseStatus;
+use crate::rest_api::error_code::ErrorCode;
+
+use super::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Event of the client middleware's automatic reconnection.
+pub enum ReconnectEvent {
+    /// Server has forgotten the client's session,
+    /// connection request is being sent.
+    Reconnecting,
+
+    /// Client is connected again and
+    /// the failed call is replayed.
+    Reconnected,
+
+    /// Server has rejected the connection request.
+    Failed
+}
+
+#[derive(Clone, Default)]
+/// Automatic reconnection of the client middleware.
+/// 
+/// When the server forgets the client's session (e.g. it
+/// was restarted or the client's record is expired) the
+/// client's calls fail with the `ClientNotConnected` status
+/// or the `CertificateExpired` error code. Such calls are
+/// replayed after the client is connected to the same server
+/// again with a fresh connection certificate.
+/// 
+/// Disabled by default.
+pub struct AutoReconnect {
+    /// Maximal amount of reconnections made during
+    /// one call. Prevents infinite reconnection loops.
+    pub max_reconnects: u32,
+
+    callback: Option<Arc<dyn Fn(ReconnectEvent) + Send + Sync>>
+}
+
+impl AutoReconnect {
+    #[inline]
+    /// Reconnect at most given amount of times during one call.
+    pub fn new(max_reconnects: u32) -> Self {
+        Self {
+            max_reconnects,
+            callback: None
+        }
+    }
+
+    #[inline]
+    /// Don't reconnect the client.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    /// Call the given function on every reconnection event.
+    pub fn with_callback(self, callback: impl Fn(ReconnectEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Some(Arc::new(callback)),
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.max_reconnects > 0
+    }
+
+    /// Check if the call failed because the
+    /// server has forgotten the client's session.
+    pub fn is_session_lost(error: &Error) -> bool {
+        matches!(error,
+            Error::RequestFailed { status: ResponseStatus::ClientNotConnected, .. } |
+            Error::RequestFailed { code: ErrorCode::CertificateExpired, .. }
+        )
+    }
+
+    #[inline]
+    pub(crate) fn notify(&self, event: ReconnectEvent) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?event, "Automatic reconnection event");
+
+        if let Some(callback) = &self.callback {
+            callback(event);
+        }
+    }
+}
+
+impl std::fmt::Debug for AutoReconnect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AutoReconnect")
+            .field("max_reconnects", &self.max_reconnects)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+impl std::hash::Hash for AutoReconnect {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.max_reconnects.hash(state);
+
+        self.callback.as_ref()
+            .map(|callback| Arc::as_ptr(callback) as *const () as usize)
+            .hash(state);
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Connection certificate replaced on reconnection.
+/// 
+/// Clones share the same certificate.
+pub(crate) struct SharedCertificate(Arc<RwLock<ConnectionCertificate>>);
+
+impl SharedCertificate {
+    #[inline]
+    pub fn new(certificate: ConnectionCertificate) -> Self {
+        Self(Arc::new(RwLock::new(certificate)))
+    }
+
+    #[inline]
+    pub fn get(&self) -> ConnectionCertificate {
+        self.0.read()
+            .expect("Failed to lock connection certificate")
+            .clone()
+    }
+
+    #[inline]
+    pub fn set(&self, certificate: ConnectionCertificate) {
+        *self.0.write().expect("Failed to lock connection certificate") = certificate;
+    }
+}
+
+impl std::hash::Hash for SharedCertificate {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
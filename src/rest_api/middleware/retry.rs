@@ -0,0 +1,259 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::crypto::utils::safe_random_u64;
+use crate::rest_api::status::ResponseStatus;
+
+use super::Error;
+
+#[derive(Debug, Clone, Copy, Hash)]
+/// Policy of the client middleware's requests retrying.
+/// 
+/// Requests failed because of the transport errors or
+/// with retryable response statuses are repeated with
+/// exponentially growing delays.
+/// 
+/// `GET` requests are idempotent and repeated as is,
+/// while signed `POST` requests are crafted again on
+/// every attempt with a fresh proof seed, so they're
+/// not rejected by servers with replay protection.
+pub struct RetryPolicy {
+    /// Maximal amount of attempts, including the first one.
+    pub max_attempts: u32,
+
+    /// Delay before the first retry. It's doubled
+    /// on every next retry.
+    pub base_delay: Duration,
+
+    /// Maximal delay between attempts, without jitter.
+    pub max_delay: Duration,
+
+    /// Maximal random time added to every delay.
+    pub jitter: Duration,
+
+    /// Check if the request failed with the
+    /// given response status should be repeated.
+    pub retryable: fn(ResponseStatus) -> bool
+}
+
+impl RetryPolicy {
+    #[inline]
+    /// Policy making only one attempt.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    /// Policy making at most given amount of attempts.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    pub fn with_base_delay(self, base_delay: Duration) -> Self {
+        Self {
+            base_delay,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_max_delay(self, max_delay: Duration) -> Self {
+        Self {
+            max_delay,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_jitter(self, jitter: Duration) -> Self {
+        Self {
+            jitter,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_retryable(self, retryable: fn(ResponseStatus) -> bool) -> Self {
+        Self {
+            retryable,
+            ..self
+        }
+    }
+
+    /// Statuses retried by default: server errors,
+    /// rate limits and lookup timeouts.
+    pub fn is_retryable_status(status: ResponseStatus) -> bool {
+        matches!(status,
+            ResponseStatus::ServerError |
+            ResponseStatus::RateLimited |
+            ResponseStatus::TooManyRequests |
+            ResponseStatus::ClientLookupTimeout
+        )
+    }
+
+    /// Check if the request failed with the given error
+    /// should be repeated.
+    /// 
+    /// Transport errors and timeouts are always retried.
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        match error {
+            Error::RequestFailed { status, .. } => (self.retryable)(*status),
+            Error::Other(_) | Error::Timeout { .. } => true,
+
+            _ => false
+        }
+    }
+
+    /// Get delay before the given retry, starting from 1.
+    /// 
+    /// ```rust
+    /// use std::time::Duration;
+    /// 
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let policy = RetryPolicy::new(5)
+    ///     .with_base_delay(Duration::from_millis(100))
+    ///     .with_max_delay(Duration::from_millis(300))
+    ///     .with_jitter(Duration::ZERO);
+    /// 
+    /// assert_eq!(policy.delay(1), Duration::from_millis(100));
+    /// assert_eq!(policy.delay(2), Duration::from_millis(200));
+    /// assert_eq!(policy.delay(3), Duration::from_millis(300));
+    /// ```
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 1u32.checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+
+        let delay = self.base_delay.saturating_mul(factor)
+            .min(self.max_delay);
+
+        let jitter = self.jitter.as_millis() as u64;
+
+        if jitter == 0 {
+            return delay;
+        }
+
+        delay + Duration::from_millis(safe_random_u64() % (jitter + 1))
+    }
+
+    /// Run the operation until it succeeds, fails with
+    /// a non-retryable error or runs out of attempts.
+    /// 
+    /// - `name` must contain name of the operation
+    ///   used by the tracing events.
+    /// 
+    /// - `operation` must craft and perform
+    ///   the request from scratch.
+    pub async fn run<T, F, Fut>(&self, name: &str, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>
+    {
+        let mut attempt = 1;
+
+        loop {
+            match operation().await {
+                Err(err) if attempt < self.max_attempts && self.is_retryable(&err) => {
+                    let delay = self.delay(attempt);
+
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(name, attempt, ?delay, "Request failed, retrying: {err}");
+
+                    #[cfg(not(feature = "tracing"))]
+                    let _ = name;
+
+                    tokio::time::sleep(delay).await;
+
+                    attempt += 1;
+                }
+
+                result => return result
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: Duration::from_millis(100),
+            retryable: Self::is_retryable_status
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use crate::rest_api::error_code::ErrorCode;
+
+    use super::*;
+
+    fn failure(status: ResponseStatus) -> Error {
+        Error::RequestFailed {
+            status,
+            code: ErrorCode::Internal,
+            reason: String::new()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn run() {
+        let policy = RetryPolicy::new(3);
+        let attempts = AtomicU32::new(0);
+
+        // Retryable failures are repeated
+        let result = policy.run("test", || async {
+            match attempts.fetch_add(1, Ordering::Relaxed) {
+                0 => Err(Error::Other("connection refused".into())),
+                1 => Err(failure(ResponseStatus::ServerError)),
+
+                _ => Ok(())
+            }
+        }).await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.swap(0, Ordering::Relaxed), 3);
+
+        // Other failures are returned immediately
+        let result = policy.run("test", || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+
+            Err::<(), _>(failure(ResponseStatus::Forbidden))
+        }).await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.swap(0, Ordering::Relaxed), 1);
+
+        // Attempts are limited
+        let result = policy.run("test", || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+
+            Err::<(), _>(failure(ResponseStatus::TooManyRequests))
+        }).await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.swap(0, Ordering::Relaxed), 3);
+
+        let result = RetryPolicy::none().run("test", || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+
+            Err::<(), _>(Error::Other("connection refused".into()))
+        }).await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,14 +1,39 @@
 use std::net::ToSocketAddrs;
 use std::sync::Arc;
+use std::collections::HashMap;
 
+#[cfg(any(feature = "router-cleanup", feature = "health-checks"))]
+use std::time::Duration;
+
+use crate::crypto::asymmetric::PublicKey;
 use crate::http::client::HttpClient;
 use crate::http::server::HttpServer;
 
 use crate::drivers::server::prelude::*;
 
 use crate::rest_api::prelude::*;
+use crate::rest_api::pagination::{paginate, table_version};
+
+use crate::time::timestamp;
+
+use super::interceptor::InterceptedRoutes;
+
+#[cfg(feature = "announce-fanout")]
+use super::fanout::{AnnounceFanoutWorker, AnnounceFanoutStats};
+
+#[cfg(feature = "webhooks")]
+use super::webhooks::{WebhookWorker, WebhookEvent, WebhookStats};
+
+#[cfg(feature = "admin-api")]
+use super::admin::AdminAuth;
+
+#[cfg(feature = "mdns")]
+use crate::discovery::{Advertiser, ServiceInfo};
+
+#[cfg(feature = "health-checks")]
+use crate::drivers::server::health::check_servers_health;
 
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone)]
 /// Server HTTP middleware
 /// 
 /// This struct is used to process HTTP REST API requests
@@ -16,13 +41,24 @@ use crate::rest_api::prelude::*;
 pub struct Server<HttpClientExt, HttpServerExt, RouterExt, TraversalExt, MessagesInboxExt> {
     http_client: HttpClientExt,
     http_server: HttpServerExt,
-    driver: Arc<ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>>
+    driver: Arc<ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>>,
+    interceptors: Interceptors,
+    access_log: AccessLogHook,
+
+    #[cfg(feature = "announce-fanout")]
+    fanout: Arc<AnnounceFanoutWorker<HttpClientExt>>,
+
+    #[cfg(feature = "webhooks")]
+    webhooks: Arc<WebhookWorker<HttpClientExt>>,
+
+    #[cfg(feature = "maintenance")]
+    maintenance: Option<MaintenanceConfig>
 }
 
 impl<HttpClientExt, HttpServerExt, RouterExt, TraversalExt, MessagesInboxExt>
     Server<HttpClientExt, HttpServerExt, RouterExt, TraversalExt, MessagesInboxExt>
 where
-    HttpClientExt: HttpClient,
+    HttpClientExt: HttpClient + 'static,
     HttpServerExt: HttpServer,
     RouterExt: Router + Send + Sync + 'static,
     TraversalExt: Traversal + Send + Sync + 'static,
@@ -45,80 +81,326 @@ where
             "Building server REST API middleware"
         );
 
+        #[cfg(feature = "announce-fanout")]
+        let fanout = Arc::new(AnnounceFanoutWorker::new(
+            http_client.clone(),
+            server_driver.params().announce_fanout,
+            server_driver.params().secret_key.clone(),
+            &server_driver.params().address
+        ));
+
+        #[cfg(feature = "webhooks")]
+        let webhooks = Arc::new(WebhookWorker::new(
+            http_client.clone(),
+            server_driver.params().webhooks.clone()
+        ));
+
+        http_server.set_body_limits(server_driver.params().body_limits.clone());
+        http_server.set_cors(server_driver.params().cors.clone());
+
         let driver = Arc::new(server_driver);
+        let started_at = std::time::Instant::now();
+
+        let interceptors = Interceptors::default();
+        let access_log = AccessLogHook::default();
+
+        let mut routes = InterceptedRoutes::new(
+            &mut http_server,
+            interceptors.clone(),
+            driver.rate_limiter().cloned(),
+            access_log.clone()
+        );
 
-        http_server.get("/api/v1/info", {
+        routes.get("/api/v1/info", {
             let driver = driver.clone();
 
             |client_address| async move {
                 #[cfg(feature = "tracing")]
                 tracing::trace!(?client_address, "GET /api/v1/info");
 
-                InfoResponse::new(&driver.params().secret_key)
+                let response = InfoResponse::new(&driver.params().secret_key)
+                    .with_capabilities(&driver.params().secret_key, driver.capabilities());
+
+                let response = response.with_connect_difficulty(driver.params().connect_difficulty);
+
+                match driver.params().certificate_lifetime {
+                    Some(lifetime) => response.with_certificate_lifetime(lifetime.as_secs()),
+                    None => response
+                }
             }
         }).await;
 
-        http_server.get("/api/v1/clients", {
+        routes.get("/api/v1/stats", {
             let driver = driver.clone();
 
-            |client_address| async move {
+            move |client_address| async move {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(?client_address, "GET /api/v1/stats");
+
+                let privacy = driver.params().stats_privacy;
+
+                let metrics = if privacy.hide_clients && privacy.hide_servers {
+                    None
+                } else {
+                    match driver.router().metrics().await {
+                        Ok(metrics) => Some(metrics),
+
+                        Err(_err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!("Failed to collect router metrics: {_err}");
+
+                            None
+                        }
+                    }
+                };
+
+                let inbox = if privacy.hide_inbox {
+                    None
+                } else {
+                    match driver.inbox_stats().await {
+                        Ok(stats) => Some(stats),
+
+                        Err(_err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!("Failed to collect inbox stats: {_err}");
+
+                            None
+                        }
+                    }
+                };
+
+                StatsResponse::new(
+                    &driver.params().secret_key,
+                    started_at.elapsed().as_secs(),
+                    metrics.filter(|_| !privacy.hide_clients).map(|metrics| metrics.local_clients),
+                    metrics.filter(|_| !privacy.hide_servers).map(|metrics| metrics.servers),
+                    inbox
+                )
+            }
+        }).await;
+
+        routes.get_with_query("/api/v1/clients", {
+            let driver = driver.clone();
+
+            |client_address, query| async move {
                 #[cfg(feature = "tracing")]
-                tracing::trace!(?client_address, "GET /api/v1/clients");
+                tracing::trace!(?client_address, ?query, "GET /api/v1/clients");
+
+                let request = PageRequest::from_query(&query)
+                    .map_err(|err| err.to_string())?;
+
+                let client_type = match query.get("client_type") {
+                    Some(client_type) => Some(client_type.parse::<ClientType>()
+                        .map_err(|_| PaginationError::InvalidParameter("client_type").to_string())?),
+
+                    None => None
+                };
+
+                // Continuation tokens point to the cursor key
+                // so the whole filtered list is needed
+                if request.token.is_some() {
+                    let (clients, total) = driver.router()
+                        .local_clients_filtered(client_type, 0, None).await
+                        .unwrap_or_default();
 
-                let clients = driver.router()
-                    .local_clients().await
+                    let page = paginate(
+                        &driver.params().secret_key,
+                        clients,
+                        |client| client.public_key.to_base64(),
+                        &request
+                    ).map_err(|err| err.to_string())?;
+
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!("GET /api/v1/clients: returned {} records", page.items.len());
+
+                    return Ok(ClientsResponse::page(page).with_total(total));
+                }
+
+                let offset = request.offset.unwrap_or(0);
+
+                let (clients, total) = driver.router()
+                    .local_clients_filtered(client_type, offset, request.limit).await
                     .unwrap_or_default();
 
                 #[cfg(feature = "tracing")]
                 tracing::trace!("GET /api/v1/clients: returned {} records", clients.len());
 
-                ClientsResponse::new(clients)
+                // Keep legacy response if no pagination was requested
+                if request.is_empty() {
+                    return Ok(ClientsResponse::new(clients).with_total(total));
+                }
+
+                // Router returns only the requested slice
+                // so the token is stamped by its keys
+                let next = match clients.last() {
+                    Some(last) if offset + (clients.len() as u64) < total => {
+                        let keys = clients.iter()
+                            .map(|client| client.public_key.to_base64())
+                            .collect::<Vec<_>>();
+
+                        let version = table_version(keys.iter().map(String::as_str));
+
+                        Some(ContinuationToken::new(last.public_key.to_base64(), version).sign(&driver.params().secret_key))
+                    }
+
+                    _ => None
+                };
+
+                Ok(ClientsResponse::page(Page::new(clients, next)).with_total(total))
             }
         }).await;
 
-        http_server.get("/api/v1/servers", {
+        routes.get_with_query("/api/v1/servers", {
             let driver = driver.clone();
 
-            |client_address| async move {
+            |client_address, query| async move {
                 #[cfg(feature = "tracing")]
-                tracing::trace!(?client_address, "GET /api/v1/servers");
+                tracing::trace!(?client_address, ?query, "GET /api/v1/servers");
+
+                let request = PageRequest::from_query(&query)
+                    .map_err(|err| err.to_string())?;
 
-                let servers = driver.router()
-                    .servers().await
+                // Continuation tokens point to the cursor key
+                // so the whole list is needed
+                if request.token.is_some() {
+                    let (servers, total) = driver.router()
+                        .servers_page(0, None).await
+                        .unwrap_or_default();
+
+                    let page = paginate(
+                        &driver.params().secret_key,
+                        servers,
+                        |server| server.public_key.to_base64(),
+                        &request
+                    ).map_err(|err| err.to_string())?;
+
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!("GET /api/v1/servers: returned {} records", page.items.len());
+
+                    return Ok(ServersResponse::page(page).with_total(total));
+                }
+
+                let offset = request.offset.unwrap_or(0);
+
+                let (servers, total) = driver.router()
+                    .servers_page(offset, request.limit).await
                     .unwrap_or_default();
 
                 #[cfg(feature = "tracing")]
                 tracing::trace!("GET /api/v1/servers: returned {} records", servers.len());
 
-                ServersResponse::new(servers)
+                // Keep legacy response if no pagination was requested
+                if request.is_empty() {
+                    return Ok(ServersResponse::new(servers).with_total(total));
+                }
+
+                // Router returns only the requested slice
+                // so the token is stamped by its keys
+                let next = match servers.last() {
+                    Some(last) if offset + (servers.len() as u64) < total => {
+                        let keys = servers.iter()
+                            .map(|server| server.public_key.to_base64())
+                            .collect::<Vec<_>>();
+
+                        let version = table_version(keys.iter().map(String::as_str));
+
+                        Some(ContinuationToken::new(last.public_key.to_base64(), version).sign(&driver.params().secret_key))
+                    }
+
+                    _ => None
+                };
+
+                Ok(ServersResponse::page(Page::new(servers, next)).with_total(total))
             }
         }).await;
 
-        http_server.post::<ConnectRequest, ConnectResponse, _>("/api/v1/connect", {
+        routes.post::<ConnectRequest, ConnectResponse, _>("/api/v1/connect", {
             let driver = driver.clone();
 
+            #[cfg(feature = "announce-fanout")]
+            let fanout = fanout.clone();
+
+            #[cfg(feature = "webhooks")]
+            let webhooks = webhooks.clone();
+
             |client_address, request: ConnectRequest| async move {
                 #[cfg(feature = "tracing")]
                 tracing::trace!(?client_address, "POST /api/v1/connect");
 
                 // Validate incoming request
-                let validated = match request.validate(&driver.params().secret_key.public_key()) {
-                    Ok(validated) => validated,
+                let validated = request.validate(&driver.params().secret_key.public_key());
 
-                    Err(err) => return ConnectResponse::error(
-                        ResponseStatus::ServerError,
-                        format!("Failed to validate request: {err}")
-                    )
-                };
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/connect", &request.0, validated, Some(Incident::InvalidSignature)).await {
+                    return ConnectResponse::error(status, code, reason);
+                }
+
+                // Check the connection certificate's expiration date
+                if driver.is_certificate_expired(&request.0.request.certificate) {
+                    return ConnectResponse::error(
+                        ResponseStatus::CertificateExpired,
+                        ErrorCode::CertificateExpired,
+                        "Connection certificate is expired"
+                    );
+                }
+
+                // Renew the certificate of already connected client.
+                // Inbox is stored by the client's key so its queued
+                // messages are kept
+                let renewed = driver.client_certificate(&request.0.public_key).await;
+
+                if let Some(certificate) = &renewed {
+                    if certificate.token.auth_date > request.0.request.certificate.token.auth_date {
+                        return ConnectResponse::error(
+                            ResponseStatus::RequestValidationFailed,
+                            ErrorCode::ValidationFailed,
+                            "Connection certificate is older than the current one"
+                        );
+                    }
+                }
 
-                // Check if request is valid
-                if !validated {
+                // Only new clients must solve the challenge
+                let difficulty = driver.params().connect_difficulty;
+
+                if renewed.is_none() && !request.validate_work(difficulty) {
                     return ConnectResponse::error(
-                        ResponseStatus::RequestValidationFailed,
-                        "Request validation failed"
+                        ResponseStatus::InsufficientWork,
+                        ErrorCode::ValidationFailed,
+                        format!("Proof of work of difficulty {difficulty} is required")
                     );
                 }
 
+                // Register client's alias before indexing
+                // so the taken aliases don't connect the client
+                if let Some(alias) = &request.0.request.alias {
+                    if let Err(err) = alias.validate() {
+                        return ConnectResponse::error(
+                            ResponseStatus::InvalidClientAlias,
+                            ErrorCode::InvalidRequest,
+                            format!("Invalid client alias: {err}")
+                        );
+                    }
+
+                    match driver.router().register_alias(alias, &request.0.public_key).await {
+                        Ok(true) => (),
+
+                        Ok(false) => return ConnectResponse::error(
+                            ResponseStatus::ClientAliasTaken,
+                            ErrorCode::Conflict,
+                            format!("Client alias '{alias}' is not available")
+                        ),
+
+                        Err(err) => {
+                            let status = driver.router().error_status(&err);
+
+                            return ConnectResponse::error(
+                                status,
+                                ErrorCode::from(status),
+                                format!("Failed to register client alias: {err}")
+                            );
+                        }
+                    }
+                }
+
                 // Index client in the routing table
                 let client = Client::new(
                     request.0.public_key,
@@ -130,16 +412,42 @@ where
                 tracing::trace!(
                     client_public = client.public_key.to_base64(),
                     client_info = std::any::type_name_of_val(&client.info),
+                    renewed = renewed.is_some(),
                     "POST /api/v1/connect: indexing local client"
                 );
 
-                if let Err(err) = driver.router().index_local_client(client).await {
+                if let Err(err) = driver.router().index_local_client(client.clone()).await {
+                    let status = driver.router().error_status(&err);
+
                     return ConnectResponse::error(
-                        ResponseStatus::ServerError,
+                        status,
+                        ErrorCode::from(status),
                         format!("Failed to index local client: {err}")
                     );
                 }
 
+                #[cfg(feature = "webhooks")]
+                if renewed.is_none() {
+                    webhooks.notify(WebhookEvent::ClientConnected {
+                        client: client.public_key.clone()
+                    });
+                }
+
+                // Announce connected client to other servers
+                #[cfg(feature = "announce-fanout")]
+                if fanout.is_enabled() {
+                    match driver.router().servers().await {
+                        Ok(servers) => {
+                            fanout.announce(client, servers);
+                        }
+
+                        Err(_err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!("POST /api/v1/connect: failed to get known servers for announce fan-out: {_err}");
+                        }
+                    }
+                }
+
                 ConnectResponse::success(
                     ResponseStatus::Success,
                     &driver.params().secret_key,
@@ -148,7 +456,7 @@ where
             }
         }).await;
 
-        http_server.post::<DisconnectRequest, DisconnectResponse, _>("/api/v1/disconnect", {
+        routes.post::<DisconnectRequest, DisconnectResponse, _>("/api/v1/disconnect", {
             let driver = driver.clone();
 
             |client_address, request: DisconnectRequest| async move {
@@ -156,21 +464,40 @@ where
                 tracing::trace!(?client_address, "POST /api/v1/disconnect");
 
                 // Validate incoming request
-                let validated = match request.validate() {
-                    Ok(validated) => validated,
+                let validated = request.validate();
 
-                    Err(err) => return DisconnectResponse::error(
-                        ResponseStatus::ServerError,
-                        format!("Failed to validate request: {err}")
-                    )
-                };
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/disconnect", &request.0, validated, None).await {
+                    return DisconnectResponse::error(status, code, reason);
+                }
 
-                // Check if request is valid
-                if !validated {
-                    return DisconnectResponse::error(
-                        ResponseStatus::RequestValidationFailed,
-                        "Request validation failed"
+                // Check the client's connection certificate
+                if let Some(certificate) = driver.client_certificate(&request.0.public_key).await {
+                    if driver.is_certificate_expired(&certificate) {
+                        return DisconnectResponse::error(
+                            ResponseStatus::CertificateExpired,
+                            ErrorCode::CertificateExpired,
+                            "Connection certificate is expired"
+                        );
+                    }
+                }
+
+                // Purge the inbox only for validated requests
+                if request.0.request.purge_inbox {
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(
+                        client_public = request.0.public_key.to_base64(),
+                        "POST /api/v1/disconnect: purging client's inbox"
                     );
+
+                    if let Err(err) = driver.messages_inbox().purge(request.0.public_key.clone(), None).await {
+                        let status = driver.messages_inbox().error_status(&err);
+
+                        return DisconnectResponse::error(
+                            status,
+                            ErrorCode::from(status),
+                            format!("Failed to purge client's inbox: {err}")
+                        );
+                    }
                 }
 
                 #[cfg(feature = "tracing")]
@@ -182,6 +509,7 @@ where
                 if let Err(err) = driver.router().disconnect(&request.0.public_key).await {
                     return DisconnectResponse::error(
                         ResponseStatus::ServerError,
+                        ErrorCode::Internal,
                         format!("Failed to disconnect client: {err}")
                     );
                 }
@@ -194,53 +522,57 @@ where
             }
         }).await;
 
-        http_server.post::<AnnounceRequest, AnnounceResponse, _>("/api/v1/announce", {
+        routes.post::<HeartbeatRequest, HeartbeatResponse, _>("/api/v1/heartbeat", {
             let driver = driver.clone();
 
-            |client_address, request: AnnounceRequest| async move {
+            |client_address, request: HeartbeatRequest| async move {
                 #[cfg(feature = "tracing")]
-                tracing::trace!(?client_address, "POST /api/v1/announce");
+                tracing::trace!(?client_address, "POST /api/v1/heartbeat");
 
                 // Validate incoming request
-                let validated = match request.validate() {
-                    Ok(validated) => validated,
+                let validated = request.validate();
+
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/heartbeat", &request.0, validated, None).await {
+                    return HeartbeatResponse::error(status, code, reason);
+                }
+
+                #[cfg(feature = "tracing")]
+                tracing::trace!(
+                    client_public = request.0.public_key.to_base64(),
+                    "POST /api/v1/heartbeat: updating client's last seen time"
+                );
 
-                    Err(err) => return AnnounceResponse::error(
+                let touched = match driver.router().touch_local_client(&request.0.public_key).await {
+                    Ok(touched) => touched,
+
+                    Err(err) => return HeartbeatResponse::error(
                         ResponseStatus::ServerError,
-                        format!("Failed to validate request: {err}")
+                        ErrorCode::Internal,
+                        format!("Failed to update client's last seen time: {err}")
                     )
                 };
 
-                // Check if request is valid
-                if !validated {
-                    return AnnounceResponse::error(
-                        ResponseStatus::RequestValidationFailed,
-                        "Request validation failed"
-                    );
-                }
+                // Routers without last seen tracking never touch
+                // the client, so check that it's still connected
+                if !touched {
+                    match driver.router().lookup_local_client(&request.0.public_key, None).await {
+                        Ok(Some(_)) => (),
 
-                // Index client in the routing table
-                match request.0.request {
-                    AnnounceRequestBody::Client { client, server } => {
-                        if let Err(err) = driver.router().index_remote_client(client, server).await {
-                            return AnnounceResponse::error(
-                                ResponseStatus::ServerError,
-                                format!("Failed to index remote client: {err}")
-                            );
-                        }
-                    }
+                        Ok(None) => return HeartbeatResponse::error(
+                            ResponseStatus::ClientNotConnected,
+                            ErrorCode::NotFound,
+                            "Client is not connected to the server"
+                        ),
 
-                    AnnounceRequestBody::Server { server } => {
-                        if let Err(err) = driver.router().index_server(server).await {
-                            return AnnounceResponse::error(
-                                ResponseStatus::ServerError,
-                                format!("Failed to index server: {err}")
-                            );
-                        }
+                        Err(err) => return HeartbeatResponse::error(
+                            ResponseStatus::ServerError,
+                            ErrorCode::Internal,
+                            format!("Failed to lookup client: {err}")
+                        )
                     }
                 }
 
-                AnnounceResponse::success(
+                HeartbeatResponse::success(
                     ResponseStatus::Success,
                     &driver.params().secret_key,
                     request.0.proof_seed
@@ -248,33 +580,183 @@ where
             }
         }).await;
 
-        http_server.post::<LookupRequest, LookupResponse, _>("/api/v1/lookup", {
+        routes.post::<AnnounceRequest, AnnounceResponse, _>("/api/v1/announce", {
             let driver = driver.clone();
 
-            |client_address, request: LookupRequest| async move {
+            #[cfg(feature = "announce-fanout")]
+            let fanout = fanout.clone();
+
+            #[cfg(feature = "webhooks")]
+            let webhooks = webhooks.clone();
+
+            |client_address, request: AnnounceRequest| async move {
                 #[cfg(feature = "tracing")]
-                tracing::trace!(?client_address, "POST /api/v1/lookup");
+                tracing::trace!(?client_address, "POST /api/v1/announce");
 
                 // Validate incoming request
-                let validated = match request.validate() {
-                    Ok(validated) => validated,
+                let validated = request.validate();
 
-                    Err(err) => return LookupResponse::error(
-                        ResponseStatus::ServerError,
-                        format!("Failed to validate request: {err}")
-                    )
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/announce", &request.0, validated, Some(Incident::InvalidAnnounce)).await {
+                    return AnnounceResponse::error(status, code, reason);
+                }
+
+                let announcer = request.0.public_key;
+                let proof_seed = request.0.proof_seed;
+
+                let entries = match request.0.request {
+                    AnnounceRequestBody::Bulk { entries } => entries,
+
+                    // Keep legacy response for single entry announces
+                    entry => {
+                        #[cfg(feature = "webhooks")]
+                        let event = announce_event(&entry);
+
+                        #[cfg(feature = "announce-fanout")]
+                        let forwarded = entry.clone();
+
+                        return match announce_entry(&driver, &announcer, entry).await {
+                            AnnounceEntryResult::Accepted => {
+                                #[cfg(feature = "webhooks")]
+                                if let Some(event) = event {
+                                    webhooks.notify(event);
+                                }
+
+                                // Gossip the record to other servers
+                                #[cfg(feature = "announce-fanout")]
+                                forward_entries(&fanout, &driver, &announcer, [forwarded]).await;
+
+                                AnnounceResponse::success(
+                                    ResponseStatus::Success,
+                                    &driver.params().secret_key,
+                                    proof_seed
+                                )
+                            }
+
+                            AnnounceEntryResult::Rejected { status, reason } => AnnounceResponse::error(status, ErrorCode::from(status), reason)
+                        };
+                    }
                 };
 
-                // Check if request is valid
-                if !validated {
-                    return LookupResponse::error(
-                        ResponseStatus::RequestValidationFailed,
-                        "Request validation failed"
+                let max_entries = driver.params().announce_limits.max_entries;
+
+                if entries.len() > max_entries {
+                    return AnnounceResponse::error(
+                        ResponseStatus::TooManyAnnounceEntries,
+                        ErrorCode::QuotaExceeded,
+                        format!("Bulk announce can contain at most {max_entries} entries")
                     );
                 }
 
+                #[cfg(feature = "webhooks")]
+                let events = entries.iter()
+                    .map(announce_event)
+                    .collect::<Vec<_>>();
+
+                #[cfg(feature = "announce-fanout")]
+                let forwarded = entries.clone();
+
+                let results = announce_entries(&driver, &announcer, entries).await;
+
+                #[cfg(feature = "webhooks")]
+                for (result, event) in results.iter().zip(events) {
+                    if let (true, Some(event)) = (result.is_accepted(), event) {
+                        webhooks.notify(event);
+                    }
+                }
+
+                // Gossip accepted records to other servers
+                #[cfg(feature = "announce-fanout")]
+                {
+                    let accepted = forwarded.into_iter()
+                        .zip(results.iter().map(AnnounceEntryResult::is_accepted))
+                        .filter_map(|(entry, accepted)| accepted.then_some(entry))
+                        .collect::<Vec<_>>();
+
+                    forward_entries(&fanout, &driver, &announcer, accepted).await;
+                }
+
+                AnnounceResponse::bulk(&driver.params().secret_key, proof_seed, results)
+            }
+        }).await;
+
+        routes.post::<LookupRequest, LookupResponse, _>("/api/v1/lookup", {
+            let driver = driver.clone();
+
+            |client_address, request: LookupRequest| async move {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(?client_address, "POST /api/v1/lookup");
+
+                // Validate incoming request
+                let validated = request.validate();
+
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/lookup", &request.0, validated, None).await {
+                    return LookupResponse::error(status, code, reason);
+                }
+
+                // Check the requester's certificate
+                if let Some(certificate) = driver.client_certificate(&request.0.public_key).await {
+                    if driver.is_certificate_expired(&certificate) {
+                        return LookupResponse::error(
+                            ResponseStatus::CertificateExpired,
+                            ErrorCode::CertificateExpired,
+                            "Connection certificate is expired"
+                        );
+                    }
+
+                    if let Some(scope) = certificate.scope {
+                        if let Err(err) = scope.check(CertificateOperation::Lookup, None) {
+                            return LookupResponse::error(ResponseStatus::Unauthorized, ErrorCode::Forbidden, err.to_string());
+                        }
+                    }
+                }
+
+                let client_type = request.0.request.client_type();
+
+                let public_key = match &request.0.request {
+                    LookupRequestBody::PublicKey { public_key, .. } => public_key.clone(),
+
+                    // Aliases are registered only by local clients
+                    LookupRequestBody::Alias { alias, .. } => {
+                        let resolved = match driver.router().resolve_alias(alias).await {
+                            Ok(resolved) => resolved,
+
+                            Err(err) => return LookupResponse::error(
+                                ResponseStatus::ServerError,
+                                ErrorCode::Internal,
+                                format!("Failed to resolve client alias: {err}")
+                            )
+                        };
+
+                        let found = match resolved {
+                            Some(public_key) => driver.router().lookup_local_client(&public_key, client_type).await,
+                            None => Ok(None)
+                        };
+
+                        return match found {
+                            Ok(Some((client, available))) => LookupResponse::success(
+                                ResponseStatus::Success,
+                                &driver.params().secret_key,
+                                request.0.proof_seed,
+                                LookupResponseBody::local(client, available)
+                            ),
+
+                            Ok(None) => LookupResponse::error(
+                                ResponseStatus::ClientNotFound,
+                                ErrorCode::NotFound,
+                                format!("Client with alias '{alias}' is not connected")
+                            ),
+
+                            Err(err) => LookupResponse::error(
+                                ResponseStatus::ServerError,
+                                ErrorCode::Internal,
+                                format!("Failed to lookup local client: {err}")
+                            )
+                        };
+                    }
+                };
+
                 // Try to find the client in the local index
-                match driver.router().lookup_local_client(&request.0.public_key, request.0.request.client_type).await {
+                match driver.router().lookup_local_client(&public_key, client_type).await {
                     Ok(Some((client, available))) => {
                         let body = LookupResponseBody::local(client, available);
 
@@ -288,6 +770,7 @@ where
 
                     Err(err) => return LookupResponse::error(
                         ResponseStatus::ServerError,
+                        ErrorCode::Internal,
                         format!("Failed to lookup local client: {err}")
                     ),
 
@@ -295,7 +778,7 @@ where
                 }
 
                 // Try to find the client in the remote index
-                match driver.router().lookup_remote_client(&request.0.public_key, request.0.request.client_type).await {
+                match driver.router().lookup_remote_client(&public_key, client_type).await {
                     Ok(Some((client, server, available))) => {
                         let body = LookupResponseBody::remote(client, server, available);
 
@@ -309,6 +792,7 @@ where
 
                     Err(err) => return LookupResponse::error(
                         ResponseStatus::ServerError,
+                        ErrorCode::Internal,
                         format!("Failed to lookup remote client: {err}")
                     ),
 
@@ -317,7 +801,7 @@ where
 
                 // Return searching hint if neither local nor known remote record found
                 let hint = driver.router()
-                    .lookup_remote_client_hint(&request.0.public_key, request.0.request.client_type)
+                    .lookup_remote_client_hint(&public_key, client_type)
                     .await;
 
                 match hint {
@@ -325,108 +809,660 @@ where
                         ResponseStatus::Success,
                         &driver.params().secret_key,
                         request.0.proof_seed,
-                        LookupResponseBody::hint(hint)
+                        LookupResponseBody::scored_hint(hint)
                     ),
 
                     Err(err) => LookupResponse::error(
                         ResponseStatus::ServerError,
+                        ErrorCode::Internal,
                         format!("Failed to lookup remote client hint: {err}")
                     )
                 }
             }
         }).await;
 
-        http_server.post::<SendRequest, SendResponse, _>("/api/v1/send", {
+        routes.post::<LookupBatchRequest, LookupBatchResponse, _>("/api/v1/lookup_batch", {
             let driver = driver.clone();
 
-            |client_address, request: SendRequest| async move {
+            |client_address, request: LookupBatchRequest| async move {
                 #[cfg(feature = "tracing")]
-                tracing::trace!(?client_address, "POST /api/v1/send");
+                tracing::trace!(?client_address, entries = request.0.request.entries.len(), "POST /api/v1/lookup_batch");
 
                 // Validate incoming request
-                let validated = match request.validate() {
-                    Ok(validated) => validated,
+                let validated = request.validate();
 
-                    Err(err) => return SendResponse::error(
-                        ResponseStatus::ServerError,
-                        format!("Failed to validate request: {err}")
-                    )
-                };
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/lookup_batch", &request.0, validated, None).await {
+                    return LookupBatchResponse::error(status, code, reason);
+                }
 
-                // Check if request is valid
-                if !validated {
-                    return SendResponse::error(
-                        ResponseStatus::RequestValidationFailed,
-                        "Request validation failed"
+                // Check the requester's certificate
+                if let Some(certificate) = driver.client_certificate(&request.0.public_key).await {
+                    if driver.is_certificate_expired(&certificate) {
+                        return LookupBatchResponse::error(
+                            ResponseStatus::CertificateExpired,
+                            ErrorCode::CertificateExpired,
+                            "Connection certificate is expired"
+                        );
+                    }
+
+                    if let Some(scope) = certificate.scope {
+                        if let Err(err) = scope.check(CertificateOperation::Lookup, None) {
+                            return LookupBatchResponse::error(ResponseStatus::Unauthorized, ErrorCode::Forbidden, err.to_string());
+                        }
+                    }
+                }
+
+                // Check the batch size
+                let max_batch_size = driver.params().max_lookup_batch_size;
+                let batch_size = request.0.request.entries.len();
+
+                if batch_size > max_batch_size {
+                    return LookupBatchResponse::error(
+                        ResponseStatus::BatchTooLarge,
+                        ErrorCode::QuotaExceeded,
+                        format!("Batch is too large: {batch_size} clients, at most {max_batch_size} allowed")
                     );
                 }
 
-                // Add message to the inbox
-                let result = driver.messages_inbox().add_message(
-                    request.0.request.sender,
-                    request.0.request.receiver_public,
-                    request.0.request.channel,
-                    request.0.request.message
-                ).await;
+                let mut resolved = HashMap::<_, LookupBatchResult>::with_capacity(batch_size);
+                let mut results = Vec::with_capacity(batch_size);
 
-                match result {
-                    Ok(()) => SendResponse::success(
-                        ResponseStatus::Success,
-                        &driver.params().secret_key,
-                        request.0.proof_seed
-                    ),
+                for entry in request.0.request.entries {
+                    // Duplicated entries are resolved only once
+                    if let Some(result) = resolved.get(&entry) {
+                        results.push(result.clone());
 
-                    Err(err) => SendResponse::error(
-                        ResponseStatus::ServerError,
-                        format!("Failed to index message: {err}")
-                    )
+                        continue;
+                    }
+
+                    let result = match lookup_batch_entry(&driver, &entry.0, entry.1).await {
+                        Ok(result) => result,
+                        Err(reason) => return LookupBatchResponse::error(ResponseStatus::ServerError, ErrorCode::Internal, reason)
+                    };
+
+                    results.push(result.clone());
+                    resolved.insert(entry, result);
                 }
+
+                LookupBatchResponse::success(
+                    ResponseStatus::Success,
+                    &driver.params().secret_key,
+                    request.0.proof_seed,
+                    LookupBatchResponseBody::new(results)
+                )
             }
         }).await;
 
-        http_server.post::<PollRequest, PollResponse, _>("/api/v1/poll", {
+        routes.post::<SendRequest, SendResponse, _>("/api/v1/send", {
             let driver = driver.clone();
 
-            |client_address, request: PollRequest| async move {
+            #[cfg(feature = "webhooks")]
+            let webhooks = webhooks.clone();
+
+            |client_address, request: SendRequest| async move {
                 #[cfg(feature = "tracing")]
-                tracing::trace!(?client_address, "POST /api/v1/poll");
+                tracing::trace!(?client_address, "POST /api/v1/send");
 
                 // Validate incoming request
-                let validated = match request.validate() {
-                    Ok(validated) => validated,
+                let validated = request.validate();
 
-                    Err(err) => return PollResponse::error(
-                        ResponseStatus::ServerError,
-                        format!("Failed to validate request: {err}")
-                    )
-                };
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/send", &request.0, validated, Some(Incident::InvalidSignature)).await {
+                    return SendResponse::error(status, code, reason);
+                }
 
-                // Check if request is valid
-                if !validated {
-                    return PollResponse::error(
-                        ResponseStatus::RequestValidationFailed,
-                        "Request validation failed"
+                // Check if the receiver is banned
+                if driver.is_blacklisted(&request.0.request.receiver_public) {
+                    return SendResponse::error(
+                        ResponseStatus::Forbidden,
+                        ErrorCode::Forbidden,
+                        "Receiver is blacklisted"
                     );
                 }
 
-                // Poll messages from the inbox
-                let messages = driver.messages_inbox().poll_messages(
-                    request.0.public_key,
+                driver.touch_client(&request.0.public_key).await;
+
+                // Check the channel name
+                if let Err(err) = request.0.request.channel.validate() {
+                    driver.report_incident(&request.0.public_key, Incident::InvalidChannel).await;
+
+                    return SendResponse::error(
+                        ResponseStatus::InvalidChannelName,
+                        ErrorCode::InvalidRequest,
+                        format!("Invalid channel name: {err}")
+                    );
+                }
+
+                // Check the message size
+                let max_size = driver.params().max_message_size;
+                let size = request.0.request.message.content.len();
+
+                if size > max_size {
+                    driver.report_incident(&request.0.public_key, Incident::OversizedMessage).await;
+
+                    return SendResponse::error(
+                        ResponseStatus::MessageTooLarge,
+                        ErrorCode::QuotaExceeded,
+                        format!("Message is too large: {size} bytes, at most {max_size} allowed")
+                    );
+                }
+
+                // Check the sender's certificate
+                let certificate = match driver.client_certificate(&request.0.public_key).await {
+                    Some(certificate) => certificate,
+                    None => request.0.request.sender.client.certificate.clone()
+                };
+
+                if driver.is_certificate_expired(&certificate) {
+                    return SendResponse::error(
+                        ResponseStatus::CertificateExpired,
+                        ErrorCode::CertificateExpired,
+                        "Sender's connection certificate is expired"
+                    );
+                }
+
+                if let Some(scope) = certificate.scope {
+                    if let Err(err) = scope.check(CertificateOperation::Send, Some(&request.0.request.channel)) {
+                        return SendResponse::error(ResponseStatus::Unauthorized, ErrorCode::Forbidden, err.to_string());
+                    }
+                }
+
+                #[cfg(feature = "webhooks")]
+                let event = WebhookEvent::MessageReceived {
+                    sender: request.0.public_key.clone(),
+                    receiver: request.0.request.receiver_public.clone()
+                };
+
+                let sender_key = request.0.public_key.clone();
+                let receiver_key = request.0.request.receiver_public.clone();
+                let size = request.0.request.message.content.len() as u64;
+
+                let receipt = request.0.request.receipt
+                    .then(|| (request.0.request.channel.clone(), request.0.request.message.encoding));
+
+                // Add message to the inbox
+                let result = driver.messages_inbox().add_message(
+                    request.0.request.sender,
+                    request.0.request.receiver_public,
                     request.0.request.channel,
-                    request.0.request.limit
+                    request.0.request.message
                 ).await;
 
+                match result {
+                    Ok(message_id) => {
+                        driver.record_usage(&sender_key, UsageEvent::Sent, size);
+                        driver.record_usage(&receiver_key, UsageEvent::Received, size);
+
+                        driver.notify_receiver(&receiver_key);
+
+                        #[cfg(feature = "webhooks")]
+                        webhooks.notify(event);
+
+                        if let Some((channel, encoding)) = receipt {
+                            driver.issue_delivery_receipts(&sender_key, receiver_key, channel, message_id, encoding).await;
+                        }
+
+                        let body = match message_id {
+                            Some(message_id) => SendResponseBody::new().with_id(message_id),
+                            None => SendResponseBody::new()
+                        };
+
+                        SendResponse::success(
+                            ResponseStatus::Success,
+                            &driver.params().secret_key,
+                            request.0.proof_seed,
+                            body
+                        )
+                    }
+
+                    Err(err) => match driver.messages_inbox().error_status(&err) {
+                        ResponseStatus::ServerError => SendResponse::error(
+                            ResponseStatus::ServerError,
+                            ErrorCode::Internal,
+                            format!("Failed to index message: {err}")
+                        ),
+
+                        status => SendResponse::error(status, ErrorCode::from(status), err.to_string())
+                    }
+                }
+            }
+        }).await;
+
+        routes.post::<SendBatchRequest, SendBatchResponse, _>("/api/v1/send_batch", {
+            let driver = driver.clone();
+
+            #[cfg(feature = "webhooks")]
+            let webhooks = webhooks.clone();
+
+            |client_address, request: SendBatchRequest| async move {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(?client_address, entries = request.0.request.entries.len(), "POST /api/v1/send_batch");
+
+                // Validate incoming request
+                let validated = request.validate();
+
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/send_batch", &request.0, validated, Some(Incident::InvalidSignature)).await {
+                    return SendBatchResponse::error(status, code, reason);
+                }
+
+                driver.touch_client(&request.0.public_key).await;
+
+                // Check the batch size
+                let max_batch_size = driver.params().max_batch_size;
+                let batch_size = request.0.request.entries.len();
+
+                if batch_size > max_batch_size {
+                    return SendBatchResponse::error(
+                        ResponseStatus::BatchTooLarge,
+                        ErrorCode::QuotaExceeded,
+                        format!("Batch is too large: {batch_size} messages, at most {max_batch_size} allowed")
+                    );
+                }
+
+                let sender_key = request.0.public_key.clone();
+
+                let mut results = Vec::with_capacity(batch_size);
+                let mut accepted = Vec::with_capacity(batch_size);
+
+                for entry in request.0.request.entries {
+                    match check_send_entry(&driver, &sender_key, &entry).await {
+                        Ok(()) => {
+                            // Reserve place for the message id
+                            results.push(None);
+
+                            accepted.push(entry);
+                        }
+
+                        Err(result) => results.push(Some(result))
+                    }
+                }
+
+                let accepted_info = accepted.iter()
+                    .map(|entry| (entry.receiver_public.clone(), entry.message.content.len() as u64))
+                    .collect::<Vec<_>>();
+
+                let receipts = accepted.iter()
+                    .map(|entry| entry.receipt.then(|| (entry.channel.clone(), entry.message.encoding)))
+                    .collect::<Vec<_>>();
+
+                let entries = accepted.into_iter()
+                    .map(|entry| (entry.sender, entry.receiver_public, entry.channel, entry.message))
+                    .collect::<Vec<_>>();
+
+                // Add accepted messages to the inbox
+                let ids = match driver.messages_inbox().add_messages(entries).await {
+                    Ok(ids) => ids,
+
+                    Err(err) => return match driver.messages_inbox().error_status(&err) {
+                        ResponseStatus::ServerError => SendBatchResponse::error(
+                            ResponseStatus::ServerError,
+                            ErrorCode::Internal,
+                            format!("Failed to index messages: {err}")
+                        ),
+
+                        status => SendBatchResponse::error(status, ErrorCode::from(status), err.to_string())
+                    }
+                };
+
+                for (((receiver_key, size), receipt), id) in accepted_info.into_iter().zip(receipts).zip(&ids) {
+                    driver.record_usage(&sender_key, UsageEvent::Sent, size);
+                    driver.record_usage(&receiver_key, UsageEvent::Received, size);
+
+                    driver.notify_receiver(&receiver_key);
+
+                    if let Some((channel, encoding)) = receipt {
+                        driver.issue_delivery_receipts(&sender_key, receiver_key.clone(), channel, *id, encoding).await;
+                    }
+
+                    #[cfg(feature = "webhooks")]
+                    webhooks.notify(WebhookEvent::MessageReceived {
+                        sender: sender_key.clone(),
+                        receiver: receiver_key
+                    });
+                }
+
+                let mut ids = ids.into_iter();
+
+                let results = results.into_iter()
+                    .map(|result| match result {
+                        Some(result) => result,
+                        None => SendBatchResult::Sent {
+                            id: ids.next().flatten()
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                SendBatchResponse::success(
+                    ResponseStatus::Success,
+                    &driver.params().secret_key,
+                    request.0.proof_seed,
+                    SendBatchResponseBody::new(results)
+                )
+            }
+        }).await;
+
+        routes.post::<PollRequest, PollResponse, _>("/api/v1/poll", {
+            let driver = driver.clone();
+
+            |client_address, request: PollRequest| async move {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(?client_address, "POST /api/v1/poll");
+
+                // Validate incoming request
+                let validated = request.validate();
+
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/poll", &request.0, validated, None).await {
+                    return PollResponse::error(status, code, reason);
+                }
+
+                driver.touch_client(&request.0.public_key).await;
+
+                let channel = request.0.request.channel_rule();
+
+                // Check the channel name
+                if let Err(err) = channel.validate() {
+                    return PollResponse::error(
+                        ResponseStatus::InvalidChannelName,
+                        ErrorCode::InvalidRequest,
+                        format!("Invalid channel name: {err}")
+                    );
+                }
+
+                // Check the client's certificate scope
+                if let Some(scope) = driver.client_scope(&request.0.public_key).await {
+                    if let Err(err) = scope.check_rule(CertificateOperation::Poll, &channel) {
+                        return PollResponse::error(ResponseStatus::Unauthorized, ErrorCode::Forbidden, err.to_string());
+                    }
+                }
+
+                let range = request.0.request.range();
+
+                // Sealed and peeked messages can't be filtered by inboxes
+                if request.0.request.sender.is_some() && (request.0.request.peek || request.0.request.sealed) {
+                    return PollResponse::error(
+                        ResponseStatus::InvalidRequestStructure,
+                        ErrorCode::InvalidRequest,
+                        "Sender filter is supported only by plain polls"
+                    );
+                }
+
+                if range.is_some() && (request.0.request.peek || request.0.request.sealed) {
+                    return PollResponse::error(
+                        ResponseStatus::InvalidRequestStructure,
+                        ErrorCode::InvalidRequest,
+                        "Time range filter is supported only by plain polls"
+                    );
+                }
+
+                if request.0.request.wildcard && (request.0.request.peek || request.0.request.sealed) {
+                    return PollResponse::error(
+                        ResponseStatus::InvalidRequestStructure,
+                        ErrorCode::InvalidRequest,
+                        "Wildcard channels are supported only by plain polls"
+                    );
+                }
+
+                // Wait for new messages if the inbox is empty
+                #[cfg(feature = "long-poll")]
+                if let Some(wait) = driver.poll_wait(request.0.request.wait_ms) {
+                    driver.wait_messages(&request.0.public_key, &channel, wait).await;
+                }
+
+                // Peek messages without removing them from the inbox
+                if request.0.request.peek {
+                    let peeked = driver.messages_inbox().peek_messages(
+                        request.0.public_key.clone(),
+                        request.0.request.channel,
+                        request.0.request.limit
+                    ).await;
+
+                    let body = match peeked {
+                        Ok(Some((messages, remaining))) if request.0.request.sealed => messages.iter()
+                            .map(|message| SealedMessageInfo::seal(message, &request.0.public_key))
+                            .collect::<Result<Vec<_>, _>>()
+                            .map(|sealed| PollResponseBody::sealed(sealed, remaining))
+                            .map_err(|err| format!("Failed to seal messages: {err}")),
+
+                        Ok(Some((messages, remaining))) => Ok(PollResponseBody::new(messages, remaining)),
+
+                        Ok(None) => Err(String::from("Messages inbox doesn't support peeking")),
+
+                        Err(err) => Err(format!("Failed to peek messages: {err}"))
+                    };
+
+                    return match body {
+                        Ok(body) => PollResponse::success(
+                            ResponseStatus::Success,
+                            &driver.params().secret_key,
+                            request.0.proof_seed,
+                            body
+                        ),
+
+                        Err(err) => PollResponse::error(ResponseStatus::ServerError, ErrorCode::Internal, err)
+                    };
+                }
+
+                // Poll sealed messages from the inbox
+                if request.0.request.sealed {
+                    let sealed = driver.messages_inbox().poll_sealed_messages(
+                        request.0.public_key.clone(),
+                        request.0.request.channel.clone(),
+                        request.0.request.limit
+                    ).await;
+
+                    let sealed = match sealed {
+                        Ok(Some(sealed)) => Ok(sealed),
+
+                        // Seal plain messages if the inbox doesn't store sealed ones
+                        Ok(None) => match driver.messages_inbox().poll_messages(
+                            request.0.public_key.clone(),
+                            channel,
+                            None,
+                            None,
+                            request.0.request.limit
+                        ).await {
+                            Ok((messages, remaining)) => {
+                                driver.issue_polled_receipts(&request.0.public_key, &messages).await;
+
+                                messages.iter()
+                                    .map(|message| SealedMessageInfo::seal(message, &request.0.public_key))
+                                    .collect::<Result<Vec<_>, _>>()
+                                    .map(|sealed| (sealed, remaining))
+                                    .map_err(|err| err.to_string())
+                            }
+
+                            Err(err) => Err(err.to_string())
+                        }
+
+                        Err(err) => Err(err.to_string())
+                    };
+
+                    return match sealed {
+                        Ok((sealed, remaining)) => {
+                            for message in &sealed {
+                                driver.record_usage(&request.0.public_key, UsageEvent::Polled, message.content.len() as u64);
+                            }
+
+                            PollResponse::success(
+                                ResponseStatus::Success,
+                                &driver.params().secret_key,
+                                request.0.proof_seed,
+                                PollResponseBody::sealed(sealed, remaining)
+                            )
+                        }
+
+                        Err(err) => PollResponse::error(
+                            ResponseStatus::ServerError,
+                            ErrorCode::Internal,
+                            format!("Failed to poll sealed messages: {err}")
+                        )
+                    };
+                }
+
+                // Lease messages until they're acknowledged
+                let leased = match driver.params().poll_lease {
+                    Some(lease) => driver.messages_inbox().lease_messages(
+                        request.0.public_key.clone(),
+                        channel.clone(),
+                        request.0.request.sender.clone(),
+                        range.clone(),
+                        request.0.request.limit,
+                        lease
+                    ).await,
+
+                    None => Ok(None)
+                };
+
+                // Poll messages from the inbox
+                let messages = match leased {
+                    Ok(Some(leased)) => Ok(leased),
+
+                    Ok(None) => driver.messages_inbox().poll_messages(
+                        request.0.public_key.clone(),
+                        channel.clone(),
+                        request.0.request.sender,
+                        range,
+                        request.0.request.limit
+                    ).await,
+
+                    Err(err) => Err(err)
+                };
+
                 match messages {
-                    Ok((messages, remaining)) => PollResponse::success(
+                    Ok((messages, remaining)) => {
+                        for message in &messages {
+                            driver.record_usage(&request.0.public_key, UsageEvent::Polled, message.message.content.len() as u64);
+                        }
+
+                        driver.issue_polled_receipts(&request.0.public_key, &messages).await;
+
+                        let mut body = PollResponseBody::new(messages, remaining);
+
+                        // Report remaining messages of every matched channel
+                        if let ChannelRule::Prefix(_) = &channel {
+                            match driver.messages_inbox().list_channels(request.0.public_key.clone()).await {
+                                Ok(channels) => {
+                                    body = body.with_remaining_by_channel(channels.into_iter()
+                                        .filter(|(name, _)| channel.matches(name))
+                                        .map(|(name, remaining)| (name.to_string(), remaining)));
+                                }
+
+                                // Messages are already polled so the response can't fail
+                                Err(_err) => {
+                                    #[cfg(feature = "tracing")]
+                                    tracing::warn!("Failed to list remaining messages of the polled channels: {_err}");
+                                }
+                            }
+                        }
+
+                        PollResponse::success(
+                            ResponseStatus::Success,
+                            &driver.params().secret_key,
+                            request.0.proof_seed,
+                            body
+                        )
+                    }
+
+                    Err(err) => PollResponse::error(
+                        ResponseStatus::ServerError,
+                        ErrorCode::Internal,
+                        format!("Failed to poll messages: {err}")
+                    )
+                }
+            }
+        }).await;
+
+        routes.post::<AckRequest, AckResponse, _>("/api/v1/ack", {
+            let driver = driver.clone();
+
+            |client_address, request: AckRequest| async move {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(?client_address, "POST /api/v1/ack");
+
+                // Validate incoming request
+                let validated = request.validate();
+
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/ack", &request.0, validated, None).await {
+                    return AckResponse::error(status, code, reason);
+                }
+
+                // Acknowledging messages is a part of polling
+                if let Some(scope) = driver.client_scope(&request.0.public_key).await {
+                    if let Err(err) = scope.check(CertificateOperation::Poll, None) {
+                        return AckResponse::error(ResponseStatus::Unauthorized, ErrorCode::Forbidden, err.to_string());
+                    }
+                }
+
+                let acknowledged = driver.messages_inbox().ack_messages(
+                    request.0.public_key.clone(),
+                    request.0.request.ids
+                ).await;
+
+                match acknowledged {
+                    Ok(Some(acknowledged)) => AckResponse::success(
                         ResponseStatus::Success,
                         &driver.params().secret_key,
                         request.0.proof_seed,
-                        PollResponseBody::new(messages, remaining)
+                        AckResponseBody::new(acknowledged)
                     ),
 
-                    Err(err) => PollResponse::error(
+                    Ok(None) => AckResponse::error(
                         ResponseStatus::ServerError,
-                        format!("Failed to poll messages: {err}")
+                        ErrorCode::Internal,
+                        "Messages inbox doesn't support acknowledgements"
+                    ),
+
+                    Err(err) => AckResponse::error(
+                        ResponseStatus::ServerError,
+                        ErrorCode::Internal,
+                        format!("Failed to acknowledge messages: {err}")
+                    )
+                }
+            }
+        }).await;
+
+        routes.post::<ChannelsRequest, ChannelsResponse, _>("/api/v1/channels", {
+            let driver = driver.clone();
+
+            |client_address, request: ChannelsRequest| async move {
+                #[cfg(feature = "tracing")]
+                tracing::trace!(?client_address, "POST /api/v1/channels");
+
+                // Validate incoming request
+                let validated = request.validate();
+
+                if let Err((status, code, reason)) = check_signed_request(&driver, "/api/v1/channels", &request.0, validated, None).await {
+                    return ChannelsResponse::error(status, code, reason);
+                }
+
+                let scope = driver.client_scope(&request.0.public_key).await;
+
+                // Listing channels is a part of polling
+                if let Some(scope) = &scope {
+                    if let Err(err) = scope.check(CertificateOperation::Poll, None) {
+                        return ChannelsResponse::error(ResponseStatus::Unauthorized, ErrorCode::Forbidden, err.to_string());
+                    }
+                }
+
+                match driver.messages_inbox().list_channels(request.0.public_key.clone()).await {
+                    Ok(channels) => {
+                        // Hide channels the client can't poll
+                        let channels = channels.into_iter()
+                            .filter(|(channel, _)| scope.as_ref().is_none_or(|scope| {
+                                scope.check(CertificateOperation::Poll, Some(channel)).is_ok()
+                            }));
+
+                        ChannelsResponse::success(
+                            ResponseStatus::Success,
+                            &driver.params().secret_key,
+                            request.0.proof_seed,
+                            ChannelsResponseBody::new(channels)
+                        )
+                    }
+
+                    Err(err) => ChannelsResponse::error(
+                        ResponseStatus::ServerError,
+                        ErrorCode::Internal,
+                        format!("Failed to list channels: {err}")
                     )
                 }
             }
@@ -435,10 +1471,64 @@ where
         Self {
             http_client,
             http_server,
-            driver
+            driver,
+            interceptors,
+            access_log,
+
+            #[cfg(feature = "announce-fanout")]
+            fanout,
+
+            #[cfg(feature = "webhooks")]
+            webhooks,
+
+            #[cfg(feature = "maintenance")]
+            maintenance: None
+        }
+    }
+
+    #[cfg(feature = "maintenance")]
+    #[inline]
+    /// Run the driver's maintenance jobs in background
+    /// while the server is running.
+    /// 
+    /// Refer to `ServerDriver::spawn_maintenance`.
+    pub fn with_maintenance(self, config: MaintenanceConfig) -> Self {
+        Self {
+            maintenance: Some(config),
+            ..self
         }
     }
 
+    #[inline]
+    /// Add interceptor called around every request handler.
+    /// 
+    /// Refer to `Interceptor`.
+    pub fn with_interceptor(self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+
+        self
+    }
+
+    #[inline]
+    pub fn interceptors(&self) -> &Interceptors {
+        &self.interceptors
+    }
+
+    #[inline]
+    /// Record every processed request in the given access log.
+    /// 
+    /// Refer to `AccessLog`.
+    pub fn with_access_log(self, access_log: impl AccessLog + 'static) -> Self {
+        self.access_log.set(Arc::new(access_log));
+
+        self
+    }
+
+    #[inline]
+    pub fn access_log(&self) -> &AccessLogHook {
+        &self.access_log
+    }
+
     #[inline]
     pub fn http_client(&self) -> &HttpClientExt {
         &self.http_client
@@ -454,12 +1544,4001 @@ where
         self.driver.clone()
     }
 
-    #[inline]
-    /// Run HTTP REST API server on given TCP listener
-    pub async fn serve(self, address: impl ToSocketAddrs + Send) -> Result<(), Box<dyn std::error::Error>> {
-        #[cfg(feature = "tracing")]
-        tracing::debug!("Starting server");
+    #[cfg(feature = "router-cleanup")]
+    /// Spawn background task removing clients which
+    /// weren't seen for longer than `older_than`.
+    /// 
+    /// Cleanup runs every `period`. Abort the returned
+    /// handle to stop it.
+    /// 
+    /// Refer to `Router::cleanup`.
+    pub fn spawn_router_cleanup(&self, period: Duration, older_than: Duration) -> tokio::task::JoinHandle<()> {
+        let driver = self.driver.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+
+            loop {
+                interval.tick().await;
+
+                match driver.router().cleanup(older_than).await {
+                    Ok(_removed) => {
+                        #[cfg(feature = "tracing")]
+                        if _removed > 0 {
+                            tracing::debug!(removed = _removed, "Removed stale routing table records");
+                        }
+                    }
+
+                    Err(_err) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("Failed to clean up routing table: {_err}");
+                    }
+                }
+            }
+        })
+    }
+
+    #[cfg(feature = "health-checks")]
+    /// Check reachability of all the known servers
+    /// and store their health statuses in the router.
+    /// 
+    /// Every server is requested by `GET /api/v1/info`, and
+    /// is healthy if it responds within `timeout` with its
+    /// indexed public key.
+    /// 
+    /// Return amounts of healthy and unhealthy servers.
+    pub async fn check_servers_health(&self, timeout: Duration) -> (u64, u64) {
+        check_servers_health(&self.http_client, &self.driver, timeout).await
+    }
+
+    #[cfg(feature = "health-checks")]
+    /// Spawn background task checking reachability
+    /// of the known servers.
+    /// 
+    /// Checks run with interval and timeout from the
+    /// `ServerParams::health_checks`. Return `None` if
+    /// they're not configured. Abort the returned
+    /// handle to stop the checks.
+    /// 
+    /// Refer to `check_servers_health`.
+    pub fn spawn_health_checks(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let params = self.driver.params().health_checks?;
+
+        let http_client = self.http_client.clone();
+        let driver = self.driver.clone();
+
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(params.interval);
+
+            loop {
+                interval.tick().await;
+
+                let (_healthy, _unhealthy) = check_servers_health(&http_client, &driver, params.timeout).await;
+
+                #[cfg(feature = "tracing")]
+                tracing::debug!(healthy = _healthy, unhealthy = _unhealthy, "Checked known servers health");
+            }
+        }))
+    }
+
+    #[cfg(feature = "bootstrap")]
+    /// Spawn background task filling the routing table
+    /// from the servers of the `ServerParams::bootstrap`.
+    /// 
+    /// Unreachable servers are retried with exponential
+    /// backoff until they respond or retries run out.
+    /// Return `None` if there are no bootstrap servers.
+    /// 
+    /// This task is spawned by the `serve` method.
+    pub fn spawn_bootstrap(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let params = self.driver.params().bootstrap.clone();
+
+        if params.servers.is_empty() {
+            return None;
+        }
+
+        let http_client = self.http_client.clone();
+        let driver = self.driver.clone();
+
+        Some(tokio::spawn(async move {
+            let mut servers = params.servers;
+            let mut backoff = params.backoff;
+
+            for attempt in 0..=params.retries {
+                if attempt > 0 {
+                    tokio::time::sleep(backoff).await;
+
+                    backoff = (backoff * 2).min(params.max_backoff);
+                }
+
+                let summary = driver.bootstrap_from(http_client.clone(), &servers).await;
+
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    attempt,
+                    bootstrapped = summary.servers.len(),
+                    indexed = summary.indexed,
+                    failed = summary.failed.len(),
+                    "Bootstrapped routing table"
+                );
+
+                servers = summary.retryable();
+
+                if servers.is_empty() {
+                    return;
+                }
+            }
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!(servers = ?servers, "Bootstrap servers are unreachable");
+        }))
+    }
+
+    #[cfg(feature = "announce-fanout")]
+    #[inline]
+    /// Get announce fan-out metrics.
+    pub fn announce_fanout_stats(&self) -> AnnounceFanoutStats {
+        self.fanout.stats()
+    }
+
+    #[cfg(feature = "webhooks")]
+    #[inline]
+    /// Get webhooks delivery metrics.
+    pub fn webhook_stats(&self) -> WebhookStats {
+        self.webhooks.stats()
+    }
+
+    #[cfg(feature = "server-axum")]
+    #[inline]
+    /// Get admission control of the HTTP server.
+    /// 
+    /// Can be used to tune the incoming requests
+    /// limits at runtime and to read the current
+    /// queue depth. Return `None` if the HTTP server
+    /// has no admission control.
+    pub fn admission_control(&self) -> Option<&crate::http::AdmissionControl> {
+        self.http_server.admission_control()
+    }
+
+    /// Run HTTP REST API server on given TCP listener
+    /// 
+    /// With the `mdns` feature the server is advertised
+    /// in the local network while it's running if
+    /// `local_discovery` param is set.
+    /// 
+    /// With the `bootstrap` feature the routing table is
+    /// filled from the bootstrap servers in background.
+    /// 
+    /// Maintenance jobs set by `with_maintenance` are
+    /// stopped when the server exits.
+    pub async fn serve(self, address: impl ToSocketAddrs + Send) -> Result<(), Box<dyn std::error::Error>> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Starting server");
+
+        #[cfg(feature = "mdns")]
+        let _advertiser = match self.driver.params().local_discovery {
+            Some(params) => {
+                let Some(port) = address.to_socket_addrs()?.next().map(|address| address.port()) else {
+                    return Err("Failed to resolve server address".into());
+                };
+
+                let info = ServiceInfo::new(port, self.driver.params().secret_key.public_key());
+
+                Some(Advertiser::start(params, info)?)
+            }
+
+            None => None
+        };
+
+        #[cfg(feature = "bootstrap")]
+        let bootstrap = self.spawn_bootstrap();
+
+        #[cfg(feature = "maintenance")]
+        let _maintenance = self.maintenance.map(|config| {
+            self.driver.clone().spawn_maintenance(self.http_client.clone(), config)
+        });
+
+        let result = self.http_server.serve(address).await;
+
+        #[cfg(feature = "bootstrap")]
+        if let Some(bootstrap) = bootstrap {
+            bootstrap.abort();
+        }
+
+        result
+    }
+
+    #[cfg(feature = "admin-api")]
+    /// Run admin REST API server on a separate TCP listener.
+    /// 
+    /// Admin routes are registered on a new HTTP server
+    /// instance, so they're not reachable from the public
+    /// listener started by `serve`.
+    pub async fn serve_admin(&self, address: impl ToSocketAddrs + Send, auth: AdminAuth) -> Result<(), Box<dyn std::error::Error>>
+    where
+        HttpServerExt: Default
+    {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Starting admin server");
+
+        let mut admin_server = HttpServerExt::default();
+
+        #[cfg(feature = "server-axum")]
+        super::admin::register(&mut admin_server, self.driver.clone(), self.admission_control().cloned(), auth).await;
+
+        #[cfg(not(feature = "server-axum"))]
+        super::admin::register(&mut admin_server, self.driver.clone(), auth).await;
+
+        admin_server.serve(address).await
+    }
+}
+
+/// Run the checks shared by all the signed routes.
+/// 
+/// Request is rejected if its signature is invalid, it's
+/// expired or replayed, its body is not signed while unsigned
+/// bodies are not accepted, or the sender is throttled or
+/// blacklisted.
+/// 
+/// If `incident` is set, invalid requests are reported with
+/// it and senders with too low reputation are rejected.
+/// 
+/// Return status, error code and reason of the response
+/// if the request is rejected.
+async fn check_signed_request<T, RouterExt, TraversalExt, MessagesInboxExt>(
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    route: &str,
+    request: &Request<T>,
+    validated: Result<bool, ValidationError>,
+    incident: Option<Incident>
+) -> Result<(), (ResponseStatus, ErrorCode, String)>
+where
+    T: Sync,
+    RouterExt: Router + Send + Sync,
+    TraversalExt: Traversal + Send + Sync,
+    MessagesInboxExt: MessagesInbox + Send + Sync
+{
+    // Check if request is valid
+    match validated {
+        Ok(true) => (),
+
+        Ok(false) => {
+            if let Some(incident) = incident {
+                driver.report_incident(&request.public_key, incident).await;
+            }
+
+            return Err((
+                ResponseStatus::RequestValidationFailed,
+                ErrorCode::ValidationFailed,
+                String::from("Request validation failed")
+            ));
+        }
+
+        Err(err) => return Err((
+            ResponseStatus::ServerError,
+            ErrorCode::ValidationFailed,
+            format!("Failed to validate request: {err}")
+        ))
+    }
+
+    // Reject expired and replayed requests
+    if let Err(err) = driver.check_replay(request) {
+        return Err((err.status(), ErrorCode::from(err.status()), err.to_string()));
+    }
+
+    // Reject requests with unsigned bodies
+    if !driver.is_body_accepted(request) {
+        return Err((ResponseStatus::RequestValidationFailed, ErrorCode::ValidationFailed, String::from("Request body is not signed")));
+    }
+
+    // Throttle the sender
+    if let Err(err) = driver.check_rate_limit(route, &request.public_key) {
+        return Err((ResponseStatus::TooManyRequests, ErrorCode::RateLimited, err.to_string()));
+    }
+
+    // Check if the sender is banned
+    if driver.is_blacklisted(&request.public_key) {
+        return Err((ResponseStatus::Forbidden, ErrorCode::Forbidden, String::from("Sender is blacklisted")));
+    }
+
+    // Check the sender's reputation
+    if incident.is_some() && driver.check_reputation(&request.public_key).await == ReputationAction::Reject {
+        return Err((ResponseStatus::ReputationTooLow, ErrorCode::Forbidden, String::from("Sender's reputation is too low")));
+    }
+
+    Ok(())
+}
+
+/// Check that the serialized announced record
+/// is not larger than the given limit.
+fn check_record_size(name: &str, record: &impl AsJson, max_size: usize) -> Result<(), AnnounceEntryResult> {
+    let size = record.to_json()
+        .and_then(|record| Ok(serde_json::to_vec(&record)?.len()));
+
+    match size {
+        Ok(size) if size <= max_size => Ok(()),
+
+        Ok(size) => Err(AnnounceEntryResult::rejected(
+            ResponseStatus::AnnounceRecordTooLarge,
+            format!("{name} record is too large: {size} bytes, at most {max_size} allowed")
+        )),
+
+        Err(err) => Err(AnnounceEntryResult::rejected(
+            ResponseStatus::InvalidRequestStructure,
+            format!("Failed to serialize {name} record: {err}")
+        ))
+    }
+}
+
+/// Validate single announced entry.
+async fn check_entry<RouterExt, TraversalExt, MessagesInboxExt>(
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    announcer: &PublicKey,
+    entry: &AnnounceRequestBody
+) -> Result<(), AnnounceEntryResult>
+where
+    RouterExt: Router + Send + Sync,
+    TraversalExt: Traversal + Send + Sync,
+    MessagesInboxExt: MessagesInbox + Send + Sync
+{
+    let limits = driver.params().announce_limits;
+
+    match entry {
+        AnnounceRequestBody::Client { client, server, .. } => {
+            check_record_size("Client", client, limits.max_client_size)
+                .and_then(|_| check_record_size("Server", server, limits.max_server_size))
+        }
+
+        AnnounceRequestBody::Server { server, .. } => check_record_size("Server", server, limits.max_server_size),
+
+        AnnounceRequestBody::Bulk { .. } => Err(AnnounceEntryResult::rejected(
+            ResponseStatus::InvalidRequestStructure,
+            "Nested bulk announces are not allowed"
+        ))
+    }?;
+
+    // Blacklisted keys are never indexed
+    let banned = match entry {
+        AnnounceRequestBody::Client { client, server, .. } => driver.is_blacklisted(&client.public_key) || driver.is_blacklisted(&server.public_key),
+        AnnounceRequestBody::Server { server, .. } => driver.is_blacklisted(&server.public_key),
+        AnnounceRequestBody::Bulk { .. } => false
+    };
+
+    if banned {
+        return Err(AnnounceEntryResult::rejected(
+            ResponseStatus::Forbidden,
+            "Announced key is blacklisted"
+        ));
+    }
+
+    if !entry.validate_entry().unwrap_or(false) {
+        driver.report_incident(announcer, Incident::InvalidAnnounce).await;
+
+        return Err(AnnounceEntryResult::rejected(
+            ResponseStatus::RequestValidationFailed,
+            "Entry validation failed"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Convert result of the entry indexing.
+/// 
+/// Router-specific status lets peers back off
+/// when the routing table is full.
+fn index_result<RouterExt: Router>(router: &RouterExt, name: &str, result: Result<bool, RouterExt::Error>) -> AnnounceEntryResult {
+    match result {
+        Ok(_) => AnnounceEntryResult::Accepted,
+
+        Err(err) => AnnounceEntryResult::rejected(
+            router.error_status(&err),
+            format!("Failed to index {name}: {err}")
+        )
+    }
+}
+
+/// Update address of the announced server
+/// if the announce is newer than the stored record.
+async fn update_server_address<RouterExt, TraversalExt, MessagesInboxExt>(
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    server: crate::rest_api::types::Server,
+    announced_at: u64
+) -> AnnounceEntryResult
+where
+    RouterExt: Router + Send + Sync,
+    TraversalExt: Traversal + Send + Sync,
+    MessagesInboxExt: MessagesInbox + Send + Sync
+{
+    // Announces from the future would pin the record
+    let result = driver.router().update_server_address(server, announced_at.min(timestamp())).await;
+
+    index_result(driver.router(), "server", result)
+}
+
+/// Validate and index single announced entry.
+async fn announce_entry<RouterExt, TraversalExt, MessagesInboxExt>(
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    announcer: &PublicKey,
+    entry: AnnounceRequestBody
+) -> AnnounceEntryResult
+where
+    RouterExt: Router + Send + Sync,
+    TraversalExt: Traversal + Send + Sync,
+    MessagesInboxExt: MessagesInbox + Send + Sync
+{
+    if let Err(rejected) = check_entry(driver, announcer, &entry).await {
+        return rejected;
+    }
+
+    match entry {
+        AnnounceRequestBody::Client { client, server, .. } => {
+            index_result(driver.router(), "remote client", driver.router().index_remote_client(client, server).await)
+        }
+
+        AnnounceRequestBody::Server { server, announced_at: Some(announced_at), .. } => {
+            update_server_address(driver, server, announced_at).await
+        }
+
+        AnnounceRequestBody::Server { server, announced_at: None, .. } => {
+            index_result(driver.router(), "server", driver.router().index_server(server).await)
+        }
+
+        AnnounceRequestBody::Bulk { .. } => unreachable!()
+    }
+}
+
+/// Validate and index entries of the bulk announce.
+/// 
+/// Entries are validated independently so a single
+/// bad entry doesn't affect the others. Valid entries
+/// are indexed using the router's bulk methods, servers
+/// first. Timestamped server entries update addresses of
+/// the known servers one by one. Return result for each
+/// entry in the same order.
+async fn announce_entries<RouterExt, TraversalExt, MessagesInboxExt>(
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    announcer: &PublicKey,
+    entries: Vec<AnnounceRequestBody>
+) -> Vec<AnnounceEntryResult>
+where
+    RouterExt: Router + Send + Sync,
+    TraversalExt: Traversal + Send + Sync,
+    MessagesInboxExt: MessagesInbox + Send + Sync
+{
+    let mut results = Vec::with_capacity(entries.len());
+
+    let mut clients = Vec::new();
+    let mut servers = Vec::new();
+
+    for (i, entry) in entries.into_iter().enumerate() {
+        if let Err(rejected) = check_entry(driver, announcer, &entry).await {
+            results.push(rejected);
+
+            continue;
+        }
+
+        // Placeholder replaced by the indexing result
+        results.push(AnnounceEntryResult::Accepted);
+
+        match entry {
+            AnnounceRequestBody::Client { client, server, .. } => clients.push((i, (client, server))),
+            AnnounceRequestBody::Server { server, announced_at: Some(announced_at), .. } => {
+                results[i] = update_server_address(driver, server, announced_at).await;
+            }
+
+            AnnounceRequestBody::Server { server, announced_at: None, .. } => servers.push((i, server)),
+            AnnounceRequestBody::Bulk { .. } => unreachable!()
+        }
+    }
+
+    let (server_slots, servers): (Vec<_>, Vec<_>) = servers.into_iter().unzip();
+    let (client_slots, clients): (Vec<_>, Vec<_>) = clients.into_iter().unzip();
+
+    for (i, result) in server_slots.into_iter().zip(driver.router().index_servers(servers).await) {
+        results[i] = index_result(driver.router(), "server", result);
+    }
+
+    for (i, result) in client_slots.into_iter().zip(driver.router().index_remote_clients(clients).await) {
+        results[i] = index_result(driver.router(), "remote client", result);
+    }
+
+    results
+}
+
+#[cfg(feature = "announce-fanout")]
+/// Forward accepted announced entries
+/// to the known servers in background.
+async fn forward_entries<HttpClientExt, RouterExt, TraversalExt, MessagesInboxExt>(
+    fanout: &AnnounceFanoutWorker<HttpClientExt>,
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    announcer: &PublicKey,
+    entries: impl IntoIterator<Item = AnnounceRequestBody>
+)
+where
+    HttpClientExt: HttpClient + 'static,
+    RouterExt: Router + Send + Sync,
+    TraversalExt: Traversal + Send + Sync,
+    MessagesInboxExt: MessagesInbox + Send + Sync
+{
+    if !fanout.is_enabled() {
+        return;
+    }
+
+    let mut entries = entries.into_iter().peekable();
+
+    if entries.peek().is_none() {
+        return;
+    }
+
+    match driver.router().servers().await {
+        Ok(servers) => {
+            for entry in entries {
+                fanout.forward(entry, announcer, servers.clone());
+            }
+        }
+
+        Err(_err) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("POST /api/v1/announce: failed to get known servers for announce fan-out: {_err}");
+        }
+    }
+}
+
+/// Search single client of the batch lookup request.
+/// 
+/// Refer to the `POST /api/v1/lookup` handler.
+async fn lookup_batch_entry<RouterExt, TraversalExt, MessagesInboxExt>(
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    public_key: &PublicKey,
+    client_type: Option<ClientType>
+) -> Result<LookupBatchResult, String>
+where
+    RouterExt: Router + Send + Sync,
+    TraversalExt: Traversal + Send + Sync,
+    MessagesInboxExt: MessagesInbox + Send + Sync
+{
+    let router = driver.router();
+
+    match router.lookup_local_client(public_key, client_type).await {
+        Ok(Some((client, available))) => return Ok(LookupBatchResult::Found(LookupResponseBody::local(client, available))),
+        Ok(None) => (),
+
+        Err(err) => return Err(format!("Failed to lookup local client: {err}"))
+    }
+
+    match router.lookup_remote_client(public_key, client_type).await {
+        Ok(Some((client, server, available))) => return Ok(LookupBatchResult::Found(LookupResponseBody::remote(client, server, available))),
+        Ok(None) => (),
+
+        Err(err) => return Err(format!("Failed to lookup remote client: {err}"))
+    }
+
+    match router.lookup_remote_client_hint(public_key, client_type).await {
+        Ok(hint) if hint.is_empty() => Ok(LookupBatchResult::NotFound),
+        Ok(hint) => Ok(LookupBatchResult::Found(LookupResponseBody::scored_hint(hint))),
+
+        Err(err) => Err(format!("Failed to lookup remote client hint: {err}"))
+    }
+}
+
+/// Validate single entry of the batch send request.
+/// 
+/// Refer to the `POST /api/v1/send` handler.
+async fn check_send_entry<RouterExt, TraversalExt, MessagesInboxExt>(
+    driver: &ServerDriver<RouterExt, TraversalExt, MessagesInboxExt>,
+    sender: &PublicKey,
+    entry: &SendRequestBody
+) -> Result<(), SendBatchResult>
+where
+    RouterExt: Router + Send + Sync,
+    TraversalExt: Traversal + Send + Sync,
+    MessagesInboxExt: MessagesInbox + Send + Sync
+{
+    if driver.is_blacklisted(&entry.receiver_public) {
+        return Err(SendBatchResult::rejected(
+            ResponseStatus::Forbidden,
+            "Receiver is blacklisted"
+        ));
+    }
+
+    // Check the channel name
+    if let Err(err) = entry.channel.validate() {
+        driver.report_incident(sender, Incident::InvalidChannel).await;
+
+        return Err(SendBatchResult::rejected(
+            ResponseStatus::InvalidChannelName,
+            format!("Invalid channel name: {err}")
+        ));
+    }
+
+    // Check the message size
+    let max_size = driver.params().max_message_size;
+    let size = entry.message.content.len();
+
+    if size > max_size {
+        driver.report_incident(sender, Incident::OversizedMessage).await;
+
+        return Err(SendBatchResult::rejected(
+            ResponseStatus::MessageTooLarge,
+            format!("Message is too large: {size} bytes, at most {max_size} allowed")
+        ));
+    }
+
+    // Check the sender's certificate
+    let certificate = match driver.client_certificate(sender).await {
+        Some(certificate) => certificate,
+        None => entry.sender.client.certificate.clone()
+    };
+
+    if driver.is_certificate_expired(&certificate) {
+        return Err(SendBatchResult::rejected(
+            ResponseStatus::CertificateExpired,
+            "Sender's connection certificate is expired"
+        ));
+    }
+
+    if let Some(scope) = certificate.scope {
+        if let Err(err) = scope.check(CertificateOperation::Send, Some(&entry.channel)) {
+            return Err(SendBatchResult::rejected(ResponseStatus::Unauthorized, err.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "webhooks")]
+/// Get webhook event of the announced entry.
+fn announce_event(entry: &AnnounceRequestBody) -> Option<WebhookEvent> {
+    match entry {
+        AnnounceRequestBody::Client { client, server, .. } => Some(WebhookEvent::ClientAnnounced {
+            client: client.public_key.clone(),
+            server: server.public_key.clone()
+        }),
+
+        AnnounceRequestBody::Server { server, .. } => Some(WebhookEvent::ServerAnnounced {
+            server: server.public_key.clone()
+        }),
+
+        AnnounceRequestBody::Bulk { .. } => None
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "client-reqwest",
+    feature = "server-axum",
+    feature = "router-global-table",
+    feature = "traversal-bfs-recursion",
+    feature = "inbox-stored-queue"
+))]
+pub(crate) mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use crate::http::{ReqwestHttpClient, AxumHttpServer, BodyLimits};
+    use crate::address::Address;
+    use crate::crypto::prelude::*;
+    use crate::drivers::ClientDriver;
+    use crate::rest_api::types::Client as ClientApiRecord;
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::Server as ServerApiRecord;
+    use crate::rest_api::middleware::Error as MiddlewareError;
+
+    use super::*;
+
+    pub type TestServer = Server<
+        ReqwestHttpClient,
+        AxumHttpServer,
+        GlobalTableRouter,
+        BfsRecursionTraversal,
+        StoredQueueMessagesInbox
+    >;
+
+    pub type TestDriver = ServerDriver<
+        GlobalTableRouter,
+        BfsRecursionTraversal,
+        StoredQueueMessagesInbox
+    >;
+
+    /// Build test server driver storing its data in the given
+    /// temp folder and listening on the given port.
+    pub async fn get_driver(folder: &str, port: u16, params: impl FnOnce(&mut ServerParams)) -> std::io::Result<TestDriver> {
+        let temp: PathBuf = std::env::temp_dir().join(folder);
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        let mut server_params = ServerParams {
+            address: format!("127.0.0.1:{port}"),
+            ..ServerParams::default()
+        };
+
+        params(&mut server_params);
+
+        Ok(ServerDriver::new(
+            GlobalTableRouter::new(temp.join("router")).await?,
+            BfsRecursionTraversal,
+            StoredQueueMessagesInbox::new(temp.join("inbox"), None).await?,
+            server_params
+        ))
+    }
+
+    /// Build test server middleware storing its data in the
+    /// given temp folder and listening on the given port.
+    pub async fn get_server(folder: &str, port: u16, params: impl FnOnce(&mut ServerParams)) -> std::io::Result<TestServer> {
+        let driver = get_driver(folder, port, params).await?;
+
+        Ok(Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await)
+    }
+
+    /// Run given test server in background.
+    pub async fn serve(server: TestServer) {
+        let address = server.driver().params().address.clone();
+
+        tokio::spawn(async move {
+            let _ = server.serve(address).await;
+        });
+
+        // Give the listener some time to start
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    #[tokio::test]
+    async fn announce_fanout() -> Result<(), Box<dyn std::error::Error>> {
+        let enable_fanout = |params: &mut ServerParams| {
+            params.announce_fanout.mode = AnnounceFanout::All;
+        };
+
+        let server_a = get_server("announce-fanout-test-a", 48467, enable_fanout).await?;
+        let server_b = get_server("announce-fanout-test-b", 48468, |_| ()).await?;
+        let server_c = get_server("announce-fanout-test-c", 48469, |_| ()).await?;
+
+        let driver_a = server_a.driver();
+        let driver_b = server_b.driver();
+        let driver_c = server_c.driver();
+
+        for driver in [&driver_b, &driver_c] {
+            let server = ServerApiRecord::new(
+                driver.params().secret_key.public_key(),
+                &driver.params().address
+            );
+
+            driver_a.router().index_server(server).await?;
+        }
+
+        let stats_server = server_a.clone();
+
+        serve(server_a).await;
+        serve(server_b).await;
+        serve(server_c).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        client.connect("127.0.0.1:48467").await?;
+
+        // Announce is performed in background
+        let mut found = None;
+
+        for _ in 0..50 {
+            found = driver_b.router().lookup_remote_client(&client_public, None).await?;
+
+            if found.is_some() {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let Some((announced_client, announced_server, _)) = found else {
+            panic!("Client wasn't announced to the server B");
+        };
+
+        assert_eq!(announced_client.public_key, client_public);
+        assert_eq!(announced_server.public_key, driver_a.params().secret_key.public_key());
+        assert_eq!(announced_server.address, "127.0.0.1:48467");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert!(driver_c.router().lookup_remote_client(&client_public, None).await?.is_some());
+
+        // Reconnection is deduplicated
+        client.connect("127.0.0.1:48467").await?;
+
+        let stats = stats_server.announce_fanout_stats();
+
+        assert_eq!(stats.queued, 2);
+        assert_eq!(stats.delivered, 2);
+        assert_eq!(stats.deduplicated, 1);
+        assert_eq!(stats.failed, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn announce_gossip() -> Result<(), Box<dyn std::error::Error>> {
+        let enable_fanout = |params: &mut ServerParams| {
+            params.announce_fanout.mode = AnnounceFanout::All;
+            params.announce_fanout.hop_limit = 1;
+        };
+
+        // Chain of servers A -> B -> C -> D
+        let server_a = get_server("announce-gossip-test-a", 48520, enable_fanout).await?;
+        let server_b = get_server("announce-gossip-test-b", 48521, enable_fanout).await?;
+        let server_c = get_server("announce-gossip-test-c", 48522, enable_fanout).await?;
+        let server_d = get_server("announce-gossip-test-d", 48523, enable_fanout).await?;
+
+        let drivers = [server_a.driver(), server_b.driver(), server_c.driver(), server_d.driver()];
+
+        for pair in drivers.windows(2) {
+            let server = ServerApiRecord::new(
+                pair[1].params().secret_key.public_key(),
+                &pair[1].params().address
+            );
+
+            pair[0].router().index_server(server).await?;
+        }
+
+        let stats_b = server_b.clone();
+        let stats_c = server_c.clone();
+
+        serve(server_a).await;
+        serve(server_b).await;
+        serve(server_c).await;
+        serve(server_d).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        client.connect("127.0.0.1:48520").await?;
+
+        // Server B forwards the record to the server C
+        let mut found = None;
+
+        for _ in 0..50 {
+            found = drivers[2].router().lookup_remote_client(&client_public, None).await?;
+
+            if found.is_some() {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let Some((_, announced_server, _)) = found else {
+            panic!("Client wasn't gossiped to the server C");
+        };
+
+        assert_eq!(announced_server.public_key, drivers[0].params().secret_key.public_key());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Hop limit stops the propagation at the server C
+        assert!(drivers[1].router().lookup_remote_client(&client_public, None).await?.is_some());
+        assert!(drivers[3].router().lookup_remote_client(&client_public, None).await?.is_none());
+
+        assert_eq!(stats_b.announce_fanout_stats().forwarded, 1);
+        assert_eq!(stats_c.announce_fanout_stats().forwarded, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sealed_poll() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = std::env::temp_dir().join("sealed-poll-test");
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        let driver = ServerDriver::new(
+            GlobalTableRouter::new(temp.join("router")).await?,
+            BfsRecursionTraversal,
+            StoredQueueMessagesInbox::new_sealed(temp.join("inbox"), StorageLayout::Flat).await?,
+            ServerParams {
+                address: String::from("127.0.0.1:48470"),
+                ..ServerParams::default()
+            }
+        );
+
+        let server: TestServer = Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await;
+
+        let server_driver = server.driver();
+        let server_secret = server_driver.params().secret_key.clone();
+
+        serve(server).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48470").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48470").await?;
+
+        let sender_secret = sender.driver().secret_key().clone();
+        let receiver_secret = receiver.driver().secret_key().clone();
+
+        for text in [b"message 1", b"message 2"] {
+            let message = Message::create(
+                &sender_secret,
+                &receiver_secret.public_key(),
+                text,
+                MessageEncoding::default(),
+                CompressionLevel::default()
+            )?;
+
+            sender.send("http://127.0.0.1:48470", receiver_secret.public_key(), "sealed channel", message).await?;
+        }
+
+        // Stored messages can't be opened with the server's secret key
+        let folder = server_driver.messages_inbox()
+            .channel_folder(&receiver_secret.public_key(), &ChannelName::new("sealed channel").unwrap())?;
+
+        let mut files = tokio::fs::read_dir(&folder).await?;
+        let mut sealed_files = 0;
+
+        while let Some(file) = files.next_entry().await? {
+            let name = file.file_name();
+
+            if name == "index" || name.to_string_lossy().ends_with(".meta") {
+                continue;
+            }
+
+            let sealed = SealedMessageInfo::from_json(&serde_json::json!({
+                "channel": "sealed channel",
+                "received_at": 0,
+                "sealed": serde_json::from_slice::<serde_json::Value>(&tokio::fs::read(file.path()).await?)?
+            }))?;
+
+            assert!(sealed.open(&server_secret).is_err());
+            assert!(sealed.open(&receiver_secret).is_ok());
+
+            sealed_files += 1;
+        }
+
+        assert_eq!(sealed_files, 2);
+
+        // Plain poll is refused
+        assert!(receiver.poll("sealed channel", None).await.is_err());
+
+        // Sealed poll round-trip
+        let (messages, 1) = receiver.poll_sealed("sealed channel", Some(1)).await? else {
+            panic!("Test 1 failed");
+        };
+
+        assert_eq!(messages[0].message.read(&receiver_secret, &sender_secret.public_key())?, b"message 1");
+
+        let (messages, 0) = receiver.poll_sealed("sealed channel", None).await? else {
+            panic!("Test 2 failed");
+        };
+
+        assert_eq!(messages[0].message.read(&receiver_secret, &sender_secret.public_key())?, b"message 2");
+        assert_eq!(messages[0].sender.client.public_key, sender_secret.public_key());
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reputation() -> Result<(), Box<dyn std::error::Error>> {
+        let driver = get_driver("reputation-test", 48472, |_| ()).await?
+            .with_reputation(DecayingReputation::new(Duration::from_secs(60)));
+
+        let server: TestServer = Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await;
+        let driver = server.driver();
+
+        serve(server).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+
+        // Paused clock is advanced while the requests are sent
+        #[cfg(feature = "timeouts")]
+        let sender = sender.with_timeouts(Timeouts::none());
+
+        let sender = sender.connect("127.0.0.1:48472").await?;
+
+        let sender_public = sender.driver().secret_key().public_key();
+        let receiver_public = SecretKey::random().public_key();
+
+        let send = || sender.send(
+            "http://127.0.0.1:48472",
+            receiver_public.clone(),
+            "channel",
+            Message::new("content", "sign", MessageEncoding::default())
+        );
+
+        send().await?;
+
+        // Repeated incidents cross the threshold
+        for _ in 0..5 {
+            driver.report_incident(&sender_public, Incident::OversizedMessage).await;
+
+            assert_eq!(driver.check_reputation(&sender_public).await, ReputationAction::Allow);
+        }
+
+        driver.report_incident(&sender_public, Incident::OversizedMessage).await;
+
+        assert_eq!(driver.check_reputation(&sender_public).await, ReputationAction::Reject);
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::ReputationTooLow, .. }) = send().await else {
+            panic!("Send request must be rejected");
+        };
+
+        // Score recovers after decay
+        tokio::time::advance(Duration::from_secs(60)).await;
+
+        assert_eq!(driver.check_reputation(&sender_public).await, ReputationAction::Allow);
+
+        send().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn inbox_quota() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = std::env::temp_dir().join("inbox-quota-test");
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        let inbox = StoredQueueMessagesInbox::new(temp.join("inbox"), None).await?
+            .with_quota(InboxQuota {
+                max_messages: 2,
+                ..InboxQuota::default()
+            });
+
+        let driver = ServerDriver::new(
+            GlobalTableRouter::new(temp.join("router")).await?,
+            BfsRecursionTraversal,
+            inbox,
+            ServerParams {
+                address: String::from("127.0.0.1:48487"),
+                ..ServerParams::default()
+            }
+        );
+
+        serve(Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48487").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48487").await?;
+
+        let send = || sender.send(
+            "http://127.0.0.1:48487",
+            receiver.driver().secret_key().public_key(),
+            "quota",
+            Message::new("message", "sign", MessageEncoding::default())
+        );
+
+        send().await?;
+        send().await?;
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::ClientInboxFull, .. }) = send().await else {
+            panic!("Inbox quota wasn't reported to the sender");
+        };
+
+        receiver.poll("quota", None).await?;
+
+        send().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn peek_poll() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("peek-poll-test", 48486, |_| ()).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48486").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48486").await?;
+
+        for content in ["message 1", "message 2", "message 3"] {
+            sender.send(
+                "http://127.0.0.1:48486",
+                receiver.driver().secret_key().public_key(),
+                "peek",
+                Message::new(content, "sign", MessageEncoding::default())
+            ).await?;
+        }
+
+        let contents = |messages: Vec<MessageInfo>| messages.into_iter()
+            .map(|info| info.message.content)
+            .collect::<Vec<_>>();
+
+        // Peeking doesn't consume messages
+        for _ in 0..2 {
+            let (messages, 1) = receiver.peek("peek", Some(2)).await? else {
+                panic!("Peek failed");
+            };
+
+            assert_eq!(contents(messages), ["message 1", "message 2"]);
+        }
+
+        let (messages, 0) = receiver.poll("peek", None).await? else {
+            panic!("Poll failed");
+        };
+
+        assert_eq!(contents(messages), ["message 1", "message 2", "message 3"]);
+
+        assert_eq!(receiver.peek("peek", None).await?, (vec![], 0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sender_filter_poll() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("sender-filter-poll-test", 48488, |_| ()).await?).await;
+
+        let alice = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48488").await?;
+
+        let bob = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48488").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48488").await?;
+
+        for (sender, content) in [(&alice, "alice 1"), (&bob, "bob 1"), (&alice, "alice 2")] {
+            sender.send(
+                "http://127.0.0.1:48488",
+                receiver.driver().secret_key().public_key(),
+                "filter",
+                Message::new(content, "sign", MessageEncoding::default())
+            ).await?;
+        }
+
+        let contents = |messages: Vec<MessageInfo>| messages.into_iter()
+            .map(|info| info.message.content)
+            .collect::<Vec<_>>();
+
+        // Other senders' messages stay in the inbox
+        let (messages, 1) = receiver.poll_from("filter", alice.driver().secret_key().public_key(), None).await? else {
+            panic!("Filtered poll failed");
+        };
+
+        assert_eq!(contents(messages), ["alice 1", "alice 2"]);
+
+        let (messages, 0) = receiver.poll("filter", None).await? else {
+            panic!("Poll failed");
+        };
+
+        assert_eq!(contents(messages), ["bob 1"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_channels() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("list-channels-test", 48490, |_| ()).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48490").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48490").await?;
+
+        assert!(receiver.list_channels().await?.is_empty());
+
+        for channel in ["chat", "status", "chat"] {
+            sender.send(
+                "http://127.0.0.1:48490",
+                receiver.driver().secret_key().public_key(),
+                channel,
+                Message::new("content", "sign", MessageEncoding::default())
+            ).await?;
+        }
+
+        assert_eq!(receiver.list_channels().await?, [
+            (ChannelName::new("chat").unwrap(), 2),
+            (ChannelName::new("status").unwrap(), 1)
+        ]);
+
+        assert!(sender.list_channels().await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ack_poll() -> Result<(), Box<dyn std::error::Error>> {
+        let lease = Duration::from_millis(500);
+
+        serve(get_server("ack-poll-test", 48491, |params| params.poll_lease = Some(lease)).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48491").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48491").await?;
+
+        for text in ["message 1", "message 2"] {
+            sender.send(
+                "http://127.0.0.1:48491",
+                receiver.driver().secret_key().public_key(),
+                "channel",
+                Message::new(text, "sign", MessageEncoding::default())
+            ).await?;
+        }
+
+        let (messages, 0) = receiver.poll("channel", None).await? else {
+            panic!("Leasing poll failed");
+        };
+
+        assert_eq!(messages.len(), 2);
+
+        // Leased messages are not returned until their leases expire
+        assert_eq!(receiver.poll("channel", None).await?, (vec![], 0));
+
+        assert_eq!(receiver.ack(messages[0].id).await?, 1);
+
+        tokio::time::sleep(lease + Duration::from_millis(100)).await;
+
+        // Unacknowledged message is returned again
+        let (expired, 0) = receiver.poll("channel", None).await? else {
+            panic!("Expired lease poll failed");
+        };
+
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].message.content, "message 2");
+        assert_eq!(expired[0].id, messages[1].id);
+
+        assert_eq!(receiver.ack(expired[0].id).await?, 1);
+
+        tokio::time::sleep(lease + Duration::from_millis(100)).await;
+
+        assert_eq!(receiver.poll("channel", None).await?, (vec![], 0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn range_poll() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("range-poll-test", 48495, |_| ()).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48495").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48495").await?;
+
+        let send = |content: &'static str| sender.send(
+            "http://127.0.0.1:48495",
+            receiver.driver().secret_key().public_key(),
+            "range",
+            Message::new(content, "sign", MessageEncoding::default())
+        );
+
+        send("old message").await?;
+
+        // Timestamps have seconds precision
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        let middle = crate::time::timestamp();
+
+        send("new message").await?;
+
+        let (messages, 1) = receiver.poll_range("range", middle..u64::MAX, None).await? else {
+            panic!("Range poll failed");
+        };
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message.content, "new message");
+
+        // Peeked messages can't be filtered
+        let request = PollRequest(Request::new(
+            receiver.driver().secret_key(),
+            PollRequestBody::peek(ChannelName::new("range").unwrap(), None).with_after(middle)
+        ));
+
+        let response = ReqwestHttpClient::default().post_request::<PollRequest, PollResponse>(
+            "http://127.0.0.1:48495/api/v1/poll",
+            request
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::InvalidRequestStructure, .. }));
+
+        // Messages out of the range are kept
+        let (messages, 0) = receiver.poll("range", None).await? else {
+            panic!("Poll failed");
+        };
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message.content, "old message");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn message_ids() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("message-ids-test", 48496, |_| ()).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48496").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48496").await?;
+
+        let mut ids = Vec::with_capacity(2);
+
+        for content in ["message 1", "message 2"] {
+            let id = sender.send(
+                "http://127.0.0.1:48496",
+                receiver.driver().secret_key().public_key(),
+                "ids",
+                Message::new(content, "sign", MessageEncoding::default())
+            ).await?;
+
+            assert!(id.is_some());
+
+            ids.push(id);
+        }
+
+        let (messages, 0) = receiver.poll("ids", None).await? else {
+            panic!("Poll failed");
+        };
+
+        assert_eq!(messages.iter().map(|info| info.id).collect::<Vec<_>>(), ids);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn inbox_observer() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::drivers::server::messages_inbox::test_suite::TestObserver;
+
+        let observer = TestObserver::default();
+
+        let driver = get_driver("inbox-observer-test", 48494, |_| ()).await?
+            .with_inbox_observer(observer.clone());
+
+        serve(Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48494").await?;
+
+        let receiver_public = SecretKey::random().public_key();
+
+        for channel in ["chat", "failing"] {
+            sender.send(
+                "http://127.0.0.1:48494",
+                receiver_public.clone(),
+                channel,
+                Message::new("message", "sign", MessageEncoding::default())
+            ).await?;
+        }
+
+        assert_eq!(*observer.0.lock().unwrap(), [
+            (receiver_public.clone(), ChannelName::new("chat").unwrap(), String::from("message")),
+            (receiver_public, ChannelName::new("failing").unwrap(), String::from("message"))
+        ]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn router_observer() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::drivers::server::router::tests::TestObserver;
+
+        let observer = TestObserver::default();
+
+        let driver = get_driver("router-observer-test", 48500, |_| ()).await?
+            .with_router_observer(observer.clone());
+
+        serve(Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48500").await?;
+
+        let http = ReqwestHttpClient::default();
+
+        let server = ServerApiRecord::new(SecretKey::random().public_key(), "example.org");
+
+        // Observer errors don't fail the announce
+        let response = http.post_request::<_, AnnounceResponse>(
+            "http://127.0.0.1:48500/api/v1/announce",
+            AnnounceRequest::server(&SecretKey::random(), server.clone())
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Success { .. }));
+
+        let events = observer.0.lock().unwrap().clone();
+
+        assert_eq!(events.len(), 2);
+
+        assert!(matches!(&events[0], RouterEvent::ClientIndexed { client: indexed, server: None } if indexed.public_key == client.driver().secret_key().public_key()));
+        assert_eq!(events[1], RouterEvent::ServerIndexed { server });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn disconnect_purge() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("disconnect-purge-test", 48492, |_| ()).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48492").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48492").await?;
+
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        let send = || async {
+            for channel in ["chat", "status"] {
+                sender.send(
+                    "http://127.0.0.1:48492",
+                    receiver_public.clone(),
+                    channel,
+                    Message::new("message", "sign", MessageEncoding::default())
+                ).await?;
+            }
+
+            Ok::<_, Box<dyn std::error::Error>>(())
+        };
+
+        send().await?;
+
+        // Messages are kept by the plain disconnect
+        let receiver = receiver.disconnect().await?
+            .connect("127.0.0.1:48492").await?;
+
+        assert_eq!(receiver.poll("chat", None).await?.0.len(), 1);
+
+        send().await?;
+
+        let receiver = receiver.disconnect_purging().await?
+            .connect("127.0.0.1:48492").await?;
+
+        assert_eq!(receiver.poll("chat", None).await?, (vec![], 0));
+        assert_eq!(receiver.poll("status", None).await?, (vec![], 0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn message_size() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("message-size-test", 48493, |params| params.max_message_size = 16).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48493").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48493").await?;
+
+        let send = |content: &str| sender.send(
+            "http://127.0.0.1:48493",
+            receiver.driver().secret_key().public_key(),
+            "channel",
+            Message::new(content, "sign", MessageEncoding::default())
+        );
+
+        send("small message").await?;
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::MessageTooLarge, .. }) = send("too large message").await else {
+            panic!("Large message was accepted");
+        };
+
+        let (messages, 0) = receiver.poll("channel", None).await? else {
+            panic!("Poll failed");
+        };
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message.content, "small message");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn wildcard_poll() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("wildcard-poll-test", 48489, |_| ()).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48489").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48489").await?;
+
+        for channel in ["chat/alice", "chat/bob", "status"] {
+            sender.send(
+                "http://127.0.0.1:48489",
+                receiver.driver().secret_key().public_key(),
+                channel,
+                Message::new(channel, "sign", MessageEncoding::default())
+            ).await?;
+        }
+
+        // Exact channel polls don't parse patterns
+        assert_eq!(receiver.poll("chat/*", None).await?, (vec![], 0));
+
+        let (messages, 0) = receiver.poll_wildcard("chat/*", None).await? else {
+            panic!("Wildcard poll failed");
+        };
+
+        let mut channels = messages.iter()
+            .map(|info| info.channel.as_str())
+            .collect::<Vec<_>>();
+
+        channels.sort();
+
+        assert_eq!(channels, ["chat/alice", "chat/bob"]);
+        assert!(messages.iter().all(|info| info.channel.as_str() == info.message.content));
+
+        let (messages, 0) = receiver.poll("status", None).await? else {
+            panic!("Poll failed");
+        };
+
+        assert_eq!(messages.len(), 1);
+
+        // Remaining messages are reported per channel
+        for channel in ["chat/alice", "chat/alice", "chat/bob", "status"] {
+            sender.send(
+                "http://127.0.0.1:48489",
+                receiver.driver().secret_key().public_key(),
+                channel,
+                Message::new(format!("{channel} {}", safe_random_u64()), "sign", MessageEncoding::default())
+            ).await?;
+        }
+
+        let (messages, remaining) = receiver.poll_wildcard_by_channel("chat/*", Some(1)).await?;
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(remaining.values().sum::<u64>(), 2);
+        assert!(remaining.keys().all(|channel| channel.starts_with("chat/")));
+
+        let (_, remaining) = receiver.poll_wildcard_by_channel("chat/*", None).await?;
+
+        assert!(remaining.is_empty());
+
+        Ok(())
+    }
+
+    #[cfg(all(
+        feature = "usage-hourly",
+        feature = "test_utils",
+        feature = "router-ram",
+        feature = "inbox-ram"
+    ))]
+    #[tokio::test(start_paused = true)]
+    async fn usage_accounting() -> Result<(), Box<dyn std::error::Error>> {
+        use std::net::SocketAddr;
+
+        use crate::test_utils::InMemoryTransport;
+
+        // Real sockets make the paused clock advance
+        // while requests are in flight
+        let transport = InMemoryTransport::new();
+        let address = SocketAddr::from(([10, 0, 0, 1], 8001));
+
+        let driver = ServerDriver::new(
+            RamRouter::new(),
+            BfsRecursionTraversal,
+            RamMessagesInbox::new(),
+            ServerParams {
+                address: address.to_string(),
+                ..ServerParams::default()
+            }
+        ).with_usage(HourlyUsage::new(2));
+
+        let server = Server::new(transport.client(address), transport.server(), driver).await;
+        let driver = server.driver();
+
+        tokio::spawn(async move {
+            let _ = server.serve(address).await;
+        });
+
+        transport.wait_up(&address).await;
+
+        let sender = ClientMiddleware::new(transport.client(([10, 1, 0, 1], 8001)), ClientDriver::random())
+            .connect(address).await?;
+
+        let receiver = ClientMiddleware::new(transport.client(([10, 1, 0, 2], 8001)), ClientDriver::random())
+            .connect(address).await?;
+
+        let sender_public = sender.driver().secret_key().public_key();
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        let send = |content: &'static str| sender.send(
+            address.to_string(),
+            receiver_public.clone(),
+            "usage",
+            Message::new(content, "sign", MessageEncoding::default())
+        );
+
+        send("hello").await?;
+        send("hello, world").await?;
+
+        assert_eq!(receiver.poll("usage", None).await?.0.len(), 2);
+
+        let sender_usage = driver.usage(&sender_public);
+        let receiver_usage = driver.usage(&receiver_public);
+
+        assert_eq!((sender_usage.sent_messages, sender_usage.sent_bytes), (2, 17));
+        assert_eq!(sender_usage.received_messages + sender_usage.polled_messages, 0);
+
+        assert_eq!((receiver_usage.received_messages, receiver_usage.received_bytes), (2, 17));
+        assert_eq!((receiver_usage.polled_messages, receiver_usage.polled_bytes), (2, 17));
+        assert_eq!(receiver_usage.sent_messages, 0);
+
+        assert_eq!(driver.top_usage(1), vec![(receiver_public.clone(), receiver_usage)]);
+
+        // Next hour bucket
+        tokio::time::advance(Duration::from_secs(3600)).await;
+
+        send("bye").await?;
+
+        assert_eq!(driver.usage(&sender_public).sent_messages, 3);
+        assert_eq!(driver.usage(&sender_public).sent_bytes, 20);
+
+        // The first bucket is out of the retention window
+        tokio::time::advance(Duration::from_secs(3600)).await;
+
+        assert_eq!(driver.usage(&sender_public).sent_messages, 1);
+        assert_eq!(driver.usage(&sender_public).sent_bytes, 3);
+
+        assert_eq!(driver.usage(&receiver_public).polled_messages, 0);
+        assert_eq!(driver.usage(&receiver_public).received_bytes, 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn server_reannounce() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("server-reannounce-test", 48505, |_| ()).await?;
+        let driver = server.driver();
+
+        serve(server).await;
+
+        let http = ReqwestHttpClient::default();
+        let announcer = SecretKey::random();
+
+        let announce = |request: AnnounceRequest| http.post_request::<_, AnnounceResponse>("http://127.0.0.1:48505/api/v1/announce", request);
+
+        let public_key = SecretKey::random().public_key();
+        let now = crate::time::timestamp();
+
+        let lookup = || async {
+            driver.router().lookup_server(&public_key).await
+                .map(|server| server.map(|(server, _)| server.address))
+        };
+
+        announce(AnnounceRequest::server_at(&announcer, ServerApiRecord::new(public_key.clone(), "old.example.org"), now - 60)).await.map_err(MiddlewareError::from)?;
+        announce(AnnounceRequest::server_at(&announcer, ServerApiRecord::new(public_key.clone(), "new.example.org"), now - 30)).await.map_err(MiddlewareError::from)?;
+
+        assert_eq!(lookup().await?.as_deref(), Some("new.example.org"));
+
+        // Older announces are accepted but ignored
+        let response = announce(AnnounceRequest::server_at(&announcer, ServerApiRecord::new(public_key.clone(), "stale.example.org"), now - 45)).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Success { .. }));
+        assert_eq!(lookup().await?.as_deref(), Some("new.example.org"));
+
+        // Announces from the future don't pin the record
+        let request = AnnounceRequest::bulk(&announcer, [
+            AnnounceRequestBody::server_at(ServerApiRecord::new(public_key.clone(), "future.example.org"), u64::MAX)
+        ]);
+
+        announce(request).await.map_err(MiddlewareError::from)?;
+
+        assert_eq!(lookup().await?.as_deref(), Some("future.example.org"));
+
+        announce(AnnounceRequest::server(&announcer, ServerApiRecord::new(public_key.clone(), "legacy.example.org"))).await.map_err(MiddlewareError::from)?;
+
+        assert_eq!(lookup().await?.as_deref(), Some("legacy.example.org"));
+        assert_eq!(driver.router().servers().await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn partial_announce() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("partial-announce-test", 48485, |params| {
+            params.announce_limits.max_server_size = 256;
+            params.announce_limits.max_entries = 4;
+        }).await?;
+
+        let driver = server.driver();
+
+        serve(server).await;
+
+        let http = ReqwestHttpClient::default();
+        let announcer = SecretKey::random();
+
+        let announce = |request: AnnounceRequest| http.post_request::<_, AnnounceResponse>("http://127.0.0.1:48485/api/v1/announce", request);
+
+        let valid_server = ServerApiRecord::new(SecretKey::random().public_key(), "example.org");
+        let oversized_server = ServerApiRecord::new(SecretKey::random().public_key(), "a".repeat(512));
+
+        let client_secret = SecretKey::random();
+
+        let valid_client = ClientApiRecord::new(
+            client_secret.public_key(),
+            ConnectionCertificate::new(&client_secret, valid_server.public_key.clone()),
+            ClientInfo::thin()
+        );
+
+        // Certificate is signed for another server
+        let invalid_client = ClientApiRecord::new(
+            client_secret.public_key(),
+            ConnectionCertificate::new(&SecretKey::random(), valid_server.public_key.clone()),
+            ClientInfo::thin()
+        );
+
+        let request = AnnounceRequest::bulk(&announcer, [
+            AnnounceRequestBody::server(valid_server.clone()),
+            AnnounceRequestBody::server(oversized_server.clone()),
+            AnnounceRequestBody::client(invalid_client, valid_server.clone()),
+            AnnounceRequestBody::client(valid_client, valid_server.clone())
+        ]);
+
+        let response = announce(request).await.map_err(MiddlewareError::from)?;
+
+        let Response::Success { response: body, .. } = response.0 else {
+            panic!("Bulk announce request failed: {response:?}");
+        };
+
+        assert_eq!(body.entries.len(), 4);
+
+        assert!(body.entries[0].is_accepted());
+        assert!(body.entries[3].is_accepted());
+
+        assert!(matches!(body.entries[1], AnnounceEntryResult::Rejected { status: ResponseStatus::AnnounceRecordTooLarge, .. }));
+        assert!(matches!(body.entries[2], AnnounceEntryResult::Rejected { status: ResponseStatus::RequestValidationFailed, .. }));
+
+        // Bad entries don't affect the valid ones
+        assert!(driver.router().lookup_server(&valid_server.public_key).await?.is_some());
+        assert!(driver.router().lookup_server(&oversized_server.public_key).await?.is_none());
+        assert!(driver.router().lookup_remote_client(&client_secret.public_key(), None).await?.is_some());
+
+        // Too many entries
+        let request = AnnounceRequest::bulk(&announcer, (0..5).map(|_| AnnounceRequestBody::server(valid_server.clone())));
+
+        let response = announce(request).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::TooManyAnnounceEntries, .. }));
+
+        // Single entry announces keep legacy response
+        let response = announce(AnnounceRequest::server(&announcer, oversized_server)).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::AnnounceRecordTooLarge, .. }));
+
+        let response = announce(AnnounceRequest::server(&announcer, valid_server)).await.map_err(MiddlewareError::from)?;
+
+        let Response::Success { response: body, .. } = response.0 else {
+            panic!("Announce request failed: {response:?}");
+        };
+
+        assert!(body.entries.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pagination() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("pagination-test", 48474, |_| ()).await?;
+        let driver = server.driver();
+
+        for i in 0..5 {
+            let server = ServerApiRecord::new(SecretKey::random().public_key(), format!("example{i}.org"));
+
+            driver.router().index_server(server).await?;
+        }
+
+        serve(server).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+
+        // Legacy requests
+        assert_eq!(client.get_servers("127.0.0.1:48474").await?.len(), 5);
+
+        let page = client.get_servers_page("127.0.0.1:48474", &PageRequest::offset(3, Some(10))).await?;
+
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.next, None);
+
+        // Insertion between pages
+        let first = client.get_servers_page("127.0.0.1:48474", &PageRequest::first(2)).await?;
+
+        let inserted = ServerApiRecord::new(SecretKey::random().public_key(), "inserted.org");
+
+        driver.router().index_server(inserted).await?;
+
+        let mut servers = first.items;
+        let mut request = PageRequest::next(first.next.unwrap(), Some(2));
+
+        loop {
+            let page = client.get_servers_page("127.0.0.1:48474", &request).await?;
+
+            servers.extend(page.items);
+
+            match page.next {
+                Some(token) => request = PageRequest::next(token, Some(2)),
+                None => break
+            }
+        }
+
+        let unique = servers.iter()
+            .map(|server| server.public_key.clone())
+            .collect::<std::collections::HashSet<_>>();
+
+        assert_eq!(unique.len(), servers.len());
+        assert!(servers.len() >= 5);
+
+        assert_eq!(client.walk_servers("127.0.0.1:48474", 2).await?.len(), 6);
+
+        // Total counts and pages iterator
+        let response = client.get_servers_paged("127.0.0.1:48474", &PageRequest::offset(1, Some(2))).await?;
+
+        assert_eq!(response.servers.len(), 2);
+        assert_eq!(response.total, Some(6));
+
+        let mut pages = client.servers_pages("127.0.0.1:48474", 4);
+
+        assert_eq!(pages.next_page().await?.map(|page| page.len()), Some(4));
+        assert_eq!(pages.total(), Some(6));
+        assert_eq!(pages.next_page().await?.map(|page| page.len()), Some(2));
+        assert_eq!(pages.next_page().await?, None);
+
+        // Tampered token
+        let mut token = client.get_servers_page("127.0.0.1:48474", &PageRequest::first(2)).await?
+            .next
+            .unwrap();
+
+        token.replace_range(0..1, if token.starts_with('A') { "B" } else { "A" });
+
+        assert!(client.get_servers_page("127.0.0.1:48474", &PageRequest::next(token, Some(2))).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn clients_filter() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("clients-filter-test", 48503, |_| ()).await?;
+        let driver = server.driver();
+
+        for client_type in [ClientType::Thin, ClientType::Thick, ClientType::Thick, ClientType::Thin, ClientType::Thick] {
+            let mut client = get_client();
+
+            client.info.client_type = client_type;
+
+            driver.router().index_local_client(client).await?;
+        }
+
+        serve(server).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+
+        // Legacy clients ignore the total count
+        assert_eq!(client.get_clients("127.0.0.1:48503").await?.len(), 5);
+
+        let first = client.get_clients_filtered("127.0.0.1:48503", ClientType::Thick, &PageRequest::first(2)).await?;
+
+        assert_eq!(first.clients.len(), 2);
+        assert_eq!(first.total, Some(3));
+
+        let second = client.get_clients_filtered("127.0.0.1:48503", ClientType::Thick, &PageRequest::next(first.next.unwrap(), Some(2))).await?;
+
+        assert_eq!(second.clients.len(), 1);
+        assert_eq!(second.next, None);
+
+        assert!(first.clients.iter()
+            .chain(second.clients.iter())
+            .all(|client| client.info.client_type == ClientType::Thick));
+
+        let page = client.get_clients_filtered("127.0.0.1:48503", ClientType::Thin, &PageRequest::offset(1, None)).await?;
+
+        assert_eq!(page.clients.len(), 1);
+        assert_eq!(page.total, Some(2));
+        assert_eq!(page.next, None);
+
+        let http = ReqwestHttpClient::default();
+
+        assert!(http.get_request::<ClientsResponse>("http://127.0.0.1:48503/api/v1/clients?client_type=invalid").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ordered_delivery() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("ordered-delivery-test", 48475, |_| ()).await?;
+
+        serve(server).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48475").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48475").await?;
+
+        let sender_secret = sender.driver().secret_key().clone();
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        let messages = (0..8)
+            .map(|_| {
+                let sequence = sender.next_sequence(&receiver_public, ChannelName::new("ordered").unwrap());
+
+                Message::create_sequenced(
+                    &sender_secret,
+                    &receiver_public,
+                    sequence.to_string(),
+                    sequence,
+                    MessageEncoding::default(),
+                    CompressionLevel::default()
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut reorderer = MessageReorderer::new(ReorderParams {
+            window: 16,
+            gap_timeout: Duration::from_millis(200)
+        });
+
+        let sequences = |events: &[OrderedEvent]| events.iter()
+            .map(|event| match event {
+                OrderedEvent::Message(info) => info.message.sequence.unwrap(),
+                OrderedEvent::GapDetected { .. } => 0
+            })
+            .collect::<Vec<_>>();
+
+        // Shuffled delivery is released in order
+        for i in [2, 0, 4, 1, 3] {
+            sender.send("http://127.0.0.1:48475", receiver_public.clone(), "ordered", messages[i].clone()).await?;
+        }
+
+        let (events, _) = receiver.poll_ordered("ordered", None, &mut reorderer).await?;
+
+        assert_eq!(sequences(&events), [1, 2, 3, 4, 5]);
+
+        // Dropped sequence number produces a gap
+        for i in [7, 5] {
+            sender.send("http://127.0.0.1:48475", receiver_public.clone(), "ordered", messages[i].clone()).await?;
+        }
+
+        let (events, _) = receiver.poll_ordered("ordered", None, &mut reorderer).await?;
+
+        assert_eq!(sequences(&events), [6]);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let events = reorderer.flush();
+
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], OrderedEvent::GapDetected { from: 7, to: 7, .. }));
+        assert_eq!(sequences(&events[1..]), [8]);
+
+        assert!(reorderer.flush().is_empty());
+
+        // Sequence numbers are verified by the receiver
+        let OrderedEvent::Message(info) = &events[1] else {
+            unreachable!();
+        };
+
+        assert_eq!(info.message.read(receiver.driver().secret_key(), &sender_secret.public_key())?, b"8");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn certificate_scope() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("certificate-scope-test", 48479, |_| ()).await?;
+        let server_public = server.driver().params().secret_key.public_key();
+
+        serve(server).await;
+
+        let scope = CertificateScope::new(
+            vec![ChannelRule::Prefix(String::from("bot/"))],
+            vec![CertificateOperation::Send, CertificateOperation::Poll]
+        );
+
+        let scoped = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect_scoped("127.0.0.1:48479", server_public, scope.clone()).await?;
+
+        let unscoped = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48479").await?;
+
+        assert_eq!(scoped.connection_certificate().scope.as_ref(), Some(&scope));
+        assert_eq!(unscoped.connection_certificate().scope, None);
+
+        let unscoped_public = unscoped.driver().secret_key().public_key();
+
+        let message = || Message::new("content", "sign", MessageEncoding::default());
+
+        fn unauthorized<T>(result: Result<T, MiddlewareError>) -> String {
+            match result {
+                Err(MiddlewareError::RequestFailed { status: ResponseStatus::Unauthorized, code: ErrorCode::Forbidden, reason }) => reason,
+                _ => panic!("Request must be rejected as unauthorized")
+            }
+        }
+
+        // Allowed operations
+        scoped.send("http://127.0.0.1:48479", unscoped_public.clone(), "bot/status", message()).await?;
+        scoped.poll("bot/commands", None).await?;
+
+        // Channel outside of the scope
+        let reason = unauthorized(scoped.send("http://127.0.0.1:48479", unscoped_public.clone(), "chat", message()).await);
+
+        assert!(reason.contains("Channel `chat`"));
+
+        let reason = unauthorized(scoped.poll("chat", None).await);
+
+        assert!(reason.contains("Channel `chat`"));
+
+        // Operation outside of the scope
+        // Lookup middleware skips failed servers, so the request is sent directly
+        let response = scoped.http_client_ref().post_request::<LookupRequest, LookupResponse>(
+            "http://127.0.0.1:48479/api/v1/lookup",
+            LookupRequest::new(scoped.driver_ref().secret_key(), unscoped_public.clone(), None)
+        ).await.map_err(MiddlewareError::from)?;
+
+        let Response::Error { status: ResponseStatus::Unauthorized, reason, .. } = response.0 else {
+            panic!("Lookup request must be rejected as unauthorized");
+        };
+
+        assert!(reason.contains("Operation `lookup`"));
+
+        // Unscoped certificates are not restricted
+        unscoped.send("http://127.0.0.1:48479", unscoped_public.clone(), "chat", message()).await?;
+
+        let (messages, _) = unscoped.poll("chat", None).await?;
+
+        assert_eq!(messages.len(), 1);
+
+        assert!(unscoped.lookup(unscoped_public, None).await?.is_some());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "admin-api")]
+    #[tokio::test]
+    async fn admin_api() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::rest_api::middleware::{
+            AdminAuth,
+            AdminRequest,
+            AdminResponse,
+            AdminStats,
+            RoutingExport,
+            AdminPurge,
+            AdminBlock,
+            AdminMaintenance,
+            MaintenanceReport
+        };
+
+        let driver = get_driver("admin-api-test", 48476, |_| ()).await?
+            .with_rate_limiter(RateLimiter::default());
+
+        let server = Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await;
+
+        let admin_secret = SecretKey::random();
+
+        tokio::spawn({
+            let server = server.clone();
+
+            async move {
+                let _ = server.serve_admin("127.0.0.1:48477", AdminAuth::Token(String::from("admin token"))).await;
+            }
+        });
+
+        tokio::spawn({
+            let server = server.clone();
+            let admin_public = admin_secret.public_key();
+
+            async move {
+                let _ = server.serve_admin("127.0.0.1:48478", AdminAuth::Keys(vec![admin_public])).await;
+            }
+        });
+
+        let driver = server.driver();
+
+        serve(server).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        client.connect("127.0.0.1:48476").await?;
+
+        let http = ReqwestHttpClient::default();
+
+        // Valid auth
+        let response = http.post_request::<_, AdminResponse<AdminStats>>(
+            "http://127.0.0.1:48477/admin/v1/stats",
+            AdminRequest::with_token("admin token", ())
+        ).await.map_err(MiddlewareError::from)?;
+
+        let Response::Success { response: stats, .. } = response else {
+            panic!("Admin stats request failed: {response:?}");
+        };
+
+        assert_eq!(stats.local_clients, 1);
+
+        // Server has no usage tracker
+        assert!(stats.usage.is_empty());
+
+        let response = http.post_request::<_, AdminResponse<RoutingExport>>(
+            "http://127.0.0.1:48478/admin/v1/routing",
+            AdminRequest::signed(&admin_secret, ())
+        ).await.map_err(MiddlewareError::from)?;
+
+        let Response::Success { response: export, .. } = response else {
+            panic!("Admin routing request failed: {response:?}");
+        };
+
+        assert_eq!(export.local_clients[0].public_key, client_public);
+
+        let response = http.post_request::<_, AdminResponse<()>>(
+            "http://127.0.0.1:48478/admin/v1/disconnect",
+            AdminRequest::signed(&admin_secret, client_public.to_base64())
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Success { .. }));
+        assert!(client.get_clients("127.0.0.1:48476").await?.is_empty());
+
+        let response = http.post_request::<_, AdminResponse<()>>(
+            "http://127.0.0.1:48478/admin/v1/purge",
+            AdminRequest::signed(&admin_secret, AdminPurge {
+                receiver: client_public.clone(),
+                channel: None
+            })
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Success { .. }));
+
+        let blocked_public = SecretKey::random().public_key();
+
+        let response = http.post_request::<_, AdminResponse<()>>(
+            "http://127.0.0.1:48477/admin/v1/block",
+            AdminRequest::with_token("admin token", AdminBlock {
+                public_key: blocked_public.clone(),
+                blocked: true
+            })
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Success { .. }));
+        assert!(driver.is_blacklisted(&blocked_public));
+
+        client.connect("127.0.0.1:48476").await?;
+
+        // Routers track last seen time in seconds
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        let response = http.post_request::<_, AdminResponse<MaintenanceReport>>(
+            "http://127.0.0.1:48478/admin/v1/maintenance",
+            AdminRequest::signed(&admin_secret, AdminMaintenance {
+                inbox_cleanup: true,
+                router_cleanup: Some(Duration::ZERO)
+            })
+        ).await.map_err(MiddlewareError::from)?;
+
+        let Response::Success { response: report, .. } = response else {
+            panic!("Admin maintenance request failed: {response:?}");
+        };
+
+        assert_eq!(report.router_removed, 1);
+        assert!(client.get_clients("127.0.0.1:48476").await?.is_empty());
+
+        let limits = RateLimits {
+            per_key: RateLimit::per_second(5),
+            ..RateLimits::default()
+        };
+
+        let response = http.post_request::<_, AdminResponse<()>>(
+            "http://127.0.0.1:48478/admin/v1/rate_limits",
+            AdminRequest::signed(&admin_secret, limits.clone())
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Success { .. }));
+        assert_eq!(driver.rate_limiter().map(RateLimiter::limits), Some(limits));
+
+        // Replayed and stale requests
+        let request = AdminRequest::signed(&admin_secret, ());
+
+        let response = http.post_request::<_, AdminResponse<AdminStats>>(
+            "http://127.0.0.1:48478/admin/v1/stats",
+            request.clone()
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Success { .. }));
+
+        let response = http.post_request::<_, AdminResponse<AdminStats>>(
+            "http://127.0.0.1:48478/admin/v1/stats",
+            request
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Error { status: ResponseStatus::RequestExpired, .. }));
+
+        let mut request = AdminRequest::with_token("admin token", ());
+
+        request.request.timestamp = Some(crate::time::timestamp() - 3600);
+
+        let response = http.post_request::<_, AdminResponse<AdminStats>>(
+            "http://127.0.0.1:48477/admin/v1/stats",
+            request
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Error { status: ResponseStatus::RequestExpired, .. }));
+
+        let response = http.post_request::<_, AdminResponse<AdminStats>>(
+            "http://127.0.0.1:48478/admin/v1/stats",
+            AdminRequest {
+                token: None,
+                request: Request::legacy(&admin_secret, ())
+            }
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Error { status: ResponseStatus::RequestExpired, .. }));
+
+        // Invalid auth
+        let response = http.post_request::<_, AdminResponse<AdminStats>>(
+            "http://127.0.0.1:48477/admin/v1/stats",
+            AdminRequest::with_token("wrong token", ())
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Error { status: ResponseStatus::RequestValidationFailed, .. }));
+
+        let response = http.post_request::<_, AdminResponse<AdminStats>>(
+            "http://127.0.0.1:48478/admin/v1/stats",
+            AdminRequest::signed(&SecretKey::random(), ())
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response, Response::Error { status: ResponseStatus::RequestValidationFailed, .. }));
+
+        // Admin routes are not served on the public listener
+        let response = reqwest::Client::new()
+            .post("http://127.0.0.1:48476/admin/v1/stats")
+            .body(AdminRequest::with_token("admin token", ()).to_json()?.to_string())
+            .send().await?;
+
+        assert_eq!(response.status(), 404);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mdns")]
+    #[tokio::test]
+    async fn local_discovery() -> Result<(), Box<dyn std::error::Error>> {
+        use std::net::{Ipv4Addr, SocketAddrV4};
+
+        use crate::discovery::{DiscoveryParams, DiscoveryError, Advertiser, ServiceInfo};
+
+        // Loopback multicast is not available everywhere
+        if std::env::var_os("HYPERBOREA_SKIP_MULTICAST_TESTS").is_some() {
+            return Ok(());
+        }
+
+        let discovery = DiscoveryParams {
+            group: SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 48483),
+            interface: Ipv4Addr::LOCALHOST,
+            ttl: 120
+        };
+
+        match Advertiser::start(discovery, ServiceInfo::new(0, SecretKey::random().public_key())) {
+            Ok(advertiser) => advertiser.stop(),
+            Err(DiscoveryError::Io(_)) => return Ok(()),
+            Err(err) => return Err(err.into())
+        }
+
+        let enable_discovery = |params: &mut ServerParams| {
+            params.local_discovery = Some(discovery);
+        };
+
+        let server_a = get_server("local-discovery-test-a", 48480, enable_discovery).await?;
+        let server_b = get_server("local-discovery-test-b", 48481, enable_discovery).await?;
+
+        let public_a = server_a.driver().params().secret_key.public_key();
+        let public_b = server_b.driver().params().secret_key.public_key();
+
+        serve(server_a).await;
+        serve(server_b).await;
+
+        // Advertiser claiming the server A's port with a foreign key
+        let forged_public = SecretKey::random().public_key();
+
+        let _forged = Advertiser::start(discovery, ServiceInfo::new(48480, forged_public.clone()))?;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+
+        let servers = client.discover_local_servers(&discovery, Duration::from_millis(500)).await?;
+
+        assert!(servers.contains(&ServerApiRecord::new(public_a, "127.0.0.1:48480")));
+        assert!(servers.contains(&ServerApiRecord::new(public_b, "127.0.0.1:48481")));
+
+        assert!(!servers.iter().any(|server| server.public_key == forged_public));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "router-ram")]
+    #[tokio::test]
+    async fn routing_table_full() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = std::env::temp_dir().join("routing-table-full-test");
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        let router = RamRouter::new()
+            .with_limits(RouterLimits::new()
+                .with_max_servers(1)
+                .with_eviction(RouterEviction::Disabled));
+
+        let driver = ServerDriver::new(
+            router,
+            BfsRecursionTraversal,
+            StoredQueueMessagesInbox::new(&temp, None).await?,
+            ServerParams {
+                address: String::from("127.0.0.1:48497"),
+                ..ServerParams::default()
+            }
+        );
+
+        let server = Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await;
+
+        tokio::spawn(async move {
+            let _ = server.serve("127.0.0.1:48497").await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let http = ReqwestHttpClient::default();
+        let announcer = SecretKey::random();
+
+        let announce = |request: AnnounceRequest| http.post_request::<_, AnnounceResponse>("http://127.0.0.1:48497/api/v1/announce", request);
+
+        let servers = [
+            ServerApiRecord::new(SecretKey::random().public_key(), "example1.org"),
+            ServerApiRecord::new(SecretKey::random().public_key(), "example2.org")
+        ];
+
+        let response = announce(AnnounceRequest::server(&announcer, servers[0].clone())).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Success { .. }));
+
+        // Peers can back off when the table is full
+        let response = announce(AnnounceRequest::server(&announcer, servers[1].clone())).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::RoutingTableFull, .. }));
+
+        let request = AnnounceRequest::bulk(&announcer, servers.map(AnnounceRequestBody::server));
+
+        let Response::Success { response: body, .. } = announce(request).await.map_err(MiddlewareError::from)?.0 else {
+            panic!("Bulk announce request failed");
+        };
+
+        assert!(body.entries[0].is_accepted());
+        assert!(matches!(body.entries[1], AnnounceEntryResult::Rejected { status: ResponseStatus::RoutingTableFull, .. }));
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "router-ram", feature = "router-cleanup"))]
+    #[tokio::test]
+    async fn router_cleanup() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = std::env::temp_dir().join("router-cleanup-test");
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        let driver = ServerDriver::new(
+            RamRouter::new().with_availability_timeout(Duration::from_millis(300)),
+            BfsRecursionTraversal,
+            StoredQueueMessagesInbox::new(&temp, None).await?,
+            ServerParams {
+                address: String::from("127.0.0.1:48501"),
+                ..ServerParams::default()
+            }
+        );
+
+        let server = Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await;
+        let driver = server.driver();
+
+        let cleanup = server.spawn_router_cleanup(Duration::from_millis(100), Duration::from_millis(600));
+
+        tokio::spawn(async move {
+            let _ = server.serve("127.0.0.1:48501").await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48501").await?;
+
+        let client_public = client.driver().secret_key().public_key();
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        assert!(matches!(driver.router().lookup_local_client(&client_public, None).await?, Some((_, false))));
+
+        // Requests refresh the last seen time
+        client.poll("channel", None).await?;
+
+        assert!(matches!(driver.router().lookup_local_client(&client_public, None).await?, Some((_, true))));
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        assert_eq!(driver.router().local_clients().await?.len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        assert!(driver.router().local_clients().await?.is_empty());
+
+        cleanup.abort();
+
+        Ok(())
+    }
+
+    #[cfg(feature = "health-checks")]
+    #[tokio::test]
+    async fn health_checks() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("health-checks-test", 48504, |_| ()).await?;
+        let driver = server.driver();
+
+        let healthy = ServerApiRecord::new(driver.params().secret_key.public_key(), "127.0.0.1:48504");
+        let impostor = ServerApiRecord::new(SecretKey::random().public_key(), "127.0.0.1:48504");
+        let unreachable = ServerApiRecord::new(SecretKey::random().public_key(), "127.0.0.1:1");
+
+        for server in [&healthy, &impostor, &unreachable] {
+            driver.router().index_server(server.clone()).await?;
+        }
+
+        serve(server.clone()).await;
+
+        assert_eq!(server.check_servers_health(Duration::from_secs(2)).await, (1, 2));
+
+        assert_eq!(driver.router().server_health(&healthy.public_key).await?, ServerHealth::Healthy);
+        assert_eq!(driver.router().server_health(&impostor.public_key).await?, ServerHealth::Unhealthy);
+        assert_eq!(driver.router().server_health(&unreachable.public_key).await?, ServerHealth::Unhealthy);
+
+        let hints = driver.router().lookup_remote_client_hint(&SecretKey::random().public_key(), None).await?;
+
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].server, healthy);
+
+        // Checks are not configured
+        assert!(server.spawn_health_checks().is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn blacklist() -> Result<(), Box<dyn std::error::Error>> {
+        let blacklist = Blacklist::new();
+
+        let driver = get_driver("blacklist-test", 48502, |_| ()).await?
+            .with_blacklist(blacklist.clone());
+
+        let server: TestServer = Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await;
+        let driver = server.driver();
+
+        serve(server).await;
+
+        let banned = ClientDriver::random();
+        let banned_public = banned.secret_key().public_key();
+
+        blacklist.add(banned_public.clone());
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::Forbidden, .. }) = ClientMiddleware::new(ReqwestHttpClient::default(), banned.clone()).connect("127.0.0.1:48502").await else {
+            panic!("Connect request must be rejected");
+        };
+
+        assert!(driver.router().lookup_local_client(&banned_public, None).await?.is_none());
+
+        // Bans are changed without restart
+        blacklist.remove(&banned_public);
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), banned)
+            .connect("127.0.0.1:48502").await?;
+
+        let receiver_public = SecretKey::random().public_key();
+
+        blacklist.add(receiver_public.clone());
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::Forbidden, .. }) = client.send(
+            "http://127.0.0.1:48502",
+            receiver_public,
+            "channel",
+            Message::new("content", "sign", MessageEncoding::default())
+        ).await else {
+            panic!("Send request must be rejected");
+        };
+
+        blacklist.add(banned_public);
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::Forbidden, .. }) = client.poll("channel", None).await else {
+            panic!("Poll request must be rejected");
+        };
+
+        // All the signed routes check the blacklist
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::Forbidden, .. }) = client.ack([1]).await else {
+            panic!("Ack request must be rejected");
+        };
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::Forbidden, .. }) = client.list_channels().await else {
+            panic!("Channels request must be rejected");
+        };
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::Forbidden, .. }) = client.heartbeat().await else {
+            panic!("Heartbeat request must be rejected");
+        };
+
+        // Blacklisted announced keys are not indexed
+        let http = ReqwestHttpClient::default();
+        let server_public = SecretKey::random().public_key();
+
+        blacklist.add(server_public.clone());
+
+        let request = AnnounceRequest::bulk(&SecretKey::random(), [
+            AnnounceRequestBody::server(ServerApiRecord::new(server_public.clone(), "example1.org")),
+            AnnounceRequestBody::server(ServerApiRecord::new(SecretKey::random().public_key(), "example2.org"))
+        ]);
+
+        let Response::Success { response: body, .. } = http.post_request::<_, AnnounceResponse>("http://127.0.0.1:48502/api/v1/announce", request).await.map_err(MiddlewareError::from)?.0 else {
+            panic!("Bulk announce request failed");
+        };
+
+        assert!(matches!(body.entries[0], AnnounceEntryResult::Rejected { status: ResponseStatus::Forbidden, .. }));
+        assert!(body.entries[1].is_accepted());
+
+        assert!(driver.router().lookup_server(&server_public).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn client_aliases() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("client-aliases-test", 48506, |_| ()).await?;
+        let server_public = server.driver().params().secret_key.public_key();
+
+        serve(server).await;
+
+        let connect = |alias: &str| {
+            // Skip client-side validation to test the server's one
+            let alias = ClientAlias::from_json(&serde_json::Value::from(alias)).unwrap();
+            let server_public = server_public.clone();
+
+            async move {
+                ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+                    .connect_with_alias("127.0.0.1:48506", server_public, alias).await
+            }
+        };
+
+        let alice = connect("alice").await?;
+        let alice_public = alice.driver().secret_key().public_key();
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::ClientAliasTaken, .. }) = connect("alice").await else {
+            panic!("Taken alias must be rejected");
+        };
+
+        let Err(MiddlewareError::RequestFailed { status: ResponseStatus::InvalidClientAlias, .. }) = connect("Not Valid").await else {
+            panic!("Invalid alias must be rejected");
+        };
+
+        let bob = connect("bob").await?;
+
+        let Some((client, server, _)) = bob.lookup_alias("127.0.0.1:48506", ClientAlias::new("alice")?, None).await? else {
+            panic!("Client must be found by its alias");
+        };
+
+        assert_eq!(client.public_key, alice_public);
+        assert_eq!(server.public_key, server_public);
+
+        assert!(bob.lookup_alias("127.0.0.1:48506", ClientAlias::new("alice")?, Some(ClientType::Server)).await?.is_none());
+        assert!(bob.lookup_alias("127.0.0.1:48506", ClientAlias::new("carol")?, None).await?.is_none());
+
+        // Aliases are released on disconnect
+        alice.disconnect().await?;
+
+        assert!(bob.lookup_alias("127.0.0.1:48506", ClientAlias::new("alice")?, None).await?.is_none());
+
+        connect("alice").await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bootstrap")]
+    #[tokio::test]
+    async fn bootstrap() -> Result<(), Box<dyn std::error::Error>> {
+        let server_a = get_server("bootstrap-test-a", 48507, |params| {
+            params.bootstrap.servers = vec![
+                Address::Raw(String::from("127.0.0.1:48508")),
+                Address::Http { address: String::from("127.0.0.1:48507") }
+            ];
+
+            params.bootstrap.backoff = Duration::from_millis(200);
+        }).await?;
+
+        let server_b = get_server("bootstrap-test-b", 48508, |_| ()).await?;
+
+        let driver_a = server_a.driver();
+        let driver_b = server_b.driver();
+
+        let known_server = ServerApiRecord::new(SecretKey::random().public_key(), "example.org");
+
+        driver_b.router().index_server(known_server.clone()).await?;
+
+        // Bootstrap server is not started yet
+        serve(server_a).await;
+
+        assert!(driver_a.router().servers().await?.is_empty());
+
+        serve(server_b).await;
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let server_b_public = driver_b.params().secret_key.public_key();
+
+        assert!(driver_a.router().lookup_server(&server_b_public).await?.is_some());
+        assert!(driver_a.router().lookup_server(&known_server.public_key).await?.is_some());
+
+        // Server doesn't index itself
+        assert_eq!(driver_a.router().servers().await?.len(), 2);
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect_bootstrap(&[
+                Address::Https { address: String::from("127.0.0.1:48507") },
+                Address::Raw(String::from("127.0.0.1:1")),
+                Address::Raw(String::from("127.0.0.1:48508"))
+            ]).await?;
+
+        assert_eq!(client.connected_server().public_key, server_b_public);
+
+        assert!(ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random()).connect_bootstrap(&[]).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stats() -> Result<(), Box<dyn std::error::Error>> {
+        let public_server = get_server("stats-test-public", 48509, |_| ()).await?;
+
+        let private_server = get_server("stats-test-private", 48510, |params| {
+            params.stats_privacy = StatsPrivacy::PRIVATE;
+        }).await?;
+
+        let public_driver = public_server.driver();
+
+        public_driver.router().index_server(ServerApiRecord::new(SecretKey::random().public_key(), "example.org")).await?;
+
+        serve(public_server).await;
+        serve(private_server).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+
+        client.connect("127.0.0.1:48509").await?;
+
+        let stats = client.get_stats("127.0.0.1:48509").await?;
+
+        assert_eq!(stats.public_key, public_driver.params().secret_key.public_key());
+        assert_eq!(stats.local_clients, Some(1));
+        assert_eq!(stats.servers, Some(1));
+        assert_eq!(stats.inbox, Some(public_driver.inbox_stats().await?));
+
+        // Sensitive statistics are hidden
+        let stats = client.get_stats("127.0.0.1:48510").await?;
+
+        assert_eq!(stats.local_clients, None);
+        assert_eq!(stats.servers, None);
+        assert_eq!(stats.inbox, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn heartbeat() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("heartbeat-test", 48511, |_| ()).await?;
+        let driver = server.driver();
+
+        serve(server).await;
+
+        let client_driver = ClientDriver::random();
+        let client_public = client_driver.secret_key().public_key();
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), client_driver)
+            .connect("127.0.0.1:48511").await?;
+
+        client.heartbeat().await?;
+
+        // Heartbeats don't reconnect the client
+        driver.router().disconnect(&client_public).await?;
+
+        assert!(matches!(
+            client.heartbeat().await,
+            Err(MiddlewareError::RequestFailed { status: ResponseStatus::ClientNotConnected, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_batch() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("send-batch-test", 48512, |params| {
+            params.max_message_size = 16;
+            params.max_batch_size = 3;
+        }).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48512").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48512").await?;
+
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        let message = |content: &str| (
+            receiver_public.clone(),
+            ChannelName::new("channel").unwrap(),
+            Message::new(content, "sign", MessageEncoding::default())
+        );
+
+        let results = sender.send_batch("http://127.0.0.1:48512", vec![
+            message("first"),
+            message("too large message"),
+            message("second")
+        ]).await?;
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_sent());
+        assert!(matches!(results[1], SendBatchResult::Rejected { status: ResponseStatus::MessageTooLarge, .. }));
+        assert!(results[2].is_sent());
+
+        // Whole batch is rejected if it's too large
+        let result = sender.send_batch("http://127.0.0.1:48512", vec![
+            message("a"),
+            message("b"),
+            message("c"),
+            message("d")
+        ]).await;
+
+        assert!(matches!(result, Err(MiddlewareError::RequestFailed { status: ResponseStatus::BatchTooLarge, .. })));
+
+        let (messages, 0) = receiver.poll("channel", None).await? else {
+            panic!("Poll failed");
+        };
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].message.content, "first");
+        assert_eq!(messages[1].message.content, "second");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lookup_batch() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("lookup-batch-test", 48513, |params| params.max_lookup_batch_size = 3).await?).await;
+
+        let alice = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48513").await?;
+
+        let alice_public = alice.driver().secret_key().public_key();
+        let unknown_public = SecretKey::random().public_key();
+
+        let results = alice.lookup_batch("127.0.0.1:48513", vec![
+            (alice_public.clone(), None),
+            (unknown_public.clone(), None),
+            (alice_public.clone(), None)
+        ]).await?;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1], LookupBatchResult::NotFound);
+        assert_eq!(results[0], results[2]);
+
+        let LookupBatchResult::Found(LookupResponseBody::Local { client, .. }) = &results[0] else {
+            panic!("Local client wasn't found");
+        };
+
+        assert_eq!(client.public_key, alice_public);
+
+        // Whole batch is rejected if it's too large
+        let result = alice.lookup_batch("127.0.0.1:48513", vec![(unknown_public, None); 4]).await;
+
+        assert!(matches!(result, Err(MiddlewareError::RequestFailed { status: ResponseStatus::BatchTooLarge, .. })));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn replay_protection() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("replay-protection-test", 48514, |params| {
+            params.replay_protection = Some(ReplayProtection {
+                legacy_requests: LegacyRequests::Reject,
+                ..ReplayProtection::default()
+            });
+        }).await?).await;
+
+        let client_driver = ClientDriver::random();
+        let client_secret = client_driver.secret_key().clone();
+
+        ClientMiddleware::new(ReqwestHttpClient::default(), client_driver)
+            .connect("127.0.0.1:48514").await?;
+
+        let http = ReqwestHttpClient::default();
+
+        let heartbeat = |request: HeartbeatRequest| http.post_request::<_, HeartbeatResponse>("http://127.0.0.1:48514/api/v1/heartbeat", request);
+
+        let request = HeartbeatRequest::new(&client_secret);
+
+        let response = heartbeat(request.clone()).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Success { .. }));
+
+        // The same request can't be sent twice
+        let response = heartbeat(request).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::RequestExpired, .. }));
+
+        // Requests without timestamps are rejected
+        let request = HeartbeatRequest(Request::legacy(&client_secret, HeartbeatRequestBody::new()));
+
+        let response = heartbeat(request).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::RequestExpired, .. }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn certificate_expiry() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("certificate-expiry-test", 48515, |params| {
+            params.certificate_lifetime = Some(Duration::from_secs(1));
+        }).await?).await;
+
+        let info = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .get_info("127.0.0.1:48515").await?;
+
+        assert_eq!(info.certificate_lifetime, Some(1));
+
+        let mut client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48515").await?;
+
+        let certificate = client.connection_certificate().clone();
+        let client_public = client.driver().secret_key().public_key();
+
+        assert_eq!(certificate.expires_at, Some(certificate.token.auth_date + 1));
+
+        let message = Message::new("content", "sign", MessageEncoding::default());
+
+        client.send("http://127.0.0.1:48515", client_public.clone(), "chat", message).await?;
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        // Expired certificates are rejected
+        let response = client.http_client_ref().post_request::<LookupRequest, LookupResponse>(
+            "http://127.0.0.1:48515/api/v1/lookup",
+            LookupRequest::new(client.driver_ref().secret_key(), client_public.clone(), None)
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::CertificateExpired, .. }));
+        assert_eq!(response.0.error_code(), Some(&ErrorCode::CertificateExpired));
+
+        // Renewed certificate keeps the queued messages
+        client.renew().await?;
+
+        assert!(client.connection_certificate().token.auth_date > certificate.token.auth_date);
+        assert!(client.lookup(client_public, None).await?.is_some());
+
+        let (messages, _) = client.poll("chat", None).await?;
+
+        assert_eq!(messages.len(), 1);
+
+        // Expired certificates can't replace the renewed one
+        let request = ConnectRequest(Request::new(
+            client.driver_ref().secret_key(),
+            ConnectRequestBody::from_certificate(ClientInfo::thin(), certificate)
+        ));
+
+        let response = client.http_client_ref().post_request::<ConnectRequest, ConnectResponse>(
+            "http://127.0.0.1:48515/api/v1/connect",
+            request
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { .. }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn interceptors() -> Result<(), Box<dyn std::error::Error>> {
+        use std::ops::ControlFlow;
+        use std::net::SocketAddr;
+
+        use serde_json::Value as Json;
+
+        use crate::http::HttpClient;
+
+        /// Deny announces and mark info responses.
+        struct DenyAnnounce;
+
+        #[async_trait::async_trait]
+        impl Interceptor for DenyAnnounce {
+            async fn before_request(&self, route: &str, _client_address: SocketAddr, _request: &Json) -> ControlFlow<Response<Json>> {
+                if route == "/api/v1/announce" {
+                    return ControlFlow::Break(Response::error(ResponseStatus::Forbidden, ErrorCode::Forbidden, "Announces are disabled"));
+                }
+
+                ControlFlow::Continue(())
+            }
+
+            async fn after_response(&self, route: &str, response: &mut Json) {
+                if route == "/api/v1/info" {
+                    response["intercepted"] = Json::Bool(true);
+                }
+            }
+        }
+
+        let counter = RequestCounter::default();
+
+        let server = get_server("interceptors-test", 48516, |_| ()).await?
+            .with_interceptor(counter.clone())
+            .with_interceptor(DenyAnnounce);
+
+        assert_eq!(server.interceptors().len(), 2);
+
+        serve(server).await;
+
+        // Pass-through requests
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48516").await?;
+
+        let info = client.http_client_ref()
+            .get_request::<Json>("http://127.0.0.1:48516/api/v1/info").await
+            .map_err(MiddlewareError::from)?;
+
+        assert_eq!(info["intercepted"], Json::Bool(true));
+        assert!(InfoResponse::from_json(&info).is_ok());
+
+        // Short-circuited requests
+        let result = client.announce("http://127.0.0.1:48516").await;
+
+        assert!(matches!(result, Err(MiddlewareError::RequestFailed { status: ResponseStatus::Forbidden, code: ErrorCode::Forbidden, .. })));
+
+        // Requests are counted before being short-circuited
+        assert_eq!(counter.get("127.0.0.1".parse()?), counter.total());
+        assert!(counter.total() >= 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rate_limits() -> Result<(), Box<dyn std::error::Error>> {
+        use serde_json::Value as Json;
+
+        use crate::http::HttpClient;
+
+        let rate_limiter = RateLimiter::new(RateLimits {
+            per_key: RateLimit::new(3, Duration::from_secs(60)),
+            per_ip: RateLimit::new(2, Duration::from_secs(60)),
+            ..RateLimits::default()
+        }.with_route("/api/v1/announce", RateLimit::new(1, Duration::from_secs(60))));
+
+        let driver = get_driver("rate-limits-test", 48517, |_| ()).await?
+            .with_rate_limiter(rate_limiter.clone());
+
+        serve(Server::new(ReqwestHttpClient::default(), AxumHttpServer::default(), driver).await).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48517").await?;
+
+        // Signed requests are limited by the sender's key
+        client.heartbeat().await?;
+        client.heartbeat().await?;
+
+        assert!(matches!(
+            client.heartbeat().await,
+            Err(MiddlewareError::RequestFailed { status: ResponseStatus::TooManyRequests, code: ErrorCode::RateLimited, .. })
+        ));
+
+        // Overridden routes have their own buckets
+        client.announce("http://127.0.0.1:48517").await?;
+
+        assert!(matches!(
+            client.announce("http://127.0.0.1:48517").await,
+            Err(MiddlewareError::RequestFailed { status: ResponseStatus::TooManyRequests, .. })
+        ));
+
+        // Unsigned requests are limited by the IP address
+        let stats = client.http_client_ref()
+            .get_request::<Json>("http://127.0.0.1:48517/api/v1/stats").await
+            .map_err(MiddlewareError::from)?;
+
+        assert!(StatsResponse::from_json(&stats).is_ok());
+
+        let stats = client.http_client_ref()
+            .get_request::<Json>("http://127.0.0.1:48517/api/v1/stats").await
+            .map_err(MiddlewareError::from)?;
+
+        assert_eq!(stats["status"], Json::from(ResponseStatus::TooManyRequests.to_code()));
+
+        // Other clients are not affected
+        ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect_to("127.0.0.1:48517", client.connected_server().public_key.clone()).await?;
+
+        assert_eq!(rate_limiter.len(), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn capabilities() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("capabilities-batch-test", 48518, |params| {
+            params.max_message_size = 16;
+            params.max_batch_size = 2;
+            params.certificate_lifetime = Some(Duration::from_secs(3600));
+        }).await?).await;
+
+        serve(get_server("capabilities-legacy-test", 48519, |params| {
+            params.max_batch_size = 0;
+        }).await?).await;
+
+        let capabilities = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .get_capabilities("127.0.0.1:48518").await?;
+
+        assert!(capabilities.send_batch());
+        assert!(capabilities.lookup_batch());
+        assert!(!capabilities.ack_poll());
+        assert_eq!(capabilities.max_message_size(), Some(16));
+        assert_eq!(capabilities.max_batch_size(), Some(2));
+        assert_eq!(capabilities.certificate_lifetime(), Some(3600));
+
+        for port in [48518, 48519] {
+            let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+                .connect(format!("127.0.0.1:{port}")).await?;
+
+            let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+                .connect(format!("127.0.0.1:{port}")).await?;
+
+            let receiver_public = receiver.driver().secret_key().public_key();
+
+            let message = |content: &str| (
+                receiver_public.clone(),
+                ChannelName::new("channel").unwrap(),
+                Message::new(content, "sign", MessageEncoding::default())
+            );
+
+            // Batches are split by the advertised limit,
+            // or replaced by sequential sends
+            let results = sender.send_many(format!("http://127.0.0.1:{port}"), vec![
+                message("first"),
+                message("second"),
+                message("third"),
+                message("fourth"),
+                message("fifth")
+            ]).await?;
+
+            assert_eq!(results.len(), 5);
+            assert!(results.iter().all(SendBatchResult::is_sent));
+
+            let (messages, 0) = receiver.poll("channel", None).await? else {
+                panic!("Poll failed");
+            };
+
+            assert_eq!(messages.len(), 5);
+            assert_eq!(messages[4].message.content, "fifth");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delivery_receipts() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("delivery-receipts-test", 48524, |_| ()).await?;
+        let server_public = server.driver().params().secret_key.public_key();
+
+        serve(server).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48524").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48524").await?;
+
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        let message = Message::create(
+            sender.driver().secret_key(),
+            &receiver_public,
+            b"Hello, World!",
+            MessageEncoding::default(),
+            CompressionLevel::default()
+        )?;
+
+        let id = sender.send_with_receipt("http://127.0.0.1:48524", receiver_public.clone(), "channel", message.clone()).await?;
+
+        // Messages without the flag are not reported
+        sender.send("http://127.0.0.1:48524", receiver_public.clone(), "channel", message).await?;
+
+        let (receipts, 0) = sender.poll_receipts(None).await? else {
+            panic!("Failed to poll delivery receipts");
+        };
+
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].status, DeliveryStatus::Delivered);
+        assert_eq!(receipts[0].message_id, id);
+        assert_eq!(receipts[0].receiver, receiver_public);
+        assert_eq!(receipts[0].channel, ChannelName::new("channel").unwrap());
+        assert_eq!(receipts[0].server, server_public);
+
+        let (messages, 0) = receiver.poll("channel", None).await? else {
+            panic!("Failed to poll messages");
+        };
+
+        assert_eq!(messages.len(), 2);
+
+        let (receipts, 0) = sender.poll_receipts(None).await? else {
+            panic!("Failed to poll delivery receipts");
+        };
+
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].status, DeliveryStatus::Polled);
+        assert_eq!(receipts[0].message_id, id);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn standard_negotiation() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("standard-negotiation-test", 48525, |_| ()).await?).await;
+
+        let middleware = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+
+        let info = middleware.get_info("127.0.0.1:48525").await?;
+
+        assert_eq!(info.supported_versions(), ProtocolVersions::SUPPORTED);
+
+        let client = middleware.connect("127.0.0.1:48525").await?;
+
+        assert_eq!(client.standard(), ProtocolVersion::CURRENT.0);
+
+        // Requests are made by the negotiated standard
+        let (messages, 0) = client.poll("channel", None).await? else {
+            panic!("Poll failed");
+        };
+
+        assert!(messages.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unsigned_bodies() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("unsigned-bodies-test", 48526, |params| {
+            params.unsigned_bodies = UnsignedBodies::Reject;
+        }).await?).await;
+
+        let client_driver = ClientDriver::random();
+        let client_secret = client_driver.secret_key().clone();
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), client_driver)
+            .connect("127.0.0.1:48526").await?;
+
+        let http = ReqwestHttpClient::default();
+
+        let heartbeat = |request: HeartbeatRequest| http.post_request::<_, HeartbeatResponse>("http://127.0.0.1:48526/api/v1/heartbeat", request);
+
+        let response = heartbeat(HeartbeatRequest::new(&client_secret)).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Success { .. }));
+
+        // Requests of the old clients are rejected
+        let mut request = HeartbeatRequest::new(&client_secret);
+
+        request.0.body_sign = None;
+
+        let response = heartbeat(request).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::RequestValidationFailed, .. }));
+
+        // Stripped body signature doesn't let the body be changed
+        let sender = Sender::new(client.get_client(), client.connected_server().clone());
+
+        let tampered = || {
+            let mut request = SendRequest::new(
+                &client_secret,
+                sender.clone(),
+                SecretKey::random().public_key(),
+                ChannelName::new("channel").unwrap(),
+                Message::new("content", "sign", MessageEncoding::default())
+            );
+
+            request.0.body_sign = None;
+            request.0.request.receiver_public = SecretKey::random().public_key();
+
+            request
+        };
+
+        let response = http.post_request::<_, SendResponse>("http://127.0.0.1:48526/api/v1/send", tampered()).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::RequestValidationFailed, .. }));
+
+        // Unsigned bodies are rejected by default
+        assert_eq!(ServerParams::default().unsigned_bodies, UnsignedBodies::Reject);
+
+        // Tampered requests are accepted during the migration
+        serve(get_server("unsigned-bodies-accept-test", 48547, |params| {
+            params.unsigned_bodies = UnsignedBodies::Accept;
+        }).await?).await;
+
+        let response = http.post_request::<_, SendResponse>("http://127.0.0.1:48547/api/v1/send", tampered()).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Success { .. }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn body_limits() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("body-limits-test", 48527, |params| {
+            params.body_limits = BodyLimits::new(64 * 1024)
+                .with_route("/api/v1/send", 4096);
+        }).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48527").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48527").await?;
+
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        let request = |content: String| SendRequest(Request::new(
+            sender.driver().secret_key(),
+            SendRequestBody::new(
+                Sender::new(sender.get_client(), sender.connected_server().clone()),
+                receiver_public.clone(),
+                ChannelName::new("channel").unwrap(),
+                Message::new(content, "sign", MessageEncoding::default())
+            )
+        ));
+
+        let body_size = |request: &SendRequest| request.to_json().map(|json| json.to_string().len());
+
+        // Proof seeds and signatures differ in length,
+        // so the bodies are a few bytes off the limit
+        let overhead = body_size(&request(String::new()))?;
+
+        let content = "a".repeat(4096 - overhead + 16);
+
+        let http = ReqwestHttpClient::default();
+
+        let response = http.post_raw(
+            "http://127.0.0.1:48527/api/v1/send",
+            request(content).to_json()?.to_string().into_bytes(),
+            vec![]
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert_eq!(response.status, 413);
+
+        let response = Response::<()>::from_json(&response.body.unwrap())?;
+
+        assert!(matches!(response, Response::Error { status: ResponseStatus::RequestBodyTooLarge, code: ErrorCode::QuotaExceeded, .. }));
+
+        // Bodies within the limit are accepted
+        let content = "a".repeat(4096 - overhead - 16);
+
+        let response = http.post_request::<_, SendResponse>("http://127.0.0.1:48527/api/v1/send", request(content))
+            .await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Success { .. }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn access_log() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Default, Clone)]
+        struct CollectingLog(Arc<std::sync::Mutex<Vec<AccessLogEntry>>>);
+
+        #[async_trait::async_trait]
+        impl AccessLog for CollectingLog {
+            async fn record(&self, entry: AccessLogEntry) {
+                self.0.lock().unwrap().push(entry);
+            }
+        }
+
+        let log = CollectingLog::default();
+
+        let server = get_server("access-log-test", 48528, |_| ()).await?
+            .with_access_log(log.clone());
+
+        assert!(server.access_log().is_enabled());
+
+        serve(server).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48528").await?;
+
+        let public_key = client.driver().secret_key().public_key();
+
+        let entries = log.0.lock().unwrap().clone();
+
+        let info = entries.iter()
+            .find(|entry| entry.route == "/api/v1/info")
+            .expect("Info request must be recorded");
+
+        assert_eq!(info.status, None);
+        assert_eq!(info.public_key, None);
+
+        let connect = entries.iter()
+            .find(|entry| entry.route == "/api/v1/connect")
+            .expect("Connect request must be recorded");
+
+        assert_eq!(connect.status, Some(ResponseStatus::Success));
+        assert_eq!(connect.public_key, Some(public_key));
+        assert!(connect.client_address.is_loopback());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sender_certificate() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("sender-certificate-test", 48529, |_| ()).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48529").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48529").await?;
+
+        let http = ReqwestHttpClient::default();
+
+        let send = |sender_record: ClientApiRecord, server: ServerApiRecord| {
+            let request = SendRequest::new(
+                sender.driver_ref().secret_key(),
+                Sender::new(sender_record, server),
+                receiver.driver().secret_key().public_key(),
+                ChannelName::new("channel").unwrap(),
+                Message::new("content", "sign", MessageEncoding::default())
+            );
+
+            http.post_request::<_, SendResponse>("http://127.0.0.1:48529/api/v1/send", request)
+        };
+
+        // Sender impersonating another client
+        let response = send(receiver.get_client(), receiver.connected_server().clone()).await
+            .map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::RequestValidationFailed, .. }));
+
+        // Sender claiming to be connected to another server
+        let forged_server = ServerApiRecord::new(SecretKey::random().public_key(), "127.0.0.1:48530");
+
+        let response = send(sender.get_client(), forged_server).await
+            .map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::RequestValidationFailed, .. }));
+
+        // Sender with the certificate of its server
+        let response = send(sender.get_client(), sender.connected_server().clone()).await
+            .map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Success { .. }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_to() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("send-to-test", 48531, |_| ()).await?;
+
+        let server_public = server.driver().params().secret_key.public_key();
+
+        serve(server).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48531").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48531").await?;
+
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        let result = sender.send_to(receiver_public.clone(), "channel", b"Hello, World!", MessageEncoding::default()).await?;
+
+        assert_eq!(result.receiver.public_key, receiver_public);
+        assert_eq!(result.server.public_key, server_public);
+        assert!(result.available);
+
+        let (messages, _) = receiver.poll("channel", None).await?;
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].message.read(receiver.driver_ref().secret_key(), &sender.driver().secret_key().public_key())?, b"Hello, World!");
+
+        // Unknown receivers and server addresses are rejected
+        let result = sender.send_to(SecretKey::random().public_key(), "channel", b"", MessageEncoding::default()).await;
+
+        assert!(matches!(result, Err(SendToError::ReceiverNotFound(_))));
+
+        let result = sender.send_to(Address::Raw(String::from("127.0.0.1:48531")), "channel", b"", MessageEncoding::default()).await;
+
+        assert!(matches!(result, Err(SendToError::InvalidTarget(_))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn retry_policy() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("retry-policy-test", 48532, |_| ()).await?;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+
+        // Server isn't started yet
+        assert!(client.connect("127.0.0.1:48532").await.is_err());
+
+        // Start the server while the client retries
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+
+            serve(server).await;
+        });
+
+        let policy = RetryPolicy::new(10)
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(200));
+
+        let client = client.with_retry_policy(policy)
+            .connect("127.0.0.1:48532").await?;
+
+        // Policy is inherited by the connected client
+        assert_eq!(client.retry_policy().max_attempts, 10);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn auto_reconnect() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("auto-reconnect-test", 48533, |_| ()).await?;
+        let driver = server.driver();
+
+        serve(server).await;
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let auto_reconnect = AutoReconnect::new(1).with_callback({
+            let events = events.clone();
+
+            move |event| events.lock().unwrap().push(event)
+        });
+
+        let client_driver = ClientDriver::random();
+        let client_public = client_driver.secret_key().public_key();
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), client_driver)
+            .with_auto_reconnect(auto_reconnect)
+            .connect("127.0.0.1:48533").await?;
+
+        // Server forgets the client
+        driver.router().disconnect(&client_public).await?;
+
+        client.heartbeat().await?;
+
+        assert_eq!(events.lock().unwrap().as_slice(), &[ReconnectEvent::Reconnecting, ReconnectEvent::Reconnected]);
+        assert!(driver.router().lookup_local_client(&client_public, None).await?.is_some());
+
+        // Disabled reconnection surfaces the error
+        driver.router().disconnect(&client_public).await?;
+
+        let client = client.with_auto_reconnect(AutoReconnect::disabled());
+
+        assert!(matches!(
+            client.heartbeat().await,
+            Err(MiddlewareError::RequestFailed { status: ResponseStatus::ClientNotConnected, .. })
+        ));
+
+        assert_eq!(events.lock().unwrap().len(), 2);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "long-poll")]
+    #[tokio::test]
+    async fn long_poll() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Instant;
+
+        serve(get_server("long-poll-test", 48534, |params| {
+            params.max_poll_wait = Some(Duration::from_secs(3));
+        }).await?).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48534").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48534").await?;
+
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        let capabilities = receiver.disconnected().get_capabilities("127.0.0.1:48534").await?;
+
+        assert!(capabilities.long_poll());
+        assert_eq!(capabilities.max_poll_wait(), Some(3000));
+
+        // Waiting time is bounded by the server
+        let started = Instant::now();
+
+        let (messages, _) = receiver.poll_wait("channel", None, Duration::from_secs(60)).await?;
+
+        assert!(messages.is_empty());
+        assert!(started.elapsed() >= Duration::from_secs(3));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // Messages of other channels don't end the wait
+        sender.send_to(receiver_public.clone(), "other", b"Hello, World!", MessageEncoding::default()).await?;
+
+        let (messages, _) = receiver.poll_wait("channel", None, Duration::from_millis(300)).await?;
+
+        assert!(messages.is_empty());
+
+        // Polls respond as soon as a message arrives
+        let started = Instant::now();
+
+        let (messages, _) = tokio::join!(
+            receiver.poll_wait("channel", None, Duration::from_secs(10)),
+            async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+
+                sender.send_to(receiver_public.clone(), "channel", b"Hello, World!", MessageEncoding::default()).await
+            }
+        );
+
+        let (messages, _) = messages?;
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].channel, ChannelName::new("channel").unwrap());
+        assert!(started.elapsed() < Duration::from_millis(2500));
+
+        // Timed out requests are reported as empty polls
+        let http_client = ReqwestHttpClient::new(reqwest::Client::builder()
+            .timeout(Duration::from_secs(1))
+            .build()?);
+
+        let impatient = ClientMiddleware::new(http_client, ClientDriver::random())
+            .connect("127.0.0.1:48534").await?;
+
+        let (messages, remaining) = impatient.poll_wait("channel", None, Duration::from_secs(10)).await?;
+
+        assert!(messages.is_empty());
+        assert_eq!(remaining, 0);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "subscribe")]
+    #[tokio::test]
+    async fn subscribe() -> Result<(), Box<dyn std::error::Error>> {
+        use tokio::sync::{mpsc, Semaphore};
+
+        use crate::crypto::compression::CompressionLevel;
+
+        let server = get_server("subscribe-test", 48535, |_| ()).await?;
+
+        let driver = server.driver();
+
+        serve(server).await;
+
+        let sender = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48535").await?;
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48535").await?;
+
+        let receiver_public = receiver.driver().secret_key().public_key();
+        let sender_public = sender.driver().secret_key().public_key();
+
+        let (messages, mut received) = mpsc::unbounded_channel();
+
+        // Handler waits for a permit for every message
+        let permits = Arc::new(Semaphore::new(0));
+
+        let params = SubscriptionParams::default()
+            .with_limit(Some(1))
+            .with_interval(Duration::from_millis(100));
+
+        let mut subscription = receiver.subscribe(ChannelName::new("channel").unwrap(), params, {
+            let permits = permits.clone();
+
+            move |message| {
+                let permits = permits.clone();
+                let messages = messages.clone();
+
+                async move {
+                    permits.acquire().await.unwrap().forget();
+
+                    messages.send(message).unwrap();
+                }
+            }
+        });
+
+        // Messages of other channels are ignored
+        let _other = receiver.subscribe(ChannelName::new("other").unwrap(), params, |_| async {});
+
+        for content in [b"first", b"other"] {
+            sender.send_to(receiver_public.clone(), "channel", content, MessageEncoding::default()).await?;
+        }
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        // Slow handler leaves messages in the inbox
+        let channels = driver.messages_inbox().list_channels(receiver_public.clone()).await?;
+
+        assert_eq!(channels, vec![(ChannelName::new("channel").unwrap(), 1)]);
+
+        permits.add_permits(2);
+
+        for content in [b"first", b"other"] {
+            let message = tokio::time::timeout(Duration::from_secs(5), received.recv()).await?.unwrap();
+
+            assert_eq!(message.content, content);
+            assert_eq!(message.channel, ChannelName::new("channel").unwrap());
+            assert_eq!(message.sender.client.public_key, sender_public);
+        }
+
+        // Messages with invalid signatures are reported
+        let message = Message::create(
+            &SecretKey::random(),
+            &receiver_public,
+            b"Hello, World!",
+            MessageEncoding::default(),
+            CompressionLevel::default()
+        )?;
+
+        sender.send("http://127.0.0.1:48535", receiver_public.clone(), "channel", message).await?;
+
+        let error = tokio::time::timeout(Duration::from_secs(5), subscription.next_error()).await?;
+
+        assert!(matches!(error, Some(SubscriptionError::ReadFailed { .. })));
+
+        // Cancelled subscriptions stop polling
+        subscription.cancel().await;
+
+        sender.send_to(receiver_public.clone(), "channel", b"last", MessageEncoding::default()).await?;
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let channels = driver.messages_inbox().list_channels(receiver_public).await?;
+
+        assert_eq!(channels, vec![(ChannelName::new("channel").unwrap(), 1)]);
+        assert!(received.try_recv().is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "timeouts")]
+    #[tokio::test]
+    async fn timeouts() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Instant;
+
+        // Server accepting connections and never responding
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:48536").await?;
+
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let timeouts = Timeouts::new(Duration::from_millis(300))
+            .with_route("/api/v1/info", Duration::from_millis(100));
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .with_timeouts(timeouts);
+
+        let started = Instant::now();
+
+        let result = client.connect_to("127.0.0.1:48536", SecretKey::random().public_key()).await;
+
+        assert!(matches!(result, Err(MiddlewareError::Timeout { route, .. }) if route == "/api/v1/connect"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let result = client.get_info("127.0.0.1:48536").await;
+
+        assert!(matches!(result, Err(MiddlewareError::Timeout { route, elapsed }) if route == "/api/v1/info" && elapsed < Duration::from_millis(300)));
+
+        // Long polls are not killed while the server waits
+        #[cfg(feature = "long-poll")]
+        {
+            serve(get_server("timeouts-test", 48537, |_| ()).await?).await;
+
+            let client = client.connect("127.0.0.1:48537").await?;
+
+            let (messages, _) = client.poll_wait("channel", None, Duration::from_secs(1)).await?;
+
+            assert!(messages.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failover() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::Mutex;
+
+        let server_a = get_server("failover-test-a", 48538, |_| ()).await?;
+        let server_b = get_server("failover-test-b", 48539, |_| ()).await?;
+
+        let server_a_public = server_a.driver().params().secret_key.public_key();
+        let server_b_public = server_b.driver().params().secret_key.public_key();
+
+        let server_a = tokio::spawn(async move {
+            let _ = server_a.serve("127.0.0.1:48538").await;
+        });
+
+        serve(server_b).await;
+
+        // Don't reuse connections to the stopped server
+        let http_client = ReqwestHttpClient::new(reqwest::Client::builder()
+            .pool_max_idle_per_host(0)
+            .build()?);
+
+        let servers = [
+            Address::Raw(String::from("127.0.0.1:48540")),
+            crate::address::parse("http://127.0.0.1:48538")?,
+            crate::address::parse("http://127.0.0.1:48539")?
+        ];
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        // Unreachable servers are skipped
+        let client = ClientMiddleware::new(http_client, ClientDriver::random())
+            .connect_failover(&servers).await?
+            .with_callback({
+                let events = events.clone();
+
+                move |event| events.lock().unwrap().push(event)
+            });
+
+        let client_public = client.connected().driver().secret_key().public_key();
+
+        assert_eq!(client.servers().len(), 3);
+        assert_eq!(client.active_server().public_key, server_a_public);
+
+        client.heartbeat().await?;
+
+        // Calls are replayed by the next server
+        server_a.abort();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let (messages, _) = client.poll("channel", None).await?;
+
+        assert!(messages.is_empty());
+        assert_eq!(client.active_server().public_key, server_b_public);
+
+        let events = events.lock().unwrap().clone();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].previous.public_key, server_a_public);
+        assert_eq!(events[0].current.public_key, server_b_public);
+
+        // Certificate is issued for the new server
+        assert!(client.connected().connection_certificate().validate(&client_public, &server_b_public)?);
+        assert!(client.lookup(client_public, None).await?.is_some());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "session")]
+    #[tokio::test]
+    async fn session() -> Result<(), Box<dyn std::error::Error>> {
+        let server = get_server("session-test", 48541, |_| ()).await?;
+        let driver = server.driver();
+
+        serve(server).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+        let client_secret = client.driver().secret_key().clone();
+        let client_public = client_secret.public_key();
+
+        let session = client.connect_session("127.0.0.1:48541").await?;
+
+        assert_eq!(session.capabilities(), &client.get_capabilities("127.0.0.1:48541").await?);
+        assert_eq!(session.connected_server().public_key, driver.params().secret_key.public_key());
+
+        let message = Message::create(
+            &client_secret,
+            &client_public,
+            b"Hello, World!",
+            MessageEncoding::default(),
+            CompressionLevel::default()
+        )?;
+
+        session.send("http://127.0.0.1:48541", client_public.clone(), "channel", message).await?;
+
+        let (messages, _) = session.poll("channel", None).await?;
+
+        assert_eq!(messages.len(), 1);
+        assert!(session.lookup(client_public.clone(), None).await?.is_some());
+
+        // Dropped session is disconnected in background
+        drop(session);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert!(driver.router().lookup_local_client(&client_public, None).await?.is_none());
+
+        // Disconnection can be disabled
+        let session = client.connect_session("127.0.0.1:48541").await?
+            .with_disconnect_on_drop(false);
+
+        drop(session);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert!(driver.router().lookup_local_client(&client_public, None).await?.is_some());
+
+        client.connect_session("127.0.0.1:48541").await?
+            .close().await?;
+
+        assert!(driver.router().lookup_local_client(&client_public, None).await?.is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "outbox")]
+    #[tokio::test]
+    async fn outbox() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::Mutex;
+
+        let server = get_server("outbox-test", 48542, |_| ()).await?;
+        let restarted = server.clone();
+
+        let server = tokio::spawn(async move {
+            let _ = server.serve("127.0.0.1:48542").await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let outbox = Outbox::memory()
+            .with_params(OutboxParams::default().with_retry_policy(RetryPolicy::none()
+                .with_base_delay(Duration::from_millis(50))
+                .with_max_delay(Duration::from_millis(200))
+                .with_jitter(Duration::ZERO)))
+            .with_callback({
+                let events = events.clone();
+
+                move |event| events.lock().unwrap().push(event)
+            });
+
+        // Don't reuse connections to the stopped server
+        let http_client = ReqwestHttpClient::new(reqwest::Client::builder()
+            .pool_max_idle_per_host(0)
+            .build()?);
+
+        let sender = ClientMiddleware::new(http_client, ClientDriver::random())
+            .with_retry_policy(RetryPolicy::none())
+            .connect("127.0.0.1:48542").await?
+            .with_outbox(outbox.clone());
+
+        let receiver = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .connect("127.0.0.1:48542").await?;
+
+        let sender_public = sender.driver().secret_key().public_key();
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        sender.send_to(receiver_public.clone(), "channel", b"first", MessageEncoding::default()).await?;
+
+        // Messages are queued while the server is down
+        server.abort();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        for payload in ["second", "third"] {
+            let Err(SendToError::Queued(_)) = sender.send_to(receiver_public.clone(), "channel", payload, MessageEncoding::default()).await else {
+                panic!("Message wasn't queued");
+            };
+        }
+
+        assert_eq!(outbox.len().await?, 2);
+        assert!(outbox.entries().await?.iter().all(|entry| entry.receiver == receiver_public && entry.channel.as_str() == "channel"));
+
+        let flush = outbox.flush(&sender).await?;
+
+        assert_eq!(flush.delivered, 0);
+        assert_eq!(flush.remaining, 2);
+
+        // And flushed in order when it's back
+        serve(restarted).await;
+
+        let _flusher = outbox.spawn_flusher(sender.clone());
+
+        for _ in 0..50 {
+            if outbox.is_empty().await? {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        assert!(outbox.is_empty().await?);
+
+        let (messages, _) = receiver.poll("channel", None).await?;
+
+        let messages = messages.into_iter()
+            .map(|info| info.message.read(receiver.driver_ref().secret_key(), &sender_public))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(messages, [b"first".to_vec(), b"second".to_vec(), b"third".to_vec()]);
+
+        let events = events.lock().unwrap().clone();
+
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[0], OutboxEvent::Queued { receiver, .. } if receiver == &receiver_public));
+        assert!(matches!(&events[3], OutboxEvent::Delivered { result, .. } if result.receiver.public_key == receiver_public));
+
+        // Old messages are dropped
+        let outbox = Outbox::memory()
+            .with_params(OutboxParams::default().with_max_age(Some(Duration::ZERO)));
+
+        let sender_record = Sender::new(sender.get_client(), sender.connected_server().clone());
+        let message = Message::create(sender.driver_ref().secret_key(), &receiver_public, b"", MessageEncoding::default(), CompressionLevel::default())?;
+
+        outbox.enqueue(sender_record.clone(), receiver_public.clone(), ChannelName::new("channel").unwrap(), message.clone()).await?;
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        let flush = outbox.flush(&sender).await?;
+
+        assert_eq!(flush.expired, 1);
+        assert!(outbox.is_empty().await?);
+
+        // Or purged
+        outbox.enqueue(sender_record, receiver_public.clone(), ChannelName::new("channel").unwrap(), message).await?;
+        outbox.purge(Some(receiver_public), None).await?;
+
+        assert!(outbox.is_empty().await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn connect_work() -> Result<(), Box<dyn std::error::Error>> {
+        serve(get_server("connect-work-test", 48543, |params| {
+            params.connect_difficulty = 8;
+        }).await?).await;
+
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+
+        let info = client.get_info("127.0.0.1:48543").await?;
+
+        assert_eq!(info.connect_difficulty, Some(8));
+        assert_eq!(info.capabilities.and_then(|capabilities| capabilities.connect_difficulty()), Some(8));
+
+        // Requests without solution are rejected
+        let client_secret = client.driver_ref().secret_key().clone();
+
+        let request = ConnectRequest::new(&client_secret, info.public_key.clone(), ClientInfo::thin());
+
+        let response = client.http_client_ref().post_request::<ConnectRequest, ConnectResponse>(
+            "http://127.0.0.1:48543/api/v1/connect",
+            request
+        ).await.map_err(MiddlewareError::from)?;
+
+        assert!(matches!(response.0, Response::Error { status: ResponseStatus::InsufficientWork, .. }));
+
+        // Challenge is solved by the middleware
+        let mut connected = client.connect("127.0.0.1:48543").await?;
+
+        assert!(connected.lookup(client_secret.public_key(), None).await?.is_some());
+
+        // Renewals are not challenged
+        connected.renew().await?;
+
+        // Unexpected challenge is solved as well
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random());
+
+        client.connect_to("127.0.0.1:48543", info.public_key.clone()).await?;
+
+        // Bounded solving
+        let client = ClientMiddleware::new(ReqwestHttpClient::default(), ClientDriver::random())
+            .with_max_work(1);
+
+        let Err(MiddlewareError::WorkNotSolved { difficulty: 8, .. }) = client.connect("127.0.0.1:48543").await else {
+            panic!("Challenge must not be solved");
+        };
 
-        self.http_server.serve(address).await
+        Ok(())
     }
 }
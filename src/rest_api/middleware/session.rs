@@ -0,0 +1,153 @@
+use crate::crypto::asymmetric::PublicKey;
+use crate::http::client::HttpClient;
+
+use crate::rest_api::prelude::{
+    *,
+    Client as ClientApiRecord,
+    Server as ServerApiRecord
+};
+
+use super::{Client, ConnectedClient, Error};
+
+#[derive(Debug)]
+/// Session of the client connected to the server.
+/// 
+/// Unlike `ConnectedClient` the session owns its connection
+/// certificate and is not cloneable. When the session is
+/// dropped the disconnection request is sent in background
+/// so the server doesn't keep the client's record. Use the
+/// `close` method to wait for the disconnection.
+/// 
+/// Refer to `Client::connect_session`.
+pub struct ClientSession<T: HttpClient + 'static> {
+    /// Always `Some` until the session is closed.
+    client: Option<ConnectedClient<T>>,
+    capabilities: ServerCapabilities,
+    disconnect_on_drop: bool
+}
+
+impl<T: HttpClient + 'static> ClientSession<T> {
+    #[inline]
+    /// Make session of the connected client.
+    /// 
+    /// - `capabilities` must contain capabilities
+    ///   of the server the client is connected to.
+    pub fn new(client: ConnectedClient<T>, capabilities: ServerCapabilities) -> Self {
+        Self {
+            client: Some(client),
+            capabilities,
+            disconnect_on_drop: true
+        }
+    }
+
+    #[inline]
+    /// Send disconnection request when the
+    /// session is dropped. Default is `true`.
+    pub fn with_disconnect_on_drop(mut self, disconnect_on_drop: bool) -> Self {
+        self.disconnect_on_drop = disconnect_on_drop;
+
+        self
+    }
+
+    #[inline]
+    pub fn disconnect_on_drop(&self) -> bool {
+        self.disconnect_on_drop
+    }
+
+    #[inline]
+    /// Get client connected to the server.
+    pub fn client(&self) -> &ConnectedClient<T> {
+        self.client.as_ref().expect("Session is closed")
+    }
+
+    #[inline]
+    /// Get features and limits of the server
+    /// negotiated during the connection.
+    pub fn capabilities(&self) -> &ServerCapabilities {
+        &self.capabilities
+    }
+
+    #[inline]
+    pub fn connected_server(&self) -> &ServerApiRecord {
+        self.client().connected_server()
+    }
+
+    #[inline]
+    pub fn connection_certificate(&self) -> ConnectionCertificate {
+        self.client().connection_certificate()
+    }
+
+    #[inline]
+    /// Get protocol standard negotiated with the server.
+    pub fn standard(&self) -> u64 {
+        self.client().standard()
+    }
+
+    /// Lookup client using the session's server.
+    /// 
+    /// Refer to `ConnectedClient::lookup`.
+    pub async fn lookup(&self, client_public: PublicKey, client_type: Option<ClientType>) -> Result<Option<(ClientApiRecord, ServerApiRecord, bool)>, Error> {
+        self.client().lookup(client_public, client_type).await
+    }
+
+    /// Poll messages from the session's inbox.
+    /// 
+    /// Refer to `ConnectedClient::poll`.
+    pub async fn poll(&self, channel: impl ToString, limit: Option<u64>) -> Result<(Vec<MessageInfo>, u64), Error> {
+        self.client().poll(channel, limit).await
+    }
+
+    /// Send message using the session's server.
+    /// 
+    /// Refer to `ConnectedClient::send`.
+    pub async fn send(&self, receiver_server: impl AsRef<str>, receiver_public: PublicKey, channel: impl ToString, message: Message) -> Result<Option<u64>, Error> {
+        self.client().send(receiver_server, receiver_public, channel, message).await
+    }
+
+    /// Disconnect from the server and
+    /// wait for the server's response.
+    /// 
+    /// Refer to `ConnectedClient::disconnect`.
+    pub async fn close(mut self) -> Result<Client<T>, Error> {
+        self.client.take()
+            .expect("Session is closed")
+            .disconnect().await
+    }
+
+    #[inline]
+    /// Keep the client connected to the server
+    /// and return it without closing the session.
+    pub fn into_client(mut self) -> ConnectedClient<T> {
+        self.client.take().expect("Session is closed")
+    }
+}
+
+impl<T: HttpClient + 'static> Drop for ClientSession<T> {
+    fn drop(&mut self) {
+        let Some(client) = self.client.take() else {
+            return;
+        };
+
+        if !self.disconnect_on_drop {
+            return;
+        }
+
+        // Nothing can be sent without the runtime
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(server = client.connected_server().address, "Session dropped outside of the runtime, client is not disconnected");
+
+            return;
+        };
+
+        runtime.spawn(async move {
+            #[cfg(feature = "tracing")]
+            let server = client.connected_server().address.clone();
+
+            if let Err(_err) = client.disconnect().await {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(server, "Failed to disconnect dropped session: {_err}");
+            }
+        });
+    }
+}
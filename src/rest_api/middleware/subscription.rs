@@ -0,0 +1,268 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+use crate::http::client::HttpClient;
+use crate::rest_api::prelude::*;
+
+use super::{ConnectedClient, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Params of the client middleware's subscription.
+pub struct SubscriptionParams {
+    /// Maximal amount of messages polled at once.
+    /// 
+    /// Next messages are polled only when all the
+    /// polled ones are handled. Default is 16.
+    pub limit: Option<u64>,
+
+    /// Delay before the next poll when the previous
+    /// one returned no messages or failed.
+    /// 
+    /// Default is 1 second.
+    pub interval: Duration,
+
+    /// Wait up to the given time for new messages
+    /// using long polls. Default is `None`.
+    /// 
+    /// Refer to `ConnectedClient::poll_wait`.
+    pub wait: Option<Duration>,
+
+    /// Acknowledge handled messages leased by the
+    /// server, so they're polled again only if they
+    /// were not handled. Default is `false`.
+    /// 
+    /// Refer to `ConnectedClient::ack`.
+    pub acknowledge: bool,
+
+    /// Maximal amount of unread errors.
+    /// 
+    /// Newer errors are dropped when the errors
+    /// channel is full. Default is 64.
+    pub errors_capacity: usize
+}
+
+impl SubscriptionParams {
+    #[inline]
+    pub fn with_limit(self, limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_interval(self, interval: Duration) -> Self {
+        Self {
+            interval,
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_wait(self, wait: Duration) -> Self {
+        Self {
+            wait: Some(wait),
+            ..self
+        }
+    }
+
+    #[inline]
+    pub fn with_acknowledge(self, acknowledge: bool) -> Self {
+        Self {
+            acknowledge,
+            ..self
+        }
+    }
+}
+
+impl Default for SubscriptionParams {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            limit: Some(16),
+            interval: Duration::from_secs(1),
+            wait: None,
+            acknowledge: false,
+            errors_capacity: 64
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Message received by the subscription.
+pub struct SubscriptionMessage {
+    /// Sender client and server it is connected to.
+    pub sender: Sender,
+
+    pub channel: ChannelName,
+
+    /// Decrypted and verified content of the message.
+    pub content: Vec<u8>,
+
+    /// UTC timestamp of the message's
+    /// receiving by the server.
+    pub received_at: u64,
+
+    /// Server-assigned id of the message.
+    pub id: Option<u64>
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SubscriptionError {
+    #[error("Failed to poll messages: {0}")]
+    PollFailed(#[source] Error),
+
+    #[error("Failed to read message of {}: {source}", sender.client.public_key.to_base64())]
+    ReadFailed {
+        sender: Box<Sender>,
+        source: MessagesError
+    },
+
+    #[error("Failed to acknowledge messages: {0}")]
+    AckFailed(#[source] Error)
+}
+
+#[derive(Debug)]
+/// Handle of the background subscription task.
+/// 
+/// Task is cancelled when the handle is dropped.
+pub struct Subscription {
+    task: JoinHandle<()>,
+    cancel: watch::Sender<bool>,
+    errors: mpsc::Receiver<SubscriptionError>
+}
+
+impl Subscription {
+    /// Spawn task polling messages of the channel
+    /// and calling the handler with each of them.
+    /// 
+    /// Refer to `ConnectedClient::subscribe`.
+    pub(crate) fn spawn<T, F, Fut>(client: ConnectedClient<T>, channel: ChannelName, params: SubscriptionParams, handler: F) -> Self
+    where
+        T: HttpClient + 'static,
+        F: Fn(SubscriptionMessage) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send
+    {
+        let (cancel, mut cancelled) = watch::channel(false);
+        let (errors_sender, errors) = mpsc::channel(params.errors_capacity.max(1));
+
+        let report = move |error: SubscriptionError| {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Subscription error: {error}");
+
+            // Don't block polling if the errors are not read
+            let _ = errors_sender.try_send(error);
+        };
+
+        let task = tokio::spawn(async move {
+            let channel = channel.to_string();
+
+            while !*cancelled.borrow() {
+                // Cancellation is checked between polls only,
+                // so the polled messages are always handled
+                let result = match params.wait {
+                    Some(wait) => client.poll_wait(&channel, params.limit, wait).await,
+                    None => client.poll(&channel, params.limit).await
+                };
+
+                let messages = match result {
+                    Ok((messages, _)) => messages,
+
+                    Err(err) => {
+                        report(SubscriptionError::PollFailed(err));
+
+                        Vec::new()
+                    }
+                };
+
+                if messages.is_empty() {
+                    tokio::select! {
+                        _ = tokio::time::sleep(params.interval) => (),
+                        _ = cancelled.changed() => ()
+                    }
+
+                    continue;
+                }
+
+                let mut handled = Vec::with_capacity(messages.len());
+
+                for info in messages {
+                    let sender = &info.sender.client.public_key;
+
+                    match info.message.read(client.driver_ref().secret_key(), sender) {
+                        Ok(content) => {
+                            handler(SubscriptionMessage {
+                                sender: info.sender,
+                                channel: info.channel,
+                                content,
+                                received_at: info.received_at,
+                                id: info.id
+                            }).await;
+                        }
+
+                        Err(err) => report(SubscriptionError::ReadFailed {
+                            sender: Box::new(info.sender),
+                            source: err
+                        })
+                    }
+
+                    // Unreadable messages are acknowledged
+                    // as well so they're not polled again
+                    handled.extend(info.id);
+                }
+
+                if params.acknowledge && !handled.is_empty() {
+                    if let Err(err) = client.ack(handled).await {
+                        report(SubscriptionError::AckFailed(err));
+                    }
+                }
+            }
+        });
+
+        Self {
+            task,
+            cancel,
+            errors
+        }
+    }
+
+    /// Stop polling and wait until the
+    /// already polled messages are handled.
+    pub async fn cancel(mut self) {
+        let _ = self.cancel.send(true);
+
+        let _ = (&mut self.task).await;
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    #[inline]
+    /// Wait for the next error of the subscription.
+    /// 
+    /// Return `None` if the task is finished
+    /// and all its errors are read.
+    pub async fn next_error(&mut self) -> Option<SubscriptionError> {
+        self.errors.recv().await
+    }
+
+    #[inline]
+    /// Get the next error of the subscription
+    /// if there's one already.
+    pub fn try_next_error(&mut self) -> Option<SubscriptionError> {
+        self.errors.try_recv().ok()
+    }
+}
+
+impl Drop for Subscription {
+    #[inline]
+    fn drop(&mut self) {
+        // Let the task finish handling polled messages
+        let _ = self.cancel.send(true);
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+
+use super::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Timeouts of the client middleware's requests.
+/// 
+/// Requests which take longer fail with the
+/// `Error::Timeout`. Timeouts of the long polls
+/// are extended by their waiting time.
+pub struct Timeouts {
+    /// Timeout of the requests to the routes
+    /// without override. Not bounded if `None`.
+    pub default: Option<Duration>,
+
+    /// Timeouts of the specific routes,
+    /// e.g. `/api/v1/connect`.
+    pub routes: BTreeMap<String, Duration>
+}
+
+impl Timeouts {
+    #[inline]
+    /// Bound all the requests by the given timeout.
+    pub fn new(default: Duration) -> Self {
+        Self {
+            default: Some(default),
+            routes: BTreeMap::new()
+        }
+    }
+
+    #[inline]
+    /// Don't bound the requests.
+    pub fn none() -> Self {
+        Self {
+            default: None,
+            routes: BTreeMap::new()
+        }
+    }
+
+    #[inline]
+    /// Bound requests to the given route,
+    /// e.g. `/api/v1/connect`, by the given timeout.
+    pub fn with_route(mut self, route: impl ToString, timeout: Duration) -> Self {
+        self.routes.insert(route.to_string(), timeout);
+
+        self
+    }
+
+    #[inline]
+    /// Get timeout of the given route.
+    /// 
+    /// ```rust
+    /// use std::time::Duration;
+    /// 
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let timeouts = Timeouts::new(Duration::from_secs(5))
+    ///     .with_route("/api/v1/connect", Duration::from_secs(1));
+    /// 
+    /// assert_eq!(timeouts.get("/api/v1/connect"), Some(Duration::from_secs(1)));
+    /// assert_eq!(timeouts.get("/api/v1/poll"), Some(Duration::from_secs(5)));
+    /// ```
+    pub fn get(&self, route: &str) -> Option<Duration> {
+        self.routes.get(route).copied().or(self.default)
+    }
+
+    /// Get route of the request's URL.
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// assert_eq!(Timeouts::route("http://127.0.0.1:8001/api/v1/poll"), "/api/v1/poll");
+    /// assert_eq!(Timeouts::route("http://127.0.0.1:8001/api/v1/clients?page=2"), "/api/v1/clients");
+    /// assert_eq!(Timeouts::route("127.0.0.1:8001"), "/");
+    /// ```
+    pub fn route(url: &str) -> &str {
+        let url = url.split_once("://")
+            .map(|(_, url)| url)
+            .unwrap_or(url);
+
+        let path = url.find('/')
+            .map(|start| &url[start..])
+            .unwrap_or("/");
+
+        path.split(['?', '#'])
+            .next()
+            .unwrap_or(path)
+    }
+
+    /// Run the request to the given URL bounded by its
+    /// route's timeout extended by the given waiting time.
+    pub async fn run<T>(&self, url: &str, wait: Duration, request: impl Future<Output = Result<T, Error>>) -> Result<T, Error> {
+        let route = Self::route(url);
+
+        let Some(timeout) = self.get(route) else {
+            return request.await;
+        };
+
+        let started = tokio::time::Instant::now();
+
+        match tokio::time::timeout(timeout.saturating_add(wait), request).await {
+            Ok(result) => result,
+
+            Err(_) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(route, ?timeout, ?wait, "Request timed out");
+
+                Err(Error::Timeout {
+                    route: route.to_string(),
+                    elapsed: started.elapsed()
+                })
+            }
+        }
+    }
+}
+
+impl Default for Timeouts {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn run() {
+        let timeouts = Timeouts::new(Duration::from_secs(1))
+            .with_route("/api/v1/connect", Duration::from_secs(5));
+
+        let sleep = |time| async move {
+            tokio::time::sleep(time).await;
+
+            Ok(())
+        };
+
+        let result = timeouts.run("http://127.0.0.1:8001/api/v1/poll", Duration::ZERO, sleep(Duration::from_secs(2))).await;
+
+        assert!(matches!(result, Err(Error::Timeout { route, .. }) if route == "/api/v1/poll"));
+
+        // Routes have their own timeouts
+        assert!(timeouts.run("http://127.0.0.1:8001/api/v1/connect", Duration::ZERO, sleep(Duration::from_secs(2))).await.is_ok());
+
+        // Waiting time extends the timeout
+        assert!(timeouts.run("http://127.0.0.1:8001/api/v1/poll", Duration::from_secs(3), sleep(Duration::from_secs(2))).await.is_ok());
+
+        assert!(Timeouts::none().run("http://127.0.0.1:8001/api/v1/poll", Duration::ZERO, sleep(Duration::from_secs(60))).await.is_ok());
+    }
+}
@@ -0,0 +1,358 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde_json::{json, Value as Json};
+
+use k256::sha2::{Sha256, Digest};
+
+use crate::time::timestamp;
+
+use crate::crypto::prelude::*;
+use crate::http::client::HttpClient;
+
+use crate::drivers::server::{WebhookEventKind, Webhook, WebhooksParams};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Server event reported by webhooks.
+pub enum WebhookEvent {
+    ClientConnected {
+        client: PublicKey
+    },
+
+    ClientAnnounced {
+        client: PublicKey,
+        server: PublicKey
+    },
+
+    ServerAnnounced {
+        server: PublicKey
+    },
+
+    MessageReceived {
+        sender: PublicKey,
+        receiver: PublicKey
+    }
+}
+
+impl WebhookEvent {
+    pub fn kind(&self) -> WebhookEventKind {
+        match self {
+            Self::ClientConnected { .. } => WebhookEventKind::ClientConnected,
+            Self::ClientAnnounced { .. } => WebhookEventKind::ClientAnnounced,
+            Self::ServerAnnounced { .. } => WebhookEventKind::ServerAnnounced,
+            Self::MessageReceived { .. } => WebhookEventKind::MessageReceived
+        }
+    }
+
+    /// Get named keys involved in the event.
+    pub fn keys(&self) -> Vec<(&'static str, &PublicKey)> {
+        match self {
+            Self::ClientConnected { client } => vec![("client", client)],
+            Self::ClientAnnounced { client, server } => vec![("client", client), ("server", server)],
+            Self::ServerAnnounced { server } => vec![("server", server)],
+            Self::MessageReceived { sender, receiver } => vec![("sender", sender), ("receiver", receiver)]
+        }
+    }
+
+    /// Check if the event should be reported by the webhook.
+    pub fn matches(&self, webhook: &Webhook) -> bool {
+        let filter = &webhook.filter;
+
+        let event_matches = filter.events.is_empty() || filter.events.contains(&self.kind());
+
+        let keys_matches = filter.keys.is_empty() || self.keys()
+            .into_iter()
+            .any(|(_, key)| filter.keys.contains(key));
+
+        event_matches && keys_matches
+    }
+
+    /// Get webhook payload of the event.
+    /// 
+    /// Payload contains only the event type, its time
+    /// and fingerprints of the involved keys.
+    pub fn to_payload(&self, timestamp: u64) -> Json {
+        let mut payload = json!({
+            "event": self.kind().name(),
+            "timestamp": timestamp
+        });
+
+        for (name, key) in self.keys() {
+            payload[name] = Json::String(fingerprint(key));
+        }
+
+        payload
+    }
+}
+
+/// Get short fingerprint of the public key.
+/// 
+/// Fingerprint is a hex encoded first 8 bytes
+/// of the key's SHA256 hash.
+pub fn fingerprint(key: &PublicKey) -> String {
+    Sha256::digest(key.to_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Get value of the `X-Hyperborea-Signature` header
+/// for the given webhook body.
+pub fn webhook_signature(secret: impl AsRef<[u8]>, body: impl AsRef<[u8]>) -> String {
+    let signature = hmac_sha256(secret, body)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+
+    format!("sha256={signature}")
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Webhooks delivery metrics.
+pub struct WebhookStats {
+    /// Amount of queued notifications.
+    pub queued: u64,
+
+    /// Amount of successfully delivered notifications.
+    pub delivered: u64,
+
+    /// Amount of retried delivery attempts.
+    pub retried: u64,
+
+    /// Amount of notifications dropped after all the retries.
+    pub dead_letters: u64
+}
+
+#[derive(Default, Debug)]
+struct Counters {
+    queued: AtomicU64,
+    delivered: AtomicU64,
+    retried: AtomicU64,
+    dead_letters: AtomicU64
+}
+
+#[derive(Debug)]
+/// Background deliverer of the webhook notifications.
+pub(crate) struct WebhookWorker<T> {
+    http_client: T,
+    params: WebhooksParams,
+    counters: Arc<Counters>
+}
+
+impl<T: HttpClient + 'static> WebhookWorker<T> {
+    pub fn new(http_client: T, params: WebhooksParams) -> Self {
+        Self {
+            http_client,
+            params,
+            counters: Arc::new(Counters::default())
+        }
+    }
+
+    pub fn stats(&self) -> WebhookStats {
+        WebhookStats {
+            queued: self.counters.queued.load(Ordering::Relaxed),
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            retried: self.counters.retried.load(Ordering::Relaxed),
+            dead_letters: self.counters.dead_letters.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Send the event to all the matching webhooks in background.
+    /// 
+    /// Return amount of queued notifications.
+    pub fn notify(&self, event: WebhookEvent) -> usize {
+        let hooks = self.params.hooks.iter()
+            .filter(|hook| event.matches(hook))
+            .collect::<Vec<_>>();
+
+        if hooks.is_empty() {
+            return 0;
+        }
+
+        let body = event.to_payload(timestamp()).to_string().into_bytes();
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            event = event.kind().name(),
+            hooks = hooks.len(),
+            "Queueing webhook notifications"
+        );
+
+        for hook in &hooks {
+            self.counters.queued.fetch_add(1, Ordering::Relaxed);
+
+            let http_client = self.http_client.clone();
+            let counters = self.counters.clone();
+
+            let url = hook.url.clone();
+            let body = body.clone();
+            let retries = self.params.retries;
+            let mut backoff = self.params.backoff;
+
+            let headers = vec![
+                (String::from("X-Hyperborea-Event"), event.kind().name().to_string()),
+                (String::from("X-Hyperborea-Signature"), webhook_signature(&hook.secret, &body))
+            ];
+
+            tokio::spawn(async move {
+                for attempt in 0..=retries {
+                    if attempt > 0 {
+                        counters.retried.fetch_add(1, Ordering::Relaxed);
+
+                        tokio::time::sleep(backoff).await;
+
+                        backoff *= 2;
+                    }
+
+                    match http_client.post_raw(&url, body.clone(), headers.clone()).await {
+                        Ok(response) if (200..300).contains(&response.status) => {
+                            counters.delivered.fetch_add(1, Ordering::Relaxed);
+
+                            return;
+                        }
+
+                        Ok(_response) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(url, attempt, status = _response.status, "Webhook delivery failed");
+                        }
+
+                        Err(_err) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(url, attempt, "Webhook delivery failed: {_err}");
+                        }
+                    }
+                }
+
+                counters.dead_letters.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+
+        hooks.len()
+    }
+}
+
+#[cfg(all(test, feature = "client-reqwest", feature = "server-axum"))]
+mod tests {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::body::Bytes;
+
+    use crate::http::ReqwestHttpClient;
+    use crate::drivers::server::WebhookFilter;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Endpoint {
+        requests: Mutex<Vec<(HeaderMap, Bytes)>>,
+        failures: AtomicU64
+    }
+
+    async fn handle(State(endpoint): State<Arc<Endpoint>>, headers: HeaderMap, body: Bytes) -> StatusCode {
+        endpoint.requests.lock().unwrap().push((headers, body));
+
+        // Fail given amount of first requests
+        let failed = endpoint.failures.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |failures| failures.checked_sub(1));
+
+        if failed.is_ok() {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    async fn wait_stats(worker: &WebhookWorker<ReqwestHttpClient>, done: impl Fn(&WebhookStats) -> bool) -> WebhookStats {
+        for _ in 0..100 {
+            let stats = worker.stats();
+
+            if done(&stats) {
+                return stats;
+            }
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        worker.stats()
+    }
+
+    #[tokio::test]
+    async fn webhooks() -> Result<(), Box<dyn std::error::Error>> {
+        let endpoint = Arc::new(Endpoint::default());
+
+        endpoint.failures.store(2, Ordering::Relaxed);
+
+        let router = axum::Router::new()
+            .route("/hook", axum::routing::post(handle))
+            .with_state(endpoint.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:48473").await?;
+
+        tokio::spawn(async move {
+            let _ = axum::serve(listener, router).await;
+        });
+
+        let watched = SecretKey::random().public_key();
+        let sender = SecretKey::random().public_key();
+
+        let worker = WebhookWorker::new(ReqwestHttpClient::default(), WebhooksParams {
+            hooks: vec![Webhook {
+                filter: WebhookFilter {
+                    events: vec![WebhookEventKind::MessageReceived],
+                    keys: vec![watched.clone()]
+                },
+                url: String::from("http://127.0.0.1:48473/hook"),
+                secret: b"secret".to_vec()
+            }],
+            retries: 3,
+            backoff: Duration::from_millis(10)
+        });
+
+        // Filter matching
+        assert_eq!(worker.notify(WebhookEvent::MessageReceived { sender: sender.clone(), receiver: SecretKey::random().public_key() }), 0);
+        assert_eq!(worker.notify(WebhookEvent::ClientConnected { client: watched.clone() }), 0);
+        assert_eq!(worker.notify(WebhookEvent::MessageReceived { sender: sender.clone(), receiver: watched.clone() }), 1);
+
+        // Retries on 500s
+        let stats = wait_stats(&worker, |stats| stats.delivered == 1).await;
+
+        assert_eq!(stats, WebhookStats {
+            queued: 1,
+            delivered: 1,
+            retried: 2,
+            dead_letters: 0
+        });
+
+        let requests = endpoint.requests.lock().unwrap().clone();
+
+        assert_eq!(requests.len(), 3);
+
+        for (headers, body) in requests {
+            assert_eq!(headers["X-Hyperborea-Event"], "message_received");
+
+            // Signature covers exact body bytes
+            assert_eq!(headers["X-Hyperborea-Signature"], webhook_signature(b"secret", &body).as_str());
+
+            let payload = serde_json::from_slice::<Json>(&body)?;
+
+            assert_eq!(payload["event"], "message_received");
+            assert_eq!(payload["sender"], fingerprint(&sender));
+            assert_eq!(payload["receiver"], fingerprint(&watched));
+            assert_eq!(payload.as_object().unwrap().len(), 4);
+        }
+
+        // Dead letters
+        endpoint.failures.store(100, Ordering::Relaxed);
+
+        worker.notify(WebhookEvent::MessageReceived { sender, receiver: watched });
+
+        let stats = wait_stats(&worker, |stats| stats.dead_letters == 1).await;
+
+        assert_eq!(stats.dead_letters, 1);
+        assert_eq!(stats.retried, 5);
+
+        Ok(())
+    }
+}
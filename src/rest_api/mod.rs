@@ -5,9 +5,14 @@ use crate::crypto::Error as CryptographyError;
 pub mod request;
 pub mod response;
 pub mod status;
+pub mod error_code;
+pub mod version;
 pub mod types;
 pub mod requests;
 pub mod middleware;
+pub mod compat;
+pub mod pagination;
+pub mod wire_fixtures;
 
 pub mod prelude {
     pub use super::{
@@ -19,6 +24,15 @@ pub mod prelude {
     pub use super::request::Request;
     pub use super::response::Response;
     pub use super::status::ResponseStatus;
+    pub use super::error_code::ErrorCode;
+    pub use super::version::{ProtocolVersion, ProtocolVersions, VersionedRequest};
+
+    pub use super::pagination::{
+        ContinuationToken,
+        PageRequest,
+        Page,
+        PaginationError
+    };
 
     pub use super::types::*;
     pub use super::requests::*;
@@ -26,8 +40,56 @@ pub mod prelude {
     pub use super::middleware::{
         Client as ClientMiddleware,
         ConnectedClient as ConnectedClientMiddleware,
+        ServersPages,
+        SendToResult,
+        SendToError,
+        AutoReconnect,
+        ReconnectEvent,
+        FailoverClient,
+        FailoverEvent,
+        Interceptor,
+        Interceptors,
+        RequestCounter,
+        AccessLog,
+        AccessLogEntry,
+        AccessLogHook,
+        TracingAccessLog,
         Server as ServerMiddleware,
-        Error as MiddlewareError
+        Error as MiddlewareError,
+        MessageReorderer,
+        ReorderParams,
+        OrderedEvent
+    };
+
+    #[cfg(feature = "heartbeat")]
+    pub use super::middleware::HeartbeatHandle;
+
+    #[cfg(feature = "retry")]
+    pub use super::middleware::RetryPolicy;
+
+    #[cfg(feature = "timeouts")]
+    pub use super::middleware::Timeouts;
+
+    #[cfg(feature = "session")]
+    pub use super::middleware::ClientSession;
+
+    #[cfg(feature = "outbox")]
+    pub use super::middleware::{
+        Outbox,
+        OutboxParams,
+        OutboxEntry,
+        OutboxEvent,
+        OutboxFlush,
+        OutboxFlusher,
+        OutboxError
+    };
+
+    #[cfg(feature = "subscribe")]
+    pub use super::middleware::{
+        Subscription,
+        SubscriptionParams,
+        SubscriptionMessage,
+        SubscriptionError
     };
 }
 
@@ -127,4 +189,5 @@ impl_as_json!(
     i8 i16 i32 i64 i128 isize
     String
     std::path::PathBuf
+    serde_json::Value
 );
@@ -0,0 +1,468 @@
+//! Shared pagination of the REST API list endpoints.
+//! 
+//! Lists are sorted by a stable cursor key of their entries
+//! (e.g. base64 encoded public key), and continuation tokens
+//! point to the last returned key instead of an offset. This
+//! keeps pages consistent when entries are added or removed
+//! between requests.
+//! 
+//! Tokens are opaque to the clients and signed by the server's
+//! secret key so they can't be forged or tampered with.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Value as Json};
+
+use k256::sha2::{Sha256, Digest};
+
+use crate::crypto::prelude::*;
+use crate::crypto::encoding::base64;
+
+use super::{AsJson, AsJsonError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PaginationError {
+    #[error("Continuation token has invalid format")]
+    InvalidFormat,
+
+    #[error("Continuation token has invalid signature")]
+    InvalidSignature,
+
+    #[error("Query parameter `{0}` has invalid value")]
+    InvalidParameter(&'static str)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Signed position of the page walker.
+pub struct ContinuationToken {
+    /// Cursor key of the last returned entry.
+    pub after: String,
+
+    /// Version stamp of the table at the moment
+    /// when the token was minted.
+    pub version: u64
+}
+
+impl ContinuationToken {
+    #[inline]
+    pub fn new(after: impl ToString, version: u64) -> Self {
+        Self {
+            after: after.to_string(),
+            version
+        }
+    }
+
+    /// Derive tokens signing key from the server's secret.
+    fn signing_key(secret_key: &SecretKey) -> [u8; 32] {
+        hmac_sha256(secret_key.serialize(), b"hyperborea/pagination")
+    }
+
+    /// Encode token to the opaque string signed by the server.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::pagination::ContinuationToken;
+    /// 
+    /// let secret = SecretKey::random();
+    /// 
+    /// let token = ContinuationToken::new("cursor", 1);
+    /// 
+    /// assert_eq!(ContinuationToken::verify(&secret, token.sign(&secret)).unwrap(), token);
+    /// assert!(ContinuationToken::verify(&SecretKey::random(), token.sign(&secret)).is_err());
+    /// ```
+    pub fn sign(&self, secret_key: &SecretKey) -> String {
+        let mut bytes = self.version.to_be_bytes().to_vec();
+
+        bytes.extend_from_slice(self.after.as_bytes());
+
+        let signature = hmac_sha256(Self::signing_key(secret_key), &bytes);
+
+        bytes.extend_from_slice(&signature);
+
+        base64::encode(bytes)
+    }
+
+    /// Decode token and verify its signature.
+    pub fn verify(secret_key: &SecretKey, token: impl AsRef<str>) -> Result<Self, PaginationError> {
+        let bytes = base64::decode(token.as_ref())
+            .map_err(|_| PaginationError::InvalidFormat)?;
+
+        if bytes.len() < 40 {
+            return Err(PaginationError::InvalidFormat);
+        }
+
+        let (payload, signature) = bytes.split_at(bytes.len() - 32);
+
+        if hmac_sha256(Self::signing_key(secret_key), payload) != signature {
+            return Err(PaginationError::InvalidSignature);
+        }
+
+        let mut version = [0; 8];
+
+        version.copy_from_slice(&payload[..8]);
+
+        let after = String::from_utf8(payload[8..].to_vec())
+            .map_err(|_| PaginationError::InvalidFormat)?;
+
+        Ok(Self {
+            after,
+            version: u64::from_be_bytes(version)
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+/// Pagination parameters of the list request.
+/// 
+/// If both token and offset are given then
+/// the token is used.
+pub struct PageRequest {
+    /// Continuation token returned with the previous page.
+    pub token: Option<String>,
+
+    /// Amount of entries to skip.
+    /// 
+    /// Legacy alternative of the continuation tokens.
+    /// Offsets are not stable when the table changes
+    /// between requests.
+    pub offset: Option<u64>,
+
+    /// Maximal amount of entries on the page.
+    pub limit: Option<u64>
+}
+
+impl PageRequest {
+    #[inline]
+    /// Request first page with given size.
+    pub fn first(limit: u64) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    #[inline]
+    /// Request page which follows the one
+    /// that returned given token.
+    pub fn next(token: impl ToString, limit: Option<u64>) -> Self {
+        Self {
+            token: Some(token.to_string()),
+            offset: None,
+            limit
+        }
+    }
+
+    #[inline]
+    /// Request page with given offset.
+    pub fn offset(offset: u64, limit: Option<u64>) -> Self {
+        Self {
+            token: None,
+            offset: Some(offset),
+            limit
+        }
+    }
+
+    #[inline]
+    /// Check if request has no pagination parameters.
+    pub fn is_empty(&self) -> bool {
+        self.token.is_none() && self.offset.is_none() && self.limit.is_none()
+    }
+
+    /// Parse pagination parameters from the URL query.
+    pub fn from_query(query: &HashMap<String, String>) -> Result<Self, PaginationError> {
+        let offset = match query.get("offset") {
+            Some(offset) => Some(offset.parse().map_err(|_| PaginationError::InvalidParameter("offset"))?),
+            None => None
+        };
+
+        let limit = match query.get("limit") {
+            Some(limit) => Some(limit.parse().map_err(|_| PaginationError::InvalidParameter("limit"))?),
+            None => None
+        };
+
+        Ok(Self {
+            token: query.get("token").cloned(),
+            offset,
+            limit
+        })
+    }
+
+    /// Encode pagination parameters as the URL query.
+    /// 
+    /// Return empty string if there are no parameters.
+    pub fn to_query(&self) -> String {
+        let mut params = Vec::with_capacity(3);
+
+        if let Some(token) = &self.token {
+            // URL safe base64 has only `=` which should be escaped
+            params.push(format!("token={}", token.replace('=', "%3D")));
+        }
+
+        if let Some(offset) = self.offset {
+            params.push(format!("offset={offset}"));
+        }
+
+        if let Some(limit) = self.limit {
+            params.push(format!("limit={limit}"));
+        }
+
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", params.join("&"))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Page of the list returned by the server.
+pub struct Page<T> {
+    pub items: Vec<T>,
+
+    /// Continuation token of the next page.
+    /// 
+    /// `None` if this is the last page.
+    pub next: Option<String>
+}
+
+impl<T> Page<T> {
+    #[inline]
+    pub fn new(items: impl Into<Vec<T>>, next: Option<String>) -> Self {
+        Self {
+            items: items.into(),
+            next
+        }
+    }
+}
+
+impl<T: AsJson> AsJson for Page<T> {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let mut page = json!({
+            "items": self.items.to_json()?
+        });
+
+        if let Some(next) = &self.next {
+            page["next"] = Json::String(next.clone());
+        }
+
+        Ok(page)
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(items) = json.get("items") else {
+            return Err(AsJsonError::FieldNotFound("items"));
+        };
+
+        let next = match json.get("next") {
+            Some(next) => Some(next.as_str()
+                .ok_or(AsJsonError::FieldValueInvalid("next"))?
+                .to_string()),
+
+            None => None
+        };
+
+        Ok(Self {
+            items: Vec::from_json(items)?,
+            next
+        })
+    }
+}
+
+/// Calculate version stamp of the table from
+/// the sorted cursor keys of its entries.
+pub fn table_version<'a>(keys: impl IntoIterator<Item = &'a str>) -> u64 {
+    let mut hasher = Sha256::new();
+
+    for key in keys {
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+    }
+
+    let mut version = [0; 8];
+
+    version.copy_from_slice(&hasher.finalize()[..8]);
+
+    u64::from_be_bytes(version)
+}
+
+/// Slice the page of the given table.
+/// 
+/// - `cursor` must return stable unique key of the entry.
+/// 
+/// Continuation token of the next page is signed by the
+/// `secret_key` and returned only if there are more entries
+/// after the page. Request without any parameters returns
+/// the whole table.
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use hyperborealib::crypto::prelude::*;
+/// use hyperborealib::rest_api::pagination::*;
+/// 
+/// let secret = SecretKey::random();
+/// 
+/// let page = paginate(&secret, vec![3, 1, 2], |num| num.to_string(), &PageRequest::first(2)).unwrap();
+/// 
+/// assert_eq!(page.items, [1, 2]);
+/// 
+/// let page = paginate(&secret, vec![3, 1, 2], |num| num.to_string(), &PageRequest::next(page.next.unwrap(), Some(2))).unwrap();
+/// 
+/// assert_eq!(page.items, [3]);
+/// assert_eq!(page.next, None);
+/// ```
+pub fn paginate<T>(
+    secret_key: &SecretKey,
+    items: impl IntoIterator<Item = T>,
+    cursor: impl Fn(&T) -> String,
+    request: &PageRequest
+) -> Result<Page<T>, PaginationError> {
+    let mut items = items.into_iter()
+        .map(|item| (cursor(&item), item))
+        .collect::<Vec<_>>();
+
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let version = table_version(items.iter().map(|(key, _)| key.as_str()));
+
+    // Skip already returned entries
+    let start = match (&request.token, request.offset) {
+        (Some(token), _) => {
+            let token = ContinuationToken::verify(secret_key, token)?;
+
+            items.partition_point(|(key, _)| key <= &token.after)
+        }
+
+        (None, Some(offset)) => (offset as usize).min(items.len()),
+        (None, None) => 0
+    };
+
+    let end = match request.limit {
+        Some(limit) => start.saturating_add(limit as usize).min(items.len()),
+        None => items.len()
+    };
+
+    let next = if end < items.len() && end > start {
+        Some(ContinuationToken::new(&items[end - 1].0, version).sign(secret_key))
+    } else {
+        None
+    };
+
+    let items = items.drain(start..end)
+        .map(|(_, item)| item)
+        .collect::<Vec<_>>();
+
+    Ok(Page::new(items, next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tampering() {
+        let secret = SecretKey::random();
+
+        let token = ContinuationToken::new("cursor", 123).sign(&secret);
+
+        assert_eq!(ContinuationToken::verify(&secret, &token).unwrap(), ContinuationToken::new("cursor", 123));
+
+        // Changed cursor position
+        let mut bytes = base64::decode(&token).unwrap();
+
+        bytes[8] ^= 1;
+
+        assert!(matches!(
+            ContinuationToken::verify(&secret, base64::encode(&bytes)),
+            Err(PaginationError::InvalidSignature)
+        ));
+
+        // Forged by another key
+        let forged = ContinuationToken::new("cursor", 123).sign(&SecretKey::random());
+
+        assert!(matches!(
+            ContinuationToken::verify(&secret, forged),
+            Err(PaginationError::InvalidSignature)
+        ));
+
+        // Garbage
+        assert!(matches!(
+            ContinuationToken::verify(&secret, "hello"),
+            Err(PaginationError::InvalidFormat)
+        ));
+
+        assert!(paginate(&secret, vec![1, 2, 3], |num| num.to_string(), &PageRequest::next("hello", None)).is_err());
+    }
+
+    #[test]
+    fn consistency() -> Result<(), PaginationError> {
+        let secret = SecretKey::random();
+
+        let mut table = vec!["b", "d", "f", "h"];
+
+        let first = paginate(&secret, table.clone(), |key| key.to_string(), &PageRequest::first(2))?;
+
+        assert_eq!(first.items, ["b", "d"]);
+
+        // New entries before and after the cursor
+        table.extend(["a", "c", "e"]);
+
+        let second = paginate(&secret, table.clone(), |key| key.to_string(), &PageRequest::next(first.next.unwrap(), Some(2)))?;
+
+        assert_eq!(second.items, ["e", "f"]);
+
+        let third = paginate(&secret, table.clone(), |key| key.to_string(), &PageRequest::next(second.next.unwrap(), Some(2)))?;
+
+        assert_eq!(third.items, ["h"]);
+        assert_eq!(third.next, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn offsets() -> Result<(), PaginationError> {
+        let secret = SecretKey::random();
+
+        let table = vec![5, 4, 3, 2, 1];
+
+        let page = paginate(&secret, table.clone(), |num| num.to_string(), &PageRequest::default())?;
+
+        assert_eq!(page.items, [1, 2, 3, 4, 5]);
+        assert_eq!(page.next, None);
+
+        let page = paginate(&secret, table.clone(), |num| num.to_string(), &PageRequest::offset(1, Some(3)))?;
+
+        assert_eq!(page.items, [2, 3, 4]);
+        assert!(page.next.is_some());
+
+        let page = paginate(&secret, table.clone(), |num| num.to_string(), &PageRequest::offset(10, None))?;
+
+        assert!(page.items.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn query() -> Result<(), PaginationError> {
+        let request = PageRequest {
+            token: Some(String::from("abc")),
+            offset: Some(1),
+            limit: Some(2)
+        };
+
+        assert_eq!(request.to_query(), "?token=abc&offset=1&limit=2");
+        assert_eq!(PageRequest::default().to_query(), "");
+
+        let query = HashMap::from([
+            (String::from("token"), String::from("abc")),
+            (String::from("offset"), String::from("1")),
+            (String::from("limit"), String::from("2"))
+        ]);
+
+        assert_eq!(PageRequest::from_query(&query)?, request);
+
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
-use serde_json::{json, Value as Json};
+use std::borrow::Cow;
+
+use serde_json::{json, Map, Value as Json};
 
 use crate::crypto::prelude::*;
+use crate::time::timestamp;
 
-use crate::STANDARD_VERSION;
+use super::version::ProtocolVersion;
 
 use super::{
     AsJson,
@@ -19,23 +22,89 @@ use super::{
 /// (contain a proper header) which is used to verify
 /// that the request is sent by a client with specified
 /// public key.
+/// 
+/// The proof signature covers both the proof seed
+/// and the request's timestamp, so servers can reject
+/// captured requests which are sent again later.
+/// 
+/// The body signature additionally covers the canonical
+/// serialization of the request's body, so it can't be
+/// changed by anyone who forwards the request.
 pub struct Request<T> {
     pub standard: u64,
     pub public_key: PublicKey,
     pub proof_seed: u64,
     pub proof_sign: Vec<u8>,
-    pub request: T
+
+    /// UTC timestamp of the request's creation, in seconds.
+    /// 
+    /// It's `None` for the requests made by old clients,
+    /// which sign only the proof seed. Servers made before
+    /// this field was added can't validate timestamped requests.
+    pub timestamp: Option<u64>,
+
+    /// Signature of the proof seed, the timestamp
+    /// and the canonical serialization of the body.
+    /// 
+    /// It's `None` for the requests made by old clients.
+    /// Old servers ignore it and verify only the proof sign.
+    pub body_sign: Option<Vec<u8>>,
+
+    pub request: T,
+
+    /// Fields of the request's body unknown to `T`.
+    /// 
+    /// Newer clients can send optional fields which
+    /// this version doesn't support yet. They're kept
+    /// here so interceptors and handlers can read them,
+    /// and are serialized back into the body. The body
+    /// signature covers them as well.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Map::is_empty"))]
+    pub extensions: Map<String, Json>,
+
+    /// Body of the parsed request as it was received.
+    /// 
+    /// Body signature is verified against it, so requests
+    /// are valid even if this version serializes their
+    /// bodies differently than the sender did.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    received_body: ReceivedBody
 }
 
-impl<T> Request<T> {
+#[derive(Default, Debug, Clone)]
+/// Raw JSON body of the received request.
+/// 
+/// It's not a part of the request's value,
+/// so it's ignored by comparison and hashing.
+struct ReceivedBody(Option<Json>);
+
+impl PartialEq for ReceivedBody {
+    #[inline]
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ReceivedBody {}
+
+impl std::hash::Hash for ReceivedBody {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
+
+impl<T: AsJson> Request<T> {
     /// Create new REST API request.
     /// 
+    /// Request's body is signed as well. Bodies which
+    /// can't be serialized are not signed since such
+    /// requests can't be sent anyway.
+    /// 
     /// - `client_secret` must contain reference to
     ///   the secret key of the request's sender.
     ///   It is used to sign random number to validate
     ///   this request.
     /// 
-    /// - `request` can contain any value, preferably
+    /// - `request` can contain any value
     ///   implementing `AsJson` trait.
     /// 
     /// # Example
@@ -47,23 +116,70 @@ impl<T> Request<T> {
     /// let request = Request::new(&SecretKey::random(), ());
     /// ```
     pub fn new(client_secret: &SecretKey, request: T) -> Self {
-        let proof_seed = safe_random_u64_long();
+        Self::extended(client_secret, request, Map::new())
+    }
+
+    /// Create new REST API request with additional
+    /// fields in its body.
+    /// 
+    /// Fields already serialized by the `request`
+    /// are not overwritten by the extensions. Bodies
+    /// which are not JSON objects can't be extended.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use serde_json::{json, Map};
+    /// 
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let mut extensions = Map::new();
+    /// 
+    /// extensions.insert(String::from("priority"), json!("high"));
+    /// 
+    /// let body = PollRequestBody::new(ChannelName::new("example").unwrap(), None);
+    /// 
+    /// let request = Request::extended(&SecretKey::random(), body, extensions);
+    /// 
+    /// assert_eq!(request.extension("priority"), Some(&json!("high")));
+    /// assert_eq!(request.to_json().unwrap()["request"]["priority"], "high");
+    /// assert!(request.validate().unwrap());
+    /// ```
+    pub fn extended(client_secret: &SecretKey, request: T, extensions: Map<String, Json>) -> Self {
+        let mut request = Self::create(client_secret, Some(timestamp()), request);
 
-        let proof_sign = client_secret.create_signature(proof_seed.to_be_bytes());
+        request.extensions = extensions;
+        request.sign_body(client_secret);
 
-        Self {
-            standard: STANDARD_VERSION,
-            public_key: client_secret.public_key(),
-            proof_seed,
-            proof_sign,
-            request
+        request
+    }
+
+    /// Sign the request's body again.
+    /// 
+    /// Must be called after the body of the request
+    /// made by `new` is changed, otherwise the request
+    /// won't pass validation. Legacy requests stay unsigned.
+    pub fn sign_body(&mut self, client_secret: &SecretKey) {
+        if self.timestamp.is_none() {
+            return;
         }
+
+        // Signature covers the current body from now on
+        self.received_body = ReceivedBody::default();
+
+        self.body_sign = self.body_json().ok().map(|body| {
+            client_secret.create_signature(Self::body_signed_data(self.proof_seed, self.timestamp, &body))
+        });
     }
 
     /// Validate that the request's header is correct.
     /// 
     /// This method will verify that the proof signature
     /// is signed correctly by the sender using given public key.
+    /// If the body signature is present then it must be
+    /// correct as well. Body signature of the parsed request
+    /// is checked against the body it was received with.
     /// 
     /// This method will also verify that the proof seed
     /// is correctly chosen (`>= 1^63`). This is important
@@ -88,77 +204,293 @@ impl<T> Request<T> {
             return Err(ValidationError::InvalidSeed);
         }
 
-        Ok(self.public_key.verify_signature(
-            self.proof_seed.to_be_bytes(),
-            &self.proof_sign
-        )?)
+        let signed_data = Self::signed_data(self.proof_seed, self.timestamp);
+
+        if !self.public_key.verify_signature(signed_data, &self.proof_sign)? {
+            return Ok(false);
+        }
+
+        let Some(body_sign) = &self.body_sign else {
+            return Ok(true);
+        };
+
+        let body = match &self.received_body.0 {
+            Some(body) => Cow::Borrowed(body),
+
+            None => match self.body_json() {
+                Ok(body) => Cow::Owned(body),
+                Err(_) => return Ok(false)
+            }
+        };
+
+        let data = Self::body_signed_data(self.proof_seed, self.timestamp, &body);
+
+        Ok(self.public_key.verify_signature(data, body_sign)?)
+    }
+
+    /// Serialize the request's body with its extensions.
+    fn body_json(&self) -> Result<Json, AsJsonError> {
+        let mut body = self.request.to_json()?;
+
+        if let Json::Object(fields) = &mut body {
+            for (key, value) in &self.extensions {
+                if !fields.contains_key(key) {
+                    fields.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        Ok(body)
+    }
+}
+
+impl<T> Request<T> {
+    #[inline]
+    /// Create new REST API request in the old format,
+    /// without the timestamp.
+    /// 
+    /// Such requests can be replayed, so servers may
+    /// reject them. Refer to `ReplayProtection`.
+    pub fn legacy(client_secret: &SecretKey, request: T) -> Self {
+        Self::create(client_secret, None, request)
+    }
+
+    fn create(client_secret: &SecretKey, timestamp: Option<u64>, request: T) -> Self {
+        let proof_seed = safe_random_u64_long();
+
+        let proof_sign = client_secret.create_signature(Self::signed_data(proof_seed, timestamp));
+
+        Self {
+            standard: ProtocolVersion::CURRENT.0,
+            public_key: client_secret.public_key(),
+            proof_seed,
+            proof_sign,
+            timestamp,
+            body_sign: None,
+            request,
+            extensions: Map::new(),
+            received_body: ReceivedBody::default()
+        }
+    }
+
+    #[inline]
+    /// Make the request by the given standard version.
+    /// 
+    /// Version is usually negotiated with the server
+    /// using its `GET /api/v1/info` response.
+    /// Refer to `ProtocolVersions::negotiate`.
+    pub fn with_standard(self, standard: impl Into<ProtocolVersion>) -> Self {
+        Self {
+            standard: standard.into().0,
+            ..self
+        }
+    }
+
+    /// Get bytes signed by the proof.
+    fn signed_data(proof_seed: u64, timestamp: Option<u64>) -> Vec<u8> {
+        let mut data = proof_seed.to_be_bytes().to_vec();
+
+        if let Some(timestamp) = timestamp {
+            data.extend(timestamp.to_be_bytes());
+        }
+
+        data
+    }
+
+    /// Get bytes signed by the body signature.
+    fn body_signed_data(proof_seed: u64, timestamp: Option<u64>, body: &Json) -> Vec<u8> {
+        let mut data = Self::signed_data(proof_seed, timestamp);
+
+        data.extend(canonical_json(body));
+
+        data
+    }
+
+    #[inline]
+    /// Get unknown field of the request's body.
+    /// 
+    /// Refer to `extensions`.
+    pub fn extension(&self, name: impl AsRef<str>) -> Option<&Json> {
+        self.extensions.get(name.as_ref())
+    }
+
+    #[inline]
+    /// Check if the request's body is signed.
+    pub fn is_body_signed(&self) -> bool {
+        self.body_sign.is_some()
     }
 }
 
 impl<T: AsJson> AsJson for Request<T> {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        let value = match self.standard {
-            1 => json!({
-                "standard": self.standard,
-                "public_key": self.public_key.to_base64(),
-                "proof": {
-                    "seed": self.proof_seed,
-                    "sign": base64_encode(&self.proof_sign)
-                },
-                "request": self.request.to_json()?
-            }),
-
-            _ => return Err(AsJsonError::InvalidStandard(self.standard))
-        };
+        let standard = ProtocolVersion::check(self.standard)?;
+
+        let mut value = json!({
+            "standard": standard.0,
+            "public_key": self.public_key.to_base64(),
+            "proof": {
+                "seed": self.proof_seed,
+                "sign": base64_encode(&self.proof_sign)
+            },
+            "request": self.body_json()?
+        });
+
+        // Keep legacy request shape for old format requests
+        if let Some(timestamp) = self.timestamp {
+            value["proof"]["timestamp"] = Json::from(timestamp);
+        }
+
+        if let Some(body_sign) = &self.body_sign {
+            value["proof"]["body_sign"] = Json::from(base64_encode(body_sign));
+        }
 
         Ok(value)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
-        let Some(standard) = json.get("standard").and_then(Json::as_u64) else {
-            return Err(AsJsonError::FieldNotFound("standard"));
+        let standard = ProtocolVersion::parse(json)?.0;
+
+        let Some(public_key) = json.get("public_key").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("public_key"));
         };
 
-        match standard {
-            1 => {
-                let Some(public_key) = json.get("public_key").and_then(Json::as_str) else {
-                    return Err(AsJsonError::FieldNotFound("public_key"));
-                };
+        let Some(proof) = json.get("proof") else {
+            return Err(AsJsonError::FieldNotFound("proof"));
+        };
 
-                let Some(proof) = json.get("proof") else {
-                    return Err(AsJsonError::FieldNotFound("proof"));
-                };
+        let Some(proof_seed) = proof.get("seed").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("proof.seed"));
+        };
 
-                let Some(proof_seed) = proof.get("seed").and_then(Json::as_u64) else {
-                    return Err(AsJsonError::FieldNotFound("proof.seed"));
-                };
+        let Some(proof_sign) = proof.get("sign").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("proof.sign"));
+        };
 
-                let Some(proof_sign) = proof.get("sign").and_then(Json::as_str) else {
-                    return Err(AsJsonError::FieldNotFound("proof.sign"));
-                };
+        let timestamp = match proof.get("timestamp") {
+            Some(timestamp) => Some(timestamp.as_u64().ok_or(AsJsonError::FieldValueInvalid("proof.timestamp"))?),
+            None => None
+        };
 
-                let Some(request) = json.get("request") else {
-                    return Err(AsJsonError::FieldNotFound("request"));
-                };
+        let body_sign = match proof.get("body_sign") {
+            Some(sign) => Some(base64_decode(sign.as_str().ok_or(AsJsonError::FieldValueInvalid("proof.body_sign"))?)?),
+            None => None
+        };
 
-                Ok(Self {
-                    standard,
-                    public_key: PublicKey::from_base64(public_key)?,
-                    proof_seed,
-                    proof_sign: base64_decode(proof_sign)?,
-                    request: T::from_json(request)?
-                })
+        let Some(request) = json.get("request") else {
+            return Err(AsJsonError::FieldNotFound("request"));
+        };
+
+        let body = T::from_json(request)?;
+
+        // Keep fields which are not serialized by the body
+        let mut extensions = Map::new();
+
+        if let (Some(fields), Json::Object(known)) = (request.as_object(), body.to_json()?) {
+            for (key, value) in fields {
+                if !known.contains_key(key) {
+                    extensions.insert(key.clone(), value.clone());
+                }
             }
+        }
+
+        // Only signed bodies need to be kept
+        let received_body = ReceivedBody(body_sign.as_ref().map(|_| request.clone()));
 
-            _ => Err(AsJsonError::InvalidStandard(standard))
+        Ok(Self {
+            standard,
+            public_key: PublicKey::from_base64(public_key)?,
+            proof_seed,
+            proof_sign: base64_decode(proof_sign)?,
+            timestamp,
+            body_sign,
+            request: body,
+            extensions,
+            received_body
+        })
+    }
+}
+
+/// Serialize JSON value deterministically.
+/// 
+/// Objects' keys are sorted and no whitespaces are
+/// used, so the same value always has the same bytes
+/// regardless of the order it was built in.
+/// 
+/// ```rust
+/// use serde_json::json;
+/// 
+/// use hyperborealib::rest_api::request::canonical_json;
+/// 
+/// let value = json!({
+///     "b": [1, { "d": true, "c": null }],
+///     "a": "text"
+/// });
+/// 
+/// assert_eq!(canonical_json(&value), br#"{"a":"text","b":[1,{"c":null,"d":true}]}"#);
+/// ```
+pub fn canonical_json(value: &Json) -> Vec<u8> {
+    fn write(value: &Json, bytes: &mut Vec<u8>) {
+        match value {
+            Json::Array(values) => {
+                bytes.push(b'[');
+
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        bytes.push(b',');
+                    }
+
+                    write(value, bytes);
+                }
+
+                bytes.push(b']');
+            }
+
+            Json::Object(values) => {
+                let mut keys = values.keys().collect::<Vec<_>>();
+
+                keys.sort();
+
+                bytes.push(b'{');
+
+                for (i, key) in keys.into_iter().enumerate() {
+                    if i > 0 {
+                        bytes.push(b',');
+                    }
+
+                    write(&Json::String(key.clone()), bytes);
+
+                    bytes.push(b':');
+
+                    write(&values[key], bytes);
+                }
+
+                bytes.push(b'}');
+            }
+
+            // Scalars are always serialized the same way
+            value => bytes.extend(value.to_string().into_bytes())
         }
     }
+
+    let mut bytes = Vec::new();
+
+    write(value, &mut bytes);
+
+    bytes
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::rest_api::requests::ConnectRequest;
-    use crate::rest_api::types::ClientInfo;
+    use crate::rest_api::requests::{
+        ConnectRequest,
+        SendRequest,
+        PollRequest,
+        PollRequestBody
+    };
+
+    use crate::rest_api::types::{ClientInfo, ChannelName};
+    use crate::rest_api::types::sender::tests::get_sender;
+    use crate::rest_api::types::message_info::tests::get_message_info;
 
     use super::*;
 
@@ -173,8 +505,14 @@ mod tests {
             ClientInfo::thin()
         );
 
-        let request = Request::new(&secret, connect_request);
+        let request = Request::new(&secret, connect_request.clone());
+
+        assert!(request.timestamp.is_some());
+        assert_eq!(Request::from_json(&request.to_json()?)?, request);
+
+        let request = Request::legacy(&secret, connect_request);
 
+        assert!(request.to_json()?["proof"].get("timestamp").is_none());
         assert_eq!(Request::from_json(&request.to_json()?)?, request);
 
         Ok(())
@@ -214,6 +552,194 @@ mod tests {
 
         assert!(request.validate().is_err());
 
+        // Timestamp is covered by the proof
+
+        let mut request = Request::new(&secret_key, ());
+
+        request.timestamp = request.timestamp.map(|timestamp| timestamp - 1);
+
+        assert!(!request.validate()?);
+
+        request.timestamp = None;
+
+        assert!(!request.validate()?);
+
+        // Legacy requests without timestamp
+
+        let request = Request::legacy(&secret_key, ());
+
+        assert!(request.validate()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn body_sign() -> Result<(), Box<dyn std::error::Error>> {
+        let secret = SecretKey::random();
+
+        let request = ConnectRequest::new(
+            &secret,
+            SecretKey::random().public_key(),
+            ClientInfo::thin()
+        );
+
+        assert!(request.0.is_body_signed());
+        assert!(request.0.validate()?);
+
+        // Body is covered by the signature
+        let mut json = request.to_json()?;
+
+        json["request"]["client"] = ClientInfo::server("example.org").to_json()?;
+
+        assert!(!ConnectRequest::from_json(&json)?.0.validate()?);
+
+        // Order of the body's fields doesn't matter
+        let mut json = request.to_json()?;
+
+        let body = json["request"].as_object().unwrap().iter()
+            .rev()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<serde_json::Map<_, _>>();
+
+        json["request"] = Json::Object(body);
+
+        assert!(ConnectRequest::from_json(&json)?.0.validate()?);
+
+        // Requests of the old clients don't have body signature
+        let mut json = request.to_json()?;
+
+        json["proof"].as_object_mut().unwrap().remove("body_sign");
+
+        let request = ConnectRequest::from_json(&json)?;
+
+        assert!(!request.0.is_body_signed());
+        assert!(request.0.validate()?);
+
+        // Legacy requests are not signed by the body signature
+        let request = Request::legacy(&secret, ());
+
+        assert!(request.to_json()?["proof"].get("body_sign").is_none());
+        assert_eq!(Request::<()>::from_json(&request.to_json()?)?, request);
+
+        Ok(())
+    }
+
+    #[test]
+    fn body_sign_compatibility() -> Result<(), Box<dyn std::error::Error>> {
+        /// Body of the newer version with a defaulted field.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        struct Body {
+            text: String,
+            priority: u64
+        }
+
+        impl AsJson for Body {
+            fn to_json(&self) -> Result<Json, AsJsonError> {
+                Ok(json!({
+                    "text": self.text,
+                    "priority": self.priority
+                }))
+            }
+
+            fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+                Ok(Self {
+                    text: json.get("text")
+                        .and_then(Json::as_str)
+                        .ok_or(AsJsonError::FieldNotFound("text"))?
+                        .to_string(),
+
+                    priority: json.get("priority")
+                        .and_then(Json::as_u64)
+                        .unwrap_or_default()
+                })
+            }
+        }
+
+        let secret = SecretKey::random();
+
+        let mut request = Request::new(&secret, Body {
+            text: String::from("hello"),
+            priority: 0
+        });
+
+        // Body signed by the older version without the field
+        request.body_sign = Some(secret.create_signature(Request::<Body>::body_signed_data(
+            request.proof_seed,
+            request.timestamp,
+            &json!({ "text": "hello" })
+        )));
+
+        let mut json = request.to_json()?;
+
+        json["request"] = json!({ "text": "hello" });
+
+        let mut request = Request::<Body>::from_json(&json)?;
+
+        assert_eq!(request.request.priority, 0);
+        assert!(request.validate()?);
+
+        // Changed body must be signed again
+        request.request.priority = 1;
+        request.sign_body(&secret);
+
+        assert!(request.validate()?);
+        assert!(Request::<Body>::from_json(&request.to_json()?)?.validate()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn extensions() -> Result<(), Box<dyn std::error::Error>> {
+        fn extend(mut json: Json) -> Json {
+            json["request"]["future_field"] = json!({ "enabled": true });
+
+            json
+        }
+
+        let secret = SecretKey::random();
+        let message = get_message_info();
+
+        // Connect request
+
+        let json = extend(ConnectRequest::new(&secret, SecretKey::random().public_key(), ClientInfo::thin()).to_json()?);
+        let request = ConnectRequest::from_json(&json)?;
+
+        assert_eq!(request.0.extension("future_field"), Some(&json!({ "enabled": true })));
+        assert_eq!(request.to_json()?, json);
+
+        // Send request
+
+        let json = extend(SendRequest::new(&secret, get_sender(), SecretKey::random().public_key(), ChannelName::new("example").unwrap(), message.message).to_json()?);
+        let request = SendRequest::from_json(&json)?;
+
+        assert_eq!(request.0.extension("future_field"), Some(&json!({ "enabled": true })));
+        assert_eq!(request.to_json()?, json);
+
+        // Poll request
+
+        let json = extend(PollRequest::new(&secret, ChannelName::new("example").unwrap(), Some(10)).to_json()?);
+        let request = PollRequest::from_json(&json)?;
+
+        assert_eq!(request.0.extensions.len(), 1);
+        assert_eq!(request.to_json()?, json);
+
+        // Extensions are covered by the body signature
+        let mut extensions = Map::new();
+
+        extensions.insert(String::from("future_field"), json!(true));
+
+        let request = Request::extended(&secret, PollRequestBody::new(ChannelName::new("example").unwrap(), None), extensions);
+
+        assert!(request.validate()?);
+
+        let mut json = request.to_json()?;
+
+        assert_eq!(json["request"]["future_field"], json!(true));
+
+        json["request"]["future_field"] = json!(false);
+
+        assert!(!Request::<PollRequestBody>::from_json(&json)?.validate()?);
+
         Ok(())
     }
 }
@@ -0,0 +1,63 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/ack` request body.
+/// 
+/// Refer to the `AckRequest` for details.
+pub struct AckRequestBody {
+    /// Server-assigned ids of the leased messages.
+    pub ids: Vec<u64>
+}
+
+impl AckRequestBody {
+    #[inline]
+    /// Create new `POST /api/v1/ack` request body.
+    /// 
+    /// - `ids` must contain ids of the messages
+    ///   returned by the leasing poll.
+    pub fn new(ids: impl IntoIterator<Item = u64>) -> Self {
+        Self {
+            ids: ids.into_iter().collect()
+        }
+    }
+}
+
+impl AsJson for AckRequestBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "ids": self.ids
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(ids) = json.get("ids").and_then(Json::as_array) else {
+            return Err(AsJsonError::FieldNotFound("ids"));
+        };
+
+        Ok(Self {
+            ids: ids.iter()
+                .map(|id| id.as_u64().ok_or_else(|| AsJsonError::FieldValueInvalid("ids")))
+                .collect::<Result<Vec<_>, _>>()?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let request = AckRequestBody::new([1, u64::MAX]);
+
+        assert_eq!(request.to_json()?, json!({ "ids": [1, u64::MAX] }));
+        assert_eq!(AckRequestBody::from_json(&request.to_json()?)?, request);
+
+        assert!(AckRequestBody::from_json(&json!({ "ids": ["1"] })).is_err());
+
+        Ok(())
+    }
+}
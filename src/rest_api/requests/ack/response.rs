@@ -0,0 +1,59 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/ack` response body.
+/// 
+/// Refer to `AckResponse` for details.
+pub struct AckResponseBody {
+    /// Amount of messages removed from the inbox.
+    pub acknowledged: u64
+}
+
+impl AckResponseBody {
+    #[inline]
+    /// Create new `POST /api/v1/ack` response body.
+    /// 
+    /// - `acknowledged` must contain amount of
+    ///   the removed messages.
+    pub fn new(acknowledged: u64) -> Self {
+        Self {
+            acknowledged
+        }
+    }
+}
+
+impl AsJson for AckResponseBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "acknowledged": self.acknowledged
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(acknowledged) = json.get("acknowledged").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("acknowledged"));
+        };
+
+        Ok(Self {
+            acknowledged
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let response = AckResponseBody::new(2);
+
+        assert_eq!(response.to_json()?, json!({ "acknowledged": 2 }));
+        assert_eq!(AckResponseBody::from_json(&response.to_json()?)?, response);
+
+        Ok(())
+    }
+}
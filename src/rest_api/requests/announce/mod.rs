@@ -7,7 +7,7 @@ mod request;
 mod response;
 
 pub use request::AnnounceRequestBody;
-pub use response::AnnounceResponseBody;
+pub use response::{AnnounceResponseBody, AnnounceEntryResult};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -55,17 +55,53 @@ impl AnnounceRequest {
         Self(Request::new(client_secret, AnnounceRequestBody::server(server)))
     }
 
+    #[inline]
+    /// Craft new `POST /api/v1/announce` server request
+    /// with the announce timestamp.
+    /// 
+    /// Servers replace address of the known server
+    /// only if the announce is newer than their record.
+    /// 
+    /// - `client_secret` must contain reference to the
+    ///   client's secret key. It is used to sign the proof
+    ///   and connection certificate to the server.
+    /// 
+    /// - `server` must contain information about the announcing server.
+    /// 
+    /// - `announced_at` must contain UTC timestamp of the announce.
+    pub fn server_at(client_secret: &SecretKey, server: Server, announced_at: u64) -> Self {
+        Self(Request::new(client_secret, AnnounceRequestBody::server_at(server, announced_at)))
+    }
+
+    #[inline]
+    /// Craft new `POST /api/v1/announce` bulk request.
+    /// 
+    /// - `client_secret` must contain reference to the
+    ///   client's secret key. It is used to sign the proof
+    ///   and connection certificate to the server.
+    /// 
+    /// - `entries` must contain announced client and server
+    ///   entries. Server validates and indexes them independently
+    ///   and returns verdict for each of them.
+    pub fn bulk(client_secret: &SecretKey, entries: impl IntoIterator<Item = AnnounceRequestBody>) -> Self {
+        Self(Request::new(client_secret, AnnounceRequestBody::bulk(entries)))
+    }
+
     /// Validate the request.
     /// 
     /// Calls `validate()` function on the request's body
     /// and verifies that the provided connection certificate
     /// is signed for the specified server.
+    /// 
+    /// Entries of the bulk request are not validated
+    /// and must be checked one by one with the
+    /// `AnnounceRequestBody::validate_entry` method.
     pub fn validate(&self) -> Result<bool, ValidationError> {
         let mut valid_cert = true;
 
         // Validate that the client is connected to the server.
-        if let AnnounceRequestBody::Client { client, server } = &self.0.request {
-            valid_cert = client.certificate.validate(&client.public_key, &server.public_key)?;
+        if let AnnounceRequestBody::Client { .. } = &self.0.request {
+            valid_cert = self.0.request.validate_entry()?;
         }
 
         Ok(valid_cert && self.0.validate()?)
@@ -120,11 +156,52 @@ impl AnnounceResponse {
         ))
     }
 
+    /// Create successful `POST /api/v1/announce` response
+    /// to the bulk request.
+    /// 
+    /// - `server_secret` must contain reference to the
+    ///   secret key of the responding server. It is used
+    ///   to sign the response's proof.
+    /// 
+    /// - `proof_seed` must contain the same seed as used
+    ///   in the original request.
+    /// 
+    /// - `entries` must contain verdicts for each announced
+    ///   entry in the same order as in the request.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let response = AnnounceResponse::bulk(
+    ///     &SecretKey::random(),
+    ///     safe_random_u64_long(), // Here must be the original request's proof seed
+    ///     [
+    ///         AnnounceEntryResult::Accepted,
+    ///         AnnounceEntryResult::rejected(ResponseStatus::AnnounceRecordTooLarge, "Server record is too large")
+    ///     ]
+    /// );
+    /// ```
+    pub fn bulk(server_secret: &SecretKey, proof_seed: u64, entries: impl IntoIterator<Item = AnnounceEntryResult>) -> Self {
+        let proof = server_secret.create_signature(proof_seed.to_be_bytes());
+
+        Self(Response::success(
+            ResponseStatus::Success,
+            server_secret.public_key(),
+            proof,
+            AnnounceResponseBody::with_entries(entries)
+        ))
+    }
+
     #[inline]
     /// Create failed `POST /api/v1/announce` response.
     /// 
     /// - `status` must contain response's status.
     /// 
+    /// - `code` must contain machine-readable category of the error.
+    /// 
     /// - `reason` must contain error reason (message and/or description).
     /// 
     /// # Example
@@ -135,11 +212,12 @@ impl AnnounceResponse {
     /// 
     /// let response = AnnounceResponse::error(
     ///     ResponseStatus::ServerError,
+    ///     ErrorCode::Internal,
     ///     "Example error"
     /// );
     /// ```
-    pub fn error(status: ResponseStatus, reason: impl ToString) -> Self {
-        Self(Response::error(status, reason))
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
     }
 
     #[inline]
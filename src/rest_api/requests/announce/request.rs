@@ -11,11 +11,34 @@ use crate::rest_api::prelude::*;
 pub enum AnnounceRequestBody {
     Client {
         client: Client,
-        server: Server
+        server: Server,
+
+        /// Amount of servers which forwarded this announce.
+        /// 
+        /// Used to stop the announce fan-out.
+        hops: u32
     },
 
     Server {
-        server: Server
+        server: Server,
+
+        /// UTC timestamp of the announce.
+        /// 
+        /// Used to replace address of the known server only
+        /// by newer announces. Announces without timestamp
+        /// always replace the stored address.
+        announced_at: Option<u64>,
+
+        /// Amount of servers which forwarded this announce.
+        /// 
+        /// Used to stop the announce fan-out.
+        hops: u32
+    },
+
+    /// Multiple client and server entries
+    /// processed independently.
+    Bulk {
+        entries: Vec<AnnounceRequestBody>
     }
 }
 
@@ -30,7 +53,8 @@ impl AnnounceRequestBody {
     pub fn client(client: Client, server: Server) -> Self {
         Self::Client {
             client,
-            server
+            server,
+            hops: 0
         }
     }
 
@@ -41,7 +65,85 @@ impl AnnounceRequestBody {
     ///   server that is being announced.
     pub fn server(server: Server) -> Self {
         Self::Server {
-            server
+            server,
+            announced_at: None,
+            hops: 0
+        }
+    }
+
+    #[inline]
+    /// Create new `POST /api/v1/announce` server request body
+    /// with the announce timestamp.
+    /// 
+    /// - `server` must contain information about the
+    ///   server that is being announced.
+    /// 
+    /// - `announced_at` must contain UTC timestamp
+    ///   of the announce.
+    pub fn server_at(server: Server, announced_at: u64) -> Self {
+        Self::Server {
+            server,
+            announced_at: Some(announced_at),
+            hops: 0
+        }
+    }
+
+    #[inline]
+    /// Create new `POST /api/v1/announce` bulk request body.
+    /// 
+    /// - `entries` must contain client and server entries.
+    ///   Nested bulk entries are not allowed.
+    pub fn bulk(entries: impl IntoIterator<Item = Self>) -> Self {
+        Self::Bulk {
+            entries: entries.into_iter().collect()
+        }
+    }
+
+    #[inline]
+    /// Get amount of servers which forwarded this announce.
+    /// 
+    /// Bulk announces are never forwarded.
+    pub fn hops(&self) -> u32 {
+        match self {
+            Self::Client { hops, .. } |
+            Self::Server { hops, .. } => *hops,
+
+            Self::Bulk { .. } => 0
+        }
+    }
+
+    #[inline]
+    /// Set amount of servers which forwarded this announce.
+    /// 
+    /// Does nothing for bulk announces.
+    pub fn with_hops(mut self, amount: u32) -> Self {
+        if let Self::Client { hops, .. } | Self::Server { hops, .. } = &mut self {
+            *hops = amount;
+        }
+
+        self
+    }
+
+    /// Get announced entries.
+    /// 
+    /// Single entry bodies return themselves.
+    pub fn entries(&self) -> &[Self] {
+        match self {
+            Self::Bulk { entries } => entries,
+            _ => std::slice::from_ref(self)
+        }
+    }
+
+    /// Validate single announced entry.
+    /// 
+    /// Verifies that the announced client's connection
+    /// certificate is signed for the specified server.
+    /// Bulk entries are always invalid.
+    pub fn validate_entry(&self) -> Result<bool, ValidationError> {
+        match self {
+            Self::Client { client, server, .. } => Ok(client.certificate.validate(&client.public_key, &server.public_key)?),
+            Self::Server { .. } => Ok(true),
+            Self::Bulk { .. } => Ok(false)
         }
     }
 }
@@ -49,18 +151,45 @@ impl AnnounceRequestBody {
 impl AsJson for AnnounceRequestBody {
     fn to_json(&self) -> Result<Json, AsJsonError> {
         match self {
-            Self::Client { client, server } => {
-                Ok(json!({
+            Self::Client { client, server, hops } => {
+                let mut json = json!({
                     "announce": "client",
                     "client": client.to_json()?,
                     "server": server.to_json()?
-                }))
+                });
+
+                // Keep legacy format for not forwarded announces
+                if *hops > 0 {
+                    json["hops"] = Json::from(*hops);
+                }
+
+                Ok(json)
             }
 
-            Self::Server { server } => {
-                Ok(json!({
+            Self::Server { server, announced_at, hops } => {
+                let mut json = json!({
                     "announce": "server",
                     "server": server.to_json()?,
+                });
+
+                // Keep legacy format for announces without timestamp
+                if let Some(announced_at) = announced_at {
+                    json["announced_at"] = Json::from(*announced_at);
+                }
+
+                if *hops > 0 {
+                    json["hops"] = Json::from(*hops);
+                }
+
+                Ok(json)
+            }
+
+            Self::Bulk { entries } => {
+                Ok(json!({
+                    "announce": "bulk",
+                    "entries": entries.iter()
+                        .map(Self::to_json)
+                        .collect::<Result<Vec<_>, _>>()?
                 }))
             }
         }
@@ -71,6 +200,14 @@ impl AsJson for AnnounceRequestBody {
             return Err(AsJsonError::FieldNotFound("announce"));
         };
 
+        let hops = match json.get("hops") {
+            Some(hops) => hops.as_u64()
+                .and_then(|hops| u32::try_from(hops).ok())
+                .ok_or(AsJsonError::FieldValueInvalid("hops"))?,
+
+            None => 0
+        };
+
         match announce {
             "client" => {
                 Ok(Self::Client {
@@ -80,15 +217,43 @@ impl AsJson for AnnounceRequestBody {
 
                     server: json.get("server")
                         .ok_or_else(|| AsJsonError::FieldNotFound("server"))
-                        .and_then(Server::from_json)?
+                        .and_then(Server::from_json)?,
+
+                    hops
                 })
             }
 
             "server" => {
+                let announced_at = match json.get("announced_at") {
+                    Some(announced_at) => Some(announced_at.as_u64().ok_or(AsJsonError::FieldValueInvalid("announced_at"))?),
+                    None => None
+                };
+
                 Ok(Self::Server {
                     server: json.get("server")
                         .ok_or_else(|| AsJsonError::FieldNotFound("server"))
-                        .and_then(Server::from_json)?
+                        .and_then(Server::from_json)?,
+
+                    announced_at,
+                    hops
+                })
+            }
+
+            "bulk" => {
+                let Some(entries) = json.get("entries").and_then(Json::as_array) else {
+                    return Err(AsJsonError::FieldNotFound("entries"));
+                };
+
+                let entries = entries.iter()
+                    .map(Self::from_json)
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if entries.iter().any(|entry| matches!(entry, Self::Bulk { .. })) {
+                    return Err(AsJsonError::FieldValueInvalid("Field 'entries' contains nested bulk entry"));
+                }
+
+                Ok(Self::Bulk {
+                    entries
                 })
             }
 
@@ -112,6 +277,12 @@ mod tests {
         let request = AnnounceRequestBody::client(client, server);
 
         assert_eq!(AnnounceRequestBody::from_json(&request.to_json()?)?, request);
+        assert!(request.to_json()?.get("hops").is_none());
+
+        let request = request.with_hops(2);
+
+        assert_eq!(AnnounceRequestBody::from_json(&request.to_json()?)?, request);
+        assert_eq!(request.hops(), 2);
 
         Ok(())
     }
@@ -120,9 +291,32 @@ mod tests {
     fn serialize_server() -> Result<(), AsJsonError> {
         let server = get_server();
 
-        let request = AnnounceRequestBody::server(server);
+        let request = AnnounceRequestBody::server(server.clone());
+
+        assert_eq!(AnnounceRequestBody::from_json(&request.to_json()?)?, request);
+        assert!(request.to_json()?.get("announced_at").is_none());
+
+        let request = AnnounceRequestBody::server_at(server, 123);
+
+        assert_eq!(AnnounceRequestBody::from_json(&request.to_json()?)?, request);
+
+        Ok(())
+    }
+
+    #[test]
+    fn serialize_bulk() -> Result<(), AsJsonError> {
+        let request = AnnounceRequestBody::bulk([
+            AnnounceRequestBody::client(get_client(), get_server()),
+            AnnounceRequestBody::server(get_server())
+        ]);
 
         assert_eq!(AnnounceRequestBody::from_json(&request.to_json()?)?, request);
+        assert_eq!(request.entries().len(), 2);
+
+        // Nested bulk entries are not allowed
+        let nested = AnnounceRequestBody::bulk([request]);
+
+        assert!(AnnounceRequestBody::from_json(&nested.to_json()?).is_err());
 
         Ok(())
     }
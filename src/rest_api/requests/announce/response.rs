@@ -1,33 +1,135 @@
 use serde_json::{json, Value as Json};
 
 use crate::rest_api::{AsJson, AsJsonError};
+use crate::rest_api::status::ResponseStatus;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Verdict of the single bulk announce entry.
+pub enum AnnounceEntryResult {
+    /// Entry was indexed by the server.
+    Accepted,
+
+    /// Entry was rejected by the server.
+    Rejected {
+        status: ResponseStatus,
+        reason: String
+    }
+}
+
+impl AnnounceEntryResult {
+    #[inline]
+    pub fn rejected(status: ResponseStatus, reason: impl ToString) -> Self {
+        Self::Rejected {
+            status,
+            reason: reason.to_string()
+        }
+    }
+
+    #[inline]
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accepted)
+    }
+}
+
+impl AsJson for AnnounceEntryResult {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        match self {
+            Self::Accepted => Ok(json!({
+                "status": ResponseStatus::Success.to_code()
+            })),
+
+            Self::Rejected { status, reason } => Ok(json!({
+                "status": status.to_code(),
+                "reason": reason
+            }))
+        }
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(status) = json.get("status").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("status"));
+        };
+
+        let Some(status) = ResponseStatus::from_code(status) else {
+            return Err(AsJsonError::FieldValueInvalid("status"));
+        };
+
+        if status.is_success() {
+            return Ok(Self::Accepted);
+        }
+
+        let Some(reason) = json.get("reason").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("reason"));
+        };
+
+        Ok(Self::Rejected {
+            status,
+            reason: reason.to_string()
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// `POST /api/v1/announce` response body.
 /// 
 /// Refer to `AnnounceResponse` for details.
-pub struct AnnounceResponseBody;
+pub struct AnnounceResponseBody {
+    /// Verdicts of the bulk announce entries
+    /// in the same order as in the request.
+    /// 
+    /// Empty for the single entry announces.
+    pub entries: Vec<AnnounceEntryResult>
+}
 
 impl AnnounceResponseBody {
     #[inline]
-    #[allow(clippy::new_without_default)]
     /// Create announce response body.
     /// 
     /// It doesn't contain any important info
     /// so everything is filled automatically.
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    #[inline]
+    /// Create bulk announce response body.
+    pub fn with_entries(entries: impl IntoIterator<Item = AnnounceEntryResult>) -> Self {
+        Self {
+            entries: entries.into_iter().collect()
+        }
     }
 }
 
 impl AsJson for AnnounceResponseBody {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({}))
+        // Keep legacy response shape for single entry announces
+        if self.entries.is_empty() {
+            return Ok(json!({}));
+        }
+
+        Ok(json!({
+            "entries": self.entries.iter()
+                .map(AnnounceEntryResult::to_json)
+                .collect::<Result<Vec<_>, _>>()?
+        }))
     }
 
-    fn from_json(_json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
-        Ok(Self)
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(entries) = json.get("entries") else {
+            return Ok(Self::default());
+        };
+
+        let Some(entries) = entries.as_array() else {
+            return Err(AsJsonError::FieldValueInvalid("entries"));
+        };
+
+        Ok(Self {
+            entries: entries.iter()
+                .map(AnnounceEntryResult::from_json)
+                .collect::<Result<Vec<_>, _>>()?
+        })
     }
 }
 
@@ -37,7 +139,15 @@ mod tests {
 
     #[test]
     fn serialize() -> Result<(), AsJsonError> {
-        let response = AnnounceResponseBody;
+        let response = AnnounceResponseBody::new();
+
+        assert_eq!(response.to_json()?, json!({}));
+        assert_eq!(AnnounceResponseBody::from_json(&response.to_json()?)?, response);
+
+        let response = AnnounceResponseBody::with_entries([
+            AnnounceEntryResult::Accepted,
+            AnnounceEntryResult::rejected(ResponseStatus::AnnounceRecordTooLarge, "Too large")
+        ]);
 
         assert_eq!(AnnounceResponseBody::from_json(&response.to_json()?)?, response);
 
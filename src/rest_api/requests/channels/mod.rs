@@ -0,0 +1,120 @@
+use serde_json::Value as Json;
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+mod request;
+mod response;
+
+pub use request::*;
+pub use response::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/channels` request.
+/// 
+/// This request is used to list channels of the server's
+/// inbox which have pending messages for the requesting
+/// client, so it can poll only the needed ones.
+pub struct ChannelsRequest(pub Request<ChannelsRequestBody>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/channels` response.
+pub struct ChannelsResponse(pub Response<ChannelsResponseBody>);
+
+impl ChannelsRequest {
+    #[inline]
+    pub fn new(client_secret: &SecretKey) -> Self {
+        Self(Request::new(client_secret, ChannelsRequestBody::new()))
+    }
+
+    #[inline]
+    /// Validate the request.
+    /// 
+    /// Calls `validate()` function on the request's body.
+    pub fn validate(&self) -> Result<bool, ValidationError> {
+        self.0.validate()
+    }
+}
+
+impl AsJson for ChannelsRequest {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        self.0.to_json()
+    }
+
+    #[inline]
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self(Request::from_json(json)?))
+    }
+}
+
+impl ChannelsResponse {
+    pub fn success(status: ResponseStatus, server_secret: &SecretKey, proof_seed: u64, response_body: ChannelsResponseBody) -> Self {
+        let proof = server_secret.create_signature(proof_seed.to_be_bytes());
+
+        Self(Response::success(
+            status,
+            server_secret.public_key(),
+            proof,
+            response_body
+        ))
+    }
+
+    #[inline]
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
+    }
+
+    #[inline]
+    /// Validate the response.
+    /// 
+    /// Calls `validate()` function on the response's body.
+    pub fn validate(&self, proof_seed: u64) -> Result<bool, ValidationError> {
+        self.0.validate(proof_seed)
+    }
+}
+
+impl AsJson for ChannelsResponse {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        self.0.to_json()
+    }
+
+    #[inline]
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self(Response::from_json(json)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() -> Result<(), Box<dyn std::error::Error>> {
+        let client = SecretKey::random();
+        let server = SecretKey::random();
+
+        let request = ChannelsRequest::new(&client);
+
+        assert!(request.validate()?);
+
+        let request = ChannelsRequest::from_json(&request.to_json()?)?;
+
+        assert!(request.validate()?);
+
+        let response = ChannelsResponse::success(
+            ResponseStatus::Success,
+            &server,
+            request.0.proof_seed,
+            ChannelsResponseBody::new([(ChannelName::new("channel").unwrap(), 3)])
+        );
+
+        assert!(response.validate(request.0.proof_seed)?);
+        assert!(!response.validate(request.0.proof_seed + 1)?);
+
+        Ok(())
+    }
+}
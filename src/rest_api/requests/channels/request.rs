@@ -0,0 +1,46 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/channels` request body.
+/// 
+/// Refer to the `ChannelsRequest` for details.
+pub struct ChannelsRequestBody;
+
+impl ChannelsRequestBody {
+    #[inline]
+    #[allow(clippy::new_without_default)]
+    /// Create channels request body.
+    /// 
+    /// It doesn't contain any important info
+    /// so everything is filled automatically.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl AsJson for ChannelsRequestBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({}))
+    }
+
+    fn from_json(_json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let request = ChannelsRequestBody;
+
+        assert_eq!(ChannelsRequestBody::from_json(&request.to_json()?)?, request);
+
+        Ok(())
+    }
+}
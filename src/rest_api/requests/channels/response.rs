@@ -0,0 +1,88 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/channels` response body.
+/// 
+/// Refer to `ChannelsResponse` for details.
+pub struct ChannelsResponseBody {
+    /// Names of the channels with pending
+    /// messages and amount of these messages.
+    pub channels: Vec<(ChannelName, u64)>
+}
+
+impl ChannelsResponseBody {
+    #[inline]
+    /// Create new `POST /api/v1/channels` response body.
+    /// 
+    /// - `channels` must contain names of the inbox channels
+    ///   with amounts of their pending messages.
+    pub fn new(channels: impl IntoIterator<Item = (ChannelName, u64)>) -> Self {
+        Self {
+            channels: channels.into_iter().collect()
+        }
+    }
+}
+
+impl AsJson for ChannelsResponseBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "channels": self.channels.iter()
+                .map(|(channel, messages)| json!({
+                    "channel": channel.as_str(),
+                    "messages": messages
+                }))
+                .collect::<Vec<_>>()
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(channels) = json.get("channels").and_then(Json::as_array) else {
+            return Err(AsJsonError::FieldNotFound("channels"));
+        };
+
+        Ok(Self {
+            channels: channels.iter()
+                .map(|channel| {
+                    let name = channel.get("channel")
+                        .map(ChannelName::from_json)
+                        .ok_or_else(|| AsJsonError::FieldNotFound("channels.channel"))??;
+
+                    let messages = channel.get("messages")
+                        .and_then(Json::as_u64)
+                        .ok_or_else(|| AsJsonError::FieldNotFound("channels.messages"))?;
+
+                    Ok((name, messages))
+                })
+                .collect::<Result<Vec<_>, AsJsonError>>()?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let response = ChannelsResponseBody::new([]);
+
+        assert_eq!(response.to_json()?, json!({ "channels": [] }));
+        assert_eq!(ChannelsResponseBody::from_json(&response.to_json()?)?, response);
+
+        let response = ChannelsResponseBody::new([
+            (ChannelName::new("chat/alice").unwrap(), 2),
+            (ChannelName::new("status").unwrap(), 1)
+        ]);
+
+        assert_eq!(ChannelsResponseBody::from_json(&response.to_json()?)?, response);
+
+        assert!(ChannelsResponseBody::from_json(&json!({
+            "channels": [{ "channel": "status" }]
+        })).is_err());
+
+        Ok(())
+    }
+}
@@ -17,7 +17,19 @@ use crate::STANDARD_VERSION;
 /// to lookup the clients.
 pub struct ClientsResponse {
     pub standard: u64,
-    pub clients: Vec<Client>
+    pub clients: Vec<Client>,
+
+    /// Continuation token of the next page.
+    /// 
+    /// Sent only when the clients list was
+    /// requested with pagination parameters.
+    pub next: Option<String>,
+
+    /// Total amount of the clients matching the request.
+    /// 
+    /// Not sent by legacy servers and ignored
+    /// by legacy clients.
+    pub total: Option<u64>
 }
 
 impl ClientsResponse {
@@ -56,46 +68,83 @@ impl ClientsResponse {
     pub fn new(clients: impl Into<Vec<Client>>) -> Self {
         Self {
             standard: STANDARD_VERSION,
-            clients: clients.into()
+            clients: clients.into(),
+            next: None,
+            total: None
         }
     }
+
+    /// Create new `GET /api/v1/clients` response
+    /// from the page of the clients list.
+    pub fn page(page: Page<Client>) -> Self {
+        Self {
+            standard: STANDARD_VERSION,
+            clients: page.items,
+            next: page.next,
+            total: None
+        }
+    }
+
+    #[inline]
+    /// Set total amount of the matching clients.
+    pub fn with_total(mut self, total: u64) -> Self {
+        self.total = Some(total);
+
+        self
+    }
 }
 
 impl AsJson for ClientsResponse {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        match self.standard {
-            1 => Ok(json!({
-                "standard": self.standard,
-                "clients": self.clients.iter()
-                    .map(AsJson::to_json)
-                    .collect::<Result<Vec<_>, _>>()?
-            })),
-
-            _ => Err(AsJsonError::InvalidStandard(self.standard))
+        let standard = ProtocolVersion::check(self.standard)?;
+
+        let mut response = json!({
+            "standard": standard.0,
+            "clients": self.clients.iter()
+                .map(AsJson::to_json)
+                .collect::<Result<Vec<_>, _>>()?
+        });
+
+        if let Some(next) = &self.next {
+            response["next"] = Json::String(next.clone());
         }
+
+        if let Some(total) = self.total {
+            response["total"] = Json::from(total);
+        }
+
+        Ok(response)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
-        let Some(standard) = json.get("standard").and_then(Json::as_u64) else {
-            return Err(AsJsonError::FieldNotFound("standard"));
+        let standard = ProtocolVersion::parse(json)?.0;
+
+        let Some(clients) = json.get("clients").and_then(Json::as_array) else {
+            return Err(AsJsonError::FieldNotFound("clients"));
         };
 
-        match standard {
-            1 => {
-                let Some(clients) = json.get("clients").and_then(Json::as_array) else {
-                    return Err(AsJsonError::FieldNotFound("clients"));
-                };
-
-                Ok(Self {
-                    standard,
-                    clients: clients.iter()
-                        .map(AsJson::from_json)
-                        .collect::<Result<Vec<_>, _>>()?
-                })
-            }
-
-            _ => Err(AsJsonError::InvalidStandard(standard))
-        }
+        let next = match json.get("next") {
+            Some(next) => Some(next.as_str()
+                .ok_or(AsJsonError::FieldValueInvalid("next"))?
+                .to_string()),
+
+            None => None
+        };
+
+        let total = match json.get("total") {
+            Some(total) => Some(total.as_u64().ok_or(AsJsonError::FieldValueInvalid("total"))?),
+            None => None
+        };
+
+        Ok(Self {
+            standard,
+            clients: clients.iter()
+                .map(AsJson::from_json)
+                .collect::<Result<Vec<_>, _>>()?,
+
+            next,
+            total
+        })
     }
 }
 
@@ -115,6 +164,12 @@ mod tests {
 
         assert_eq!(ClientsResponse::from_json(&response.to_json()?)?, response);
 
+        let response = ClientsResponse::page(Page::new(vec![
+            get_client()
+        ], Some(String::from("token")))).with_total(10);
+
+        assert_eq!(ClientsResponse::from_json(&response.to_json()?)?, response);
+
         Ok(())
     }
 }
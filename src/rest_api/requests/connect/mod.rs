@@ -1,5 +1,9 @@
+use std::ops::Range;
+
 use serde_json::Value as Json;
 
+use k256::sha2::{Sha256, Digest};
+
 use crate::crypto::prelude::*;
 use crate::rest_api::prelude::*;
 
@@ -57,6 +61,15 @@ impl ConnectRequest {
         Self(Request::new(client_secret, ConnectRequestBody::new(client_secret, server_public, client)))
     }
 
+    #[inline]
+    /// Create new connect request with the connection
+    /// certificate restricted to the given scope.
+    pub fn scoped(client_secret: &SecretKey, server_public: PublicKey, client: ClientInfo, scope: CertificateScope) -> Self {
+        let certificate = ConnectionCertificate::new_scoped(client_secret, server_public, scope);
+
+        Self(Request::new(client_secret, ConnectRequestBody::from_certificate(client, certificate)))
+    }
+
     #[inline]
     /// Validate the request.
     /// 
@@ -70,6 +83,102 @@ impl ConnectRequest {
     pub fn validate(&self, server_public: &PublicKey) -> Result<bool, ValidationError> {
         Ok(self.0.validate()? && self.0.request.certificate.validate(&self.0.public_key, server_public)?)
     }
+
+    /// Get hash of the proof of work challenge.
+    /// 
+    /// The challenge is `sha256(public_key || proof_seed || nonce)`,
+    /// so the solution can't be reused by other clients or requests.
+    pub fn work_hash(public_key: &PublicKey, proof_seed: u64, nonce: u64) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(public_key.to_bytes())
+            .chain_update(proof_seed.to_be_bytes())
+            .chain_update(nonce.to_be_bytes())
+            .finalize()
+            .into()
+    }
+
+    /// Get amount of the leading zero bits of the hash.
+    fn leading_zeros(hash: &[u8]) -> u32 {
+        let mut zeros = 0;
+
+        for byte in hash {
+            zeros += byte.leading_zeros();
+
+            if *byte != 0 {
+                break;
+            }
+        }
+
+        zeros
+    }
+
+    /// Find nonce solving the proof of work challenge
+    /// of the given difficulty within the range.
+    /// 
+    /// Expected amount of the tried nonces is `2^difficulty`.
+    pub fn find_nonce(public_key: &PublicKey, proof_seed: u64, difficulty: u32, nonces: Range<u64>) -> Option<u64> {
+        nonces.into_iter().find(|nonce| {
+            Self::leading_zeros(&Self::work_hash(public_key, proof_seed, *nonce)) >= difficulty
+        })
+    }
+
+    /// Validate solution of the proof of work challenge.
+    /// 
+    /// The hash of the request's public key, proof seed and
+    /// nonce must have at least `difficulty` leading zero bits.
+    /// Any request is valid if the difficulty is 0.
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let client_secret = SecretKey::random();
+    /// let server_public = SecretKey::random().public_key();
+    /// 
+    /// let request = ConnectRequest::new(&client_secret, server_public, ClientInfo::thin());
+    /// 
+    /// assert!(request.validate_work(0));
+    /// 
+    /// let request = request.solve_work(&client_secret, 8, 1 << 20).unwrap();
+    /// 
+    /// assert!(request.validate_work(8));
+    /// ```
+    pub fn validate_work(&self, difficulty: u32) -> bool {
+        if difficulty == 0 {
+            return true;
+        }
+
+        let Some(nonce) = self.0.request.nonce else {
+            return false;
+        };
+
+        Self::leading_zeros(&Self::work_hash(&self.0.public_key, self.0.proof_seed, nonce)) >= difficulty
+    }
+
+    #[inline]
+    /// Set solution of the proof of work challenge
+    /// and sign the request's body again.
+    pub fn with_nonce(mut self, client_secret: &SecretKey, nonce: u64) -> Self {
+        self.0.request.nonce = Some(nonce);
+        self.0.sign_body(client_secret);
+
+        self
+    }
+
+    /// Solve proof of work challenge of the given difficulty
+    /// trying at most `max_iterations` nonces.
+    /// 
+    /// Return `None` if no solution was found. The request
+    /// is returned as is if the difficulty is 0.
+    pub fn solve_work(self, client_secret: &SecretKey, difficulty: u32, max_iterations: u64) -> Option<Self> {
+        if difficulty == 0 {
+            return Some(self);
+        }
+
+        let nonce = Self::find_nonce(&self.0.public_key, self.0.proof_seed, difficulty, 0..max_iterations)?;
+
+        Some(self.with_nonce(client_secret, nonce))
+    }
 }
 
 impl AsJson for ConnectRequest {
@@ -125,6 +234,8 @@ impl ConnectResponse {
     /// 
     /// - `status` must contain response's status.
     /// 
+    /// - `code` must contain machine-readable category of the error.
+    /// 
     /// - `reason` must contain error reason (message and/or description).
     /// 
     /// # Example
@@ -135,11 +246,12 @@ impl ConnectResponse {
     /// 
     /// let response = ConnectResponse::error(
     ///     ResponseStatus::ServerError,
+    ///     ErrorCode::Internal,
     ///     "Example error"
     /// );
     /// ```
-    pub fn error(status: ResponseStatus, reason: impl ToString) -> Self {
-        Self(Response::error(status, reason))
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
     }
 
     #[inline]
@@ -162,3 +274,46 @@ impl AsJson for ConnectResponse {
         Ok(Self(Response::from_json(json)?))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn work() -> Result<(), ValidationError> {
+        let secret = SecretKey::random();
+        let server = SecretKey::random().public_key();
+
+        let request = ConnectRequest::new(&secret, server.clone(), ClientInfo::thin());
+
+        // No challenge
+        assert!(request.validate_work(0));
+        assert!(!request.validate_work(1));
+        assert_eq!(request.clone().solve_work(&secret, 0, 0), Some(request.clone()));
+
+        // Solved challenge
+        let solved = request.clone().solve_work(&secret, 8, 1 << 20).unwrap();
+
+        assert!(solved.validate(&server)?);
+        assert!(solved.validate_work(8));
+
+        // Nonce is covered by the body signature
+        let mut copied = ConnectRequest::new(&secret, server.clone(), ClientInfo::thin());
+
+        copied.0.request.nonce = solved.0.request.nonce;
+
+        assert!(!copied.validate(&server)?);
+
+        let copied = copied.with_nonce(&secret, solved.0.request.nonce.unwrap());
+
+        assert!(copied.validate(&server)?);
+
+        assert_eq!(ConnectRequest::leading_zeros(&[0, 0b0001_0000, 0xFF]), 11);
+        assert_eq!(ConnectRequest::leading_zeros(&[0; 4]), 32);
+
+        // Iterations are bounded
+        assert!(request.solve_work(&secret, 64, 16).is_none());
+
+        Ok(())
+    }
+}
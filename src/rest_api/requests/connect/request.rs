@@ -10,7 +10,15 @@ use crate::rest_api::prelude::*;
 /// Refer to the `ConnectRequest` for details.
 pub struct ConnectRequestBody {
     pub certificate: ConnectionCertificate,
-    pub client: ClientInfo
+    pub client: ClientInfo,
+
+    /// Alias registered for the client on the server.
+    pub alias: Option<ClientAlias>,
+
+    /// Solution of the server's proof of work challenge.
+    /// 
+    /// Refer to `ConnectRequest::validate_work`.
+    pub nonce: Option<u64>
 }
 
 impl ConnectRequestBody {
@@ -40,7 +48,9 @@ impl ConnectRequestBody {
     pub fn new(client_secret: &SecretKey, server_public: PublicKey, client: ClientInfo) -> Self {
         Self {
             certificate: ConnectionCertificate::new(client_secret, server_public),
-            client
+            client,
+            alias: None,
+            nonce: None
         }
     }
 
@@ -77,17 +87,51 @@ impl ConnectRequestBody {
     pub fn from_certificate(client: ClientInfo, certificate: ConnectionCertificate) -> Self {
         Self {
             client,
-            certificate
+            certificate,
+            alias: None,
+            nonce: None
         }
     }
+
+    #[inline]
+    /// Register the given alias for the client.
+    /// 
+    /// Server will reject the connection if the alias
+    /// is already registered by another client.
+    pub fn with_alias(mut self, alias: ClientAlias) -> Self {
+        self.alias = Some(alias);
+
+        self
+    }
+
+    #[inline]
+    /// Set solution of the server's proof of work challenge.
+    /// 
+    /// Refer to `ConnectRequest::solve_work`.
+    pub fn with_nonce(mut self, nonce: u64) -> Self {
+        self.nonce = Some(nonce);
+
+        self
+    }
 }
 
 impl AsJson for ConnectRequestBody {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({
+        let mut json = json!({
             "certificate": self.certificate.to_json()?,
             "client": self.client.to_json()?
-        }))
+        });
+
+        // Keep legacy format for requests without alias
+        if let Some(alias) = &self.alias {
+            json["alias"] = alias.to_json()?;
+        }
+
+        if let Some(nonce) = self.nonce {
+            json["nonce"] = Json::from(nonce);
+        }
+
+        Ok(json)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
@@ -101,7 +145,16 @@ impl AsJson for ConnectRequestBody {
 
         Ok(Self {
             certificate: ConnectionCertificate::from_json(certificate)?,
-            client: ClientInfo::from_json(client)?
+            client: ClientInfo::from_json(client)?,
+
+            alias: json.get("alias")
+                .map(ClientAlias::from_json)
+                .transpose()?,
+
+            nonce: match json.get("nonce") {
+                Some(nonce) => Some(nonce.as_u64().ok_or(AsJsonError::FieldValueInvalid("nonce"))?),
+                None => None
+            }
         })
     }
 }
@@ -117,6 +170,16 @@ mod tests {
 
         let request = ConnectRequestBody::new(&secret, public, ClientInfo::thin());
 
+        assert_eq!(ConnectRequestBody::from_json(&request.to_json()?)?, request);
+        assert!(request.to_json()?.get("alias").is_none());
+
+        let request = request.with_alias(ClientAlias::new("alice").unwrap());
+
+        assert_eq!(ConnectRequestBody::from_json(&request.to_json()?)?, request);
+        assert!(request.to_json()?.get("nonce").is_none());
+
+        let request = request.with_nonce(u64::MAX);
+
         assert_eq!(ConnectRequestBody::from_json(&request.to_json()?)?, request);
 
         Ok(())
@@ -36,6 +36,16 @@ impl DisconnectRequest {
         Self(Request::new(client_secret, DisconnectRequestBody::new()))
     }
 
+    #[inline]
+    /// Craft new `POST /api/v1/disconnect` client request
+    /// which asks the server to remove all the messages
+    /// queued for the client.
+    /// 
+    /// Refer to `DisconnectRequest::new` for details.
+    pub fn purge_inbox(client_secret: &SecretKey) -> Self {
+        Self(Request::new(client_secret, DisconnectRequestBody::new().with_purge_inbox(true)))
+    }
+
     #[inline]
     /// Validate the request.
     /// 
@@ -100,6 +110,8 @@ impl DisconnectResponse {
     /// 
     /// - `status` must contain response's status.
     /// 
+    /// - `code` must contain machine-readable category of the error.
+    /// 
     /// - `reason` must contain error reason (message and/or description).
     /// 
     /// # Example
@@ -110,11 +122,12 @@ impl DisconnectResponse {
     /// 
     /// let response = DisconnectResponse::error(
     ///     ResponseStatus::ServerError,
+    ///     ErrorCode::Internal,
     ///     "Example error"
     /// );
     /// ```
-    pub fn error(status: ResponseStatus, reason: impl ToString) -> Self {
-        Self(Response::error(status, reason))
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
     }
 
     #[inline]
@@ -2,33 +2,61 @@ use serde_json::{json, Value as Json};
 
 use crate::rest_api::prelude::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(clippy::large_enum_variant)]
 /// `POST /api/v1/disconnect` request body.
 /// 
 /// Refer to the `DisconnectRequest` for details.
-pub struct DisconnectRequestBody;
+pub struct DisconnectRequestBody {
+    /// Remove all the messages queued
+    /// for the client in the server's inbox.
+    pub purge_inbox: bool
+}
 
 impl DisconnectRequestBody {
     #[inline]
-    #[allow(clippy::new_without_default)]
     /// Create disconnect request body.
     /// 
     /// It doesn't contain any important info
     /// so everything is filled automatically.
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    #[inline]
+    /// Ask the server to remove all the
+    /// client's queued messages.
+    pub fn with_purge_inbox(self, purge_inbox: bool) -> Self {
+        Self {
+            purge_inbox
+        }
     }
 }
 
 impl AsJson for DisconnectRequestBody {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({}))
+        // Keep legacy request shape for plain disconnects
+        if !self.purge_inbox {
+            return Ok(json!({}));
+        }
+
+        Ok(json!({
+            "purge_inbox": true
+        }))
     }
 
-    fn from_json(_json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
-        Ok(Self)
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let purge_inbox = match json.get("purge_inbox") {
+            Some(purge_inbox) => purge_inbox.as_bool()
+                .ok_or(AsJsonError::FieldValueInvalid("purge_inbox"))?,
+
+            None => false
+        };
+
+        Ok(Self {
+            purge_inbox
+        })
     }
 }
 
@@ -38,10 +66,18 @@ mod tests {
 
     #[test]
     fn serialize() -> Result<(), AsJsonError> {
-        let request = DisconnectRequestBody;
+        let request = DisconnectRequestBody::new();
 
+        assert_eq!(request.to_json()?, json!({}));
         assert_eq!(DisconnectRequestBody::from_json(&request.to_json()?)?, request);
 
+        let request = DisconnectRequestBody::new()
+            .with_purge_inbox(true);
+
+        assert_eq!(DisconnectRequestBody::from_json(&request.to_json()?)?, request);
+
+        assert!(DisconnectRequestBody::from_json(&json!({ "purge_inbox": "yes" })).is_err());
+
         Ok(())
     }
 }
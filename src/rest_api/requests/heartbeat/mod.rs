@@ -0,0 +1,132 @@
+use serde_json::Value as Json;
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+mod request;
+mod response;
+
+pub use request::HeartbeatRequestBody;
+pub use response::HeartbeatResponseBody;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/heartbeat` request.
+/// 
+/// This request is sent to the `POST /api/v1/heartbeat` to
+/// tell a server that you're still connected to it when
+/// you don't send any other requests for a long time.
+pub struct HeartbeatRequest(pub Request<HeartbeatRequestBody>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/heartbeat` response.
+pub struct HeartbeatResponse(pub Response<HeartbeatResponseBody>);
+
+impl HeartbeatRequest {
+    #[inline]
+    /// Craft new `POST /api/v1/heartbeat` client request.
+    /// 
+    /// - `client_secret` must contain reference to the
+    ///   client's secret key. It is used to sign the proof.
+    pub fn new(client_secret: &SecretKey) -> Self {
+        Self(Request::new(client_secret, HeartbeatRequestBody::new()))
+    }
+
+    #[inline]
+    /// Validate the request.
+    /// 
+    /// Verifies that the request's proof
+    /// is signed by the client.
+    pub fn validate(&self) -> Result<bool, ValidationError> {
+        self.0.validate()
+    }
+}
+
+impl AsJson for HeartbeatRequest {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        self.0.to_json()
+    }
+
+    #[inline]
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self(Request::from_json(json)?))
+    }
+}
+
+impl HeartbeatResponse {
+    /// Create successful `POST /api/v1/heartbeat` response.
+    /// 
+    /// - `status` must contain status code of the response
+    ///   (`100 Success` in most cases).
+    /// 
+    /// - `server_secret` must contain reference to the
+    ///   secret key of the responding server. It is used
+    ///   to sign the response's proof.
+    /// 
+    /// - `proof_seed` must contain the same seed as used
+    ///   in the original request.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let server_secret = SecretKey::random();
+    /// let request = HeartbeatRequest::new(&SecretKey::random());
+    /// 
+    /// assert!(request.validate().unwrap());
+    /// 
+    /// let response = HeartbeatResponse::success(
+    ///     ResponseStatus::Success,
+    ///     &server_secret,
+    ///     request.0.proof_seed
+    /// );
+    /// 
+    /// assert!(response.validate(request.0.proof_seed).unwrap());
+    /// ```
+    pub fn success(status: ResponseStatus, server_secret: &SecretKey, proof_seed: u64) -> Self {
+        let proof = server_secret.create_signature(proof_seed.to_be_bytes());
+
+        Self(Response::success(
+            status,
+            server_secret.public_key(),
+            proof,
+            HeartbeatResponseBody::new()
+        ))
+    }
+
+    #[inline]
+    /// Create failed `POST /api/v1/heartbeat` response.
+    /// 
+    /// - `status` must contain response's status.
+    /// 
+    /// - `code` must contain machine-readable category of the error.
+    /// 
+    /// - `reason` must contain error reason (message and/or description).
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
+    }
+
+    #[inline]
+    /// Validate the response.
+    /// 
+    /// Verifies that the proof seed is signed by the server.
+    pub fn validate(&self, proof_seed: u64) -> Result<bool, ValidationError> {
+        self.0.validate(proof_seed)
+    }
+}
+
+impl AsJson for HeartbeatResponse {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        self.0.to_json()
+    }
+
+    #[inline]
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self(Response::from_json(json)?))
+    }
+}
@@ -0,0 +1,47 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::{AsJson, AsJsonError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/heartbeat` request body.
+/// 
+/// Refer to the `HeartbeatRequest` for details.
+pub struct HeartbeatRequestBody;
+
+impl HeartbeatRequestBody {
+    #[inline]
+    #[allow(clippy::new_without_default)]
+    /// Create heartbeat request body.
+    /// 
+    /// It doesn't contain any important info
+    /// so everything is filled automatically.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl AsJson for HeartbeatRequestBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({}))
+    }
+
+    fn from_json(_json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let request = HeartbeatRequestBody;
+
+        assert_eq!(request.to_json()?, json!({}));
+        assert_eq!(HeartbeatRequestBody::from_json(&request.to_json()?)?, request);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::{AsJson, AsJsonError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/heartbeat` response body.
+/// 
+/// Refer to `HeartbeatResponse` for details.
+pub struct HeartbeatResponseBody;
+
+impl HeartbeatResponseBody {
+    #[inline]
+    #[allow(clippy::new_without_default)]
+    /// Create heartbeat response body.
+    /// 
+    /// It doesn't contain any important info
+    /// so everything is filled automatically.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl AsJson for HeartbeatResponseBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({}))
+    }
+
+    fn from_json(_json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let response = HeartbeatResponseBody;
+
+        assert_eq!(response.to_json()?, json!({}));
+        assert_eq!(HeartbeatResponseBody::from_json(&response.to_json()?)?, response);
+
+        Ok(())
+    }
+}
@@ -3,8 +3,6 @@ use serde_json::{json, Value as Json};
 use crate::crypto::prelude::*;
 use crate::rest_api::prelude::*;
 
-use crate::STANDARD_VERSION;
-
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// `GET /api/v1/info` response.
@@ -20,6 +18,34 @@ pub struct InfoResponse {
     pub proof_seed: u64,
     pub proof_sign: Vec<u8>,
 
+    /// Range of the standard versions supported by the server.
+    /// 
+    /// Not advertised by the legacy servers, which
+    /// support only the response's `standard`.
+    pub standards: Option<ProtocolVersions>,
+
+    /// Maximal lifetime of the connection certificates
+    /// accepted by the server, in seconds.
+    /// 
+    /// Clients should connect again to renew their
+    /// certificates before it passes.
+    pub certificate_lifetime: Option<u64>,
+
+    /// Proof of work difficulty required from the
+    /// new clients connecting to the server.
+    /// 
+    /// Refer to `ConnectRequest::validate_work`.
+    pub connect_difficulty: Option<u32>,
+
+    /// Features and limits of the server.
+    /// 
+    /// Not advertised by the legacy servers.
+    pub capabilities: Option<ServerCapabilities>,
+
+    /// Signature of the proof seed and the
+    /// server's capabilities.
+    pub capabilities_sign: Option<Vec<u8>>
+
     // TODO: stats
 }
 
@@ -44,15 +70,98 @@ impl InfoResponse {
         let proof_sign = server_secret.create_signature(proof_seed.to_be_bytes());
 
         Self {
-            standard: STANDARD_VERSION,
+            standard: ProtocolVersion::CURRENT.0,
             public_key: server_secret.public_key(),
             proof_seed,
-            proof_sign
+            proof_sign,
+            standards: Some(ProtocolVersions::SUPPORTED),
+            certificate_lifetime: None,
+            connect_difficulty: None,
+            capabilities: None,
+            capabilities_sign: None
         }
     }
 
+    #[inline]
+    /// Advertise maximal lifetime of the connection
+    /// certificates accepted by the server, in seconds.
+    pub fn with_certificate_lifetime(mut self, lifetime: u64) -> Self {
+        self.certificate_lifetime = Some(lifetime);
+
+        self
+    }
+
+    #[inline]
+    /// Advertise proof of work difficulty required
+    /// from the new clients, in leading zero bits.
+    /// 
+    /// Clients are not challenged if it's 0.
+    pub fn with_connect_difficulty(mut self, difficulty: u32) -> Self {
+        self.connect_difficulty = Some(difficulty).filter(|difficulty| *difficulty > 0);
+
+        self
+    }
+
+    #[inline]
+    /// Get range of the standard versions
+    /// supported by the server.
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let mut response = InfoResponse::new(&SecretKey::random());
+    /// 
+    /// assert_eq!(response.supported_versions(), ProtocolVersions::SUPPORTED);
+    /// 
+    /// // Legacy servers support only their own standard
+    /// response.standards = None;
+    /// 
+    /// assert_eq!(response.supported_versions(), ProtocolVersions::single(response.standard));
+    /// ```
+    pub fn supported_versions(&self) -> ProtocolVersions {
+        self.standards.unwrap_or_else(|| ProtocolVersions::single(self.standard))
+    }
+
+    /// Advertise features and limits of the server.
+    /// 
+    /// Capabilities are signed together with the
+    /// response's proof seed by the server's secret key.
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let secret = SecretKey::random();
+    /// 
+    /// let capabilities = ServerCapabilities::new()
+    ///     .with_capability(ServerCapabilities::SEND_BATCH, true);
+    /// 
+    /// let response = InfoResponse::new(&secret)
+    ///     .with_capabilities(&secret, capabilities);
+    /// 
+    /// assert!(response.validate().unwrap());
+    /// ```
+    pub fn with_capabilities(mut self, server_secret: &SecretKey, capabilities: ServerCapabilities) -> Self {
+        self.capabilities_sign = Some(server_secret.create_signature(Self::capabilities_proof(self.proof_seed, &capabilities)));
+        self.capabilities = Some(capabilities);
+
+        self
+    }
+
+    fn capabilities_proof(proof_seed: u64, capabilities: &ServerCapabilities) -> Vec<u8> {
+        let mut proof = proof_seed.to_be_bytes().to_vec();
+
+        proof.extend(capabilities.to_bytes());
+
+        proof
+    }
+
     /// Validate response proof.
     /// 
+    /// Advertised capabilities must be signed
+    /// by the server as well.
+    /// 
     /// # Example
     /// 
     /// ```rust
@@ -68,68 +177,134 @@ impl InfoResponse {
             return Err(ValidationError::InvalidSeed);
         }
 
+        if !self.public_key.verify_signature(self.proof_seed.to_be_bytes(), &self.proof_sign)? {
+            return Ok(false);
+        }
+
+        let Some(capabilities) = &self.capabilities else {
+            return Ok(true);
+        };
+
+        let Some(capabilities_sign) = &self.capabilities_sign else {
+            return Ok(false);
+        };
+
         Ok(self.public_key.verify_signature(
-            self.proof_seed.to_be_bytes(),
-            &self.proof_sign
+            Self::capabilities_proof(self.proof_seed, capabilities),
+            capabilities_sign
         )?)
     }
 }
 
 impl AsJson for InfoResponse {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        match self.standard {
-            1 => Ok(json!({
-                "standard": self.standard,
-                "server": {
-                    "public_key": self.public_key.to_base64(),
-                },
-                "proof": {
-                    "seed": self.proof_seed,
-                    "sign": base64_encode(&self.proof_sign)
-                }
-            })),
-
-            _ => Err(AsJsonError::InvalidStandard(self.standard))
+        let standard = ProtocolVersion::check(self.standard)?;
+
+        let mut value = json!({
+            "standard": standard.0,
+            "server": {
+                "public_key": self.public_key.to_base64(),
+            },
+            "proof": {
+                "seed": self.proof_seed,
+                "sign": base64_encode(&self.proof_sign)
+            }
+        });
+
+        // Legacy servers don't advertise supported standards
+        if let Some(standards) = &self.standards {
+            value["server"]["standards"] = standards.to_json()?;
+        }
+
+        // Keep legacy response shape for servers without certificates lifetime
+        if let Some(lifetime) = self.certificate_lifetime {
+            value["server"]["certificate_lifetime"] = Json::from(lifetime);
         }
+
+        // Servers without the challenge keep legacy response shape as well
+        if let Some(difficulty) = self.connect_difficulty {
+            value["server"]["connect_difficulty"] = Json::from(difficulty);
+        }
+
+        // Legacy servers don't advertise capabilities
+        if let Some(capabilities) = &self.capabilities {
+            let capabilities = capabilities.to_json()?;
+
+            value["server"]["capabilities"] = capabilities["capabilities"].clone();
+            value["server"]["limits"] = capabilities["limits"].clone();
+        }
+
+        if let Some(capabilities_sign) = &self.capabilities_sign {
+            value["proof"]["capabilities_sign"] = Json::from(base64_encode(capabilities_sign));
+        }
+
+        Ok(value)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
-        let Some(standard) = json.get("standard").and_then(Json::as_u64) else {
-            return Err(AsJsonError::FieldNotFound("standard"));
+        let standard = ProtocolVersion::parse(json)?.0;
+
+        let Some(server) = json.get("server") else {
+            return Err(AsJsonError::FieldNotFound("server"));
         };
 
-        match standard {
-            1 => {
-                let Some(server) = json.get("server") else {
-                    return Err(AsJsonError::FieldNotFound("server"));
-                };
-
-                let Some(public_key) = server.get("public_key").and_then(Json::as_str) else {
-                    return Err(AsJsonError::FieldNotFound("server.public_key"));
-                };
-
-                let Some(proof) = json.get("proof") else {
-                    return Err(AsJsonError::FieldNotFound("proof"));
-                };
-
-                let Some(proof_seed) = proof.get("seed").and_then(Json::as_u64) else {
-                    return Err(AsJsonError::FieldNotFound("proof.seed"));
-                };
-
-                let Some(proof_sign) = proof.get("sign").and_then(Json::as_str) else {
-                    return Err(AsJsonError::FieldNotFound("proof.sign"));
-                };
-
-                Ok(Self {
-                    standard,
-                    public_key: PublicKey::from_base64(public_key)?,
-                    proof_seed,
-                    proof_sign: base64_decode(proof_sign)?
-                })
-            }
+        let Some(public_key) = server.get("public_key").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("server.public_key"));
+        };
 
-            _ => Err(AsJsonError::InvalidStandard(standard))
-        }
+        let Some(proof) = json.get("proof") else {
+            return Err(AsJsonError::FieldNotFound("proof"));
+        };
+
+        let Some(proof_seed) = proof.get("seed").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("proof.seed"));
+        };
+
+        let Some(proof_sign) = proof.get("sign").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("proof.sign"));
+        };
+
+        let certificate_lifetime = match server.get("certificate_lifetime") {
+            Some(lifetime) => Some(lifetime.as_u64().ok_or(AsJsonError::FieldValueInvalid("server.certificate_lifetime"))?),
+            None => None
+        };
+
+        let connect_difficulty = match server.get("connect_difficulty") {
+            Some(difficulty) => Some(
+                difficulty.as_u64()
+                    .and_then(|difficulty| u32::try_from(difficulty).ok())
+                    .ok_or(AsJsonError::FieldValueInvalid("server.connect_difficulty"))?
+            ),
+
+            None => None
+        };
+
+        let standards = match server.get("standards") {
+            Some(standards) => Some(ProtocolVersions::from_json(standards)?),
+            None => None
+        };
+
+        let capabilities = match (server.get("capabilities"), server.get("limits")) {
+            (None, None) => None,
+            _ => Some(ServerCapabilities::from_json(server)?)
+        };
+
+        let capabilities_sign = match proof.get("capabilities_sign") {
+            Some(sign) => Some(base64_decode(sign.as_str().ok_or(AsJsonError::FieldValueInvalid("proof.capabilities_sign"))?)?),
+            None => None
+        };
+
+        Ok(Self {
+            standard,
+            public_key: PublicKey::from_base64(public_key)?,
+            proof_seed,
+            proof_sign: base64_decode(proof_sign)?,
+            standards,
+            certificate_lifetime,
+            connect_difficulty,
+            capabilities,
+            capabilities_sign
+        })
     }
 }
 
@@ -142,6 +317,74 @@ mod tests {
         let response = InfoResponse::new(&SecretKey::random());
 
         assert_eq!(InfoResponse::from_json(&response.to_json()?)?, response);
+        assert!(response.to_json()?["server"].get("certificate_lifetime").is_none());
+
+        let response = response.with_certificate_lifetime(3600);
+
+        assert_eq!(InfoResponse::from_json(&response.to_json()?)?, response);
+
+        // Difficulty is not advertised if clients are not challenged
+        let response = response.with_connect_difficulty(0);
+
+        assert!(response.to_json()?["server"].get("connect_difficulty").is_none());
+
+        let response = response.with_connect_difficulty(12);
+
+        assert_eq!(response.to_json()?["server"]["connect_difficulty"], 12);
+        assert_eq!(InfoResponse::from_json(&response.to_json()?)?, response);
+
+        Ok(())
+    }
+
+    #[test]
+    fn standards() -> Result<(), AsJsonError> {
+        let mut response = InfoResponse::new(&SecretKey::random());
+
+        assert_eq!(InfoResponse::from_json(&response.to_json()?)?.supported_versions(), ProtocolVersions::SUPPORTED);
+
+        // Keep legacy response shape
+        response.standards = None;
+
+        let json = response.to_json()?;
+
+        assert!(json["server"].get("standards").is_none());
+        assert_eq!(InfoResponse::from_json(&json)?.supported_versions(), ProtocolVersions::single(response.standard));
+
+        Ok(())
+    }
+
+    #[test]
+    fn capabilities() -> Result<(), Box<dyn std::error::Error>> {
+        let secret = SecretKey::random();
+
+        let capabilities = ServerCapabilities::new()
+            .with_capability(ServerCapabilities::SEND_BATCH, true)
+            .with_limit(ServerCapabilities::MAX_BATCH_SIZE, 64);
+
+        let response = InfoResponse::new(&secret)
+            .with_capabilities(&secret, capabilities.clone());
+
+        let mut json = response.to_json()?;
+
+        assert_eq!(InfoResponse::from_json(&json)?, response);
+        assert!(response.validate()?);
+
+        // Unknown capabilities are covered by the signature
+        json["server"]["capabilities"]["future_capability"] = Json::Bool(true);
+
+        assert!(!InfoResponse::from_json(&json)?.validate()?);
+
+        let response = InfoResponse::new(&secret)
+            .with_capabilities(&secret, capabilities.with_capability("future_capability", true));
+
+        assert!(InfoResponse::from_json(&response.to_json()?)?.validate()?);
+
+        // Capabilities must be signed
+        let mut response = InfoResponse::new(&secret);
+
+        response.capabilities = Some(ServerCapabilities::new().with_capability(ServerCapabilities::SEND_BATCH, true));
+
+        assert!(!response.validate()?);
 
         Ok(())
     }
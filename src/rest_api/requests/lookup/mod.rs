@@ -56,6 +56,25 @@ impl LookupRequest {
         Self(Request::new(client_secret, LookupRequestBody::new(lookup_client_public, lookup_client_type)))
     }
 
+    #[inline]
+    /// Craft new `POST /api/v1/lookup` request
+    /// searching the client by its alias.
+    /// 
+    /// Aliases are registered by clients on their servers,
+    /// so the request must be sent to the server to which
+    /// the needed client is connected.
+    /// 
+    /// - `client_secret` must contain reference to the client's
+    ///   secret key. It will be used to sign the request.
+    /// 
+    /// - `alias` must contain alias of the client we want to find.
+    /// 
+    /// - `client_type` should contain filter of the
+    ///   client type we want to find, or `None` if any.
+    pub fn alias(client_secret: &SecretKey, alias: ClientAlias, client_type: Option<ClientType>) -> Self {
+        Self(Request::new(client_secret, LookupRequestBody::alias(alias, client_type)))
+    }
+
     #[inline]
     /// Validate the request.
     /// 
@@ -127,6 +146,8 @@ impl LookupResponse {
     /// 
     /// - `status` must contain response's status.
     /// 
+    /// - `code` must contain machine-readable category of the error.
+    /// 
     /// - `reason` must contain error reason (message and/or description).
     /// 
     /// # Example
@@ -137,11 +158,12 @@ impl LookupResponse {
     /// 
     /// let response = LookupResponse::error(
     ///     ResponseStatus::ServerError,
+    ///     ErrorCode::Internal,
     ///     "Example error"
     /// );
     /// ```
-    pub fn error(status: ResponseStatus, reason: impl ToString) -> Self {
-        Self(Response::error(status, reason))
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
     }
 
     #[inline]
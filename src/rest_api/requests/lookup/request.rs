@@ -10,9 +10,19 @@ use crate::rest_api::prelude::*;
 /// `POST /api/v1/lookup` request body.
 /// 
 /// Refer to `LookupRequest` for details.
-pub struct LookupRequestBody {
-    pub public_key: PublicKey,
-    pub client_type: Option<ClientType>
+pub enum LookupRequestBody {
+    /// Find client by its public key.
+    PublicKey {
+        public_key: PublicKey,
+        client_type: Option<ClientType>
+    },
+
+    /// Find local client of the server
+    /// by its registered alias.
+    Alias {
+        alias: ClientAlias,
+        client_type: Option<ClientType>
+    }
 }
 
 impl LookupRequestBody {
@@ -37,33 +47,86 @@ impl LookupRequestBody {
     /// let request_body = LookupRequestBody::new(client_public, None);
     /// ```
     pub fn new(client_public: PublicKey, client_type: Option<ClientType>) -> Self {
-        Self {
+        Self::PublicKey {
             public_key: client_public,
             client_type
         }
     }
+
+    #[inline]
+    /// Create new `POST /api/v1/lookup` request body
+    /// searching the client by its alias.
+    /// 
+    /// - `alias` must contain alias registered by
+    ///   the client on the requested server.
+    /// 
+    /// - `client_type` is an optional filter field
+    ///   of the needed client type.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let alias = ClientAlias::new("alice").unwrap();
+    /// 
+    /// let request_body = LookupRequestBody::alias(alias, None);
+    /// ```
+    pub fn alias(alias: ClientAlias, client_type: Option<ClientType>) -> Self {
+        Self::Alias {
+            alias,
+            client_type
+        }
+    }
+
+    #[inline]
+    /// Get filter of the needed client type.
+    pub fn client_type(&self) -> Option<ClientType> {
+        match self {
+            Self::PublicKey { client_type, .. } |
+            Self::Alias { client_type, .. } => *client_type
+        }
+    }
 }
 
 impl AsJson for LookupRequestBody {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({
-            "public_key": self.public_key.to_base64(),
-            "type": self.client_type.map(|value| value.to_string())
-        }))
+        let client_type = self.client_type().map(|value| value.to_string());
+
+        match self {
+            Self::PublicKey { public_key, .. } => Ok(json!({
+                "public_key": public_key.to_base64(),
+                "type": client_type
+            })),
+
+            Self::Alias { alias, .. } => Ok(json!({
+                "alias": alias.to_json()?,
+                "type": client_type
+            }))
+        }
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
-        Ok(Self {
+        let client_type = json.get("type")
+            .and_then(Json::as_str)
+            .map(ClientType::from_str)
+            .transpose()
+            .map_err(|_| AsJsonError::FieldValueInvalid("Invalid client type value"))?;
+
+        if let Some(alias) = json.get("alias") {
+            return Ok(Self::Alias {
+                alias: ClientAlias::from_json(alias)?,
+                client_type
+            });
+        }
+
+        Ok(Self::PublicKey {
             public_key: json.get("public_key")
                 .and_then(Json::as_str)
                 .ok_or_else(|| AsJsonError::FieldNotFound("public_key"))
                 .map(PublicKey::from_base64)??,
 
-            client_type: json.get("type")
-                .and_then(Json::as_str)
-                .map(ClientType::from_str)
-                .transpose()
-                .map_err(|_| AsJsonError::FieldValueInvalid("Invalid client type value"))?
+            client_type
         })
     }
 }
@@ -84,6 +147,11 @@ mod tests {
 
         assert_eq!(LookupRequestBody::from_json(&request.to_json()?)?, request);
 
+        let request = LookupRequestBody::alias(ClientAlias::new("alice").unwrap(), Some(ClientType::Thick));
+
+        assert_eq!(LookupRequestBody::from_json(&request.to_json()?)?, request);
+        assert_eq!(request.client_type(), Some(ClientType::Thick));
+
         Ok(())
     }
 }
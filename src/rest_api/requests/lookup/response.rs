@@ -29,8 +29,10 @@ pub enum LookupResponseBody {
     /// which supposed to give lookup hints - it's recommended
     /// to return list of all known servers here to reduce
     /// future possible network requests (`/api/v1/servers`).
+    /// 
+    /// Servers are sorted by their scores in descending order.
     Hint {
-        servers: Vec<Server>
+        servers: Vec<ServerHint>
     }
 }
 
@@ -41,8 +43,8 @@ impl LookupResponseBody {
     /// - `client` must contain information about the client.
     /// 
     /// - `available` must indicate whether the client is available.
-    ///   This flag is managed by the server and doesn't have any
-    ///   standard description.
+    ///   Shipped routers set it when the client made a request
+    ///   to the server recently. There's no standard description.
     /// 
     /// # Example
     /// 
@@ -80,8 +82,8 @@ impl LookupResponseBody {
     ///   this `client` is connected.
     /// 
     /// - `available` must indicate whether the client is available.
-    ///   This flag is managed by the server and doesn't have any
-    ///   standard description.
+    ///   Shipped routers set it when the client was announced
+    ///   recently. There's no standard description.
     /// 
     /// # Example
     /// 
@@ -133,6 +135,43 @@ impl LookupResponseBody {
     pub fn hint(servers: impl Into<Vec<Server>>) -> Self {
         Self::Hint {
             servers: servers.into()
+                .into_iter()
+                .map(ServerHint::from)
+                .collect()
+        }
+    }
+
+    /// Craft `disposition: hint` lookup response
+    /// with scored servers.
+    /// 
+    /// - `servers` should contain list of servers which
+    ///   can know the needed client and their scores.
+    ///   They are sorted by the scores in descending order.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let response_body = LookupResponseBody::scored_hint(vec![
+    ///     ServerHint::new(Server::new(SecretKey::random().public_key(), "example1.org"), 10),
+    ///     ServerHint::new(Server::new(SecretKey::random().public_key(), "example2.org"), 30)
+    /// ]);
+    /// 
+    /// let LookupResponseBody::Hint { servers } = response_body else {
+    ///     unreachable!();
+    /// };
+    /// 
+    /// assert_eq!(servers[0].score, 30);
+    /// ```
+    pub fn scored_hint(servers: impl Into<Vec<ServerHint>>) -> Self {
+        let mut servers = servers.into();
+
+        servers.sort_by_key(|hint| std::cmp::Reverse(hint.score));
+
+        Self::Hint {
+            servers
         }
     }
 }
@@ -208,13 +247,23 @@ impl AsJson for LookupResponseBody {
             }
 
             "hint" => {
-                Ok(Self::Hint {
-                    servers: json.get("servers")
-                        .and_then(Json::as_array)
-                        .ok_or_else(|| AsJsonError::FieldNotFound("servers"))?
-                        .iter()
+                let Some(servers) = json.get("servers") else {
+                    return Err(AsJsonError::FieldNotFound("servers"));
+                };
+
+                // Some servers return single hint object
+                let servers = match servers {
+                    Json::Array(servers) => servers.iter()
                         .map(AsJson::from_json)
-                        .collect::<Result<Vec<_>, _>>()?
+                        .collect::<Result<Vec<_>, _>>()?,
+
+                    Json::Object(_) => vec![ServerHint::from_json(servers)?],
+
+                    _ => return Err(AsJsonError::FieldValueInvalid("servers"))
+                };
+
+                Ok(Self::Hint {
+                    servers
                 })
             }
 
@@ -263,6 +312,23 @@ mod tests {
 
         assert_eq!(LookupResponseBody::from_json(&response.to_json()?)?, response);
 
+        let response = LookupResponseBody::scored_hint(vec![
+            ServerHint::new(get_server(), 1),
+            ServerHint::new(get_server(), 2)
+        ]);
+
+        assert_eq!(LookupResponseBody::from_json(&response.to_json()?)?, response);
+
+        // Single hint object
+        let server = get_server();
+
+        let response = LookupResponseBody::from_json(&json!({
+            "disposition": "hint",
+            "servers": server.to_json()?
+        }))?;
+
+        assert_eq!(response, LookupResponseBody::hint([server]));
+
         Ok(())
     }
 }
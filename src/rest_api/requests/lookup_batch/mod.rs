@@ -0,0 +1,94 @@
+use serde_json::Value as Json;
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+mod request;
+mod response;
+
+pub use request::LookupBatchRequestBody;
+pub use response::{LookupBatchResponseBody, LookupBatchResult};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/lookup_batch` request.
+/// 
+/// This request searches multiple clients in the chosen
+/// server's routing table using a single HTTP request.
+/// Every entry is resolved by the server the same way as
+/// the `POST /api/v1/lookup` request with a public key.
+/// 
+/// Servers reject batches with more than
+/// `ServerParams::max_lookup_batch_size` entries
+/// with the `BatchTooLarge` status.
+pub struct LookupBatchRequest(pub Request<LookupBatchRequestBody>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/lookup_batch` response.
+pub struct LookupBatchResponse(pub Response<LookupBatchResponseBody>);
+
+impl LookupBatchRequest {
+    #[inline]
+    pub fn new(client_secret: &SecretKey, entries: impl Into<Vec<(PublicKey, Option<ClientType>)>>) -> Self {
+        Self(Request::new(client_secret, LookupBatchRequestBody::new(entries)))
+    }
+
+    #[inline]
+    /// Validate the request.
+    /// 
+    /// Calls `validate()` function on the request's body.
+    pub fn validate(&self) -> Result<bool, ValidationError> {
+        self.0.validate()
+    }
+}
+
+impl AsJson for LookupBatchRequest {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        self.0.to_json()
+    }
+
+    #[inline]
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self(Request::from_json(json)?))
+    }
+}
+
+impl LookupBatchResponse {
+    pub fn success(status: ResponseStatus, server_secret: &SecretKey, proof_seed: u64, body: LookupBatchResponseBody) -> Self {
+        let proof = server_secret.create_signature(proof_seed.to_be_bytes());
+
+        Self(Response::success(
+            status,
+            server_secret.public_key(),
+            proof,
+            body
+        ))
+    }
+
+    #[inline]
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
+    }
+
+    #[inline]
+    /// Validate the response.
+    /// 
+    /// Calls `validate()` function on the response's body.
+    pub fn validate(&self, proof_seed: u64) -> Result<bool, ValidationError> {
+        self.0.validate(proof_seed)
+    }
+}
+
+impl AsJson for LookupBatchResponse {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        self.0.to_json()
+    }
+
+    #[inline]
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self(Response::from_json(json)?))
+    }
+}
@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use serde_json::{json, Value as Json};
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/lookup_batch` request body.
+/// 
+/// Every entry has the same format as the
+/// `POST /api/v1/lookup` request body:
+/// 
+/// ```json
+/// {
+///     "clients": [
+///         {
+///             "public_key": "<base64>",
+///             "type": "thin"
+///         }
+///     ]
+/// }
+/// ```
+/// 
+/// Refer to `LookupBatchRequest` for details.
+pub struct LookupBatchRequestBody {
+    /// Public keys of the needed clients
+    /// and optional filters of their types.
+    pub entries: Vec<(PublicKey, Option<ClientType>)>
+}
+
+impl LookupBatchRequestBody {
+    #[inline]
+    pub fn new(entries: impl Into<Vec<(PublicKey, Option<ClientType>)>>) -> Self {
+        Self {
+            entries: entries.into()
+        }
+    }
+}
+
+impl AsJson for LookupBatchRequestBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let entries = self.entries.iter()
+            .map(|(public_key, client_type)| json!({
+                "public_key": public_key.to_base64(),
+                "type": client_type.map(|value| value.to_string())
+            }))
+            .collect::<Vec<_>>();
+
+        Ok(json!({
+            "clients": entries
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(entries) = json.get("clients") else {
+            return Err(AsJsonError::FieldNotFound("clients"));
+        };
+
+        let Some(entries) = entries.as_array() else {
+            return Err(AsJsonError::FieldValueInvalid("clients"));
+        };
+
+        let entries = entries.iter()
+            .map(|entry| {
+                let public_key = entry.get("public_key")
+                    .and_then(Json::as_str)
+                    .ok_or_else(|| AsJsonError::FieldNotFound("clients[].public_key"))
+                    .map(PublicKey::from_base64)??;
+
+                let client_type = entry.get("type")
+                    .and_then(Json::as_str)
+                    .map(ClientType::from_str)
+                    .transpose()
+                    .map_err(|_| AsJsonError::FieldValueInvalid("Invalid client type value"))?;
+
+                Ok((public_key, client_type))
+            })
+            .collect::<Result<Vec<_>, AsJsonError>>()?;
+
+        Ok(Self {
+            entries
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let request = LookupBatchRequestBody::new([
+            (SecretKey::random().public_key(), Some(ClientType::Thin)),
+            (SecretKey::random().public_key(), None)
+        ]);
+
+        assert_eq!(LookupBatchRequestBody::from_json(&request.to_json()?)?, request);
+
+        let request = LookupBatchRequestBody::default();
+
+        assert_eq!(request.to_json()?, json!({ "clients": [] }));
+        assert_eq!(LookupBatchRequestBody::from_json(&request.to_json()?)?, request);
+
+        assert!(LookupBatchRequestBody::from_json(&json!({ "clients": [{}] })).is_err());
+
+        Ok(())
+    }
+}
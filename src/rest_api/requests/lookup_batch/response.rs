@@ -0,0 +1,132 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Result of the single `POST /api/v1/lookup_batch` entry.
+pub enum LookupBatchResult {
+    /// Server knows about the client or servers
+    /// which could know about it.
+    /// 
+    /// Refer to `LookupResponseBody`.
+    Found(LookupResponseBody),
+
+    /// There's no info about the client
+    /// and no hints for it.
+    NotFound
+}
+
+impl LookupBatchResult {
+    #[inline]
+    pub fn is_found(&self) -> bool {
+        matches!(self, Self::Found(_))
+    }
+}
+
+impl AsJson for LookupBatchResult {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        match self {
+            Self::Found(body) => body.to_json(),
+
+            Self::NotFound => Ok(json!({
+                "disposition": "not_found"
+            }))
+        }
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        if json.get("disposition").and_then(Json::as_str) == Some("not_found") {
+            return Ok(Self::NotFound);
+        }
+
+        Ok(Self::Found(LookupResponseBody::from_json(json)?))
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/lookup_batch` response body.
+/// 
+/// Contains results of the request's entries
+/// in the same order. Every result has the same
+/// format as the `POST /api/v1/lookup` response
+/// body, or `not_found` disposition:
+/// 
+/// ```json
+/// {
+///     "results": [
+///         { "disposition": "local", "client": { ... }, "available": true },
+///         { "disposition": "not_found" }
+///     ]
+/// }
+/// ```
+/// 
+/// Refer to `LookupBatchResponse` for details.
+pub struct LookupBatchResponseBody {
+    pub results: Vec<LookupBatchResult>
+}
+
+impl LookupBatchResponseBody {
+    #[inline]
+    pub fn new(results: impl Into<Vec<LookupBatchResult>>) -> Self {
+        Self {
+            results: results.into()
+        }
+    }
+}
+
+impl AsJson for LookupBatchResponseBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let results = self.results.iter()
+            .map(LookupBatchResult::to_json)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(json!({
+            "results": results
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(results) = json.get("results") else {
+            return Err(AsJsonError::FieldNotFound("results"));
+        };
+
+        let Some(results) = results.as_array() else {
+            return Err(AsJsonError::FieldValueInvalid("results"));
+        };
+
+        Ok(Self {
+            results: results.iter()
+                .map(LookupBatchResult::from_json)
+                .collect::<Result<Vec<_>, _>>()?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rest_api::types::client::tests::get_client;
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let response = LookupBatchResponseBody::new([
+            LookupBatchResult::Found(LookupResponseBody::local(get_client(), true)),
+            LookupBatchResult::Found(LookupResponseBody::remote(get_client(), get_server(), false)),
+            LookupBatchResult::Found(LookupResponseBody::hint([get_server()])),
+            LookupBatchResult::NotFound
+        ]);
+
+        assert_eq!(LookupBatchResponseBody::from_json(&response.to_json()?)?, response);
+
+        assert_eq!(LookupBatchResult::NotFound.to_json()?, json!({ "disposition": "not_found" }));
+
+        assert!(LookupBatchResponseBody::from_json(&json!({ "results": [{ "disposition": "unknown" }] })).is_err());
+
+        Ok(())
+    }
+}
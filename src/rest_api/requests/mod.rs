@@ -23,22 +23,63 @@
 //!  └────────┘                └────────┘ 
 //! ```
 
+use super::version::{ProtocolVersion, VersionedRequest};
+
 mod clients;
 mod servers;
 mod info;
+mod stats;
 mod connect;
 mod disconnect;
+mod heartbeat;
 mod announce;
 mod lookup;
+mod lookup_batch;
 mod send;
+mod send_batch;
 mod poll;
+mod channels;
+mod ack;
 
 pub use clients::*;
 pub use servers::*;
 pub use info::*;
+pub use stats::*;
 pub use connect::*;
 pub use disconnect::*;
+pub use heartbeat::*;
 pub use announce::*;
 pub use lookup::*;
+pub use lookup_batch::*;
 pub use send::*;
+pub use send_batch::*;
 pub use poll::*;
+pub use channels::*;
+pub use ack::*;
+
+macro_rules! impl_versioned_requests {
+    ($($request:ty),*) => {
+        $(
+            impl VersionedRequest for $request {
+                #[inline]
+                fn with_standard(self, standard: ProtocolVersion) -> Self {
+                    Self(self.0.with_standard(standard))
+                }
+            }
+        )*
+    };
+}
+
+impl_versioned_requests!(
+    ConnectRequest,
+    DisconnectRequest,
+    HeartbeatRequest,
+    AnnounceRequest,
+    LookupRequest,
+    LookupBatchRequest,
+    SendRequest,
+    SendBatchRequest,
+    PollRequest,
+    ChannelsRequest,
+    AckRequest
+);
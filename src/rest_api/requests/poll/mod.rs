@@ -27,10 +27,31 @@ pub struct PollResponse(pub Response<PollResponseBody>);
 
 impl PollRequest {
     #[inline]
-    pub fn new(client_secret: &SecretKey, channel: impl ToString, limit: Option<u64>) -> Self {
+    pub fn new(client_secret: &SecretKey, channel: impl Into<ChannelName>, limit: Option<u64>) -> Self {
         Self(Request::new(client_secret, PollRequestBody::new(channel, limit)))
     }
 
+    #[inline]
+    /// Create new poll request for messages
+    /// sealed to the client.
+    pub fn sealed(client_secret: &SecretKey, channel: impl Into<ChannelName>, limit: Option<u64>) -> Self {
+        Self(Request::new(client_secret, PollRequestBody::sealed(channel, limit)))
+    }
+
+    #[inline]
+    /// Create new poll request which reads messages
+    /// without removing them from the inbox.
+    pub fn peek(client_secret: &SecretKey, channel: impl Into<ChannelName>, limit: Option<u64>) -> Self {
+        Self(Request::new(client_secret, PollRequestBody::peek(channel, limit)))
+    }
+
+    #[inline]
+    /// Create new poll request for messages of all
+    /// the channels matched by the pattern.
+    pub fn wildcard(client_secret: &SecretKey, pattern: impl Into<ChannelName>, limit: Option<u64>) -> Self {
+        Self(Request::new(client_secret, PollRequestBody::wildcard(pattern, limit)))
+    }
+
     #[inline]
     /// Validate the request.
     /// 
@@ -65,8 +86,8 @@ impl PollResponse {
     }
 
     #[inline]
-    pub fn error(status: ResponseStatus, reason: impl ToString) -> Self {
-        Self(Response::error(status, reason))
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
     }
 
     #[inline]
@@ -1,5 +1,9 @@
+use std::ops::Range;
+use std::time::Duration;
+
 use serde_json::{json, Value as Json};
 
+use crate::crypto::prelude::*;
 use crate::rest_api::prelude::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -8,8 +12,50 @@ use crate::rest_api::prelude::*;
 /// 
 /// Refer to `PollRequest` for details.
 pub struct PollRequestBody {
-    pub channel: String,
-    pub limit: Option<u64>
+    pub channel: ChannelName,
+    pub limit: Option<u64>,
+
+    /// Request messages sealed to the client
+    /// instead of the plain ones.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sealed: bool,
+
+    /// Read messages without removing
+    /// them from the inbox.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub peek: bool,
+
+    /// Treat the channel name as a pattern.
+    /// 
+    /// Refer to `ChannelRule::parse` for details.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wildcard: bool,
+
+    /// Poll only messages sent by the client
+    /// with the given public key.
+    /// 
+    /// Messages of other senders stay in the inbox.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sender: Option<PublicKey>,
+
+    /// Poll only messages received at
+    /// or after the given unix timestamp.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub after: Option<u64>,
+
+    /// Poll only messages received
+    /// before the given unix timestamp.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub before: Option<u64>,
+
+    /// Wait up to the given amount of milliseconds
+    /// for new messages if there are none yet.
+    /// 
+    /// Servers bound the waiting time by their own
+    /// maximum, or respond immediately if they don't
+    /// support long polls.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wait_ms: Option<u64>
 }
 
 impl PollRequestBody {
@@ -28,30 +74,209 @@ impl PollRequestBody {
     /// use hyperborealib::rest_api::prelude::*;
     /// 
     /// // Read exactly one message from "example channel" channel
-    /// let request_body = PollRequestBody::new("example channel", Some(1));
+    /// let request_body = PollRequestBody::new(ChannelName::new("example channel").unwrap(), Some(1));
+    /// ```
+    pub fn new(channel: impl Into<ChannelName>, limit: Option<u64>) -> Self {
+        Self {
+            channel: channel.into(),
+            limit,
+            sealed: false,
+            peek: false,
+            wildcard: false,
+            sender: None,
+            after: None,
+            before: None,
+            wait_ms: None
+        }
+    }
+
+    #[inline]
+    /// Create new `POST /api/v1/poll` request body
+    /// which requests messages sealed to the client.
+    /// 
+    /// Refer to `SealedMessageInfo` for details.
+    pub fn sealed(channel: impl Into<ChannelName>, limit: Option<u64>) -> Self {
+        Self {
+            sealed: true,
+            ..Self::new(channel, limit)
+        }
+    }
+
+    #[inline]
+    /// Create new `POST /api/v1/poll` request body
+    /// which reads messages without removing them
+    /// from the inbox.
+    /// 
+    /// Inbox backends may not support peeking,
+    /// in which case the server returns an error.
+    pub fn peek(channel: impl Into<ChannelName>, limit: Option<u64>) -> Self {
+        Self {
+            peek: true,
+            ..Self::new(channel, limit)
+        }
+    }
+
+    #[inline]
+    /// Create new `POST /api/v1/poll` request body
+    /// which polls messages from all the channels
+    /// matched by the pattern.
+    /// 
+    /// Polled messages are ordered by their receiving
+    /// time, and their `channel` field contains name
+    /// of the channel they were sent to.
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// // Poll messages from all the "chat/" channels
+    /// let request_body = PollRequestBody::wildcard(ChannelName::new("chat/*").unwrap(), None);
+    /// 
+    /// assert_eq!(request_body.channel_rule(), ChannelRule::Prefix(String::from("chat/")));
     /// ```
-    pub fn new(channel: impl ToString, limit: Option<u64>) -> Self {
+    pub fn wildcard(pattern: impl Into<ChannelName>, limit: Option<u64>) -> Self {
         Self {
-            channel: channel.to_string(),
-            limit
+            wildcard: true,
+            ..Self::new(pattern, limit)
+        }
+    }
+
+    /// Get rule matching the channels
+    /// the messages should be polled from.
+    pub fn channel_rule(&self) -> ChannelRule {
+        if self.wildcard {
+            ChannelRule::parse(&self.channel)
+        } else {
+            ChannelRule::Exact(self.channel.clone())
+        }
+    }
+
+    #[inline]
+    /// Poll only messages sent by the client
+    /// with the given public key.
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let sender = SecretKey::random().public_key();
+    /// 
+    /// let request_body = PollRequestBody::new(ChannelName::new("example channel").unwrap(), None)
+    ///     .with_sender(sender);
+    /// ```
+    pub fn with_sender(mut self, sender: PublicKey) -> Self {
+        self.sender = Some(sender);
+
+        self
+    }
+
+    #[inline]
+    /// Poll only messages received at
+    /// or after the given unix timestamp.
+    /// 
+    /// Older messages stay in the inbox.
+    pub fn with_after(mut self, timestamp: u64) -> Self {
+        self.after = Some(timestamp);
+
+        self
+    }
+
+    #[inline]
+    /// Poll only messages received
+    /// before the given unix timestamp.
+    /// 
+    /// Newer messages stay in the inbox.
+    pub fn with_before(mut self, timestamp: u64) -> Self {
+        self.before = Some(timestamp);
+
+        self
+    }
+
+    #[inline]
+    /// Wait up to the given time for new messages
+    /// if there are none yet.
+    /// 
+    /// ```rust
+    /// use std::time::Duration;
+    /// 
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let request_body = PollRequestBody::new(ChannelName::new("example channel").unwrap(), None)
+    ///     .with_wait(Duration::from_secs(10));
+    /// 
+    /// assert_eq!(request_body.wait_ms, Some(10000));
+    /// ```
+    pub fn with_wait(mut self, wait: Duration) -> Self {
+        self.wait_ms = Some(wait.as_millis() as u64);
+
+        self
+    }
+
+    /// Get range of the polled messages'
+    /// receiving timestamps.
+    /// 
+    /// Return `None` if messages are not filtered.
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let request_body = PollRequestBody::new(ChannelName::new("example channel").unwrap(), None)
+    ///     .with_after(1000);
+    /// 
+    /// assert_eq!(request_body.range(), Some(1000..u64::MAX));
+    /// ```
+    pub fn range(&self) -> Option<Range<u64>> {
+        if self.after.is_none() && self.before.is_none() {
+            return None;
         }
+
+        Some(self.after.unwrap_or(0)..self.before.unwrap_or(u64::MAX))
     }
 }
 
 impl AsJson for PollRequestBody {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({
-            "channel": self.channel,
+        let mut json = json!({
+            "channel": self.channel.to_json()?,
             "limit": self.limit
-        }))
+        });
+
+        // Keep the original format for the plain poll
+        if self.sealed {
+            json["sealed"] = Json::Bool(true);
+        }
+
+        if self.peek {
+            json["peek"] = Json::Bool(true);
+        }
+
+        if self.wildcard {
+            json["wildcard"] = Json::Bool(true);
+        }
+
+        if let Some(sender) = &self.sender {
+            json["sender"] = Json::String(sender.to_base64());
+        }
+
+        if let Some(after) = self.after {
+            json["after"] = Json::from(after);
+        }
+
+        if let Some(before) = self.before {
+            json["before"] = Json::from(before);
+        }
+
+        if let Some(wait_ms) = self.wait_ms {
+            json["wait_ms"] = Json::from(wait_ms);
+        }
+
+        Ok(json)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
         Ok(Self {
             channel: json.get("channel")
-                .and_then(Json::as_str)
-                .map(String::from)
-                .ok_or_else(|| AsJsonError::FieldNotFound("channel"))?,
+                .map(ChannelName::from_json)
+                .ok_or_else(|| AsJsonError::FieldNotFound("channel"))??,
 
             limit: json.get("limit")
                 .ok_or_else(|| AsJsonError::FieldNotFound("channel"))
@@ -63,7 +288,46 @@ impl AsJson for PollRequestBody {
                             .map(Some)
                             .ok_or_else(|| AsJsonError::FieldValueInvalid("channel"))
                     }
-                })?
+                })?,
+
+            sealed: json.get("sealed")
+                .and_then(Json::as_bool)
+                .unwrap_or(false),
+
+            peek: json.get("peek")
+                .and_then(Json::as_bool)
+                .unwrap_or(false),
+
+            wildcard: json.get("wildcard")
+                .and_then(Json::as_bool)
+                .unwrap_or(false),
+
+            sender: match json.get("sender") {
+                Some(Json::Null) | None => None,
+
+                Some(sender) => sender.as_str()
+                    .and_then(|sender| PublicKey::from_base64(sender).ok())
+                    .map(Some)
+                    .ok_or_else(|| AsJsonError::FieldValueInvalid("sender"))?
+            },
+
+            after: match json.get("after") {
+                Some(Json::Null) | None => None,
+
+                Some(after) => Some(after.as_u64().ok_or(AsJsonError::FieldValueInvalid("after"))?)
+            },
+
+            before: match json.get("before") {
+                Some(Json::Null) | None => None,
+
+                Some(before) => Some(before.as_u64().ok_or(AsJsonError::FieldValueInvalid("before"))?)
+            },
+
+            wait_ms: match json.get("wait_ms") {
+                Some(Json::Null) | None => None,
+
+                Some(wait_ms) => Some(wait_ms.as_u64().ok_or(AsJsonError::FieldValueInvalid("wait_ms"))?)
+            }
         })
     }
 }
@@ -76,14 +340,112 @@ mod tests {
     fn serialize() -> Result<(), AsJsonError> {
         use crate::rest_api::prelude::*;
 
-        let request = PollRequestBody::new("Hello, World!", None);
+        let request = PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), None);
+
+        assert_eq!(PollRequestBody::from_json(&request.to_json()?)?, request);
+
+        let request = PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), Some(5));
+
+        assert_eq!(PollRequestBody::from_json(&request.to_json()?)?, request);
+
+        let request = PollRequestBody::sealed(ChannelName::new("Hello, World!").unwrap(), Some(5));
+
+        assert_eq!(PollRequestBody::from_json(&request.to_json()?)?, request);
+
+        let request = PollRequestBody::peek(ChannelName::new("Hello, World!").unwrap(), Some(5));
+
+        assert_eq!(request.to_json()?["peek"], Json::Bool(true));
+        assert_eq!(PollRequestBody::from_json(&request.to_json()?)?, request);
+
+        // Old peers don't send the field
+        let mut json = request.to_json()?;
+
+        json.as_object_mut().unwrap().remove("peek");
+
+        assert!(!PollRequestBody::from_json(&json)?.peek);
+        assert!(PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), None).to_json()?.get("peek").is_none());
+
+        let sender = SecretKey::random().public_key();
+
+        let request = PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), Some(5))
+            .with_sender(sender.clone());
+
+        assert_eq!(request.to_json()?["sender"], Json::String(sender.to_base64()));
+        assert_eq!(PollRequestBody::from_json(&request.to_json()?)?, request);
+
+        assert!(PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), None).to_json()?.get("sender").is_none());
+
+        let request = PollRequestBody::wildcard(ChannelName::new("chat/*").unwrap(), None);
+
+        assert_eq!(request.to_json()?["wildcard"], Json::Bool(true));
+        assert_eq!(PollRequestBody::from_json(&request.to_json()?)?, request);
+
+        // Exact channels are not parsed as patterns
+        let request = PollRequestBody::new(ChannelName::new("chat/*").unwrap(), None);
+
+        assert!(request.to_json()?.get("wildcard").is_none());
+        assert_eq!(request.channel_rule(), ChannelRule::Exact(ChannelName::new("chat/*").unwrap()));
+
+        // Invalid public keys are rejected
+        let mut json = request.to_json()?;
+
+        json["sender"] = Json::String(String::from("invalid"));
+
+        assert!(PollRequestBody::from_json(&json).is_err());
 
+        let request = PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), Some(5))
+            .with_after(100)
+            .with_before(200);
+
+        assert_eq!(request.to_json()?["after"], Json::from(100));
+        assert_eq!(request.to_json()?["before"], Json::from(200));
+        assert_eq!(request.range(), Some(100..200));
         assert_eq!(PollRequestBody::from_json(&request.to_json()?)?, request);
 
-        let request = PollRequestBody::new("Hello, World!", Some(5));
+        let request = PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), None)
+            .with_before(200);
+
+        assert!(request.to_json()?.get("after").is_none());
+        assert_eq!(request.range(), Some(0..200));
+        assert_eq!(PollRequestBody::from_json(&request.to_json()?)?, request);
+
+        // Old peers don't send the fields
+        assert_eq!(PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), None).range(), None);
+
+        let mut json = request.to_json()?;
 
+        json["after"] = Json::from("yesterday");
+
+        assert!(PollRequestBody::from_json(&json).is_err());
+
+        let request = PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), None)
+            .with_wait(Duration::from_millis(1500));
+
+        assert_eq!(request.to_json()?["wait_ms"], Json::from(1500));
         assert_eq!(PollRequestBody::from_json(&request.to_json()?)?, request);
 
+        // Old peers don't send the field
+        assert!(PollRequestBody::new(ChannelName::new("Hello, World!").unwrap(), None).to_json()?.get("wait_ms").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::crypto::prelude::*;
+
+        let request = PollRequest::peek(&SecretKey::random(), ChannelName::new("Hello, World!").unwrap(), Some(5));
+
+        assert!(request.validate()?);
+
+        let request = PollRequest::from_json(&request.to_json()?)?;
+
+        assert!(request.validate()?);
+        assert!(request.0.request.peek);
+        assert!(request.0.request.channel.validate().is_ok());
+
+        assert!(PollRequestBody::peek(ChannelName::new_unchecked(""), None).channel.validate().is_err());
+
         Ok(())
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde_json::{json, Value as Json};
 
 use crate::rest_api::prelude::*;
@@ -9,7 +11,23 @@ use crate::rest_api::prelude::*;
 /// Refer to `PollResponse` for details.
 pub struct PollResponseBody {
     pub messages: Vec<MessageInfo>,
-    pub remaining: u64
+
+    /// Messages sealed to the client.
+    /// 
+    /// Returned instead of `messages` when
+    /// sealed poll was requested.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sealed_messages: Vec<SealedMessageInfo>,
+
+    pub remaining: u64,
+
+    /// Amounts of remaining messages of the
+    /// channels matched by the wildcard poll.
+    /// 
+    /// Channels without remaining messages are
+    /// not listed. Empty for the exact channel polls.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub remaining_by_channel: BTreeMap<String, u64>
 }
 
 impl PollResponseBody {
@@ -23,20 +41,59 @@ impl PollResponseBody {
     pub fn new(messages: impl Into<Vec<MessageInfo>>, remaining: u64) -> Self {
         Self {
             messages: messages.into(),
-            remaining
+            sealed_messages: vec![],
+            remaining,
+            remaining_by_channel: BTreeMap::new()
+        }
+    }
+
+    #[inline]
+    /// Create new `POST /api/v1/poll` response body
+    /// with messages sealed to the client.
+    /// 
+    /// - `sealed_messages` must be a vector of sealed messages info
+    ///   stored in the server inbox for the requester client.
+    /// 
+    /// - `remaining` must be a number of remaining inbox messages.
+    pub fn sealed(sealed_messages: impl Into<Vec<SealedMessageInfo>>, remaining: u64) -> Self {
+        Self {
+            messages: vec![],
+            sealed_messages: sealed_messages.into(),
+            remaining,
+            remaining_by_channel: BTreeMap::new()
+        }
+    }
+
+    #[inline]
+    /// Set amounts of remaining messages
+    /// of the polled channels.
+    pub fn with_remaining_by_channel(self, remaining_by_channel: impl IntoIterator<Item = (String, u64)>) -> Self {
+        Self {
+            remaining_by_channel: remaining_by_channel.into_iter().collect(),
+            ..self
         }
     }
 }
 
 impl AsJson for PollResponseBody {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({
+        let mut json = json!({
             "messages": self.messages.iter()
                 .map(MessageInfo::to_json)
                 .collect::<Result<Vec<_>, _>>()?,
 
             "remaining": self.remaining
-        }))
+        });
+
+        if !self.sealed_messages.is_empty() {
+            json["sealed_messages"] = self.sealed_messages.to_json()?;
+        }
+
+        if !self.remaining_by_channel.is_empty() {
+            json["remaining_by_channel"] = json!(self.remaining_by_channel);
+        }
+
+        Ok(json)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
@@ -50,9 +107,28 @@ impl AsJson for PollResponseBody {
                 })
                 .ok_or_else(|| AsJsonError::FieldNotFound("messages"))??,
 
+            sealed_messages: json.get("sealed_messages")
+                .map(Vec::<SealedMessageInfo>::from_json)
+                .transpose()?
+                .unwrap_or_default(),
+
             remaining: json.get("remaining")
                 .and_then(Json::as_u64)
-                .ok_or_else(|| AsJsonError::FieldNotFound("remaining"))?
+                .ok_or_else(|| AsJsonError::FieldNotFound("remaining"))?,
+
+            remaining_by_channel: match json.get("remaining_by_channel") {
+                Some(channels) => channels.as_object()
+                    .ok_or_else(|| AsJsonError::FieldValueInvalid("remaining_by_channel"))?
+                    .iter()
+                    .map(|(channel, remaining)| {
+                        remaining.as_u64()
+                            .map(|remaining| (channel.clone(), remaining))
+                            .ok_or_else(|| AsJsonError::FieldValueInvalid("remaining_by_channel"))
+                    })
+                    .collect::<Result<BTreeMap<_, _>, _>>()?,
+
+                None => BTreeMap::new()
+            }
         })
     }
 }
@@ -81,12 +157,27 @@ mod tests {
         let encoding = MessageEncoding::from_str("base64").unwrap();
         let message = Message::new("content", "sign", encoding);
 
-        let info = MessageInfo::now(sender, "Hello, World!", message);
+        let info = MessageInfo::now(sender, ChannelName::new("Hello, World!").unwrap(), message);
 
         let response = PollResponseBody::new(vec![info], 100);
 
+        assert!(response.to_json()?.get("remaining_by_channel").is_none());
+        assert_eq!(PollResponseBody::from_json(&response.to_json()?)?, response);
+
+        let response = response.with_remaining_by_channel([
+            (String::from("chat/alice"), 60),
+            (String::from("chat/bob"), 40)
+        ]);
+
+        assert_eq!(response.to_json()?["remaining_by_channel"], json!({ "chat/alice": 60, "chat/bob": 40 }));
         assert_eq!(PollResponseBody::from_json(&response.to_json()?)?, response);
 
+        let mut json = response.to_json()?;
+
+        json["remaining_by_channel"]["chat/bob"] = json!("40");
+
+        assert!(PollResponseBody::from_json(&json).is_err());
+
         Ok(())
     }
 }
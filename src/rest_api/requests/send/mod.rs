@@ -28,16 +28,20 @@ pub struct SendResponse(pub Response<SendResponseBody>);
 
 impl SendRequest {
     #[inline]
-    pub fn new(client_secret: &SecretKey, sender: Sender, receiver_public: PublicKey, channel: impl ToString, message: Message) -> Self {
+    pub fn new(client_secret: &SecretKey, sender: Sender, receiver_public: PublicKey, channel: impl Into<ChannelName>, message: Message) -> Self {
         Self(Request::new(client_secret, SendRequestBody::new(sender, receiver_public, channel, message)))
     }
 
     #[inline]
     /// Validate the request.
     /// 
-    /// Calls `validate()` function on the request's body.
+    /// Calls `validate()` function on the request's body
+    /// and verifies that the message's sender is the
+    /// request's author connected to the sender's server.
     pub fn validate(&self) -> Result<bool, ValidationError> {
-        self.0.validate()
+        let sender = &self.0.request.sender;
+
+        Ok(self.0.validate()? && sender.client.public_key == self.0.public_key && sender.validate()?)
     }
 }
 
@@ -54,20 +58,20 @@ impl AsJson for SendRequest {
 }
 
 impl SendResponse {
-    pub fn success(status: ResponseStatus, server_secret: &SecretKey, proof_seed: u64) -> Self {
+    pub fn success(status: ResponseStatus, server_secret: &SecretKey, proof_seed: u64, body: SendResponseBody) -> Self {
         let proof = server_secret.create_signature(proof_seed.to_be_bytes());
 
         Self(Response::success(
             status,
             server_secret.public_key(),
             proof,
-            SendResponseBody::new()
+            body
         ))
     }
 
     #[inline]
-    pub fn error(status: ResponseStatus, reason: impl ToString) -> Self {
-        Self(Response::error(status, reason))
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
     }
 
     #[inline]
@@ -12,32 +12,54 @@ use crate::rest_api::prelude::*;
 pub struct SendRequestBody {
     pub sender: Sender,
     pub receiver_public: PublicKey,
-    pub channel: String,
-    pub message: Message
+    pub channel: ChannelName,
+    pub message: Message,
+
+    /// Request delivery receipts from the receiver's server.
+    /// 
+    /// Refer to `DeliveryReceipt` for details.
+    pub receipt: bool
 }
 
 impl SendRequestBody {
     #[inline]
-    pub fn new(sender: Sender, receiver_public: PublicKey, channel: impl ToString, message: Message) -> Self {
+    pub fn new(sender: Sender, receiver_public: PublicKey, channel: impl Into<ChannelName>, message: Message) -> Self {
         Self {
             sender,
             receiver_public,
-            channel: channel.to_string(),
-            message
+            channel: channel.into(),
+            message,
+            receipt: false
+        }
+    }
+
+    #[inline]
+    /// Request delivery receipts of the message.
+    pub fn with_receipt(self, receipt: bool) -> Self {
+        Self {
+            receipt,
+            ..self
         }
     }
 }
 
 impl AsJson for SendRequestBody {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({
+        let mut request = json!({
             "sender": self.sender.to_json()?,
             "receiver": {
                 "public_key": self.receiver_public.to_base64()
             },
-            "channel": self.channel,
+            "channel": self.channel.to_json()?,
             "message": self.message.to_json()?
-        }))
+        });
+
+        // Keep legacy shape for messages without receipts
+        if self.receipt {
+            request["receipt"] = Json::Bool(true);
+        }
+
+        Ok(request)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
@@ -56,14 +78,17 @@ impl AsJson for SendRequestBody {
                 .map(PublicKey::from_base64)??,
 
             channel: json.get("channel")
-                .and_then(Json::as_str)
-                .map(String::from)
-                .ok_or_else(|| AsJsonError::FieldNotFound("channel"))?,
+                .map(ChannelName::from_json)
+                .ok_or_else(|| AsJsonError::FieldNotFound("channel"))??,
 
             message: json.get("message")
                 .map(Message::from_json)
-                .ok_or_else(|| AsJsonError::FieldNotFound("message"))??
+                .ok_or_else(|| AsJsonError::FieldNotFound("message"))??,
 
+            receipt: match json.get("receipt") {
+                Some(receipt) => receipt.as_bool().ok_or(AsJsonError::FieldValueInvalid("receipt"))?,
+                None => false
+            }
         })
     }
 }
@@ -92,7 +117,12 @@ mod tests {
         let message_encoding = MessageEncoding::from_str("base64").unwrap();
         let message = Message::new("content", "sign", message_encoding);
 
-        let request = SendRequestBody::new(sender, server.public_key, "amogus", message);
+        let request = SendRequestBody::new(sender, server.public_key, ChannelName::new("amogus").unwrap(), message);
+
+        assert_eq!(SendRequestBody::from_json(&request.to_json()?)?, request);
+        assert!(request.to_json()?.get("receipt").is_none());
+
+        let request = request.with_receipt(true);
 
         assert_eq!(SendRequestBody::from_json(&request.to_json()?)?, request);
 
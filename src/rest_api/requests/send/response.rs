@@ -2,28 +2,60 @@ use serde_json::{json, Value as Json};
 
 use crate::rest_api::{AsJson, AsJsonError};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// `POST /api/v1/send` response body.
 /// 
 /// Refer to `SendResponse` for details.
-pub struct SendResponseBody;
+pub struct SendResponseBody {
+    /// Id assigned to the stored message by the server.
+    /// 
+    /// The same id is returned in the `MessageInfo` of the
+    /// polled message. It's `None` if the message was dropped
+    /// as a duplicate or the server doesn't support message ids.
+    pub id: Option<u64>
+}
 
 impl SendResponseBody {
     #[inline]
-    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    #[inline]
+    /// Set id assigned to the stored message.
+    pub fn with_id(self, id: u64) -> Self {
+        Self {
+            id: Some(id)
+        }
     }
 }
 
 impl AsJson for SendResponseBody {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({}))
+        // Keep legacy response shape if no id was assigned
+        let Some(id) = self.id else {
+            return Ok(json!({}));
+        };
+
+        Ok(json!({
+            "id": id
+        }))
     }
 
-    fn from_json(_json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
-        Ok(Self)
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let id = match json.get("id") {
+            Some(id) => match id.as_u64() {
+                Some(id) => Some(id),
+                None => return Err(AsJsonError::FieldValueInvalid("id"))
+            }
+
+            None => None
+        };
+
+        Ok(Self {
+            id
+        })
     }
 }
 
@@ -33,10 +65,18 @@ mod tests {
 
     #[test]
     fn serialize() -> Result<(), AsJsonError> {
-        let response = SendResponseBody;
+        let response = SendResponseBody::new();
 
+        assert_eq!(response.to_json()?, json!({}));
         assert_eq!(SendResponseBody::from_json(&response.to_json()?)?, response);
 
+        let response = SendResponseBody::new().with_id(12345);
+
+        assert_eq!(response.to_json()?, json!({ "id": 12345 }));
+        assert_eq!(SendResponseBody::from_json(&response.to_json()?)?, response);
+
+        assert!(SendResponseBody::from_json(&json!({ "id": "12345" })).is_err());
+
         Ok(())
     }
 }
@@ -0,0 +1,113 @@
+use serde_json::Value as Json;
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+mod request;
+mod response;
+
+pub use request::SendBatchRequestBody;
+pub use response::{SendBatchResponseBody, SendBatchResult};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/send_batch` request.
+/// 
+/// This request stores multiple messages in a server's inbox
+/// using a single HTTP request and a single proof signature.
+/// Every entry is checked by the server the same way as the
+/// `POST /api/v1/send` request body, and rejected entries
+/// don't affect the other ones.
+/// 
+/// # Size
+/// 
+/// Every entry repeats the sender's record, which adds a few
+/// hundred bytes on top of the message's content. Servers
+/// reject batches with more than `ServerParams::max_batch_size`
+/// entries and check size of every message separately,
+/// so the request's body can be up to `max_batch_size`
+/// times larger than a single `POST /api/v1/send` request.
+/// HTTP servers may apply their own limits to the body size.
+pub struct SendBatchRequest(pub Request<SendBatchRequestBody>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/send_batch` response.
+pub struct SendBatchResponse(pub Response<SendBatchResponseBody>);
+
+impl SendBatchRequest {
+    #[inline]
+    pub fn new(client_secret: &SecretKey, entries: impl Into<Vec<SendRequestBody>>) -> Self {
+        Self(Request::new(client_secret, SendBatchRequestBody::new(entries)))
+    }
+
+    #[inline]
+    /// Validate the request.
+    /// 
+    /// Calls `validate()` function on the request's body
+    /// and verifies that the messages' senders are the
+    /// request's author connected to their servers.
+    pub fn validate(&self) -> Result<bool, ValidationError> {
+        if !self.0.validate()? {
+            return Ok(false);
+        }
+
+        for entry in &self.0.request.entries {
+            if entry.sender.client.public_key != self.0.public_key || !entry.sender.validate()? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl AsJson for SendBatchRequest {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        self.0.to_json()
+    }
+
+    #[inline]
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self(Request::from_json(json)?))
+    }
+}
+
+impl SendBatchResponse {
+    pub fn success(status: ResponseStatus, server_secret: &SecretKey, proof_seed: u64, body: SendBatchResponseBody) -> Self {
+        let proof = server_secret.create_signature(proof_seed.to_be_bytes());
+
+        Self(Response::success(
+            status,
+            server_secret.public_key(),
+            proof,
+            body
+        ))
+    }
+
+    #[inline]
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
+        Self(Response::error(status, code, reason))
+    }
+
+    #[inline]
+    /// Validate the response.
+    /// 
+    /// Calls `validate()` function on the response's body.
+    pub fn validate(&self, proof_seed: u64) -> Result<bool, ValidationError> {
+        self.0.validate(proof_seed)
+    }
+}
+
+impl AsJson for SendBatchResponse {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        self.0.to_json()
+    }
+
+    #[inline]
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        Ok(Self(Response::from_json(json)?))
+    }
+}
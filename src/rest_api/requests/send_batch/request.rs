@@ -0,0 +1,118 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/send_batch` request body.
+/// 
+/// Every entry has the same format as the
+/// `POST /api/v1/send` request body:
+/// 
+/// ```json
+/// {
+///     "messages": [
+///         {
+///             "sender": { ... },
+///             "receiver": {
+///                 "public_key": "<base64>"
+///             },
+///             "channel": "<name>",
+///             "message": { ... }
+///         }
+///     ]
+/// }
+/// ```
+/// 
+/// Refer to `SendBatchRequest` for details.
+pub struct SendBatchRequestBody {
+    pub entries: Vec<SendRequestBody>
+}
+
+impl SendBatchRequestBody {
+    #[inline]
+    pub fn new(entries: impl Into<Vec<SendRequestBody>>) -> Self {
+        Self {
+            entries: entries.into()
+        }
+    }
+
+    #[inline]
+    /// Get total size of the messages' content in bytes.
+    pub fn content_size(&self) -> usize {
+        self.entries.iter()
+            .map(|entry| entry.message.content.len())
+            .sum()
+    }
+}
+
+impl AsJson for SendBatchRequestBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let entries = self.entries.iter()
+            .map(SendRequestBody::to_json)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(json!({
+            "messages": entries
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(entries) = json.get("messages") else {
+            return Err(AsJsonError::FieldNotFound("messages"));
+        };
+
+        let Some(entries) = entries.as_array() else {
+            return Err(AsJsonError::FieldValueInvalid("messages"));
+        };
+
+        Ok(Self {
+            entries: entries.iter()
+                .map(SendRequestBody::from_json)
+                .collect::<Result<Vec<_>, _>>()?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::crypto::asymmetric::SecretKey;
+
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let client = SecretKey::random();
+        let server = SecretKey::random();
+
+        let info = ClientInfo::thin();
+        let cert = ConnectionCertificate::new(&client, server.public_key());
+
+        let client = Client::new(client.public_key(), cert, info);
+        let server = Server::new(server.public_key(), "amogus");
+
+        let sender = Sender::new(client, server.clone());
+
+        let message_encoding = MessageEncoding::from_str("base64").unwrap();
+
+        let request = SendBatchRequestBody::new([
+            SendRequestBody::new(sender.clone(), server.public_key.clone(), ChannelName::new("amogus").unwrap(), Message::new("content", "sign", message_encoding)),
+            SendRequestBody::new(sender, SecretKey::random().public_key(), ChannelName::new("sus").unwrap(), Message::new("hello", "sign", message_encoding))
+        ]);
+
+        assert_eq!(request.content_size(), 12);
+        assert_eq!(SendBatchRequestBody::from_json(&request.to_json()?)?, request);
+
+        let request = SendBatchRequestBody::default();
+
+        assert_eq!(request.to_json()?, json!({ "messages": [] }));
+        assert_eq!(SendBatchRequestBody::from_json(&request.to_json()?)?, request);
+
+        assert!(SendBatchRequestBody::from_json(&json!({ "messages": {} })).is_err());
+        assert!(SendBatchRequestBody::from_json(&json!({ "messages": [{}] })).is_err());
+
+        Ok(())
+    }
+}
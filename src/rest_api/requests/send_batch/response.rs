@@ -0,0 +1,171 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Verdict of the single `POST /api/v1/send_batch` entry.
+pub enum SendBatchResult {
+    /// Message was added to the inbox.
+    /// 
+    /// Refer to `SendResponseBody` for the id details.
+    Sent {
+        id: Option<u64>
+    },
+
+    /// Message was rejected by the server.
+    Rejected {
+        status: ResponseStatus,
+        reason: String
+    }
+}
+
+impl SendBatchResult {
+    #[inline]
+    pub fn rejected(status: ResponseStatus, reason: impl ToString) -> Self {
+        Self::Rejected {
+            status,
+            reason: reason.to_string()
+        }
+    }
+
+    #[inline]
+    pub fn is_sent(&self) -> bool {
+        matches!(self, Self::Sent { .. })
+    }
+}
+
+impl AsJson for SendBatchResult {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        match self {
+            Self::Sent { id: Some(id) } => Ok(json!({
+                "status": ResponseStatus::Success.to_code(),
+                "id": id
+            })),
+
+            Self::Sent { id: None } => Ok(json!({
+                "status": ResponseStatus::Success.to_code()
+            })),
+
+            Self::Rejected { status, reason } => Ok(json!({
+                "status": status.to_code(),
+                "reason": reason
+            }))
+        }
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(status) = json.get("status") else {
+            return Err(AsJsonError::FieldNotFound("status"));
+        };
+
+        let Some(status) = status.as_u64().and_then(ResponseStatus::from_code) else {
+            return Err(AsJsonError::FieldValueInvalid("status"));
+        };
+
+        if status.is_success() {
+            let id = match json.get("id") {
+                Some(id) => Some(id.as_u64().ok_or(AsJsonError::FieldValueInvalid("id"))?),
+                None => None
+            };
+
+            return Ok(Self::Sent { id });
+        }
+
+        let Some(reason) = json.get("reason").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("reason"));
+        };
+
+        Ok(Self::Rejected {
+            status,
+            reason: reason.to_string()
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `POST /api/v1/send_batch` response body.
+/// 
+/// Contains results of the request's entries
+/// in the same order:
+/// 
+/// ```json
+/// {
+///     "results": [
+///         { "status": 100, "id": 1 },
+///         { "status": 322, "reason": "Message is too large" }
+///     ]
+/// }
+/// ```
+/// 
+/// Refer to `SendBatchResponse` for details.
+pub struct SendBatchResponseBody {
+    pub results: Vec<SendBatchResult>
+}
+
+impl SendBatchResponseBody {
+    #[inline]
+    pub fn new(results: impl Into<Vec<SendBatchResult>>) -> Self {
+        Self {
+            results: results.into()
+        }
+    }
+}
+
+impl AsJson for SendBatchResponseBody {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let results = self.results.iter()
+            .map(SendBatchResult::to_json)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(json!({
+            "results": results
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(results) = json.get("results") else {
+            return Err(AsJsonError::FieldNotFound("results"));
+        };
+
+        let Some(results) = results.as_array() else {
+            return Err(AsJsonError::FieldValueInvalid("results"));
+        };
+
+        Ok(Self {
+            results: results.iter()
+                .map(SendBatchResult::from_json)
+                .collect::<Result<Vec<_>, _>>()?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let response = SendBatchResponseBody::new([
+            SendBatchResult::Sent { id: Some(12345) },
+            SendBatchResult::Sent { id: None },
+            SendBatchResult::rejected(ResponseStatus::MessageTooLarge, "Message is too large")
+        ]);
+
+        assert_eq!(response.to_json()?, json!({
+            "results": [
+                { "status": 100, "id": 12345 },
+                { "status": 100 },
+                { "status": 322, "reason": "Message is too large" }
+            ]
+        }));
+
+        assert_eq!(SendBatchResponseBody::from_json(&response.to_json()?)?, response);
+
+        assert!(SendBatchResponseBody::from_json(&json!({ "results": [{ "status": 100, "id": "1" }] })).is_err());
+        assert!(SendBatchResponseBody::from_json(&json!({ "results": [{ "status": 322 }] })).is_err());
+
+        Ok(())
+    }
+}
@@ -18,7 +18,21 @@ use crate::STANDARD_VERSION;
 /// to lookup the clients.
 pub struct ServersResponse {
     pub standard: u64,
-    pub servers: Vec<Server>
+    pub servers: Vec<Server>,
+
+    /// Continuation token of the next page.
+    /// 
+    /// Sent only when the servers list was
+    /// requested with pagination parameters.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub next: Option<String>,
+
+    /// Total amount of the known servers.
+    /// 
+    /// Not sent by legacy servers and ignored
+    /// by legacy clients.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub total: Option<u64>
 }
 
 impl ServersResponse {
@@ -42,46 +56,83 @@ impl ServersResponse {
     pub fn new(servers: impl Into<Vec<Server>>) -> Self {
         Self {
             standard: STANDARD_VERSION,
-            servers: servers.into()
+            servers: servers.into(),
+            next: None,
+            total: None
         }
     }
+
+    /// Create new `GET /api/v1/servers` response
+    /// from the page of the servers list.
+    pub fn page(page: Page<Server>) -> Self {
+        Self {
+            standard: STANDARD_VERSION,
+            servers: page.items,
+            next: page.next,
+            total: None
+        }
+    }
+
+    #[inline]
+    /// Set total amount of the known servers.
+    pub fn with_total(mut self, total: u64) -> Self {
+        self.total = Some(total);
+
+        self
+    }
 }
 
 impl AsJson for ServersResponse {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        match self.standard {
-            1 => Ok(json!({
-                "standard": self.standard,
-                "servers": self.servers.iter()
-                    .map(AsJson::to_json)
-                    .collect::<Result<Vec<_>, AsJsonError>>()?
-            })),
-
-            _ => Err(AsJsonError::InvalidStandard(self.standard))
+        let standard = ProtocolVersion::check(self.standard)?;
+
+        let mut response = json!({
+            "standard": standard.0,
+            "servers": self.servers.iter()
+                .map(AsJson::to_json)
+                .collect::<Result<Vec<_>, AsJsonError>>()?
+        });
+
+        if let Some(next) = &self.next {
+            response["next"] = Json::String(next.clone());
         }
+
+        if let Some(total) = self.total {
+            response["total"] = Json::from(total);
+        }
+
+        Ok(response)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
-        let Some(standard) = json.get("standard").and_then(Json::as_u64) else {
-            return Err(AsJsonError::FieldNotFound("standard"));
+        let standard = ProtocolVersion::parse(json)?.0;
+
+        let Some(servers) = json.get("servers").and_then(Json::as_array) else {
+            return Err(AsJsonError::FieldNotFound("servers"));
         };
 
-        match standard {
-            1 => {
-                let Some(servers) = json.get("servers").and_then(Json::as_array) else {
-                    return Err(AsJsonError::FieldNotFound("servers"));
-                };
-
-                Ok(Self {
-                    standard,
-                    servers: servers.iter()
-                        .map(AsJson::from_json)
-                        .collect::<Result<Vec<_>, AsJsonError>>()?
-                })
-            }
-
-            _ => Err(AsJsonError::InvalidStandard(standard))
-        }
+        let next = match json.get("next") {
+            Some(next) => Some(next.as_str()
+                .ok_or(AsJsonError::FieldValueInvalid("next"))?
+                .to_string()),
+
+            None => None
+        };
+
+        let total = match json.get("total") {
+            Some(total) => Some(total.as_u64().ok_or(AsJsonError::FieldValueInvalid("total"))?),
+            None => None
+        };
+
+        Ok(Self {
+            standard,
+            servers: servers.iter()
+                .map(AsJson::from_json)
+                .collect::<Result<Vec<_>, AsJsonError>>()?,
+
+            next,
+            total
+        })
     }
 }
 
@@ -101,6 +152,12 @@ mod tests {
 
         assert_eq!(ServersResponse::from_json(&response.to_json()?)?, response);
 
+        let response = ServersResponse::page(Page::new(vec![
+            get_server()
+        ], Some(String::from("token")))).with_total(3);
+
+        assert_eq!(ServersResponse::from_json(&response.to_json()?)?, response);
+
         Ok(())
     }
 }
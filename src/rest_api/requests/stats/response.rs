@@ -0,0 +1,249 @@
+use serde_json::{json, Value as Json};
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+use crate::drivers::server::messages_inbox::InboxStats;
+
+use crate::STANDARD_VERSION;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// `GET /api/v1/stats` response.
+/// 
+/// This API should provide current state of the running
+/// server for its operators. Fields hidden by the server's
+/// privacy settings are `None`.
+/// 
+/// The proof sign covers both the proof seed and all
+/// the statistics, so they can't be changed by a proxy.
+pub struct StatsResponse {
+    pub standard: u64,
+    pub public_key: PublicKey,
+
+    /// Time since the server was started, in seconds.
+    pub uptime: u64,
+
+    /// Amount of the clients connected to the server.
+    pub local_clients: Option<u64>,
+
+    /// Amount of the servers known by the server.
+    pub servers: Option<u64>,
+
+    /// Statistics of the server's messages inbox.
+    pub inbox: Option<InboxStats>,
+
+    pub proof_seed: u64,
+    pub proof_sign: Vec<u8>
+}
+
+impl StatsResponse {
+    /// Create `GET /api/v1/stats` response.
+    /// 
+    /// - `server_secret` must contain reference to the
+    ///   server's secret key. It is used to sign
+    ///   the response proof.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let response = StatsResponse::new(&SecretKey::random(), 60, Some(3), None, None);
+    /// 
+    /// assert!(response.validate().unwrap());
+    /// ```
+    pub fn new(
+        server_secret: &SecretKey,
+        uptime: u64,
+        local_clients: Option<u64>,
+        servers: Option<u64>,
+        inbox: Option<InboxStats>
+    ) -> Self {
+        let mut response = Self {
+            standard: STANDARD_VERSION,
+            public_key: server_secret.public_key(),
+            uptime,
+            local_clients,
+            servers,
+            inbox,
+            proof_seed: safe_random_u64_long(),
+            proof_sign: vec![]
+        };
+
+        response.proof_sign = server_secret.create_signature(response.signed_data());
+
+        response
+    }
+
+    /// Get bytes signed by the proof.
+    fn signed_data(&self) -> Vec<u8> {
+        fn push_option(data: &mut Vec<u8>, value: Option<u64>) {
+            match value {
+                Some(value) => {
+                    data.push(1);
+                    data.extend(value.to_be_bytes());
+                }
+
+                None => data.push(0)
+            }
+        }
+
+        let mut data = Vec::with_capacity(64);
+
+        data.extend(self.proof_seed.to_be_bytes());
+        data.extend(self.uptime.to_be_bytes());
+
+        push_option(&mut data, self.local_clients);
+        push_option(&mut data, self.servers);
+
+        match &self.inbox {
+            Some(inbox) => {
+                data.push(1);
+                data.extend(inbox.messages.to_be_bytes());
+                data.extend(inbox.bytes.to_be_bytes());
+                data.extend(inbox.receivers.to_be_bytes());
+
+                push_option(&mut data, inbox.oldest_message);
+            }
+
+            None => data.push(0)
+        }
+
+        data
+    }
+
+    /// Validate response proof.
+    pub fn validate(&self) -> Result<bool, ValidationError> {
+        if self.proof_seed < 1 << 63 {
+            return Err(ValidationError::InvalidSeed);
+        }
+
+        Ok(self.public_key.verify_signature(
+            self.signed_data(),
+            &self.proof_sign
+        )?)
+    }
+}
+
+impl AsJson for StatsResponse {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let standard = ProtocolVersion::check(self.standard)?;
+
+        let mut stats = json!({
+            "uptime": self.uptime
+        });
+
+        if let Some(local_clients) = self.local_clients {
+            stats["local_clients"] = Json::from(local_clients);
+        }
+
+        if let Some(servers) = self.servers {
+            stats["servers"] = Json::from(servers);
+        }
+
+        if let Some(inbox) = &self.inbox {
+            stats["inbox"] = inbox.to_json()?;
+        }
+
+        Ok(json!({
+            "standard": standard.0,
+            "server": {
+                "public_key": self.public_key.to_base64(),
+            },
+            "stats": stats,
+            "proof": {
+                "seed": self.proof_seed,
+                "sign": base64_encode(&self.proof_sign)
+            }
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let standard = ProtocolVersion::parse(json)?.0;
+
+        let Some(public_key) = json.get("server").and_then(|server| server.get("public_key")).and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("server.public_key"));
+        };
+
+        let Some(stats) = json.get("stats") else {
+            return Err(AsJsonError::FieldNotFound("stats"));
+        };
+
+        let Some(uptime) = stats.get("uptime").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("stats.uptime"));
+        };
+
+        let inbox = match stats.get("inbox") {
+            Some(inbox) => Some(InboxStats::from_json(inbox)?),
+            None => None
+        };
+
+        let Some(proof) = json.get("proof") else {
+            return Err(AsJsonError::FieldNotFound("proof"));
+        };
+
+        let Some(proof_seed) = proof.get("seed").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("proof.seed"));
+        };
+
+        let Some(proof_sign) = proof.get("sign").and_then(Json::as_str) else {
+            return Err(AsJsonError::FieldNotFound("proof.sign"));
+        };
+
+        Ok(Self {
+            standard,
+            public_key: PublicKey::from_base64(public_key)?,
+            uptime,
+            local_clients: stats.get("local_clients").and_then(Json::as_u64),
+            servers: stats.get("servers").and_then(Json::as_u64),
+            inbox,
+            proof_seed,
+            proof_sign: base64_decode(proof_sign)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let inbox = InboxStats {
+            messages: 10,
+            bytes: 1024,
+            receivers: 2,
+            oldest_message: Some(1700000000)
+        };
+
+        let responses = [
+            StatsResponse::new(&SecretKey::random(), 3600, Some(5), Some(12), Some(inbox)),
+            StatsResponse::new(&SecretKey::random(), 0, None, None, None)
+        ];
+
+        for response in responses {
+            assert_eq!(StatsResponse::from_json(&response.to_json()?)?, response);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate() -> Result<(), ValidationError> {
+        let mut response = StatsResponse::new(&SecretKey::random(), 3600, Some(5), Some(12), None);
+
+        assert!(response.validate()?);
+
+        // Statistics are covered by the proof
+        response.local_clients = Some(6);
+
+        assert!(!response.validate()?);
+
+        response.local_clients = None;
+
+        assert!(!response.validate()?);
+
+        Ok(())
+    }
+}
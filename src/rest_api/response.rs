@@ -5,6 +5,8 @@ use crate::crypto::prelude::*;
 use crate::STANDARD_VERSION;
 
 use super::status::ResponseStatus;
+use super::error_code::ErrorCode;
+use super::version::ProtocolVersion;
 
 use super::{
     AsJson,
@@ -33,6 +35,7 @@ pub enum Response<T> {
     Error {
         standard: u64,
         status: ResponseStatus,
+        code: ErrorCode,
         reason: String
     }
 }
@@ -84,6 +87,9 @@ impl<T> Response<T> {
     /// 
     /// - `status` must contain status code of the response.
     /// 
+    /// - `code` must contain machine-readable category
+    ///   of the error.
+    /// 
     /// - `reason` must contain the explanation string of the error
     ///   (error message and/or description).
     /// 
@@ -94,13 +100,15 @@ impl<T> Response<T> {
     /// 
     /// let response = Response::<()>::error(
     ///     ResponseStatus::ServerError,
+    ///     ErrorCode::Internal,
     ///     "Example error"
     /// );
     /// ```
-    pub fn error(status: ResponseStatus, reason: impl ToString) -> Self {
+    pub fn error(status: ResponseStatus, code: ErrorCode, reason: impl ToString) -> Self {
         Self::Error {
             standard: STANDARD_VERSION,
             status,
+            code,
             reason: reason.to_string()
         }
     }
@@ -127,6 +135,16 @@ impl<T> Response<T> {
         }
     }
 
+    /// Get `error_code` field from the error response.
+    /// 
+    /// Return `None` for successful responses.
+    pub fn error_code(&self) -> Option<&ErrorCode> {
+        match self {
+            Self::Success { .. } => None,
+            Self::Error { code, .. } => Some(code)
+        }
+    }
+
     /// Validate that the response is correct (sent by a real server).
     /// 
     /// - `proof_seed` must contain proof seed
@@ -164,6 +182,7 @@ impl<T> Response<T> {
     /// 
     /// let response = Response::<()>::error(
     ///     ResponseStatus::ServerError,
+    ///     ErrorCode::Internal,
     ///     "Example error"
     /// );
     /// 
@@ -191,31 +210,24 @@ impl<T: AsJson> AsJson for Response<T> {
     fn to_json(&self) -> Result<Json, AsJsonError> {
         let value = match self {
             Self::Success { standard, status, public_key, proof_sign, response } => {
-                match standard {
-                    1 => json!({
-                        "standard": standard,
-                        "status": status.to_code(),
-                        "public_key": public_key.to_base64(),
-                        "proof": {
-                            "sign": base64_encode(proof_sign)
-                        },
-                        "response": response.to_json()?
-                    }),
-
-                    _ => return Err(AsJsonError::InvalidStandard(*standard))
-                }
+                json!({
+                    "standard": ProtocolVersion::check(*standard)?.0,
+                    "status": status.to_code(),
+                    "public_key": public_key.to_base64(),
+                    "proof": {
+                        "sign": base64_encode(proof_sign)
+                    },
+                    "response": response.to_json()?
+                })
             }
 
-            Self::Error { standard, status, reason } => {
-                match standard {
-                    1 => json!({
-                        "standard": standard,
-                        "status": status.to_code(),
-                        "reason": reason
-                    }),
-
-                    _ => return Err(AsJsonError::InvalidStandard(*standard))
-                }
+            Self::Error { standard, status, code, reason } => {
+                json!({
+                    "standard": ProtocolVersion::check(*standard)?.0,
+                    "status": status.to_code(),
+                    "error_code": code.name(),
+                    "reason": reason
+                })
             }
         };
 
@@ -223,60 +235,62 @@ impl<T: AsJson> AsJson for Response<T> {
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
-        let Some(standard) = json.get("standard").and_then(Json::as_u64) else {
-            return Err(AsJsonError::FieldNotFound("standard"));
-        };
+        let standard = ProtocolVersion::parse(json)?.0;
 
-        match standard {
-            1 => {
-                let Some(status) = json.get("status") else {
-                    return Err(AsJsonError::FieldNotFound("status"));
-                };
+        let Some(status) = json.get("status") else {
+            return Err(AsJsonError::FieldNotFound("status"));
+        };
 
-                let Some(status) = status.as_u64().and_then(ResponseStatus::from_code) else {
-                    return Err(AsJsonError::FieldValueInvalid("status"));
-                };
+        let Some(status) = status.as_u64().and_then(ResponseStatus::from_code) else {
+            return Err(AsJsonError::FieldValueInvalid("status"));
+        };
 
-                if status.is_success() {
-                    let Some(public_key) = json.get("public_key").and_then(Json::as_str) else {
-                        return Err(AsJsonError::FieldNotFound("public_key"));
-                    };
+        if status.is_success() {
+            let Some(public_key) = json.get("public_key").and_then(Json::as_str) else {
+                return Err(AsJsonError::FieldNotFound("public_key"));
+            };
     
-                    let Some(proof) = json.get("proof") else {
-                        return Err(AsJsonError::FieldNotFound("proof"));
-                    };
+            let Some(proof) = json.get("proof") else {
+                return Err(AsJsonError::FieldNotFound("proof"));
+            };
     
-                    let Some(proof_sign) = proof.get("sign").and_then(Json::as_str) else {
-                        return Err(AsJsonError::FieldNotFound("proof.sign"));
-                    };
+            let Some(proof_sign) = proof.get("sign").and_then(Json::as_str) else {
+                return Err(AsJsonError::FieldNotFound("proof.sign"));
+            };
     
-                    let Some(response) = json.get("response") else {
-                        return Err(AsJsonError::FieldNotFound("response"));
-                    };
-
-                    Ok(Self::Success {
-                        standard,
-                        status,
-                        public_key: PublicKey::from_base64(public_key)?,
-                        proof_sign: base64_decode(proof_sign)?,
-                        response: T::from_json(response)?
-                    })
-                }
-
-                else {
-                    let Some(reason) = json.get("reason").and_then(Json::as_str) else {
-                        return Err(AsJsonError::FieldNotFound("reason"));
-                    };
-
-                    Ok(Self::Error {
-                        standard,
-                        status,
-                        reason: reason.to_string()
-                    })
-                }
-            }
+            let Some(response) = json.get("response") else {
+                return Err(AsJsonError::FieldNotFound("response"));
+            };
+
+            Ok(Self::Success {
+                standard,
+                status,
+                public_key: PublicKey::from_base64(public_key)?,
+                proof_sign: base64_decode(proof_sign)?,
+                response: T::from_json(response)?
+            })
+        }
 
-            _ => Err(AsJsonError::InvalidStandard(standard))
+        else {
+            let Some(reason) = json.get("reason").and_then(Json::as_str) else {
+                return Err(AsJsonError::FieldNotFound("reason"));
+            };
+
+            // Old servers don't send error codes
+            let code = match json.get("error_code") {
+                Some(code) => code.as_str()
+                    .map(ErrorCode::from_name)
+                    .ok_or(AsJsonError::FieldValueInvalid("error_code"))?,
+
+                None => ErrorCode::from(status)
+            };
+
+            Ok(Self::Error {
+                standard,
+                status,
+                code,
+                reason: reason.to_string()
+            })
         }
     }
 }
@@ -306,10 +320,33 @@ mod tests {
 
     #[test]
     fn serialize_error() -> Result<(), AsJsonError> {
-        let response = Response::<ConnectResponse>::error(ResponseStatus::MessageTooLarge, "Hello, World!");
+        let response = Response::<ConnectResponse>::error(ResponseStatus::MessageTooLarge, ErrorCode::QuotaExceeded, "Hello, World!");
 
         assert_eq!(Response::from_json(&response.to_json()?)?, response);
 
         Ok(())
     }
+
+    #[test]
+    fn error_codes() -> Result<(), AsJsonError> {
+        let mut json = Response::<ConnectResponse>::error(ResponseStatus::ClientNotFound, ErrorCode::NotFound, "Client not found").to_json()?;
+
+        assert_eq!(json["error_code"], "not_found");
+
+        // Unknown codes
+        json["error_code"] = Json::from("future_code");
+
+        let response = Response::<ConnectResponse>::from_json(&json)?;
+
+        assert_eq!(response.error_code(), Some(&ErrorCode::Other(String::from("future_code"))));
+
+        // Legacy responses without codes
+        json.as_object_mut().unwrap().remove("error_code");
+
+        let response = Response::<ConnectResponse>::from_json(&json)?;
+
+        assert_eq!(response.error_code(), Some(&ErrorCode::NotFound));
+
+        Ok(())
+    }
 }
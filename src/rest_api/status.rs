@@ -7,12 +7,39 @@ pub enum ResponseStatus {
     /// Server error - 200
     ServerError,
 
+    /// Server error - 201
+    RateLimited,
+
     /// Protocol error - 300
     InvalidRequestStructure,
 
     /// Protocol error - 301
     RequestValidationFailed,
 
+    /// Protocol error - 302
+    ReputationTooLow,
+
+    /// Protocol error - 303
+    Unauthorized,
+
+    /// Protocol error - 304
+    Forbidden,
+
+    /// Protocol error - 305
+    BatchTooLarge,
+
+    /// Protocol error - 306
+    RequestExpired,
+
+    /// Protocol error - 307
+    CertificateExpired,
+
+    /// Protocol error - 308
+    TooManyRequests,
+
+    /// Protocol error - 309
+    RequestBodyTooLarge,
+
     /// Protocol error - 310
     ClientLookupTimeout,
 
@@ -26,7 +53,28 @@ pub enum ResponseStatus {
     ClientInboxFull,
 
     /// Protocol error - 322
-    MessageTooLarge
+    MessageTooLarge,
+
+    /// Protocol error - 323
+    InvalidChannelName,
+
+    /// Protocol error - 330
+    AnnounceRecordTooLarge,
+
+    /// Protocol error - 331
+    TooManyAnnounceEntries,
+
+    /// Protocol error - 332
+    RoutingTableFull,
+
+    /// Protocol error - 340
+    InvalidClientAlias,
+
+    /// Protocol error - 341
+    ClientAliasTaken,
+
+    /// Protocol error - 350
+    InsufficientWork
 }
 
 impl ResponseStatus {
@@ -37,10 +85,19 @@ impl ResponseStatus {
 
             // Server error
             200 => Self::ServerError,
+            201 => Self::RateLimited,
 
             // Protocol error
             300 => Self::InvalidRequestStructure,
             301 => Self::RequestValidationFailed,
+            302 => Self::ReputationTooLow,
+            303 => Self::Unauthorized,
+            304 => Self::Forbidden,
+            305 => Self::BatchTooLarge,
+            306 => Self::RequestExpired,
+            307 => Self::CertificateExpired,
+            308 => Self::TooManyRequests,
+            309 => Self::RequestBodyTooLarge,
 
             // Protocol error - lookup error
             310 => Self::ClientLookupTimeout,
@@ -50,6 +107,19 @@ impl ResponseStatus {
             320 => Self::ClientNotConnected,
             321 => Self::ClientInboxFull,
             322 => Self::MessageTooLarge,
+            323 => Self::InvalidChannelName,
+
+            // Protocol error - announce error
+            330 => Self::AnnounceRecordTooLarge,
+            331 => Self::TooManyAnnounceEntries,
+            332 => Self::RoutingTableFull,
+
+            // Protocol error - alias error
+            340 => Self::InvalidClientAlias,
+            341 => Self::ClientAliasTaken,
+
+            // Protocol error - proof of work error
+            350 => Self::InsufficientWork,
 
             _ => return None
         };
@@ -64,10 +134,19 @@ impl ResponseStatus {
 
             // Server error
             Self::ServerError => 200,
+            Self::RateLimited => 201,
 
             // Protocol error
             Self::InvalidRequestStructure => 300,
             Self::RequestValidationFailed => 301,
+            Self::ReputationTooLow        => 302,
+            Self::Unauthorized            => 303,
+            Self::Forbidden               => 304,
+            Self::BatchTooLarge           => 305,
+            Self::RequestExpired          => 306,
+            Self::CertificateExpired      => 307,
+            Self::TooManyRequests         => 308,
+            Self::RequestBodyTooLarge     => 309,
 
             // Protocol error - lookup error
             Self::ClientLookupTimeout => 310,
@@ -76,7 +155,20 @@ impl ResponseStatus {
             // Protocol error - inbox error
             Self::ClientNotConnected => 320,
             Self::ClientInboxFull    => 321,
-            Self::MessageTooLarge    => 322
+            Self::MessageTooLarge    => 322,
+            Self::InvalidChannelName => 323,
+
+            // Protocol error - announce error
+            Self::AnnounceRecordTooLarge => 330,
+            Self::TooManyAnnounceEntries => 331,
+            Self::RoutingTableFull       => 332,
+
+            // Protocol error - alias error
+            Self::InvalidClientAlias => 340,
+            Self::ClientAliasTaken   => 341,
+
+            // Protocol error - proof of work error
+            Self::InsufficientWork => 350
         }
     }
 
@@ -0,0 +1,398 @@
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::{AsJson, AsJsonError};
+use crate::rest_api::types::{ChannelName, ChannelNameError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Operation which can be allowed by the certificate scope.
+pub enum CertificateOperation {
+    /// `POST /api/v1/send`
+    Send,
+
+    /// `POST /api/v1/poll`
+    Poll,
+
+    /// `POST /api/v1/lookup`
+    Lookup
+}
+
+impl CertificateOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Send   => "send",
+            Self::Poll   => "poll",
+            Self::Lookup => "lookup"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "send"   => Some(Self::Send),
+            "poll"   => Some(Self::Poll),
+            "lookup" => Some(Self::Lookup),
+
+            _ => None
+        }
+    }
+}
+
+impl std::fmt::Display for CertificateOperation {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Rule matching allowed channel names.
+pub enum ChannelRule {
+    /// Channel with exactly this name.
+    Exact(ChannelName),
+
+    /// Any channel which name starts with this prefix.
+    Prefix(String)
+}
+
+impl ChannelRule {
+    /// Parse channel pattern.
+    /// 
+    /// Pattern ending with `*` matches all the channels
+    /// starting with the rest of it, so `chat/*` matches
+    /// `chat/alice` and `chat/bob`. Any other pattern
+    /// matches the channel with the same name.
+    /// 
+    /// Pattern is not validated, use `validate` method
+    /// when it comes from an untrusted source.
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// assert_eq!(ChannelRule::parse("chat/*"), ChannelRule::Prefix(String::from("chat/")));
+    /// assert_eq!(ChannelRule::parse("chat"), ChannelRule::Exact(ChannelName::new("chat").unwrap()));
+    /// ```
+    pub fn parse(pattern: impl AsRef<str>) -> Self {
+        let pattern = pattern.as_ref();
+
+        match pattern.strip_suffix('*') {
+            Some(prefix) => Self::Prefix(prefix.to_string()),
+            None => Self::Exact(ChannelName::new_unchecked(pattern))
+        }
+    }
+
+    /// Check that the rule can match valid channel names.
+    /// 
+    /// Prefix is validated in its pattern form, so
+    /// `*` matching all the channels is allowed.
+    pub fn validate(&self) -> Result<(), ChannelNameError> {
+        match self {
+            Self::Exact(name) => name.validate(),
+            Self::Prefix(prefix) => ChannelName::new(format!("{prefix}*")).map(|_| ())
+        }
+    }
+
+    pub fn matches(&self, channel: &ChannelName) -> bool {
+        match self {
+            Self::Exact(name) => name == channel,
+            Self::Prefix(prefix) => channel.as_str().starts_with(prefix.as_str())
+        }
+    }
+
+    /// Check that all the channels matched
+    /// by the given rule are matched by this one.
+    pub fn covers(&self, rule: &ChannelRule) -> bool {
+        match (self, rule) {
+            (_, Self::Exact(name)) => self.matches(name),
+            (Self::Prefix(prefix), Self::Prefix(other)) => other.starts_with(prefix.as_str()),
+            (Self::Exact(_), Self::Prefix(_)) => false
+        }
+    }
+}
+
+impl std::fmt::Display for ChannelRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Exact(name) => write!(f, "{name}"),
+            Self::Prefix(prefix) => write!(f, "{prefix}*")
+        }
+    }
+}
+
+impl From<ChannelName> for ChannelRule {
+    #[inline]
+    fn from(name: ChannelName) -> Self {
+        Self::Exact(name)
+    }
+}
+
+impl From<&ChannelName> for ChannelRule {
+    #[inline]
+    fn from(name: &ChannelName) -> Self {
+        Self::Exact(name.clone())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum ScopeViolation {
+    #[error("Operation `{0}` is not allowed by the connection certificate scope")]
+    Operation(CertificateOperation),
+
+    #[error("Channel `{0}` is not allowed by the connection certificate scope")]
+    Channel(ChannelName)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Restriction of the connection certificate to
+/// specific channels and operations.
+/// 
+/// Empty list of channels or operations means
+/// that they are not restricted.
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use hyperborealib::rest_api::prelude::*;
+/// 
+/// let scope = CertificateScope::new(
+///     vec![ChannelRule::Prefix(String::from("bot/"))],
+///     vec![CertificateOperation::Send]
+/// );
+/// 
+/// assert!(scope.check(CertificateOperation::Send, Some(&ChannelName::new("bot/status").unwrap())).is_ok());
+/// assert!(scope.check(CertificateOperation::Send, Some(&ChannelName::new("chat").unwrap())).is_err());
+/// assert!(scope.check(CertificateOperation::Lookup, None).is_err());
+/// ```
+pub struct CertificateScope {
+    pub channels: Vec<ChannelRule>,
+    pub operations: Vec<CertificateOperation>
+}
+
+impl CertificateScope {
+    /// Current version of the scope format.
+    pub const VERSION: u64 = 1;
+
+    #[inline]
+    pub fn new(channels: Vec<ChannelRule>, operations: Vec<CertificateOperation>) -> Self {
+        Self {
+            channels,
+            operations
+        }
+    }
+
+    /// Check that the operation on the given channel
+    /// is allowed by the scope.
+    pub fn check(&self, operation: CertificateOperation, channel: Option<&ChannelName>) -> Result<(), ScopeViolation> {
+        if !self.operations.is_empty() && !self.operations.contains(&operation) {
+            return Err(ScopeViolation::Operation(operation));
+        }
+
+        if let Some(channel) = channel {
+            if !self.channels.is_empty() && !self.channels.iter().any(|rule| rule.matches(channel)) {
+                return Err(ScopeViolation::Channel(channel.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the operation on all the channels
+    /// matched by the given rule is allowed by the scope.
+    pub fn check_rule(&self, operation: CertificateOperation, rule: &ChannelRule) -> Result<(), ScopeViolation> {
+        if let ChannelRule::Exact(name) = rule {
+            return self.check(operation, Some(name));
+        }
+
+        self.check(operation, None)?;
+
+        if !self.channels.is_empty() && !self.channels.iter().any(|allowed| allowed.covers(rule)) {
+            return Err(ScopeViolation::Channel(ChannelName::new_unchecked(rule.to_string())));
+        }
+
+        Ok(())
+    }
+
+    /// Convert scope to the canonical bytes
+    /// covered by the certificate's signature.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Self::VERSION.to_be_bytes().to_vec();
+
+        bytes.extend((self.channels.len() as u64).to_be_bytes());
+
+        for rule in &self.channels {
+            let (kind, value) = match rule {
+                ChannelRule::Exact(name) => (0, name.as_str()),
+                ChannelRule::Prefix(prefix) => (1, prefix.as_str())
+            };
+
+            bytes.push(kind);
+            bytes.extend((value.len() as u64).to_be_bytes());
+            bytes.extend(value.as_bytes());
+        }
+
+        bytes.extend((self.operations.len() as u64).to_be_bytes());
+
+        for operation in &self.operations {
+            bytes.push(match operation {
+                CertificateOperation::Send   => 0,
+                CertificateOperation::Poll   => 1,
+                CertificateOperation::Lookup => 2
+            });
+        }
+
+        bytes
+    }
+}
+
+impl AsJson for CertificateScope {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let channels = self.channels.iter()
+            .map(|rule| match rule {
+                ChannelRule::Exact(name) => json!({ "exact": name.as_str() }),
+                ChannelRule::Prefix(prefix) => json!({ "prefix": prefix })
+            })
+            .collect::<Vec<_>>();
+
+        let operations = self.operations.iter()
+            .map(CertificateOperation::name)
+            .collect::<Vec<_>>();
+
+        Ok(json!({
+            "version": Self::VERSION,
+            "channels": channels,
+            "operations": operations
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(version) = json.get("version").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("scope.version"));
+        };
+
+        if version != Self::VERSION {
+            return Err(AsJsonError::InvalidStandard(version));
+        }
+
+        let Some(channels) = json.get("channels").and_then(Json::as_array) else {
+            return Err(AsJsonError::FieldNotFound("scope.channels"));
+        };
+
+        let Some(operations) = json.get("operations").and_then(Json::as_array) else {
+            return Err(AsJsonError::FieldNotFound("scope.operations"));
+        };
+
+        Ok(Self {
+            channels: channels.iter()
+                .map(|rule| {
+                    if let Some(name) = rule.get("exact").and_then(Json::as_str) {
+                        ChannelName::new(name)
+                            .map(ChannelRule::Exact)
+                            .map_err(|_| AsJsonError::FieldValueInvalid("scope.channels"))
+                    }
+
+                    else if let Some(prefix) = rule.get("prefix").and_then(Json::as_str) {
+                        Ok(ChannelRule::Prefix(prefix.to_string()))
+                    }
+
+                    else {
+                        Err(AsJsonError::FieldValueInvalid("scope.channels"))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+
+            operations: operations.iter()
+                .map(|operation| operation.as_str()
+                    .and_then(CertificateOperation::from_name)
+                    .ok_or(AsJsonError::FieldValueInvalid("scope.operations")))
+                .collect::<Result<Vec<_>, _>>()?
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub fn get_scope() -> CertificateScope {
+        CertificateScope::new(
+            vec![
+                ChannelRule::Exact(ChannelName::new("status").unwrap()),
+                ChannelRule::Prefix(String::from("bot/"))
+            ],
+            vec![
+                CertificateOperation::Send,
+                CertificateOperation::Poll
+            ]
+        )
+    }
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let scope = get_scope();
+
+        assert_eq!(CertificateScope::from_json(&scope.to_json()?)?, scope);
+
+        let mut json = scope.to_json()?;
+
+        json["version"] = Json::from(2);
+
+        assert!(matches!(CertificateScope::from_json(&json), Err(AsJsonError::InvalidStandard(2))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn check() {
+        let scope = get_scope();
+
+        assert!(scope.check(CertificateOperation::Send, Some(&ChannelName::new("status").unwrap())).is_ok());
+        assert!(scope.check(CertificateOperation::Poll, Some(&ChannelName::new("bot/a").unwrap())).is_ok());
+
+        assert_eq!(
+            scope.check(CertificateOperation::Send, Some(&ChannelName::new("status2").unwrap())),
+            Err(ScopeViolation::Channel(ChannelName::new("status2").unwrap()))
+        );
+
+        assert_eq!(
+            scope.check(CertificateOperation::Lookup, None),
+            Err(ScopeViolation::Operation(CertificateOperation::Lookup))
+        );
+
+        // Unrestricted scope
+        assert!(CertificateScope::default().check(CertificateOperation::Lookup, Some(&ChannelName::new("any").unwrap())).is_ok());
+    }
+
+    #[test]
+    fn check_rule() {
+        let scope = get_scope();
+
+        assert!(scope.check_rule(CertificateOperation::Poll, &ChannelRule::parse("status")).is_ok());
+        assert!(scope.check_rule(CertificateOperation::Poll, &ChannelRule::parse("bot/*")).is_ok());
+        assert!(scope.check_rule(CertificateOperation::Poll, &ChannelRule::parse("bot/chat/*")).is_ok());
+
+        // Pattern can match channels outside of the scope
+        assert_eq!(
+            scope.check_rule(CertificateOperation::Poll, &ChannelRule::parse("bo*")),
+            Err(ScopeViolation::Channel(ChannelName::new("bo*").unwrap()))
+        );
+
+        assert!(scope.check_rule(CertificateOperation::Poll, &ChannelRule::parse("status*")).is_err());
+        assert!(scope.check_rule(CertificateOperation::Lookup, &ChannelRule::parse("bot/*")).is_err());
+
+        assert!(CertificateScope::default().check_rule(CertificateOperation::Poll, &ChannelRule::parse("*")).is_ok());
+    }
+
+    #[test]
+    fn rule_pattern() {
+        assert_eq!(ChannelRule::parse("*"), ChannelRule::Prefix(String::new()));
+        assert_eq!(ChannelRule::parse("chat/*").to_string(), "chat/*");
+
+        assert!(ChannelRule::parse("*").validate().is_ok());
+        assert!(ChannelRule::parse("").validate().is_err());
+        assert!(ChannelRule::Prefix(String::from("\n")).validate().is_err());
+
+        assert!(ChannelRule::parse("chat/*").matches(&ChannelName::new("chat/alice").unwrap()));
+        assert!(!ChannelRule::parse("chat/*").matches(&ChannelName::new("chat").unwrap()));
+    }
+}
@@ -0,0 +1,276 @@
+use std::str::FromStr;
+
+use serde_json::Value as Json;
+
+use crate::rest_api::{AsJson, AsJsonError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum ChannelNameError {
+    #[error("Channel name must not be empty")]
+    Empty,
+
+    #[error("Channel name is too long: {0} bytes, maximum is {max}", max = ChannelName::MAX_LENGTH)]
+    TooLong(usize),
+
+    #[error("Channel name contains forbidden character: {0:?}")]
+    InvalidCharacter(char)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "String", into = "String"))]
+/// Name of the messages channel.
+/// 
+/// Channel name can contain any unicode characters
+/// except the control ones, must not be empty and can't
+/// be longer than `ChannelName::MAX_LENGTH` bytes.
+/// 
+/// All the conversions validate the name,
+/// including the JSON deserialization.
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use hyperborealib::rest_api::prelude::*;
+/// 
+/// let channel = ChannelName::new("example channel").unwrap();
+/// 
+/// assert_eq!(channel.to_string(), "example channel");
+/// assert_eq!(channel.to_fs_name(), "example channel");
+/// 
+/// assert!(ChannelName::new("").is_err());
+/// assert!(ChannelName::new("\0").is_err());
+/// 
+/// assert_eq!(ChannelName::new("../../etc").unwrap().to_fs_name(), "%2E%2E%2F%2E%2E%2Fetc");
+/// ```
+pub struct ChannelName(String);
+
+impl ChannelName {
+    /// Maximal length of the channel name in bytes.
+    pub const MAX_LENGTH: usize = 64;
+
+    /// Create new validated channel name.
+    pub fn new(name: impl ToString) -> Result<Self, ChannelNameError> {
+        let name = Self(name.to_string());
+
+        name.validate()?;
+
+        Ok(name)
+    }
+
+    #[inline]
+    /// Create channel name without validation.
+    /// 
+    /// Used by the types which validate
+    /// their channel names later.
+    pub(crate) fn new_unchecked(name: impl ToString) -> Self {
+        Self(name.to_string())
+    }
+
+    /// Check that the channel name is valid.
+    pub fn validate(&self) -> Result<(), ChannelNameError> {
+        if self.0.is_empty() {
+            return Err(ChannelNameError::Empty);
+        }
+
+        if self.0.len() > Self::MAX_LENGTH {
+            return Err(ChannelNameError::TooLong(self.0.len()));
+        }
+
+        if let Some(char) = self.0.chars().find(|char| char.is_control()) {
+            return Err(ChannelNameError::InvalidCharacter(char));
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get filesystem safe form of the channel name.
+    /// 
+    /// All the bytes except ASCII letters, digits, spaces,
+    /// `-` and `_` are percent-encoded, so the result can't
+    /// contain path separators or special names like `..`.
+    pub fn to_fs_name(&self) -> String {
+        let mut name = String::with_capacity(self.0.len());
+
+        for byte in self.0.bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b' ' | b'-' | b'_') {
+                name.push(byte as char);
+            }
+
+            else {
+                name.push_str(&format!("%{byte:02X}"));
+            }
+        }
+
+        name
+    }
+
+    /// Restore channel name from its filesystem safe form.
+    /// 
+    /// Return `None` if the name is not properly encoded
+    /// or the restored name is not valid.
+    pub fn from_fs_name(name: &str) -> Option<Self> {
+        let mut bytes = Vec::with_capacity(name.len());
+        let mut chars = name.bytes();
+
+        while let Some(byte) = chars.next() {
+            if byte == b'%' {
+                let high = (chars.next()? as char).to_digit(16)?;
+                let low = (chars.next()? as char).to_digit(16)?;
+
+                bytes.push((high * 16 + low) as u8);
+            }
+
+            else {
+                bytes.push(byte);
+            }
+        }
+
+        String::from_utf8(bytes).ok()
+            .and_then(|name| Self::new(name).ok())
+    }
+}
+
+impl std::fmt::Display for ChannelName {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ChannelName {
+    type Err = ChannelNameError;
+
+    #[inline]
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::new(name)
+    }
+}
+
+impl AsRef<str> for ChannelName {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for ChannelName {
+    type Error = ChannelNameError;
+
+    #[inline]
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+impl TryFrom<&String> for ChannelName {
+    type Error = ChannelNameError;
+
+    #[inline]
+    fn try_from(name: &String) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+impl TryFrom<&str> for ChannelName {
+    type Error = ChannelNameError;
+
+    #[inline]
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Self::new(name)
+    }
+}
+
+impl From<&ChannelName> for ChannelName {
+    #[inline]
+    fn from(name: &ChannelName) -> Self {
+        name.clone()
+    }
+}
+
+impl From<ChannelName> for String {
+    #[inline]
+    fn from(name: ChannelName) -> Self {
+        name.0
+    }
+}
+
+impl AsJson for ChannelName {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(Json::String(self.0.clone()))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        json.as_str()
+            .and_then(|name| Self::new(name).ok())
+            .ok_or_else(|| AsJsonError::FieldValueInvalid("channel"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        assert!(ChannelName::new("default channel").is_ok());
+        assert!(ChannelName::new("Hello, World!").is_ok());
+
+        assert_eq!(ChannelName::new(""), Err(ChannelNameError::Empty));
+        assert_eq!(ChannelName::new("a".repeat(65)), Err(ChannelNameError::TooLong(65)));
+        assert_eq!(ChannelName::new("amogus\0"), Err(ChannelNameError::InvalidCharacter('\0')));
+        assert_eq!(ChannelName::new("line\nbreak"), Err(ChannelNameError::InvalidCharacter('\n')));
+    }
+
+    #[test]
+    fn traversal() {
+        for name in ["..", ".", "../../etc", "/etc/passwd", "..\\..\\windows", "a/../b"] {
+            let fs_name = ChannelName::new(name).unwrap().to_fs_name();
+
+            assert!(!fs_name.contains('/'));
+            assert!(!fs_name.contains('\\'));
+            assert!(!fs_name.contains('.'));
+        }
+    }
+
+    #[test]
+    fn unicode() -> Result<(), AsJsonError> {
+        let channel = ChannelName::new("канал 🦀").unwrap();
+
+        assert_eq!(channel.to_string(), "канал 🦀");
+        assert!(channel.to_fs_name().is_ascii());
+
+        assert_eq!(ChannelName::from_fs_name(&channel.to_fs_name()), Some(channel.clone()));
+        assert_eq!(ChannelName::from_fs_name("chat%2Falice"), Some(ChannelName::new("chat/alice").unwrap()));
+        assert_eq!(ChannelName::from_fs_name("broken%2"), None);
+
+        assert_eq!(ChannelName::from_json(&channel.to_json()?)?, channel);
+        assert_eq!(channel.to_json()?, Json::String(String::from("канал 🦀")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(ChannelName::try_from("default channel"), ChannelName::new("default channel"));
+        assert_eq!(ChannelName::try_from(String::new()), Err(ChannelNameError::Empty));
+        assert_eq!(ChannelName::try_from(&String::from("amogus\0")), Err(ChannelNameError::InvalidCharacter('\0')));
+
+        assert!(ChannelName::from_json(&Json::String(String::new())).is_err());
+        assert!(ChannelName::from_json(&Json::String("a".repeat(65))).is_err());
+
+        assert!(serde_json::from_str::<ChannelName>("\"line\\nbreak\"").is_err());
+        assert_eq!(serde_json::from_str::<ChannelName>("\"default channel\"").ok(), ChannelName::new("default channel").ok());
+    }
+}
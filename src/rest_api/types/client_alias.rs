@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+use serde_json::Value as Json;
+
+use crate::rest_api::{AsJson, AsJsonError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum ClientAliasError {
+    #[error("Client alias is too short: {0} bytes, minimum is {min}", min = ClientAlias::MIN_LENGTH)]
+    TooShort(usize),
+
+    #[error("Client alias is too long: {0} bytes, maximum is {max}", max = ClientAlias::MAX_LENGTH)]
+    TooLong(usize),
+
+    #[error("Client alias contains forbidden character: {0:?}")]
+    InvalidCharacter(char)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+/// Short name of the client registered on its server.
+/// 
+/// Aliases are unique within a single server and can be
+/// used to lookup the server's local clients instead of
+/// their public keys. Alias can contain lowercase ASCII
+/// letters, digits, `-`, `_` and `.`, and must be from
+/// `ClientAlias::MIN_LENGTH` to `ClientAlias::MAX_LENGTH`
+/// bytes long.
+/// 
+/// Deserialized aliases are not validated. Use `validate`
+/// method when the alias comes from an untrusted source.
+/// 
+/// # Example
+/// 
+/// ```rust
+/// use hyperborealib::rest_api::prelude::*;
+/// 
+/// let alias = ClientAlias::new("alice").unwrap();
+/// 
+/// assert_eq!(alias.to_string(), "alice");
+/// 
+/// assert!(ClientAlias::new("al").is_err());
+/// assert!(ClientAlias::new("Alice").is_err());
+/// assert!(ClientAlias::new("alice bob").is_err());
+/// ```
+pub struct ClientAlias(String);
+
+impl ClientAlias {
+    /// Minimal length of the alias in bytes.
+    pub const MIN_LENGTH: usize = 3;
+
+    /// Maximal length of the alias in bytes.
+    pub const MAX_LENGTH: usize = 32;
+
+    /// Create new validated client alias.
+    pub fn new(alias: impl ToString) -> Result<Self, ClientAliasError> {
+        let alias = Self(alias.to_string());
+
+        alias.validate()?;
+
+        Ok(alias)
+    }
+
+    /// Check that the alias is valid.
+    pub fn validate(&self) -> Result<(), ClientAliasError> {
+        if self.0.len() < Self::MIN_LENGTH {
+            return Err(ClientAliasError::TooShort(self.0.len()));
+        }
+
+        if self.0.len() > Self::MAX_LENGTH {
+            return Err(ClientAliasError::TooLong(self.0.len()));
+        }
+
+        let allowed = |char: &char| char.is_ascii_lowercase() || char.is_ascii_digit() || matches!(char, '-' | '_' | '.');
+
+        if let Some(char) = self.0.chars().find(|char| !allowed(char)) {
+            return Err(ClientAliasError::InvalidCharacter(char));
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ClientAlias {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for ClientAlias {
+    type Err = ClientAliasError;
+
+    #[inline]
+    fn from_str(alias: &str) -> Result<Self, Self::Err> {
+        Self::new(alias)
+    }
+}
+
+impl AsRef<str> for ClientAlias {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<ClientAlias> for String {
+    #[inline]
+    fn from(alias: ClientAlias) -> Self {
+        alias.0
+    }
+}
+
+impl AsJson for ClientAlias {
+    #[inline]
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(Json::String(self.0.clone()))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        json.as_str()
+            .map(|alias| Self(alias.to_string()))
+            .ok_or_else(|| AsJsonError::FieldValueInvalid("alias"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        assert!(ClientAlias::new("alice").is_ok());
+        assert!(ClientAlias::new("bob-2.home_pc").is_ok());
+
+        assert_eq!(ClientAlias::new("ab"), Err(ClientAliasError::TooShort(2)));
+        assert_eq!(ClientAlias::new("a".repeat(33)), Err(ClientAliasError::TooLong(33)));
+        assert_eq!(ClientAlias::new("Alice"), Err(ClientAliasError::InvalidCharacter('A')));
+        assert_eq!(ClientAlias::new("alice/bob"), Err(ClientAliasError::InvalidCharacter('/')));
+        assert_eq!(ClientAlias::new("алиса"), Err(ClientAliasError::InvalidCharacter('а')));
+    }
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let alias = ClientAlias::new("alice").unwrap();
+
+        assert_eq!(ClientAlias::from_json(&alias.to_json()?)?, alias);
+
+        // Deserialized aliases are validated separately
+        assert!(!ClientAlias::from_json(&Json::from("Not Valid"))?.is_valid());
+
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use serde_json::{json, Value as Json};
 use crate::crypto::prelude::*;
 
 use crate::rest_api::{AsJson, AsJsonError};
-use crate::rest_api::types::ConnectionToken;
+use crate::rest_api::types::{ConnectionToken, CertificateScope};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -14,7 +14,17 @@ use crate::rest_api::types::ConnectionToken;
 /// hyperborea protocol's paper.
 pub struct ConnectionCertificate {
     pub token: ConnectionToken,
-    pub sign: Vec<u8>
+    pub sign: Vec<u8>,
+
+    /// Optional restriction of the channels and operations
+    /// the client is allowed to use. Covered by the signature.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub scope: Option<CertificateScope>,
+
+    /// Optional UTC timestamp after which the certificate
+    /// is not valid anymore. Covered by the signature.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub expires_at: Option<u64>
 }
 
 impl ConnectionCertificate {
@@ -45,10 +55,124 @@ impl ConnectionCertificate {
 
         Self {
             token,
-            sign
+            sign,
+            scope: None,
+            expires_at: None
         }
     }
 
+    /// Create new connection certificate restricted
+    /// to the given scope.
+    /// 
+    /// Servers will reject requests of the client which
+    /// are not allowed by the scope.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let client_secret = SecretKey::random();
+    /// let server_secret = SecretKey::random();
+    /// 
+    /// let scope = CertificateScope::new(
+    ///     vec![ChannelRule::Exact(ChannelName::new("status").unwrap())],
+    ///     vec![CertificateOperation::Send]
+    /// );
+    /// 
+    /// let certificate = ConnectionCertificate::new_scoped(&client_secret, server_secret.public_key(), scope);
+    /// 
+    /// assert!(certificate.validate(
+    ///     &client_secret.public_key(),
+    ///     &server_secret.public_key()
+    /// ).unwrap());
+    /// ```
+    pub fn new_scoped(client_secret: &SecretKey, server_public: PublicKey, scope: CertificateScope) -> Self {
+        let token = ConnectionToken::now(server_public);
+
+        let scope = Some(scope);
+
+        let sign = client_secret.create_signature(Self::signed_data(&token, scope.as_ref(), None));
+
+        Self {
+            token,
+            sign,
+            scope,
+            expires_at: None
+        }
+    }
+
+    /// Create new connection certificate which
+    /// expires after the given amount of seconds.
+    /// 
+    /// Servers will reject requests of the client
+    /// made after the certificate has expired, so
+    /// it should be renewed by connecting again.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let client_secret = SecretKey::random();
+    /// let server_secret = SecretKey::random();
+    /// 
+    /// let certificate = ConnectionCertificate::new_expiring(&client_secret, server_secret.public_key(), None, 3600);
+    /// 
+    /// assert_eq!(certificate.expires_at, Some(certificate.token.auth_date + 3600));
+    /// assert!(!certificate.is_expired(certificate.token.auth_date));
+    /// 
+    /// assert!(certificate.validate(
+    ///     &client_secret.public_key(),
+    ///     &server_secret.public_key()
+    /// ).unwrap());
+    /// ```
+    pub fn new_expiring(client_secret: &SecretKey, server_public: PublicKey, scope: Option<CertificateScope>, lifetime: u64) -> Self {
+        let token = ConnectionToken::now(server_public);
+
+        let expires_at = Some(token.auth_date.saturating_add(lifetime));
+
+        let sign = client_secret.create_signature(Self::signed_data(&token, scope.as_ref(), expires_at));
+
+        Self {
+            token,
+            sign,
+            scope,
+            expires_at
+        }
+    }
+
+    /// Get bytes covered by the certificate's signature.
+    /// 
+    /// Unscoped certificates without expiration date sign
+    /// only the connection token to stay compatible with
+    /// the older clients.
+    fn signed_data(token: &ConnectionToken, scope: Option<&CertificateScope>, expires_at: Option<u64>) -> Vec<u8> {
+        let mut data = token.to_bytes().to_vec();
+
+        if let Some(scope) = scope {
+            data.extend(scope.to_bytes());
+        }
+
+        if let Some(expires_at) = expires_at {
+            data.extend(expires_at.to_be_bytes());
+        }
+
+        data
+    }
+
+    #[inline]
+    /// Check if the certificate is expired at
+    /// the given UTC timestamp.
+    /// 
+    /// Certificates without expiration date
+    /// never expire.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
     /// Verify thath certificate is signed by a client
     /// with given public key and is addressed to
     /// a server with given public key.
@@ -80,16 +204,26 @@ impl ConnectionCertificate {
             return Ok(false);
         }
 
-        client_public.verify_signature(self.token.to_bytes(), &self.sign)
+        client_public.verify_signature(Self::signed_data(&self.token, self.scope.as_ref(), self.expires_at), &self.sign)
     }
 }
 
 impl AsJson for ConnectionCertificate {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({
+        let mut json = json!({
             "token": base64_encode(self.token.to_bytes()),
             "sign": base64_encode(&self.sign)
-        }))
+        });
+
+        if let Some(scope) = &self.scope {
+            json["scope"] = scope.to_json()?;
+        }
+
+        if let Some(expires_at) = self.expires_at {
+            json["expires_at"] = Json::from(expires_at);
+        }
+
+        Ok(json)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
@@ -103,13 +237,24 @@ impl AsJson for ConnectionCertificate {
 
         Ok(Self {
             token: ConnectionToken::from_bytes(base64_decode(token)?)?,
-            sign: base64_decode(sign)?
+            sign: base64_decode(sign)?,
+
+            scope: json.get("scope")
+                .map(CertificateScope::from_json)
+                .transpose()?,
+
+            expires_at: json.get("expires_at")
+                .map(|expires_at| expires_at.as_u64().ok_or(AsJsonError::FieldValueInvalid("expires_at")))
+                .transpose()?
         })
     }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
+    use crate::rest_api::wire_fixtures::load;
+    use crate::rest_api::types::certificate_scope::tests::get_scope;
+
     use super::*;
 
     pub fn get_certificate() -> ConnectionCertificate {
@@ -125,6 +270,66 @@ pub(crate) mod tests {
 
         assert_eq!(ConnectionCertificate::from_json(&cert.to_json()?)?, cert);
 
+        let cert = ConnectionCertificate::new_scoped(&SecretKey::random(), SecretKey::random().public_key(), get_scope());
+
+        assert_eq!(ConnectionCertificate::from_json(&cert.to_json()?)?, cert);
+
+        let cert = ConnectionCertificate::new_expiring(&SecretKey::random(), SecretKey::random().public_key(), Some(get_scope()), 60);
+
+        assert_eq!(ConnectionCertificate::from_json(&cert.to_json()?)?, cert);
+
+        Ok(())
+    }
+
+    #[test]
+    fn expiry() -> Result<(), CryptographyError> {
+        let client_secret = SecretKey::random();
+        let server_public = SecretKey::random().public_key();
+
+        let mut cert = ConnectionCertificate::new_expiring(&client_secret, server_public.clone(), None, 60);
+
+        let auth_date = cert.token.auth_date;
+
+        assert!(!cert.is_expired(auth_date + 59));
+        assert!(cert.is_expired(auth_date + 60));
+
+        assert!(cert.validate(&client_secret.public_key(), &server_public)?);
+
+        // Expiration date is covered by the signature
+        cert.expires_at = Some(auth_date + 3600);
+
+        assert!(!cert.validate(&client_secret.public_key(), &server_public)?);
+
+        cert.expires_at = None;
+
+        assert!(!cert.validate(&client_secret.public_key(), &server_public)?);
+        assert!(!cert.is_expired(u64::MAX));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fixtures() -> Result<(), AsJsonError> {
+        for (name, scoped) in [("connect_request", false), ("connect_scoped_request", true)] {
+            let fixture = load(name).unwrap();
+
+            let client_public = PublicKey::from_base64(fixture["public_key"].as_str().unwrap())?;
+            let mut cert = ConnectionCertificate::from_json(&fixture["request"]["certificate"])?;
+
+            let server_public = cert.token.public_key.clone();
+
+            assert_eq!(cert.scope.is_some(), scoped);
+            assert!(cert.validate(&client_public, &server_public)?);
+
+            // Scope is covered by the signature
+            cert.scope = match cert.scope {
+                Some(_) => None,
+                None => Some(get_scope())
+            };
+
+            assert!(!cert.validate(&client_public, &server_public)?);
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,301 @@
+use serde_json::{json, Value as Json};
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+use crate::time::timestamp;
+
+use super::MessagesError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Delivery state of the message reported by the receipt.
+pub enum DeliveryStatus {
+    /// Message was stored in the receiver's inbox.
+    Delivered,
+
+    /// Message was polled by the receiver.
+    Polled
+}
+
+impl DeliveryStatus {
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Delivered => "delivered",
+            Self::Polled    => "polled"
+        }
+    }
+
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "delivered" => Some(Self::Delivered),
+            "polled"    => Some(Self::Polled),
+
+            _ => None
+        }
+    }
+}
+
+impl std::fmt::Display for DeliveryStatus {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Delivery receipt of the sent message.
+/// 
+/// Receipts are requested by the `receipt` flag of the
+/// `POST /api/v1/send` request. They're generated by the
+/// receiver's server and stored in its inbox for the
+/// message's sender in the `DeliveryReceipt::CHANNEL`
+/// channel, as messages sent by the server itself.
+/// 
+/// Receipts are signed by the receiver's server
+/// so they can be verified by anyone.
+pub struct DeliveryReceipt {
+    /// Server-assigned id of the message.
+    /// 
+    /// Same id is returned to the sender by the
+    /// `POST /api/v1/send` request. Not set by the
+    /// servers which don't assign ids.
+    pub message_id: Option<u64>,
+
+    pub receiver: PublicKey,
+    pub channel: ChannelName,
+    pub status: DeliveryStatus,
+    pub timestamp: u64,
+
+    /// Public key of the server which issued the receipt.
+    pub server: PublicKey,
+    pub sign: Vec<u8>
+}
+
+impl DeliveryReceipt {
+    /// Name of the channel receipts are sent to.
+    pub const CHANNEL: &'static str = "hyperborea/receipts";
+
+    /// Create new receipt signed by the server.
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let server = SecretKey::random();
+    /// let receiver = SecretKey::random().public_key();
+    /// 
+    /// let receipt = DeliveryReceipt::new(&server, Some(17), receiver, ChannelName::new("example channel").unwrap(), DeliveryStatus::Delivered);
+    /// 
+    /// assert!(receipt.validate().unwrap());
+    /// ```
+    pub fn new(
+        server_secret: &SecretKey,
+        message_id: Option<u64>,
+        receiver: PublicKey,
+        channel: impl Into<ChannelName>,
+        status: DeliveryStatus
+    ) -> Self {
+        let mut receipt = Self {
+            message_id,
+            receiver,
+            channel: channel.into(),
+            status,
+            timestamp: timestamp(),
+            server: server_secret.public_key(),
+            sign: Vec::new()
+        };
+
+        receipt.sign = server_secret.create_signature(receipt.to_bytes());
+
+        receipt
+    }
+
+    /// Get bytes of the receipt covered by its signature.
+    fn to_bytes(&self) -> Vec<u8> {
+        let channel = self.channel.to_string();
+
+        let mut bytes = Vec::new();
+
+        match self.message_id {
+            Some(id) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&id.to_be_bytes());
+            }
+
+            None => bytes.push(0)
+        }
+
+        bytes.extend_from_slice(&self.receiver.to_bytes());
+        bytes.extend_from_slice(&(channel.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(channel.as_bytes());
+        bytes.extend_from_slice(self.status.name().as_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+
+        bytes
+    }
+
+    #[inline]
+    /// Verify the receipt's signature.
+    pub fn validate(&self) -> Result<bool, ValidationError> {
+        Ok(self.server.verify_signature(self.to_bytes(), &self.sign)?)
+    }
+
+    /// Build message of the receipt addressed
+    /// to the original message's sender.
+    /// 
+    /// Message is signed and encrypted using the
+    /// server's secret key as its sender.
+    pub fn to_message(&self, server_secret: &SecretKey, sender: &PublicKey, encoding: MessageEncoding) -> Result<Message, MessagesError> {
+        let content = serde_json::to_vec(&self.to_json()?)
+            .map_err(AsJsonError::from)?;
+
+        Message::create(server_secret, sender, content, encoding, CompressionLevel::default())
+    }
+
+    /// Read receipt from the message polled
+    /// from the `DeliveryReceipt::CHANNEL` channel.
+    /// 
+    /// - `sender_secret` must contain reference to the secret
+    ///   key of the original message's sender.
+    /// 
+    /// Both the message and the receipt must be signed
+    /// by the server which sent the receipt.
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let server_secret = SecretKey::random();
+    /// let sender_secret = SecretKey::random();
+    /// 
+    /// let receipt = DeliveryReceipt::new(
+    ///     &server_secret,
+    ///     Some(17),
+    ///     SecretKey::random().public_key(),
+    ///     ChannelName::new("example channel").unwrap(),
+    ///     DeliveryStatus::Polled
+    /// );
+    /// 
+    /// let message = receipt.to_message(&server_secret, &sender_secret.public_key(), MessageEncoding::default()).unwrap();
+    /// 
+    /// let certificate = ConnectionCertificate::new(&server_secret, server_secret.public_key());
+    /// let client = Client::new(server_secret.public_key(), certificate, ClientInfo::server("example.org"));
+    /// let server = Server::new(server_secret.public_key(), "example.org");
+    /// 
+    /// let info = MessageInfo::now(Sender::new(client, server), ChannelName::new(DeliveryReceipt::CHANNEL).unwrap(), message);
+    /// 
+    /// assert_eq!(DeliveryReceipt::read(&info, &sender_secret).unwrap(), receipt);
+    /// ```
+    pub fn read(message_info: &MessageInfo, sender_secret: &SecretKey) -> Result<Self, MessagesError> {
+        let server = &message_info.sender.client.public_key;
+
+        let content = message_info.message.read(sender_secret, server)?;
+
+        let receipt = serde_json::from_slice(&content)
+            .map_err(AsJsonError::from)
+            .and_then(|receipt| Self::from_json(&receipt))?;
+
+        if &receipt.server != server || !receipt.validate().unwrap_or(false) {
+            return Err(MessagesError::InvalidReceiptSignature);
+        }
+
+        Ok(receipt)
+    }
+}
+
+impl AsJson for DeliveryReceipt {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let mut receipt = json!({
+            "receiver": {
+                "public_key": self.receiver.to_base64()
+            },
+            "channel": self.channel.to_json()?,
+            "status": self.status.name(),
+            "timestamp": self.timestamp,
+            "proof": {
+                "public_key": self.server.to_base64(),
+                "sign": base64_encode(&self.sign)
+            }
+        });
+
+        if let Some(id) = self.message_id {
+            receipt["id"] = Json::from(id);
+        }
+
+        Ok(receipt)
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(proof) = json.get("proof") else {
+            return Err(AsJsonError::FieldNotFound("proof"));
+        };
+
+        Ok(Self {
+            message_id: match json.get("id") {
+                Some(id) => Some(id.as_u64().ok_or(AsJsonError::FieldValueInvalid("id"))?),
+                None => None
+            },
+
+            receiver: json.get("receiver")
+                .and_then(|receiver| receiver.get("public_key"))
+                .and_then(Json::as_str)
+                .map(PublicKey::from_base64)
+                .ok_or_else(|| AsJsonError::FieldNotFound("receiver.public_key"))??,
+
+            channel: json.get("channel")
+                .map(ChannelName::from_json)
+                .ok_or_else(|| AsJsonError::FieldNotFound("channel"))??,
+
+            status: json.get("status")
+                .and_then(Json::as_str)
+                .ok_or_else(|| AsJsonError::FieldNotFound("status"))
+                .map(DeliveryStatus::from_name)?
+                .ok_or_else(|| AsJsonError::FieldValueInvalid("status"))?,
+
+            timestamp: json.get("timestamp")
+                .and_then(Json::as_u64)
+                .ok_or_else(|| AsJsonError::FieldNotFound("timestamp"))?,
+
+            server: proof.get("public_key")
+                .and_then(Json::as_str)
+                .map(PublicKey::from_base64)
+                .ok_or_else(|| AsJsonError::FieldNotFound("proof.public_key"))??,
+
+            sign: proof.get("sign")
+                .and_then(Json::as_str)
+                .map(base64_decode)
+                .ok_or_else(|| AsJsonError::FieldNotFound("proof.sign"))??
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), Box<dyn std::error::Error>> {
+        let server = SecretKey::random();
+        let receiver = SecretKey::random().public_key();
+
+        for message_id in [Some(17), None] {
+            let receipt = DeliveryReceipt::new(&server, message_id, receiver.clone(), ChannelName::new("channel").unwrap(), DeliveryStatus::Delivered);
+
+            assert_eq!(DeliveryReceipt::from_json(&receipt.to_json()?)?, receipt);
+            assert!(receipt.validate()?);
+        }
+
+        // Signature covers the receipt's fields
+        let mut receipt = DeliveryReceipt::new(&server, Some(17), receiver, ChannelName::new("channel").unwrap(), DeliveryStatus::Delivered);
+
+        receipt.status = DeliveryStatus::Polled;
+
+        assert!(!receipt.validate()?);
+
+        Ok(())
+    }
+}
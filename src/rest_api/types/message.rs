@@ -16,7 +16,16 @@ use super::{MessageEncoding, MessagesError};
 pub struct Message {
     pub content: String,
     pub sign: String,
-    pub encoding: MessageEncoding
+    pub encoding: MessageEncoding,
+
+    /// Sequence number of the message within its
+    /// (sender, receiver, channel) stream.
+    /// 
+    /// Sequence number is covered by the message's
+    /// signature, so it can't be changed by the servers
+    /// relaying the message.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub sequence: Option<u64>
 }
 
 impl Message {
@@ -62,7 +71,8 @@ impl Message {
         Self {
             content: content.to_string(),
             sign: sign.to_string(),
-            encoding
+            encoding,
+            sequence: None
         }
     }
 
@@ -119,10 +129,69 @@ impl Message {
         Ok(Self {
             content: encoding.forward(data, &secret, level)?,
             sign: encoding.forward(sign, &secret, level)?,
-            encoding
+            encoding,
+            sequence: None
         })
     }
 
+    /// Build new message with a sequence number.
+    /// 
+    /// Same as `create`, but the message's signature
+    /// covers both its content and the sequence number.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use std::str::FromStr;
+    /// 
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let sender = SecretKey::random();
+    /// let receiver = SecretKey::random();
+    /// 
+    /// let encoding = MessageEncoding::from_str("base64/aes256-gcm/deflate").unwrap();
+    /// 
+    /// let mut message = Message::create_sequenced(
+    ///     &sender,
+    ///     &receiver.public_key(),
+    ///     b"Hello, World!",
+    ///     7,
+    ///     encoding,
+    ///     CompressionLevel::default()
+    /// ).unwrap();
+    /// 
+    /// assert_eq!(message.read(&receiver, &sender.public_key()).unwrap(), b"Hello, World!");
+    /// 
+    /// // Sequence number can't be changed
+    /// message.sequence = Some(8);
+    /// 
+    /// assert!(message.read(&receiver, &sender.public_key()).is_err());
+    /// ```
+    pub fn create_sequenced(sender: &SecretKey, receiver: &PublicKey, data: impl AsRef<[u8]>, sequence: u64, encoding: MessageEncoding, level: CompressionLevel) -> Result<Self, MessagesError> {
+        let secret = sender.create_shared_secret(receiver, None);
+
+        let sign = sender.create_signature(Self::signed_data(data.as_ref(), Some(sequence)));
+
+        Ok(Self {
+            content: encoding.forward(data, &secret, level)?,
+            sign: encoding.forward(sign, &secret, level)?,
+            encoding,
+            sequence: Some(sequence)
+        })
+    }
+
+    /// Get bytes covered by the message's signature.
+    fn signed_data(content: &[u8], sequence: Option<u64>) -> Vec<u8> {
+        let mut data = content.to_vec();
+
+        if let Some(sequence) = sequence {
+            data.extend_from_slice(&sequence.to_be_bytes());
+        }
+
+        data
+    }
+
     /// Read decoded message's content.
     /// 
     /// This method will decrypt, decompress and decode stored
@@ -169,7 +238,7 @@ impl Message {
         let content = self.encoding.backward(&self.content, &secret)?;
         let sign = self.encoding.backward(&self.sign, &secret)?;
 
-        if !sender.verify_signature(&content, sign)? {
+        if !sender.verify_signature(Self::signed_data(&content, self.sequence), sign)? {
             return Err(MessagesError::InvalidMessageSignature);
         }
 
@@ -179,11 +248,17 @@ impl Message {
 
 impl AsJson for Message {
     fn to_json(&self) -> Result<Json, AsJsonError> {
-        Ok(json!({
+        let mut message = json!({
             "content": self.content,
             "sign": self.sign,
             "encoding": self.encoding.to_string()
-        }))
+        });
+
+        if let Some(sequence) = self.sequence {
+            message["sequence"] = Json::from(sequence);
+        }
+
+        Ok(message)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
@@ -202,7 +277,12 @@ impl AsJson for Message {
                 .and_then(Json::as_str)
                 .map(MessageEncoding::from_str)
                 .ok_or_else(|| AsJsonError::FieldNotFound("encoding"))?
-                .map_err(|format| AsJsonError::Other(format!("Field 'encoding' contained invalid message encoding format: '{format}'").into()))?
+                .map_err(|format| AsJsonError::Other(format!("Field 'encoding' contained invalid message encoding format: '{format}'").into()))?,
+
+            sequence: match json.get("sequence") {
+                Some(sequence) => Some(sequence.as_u64().ok_or(AsJsonError::FieldValueInvalid("sequence"))?),
+                None => None
+            }
         })
     }
 }
@@ -228,6 +308,17 @@ mod tests {
             ).unwrap();
 
             assert_eq!(Message::from_json(&message.to_json()?)?, message);
+
+            let message = Message::create_sequenced(
+                &sender,
+                &receiver.public_key(),
+                b"Hello, World!",
+                u64::MAX,
+                encoding,
+                CompressionLevel::default()
+            ).unwrap();
+
+            assert_eq!(Message::from_json(&message.to_json()?)?, message);
         }
 
         Ok(())
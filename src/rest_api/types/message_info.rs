@@ -12,9 +12,18 @@ use crate::time::timestamp;
 /// hyperborea protocol's paper.
 pub struct MessageInfo {
     pub sender: Sender,
-    pub channel: String,
+    pub channel: ChannelName,
     pub message: Message,
-    pub received_at: u64
+    pub received_at: u64,
+
+    /// Server-assigned id of the message.
+    /// 
+    /// Same id is returned to the sender by the
+    /// `POST /api/v1/send` request, and is used to
+    /// acknowledge the messages polled with a lease.
+    /// 
+    /// Not set by the servers which don't assign ids.
+    pub id: Option<u64>
 }
 
 impl MessageInfo {
@@ -78,35 +87,52 @@ impl MessageInfo {
     /// // Prepare message info
     /// let message_info = MessageInfo::new(
     ///     sender,
-    ///     "example channel",
+    ///     ChannelName::new("example channel").unwrap(),
     ///     message,
     ///     timestamp()
     /// );
     /// ```
-    pub fn new(sender: Sender, channel: impl ToString, message: Message, received_at: u64) -> Self {
+    pub fn new(sender: Sender, channel: impl Into<ChannelName>, message: Message, received_at: u64) -> Self {
         Self {
             sender,
-            channel: channel.to_string(),
+            channel: channel.into(),
             message,
-            received_at
+            received_at,
+            id: None
+        }
+    }
+
+    #[inline]
+    /// Set server-assigned id of the message.
+    pub fn with_id(self, id: u64) -> Self {
+        Self {
+            id: Some(id),
+            ..self
         }
     }
 
     #[inline]
     /// Run `new()` method with current timestamp.
-    pub fn now(sender: Sender, channel: impl ToString, message: Message) -> Self {
+    pub fn now(sender: Sender, channel: impl Into<ChannelName>, message: Message) -> Self {
         Self::new(sender, channel, message, timestamp())
     }
 }
 
 impl AsJson for MessageInfo {
     fn to_json(&self) -> Result<serde_json::Value, AsJsonError> {
-        Ok(json!({
+        let mut info = json!({
             "sender": self.sender.to_json()?,
-            "channel": self.channel,
+            "channel": self.channel.to_json()?,
             "message": self.message.to_json()?,
             "received_at": self.received_at
-        }))
+        });
+
+        // Keep legacy shape for messages without an id
+        if let Some(id) = self.id {
+            info["id"] = json!(id);
+        }
+
+        Ok(info)
     }
 
     fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
@@ -116,9 +142,8 @@ impl AsJson for MessageInfo {
                 .ok_or_else(|| AsJsonError::FieldNotFound("sender"))??,
 
             channel: json.get("channel")
-                .and_then(Json::as_str)
-                .map(String::from)
-                .ok_or_else(|| AsJsonError::FieldNotFound("channel"))?,
+                .map(ChannelName::from_json)
+                .ok_or_else(|| AsJsonError::FieldNotFound("channel"))??,
 
             message: json.get("message")
                 .map(Message::from_json)
@@ -126,7 +151,12 @@ impl AsJson for MessageInfo {
 
             received_at: json.get("received_at")
                 .and_then(Json::as_u64)
-                .ok_or_else(|| AsJsonError::FieldNotFound("received_at"))?
+                .ok_or_else(|| AsJsonError::FieldNotFound("received_at"))?,
+
+            id: match json.get("id") {
+                Some(id) => Some(id.as_u64().ok_or_else(|| AsJsonError::FieldValueInvalid("id"))?),
+                None => None
+            }
         })
     }
 }
@@ -143,7 +173,7 @@ pub(crate) mod tests {
         let encoding = MessageEncoding::from_str("base64").unwrap();
         let message = Message::new("content", "sign", encoding);
 
-        MessageInfo::now(get_sender(), "Hello, World!", message)
+        MessageInfo::now(get_sender(), ChannelName::new("Hello, World!").unwrap(), message)
     }
 
     #[test]
@@ -152,6 +182,11 @@ pub(crate) mod tests {
 
         assert_eq!(MessageInfo::from_json(&message_info.to_json()?)?, message_info);
 
+        let message_info = message_info.with_id(17);
+
+        assert_eq!(message_info.to_json()?["id"], 17);
+        assert_eq!(MessageInfo::from_json(&message_info.to_json()?)?, message_info);
+
         Ok(())
     }
 }
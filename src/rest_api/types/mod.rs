@@ -13,25 +13,39 @@ use crate::crypto::Error as CryptographyError;
 
 pub(crate) mod client_type;
 pub(crate) mod client_info;
+pub(crate) mod client_alias;
 pub(crate) mod connection_token;
+pub(crate) mod certificate_scope;
 pub(crate) mod connection_certificate;
 pub(crate) mod client;
 pub(crate) mod server;
+pub(crate) mod server_hint;
+pub(crate) mod server_capabilities;
+pub(crate) mod channel_name;
 pub(crate) mod message_info;
+pub(crate) mod sealed_message_info;
 pub(crate) mod message_encoding;
 pub(crate) mod sender;
 pub(crate) mod message;
+pub(crate) mod delivery_receipt;
 
 pub use client_type::*;
 pub use client_info::*;
+pub use client_alias::*;
 pub use connection_token::*;
+pub use certificate_scope::*;
 pub use connection_certificate::*;
 pub use client::*;
 pub use server::*;
+pub use server_hint::*;
+pub use server_capabilities::*;
+pub use channel_name::*;
 pub use message_info::*;
+pub use sealed_message_info::*;
 pub use message_encoding::*;
 pub use sender::*;
 pub use message::*;
+pub use delivery_receipt::*;
 
 #[derive(Debug, thiserror::Error)]
 pub enum MessagesError {
@@ -41,6 +55,12 @@ pub enum MessagesError {
     #[error("Message's signature is invalid")]
     InvalidMessageSignature,
 
+    #[error("Delivery receipt's signature is invalid")]
+    InvalidReceiptSignature,
+
+    #[error(transparent)]
+    AsJsonError(#[from] crate::rest_api::AsJsonError),
+
     #[error(transparent)]
     CryptographyError(#[from] CryptographyError)
 }
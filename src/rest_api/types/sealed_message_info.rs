@@ -0,0 +1,150 @@
+use serde_json::{json, Value as Json};
+
+use crate::crypto::prelude::*;
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Message info sealed to its receiver.
+/// 
+/// The `MessageInfo` JSON is encrypted with a shared
+/// secret of an ephemeral keypair, generated per message,
+/// and the receiver's public key. Only the receiver's secret
+/// key can open it, so neither the stored files
+/// nor the server's secret key reveal the message.
+/// 
+/// Channel name and receiving time are kept unencrypted
+/// so the server can serve polls.
+pub struct SealedMessageInfo {
+    pub channel: ChannelName,
+    pub received_at: u64,
+    pub ephemeral_public: PublicKey,
+    pub content: Vec<u8>
+}
+
+impl SealedMessageInfo {
+    /// Seal given message info to the receiver
+    /// with given public key.
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// # let sender_secret = SecretKey::random();
+    /// # let server_secret = SecretKey::random();
+    /// # let certificate = ConnectionCertificate::new(&sender_secret, server_secret.public_key());
+    /// # let client = Client::new(sender_secret.public_key(), certificate, ClientInfo::thin());
+    /// # let server = Server::new(server_secret.public_key(), "example.org");
+    /// # let sender = Sender::new(client, server);
+    /// # let message = Message::new("content", "sign", MessageEncoding::default());
+    /// let receiver_secret = SecretKey::random();
+    /// 
+    /// let message_info = MessageInfo::now(sender, ChannelName::new("example channel").unwrap(), message);
+    /// 
+    /// let sealed = SealedMessageInfo::seal(&message_info, &receiver_secret.public_key()).unwrap();
+    /// 
+    /// assert_eq!(sealed.open(&receiver_secret).unwrap(), message_info);
+    /// ```
+    pub fn seal(message_info: &MessageInfo, receiver: &PublicKey) -> Result<Self, AsJsonError> {
+        let ephemeral_secret = SecretKey::random();
+        let secret = ephemeral_secret.create_shared_secret(receiver, None);
+
+        let content = serde_json::to_vec(&message_info.to_json()?)?;
+        let content = Encryption::ChaCha20Poly1305.encrypt(content, &secret)?;
+
+        Ok(Self {
+            channel: message_info.channel.clone(),
+            received_at: message_info.received_at,
+            ephemeral_public: ephemeral_secret.public_key(),
+            content
+        })
+    }
+
+    /// Open sealed message info using the receiver's secret key.
+    pub fn open(&self, receiver_secret: &SecretKey) -> Result<MessageInfo, AsJsonError> {
+        let secret = receiver_secret.create_shared_secret(&self.ephemeral_public, None);
+
+        let content = Encryption::ChaCha20Poly1305.decrypt(&self.content, &secret)?;
+
+        MessageInfo::from_json(&serde_json::from_slice(&content)?)
+    }
+
+    #[inline]
+    /// Size of the sealed content in bytes.
+    pub fn size(&self) -> usize {
+        self.content.len()
+    }
+}
+
+impl AsJson for SealedMessageInfo {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "channel": self.channel.to_json()?,
+            "received_at": self.received_at,
+            "sealed": {
+                "public_key": self.ephemeral_public.to_base64(),
+                "content": base64_encode(&self.content)
+            }
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(sealed) = json.get("sealed") else {
+            return Err(AsJsonError::FieldNotFound("sealed"));
+        };
+
+        Ok(Self {
+            channel: json.get("channel")
+                .map(ChannelName::from_json)
+                .ok_or_else(|| AsJsonError::FieldNotFound("channel"))??,
+
+            received_at: json.get("received_at")
+                .and_then(Json::as_u64)
+                .ok_or_else(|| AsJsonError::FieldNotFound("received_at"))?,
+
+            ephemeral_public: sealed.get("public_key")
+                .and_then(Json::as_str)
+                .map(PublicKey::from_base64)
+                .ok_or_else(|| AsJsonError::FieldNotFound("sealed.public_key"))??,
+
+            content: sealed.get("content")
+                .and_then(Json::as_str)
+                .map(base64_decode)
+                .ok_or_else(|| AsJsonError::FieldNotFound("sealed.content"))??
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rest_api::types::message_info::tests::get_message_info;
+
+    use super::*;
+
+    #[test]
+    fn seal_open() -> Result<(), AsJsonError> {
+        let receiver = SecretKey::random();
+        let message_info = get_message_info();
+
+        let sealed = SealedMessageInfo::seal(&message_info, &receiver.public_key())?;
+
+        assert_eq!(sealed.channel, message_info.channel);
+        assert_eq!(sealed.open(&receiver)?, message_info);
+
+        // Nobody else can open it
+        assert!(sealed.open(&SecretKey::random()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let sealed = SealedMessageInfo::seal(&get_message_info(), &SecretKey::random().public_key())?;
+
+        assert_eq!(SealedMessageInfo::from_json(&sealed.to_json()?)?, sealed);
+
+        Ok(())
+    }
+}
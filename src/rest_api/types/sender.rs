@@ -50,6 +50,33 @@ impl Sender {
             server
         }
     }
+
+    #[inline]
+    /// Verify that the client's connection certificate
+    /// is signed by the client for the sender's server.
+    /// 
+    /// ```rust
+    /// use hyperborealib::crypto::prelude::*;
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let client = SecretKey::random();
+    /// let server = SecretKey::random();
+    /// 
+    /// let certificate = ConnectionCertificate::new(&client, server.public_key());
+    /// let client = Client::new(client.public_key(), certificate, ClientInfo::thin());
+    /// 
+    /// let sender = Sender::new(client.clone(), Server::new(server.public_key(), "example.org"));
+    /// 
+    /// assert!(sender.validate().unwrap());
+    /// 
+    /// // Certificate is not signed for this server
+    /// let sender = Sender::new(client, Server::new(SecretKey::random().public_key(), "example.org"));
+    /// 
+    /// assert!(!sender.validate().unwrap());
+    /// ```
+    pub fn validate(&self) -> Result<bool, ValidationError> {
+        Ok(self.client.certificate.validate(&self.client.public_key, &self.server.public_key)?)
+    }
 }
 
 impl AsJson for Sender {
@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value as Json};
+
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Features and limits advertised by the server
+/// in its `GET /api/v1/info` response.
+/// 
+/// Capabilities are named boolean flags, and limits
+/// are named integers. Names unknown to this version
+/// of the library are kept as is, so they're still
+/// covered by the response's signature.
+/// 
+/// Servers which don't advertise their capabilities
+/// are expected to support none of them.
+pub struct ServerCapabilities {
+    pub capabilities: BTreeMap<String, bool>,
+    pub limits: BTreeMap<String, u64>
+}
+
+impl ServerCapabilities {
+    /// `POST /api/v1/send_batch` requests are supported.
+    pub const SEND_BATCH: &'static str = "send_batch";
+
+    /// `POST /api/v1/lookup_batch` requests are supported.
+    pub const LOOKUP_BATCH: &'static str = "lookup_batch";
+
+    /// Polled messages are leased until acknowledged
+    /// by the `POST /api/v1/ack` request.
+    pub const ACK_POLL: &'static str = "ack_poll";
+
+    /// Expired and replayed signed requests are rejected.
+    pub const REPLAY_PROTECTION: &'static str = "replay_protection";
+
+    /// Requests are rate limited.
+    pub const RATE_LIMITS: &'static str = "rate_limits";
+
+    /// Delivery receipts requested by the `POST /api/v1/send`
+    /// requests are stored in the `DeliveryReceipt::CHANNEL`.
+    pub const DELIVERY_RECEIPTS: &'static str = "delivery_receipts";
+
+    /// Polls can wait for new messages
+    /// when the client's inbox is empty.
+    pub const LONG_POLL: &'static str = "long_poll";
+
+    /// Maximal size of the sent message's content in bytes.
+    pub const MAX_MESSAGE_SIZE: &'static str = "max_message_size";
+
+    /// Maximal amount of the messages in `POST /api/v1/send_batch`.
+    pub const MAX_BATCH_SIZE: &'static str = "max_batch_size";
+
+    /// Maximal amount of the clients in `POST /api/v1/lookup_batch`.
+    pub const MAX_LOOKUP_BATCH_SIZE: &'static str = "max_lookup_batch_size";
+
+    /// Maximal lifetime of the connection certificates in seconds.
+    pub const CERTIFICATE_LIFETIME: &'static str = "certificate_lifetime";
+
+    /// Lease time of the polled messages in seconds.
+    pub const POLL_LEASE: &'static str = "poll_lease";
+
+    /// Maximal waiting time of the long polls in milliseconds.
+    pub const MAX_POLL_WAIT: &'static str = "max_poll_wait";
+
+    /// Proof of work difficulty of the `POST /api/v1/connect`
+    /// requests, in leading zero bits.
+    pub const CONNECT_DIFFICULTY: &'static str = "connect_difficulty";
+
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    /// Set value of the named capability flag.
+    pub fn with_capability(mut self, name: impl ToString, enabled: bool) -> Self {
+        self.capabilities.insert(name.to_string(), enabled);
+
+        self
+    }
+
+    #[inline]
+    /// Set value of the named limit.
+    pub fn with_limit(mut self, name: impl ToString, value: u64) -> Self {
+        self.limits.insert(name.to_string(), value);
+
+        self
+    }
+
+    #[inline]
+    /// Check if the named capability is enabled.
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let capabilities = ServerCapabilities::new()
+    ///     .with_capability(ServerCapabilities::SEND_BATCH, true);
+    /// 
+    /// assert!(capabilities.supports(ServerCapabilities::SEND_BATCH));
+    /// assert!(!capabilities.supports("future_capability"));
+    /// ```
+    pub fn supports(&self, name: &str) -> bool {
+        self.capabilities.get(name).copied().unwrap_or(false)
+    }
+
+    #[inline]
+    /// Get value of the named limit.
+    pub fn limit(&self, name: &str) -> Option<u64> {
+        self.limits.get(name).copied()
+    }
+
+    #[inline]
+    pub fn send_batch(&self) -> bool {
+        self.supports(Self::SEND_BATCH)
+    }
+
+    #[inline]
+    pub fn lookup_batch(&self) -> bool {
+        self.supports(Self::LOOKUP_BATCH)
+    }
+
+    #[inline]
+    pub fn ack_poll(&self) -> bool {
+        self.supports(Self::ACK_POLL)
+    }
+
+    #[inline]
+    pub fn long_poll(&self) -> bool {
+        self.supports(Self::LONG_POLL)
+    }
+
+    #[inline]
+    pub fn max_message_size(&self) -> Option<u64> {
+        self.limit(Self::MAX_MESSAGE_SIZE)
+    }
+
+    #[inline]
+    pub fn max_batch_size(&self) -> Option<u64> {
+        self.limit(Self::MAX_BATCH_SIZE)
+    }
+
+    #[inline]
+    pub fn max_lookup_batch_size(&self) -> Option<u64> {
+        self.limit(Self::MAX_LOOKUP_BATCH_SIZE)
+    }
+
+    #[inline]
+    pub fn certificate_lifetime(&self) -> Option<u64> {
+        self.limit(Self::CERTIFICATE_LIFETIME)
+    }
+
+    #[inline]
+    pub fn max_poll_wait(&self) -> Option<u64> {
+        self.limit(Self::MAX_POLL_WAIT)
+    }
+
+    #[inline]
+    pub fn connect_difficulty(&self) -> Option<u64> {
+        self.limit(Self::CONNECT_DIFFICULTY)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.capabilities.is_empty() && self.limits.is_empty()
+    }
+
+    /// Get bytes of the capabilities and limits
+    /// to be signed by the server.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&(self.capabilities.len() as u64).to_be_bytes());
+
+        for (name, enabled) in &self.capabilities {
+            bytes.extend_from_slice(&(name.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.push(*enabled as u8);
+        }
+
+        bytes.extend_from_slice(&(self.limits.len() as u64).to_be_bytes());
+
+        for (name, value) in &self.limits {
+            bytes.extend_from_slice(&(name.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+
+        bytes
+    }
+}
+
+impl AsJson for ServerCapabilities {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "capabilities": self.capabilities,
+            "limits": self.limits
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        // Values of unknown types are ignored
+        let capabilities = json.get("capabilities")
+            .and_then(Json::as_object)
+            .map(|capabilities| {
+                capabilities.iter()
+                    .filter_map(|(name, enabled)| Some((name.clone(), enabled.as_bool()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let limits = json.get("limits")
+            .and_then(Json::as_object)
+            .map(|limits| {
+                limits.iter()
+                    .filter_map(|(name, value)| Some((name.clone(), value.as_u64()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            capabilities,
+            limits
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let capabilities = ServerCapabilities::new()
+            .with_capability(ServerCapabilities::SEND_BATCH, true)
+            .with_capability(ServerCapabilities::ACK_POLL, false)
+            .with_limit(ServerCapabilities::MAX_BATCH_SIZE, 64);
+
+        assert_eq!(ServerCapabilities::from_json(&capabilities.to_json()?)?, capabilities);
+
+        assert!(capabilities.send_batch());
+        assert!(!capabilities.ack_poll());
+        assert!(!capabilities.lookup_batch());
+        assert_eq!(capabilities.max_batch_size(), Some(64));
+        assert_eq!(capabilities.max_message_size(), None);
+
+        // Unknown names are kept, unknown values are ignored
+        let capabilities = ServerCapabilities::from_json(&json!({
+            "capabilities": {
+                "send_batch": true,
+                "future_capability": true,
+                "future_object": { "enabled": true }
+            },
+            "limits": {
+                "future_limit": 10,
+                "future_ratio": 0.5
+            }
+        }))?;
+
+        assert!(capabilities.supports("future_capability"));
+        assert_eq!(capabilities.limit("future_limit"), Some(10));
+        assert_eq!(capabilities.capabilities.len(), 2);
+        assert_eq!(capabilities.limits.len(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use serde_json::Value as Json;
+
+use crate::rest_api::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Server which can know the looked up client.
+/// 
+/// Servers with higher scores should be requested first.
+/// There's no standard description of the score, so scores
+/// of hints returned by different servers are not comparable.
+pub struct ServerHint {
+    pub server: Server,
+    pub score: u64
+}
+
+impl ServerHint {
+    #[inline]
+    pub fn new(server: Server, score: u64) -> Self {
+        Self {
+            server,
+            score
+        }
+    }
+}
+
+impl From<Server> for ServerHint {
+    #[inline]
+    fn from(server: Server) -> Self {
+        Self::new(server, 0)
+    }
+}
+
+impl AsJson for ServerHint {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        let mut hint = self.server.to_json()?;
+
+        hint["score"] = Json::from(self.score);
+
+        Ok(hint)
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        // Legacy hints are not scored
+        let score = match json.get("score") {
+            Some(score) => score.as_u64().ok_or(AsJsonError::FieldValueInvalid("score"))?,
+            None => 0
+        };
+
+        Ok(Self {
+            server: Server::from_json(json)?,
+            score
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rest_api::types::server::tests::get_server;
+
+    use super::*;
+
+    #[test]
+    fn serialize() -> Result<(), AsJsonError> {
+        let hint = ServerHint::new(get_server(), 123);
+
+        assert_eq!(ServerHint::from_json(&hint.to_json()?)?, hint);
+
+        // Legacy server record
+        let server = get_server();
+
+        assert_eq!(ServerHint::from_json(&server.to_json()?)?, ServerHint::from(server));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use serde_json::{json, Value as Json};
+
+use crate::STANDARD_VERSION;
+
+use super::request::Request;
+use super::{AsJson, AsJsonError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+/// Version of the protocol's standard.
+/// 
+/// Every request and response header contains the
+/// `standard` field with the version it's made by.
+/// This type is the only place which decides which
+/// versions can be parsed and serialized.
+pub struct ProtocolVersion(pub u64);
+
+impl ProtocolVersion {
+    /// Version implemented by this library.
+    pub const CURRENT: Self = Self(STANDARD_VERSION);
+
+    /// Oldest version still supported by this library.
+    pub const MIN_SUPPORTED: Self = Self(1);
+
+    #[inline]
+    /// Check if the version can be parsed
+    /// and serialized by this library.
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// assert!(ProtocolVersion::CURRENT.is_supported());
+    /// assert!(!ProtocolVersion(0).is_supported());
+    /// ```
+    pub fn is_supported(&self) -> bool {
+        ProtocolVersions::SUPPORTED.contains(*self)
+    }
+
+    #[inline]
+    /// Check that the given standard version is supported.
+    /// 
+    /// Return `AsJsonError::InvalidStandard` otherwise.
+    pub fn check(standard: u64) -> Result<Self, AsJsonError> {
+        let version = Self(standard);
+
+        if !version.is_supported() {
+            return Err(AsJsonError::InvalidStandard(standard));
+        }
+
+        Ok(version)
+    }
+
+    /// Read and check the `standard` field
+    /// of the given JSON header.
+    pub fn parse(json: &Json) -> Result<Self, AsJsonError> {
+        let Some(standard) = json.get("standard").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("standard"));
+        };
+
+        Self::check(standard)
+    }
+}
+
+impl Default for ProtocolVersion {
+    #[inline]
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+impl From<u64> for ProtocolVersion {
+    #[inline]
+    fn from(standard: u64) -> Self {
+        Self(standard)
+    }
+}
+
+impl From<ProtocolVersion> for u64 {
+    #[inline]
+    fn from(version: ProtocolVersion) -> Self {
+        version.0
+    }
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Inclusive range of the protocol's standard versions.
+pub struct ProtocolVersions {
+    pub min: ProtocolVersion,
+    pub max: ProtocolVersion
+}
+
+impl ProtocolVersions {
+    /// Versions supported by this library.
+    pub const SUPPORTED: Self = Self {
+        min: ProtocolVersion::MIN_SUPPORTED,
+        max: ProtocolVersion::CURRENT
+    };
+
+    #[inline]
+    pub fn new(min: impl Into<ProtocolVersion>, max: impl Into<ProtocolVersion>) -> Self {
+        Self {
+            min: min.into(),
+            max: max.into()
+        }
+    }
+
+    #[inline]
+    /// Range of a single version.
+    pub fn single(version: impl Into<ProtocolVersion>) -> Self {
+        let version = version.into();
+
+        Self {
+            min: version,
+            max: version
+        }
+    }
+
+    #[inline]
+    pub fn contains(&self, version: ProtocolVersion) -> bool {
+        self.min <= version && version <= self.max
+    }
+
+    /// Choose the highest version supported by both ranges.
+    /// 
+    /// ```rust
+    /// use hyperborealib::rest_api::prelude::*;
+    /// 
+    /// let client = ProtocolVersions::new(1, 3);
+    /// 
+    /// assert_eq!(client.negotiate(&ProtocolVersions::new(2, 5)), Some(ProtocolVersion(3)));
+    /// assert_eq!(client.negotiate(&ProtocolVersions::single(1)), Some(ProtocolVersion(1)));
+    /// assert_eq!(client.negotiate(&ProtocolVersions::new(4, 5)), None);
+    /// ```
+    pub fn negotiate(&self, other: &Self) -> Option<ProtocolVersion> {
+        let min = self.min.max(other.min);
+        let max = self.max.min(other.max);
+
+        (min <= max).then_some(max)
+    }
+}
+
+impl Default for ProtocolVersions {
+    #[inline]
+    fn default() -> Self {
+        Self::SUPPORTED
+    }
+}
+
+impl AsJson for ProtocolVersions {
+    fn to_json(&self) -> Result<Json, AsJsonError> {
+        Ok(json!({
+            "min": self.min.0,
+            "max": self.max.0
+        }))
+    }
+
+    fn from_json(json: &Json) -> Result<Self, AsJsonError> where Self: Sized {
+        let Some(min) = json.get("min").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("min"));
+        };
+
+        let Some(max) = json.get("max").and_then(Json::as_u64) else {
+            return Err(AsJsonError::FieldNotFound("max"));
+        };
+
+        if min > max {
+            return Err(AsJsonError::FieldValueInvalid("min"));
+        }
+
+        Ok(Self::new(min, max))
+    }
+}
+
+/// Request which can be made by
+/// different standard versions.
+pub trait VersionedRequest {
+    /// Make the request by the given standard version.
+    fn with_standard(self, standard: ProtocolVersion) -> Self;
+}
+
+impl<T> VersionedRequest for Request<T> {
+    #[inline]
+    fn with_standard(self, standard: ProtocolVersion) -> Self {
+        Self::with_standard(self, standard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() -> Result<(), AsJsonError> {
+        assert_eq!(ProtocolVersion::parse(&json!({ "standard": STANDARD_VERSION }))?, ProtocolVersion::CURRENT);
+
+        assert!(matches!(ProtocolVersion::parse(&json!({})), Err(AsJsonError::FieldNotFound("standard"))));
+        assert!(matches!(ProtocolVersion::parse(&json!({ "standard": 0 })), Err(AsJsonError::InvalidStandard(0))));
+        assert!(matches!(ProtocolVersion::check(STANDARD_VERSION + 1), Err(AsJsonError::InvalidStandard(_))));
+
+        let versions = ProtocolVersions::new(1, 3);
+
+        assert_eq!(ProtocolVersions::from_json(&versions.to_json()?)?, versions);
+        assert!(ProtocolVersions::from_json(&json!({ "min": 3, "max": 1 })).is_err());
+
+        Ok(())
+    }
+}
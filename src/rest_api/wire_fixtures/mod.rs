@@ -0,0 +1,318 @@
+//! Pinned v1 wire format fixtures.
+//! 
+//! This module contains JSON payloads of all the REST API
+//! requests and responses as produced by the v1 release
+//! of the library, and a lenient structural diff utility
+//! used to check that today's serialization is still
+//! understandable by the v1 peers.
+//! 
+//! If you intentionally change the wire format - register
+//! a translation shim in the `compat` module and regenerate
+//! the fixtures by running the ignored `regenerate` test:
+//! 
+//! ```text
+//! cargo test regenerate -- --ignored
+//! ```
+
+use serde_json::Value as Json;
+
+macro_rules! fixtures {
+    ($($name:literal),*) => {
+        /// List of all the committed fixtures.
+        pub const FIXTURES: &[(&str, &str)] = &[
+            $(
+                ($name, include_str!(concat!("fixtures/", $name, ".json")))
+            ),*
+        ];
+    };
+}
+
+fixtures!(
+    "connect_request",
+    "connect_scoped_request",
+    "connect_response",
+    "disconnect_request",
+    "disconnect_response",
+    "announce_client_request",
+    "announce_server_request",
+    "announce_response",
+    "announce_bulk_request",
+    "announce_bulk_response",
+    "lookup_request",
+    "lookup_local_response",
+    "lookup_remote_response",
+    "lookup_hint_response",
+    "send_request",
+    "send_response",
+    "poll_request",
+    "poll_response",
+    "info_response",
+    "clients_response",
+    "servers_response",
+    "error_response"
+);
+
+/// Load fixture with the given name.
+pub fn load(name: &str) -> Option<Json> {
+    FIXTURES.iter()
+        .find(|(fixture, _)| *fixture == name)
+        .and_then(|(_, json)| serde_json::from_str(json).ok())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// Field from the fixture is missing.
+    Missing {
+        path: String
+    },
+
+    /// Field has different JSON type.
+    TypeChanged {
+        path: String,
+        expected: &'static str,
+        actual: &'static str
+    },
+
+    /// Field has different value.
+    ValueChanged {
+        path: String,
+        expected: Json,
+        actual: Json
+    },
+
+    /// Array has different amount of elements.
+    LengthChanged {
+        path: String,
+        expected: usize,
+        actual: usize
+    }
+}
+
+impl std::fmt::Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing { path } => write!(f, "`{path}` is missing"),
+            Self::TypeChanged { path, expected, actual } => write!(f, "`{path}` type changed from {expected} to {actual}"),
+            Self::ValueChanged { path, expected, actual } => write!(f, "`{path}` value changed from {expected} to {actual}"),
+            Self::LengthChanged { path, expected, actual } => write!(f, "`{path}` length changed from {expected} to {actual}")
+        }
+    }
+}
+
+fn type_name(value: &Json) -> &'static str {
+    match value {
+        Json::Null      => "null",
+        Json::Bool(_)   => "bool",
+        Json::Number(_) => "number",
+        Json::String(_) => "string",
+        Json::Array(_)  => "array",
+        Json::Object(_) => "object"
+    }
+}
+
+/// Compare `actual` JSON value with the `expected` one.
+/// 
+/// The diff is lenient: new fields in `actual` objects
+/// are allowed, and missing fields are equal to `null`
+/// so new optional fields can be omitted. Every removed,
+/// renamed or changed field is reported.
+pub fn lenient_diff(expected: &Json, actual: &Json) -> Vec<Difference> {
+    let mut differences = Vec::new();
+
+    diff_values("$", expected, actual, &mut differences);
+
+    differences
+}
+
+fn diff_values(path: &str, expected: &Json, actual: &Json, differences: &mut Vec<Difference>) {
+    match (expected, actual) {
+        (Json::Object(expected), Json::Object(actual)) => {
+            for (key, expected) in expected {
+                let path = format!("{path}.{key}");
+
+                match actual.get(key) {
+                    Some(actual) => diff_values(&path, expected, actual, differences),
+
+                    None if expected.is_null() => (),
+                    None => differences.push(Difference::Missing { path })
+                }
+            }
+        }
+
+        (Json::Array(expected), Json::Array(actual)) => {
+            if expected.len() != actual.len() {
+                differences.push(Difference::LengthChanged {
+                    path: path.to_string(),
+                    expected: expected.len(),
+                    actual: actual.len()
+                });
+            }
+
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                diff_values(&format!("{path}[{i}]"), expected, actual, differences);
+            }
+        }
+
+        (expected, actual) if type_name(expected) != type_name(actual) => {
+            differences.push(Difference::TypeChanged {
+                path: path.to_string(),
+                expected: type_name(expected),
+                actual: type_name(actual)
+            });
+        }
+
+        (expected, actual) if expected != actual => {
+            differences.push(Difference::ValueChanged {
+                path: path.to_string(),
+                expected: expected.clone(),
+                actual: actual.clone()
+            });
+        }
+
+        _ => ()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::rest_api::compat;
+    use crate::rest_api::prelude::*;
+
+    use super::*;
+
+    fn check<T: AsJson>(name: &str) -> Result<(), AsJsonError> {
+        let fixture = load(name)
+            .unwrap_or_else(|| panic!("Fixture {name} not found"));
+
+        let mut upgraded = fixture.clone();
+
+        compat::upgrade(name, &mut upgraded);
+
+        let mut serialized = T::from_json(&upgraded)?.to_json()?;
+
+        compat::downgrade(name, &mut serialized);
+
+        let differences = lenient_diff(&fixture, &serialized);
+
+        assert!(differences.is_empty(), "Wire format of {name} fixture is broken:\n{}", differences.iter()
+            .map(|difference| format!("- {difference}"))
+            .collect::<Vec<_>>()
+            .join("\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fixtures() -> Result<(), AsJsonError> {
+        check::<ConnectRequest>("connect_request")?;
+        check::<ConnectRequest>("connect_scoped_request")?;
+        check::<ConnectResponse>("connect_response")?;
+        check::<DisconnectRequest>("disconnect_request")?;
+        check::<DisconnectResponse>("disconnect_response")?;
+        check::<AnnounceRequest>("announce_client_request")?;
+        check::<AnnounceRequest>("announce_server_request")?;
+        check::<AnnounceResponse>("announce_response")?;
+        check::<AnnounceRequest>("announce_bulk_request")?;
+        check::<AnnounceResponse>("announce_bulk_response")?;
+        check::<LookupRequest>("lookup_request")?;
+        check::<LookupResponse>("lookup_local_response")?;
+        check::<LookupResponse>("lookup_remote_response")?;
+        check::<LookupResponse>("lookup_hint_response")?;
+        check::<SendRequest>("send_request")?;
+        check::<SendResponse>("send_response")?;
+        check::<PollRequest>("poll_request")?;
+        check::<PollResponse>("poll_response")?;
+        check::<InfoResponse>("info_response")?;
+        check::<ClientsResponse>("clients_response")?;
+        check::<ServersResponse>("servers_response")?;
+        check::<SendResponse>("error_response")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff() {
+        let expected = json!({
+            "a": 1,
+            "b": { "c": "d" },
+            "e": [1, 2],
+            "f": null
+        });
+
+        // New fields are allowed
+        assert!(lenient_diff(&expected, &json!({
+            "a": 1,
+            "b": { "c": "d", "new": true },
+            "e": [1, 2],
+            "new": "field"
+        })).is_empty());
+
+        assert_eq!(lenient_diff(&expected, &json!({
+            "a": "1",
+            "b": { "renamed": "d" },
+            "e": [1, 3, 4]
+        })), vec![
+            Difference::TypeChanged { path: String::from("$.a"), expected: "number", actual: "string" },
+            Difference::Missing { path: String::from("$.b.c") },
+            Difference::LengthChanged { path: String::from("$.e"), expected: 2, actual: 3 },
+            Difference::ValueChanged { path: String::from("$.e[1]"), expected: json!(2), actual: json!(3) }
+        ]);
+    }
+
+    #[test]
+    #[ignore]
+    /// Regenerate committed fixtures.
+    /// 
+    /// Run only when the wire format is changed deliberately.
+    fn regenerate() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::crypto::prelude::*;
+
+        use crate::rest_api::types::client::tests::get_client;
+        use crate::rest_api::types::server::tests::get_server;
+        use crate::rest_api::types::certificate_scope::tests::get_scope;
+        use crate::rest_api::types::sender::tests::get_sender;
+        use crate::rest_api::types::message_info::tests::get_message_info;
+
+        let secret = SecretKey::random();
+
+        let message = Message::new("content", "sign", MessageEncoding::default());
+
+        let fixtures = [
+            ("connect_request", ConnectRequest::new(&secret, get_server().public_key, ClientInfo::thin()).to_json()?),
+            ("connect_scoped_request", ConnectRequest::scoped(&secret, get_server().public_key, ClientInfo::thin(), get_scope()).to_json()?),
+            ("connect_response", ConnectResponse::success(ResponseStatus::Success, &secret, safe_random_u64_long()).to_json()?),
+            ("disconnect_request", DisconnectRequest::new(&secret).to_json()?),
+            ("disconnect_response", DisconnectResponse::success(ResponseStatus::Success, &secret, safe_random_u64_long()).to_json()?),
+            ("announce_client_request", AnnounceRequest::client(&secret, get_client(), get_server()).to_json()?),
+            ("announce_server_request", AnnounceRequest::server(&secret, get_server()).to_json()?),
+            ("announce_response", AnnounceResponse::success(ResponseStatus::Success, &secret, safe_random_u64_long()).to_json()?),
+            ("announce_bulk_request", AnnounceRequest::bulk(&secret, [AnnounceRequestBody::client(get_client(), get_server()), AnnounceRequestBody::server(get_server())]).to_json()?),
+            ("announce_bulk_response", AnnounceResponse::bulk(&secret, safe_random_u64_long(), [AnnounceEntryResult::Accepted, AnnounceEntryResult::rejected(ResponseStatus::AnnounceRecordTooLarge, "Server record is too large")]).to_json()?),
+            ("lookup_request", LookupRequest::new(&secret, get_client().public_key, Some(ClientType::Thin)).to_json()?),
+            ("lookup_local_response", LookupResponse::success(ResponseStatus::Success, &secret, safe_random_u64_long(), LookupResponseBody::local(get_client(), true)).to_json()?),
+            ("lookup_remote_response", LookupResponse::success(ResponseStatus::Success, &secret, safe_random_u64_long(), LookupResponseBody::remote(get_client(), get_server(), true)).to_json()?),
+            ("lookup_hint_response", LookupResponse::success(ResponseStatus::Success, &secret, safe_random_u64_long(), LookupResponseBody::hint([get_server(), get_server()])).to_json()?),
+            ("send_request", SendRequest::new(&secret, get_sender(), get_client().public_key, ChannelName::new("default channel").unwrap(), message).to_json()?),
+            ("send_response", SendResponse::success(ResponseStatus::Success, &secret, safe_random_u64_long(), SendResponseBody::new().with_id(safe_random_u64())).to_json()?),
+            ("poll_request", PollRequest::new(&secret, ChannelName::new("default channel").unwrap(), Some(10)).to_json()?),
+            ("poll_response", PollResponse::success(ResponseStatus::Success, &secret, safe_random_u64_long(), PollResponseBody::new([get_message_info(), get_message_info()], 3)).to_json()?),
+            ("info_response", InfoResponse::new(&secret).to_json()?),
+            ("clients_response", ClientsResponse::new([get_client(), get_client()]).to_json()?),
+            ("servers_response", ServersResponse::new([get_server(), get_server()]).to_json()?),
+            ("error_response", SendResponse::error(ResponseStatus::ClientNotFound, ErrorCode::NotFound, "Client not found").to_json()?)
+        ];
+
+        let folder = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/rest_api/wire_fixtures/fixtures");
+
+        std::fs::create_dir_all(&folder)?;
+
+        for (name, fixture) in fixtures {
+            std::fs::write(folder.join(format!("{name}.json")), serde_json::to_string_pretty(&fixture)? + "\n")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+use serde_json::Value as Json;
+
+use crate::http::client::{HttpClient, Response};
+use crate::http::server::{HttpServer, BodyLimits};
+
+use crate::rest_api::AsJson;
+use crate::rest_api::response::Response as ApiResponse;
+use crate::rest_api::status::ResponseStatus;
+use crate::rest_api::error_code::ErrorCode;
+
+use super::network::*;
+
+#[derive(Debug, Clone)]
+/// HTTP client sending requests over the virtual network.
+pub struct VirtualHttpClient {
+    network: VirtualNetwork,
+    address: SocketAddr
+}
+
+impl VirtualHttpClient {
+    #[inline]
+    /// Create new client sending requests
+    /// from the given virtual address.
+    pub fn new(network: VirtualNetwork, address: SocketAddr) -> Self {
+        Self {
+            network,
+            address
+        }
+    }
+
+    #[inline]
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpClient for VirtualHttpClient {
+    async fn get(&self, url: impl AsRef<str> + Send) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.network.request(self.address, Method::Get, url.as_ref(), None).await?)
+    }
+
+    async fn post(&self, url: impl AsRef<str> + Send, body: Json) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.network.request(self.address, Method::Post, url.as_ref(), Some(body)).await?)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// HTTP server serving requests from the virtual network.
+pub struct VirtualHttpServer {
+    network: VirtualNetwork,
+    routes: VirtualRoutes,
+    body_limits: BodyLimits
+}
+
+impl VirtualHttpServer {
+    #[inline]
+    pub fn new(network: VirtualNetwork) -> Self {
+        Self {
+            network,
+            routes: VirtualRoutes::default(),
+            body_limits: BodyLimits::default()
+        }
+    }
+}
+
+/// Convert handler's result to the HTTP response.
+fn to_response(response: impl AsJson) -> Response {
+    match response.to_json() {
+        Ok(body) => Response {
+            status: 200,
+            body: Some(body)
+        },
+
+        Err(_) => Response {
+            status: 500,
+            body: None
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpServer for VirtualHttpServer {
+    async fn get<T: AsJson, F: std::future::Future<Output = T> + Send>(
+        &mut self,
+        path: impl AsRef<str> + Send,
+        callback: impl FnOnce(SocketAddr) -> F + Clone + Send + Sync + 'static
+    ) {
+        self.routes.get.insert(path.as_ref().to_string(), Arc::new(move |request: VirtualRequest| {
+            let callback = callback.clone();
+
+            Box::pin(async move {
+                to_response(callback(request.client_address).await)
+            })
+        }));
+    }
+
+    async fn get_with_query<T: AsJson, F: std::future::Future<Output = Result<T, String>> + Send>(
+        &mut self,
+        path: impl AsRef<str> + Send,
+        callback: impl FnOnce(SocketAddr, HashMap<String, String>) -> F + Clone + Send + Sync + 'static
+    ) {
+        self.routes.get.insert(path.as_ref().to_string(), Arc::new(move |request: VirtualRequest| {
+            let callback = callback.clone();
+
+            Box::pin(async move {
+                match callback(request.client_address, request.query).await {
+                    Ok(response) => to_response(response),
+
+                    Err(err) => Response {
+                        status: 400,
+                        body: ApiResponse::<()>::error(ResponseStatus::InvalidRequestStructure, ErrorCode::InvalidRequest, err)
+                            .to_json()
+                            .ok()
+                    }
+                }
+            })
+        }));
+    }
+
+    async fn post<T: AsJson, F: AsJson, R: std::future::Future<Output = F> + Send>(
+        &mut self,
+        path: impl AsRef<str> + Send,
+        callback: impl FnOnce(SocketAddr, T) -> R + Clone + Send + Sync + 'static
+    ) {
+        let limit = self.body_limits.limit(path.as_ref());
+
+        self.routes.post.insert(path.as_ref().to_string(), Arc::new(move |request: VirtualRequest| {
+            let callback = callback.clone();
+
+            Box::pin(async move {
+                // Virtual requests are not serialized so
+                // the size of the serialized body is checked
+                if request.body.as_ref().is_some_and(|body| body.to_string().len() > limit) {
+                    return Response {
+                        status: 413,
+                        body: BodyLimits::error_response(limit).to_json().ok()
+                    };
+                }
+
+                let Some(request_body) = request.body.as_ref().and_then(|body| T::from_json(body).ok()) else {
+                    return Response {
+                        status: 500,
+                        body: None
+                    };
+                };
+
+                to_response(callback(request.client_address, request_body).await)
+            })
+        }));
+    }
+
+    #[inline]
+    fn set_body_limits(&mut self, limits: BodyLimits) {
+        self.body_limits = limits;
+    }
+
+    async fn serve(self, address: impl ToSocketAddrs + Send) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(address) = address.to_socket_addrs()?.next() else {
+            return Err("Failed to resolve server address".into());
+        };
+
+        let shutdown = self.network.register(address, self.routes);
+
+        shutdown.notified().await;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,910 @@
+//! Deterministic simulation of multi-server networks.
+//! 
+//! Servers and clients of the simulation communicate through
+//! the in-process `VirtualNetwork` with scriptable latencies,
+//! drop rates and partitions. All the timers are driven by the
+//! tokio clock, so the simulation must run in a single threaded
+//! runtime with paused clock (`#[tokio::test(start_paused = true)]`),
+//! where virtual time moves only when `Simulation::advance` is
+//! awaited or when all the tasks are waiting for the timers.
+//! 
+//! Random numbers of the network and all the library components
+//! running on the simulation's thread are produced from the
+//! simulation's seed, so repeated runs of the same scenario
+//! produce the same trace.
+
+use std::future::Future;
+use std::net::{SocketAddr, Ipv4Addr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::crypto::utils::seed_thread_rng;
+use crate::drivers::server::prelude::*;
+use crate::rest_api::middleware::Server;
+use crate::rest_api::types::{Message, MessageInfo};
+
+mod network;
+mod http;
+
+pub use network::*;
+pub use http::*;
+
+type ServerFactory<R, T, I> = Arc<dyn Fn(SocketAddr) -> Pin<Box<dyn Future<Output = std::io::Result<ServerDriver<R, T, I>>> + Send>> + Send + Sync>;
+
+struct SimulatedServer<R, T, I> {
+    address: SocketAddr,
+    factory: ServerFactory<R, T, I>,
+    driver: Option<Arc<ServerDriver<R, T, I>>>,
+    task: Option<JoinHandle<()>>
+}
+
+/// Scenario builder of the simulated network.
+pub struct Simulation<R, T, I> {
+    network: VirtualNetwork,
+    servers: Vec<SimulatedServer<R, T, I>>,
+    clients: u16
+}
+
+impl<R, T, I> Simulation<R, T, I>
+where
+    R: Router + Send + Sync + 'static,
+    T: Traversal + Send + Sync + 'static,
+    I: MessagesInbox + Send + Sync + 'static
+{
+    /// Create new simulation and seed random
+    /// numbers generator of the current thread.
+    pub fn new(seed: u64) -> Self {
+        seed_thread_rng(Some(seed));
+
+        Self {
+            network: VirtualNetwork::new(seed),
+            servers: Vec::new(),
+            clients: 0
+        }
+    }
+
+    #[inline]
+    pub fn network(&self) -> &VirtualNetwork {
+        &self.network
+    }
+
+    #[inline]
+    /// Get all the requests sent over the network.
+    pub fn trace(&self) -> Vec<LinkEvent> {
+        self.network.trace()
+    }
+
+    #[inline]
+    /// Get virtual address of the server.
+    pub fn server_address(&self, server: usize) -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::new(10, 0, 0, server as u8 + 1), 8001))
+    }
+
+    #[inline]
+    /// Get driver of the running server.
+    /// 
+    /// Return `None` if the server is crashed.
+    pub fn driver(&self, server: usize) -> Option<Arc<ServerDriver<R, T, I>>> {
+        self.servers[server].driver.clone()
+    }
+
+    /// Create HTTP client with a new virtual address.
+    pub fn client(&mut self) -> VirtualHttpClient {
+        self.clients += 1;
+
+        let address = SocketAddr::from((Ipv4Addr::new(10, 1, (self.clients >> 8) as u8, self.clients as u8), 8001));
+
+        VirtualHttpClient::new(self.network.clone(), address)
+    }
+
+    /// Add new server to the simulation and start it.
+    /// 
+    /// Factory is called with the server's virtual address
+    /// every time the server is started, so it should restore
+    /// the server's state from the persistent storage.
+    /// 
+    /// Return index of the added server.
+    pub async fn add_server<F, Fut>(&mut self, factory: F) -> std::io::Result<usize>
+    where
+        F: Fn(SocketAddr) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::io::Result<ServerDriver<R, T, I>>> + Send + 'static
+    {
+        let server = self.servers.len();
+
+        self.servers.push(SimulatedServer {
+            address: self.server_address(server),
+            factory: Arc::new(move |address| Box::pin(factory(address))),
+            driver: None,
+            task: None
+        });
+
+        self.start_server(server).await?;
+
+        Ok(server)
+    }
+
+    async fn start_server(&mut self, server: usize) -> std::io::Result<()> {
+        let address = self.servers[server].address;
+
+        let driver = (self.servers[server].factory)(address).await?;
+
+        let server_middleware = Server::new(
+            VirtualHttpClient::new(self.network.clone(), address),
+            VirtualHttpServer::new(self.network.clone()),
+            driver
+        ).await;
+
+        self.servers[server].driver = Some(server_middleware.driver());
+
+        self.servers[server].task = Some(tokio::spawn(async move {
+            let _ = server_middleware.serve(address).await;
+        }));
+
+        // Wait until the server registers its routes
+        while !self.network.is_up(&address) {
+            tokio::task::yield_now().await;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    /// Block all the requests between two servers.
+    pub fn partition(&self, a: usize, b: usize) {
+        self.network.partition(self.server_address(a), self.server_address(b));
+    }
+
+    #[inline]
+    /// Remove all the partitions.
+    pub fn heal(&self) {
+        self.network.heal();
+    }
+
+    #[inline]
+    /// Set parameters of the link between two servers.
+    pub fn set_link(&self, a: usize, b: usize, params: LinkParams) {
+        self.network.set_link(self.server_address(a), self.server_address(b), params);
+    }
+
+    /// Stop the server, dropping all its in-memory state.
+    pub fn crash_server(&mut self, server: usize) {
+        let server = &mut self.servers[server];
+
+        self.network.shutdown(&server.address);
+
+        if let Some(task) = server.task.take() {
+            task.abort();
+        }
+
+        server.driver = None;
+    }
+
+    /// Start the crashed server again.
+    pub async fn restart_server(&mut self, server: usize) -> std::io::Result<()> {
+        self.crash_server(server);
+        self.start_server(server).await
+    }
+
+    #[inline]
+    /// Move virtual time forward, running all
+    /// the timers scheduled within the duration.
+    pub async fn advance(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+impl<R, T, I> Drop for Simulation<R, T, I> {
+    fn drop(&mut self) {
+        for server in &mut self.servers {
+            if let Some(task) = server.task.take() {
+                task.abort();
+            }
+        }
+
+        seed_thread_rng(None);
+    }
+}
+
+/// Get sent messages which weren't received.
+pub fn missing_messages<'a>(sent: &'a [Message], received: &[MessageInfo]) -> Vec<&'a Message> {
+    sent.iter()
+        .filter(|message| !received.iter().any(|info| &info.message == *message))
+        .collect()
+}
+
+/// Get messages which were received more than once.
+pub fn duplicated_messages(received: &[MessageInfo]) -> Vec<&MessageInfo> {
+    received.iter()
+        .enumerate()
+        .filter(|(i, info)| received[..*i].iter().any(|prev| prev.message == info.message))
+        .map(|(_, info)| info)
+        .collect()
+}
+
+/// Check that every sent message was received exactly once.
+/// 
+/// # Panics
+/// 
+/// Panics with the list of lost and duplicated messages.
+pub fn assert_exactly_once(sent: &[Message], received: &[MessageInfo]) {
+    let missing = missing_messages(sent, received);
+    let duplicated = duplicated_messages(received);
+
+    assert!(
+        missing.is_empty() && duplicated.is_empty(),
+        "Messages delivery is broken\nMissing: {missing:#?}\nDuplicated: {duplicated:#?}"
+    );
+}
+
+#[cfg(all(
+    test,
+    feature = "router-global-table",
+    feature = "traversal-bfs-recursion",
+    feature = "inbox-stored-queue"
+))]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::crypto::prelude::*;
+    use crate::drivers::ClientDriver;
+    use crate::drivers::server::router::global_table::GlobalTableRouter;
+    use crate::drivers::server::traversal::bfs_recursion::BfsRecursionTraversal;
+    use crate::drivers::server::messages_inbox::stored_queue::StoredQueueMessagesInbox;
+    use crate::rest_api::prelude::*;
+    use crate::rest_api::types::Server as ServerApiRecord;
+
+    use super::*;
+
+    type TestSimulation = Simulation<GlobalTableRouter, BfsRecursionTraversal, StoredQueueMessagesInbox>;
+
+    /// Build factory of the server storing its data in the
+    /// given temp folder.
+    async fn factory(folder: &str, params: ServerParams) -> std::io::Result<impl Fn(SocketAddr) -> Pin<Box<dyn Future<Output = std::io::Result<ServerDriver<GlobalTableRouter, BfsRecursionTraversal, StoredQueueMessagesInbox>>> + Send>> + Send + Sync + 'static> {
+        let temp: PathBuf = std::env::temp_dir().join(folder);
+
+        if temp.exists() {
+            tokio::fs::remove_dir_all(&temp).await?;
+        }
+
+        Ok(move |address: SocketAddr| -> Pin<Box<dyn Future<Output = _> + Send>> {
+            let temp = temp.clone();
+
+            let params = ServerParams {
+                address: address.to_string(),
+                ..params.clone()
+            };
+
+            Box::pin(async move {
+                Ok(ServerDriver::new(
+                    GlobalTableRouter::new(temp.join("router")).await?,
+                    BfsRecursionTraversal,
+                    StoredQueueMessagesInbox::new(temp.join("inbox"), None).await?,
+                    params
+                ))
+            })
+        })
+    }
+
+    async fn relay_convergence(seed: u64) -> Result<Vec<LinkEvent>, Box<dyn std::error::Error>> {
+        let mut simulation = TestSimulation::new(seed);
+
+        let mut fanout_params = ServerParams::default();
+
+        fanout_params.announce_fanout.mode = AnnounceFanout::All;
+
+        simulation.add_server(factory("simulation-relay-a", fanout_params).await?).await?;
+        simulation.add_server(factory("simulation-relay-b", ServerParams::default()).await?).await?;
+        simulation.add_server(factory("simulation-relay-c", ServerParams::default()).await?).await?;
+
+        let drivers = (0..3)
+            .map(|server| simulation.driver(server).unwrap())
+            .collect::<Vec<_>>();
+
+        for driver in &drivers[1..] {
+            drivers[0].router().index_server(ServerApiRecord::new(
+                driver.params().secret_key.public_key(),
+                &driver.params().address
+            )).await?;
+        }
+
+        simulation.partition(0, 2);
+
+        let client = ClientMiddleware::new(simulation.client(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        client.connect(simulation.server_address(0)).await?;
+
+        // Announce reaches only the server on the same side of the partition
+        simulation.advance(Duration::from_millis(100)).await;
+
+        assert!(drivers[1].router().lookup_remote_client(&client_public, None).await?.is_some());
+        assert!(drivers[2].router().lookup_remote_client(&client_public, None).await?.is_none());
+
+        // Retried announce converges after healing
+        simulation.heal();
+        simulation.advance(Duration::from_secs(1)).await;
+
+        let Some((_, server, _)) = drivers[2].router().lookup_remote_client(&client_public, None).await? else {
+            panic!("Client wasn't announced to the server C after healing");
+        };
+
+        assert_eq!(server.address, simulation.server_address(0).to_string());
+
+        let trace = simulation.trace();
+
+        assert!(trace.iter().any(|event| event.to == simulation.server_address(2) && event.outcome == LinkOutcome::Partitioned));
+        assert!(trace.iter().any(|event| event.to == simulation.server_address(2) && event.outcome == LinkOutcome::Delivered(200)));
+
+        Ok(trace)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn healed_partition() -> Result<(), Box<dyn std::error::Error>> {
+        let trace = relay_convergence(42).await?;
+
+        // Same seed produces the same run
+        assert_eq!(relay_convergence(42).await?, trace);
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn server_restart() -> Result<(), Box<dyn std::error::Error>> {
+        let mut simulation = TestSimulation::new(42);
+
+        simulation.network().set_default_link(LinkParams {
+            latency: Latency::Uniform {
+                min: Duration::from_millis(5),
+                max: Duration::from_millis(50)
+            },
+            drop_rate: 0.0
+        });
+
+        let server = simulation.add_server(factory("simulation-restart", ServerParams::default()).await?).await?;
+        let server_address = simulation.server_address(server);
+
+        let sender = ClientMiddleware::new(simulation.client(), ClientDriver::random())
+            .connect(server_address).await?;
+
+        let receiver = ClientMiddleware::new(simulation.client(), ClientDriver::random())
+            .connect(server_address).await?;
+
+        let receiver_public = receiver.driver().secret_key().public_key();
+
+        let messages = (0..4)
+            .map(|i| Message::create(
+                sender.driver().secret_key(),
+                &receiver_public,
+                format!("message {i}"),
+                MessageEncoding::default(),
+                CompressionLevel::default()
+            ))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for message in &messages[..3] {
+            sender.send(format!("http://{server_address}"), receiver_public.clone(), "restart", message.clone()).await?;
+        }
+
+        // Requests to the crashed server fail
+        simulation.crash_server(server);
+
+        assert!(simulation.driver(server).is_none());
+        assert!(sender.send(format!("http://{server_address}"), receiver_public.clone(), "restart", messages[3].clone()).await.is_err());
+
+        simulation.advance(Duration::from_secs(10)).await;
+        simulation.restart_server(server).await?;
+
+        sender.send(format!("http://{server_address}"), receiver_public.clone(), "restart", messages[3].clone()).await?;
+
+        let (received, _) = receiver.poll("restart", None).await?;
+
+        assert_exactly_once(&messages, &received);
+
+        // Helpers report lost and duplicated messages
+        assert_eq!(missing_messages(&messages, &received[1..]), [&messages[0]]);
+        assert!(duplicated_messages(&[received[0].clone(), received[0].clone()]).len() == 1);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "traversal-bfs")]
+    #[tokio::test(start_paused = true)]
+    async fn bfs_traversal() -> Result<(), Box<dyn std::error::Error>> {
+        use std::collections::HashSet;
+
+        use crate::drivers::server::traversal::bfs::BfsTraversal;
+
+        let mut simulation = TestSimulation::new(42);
+
+        for server in 0..5 {
+            simulation.add_server(factory(&format!("simulation-bfs-{server}"), ServerParams::default()).await?).await?;
+        }
+
+        let drivers = (0..5)
+            .map(|server| simulation.driver(server).unwrap())
+            .collect::<Vec<_>>();
+
+        let record = |server: usize| ServerApiRecord::new(
+            drivers[server].params().secret_key.public_key(),
+            &drivers[server].params().address
+        );
+
+        // 0 -> 1 -> 2 -> 3 -> 1, 0 -> 4
+        for (from, to) in [(0, 1), (1, 2), (2, 3), (3, 1), (0, 4)] {
+            drivers[from].router().index_server(record(to)).await?;
+        }
+
+        let client = ClientMiddleware::new(simulation.client(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        client.connect(simulation.server_address(3)).await?;
+
+        let http = simulation.client();
+
+        let Some(found) = BfsTraversal::default().lookup(http.clone(), &drivers[0], &client_public, None).await else {
+            panic!("Client wasn't found by the traversal");
+        };
+
+        assert_eq!(found.client.public_key, client_public);
+        assert_eq!(found.server, record(3));
+        assert_eq!(found.path, [record(1), record(2), record(3)]);
+
+        // Client is too deep
+        assert!(BfsTraversal::new(2, 16).lookup(http.clone(), &drivers[0], &client_public, None).await.is_none());
+
+        let requested = |from: usize| simulation.trace()[from..].iter()
+            .filter(|event| event.path == "/api/v1/lookup")
+            .map(|event| event.to)
+            .collect::<Vec<_>>();
+
+        // Only one of the known servers is requested
+        let start = simulation.trace().len();
+
+        assert!(BfsTraversal::new(1, 1).lookup(http.clone(), &drivers[0], &client_public, None).await.is_none());
+        assert_eq!(requested(start).len(), 1);
+
+        // Every server is requested once despite the cycle
+        let start = simulation.trace().len();
+        let missing_public = SecretKey::random().public_key();
+
+        let (found, visited) = BfsTraversal::default().lookup_visited(http.clone(), &drivers[0], &missing_public, None).await;
+
+        assert!(found.is_none());
+
+        let requested = requested(start);
+
+        assert_eq!(requested.len(), 4);
+        assert_eq!(requested.iter().collect::<HashSet<_>>().len(), 4);
+
+        assert_eq!(visited.len(), 4);
+        assert_eq!(visited.iter().map(|visit| visit.depth).max(), Some(2));
+
+        // Lookups don't change the routing table
+        assert_eq!(drivers[0].router().servers().await?.len(), 2);
+
+        BfsTraversal::default().traverse(http, drivers[0].as_ref()).await;
+
+        assert_eq!(drivers[0].router().servers().await?.len(), 4);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "traversal-parallel")]
+    #[tokio::test(start_paused = true)]
+    async fn parallel_traversal() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::drivers::server::traversal::parallel::{ParallelTraversal, TraversalError};
+
+        let mut simulation = TestSimulation::new(42);
+
+        simulation.network().set_default_link(LinkParams {
+            latency: Latency::Fixed(Duration::from_millis(100)),
+            drop_rate: 0.0
+        });
+
+        for server in 0..6 {
+            simulation.add_server(factory(&format!("simulation-parallel-{server}"), ServerParams::default()).await?).await?;
+        }
+
+        let drivers = (0..6)
+            .map(|server| simulation.driver(server).unwrap())
+            .collect::<Vec<_>>();
+
+        let record = |server: usize| ServerApiRecord::new(
+            drivers[server].params().secret_key.public_key(),
+            &drivers[server].params().address
+        );
+
+        // 0 -> 1, 2, 3, 4 -> 5
+        for (from, to) in [(0, 1), (0, 2), (0, 3), (0, 4), (4, 5)] {
+            drivers[from].router().index_server(record(to)).await?;
+        }
+
+        let client = ClientMiddleware::new(simulation.client(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        client.connect(simulation.server_address(5)).await?;
+
+        let http = simulation.client();
+
+        // Server 2 is too slow and server 3 is down
+        simulation.network().set_link(http.address(), simulation.server_address(2), LinkParams {
+            latency: Latency::Fixed(Duration::from_secs(10)),
+            drop_rate: 0.0
+        });
+
+        simulation.crash_server(3);
+
+        let summary = ParallelTraversal::new(4, Duration::from_secs(1))
+            .lookup(http.clone(), &drivers[0], &client_public, None).await;
+
+        let Some(found) = summary.found else {
+            panic!("Client wasn't found by the traversal");
+        };
+
+        assert_eq!(found.client.public_key, client_public);
+        assert_eq!(found.server, record(5));
+        assert_eq!(found.path, [record(4), record(5)]);
+
+        assert_eq!(summary.requested, 5);
+        assert_eq!(summary.visited.len(), 5);
+        assert_eq!(summary.errors.len(), 2);
+
+        assert!(summary.errors.iter().any(|(server, err)| server == &record(2) && matches!(err, TraversalError::Timeout)));
+        assert!(summary.errors.iter().any(|(server, err)| server == &record(3) && matches!(err, TraversalError::Request(_))));
+
+        // Outstanding requests are cancelled once the client is found
+        let start = simulation.trace().len();
+        let started_at = tokio::time::Instant::now();
+
+        drivers[1].router().index_local_client(Client::new(
+            client_public.clone(),
+            ConnectionCertificate::new(client.driver().secret_key(), record(1).public_key),
+            ClientInfo::thin()
+        )).await?;
+
+        let summary = ParallelTraversal::new(4, Duration::from_secs(30))
+            .lookup(http.clone(), &drivers[0], &client_public, None).await;
+
+        assert_eq!(summary.found.map(|found| found.server), Some(record(1)));
+        assert!(summary.errors.iter().all(|(server, _)| server == &record(3)));
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+
+        assert!(!simulation.trace()[start..].iter().any(|event| event.to == simulation.server_address(2)));
+
+        // Servers are requested concurrently
+        simulation.heal();
+        simulation.network().set_link(http.address(), simulation.server_address(2), LinkParams {
+            latency: Latency::Fixed(Duration::from_millis(100)),
+            drop_rate: 0.0
+        });
+
+        let missing_public = SecretKey::random().public_key();
+
+        let started_at = tokio::time::Instant::now();
+
+        ParallelTraversal::new(1, Duration::from_secs(1)).with_max_depth(1)
+            .lookup(http.clone(), &drivers[0], &missing_public, None).await;
+
+        let sequential = started_at.elapsed();
+        let started_at = tokio::time::Instant::now();
+
+        ParallelTraversal::new(4, Duration::from_secs(1)).with_max_depth(1)
+            .lookup(http.clone(), &drivers[0], &missing_public, None).await;
+
+        assert!(started_at.elapsed() * 2 < sequential);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "traversal-bfs")]
+    #[tokio::test(start_paused = true)]
+    async fn traversal_cache() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::drivers::server::traversal::bfs::BfsTraversal;
+        use crate::drivers::server::traversal::cache::TraversalCache;
+
+        let mut simulation = TestSimulation::new(42);
+
+        for server in 0..3 {
+            simulation.add_server(factory(&format!("simulation-cache-{server}"), ServerParams::default()).await?).await?;
+        }
+
+        let drivers = (0..3)
+            .map(|server| simulation.driver(server).unwrap())
+            .collect::<Vec<_>>();
+
+        let record = |server: usize| ServerApiRecord::new(
+            drivers[server].params().secret_key.public_key(),
+            &drivers[server].params().address
+        );
+
+        for to in [1, 2] {
+            drivers[0].router().index_server(record(to)).await?;
+        }
+
+        simulation.crash_server(2);
+
+        let http = simulation.client();
+        let cache = TraversalCache::new(Duration::from_secs(60), 16);
+        let traversal = BfsTraversal::default().with_cache(cache.clone());
+
+        let client = ClientMiddleware::new(simulation.client(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        let requests = |from: usize| simulation.trace().len() - from;
+
+        assert!(traversal.lookup(http.clone(), &drivers[0], &client_public, None).await.is_none());
+
+        assert!(cache.is_missing(&client_public, None));
+        assert!(cache.is_unreachable(&record(2).public_key));
+
+        // Repeated lookup doesn't request any server
+        let start = simulation.trace().len();
+
+        assert!(traversal.lookup(http.clone(), &drivers[0], &client_public, None).await.is_none());
+        assert_eq!(requests(start), 0);
+
+        // Lookups of other clients skip the unreachable server
+        let start = simulation.trace().len();
+
+        assert!(traversal.lookup(http.clone(), &drivers[0], &SecretKey::random().public_key(), None).await.is_none());
+
+        assert!(simulation.trace()[start..].iter().all(|event| event.to == simulation.server_address(1)));
+
+        // Invalidated client is looked up again
+        client.connect(simulation.server_address(1)).await?;
+
+        assert!(traversal.lookup(http.clone(), &drivers[0], &client_public, None).await.is_none());
+        assert!(cache.invalidate(&client_public));
+
+        let Some(found) = traversal.lookup(http.clone(), &drivers[0], &client_public, None).await else {
+            panic!("Client wasn't found after invalidation");
+        };
+
+        assert_eq!(found.server, record(1));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "traversal-bfs")]
+    #[tokio::test(start_paused = true)]
+    async fn traversal_observer() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::Mutex;
+
+        use crate::drivers::server::traversal::bfs::BfsTraversal;
+        use crate::drivers::server::traversal::observer::{TraversalObserver, TraversalEvent};
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<TraversalEvent>>);
+
+        #[async_trait::async_trait]
+        impl TraversalObserver for Recorder {
+            async fn on_event(&self, event: TraversalEvent) {
+                self.0.lock().unwrap().push(event);
+            }
+        }
+
+        let mut simulation = TestSimulation::new(42);
+
+        for server in 0..3 {
+            simulation.add_server(factory(&format!("simulation-observer-{server}"), ServerParams::default()).await?).await?;
+        }
+
+        let drivers = (0..3)
+            .map(|server| simulation.driver(server).unwrap())
+            .collect::<Vec<_>>();
+
+        let record = |server: usize| ServerApiRecord::new(
+            drivers[server].params().secret_key.public_key(),
+            &drivers[server].params().address
+        );
+
+        for to in [1, 2] {
+            drivers[0].router().index_server(record(to)).await?;
+        }
+
+        simulation.crash_server(2);
+
+        let http = simulation.client();
+        let recorder = Arc::new(Recorder::default());
+        let traversal = BfsTraversal::default().with_observer(recorder.clone());
+
+        let client = ClientMiddleware::new(simulation.client(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        client.connect(simulation.server_address(1)).await?;
+
+        let events = || async {
+            // Let the observer's task receive all the events
+            tokio::time::sleep(Duration::from_secs(1)).await;
+
+            std::mem::take(&mut *recorder.0.lock().unwrap())
+        };
+
+        assert!(traversal.lookup(http.clone(), &drivers[0], &SecretKey::random().public_key(), None).await.is_none());
+
+        let missing_events = events().await;
+
+        for server in [1, 2] {
+            assert!(missing_events.iter().any(|event| matches!(event, TraversalEvent::ServerQueried(public_key, address, _) if public_key == &record(server).public_key && address == &record(server).address)));
+        }
+
+        assert!(missing_events.iter().any(|event| matches!(event, TraversalEvent::Failed(address, _) if address == &record(2).address)));
+        assert!(!missing_events.iter().any(|event| matches!(event, TraversalEvent::ClientFound(..) | TraversalEvent::Hint(_))));
+
+        let Some(found) = traversal.lookup(http.clone(), &drivers[0], &client_public, None).await else {
+            panic!("Client wasn't found by the traversal");
+        };
+
+        let found_events = events().await;
+
+        assert!(found_events.contains(&TraversalEvent::ClientFound(found.client, record(1))));
+
+        // Queried event is sent before the found one
+        let queried = found_events.iter().position(|event| matches!(event, TraversalEvent::ServerQueried(public_key, ..) if public_key == &record(1).public_key));
+        let found = found_events.iter().position(|event| matches!(event, TraversalEvent::ClientFound(..)));
+
+        assert!(queried.is_some() && queried < found);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "traversal-parallel")]
+    #[tokio::test(start_paused = true)]
+    async fn traversal_strategies() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::drivers::server::traversal::strategy::{StrategyTraversal, TraversalStrategy};
+
+        let mut simulation = TestSimulation::new(42);
+
+        for server in 0..4 {
+            simulation.add_server(factory(&format!("simulation-strategy-{server}"), ServerParams::default()).await?).await?;
+        }
+
+        let drivers = (0..4)
+            .map(|server| simulation.driver(server).unwrap())
+            .collect::<Vec<_>>();
+
+        let record = |server: usize| ServerApiRecord::new(
+            drivers[server].params().secret_key.public_key(),
+            &drivers[server].params().address
+        );
+
+        // 0 -> 1 -> 2 -> 3
+        for (from, to) in [(0, 1), (1, 2), (2, 3)] {
+            drivers[from].router().index_server(record(to)).await?;
+        }
+
+        let client = ClientMiddleware::new(simulation.client(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        client.connect(simulation.server_address(3)).await?;
+
+        let http = simulation.client();
+
+        let lookup = |strategy: TraversalStrategy| {
+            let http = http.clone();
+            let driver = drivers[0].clone();
+            let client_public = client_public.clone();
+
+            async move {
+                StrategyTraversal::new(strategy)
+                    .lookup(http, driver.as_ref(), &client_public, None).await
+                    .found
+            }
+        };
+
+        assert!(lookup(TraversalStrategy::Bfs { depth: 2, fan_out: 16 }).await.is_none());
+        assert!(lookup(TraversalStrategy::HintFollowing { max_hops: 1 }).await.is_none());
+
+        for strategy in [
+            TraversalStrategy::Bfs { depth: 3, fan_out: 16 },
+            TraversalStrategy::Parallel { concurrency: 4 },
+            TraversalStrategy::HintFollowing { max_hops: 2 }
+        ] {
+            let start = simulation.trace().len();
+
+            let Some(found) = lookup(strategy).await else {
+                panic!("Client wasn't found using {strategy:?} strategy");
+            };
+
+            assert_eq!(found.server, record(3));
+            assert_eq!(found.path, [record(1), record(2), record(3)]);
+
+            // Hints following doesn't request known servers
+            let servers_requested = simulation.trace()[start..].iter()
+                .any(|event| event.path == "/api/v1/servers");
+
+            assert_eq!(servers_requested, !matches!(strategy, TraversalStrategy::HintFollowing { .. }));
+        }
+
+        // Discovery doesn't depend on hints
+        StrategyTraversal::new(TraversalStrategy::HintFollowing { max_hops: 2 })
+            .traverse(http, drivers[0].as_ref()).await;
+
+        assert_eq!(drivers[0].router().servers().await?.len(), 3);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "traversal-parallel")]
+    #[tokio::test(start_paused = true)]
+    async fn traversal_routes() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::drivers::server::traversal::bfs::BfsTraversal;
+        use crate::drivers::server::traversal::parallel::ParallelTraversal;
+        use crate::drivers::server::traversal::cache::RouteCache;
+
+        let mut simulation = TestSimulation::new(42);
+
+        for server in 0..4 {
+            simulation.add_server(factory(&format!("simulation-routes-{server}"), ServerParams::default()).await?).await?;
+        }
+
+        let drivers = (0..4)
+            .map(|server| simulation.driver(server).unwrap())
+            .collect::<Vec<_>>();
+
+        let record = |server: usize| ServerApiRecord::new(
+            drivers[server].params().secret_key.public_key(),
+            &drivers[server].params().address
+        );
+
+        // 0 -> 1 -> 2 -> 3
+        for (from, to) in [(0, 1), (1, 2), (2, 3)] {
+            drivers[from].router().index_server(record(to)).await?;
+        }
+
+        let client = ClientMiddleware::new(simulation.client(), ClientDriver::random());
+        let client_public = client.driver().secret_key().public_key();
+
+        client.connect(simulation.server_address(3)).await?;
+
+        let http = simulation.client();
+        let routes = RouteCache::new(Duration::from_secs(60), 16);
+        let traversal = BfsTraversal::default().with_routes(routes.clone());
+
+        let lookups = |from: usize| simulation.trace()[from..].iter()
+            .filter(|event| event.path == "/api/v1/lookup")
+            .map(|event| event.to)
+            .collect::<Vec<_>>();
+
+        assert!(traversal.lookup(http.clone(), &drivers[0], &client_public, None).await.is_some());
+        assert_eq!(routes.route(&client_public), Some(record(3)));
+
+        // Repeated lookups request the cached route only
+        let start = simulation.trace().len();
+
+        let Some(found) = traversal.lookup(http.clone(), &drivers[0], &client_public, None).await else {
+            panic!("Client wasn't found using the cached route");
+        };
+
+        assert_eq!(found.path, [record(3)]);
+        assert_eq!(lookups(start), [simulation.server_address(3)]);
+
+        let start = simulation.trace().len();
+
+        let summary = ParallelTraversal::default()
+            .with_routes(routes.clone())
+            .lookup(http.clone(), &drivers[0], &client_public, None).await;
+
+        assert_eq!(summary.found.map(|found| found.server), Some(record(3)));
+        assert_eq!(summary.requested, 1);
+        assert_eq!(lookups(start), [simulation.server_address(3)]);
+
+        // Failed route is forgotten and the full traversal is done
+        simulation.crash_server(3);
+
+        client.connect(simulation.server_address(2)).await?;
+
+        let Some(found) = traversal.lookup(http.clone(), &drivers[0], &client_public, None).await else {
+            panic!("Client wasn't found after the route failed");
+        };
+
+        assert_eq!(found.server, record(2));
+        assert_eq!(found.path, [record(1), record(2)]);
+        assert_eq!(routes.route(&client_public), Some(record(2)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::Value as Json;
+
+use rand_chacha::ChaCha20Rng;
+use rand_chacha::rand_core::{SeedableRng, RngCore};
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::http::client::Response;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum VirtualNetworkError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Host {0} is unreachable")]
+    HostUnreachable(SocketAddr),
+
+    #[error("Link between {from} and {to} is partitioned")]
+    Partitioned {
+        from: SocketAddr,
+        to: SocketAddr
+    },
+
+    #[error("Request from {from} to {to} was dropped")]
+    Dropped {
+        from: SocketAddr,
+        to: SocketAddr
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Distribution of the virtual link latency.
+pub enum Latency {
+    Fixed(Duration),
+
+    /// Latency uniformly distributed in `min..=max` range.
+    Uniform {
+        min: Duration,
+        max: Duration
+    }
+}
+
+impl Latency {
+    /// Sample latency value from the distribution.
+    pub fn sample(&self, rng: &mut impl RngCore) -> Duration {
+        match self {
+            Self::Fixed(latency) => *latency,
+
+            Self::Uniform { min, max } => {
+                let range = max.saturating_sub(*min).as_micros() as u64;
+
+                if range == 0 {
+                    *min
+                } else {
+                    *min + Duration::from_micros(rng.next_u64() % (range + 1))
+                }
+            }
+        }
+    }
+}
+
+impl Default for Latency {
+    #[inline]
+    fn default() -> Self {
+        Self::Fixed(Duration::from_millis(10))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// Parameters of the virtual link between two hosts.
+pub struct LinkParams {
+    /// Latency of every request's and response's delivery.
+    pub latency: Latency,
+
+    /// Probability in `0.0..=1.0` range that the request
+    /// will be dropped.
+    pub drop_rate: f64
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LinkOutcome {
+    /// Request was delivered and the host has
+    /// responded with the given HTTP status.
+    Delivered(u16),
+
+    Dropped,
+    Partitioned,
+    Unreachable
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Record of the request sent over the virtual network.
+pub struct LinkEvent {
+    /// Virtual time elapsed since the network's creation.
+    pub at: Duration,
+
+    pub from: SocketAddr,
+    pub to: SocketAddr,
+    pub path: String,
+    pub outcome: LinkOutcome
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Request passed to the virtual host's handler.
+pub struct VirtualRequest {
+    pub client_address: SocketAddr,
+    pub query: HashMap<String, String>,
+    pub body: Option<Json>
+}
+
+pub type VirtualHandler = Arc<dyn Fn(VirtualRequest) -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync>;
+
+#[derive(Default, Clone)]
+/// Routes of the virtual host.
+pub struct VirtualRoutes {
+    pub get: HashMap<String, VirtualHandler>,
+    pub post: HashMap<String, VirtualHandler>
+}
+
+impl std::fmt::Debug for VirtualRoutes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VirtualRoutes")
+            .field("get", &self.get.keys().collect::<Vec<_>>())
+            .field("post", &self.post.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Post
+}
+
+#[derive(Debug)]
+struct Host {
+    routes: VirtualRoutes,
+    shutdown: Arc<Notify>
+}
+
+#[derive(Debug)]
+struct State {
+    hosts: HashMap<SocketAddr, Host>,
+    links: HashMap<(SocketAddr, SocketAddr), LinkParams>,
+    default_link: LinkParams,
+    partitions: HashSet<(SocketAddr, SocketAddr)>,
+    down: HashSet<SocketAddr>,
+    rng: ChaCha20Rng,
+    trace: Vec<LinkEvent>
+}
+
+#[derive(Debug, Clone)]
+/// In-process network connecting virtual HTTP clients
+/// and servers.
+/// 
+/// All the randomness of the network (latencies and
+/// dropped requests) comes from the seeded generator,
+/// and all the delays are performed by the tokio timers,
+/// so with the paused tokio clock every run of the same
+/// scenario produces the same trace.
+pub struct VirtualNetwork {
+    state: Arc<Mutex<State>>,
+    started_at: Instant
+}
+
+impl VirtualNetwork {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                hosts: HashMap::new(),
+                links: HashMap::new(),
+                default_link: LinkParams::default(),
+                partitions: HashSet::new(),
+                down: HashSet::new(),
+                rng: ChaCha20Rng::seed_from_u64(seed),
+                trace: Vec::new()
+            })),
+            started_at: Instant::now()
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("Failed to lock virtual network state")
+    }
+
+    #[inline]
+    /// Virtual time elapsed since the network's creation.
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Start serving given routes on the address.
+    /// 
+    /// Returned notify is triggered when the host
+    /// is shut down.
+    pub fn register(&self, address: SocketAddr, routes: VirtualRoutes) -> Arc<Notify> {
+        let shutdown = Arc::new(Notify::new());
+
+        let mut state = self.state();
+
+        state.down.remove(&address);
+
+        state.hosts.insert(address, Host {
+            routes,
+            shutdown: shutdown.clone()
+        });
+
+        shutdown
+    }
+
+    /// Stop serving the address.
+    /// 
+    /// Requests sent from this address will fail
+    /// until it's registered again.
+    pub fn shutdown(&self, address: &SocketAddr) {
+        let mut state = self.state();
+
+        state.down.insert(*address);
+
+        if let Some(host) = state.hosts.remove(address) {
+            host.shutdown.notify_one();
+        }
+    }
+
+    #[inline]
+    pub fn is_up(&self, address: &SocketAddr) -> bool {
+        self.state().hosts.contains_key(address)
+    }
+
+    #[inline]
+    /// Set parameters of the links without explicit ones.
+    pub fn set_default_link(&self, params: LinkParams) {
+        self.state().default_link = params;
+    }
+
+    /// Set parameters of the link between two hosts
+    /// in both directions.
+    pub fn set_link(&self, a: SocketAddr, b: SocketAddr, params: LinkParams) {
+        let mut state = self.state();
+
+        state.links.insert((a, b), params);
+        state.links.insert((b, a), params);
+    }
+
+    /// Block all the requests between two hosts.
+    pub fn partition(&self, a: SocketAddr, b: SocketAddr) {
+        let mut state = self.state();
+
+        state.partitions.insert((a, b));
+        state.partitions.insert((b, a));
+    }
+
+    #[inline]
+    /// Remove all the partitions.
+    pub fn heal(&self) {
+        self.state().partitions.clear();
+    }
+
+    #[inline]
+    /// Get all the requests sent over the network.
+    pub fn trace(&self) -> Vec<LinkEvent> {
+        self.state().trace.clone()
+    }
+
+    fn record(&self, from: SocketAddr, to: SocketAddr, path: &str, outcome: LinkOutcome) {
+        let at = self.elapsed();
+
+        self.state().trace.push(LinkEvent {
+            at,
+            from,
+            to,
+            path: path.to_string(),
+            outcome
+        });
+    }
+
+    /// Send request from one host to another.
+    /// 
+    /// Unknown routes are answered with HTTP 404.
+    pub async fn request(
+        &self,
+        from: SocketAddr,
+        method: Method,
+        url: &str,
+        body: Option<Json>
+    ) -> Result<Response, VirtualNetworkError> {
+        let (to, path, query) = parse_url(url)?;
+
+        // Decide request's fate at the sending time
+        let (link, dropped) = {
+            let mut state = self.state();
+
+            let link = state.links.get(&(from, to))
+                .copied()
+                .unwrap_or(state.default_link);
+
+            let roll = state.rng.next_u64() as f64 / u64::MAX as f64;
+
+            (link, roll < link.drop_rate)
+        };
+
+        let (request_latency, response_latency) = {
+            let mut state = self.state();
+
+            (link.latency.sample(&mut state.rng), link.latency.sample(&mut state.rng))
+        };
+
+        tokio::time::sleep(request_latency).await;
+
+        // Check the link when the request arrives
+        let handler = {
+            let state = self.state();
+
+            if state.down.contains(&from) {
+                Err(LinkOutcome::Unreachable)
+            }
+
+            else if state.partitions.contains(&(from, to)) {
+                Err(LinkOutcome::Partitioned)
+            }
+
+            else if dropped {
+                Err(LinkOutcome::Dropped)
+            }
+
+            else {
+                match state.hosts.get(&to) {
+                    Some(host) => Ok(match method {
+                        Method::Get => host.routes.get.get(&path).cloned(),
+                        Method::Post => host.routes.post.get(&path).cloned()
+                    }),
+
+                    None => Err(LinkOutcome::Unreachable)
+                }
+            }
+        };
+
+        let handler = match handler {
+            Ok(handler) => handler,
+
+            Err(outcome) => {
+                self.record(from, to, &path, outcome.clone());
+
+                return Err(match outcome {
+                    LinkOutcome::Partitioned => VirtualNetworkError::Partitioned { from, to },
+                    LinkOutcome::Dropped => VirtualNetworkError::Dropped { from, to },
+                    _ => VirtualNetworkError::HostUnreachable(to)
+                });
+            }
+        };
+
+        let response = match handler {
+            Some(handler) => handler(VirtualRequest {
+                client_address: from,
+                query,
+                body
+            }).await,
+
+            None => Response {
+                status: 404,
+                body: None
+            }
+        };
+
+        tokio::time::sleep(response_latency).await;
+
+        self.record(from, to, &path, LinkOutcome::Delivered(response.status));
+
+        Ok(response)
+    }
+}
+
+/// Decode `%XX` sequences of the URL component.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+
+                i += 3;
+            }
+
+            (b'+', _) => {
+                decoded.push(b' ');
+
+                i += 1;
+            }
+
+            (byte, _) => {
+                decoded.push(byte);
+
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Split URL into the host address, path and query.
+fn parse_url(url: &str) -> Result<(SocketAddr, String, HashMap<String, String>), VirtualNetworkError> {
+    let invalid = || VirtualNetworkError::InvalidUrl(url.to_string());
+
+    let rest = url.strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"))
+        .unwrap_or(url);
+
+    let (host, rest) = rest.split_once('/')
+        .map(|(host, rest)| (host, format!("/{rest}")))
+        .unwrap_or((rest, String::from("/")));
+
+    let address = host.parse::<SocketAddr>()
+        .map_err(|_| invalid())?;
+
+    let (path, query) = rest.split_once('?')
+        .map(|(path, query)| (path.to_string(), query))
+        .unwrap_or((rest.clone(), ""));
+
+    let query = query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+
+    Ok((address, path, query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_routes() -> VirtualRoutes {
+        let mut routes = VirtualRoutes::default();
+
+        routes.get.insert(String::from("/echo"), Arc::new(|request: VirtualRequest| Box::pin(async move {
+            Response {
+                status: 200,
+                body: Some(serde_json::to_value(request.query).unwrap())
+            }
+        })));
+
+        routes
+    }
+
+    #[test]
+    fn url() -> Result<(), VirtualNetworkError> {
+        let (address, path, query) = parse_url("http://10.0.0.1:8001/api/v1/clients?limit=2&token=ab%3D%3D")?;
+
+        assert_eq!(address, "10.0.0.1:8001".parse().unwrap());
+        assert_eq!(path, "/api/v1/clients");
+        assert_eq!(query["limit"], "2");
+        assert_eq!(query["token"], "ab==");
+
+        assert!(parse_url("http://localhost/api").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn links() -> Result<(), VirtualNetworkError> {
+        let network = VirtualNetwork::new(0);
+
+        let client = "10.1.0.1:8001".parse().unwrap();
+        let server = "10.0.0.1:8001".parse().unwrap();
+
+        network.register(server, echo_routes());
+
+        network.set_link(client, server, LinkParams {
+            latency: Latency::Uniform {
+                min: Duration::from_millis(10),
+                max: Duration::from_millis(20)
+            },
+            drop_rate: 0.0
+        });
+
+        // Request and response latencies are applied
+        let started_at = Instant::now();
+
+        let response = network.request(client, Method::Get, "http://10.0.0.1:8001/echo?a=b", None).await?;
+
+        assert!((Duration::from_millis(20)..=Duration::from_millis(40)).contains(&started_at.elapsed()));
+        assert_eq!(response.body, Some(serde_json::json!({ "a": "b" })));
+
+        assert_eq!(network.request(client, Method::Post, "http://10.0.0.1:8001/echo", None).await?.status, 404);
+
+        // Partitions
+        network.partition(client, server);
+
+        assert_eq!(
+            network.request(client, Method::Get, "http://10.0.0.1:8001/echo", None).await,
+            Err(VirtualNetworkError::Partitioned { from: client, to: server })
+        );
+
+        network.heal();
+
+        // Drop rate
+        network.set_link(client, server, LinkParams {
+            latency: Latency::default(),
+            drop_rate: 1.0
+        });
+
+        assert_eq!(
+            network.request(client, Method::Get, "http://10.0.0.1:8001/echo", None).await,
+            Err(VirtualNetworkError::Dropped { from: client, to: server })
+        );
+
+        // Crashed host
+        network.shutdown(&server);
+
+        assert_eq!(
+            network.request(server, Method::Get, "http://10.0.0.1:8001/echo", None).await,
+            Err(VirtualNetworkError::HostUnreachable(server))
+        );
+
+        let outcomes = network.trace()
+            .into_iter()
+            .map(|event| event.outcome)
+            .collect::<Vec<_>>();
+
+        assert_eq!(outcomes, [
+            LinkOutcome::Delivered(200),
+            LinkOutcome::Delivered(404),
+            LinkOutcome::Partitioned,
+            LinkOutcome::Dropped,
+            LinkOutcome::Unreachable
+        ]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! In-memory transport for the middlewares' tests.
+//! 
+//! `InMemoryTransport` is a `VirtualNetwork` of the `simulation`
+//! module without latencies and failures, so the server middleware
+//! and any amount of client middlewares can run in one process
+//! without binding any sockets. Unlike the simulation it doesn't
+//! need the paused tokio clock, so it's meant for the functional
+//! tests.
+//! 
+//! ```rust,no_run
+//! use std::net::SocketAddr;
+//! 
+//! use hyperborealib::prelude::*;
+//! use hyperborealib::test_utils::InMemoryTransport;
+//! 
+//! # async fn test(driver: ServerDriver<RamRouter, BfsRecursionTraversal, RamMessagesInbox>) {
+//! let transport = InMemoryTransport::new();
+//! let address = SocketAddr::from(([10, 0, 0, 1], 8001));
+//! 
+//! let server = ServerMiddleware::new(transport.client(address), transport.server(), driver).await;
+//! 
+//! tokio::spawn(async move {
+//!     let _ = server.serve(address).await;
+//! });
+//! 
+//! transport.wait_up(&address).await;
+//! 
+//! let client = ClientMiddleware::new(transport.client(([10, 1, 0, 1], 8001)), ClientDriver::random())
+//!     .connect(address).await
+//!     .unwrap();
+//! # }
+//! ```
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::simulation::{
+    VirtualNetwork,
+    VirtualHttpClient,
+    VirtualHttpServer,
+    LinkParams,
+    Latency
+};
+
+/// HTTP client sending requests over the in-memory transport.
+pub type InMemoryHttpClient = VirtualHttpClient;
+
+/// HTTP server serving requests from the in-memory transport.
+pub type InMemoryHttpServer = VirtualHttpServer;
+
+#[derive(Debug, Clone)]
+/// In-process transport connecting in-memory
+/// HTTP clients and servers.
+/// 
+/// Clones share the same hosts.
+pub struct InMemoryTransport {
+    network: VirtualNetwork
+}
+
+impl InMemoryTransport {
+    pub fn new() -> Self {
+        let network = VirtualNetwork::new(0);
+
+        network.set_default_link(LinkParams {
+            latency: Latency::Fixed(Duration::ZERO),
+            drop_rate: 0.0
+        });
+
+        Self {
+            network
+        }
+    }
+
+    #[inline]
+    /// Get the underlying virtual network.
+    pub fn network(&self) -> &VirtualNetwork {
+        &self.network
+    }
+
+    #[inline]
+    /// Create HTTP client sending requests
+    /// from the given virtual address.
+    pub fn client(&self, address: impl Into<SocketAddr>) -> InMemoryHttpClient {
+        VirtualHttpClient::new(self.network.clone(), address.into())
+    }
+
+    #[inline]
+    /// Create HTTP server serving requests
+    /// of the transport's clients.
+    pub fn server(&self) -> InMemoryHttpServer {
+        VirtualHttpServer::new(self.network.clone())
+    }
+
+    #[inline]
+    /// Stop serving the address.
+    pub fn shutdown(&self, address: &SocketAddr) {
+        self.network.shutdown(address);
+    }
+
+    #[inline]
+    pub fn is_up(&self, address: &SocketAddr) -> bool {
+        self.network.is_up(address)
+    }
+
+    /// Wait until the address is served.
+    pub async fn wait_up(&self, address: &SocketAddr) {
+        while !self.is_up(address) {
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+impl Default for InMemoryTransport {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,84 @@
+#![cfg(all(
+    feature = "test_utils",
+    feature = "router-ram",
+    feature = "traversal-bfs-recursion",
+    feature = "inbox-ram"
+))]
+
+use std::net::SocketAddr;
+
+use hyperborealib::prelude::*;
+use hyperborealib::crypto::compression::CompressionLevel;
+use hyperborealib::test_utils::{InMemoryTransport, InMemoryHttpClient};
+
+const SERVER_ADDRESS: ([u8; 4], u16) = ([10, 0, 0, 1], 8001);
+
+/// Run server middleware on the transport.
+async fn serve(transport: &InMemoryTransport) -> SocketAddr {
+    let address = SocketAddr::from(SERVER_ADDRESS);
+
+    let driver = ServerDriver::new(
+        RamRouter::new(),
+        BfsRecursionTraversal,
+        RamMessagesInbox::new(),
+        ServerParams {
+            address: address.to_string(),
+            ..ServerParams::default()
+        }
+    );
+
+    let server = ServerMiddleware::new(transport.client(address), transport.server(), driver).await;
+
+    tokio::spawn(async move {
+        let _ = server.serve(address).await;
+    });
+
+    transport.wait_up(&address).await;
+
+    address
+}
+
+fn client(transport: &InMemoryTransport, host: u8) -> ClientMiddleware<InMemoryHttpClient> {
+    ClientMiddleware::new(transport.client(([10, 1, 0, host], 8001)), ClientDriver::random())
+}
+
+#[tokio::test]
+async fn connect_send_poll() -> Result<(), Box<dyn std::error::Error>> {
+    let transport = InMemoryTransport::new();
+    let address = serve(&transport).await;
+
+    let sender = client(&transport, 1).connect(address).await?;
+    let receiver = client(&transport, 2).connect(address).await?;
+
+    let sender_secret = sender.driver().secret_key().clone();
+    let receiver_public = receiver.driver().secret_key().public_key();
+
+    assert_eq!(sender.connected_server().address, address.to_string());
+
+    let message = Message::create(
+        &sender_secret,
+        &receiver_public,
+        b"Hello, World!",
+        MessageEncoding::default(),
+        CompressionLevel::default()
+    )?;
+
+    sender.send(format!("http://{address}"), receiver_public, "channel", message).await?;
+
+    let (messages, remaining) = receiver.poll("channel", None).await?;
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(remaining, 0);
+
+    assert_eq!(messages[0].sender.client.public_key, sender_secret.public_key());
+    assert_eq!(messages[0].message.read(receiver.driver_ref().secret_key(), &sender_secret.public_key())?, b"Hello, World!");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unreachable() {
+    let transport = InMemoryTransport::new();
+
+    assert!(client(&transport, 1).connect(SocketAddr::from(SERVER_ADDRESS)).await.is_err());
+}